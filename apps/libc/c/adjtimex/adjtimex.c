@@ -0,0 +1,85 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/timex.h>
+#include <time.h>
+#include <unistd.h>
+
+int main()
+{
+    // 只读取状态
+    struct timex tx;
+    memset(&tx, 0, sizeof(tx));
+    struct timespec now;
+    clock_gettime(CLOCK_REALTIME, &now);
+    if (adjtimex(&tx) != TIME_OK) {
+        printf("adjtimex failed: read did not return TIME_OK\n");
+        return 1;
+    }
+    if (tx.freq != 0 || tx.offset != 0 || tx.time.tv_sec < now.tv_sec ||
+        tx.time.tv_sec > now.tv_sec + 5) {
+        printf("adjtimex failed: inconsistent state\n");
+        return 1;
+    }
+
+    // 将时钟向前拨一天
+    memset(&tx, 0, sizeof(tx));
+    tx.modes = ADJ_SETOFFSET;
+    tx.time.tv_sec = 86400;
+    if (adjtimex(&tx) != TIME_OK) {
+        printf("adjtimex failed: ADJ_SETOFFSET\n");
+        return 1;
+    }
+    struct timespec later;
+    clock_gettime(CLOCK_REALTIME, &later);
+    if (later.tv_sec < now.tv_sec + 86400 || later.tv_sec > now.tv_sec + 86400 + 5) {
+        printf("adjtimex failed: offset not applied\n");
+        return 1;
+    }
+
+    // 频率调整被接受，但不产生效果
+    memset(&tx, 0, sizeof(tx));
+    tx.modes = ADJ_FREQUENCY;
+    tx.freq = 1000;
+    if (clock_adjtime(CLOCK_REALTIME, &tx) != TIME_OK || tx.freq != 0) {
+        printf("adjtimex failed: frequency adjustment\n");
+        return 1;
+    }
+
+    memset(&tx, 0, sizeof(tx));
+    errno = 0;
+    if (clock_adjtime(CLOCK_MONOTONIC, &tx) != -1 || errno != EINVAL) {
+        printf("adjtimex failed: CLOCK_MONOTONIC accepted\n");
+        return 1;
+    }
+
+    // 纳秒模式下 tv_usec 以纳秒为单位，状态中带有 STA_NANO，直到切回微秒模式
+    memset(&tx, 0, sizeof(tx));
+    tx.modes = ADJ_NANO;
+    if (adjtimex(&tx) != TIME_OK || !(tx.status & STA_NANO) || tx.time.tv_usec < 0 ||
+        tx.time.tv_usec >= 1000000000) {
+        printf("adjtimex failed: ADJ_NANO\n");
+        return 1;
+    }
+    memset(&tx, 0, sizeof(tx));
+    if (adjtimex(&tx) != TIME_OK || !(tx.status & STA_NANO)) {
+        printf("adjtimex failed: STA_NANO not kept\n");
+        return 1;
+    }
+    memset(&tx, 0, sizeof(tx));
+    tx.modes = ADJ_MICRO;
+    if (adjtimex(&tx) != TIME_OK || (tx.status & STA_NANO) || tx.time.tv_usec >= 1000000) {
+        printf("adjtimex failed: ADJ_MICRO\n");
+        return 1;
+    }
+
+    // 恢复原来的时间
+    memset(&tx, 0, sizeof(tx));
+    tx.modes = ADJ_SETOFFSET;
+    tx.time.tv_sec = -86400;
+    adjtimex(&tx);
+
+    printf("adjtimex passed!\n");
+    return 0;
+}
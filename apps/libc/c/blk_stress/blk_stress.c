@@ -0,0 +1,151 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define NPROC 3
+#define ROUNDS 4
+#define FILE_SIZE (128 * 1024)
+#define SHARED_SIZE (256 * 1024)
+#define SHARED "/blk_stress_shared"
+
+static char buf[SHARED_SIZE];
+
+static unsigned char pattern(long pos, int seed)
+{
+    return (unsigned char)(pos * 37 + pos / 512 + seed);
+}
+
+// 以 8 KiB 为单位写入 size 字节的文件
+static int write_file(const char *path, long size, int seed)
+{
+    int fd = open(path, O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0)
+        return -1;
+    for (long i = 0; i < size; i++)
+        buf[i] = pattern(i, seed);
+    long off = 0;
+    while (off < size) {
+        ssize_t n = write(fd, buf + off, size - off < 8192 ? size - off : 8192);
+        if (n <= 0) {
+            close(fd);
+            return -1;
+        }
+        off += n;
+    }
+    return close(fd);
+}
+
+// 以 chunk 字节为单位顺序读取并检查文件内容，一致时返回 0
+static int check_sequential(const char *path, long size, int seed, long chunk)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    long total = 0;
+    ssize_t n;
+    while (total < size && (n = read(fd, buf + total, chunk)) > 0)
+        total += n;
+    close(fd);
+    if (total != size)
+        return -1;
+    for (long i = 0; i < size; i++) {
+        if ((unsigned char)buf[i] != pattern(i, seed))
+            return -1;
+    }
+    return 0;
+}
+
+// 从文件末尾向前逐页读取并检查，打乱顺序读取的模式
+static int check_backward(const char *path, long size, int seed)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    char page[4096];
+    for (long off = size - sizeof(page); off >= 0; off -= sizeof(page)) {
+        if (lseek(fd, off, SEEK_SET) != off || read(fd, page, sizeof(page)) != (ssize_t)sizeof(page)) {
+            close(fd);
+            return -1;
+        }
+        for (long i = 0; i < (long)sizeof(page); i++) {
+            if ((unsigned char)page[i] != pattern(off + i, seed)) {
+                close(fd);
+                return -1;
+            }
+        }
+    }
+    close(fd);
+    return 0;
+}
+
+// 子进程反复写入和读取自己的文件，同时读取共享的文件
+static int worker(int id)
+{
+    static const long chunks[] = {512, 3000, 4096, 65536};
+    char path[64];
+    snprintf(path, sizeof(path), "/blk_stress_%d", id);
+    for (int round = 0; round < ROUNDS; round++) {
+        int seed = id * 16 + round;
+        if (write_file(path, FILE_SIZE, seed) != 0) {
+            printf("blk_stress failed: worker %d cannot write round %d\n", id, round);
+            return 1;
+        }
+        if (check_sequential(SHARED, SHARED_SIZE, 0, chunks[(round + id) % 4]) != 0) {
+            printf("blk_stress failed: worker %d reads wrong shared data in round %d\n", id,
+                   round);
+            return 1;
+        }
+        if (check_sequential(path, FILE_SIZE, seed, chunks[round]) != 0 ||
+            check_backward(path, FILE_SIZE, seed) != 0) {
+            printf("blk_stress failed: worker %d reads wrong data in round %d\n", id, round);
+            return 1;
+        }
+    }
+    unlink(path);
+    return 0;
+}
+
+int main(void)
+{
+    if (write_file(SHARED, SHARED_SIZE, 0) != 0) {
+        printf("blk_stress failed: cannot write the shared file\n");
+        return 1;
+    }
+
+    pid_t pids[NPROC];
+    for (int i = 0; i < NPROC; i++) {
+        pids[i] = fork();
+        if (pids[i] < 0) {
+            printf("blk_stress failed: fork\n");
+            return 1;
+        }
+        if (pids[i] == 0)
+            _exit(worker(i));
+    }
+
+    int failed = 0;
+    for (int i = 0; i < NPROC; i++) {
+        int status;
+        if (waitpid(pids[i], &status, 0) != pids[i] || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0)
+            failed = 1;
+    }
+    unlink(SHARED);
+    if (failed) {
+        printf("blk_stress failed: a worker failed\n");
+        return 1;
+    }
+
+    // 所有进程结束后，共享文件的内容仍然正确
+    if (write_file(SHARED, SHARED_SIZE, 7) != 0 || check_backward(SHARED, SHARED_SIZE, 7) != 0 ||
+        check_sequential(SHARED, SHARED_SIZE, 7, 4096) != 0) {
+        printf("blk_stress failed: shared file after the workers\n");
+        return 1;
+    }
+    unlink(SHARED);
+
+    printf("blk_stress passed!\n");
+    return 0;
+}
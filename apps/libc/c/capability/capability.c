@@ -0,0 +1,99 @@
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define _LINUX_CAPABILITY_VERSION_3 0x20080522
+#define CAP_SYS_ADMIN 21
+
+struct cap_header {
+    uint32_t version;
+    int pid;
+};
+
+struct cap_data {
+    uint32_t effective;
+    uint32_t permitted;
+    uint32_t inheritable;
+};
+
+static long capget(struct cap_header *hdr, struct cap_data *data)
+{
+    return syscall(SYS_capget, hdr, data);
+}
+
+static long capset(struct cap_header *hdr, const struct cap_data *data)
+{
+    return syscall(SYS_capset, hdr, data);
+}
+
+// 在子进程中丢弃 CAP_SYS_ADMIN，之后的特权操作和重新获取都应失败
+static int drop_sys_admin(void)
+{
+    struct cap_header hdr = {_LINUX_CAPABILITY_VERSION_3, 0};
+    struct cap_data data[2];
+    if (capget(&hdr, data) != 0)
+        return 1;
+    struct cap_data dropped[2] = {data[0], data[1]};
+    dropped[0].effective &= ~(1u << CAP_SYS_ADMIN);
+    dropped[0].permitted &= ~(1u << CAP_SYS_ADMIN);
+    if (capset(&hdr, dropped) != 0)
+        return 2;
+    errno = 0;
+    if (sethostname("nope", 4) != -1 || errno != EPERM)
+        return 3;
+    errno = 0;
+    if (capset(&hdr, data) != -1 || errno != EPERM)
+        return 4;
+    return 0;
+}
+
+int main()
+{
+    // 不支持的版本号会被改写为内核支持的版本
+    struct cap_header hdr = {0x12345678, 0};
+    struct cap_data data[2];
+    errno = 0;
+    if (capget(&hdr, data) != -1 || errno != EINVAL || hdr.version != _LINUX_CAPABILITY_VERSION_3) {
+        printf("capability failed: version negotiation\n");
+        return 1;
+    }
+
+    if (capget(&hdr, data) != 0 || !(data[0].effective & (1u << CAP_SYS_ADMIN))) {
+        printf("capability failed: root lacks CAP_SYS_ADMIN\n");
+        return 1;
+    }
+
+    // 头部或数据的地址无效时返回 EFAULT
+    errno = 0;
+    if (capget((struct cap_header *)8, data) != -1 || errno != EFAULT) {
+        printf("capability failed: bad header address accepted\n");
+        return 1;
+    }
+    errno = 0;
+    if (capget(&hdr, (struct cap_data *)8) != -1 || errno != EFAULT) {
+        printf("capability failed: bad data address accepted\n");
+        return 1;
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+        return drop_sys_admin();
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("capability failed: drop step %d\n", WEXITSTATUS(status));
+        return 1;
+    }
+
+    // 子进程丢弃能力不影响父进程
+    if (capget(&hdr, data) != 0 || !(data[0].permitted & (1u << CAP_SYS_ADMIN))) {
+        printf("capability failed: parent lost CAP_SYS_ADMIN\n");
+        return 1;
+    }
+
+    printf("capability passed!\n");
+    return 0;
+}
@@ -0,0 +1,116 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+#define RNG_BYTES 256
+
+static const char MSG[] = "char_dev: hello from /dev/ttyS0\n";
+
+// 检查 path 是设备号为 major:minor 的字符设备
+static int check_node(const char *path, unsigned major_no, unsigned minor_no)
+{
+    struct stat st;
+    if (stat(path, &st) != 0) {
+        printf("char_dev failed: stat %s errno %d\n", path, errno);
+        return 1;
+    }
+    if (!S_ISCHR(st.st_mode) || major(st.st_rdev) != major_no || minor(st.st_rdev) != minor_no) {
+        printf("char_dev failed: %s is %#o %u:%u\n", path, st.st_mode, major(st.st_rdev),
+               minor(st.st_rdev));
+        return 1;
+    }
+    return 0;
+}
+
+// 像 cat 一样读取 path，直到读满 len 字节或读到文件结束，返回读到的字节数
+static ssize_t cat(const char *path, char *buf, size_t len)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    size_t total = 0;
+    ssize_t n;
+    while (total < len && (n = read(fd, buf + total, len - total)) > 0)
+        total += n;
+    close(fd);
+    return n < 0 ? -1 : (ssize_t)total;
+}
+
+// 串口：写入的内容出现在控制台上，没有输入时读取立即返回
+static int check_tty(void)
+{
+    if (check_node("/dev/ttyS0", 4, 64))
+        return 1;
+    int fd = open("/dev/ttyS0", O_RDWR);
+    if (fd < 0) {
+        printf("char_dev failed: open /dev/ttyS0 errno %d\n", errno);
+        return 1;
+    }
+    if (write(fd, MSG, strlen(MSG)) != (ssize_t)strlen(MSG)) {
+        printf("char_dev failed: write /dev/ttyS0 errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    char buf[64];
+    if (cat("/dev/ttyS0", buf, sizeof(buf)) < 0) {
+        printf("char_dev failed: cat /dev/ttyS0 errno %d\n", errno);
+        return 1;
+    }
+    return 0;
+}
+
+// 随机数发生器：读取总能读满，两次读到的内容不同，不能写入，不支持的 ioctl 返回 ENOTTY
+static int check_rng(void)
+{
+    if (check_node("/dev/hwrng", 10, 183))
+        return 1;
+    static char a[RNG_BYTES], b[RNG_BYTES], zero[RNG_BYTES];
+    if (cat("/dev/hwrng", a, sizeof(a)) != RNG_BYTES || cat("/dev/hwrng", b, sizeof(b)) != RNG_BYTES) {
+        printf("char_dev failed: cat /dev/hwrng errno %d\n", errno);
+        return 1;
+    }
+    if (memcmp(a, b, RNG_BYTES) == 0 || memcmp(a, zero, RNG_BYTES) == 0) {
+        printf("char_dev failed: /dev/hwrng is not random\n");
+        return 1;
+    }
+    int fd = open("/dev/hwrng", O_RDWR);
+    if (fd < 0) {
+        printf("char_dev failed: open /dev/hwrng errno %d\n", errno);
+        return 1;
+    }
+    if (write(fd, a, 8) != -1 || errno != EINVAL) {
+        printf("char_dev failed: write /dev/hwrng errno %d\n", errno);
+        return 1;
+    }
+    if (ioctl(fd, 0x12345678, 0) != -1 || errno != ENOTTY) {
+        printf("char_dev failed: unknown ioctl errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    // mknod 创建的同一设备号的节点打开的也是该设备
+    unlink("char_dev_rng");
+    if (mknod("char_dev_rng", S_IFCHR | 0644, makedev(10, 183)) != 0) {
+        printf("char_dev failed: mknod errno %d\n", errno);
+        return 1;
+    }
+    if (cat("char_dev_rng", a, 16) != 16) {
+        printf("char_dev failed: cat char_dev_rng errno %d\n", errno);
+        return 1;
+    }
+    unlink("char_dev_rng");
+    return 0;
+}
+
+int main(void)
+{
+    if (check_tty() || check_rng())
+        return 1;
+    printf("char_dev passed!\n");
+    return 0;
+}
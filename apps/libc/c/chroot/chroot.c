@@ -0,0 +1,107 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// 在 chroot 之后的子进程中运行，返回非 0 表示失败
+static int jailed(void)
+{
+    char cwd[64];
+    int fd;
+
+    if (chroot("/cr_jail") != 0) {
+        printf("chroot failed: chroot errno %d\n", errno);
+        return 1;
+    }
+    // "/" 解析为新的根目录
+    if (chdir("/") != 0 || !getcwd(cwd, sizeof(cwd)) || strcmp(cwd, "/") != 0) {
+        printf("chroot failed: getcwd after chdir(\"/\") is %s\n", cwd);
+        return 1;
+    }
+    fd = openat(AT_FDCWD, "/marker", O_RDONLY);
+    if (fd < 0) {
+        printf("chroot failed: /marker errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    // ".." 不能越过根目录
+    fd = openat(AT_FDCWD, "../../etc/inside", O_RDONLY);
+    if (fd < 0) {
+        printf("chroot failed: ../../etc/inside errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    if (openat(AT_FDCWD, "/../cr_jail/marker", O_RDONLY) != -1 || errno != ENOENT) {
+        printf("chroot failed: escaped through /.. errno %d\n", errno);
+        return 1;
+    }
+
+    // getcwd 返回相对于根目录的路径
+    if (chdir("etc") != 0 || !getcwd(cwd, sizeof(cwd)) || strcmp(cwd, "/etc") != 0) {
+        printf("chroot failed: getcwd in /etc is %s\n", cwd);
+        return 1;
+    }
+
+    // 子进程继承根目录
+    pid_t pid = fork();
+    if (pid == 0) {
+        fd = openat(AT_FDCWD, "/marker", O_RDONLY);
+        _exit(fd < 0);
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("chroot failed: root not inherited by fork\n");
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    int fd;
+
+    if (mkdirat(AT_FDCWD, "/cr_jail", 0755) != 0 || mkdirat(AT_FDCWD, "/cr_jail/etc", 0755) != 0) {
+        printf("chroot failed: mkdirat errno %d\n", errno);
+        return 1;
+    }
+    fd = openat(AT_FDCWD, "/cr_jail/marker", O_CREAT | O_WRONLY, 0644);
+    if (fd < 0 || close(fd) != 0) {
+        printf("chroot failed: create marker errno %d\n", errno);
+        return 1;
+    }
+    fd = openat(AT_FDCWD, "/cr_jail/etc/inside", O_CREAT | O_WRONLY, 0644);
+    if (fd < 0 || close(fd) != 0) {
+        printf("chroot failed: create inside errno %d\n", errno);
+        return 1;
+    }
+
+    pid_t pid = fork();
+    if (pid == 0) {
+        _exit(jailed());
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        return 1;
+    }
+
+    // 父进程的根目录不受影响
+    fd = openat(AT_FDCWD, "/cr_jail/marker", O_RDONLY);
+    if (fd < 0) {
+        printf("chroot failed: parent root changed, errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    unlinkat(AT_FDCWD, "/cr_jail/etc/inside", 0);
+    unlinkat(AT_FDCWD, "/cr_jail/marker", 0);
+    unlinkat(AT_FDCWD, "/cr_jail/etc", AT_REMOVEDIR);
+    unlinkat(AT_FDCWD, "/cr_jail", AT_REMOVEDIR);
+
+    printf("chroot passed!\n");
+    return 0;
+}
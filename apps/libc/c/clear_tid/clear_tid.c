@@ -0,0 +1,106 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <linux/futex.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+// vfork 的子进程与父进程共享地址空间，退出时按 clear_child_tid 清零并唤醒等待者。
+// 子进程把 word 登记为 clear_child_tid 后立即退出，返回子进程的 PID
+static pid_t vfork_exit(volatile int *word)
+{
+    *word = -1;
+    pid_t pid = vfork();
+    if (pid == 0) {
+        syscall(SYS_set_tid_address, word);
+        _exit(0);
+    }
+    return pid;
+}
+
+static int expect_status(pid_t pid, int killed, const char *what)
+{
+    int status;
+    if (waitpid(pid, &status, 0) != pid ||
+        (killed ? !WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL
+                : !WIFEXITED(status) || WEXITSTATUS(status) != 0)) {
+        printf("clear_tid failed: %s status %#x\n", what, status);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    // 主动退出的子进程清零 clear_child_tid
+    static volatile int word;
+    pid_t pid = vfork_exit(&word);
+    if (word != 0) {
+        printf("clear_tid failed: not cleared on exit\n");
+        return 1;
+    }
+    if (expect_status(pid, 0, "exited child"))
+        return 1;
+
+    // 被 SIGKILL 终止的子进程同样清零，另一个进程中阻塞在 futex 上的等待者被唤醒。
+    // 两个进程通过 memfd 的共享映射看到同一个字
+    int fd = memfd_create("clear_tid", 0);
+    if (fd < 0 || ftruncate(fd, 4096) != 0) {
+        printf("clear_tid failed: memfd errno %d\n", errno);
+        return 1;
+    }
+    volatile int *shared = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (shared == MAP_FAILED) {
+        printf("clear_tid failed: mmap errno %d\n", errno);
+        return 1;
+    }
+    *shared = 1;
+    pid_t waiter = fork();
+    if (waiter == 0) {
+        while (*shared == 1)
+            syscall(SYS_futex, shared, FUTEX_WAIT, 1, NULL, NULL, 0);
+        _exit(*shared == 0 ? 0 : 1);
+    }
+    struct timespec ts = {0, 50000000};
+    nanosleep(&ts, NULL);
+    // 等待者看到的值在子进程登记前后都不为 0，只有退出时的清零才能唤醒它
+    pid = vfork();
+    if (pid == 0) {
+        syscall(SYS_set_tid_address, shared);
+        kill(getpid(), SIGKILL);
+        _exit(0);
+    }
+    if (*shared != 0 || expect_status(pid, 1, "killed child") ||
+        expect_status(waiter, 0, "futex waiter"))
+        return 1;
+
+    // 地址无效时忽略，子进程照常退出
+    pid = vfork();
+    if (pid == 0) {
+        syscall(SYS_set_tid_address, (int *)8);
+        _exit(0);
+    }
+    if (expect_status(pid, 0, "child with invalid address"))
+        return 1;
+
+    // 指向尚未访问过的页时先分配该页
+    int *page = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (page == MAP_FAILED) {
+        printf("clear_tid failed: mmap errno %d\n", errno);
+        return 1;
+    }
+    pid = vfork();
+    if (pid == 0) {
+        syscall(SYS_set_tid_address, page);
+        _exit(0);
+    }
+    if (expect_status(pid, 0, "child with unpopulated page"))
+        return 1;
+
+    printf("clear_tid passed!\n");
+    return 0;
+}
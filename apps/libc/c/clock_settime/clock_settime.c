@@ -0,0 +1,85 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/time.h>
+#include <time.h>
+#include <unistd.h>
+
+// 2030-01-01 00:00:00 UTC
+#define KNOWN_DATE 1893456000L
+
+int main()
+{
+    struct timespec saved;
+    clock_gettime(CLOCK_REALTIME, &saved);
+
+    struct timespec ts = {KNOWN_DATE, 0};
+    if (clock_settime(CLOCK_REALTIME, &ts) != 0) {
+        printf("clock_settime failed: cannot set CLOCK_REALTIME\n");
+        return 1;
+    }
+    struct timespec now;
+    clock_gettime(CLOCK_REALTIME, &now);
+    if (now.tv_sec < KNOWN_DATE || now.tv_sec > KNOWN_DATE + 5) {
+        printf("clock_settime failed: clock_gettime returned %ld\n", (long)now.tv_sec);
+        return 1;
+    }
+    struct timeval tv;
+    gettimeofday(&tv, NULL);
+    if (tv.tv_sec < KNOWN_DATE || tv.tv_sec > KNOWN_DATE + 5) {
+        printf("clock_settime failed: gettimeofday returned %ld\n", (long)tv.tv_sec);
+        return 1;
+    }
+
+    // 新建文件的时间戳跟随设置后的时钟
+    int fd = open("clock_settime_file", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    struct stat st;
+    if (fd < 0 || write(fd, "x", 1) != 1 || fstat(fd, &st) != 0) {
+        printf("clock_settime failed: cannot create a file\n");
+        return 1;
+    }
+    close(fd);
+    unlink("clock_settime_file");
+    if (st.st_mtime < KNOWN_DATE || st.st_mtime > KNOWN_DATE + 5 ||
+        st.st_atime < KNOWN_DATE || st.st_atime > KNOWN_DATE + 5 ||
+        st.st_ctime < KNOWN_DATE || st.st_ctime > KNOWN_DATE + 5) {
+        printf("clock_settime failed: file times %ld %ld %ld\n", (long)st.st_atime,
+               (long)st.st_mtime, (long)st.st_ctime);
+        return 1;
+    }
+
+    // 单调时钟不能被设置
+    errno = 0;
+    if (clock_settime(CLOCK_MONOTONIC, &ts) != -1 || errno != EINVAL) {
+        printf("clock_settime failed: CLOCK_MONOTONIC was set\n");
+        return 1;
+    }
+    ts.tv_nsec = 1000000000;
+    errno = 0;
+    if (clock_settime(CLOCK_REALTIME, &ts) != -1 || errno != EINVAL) {
+        printf("clock_settime failed: invalid tv_nsec accepted\n");
+        return 1;
+    }
+
+    // 将时钟调回过去后，睡眠仍按单调时钟计时
+    tv.tv_sec = KNOWN_DATE - 86400 * 365;
+    tv.tv_usec = 0;
+    if (settimeofday(&tv, NULL) != 0) {
+        printf("clock_settime failed: settimeofday\n");
+        return 1;
+    }
+    struct timespec m0, m1;
+    clock_gettime(CLOCK_MONOTONIC, &m0);
+    usleep(10000);
+    clock_gettime(CLOCK_MONOTONIC, &m1);
+    clock_gettime(CLOCK_REALTIME, &now);
+    if (now.tv_sec < tv.tv_sec || now.tv_sec > tv.tv_sec + 5 || m1.tv_sec - m0.tv_sec > 1) {
+        printf("clock_settime failed: unexpected time after settimeofday\n");
+        return 1;
+    }
+
+    clock_settime(CLOCK_REALTIME, &saved);
+    printf("clock_settime passed!\n");
+    return 0;
+}
@@ -0,0 +1,118 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#ifndef SYS_clone3
+#define SYS_clone3 435
+#endif
+
+struct clone_args {
+    uint64_t flags;
+    uint64_t pidfd;
+    uint64_t child_tid;
+    uint64_t parent_tid;
+    uint64_t exit_signal;
+    uint64_t stack;
+    uint64_t stack_size;
+    uint64_t tls;
+    uint64_t set_tid;
+    uint64_t set_tid_size;
+    uint64_t cgroup;
+};
+
+// 比内核所知的结构更大，多出的部分用于检查 E2BIG
+struct clone_args_ext {
+    struct clone_args args;
+    uint64_t future;
+};
+
+static long clone3(void *args, size_t size)
+{
+    return syscall(SYS_clone3, args, size);
+}
+
+// 按 fork 的语义创建子进程，子进程以 code 退出，返回其退出码
+static int run_child(void *args, size_t size, int code)
+{
+    long pid = clone3(args, size);
+    if (pid < 0)
+        return -1;
+    if (pid == 0)
+        _exit(code);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return -1;
+    return WEXITSTATUS(status);
+}
+
+int main()
+{
+    struct clone_args_ext ext;
+    memset(&ext, 0, sizeof(ext));
+    ext.args.exit_signal = SIGCHLD;
+    if (run_child(&ext.args, sizeof(ext.args), 7) != 7) {
+        printf("clone3 failed: child did not exit with 7\n");
+        return 1;
+    }
+
+    // 只包含第一版字段的结构
+    if (run_child(&ext.args, 64, 8) != 8) {
+        printf("clone3 failed: version 0 struct rejected\n");
+        return 1;
+    }
+
+    // 更大的结构只要多出的部分为 0 就被接受
+    if (run_child(&ext, sizeof(ext), 9) != 9) {
+        printf("clone3 failed: larger struct with zero tail rejected\n");
+        return 1;
+    }
+    ext.future = 1;
+    errno = 0;
+    if (clone3(&ext, sizeof(ext)) != -1 || errno != E2BIG) {
+        printf("clone3 failed: nonzero tail accepted\n");
+        return 1;
+    }
+    ext.future = 0;
+
+    errno = 0;
+    if (clone3(&ext.args, 56) != -1 || errno != EINVAL) {
+        printf("clone3 failed: undersized struct accepted\n");
+        return 1;
+    }
+
+    // 栈和大小必须同时给出
+    static char stack[4096];
+    ext.args.stack = (uint64_t)(uintptr_t)stack;
+    errno = 0;
+    if (clone3(&ext.args, sizeof(ext.args)) != -1 || errno != EINVAL) {
+        printf("clone3 failed: stack without size accepted\n");
+        return 1;
+    }
+    ext.args.stack = 0;
+
+    // 退出信号不能放在 flags 中
+    ext.args.flags = SIGCHLD;
+    errno = 0;
+    if (clone3(&ext.args, sizeof(ext.args)) != -1 || errno != EINVAL) {
+        printf("clone3 failed: signal in flags accepted\n");
+        return 1;
+    }
+
+    // 不支持的标志被拒绝，不会创建子进程（本内核返回 ENOSYS，让 C 库回退到 clone）
+    ext.args.flags = CLONE_PIDFD;
+    if (clone3(&ext.args, sizeof(ext.args)) != -1) {
+        printf("clone3 failed: CLONE_PIDFD without pidfd accepted\n");
+        return 1;
+    }
+    ext.args.flags = 0;
+
+    printf("clone3 passed!\n");
+    return 0;
+}
@@ -0,0 +1,124 @@
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define DEPTH 64
+
+static volatile pid_t handler_child;
+static volatile int handler_status = -1;
+
+// 等待子进程并返回它的退出码，异常退出时返回 -1
+static int wait_exit(pid_t pid)
+{
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status)) {
+        return -1;
+    }
+    return WEXITSTATUS(status);
+}
+
+// 在信号处理函数中 fork：此时内核正在处理被信号打断的 nanosleep，
+// 子进程必须从处理函数中的 fork 返回，而不是从 nanosleep 返回
+static void alarm_handler(int sig)
+{
+    (void)sig;
+    volatile int local = 0x5a5a;
+    pid_t pid = fork();
+    if (pid == 0) {
+        _exit(local == 0x5a5a ? 7 : 1);
+    }
+    handler_child = pid;
+    handler_status = pid > 0 ? wait_exit(pid) : -1;
+}
+
+// 递归到 `depth` 层后 fork，子进程逐层返回并校验每层的局部变量
+static long nested_fork(int depth, pid_t *pid)
+{
+    volatile long local = depth * 3 + 1;
+    long sum;
+    if (depth == 0) {
+        *pid = fork();
+        sum = 0;
+    } else {
+        sum = nested_fork(depth - 1, pid);
+    }
+    if (local != depth * 3 + 1) {
+        return -1;
+    }
+    return sum < 0 ? -1 : sum + local;
+}
+
+int main(void)
+{
+    // 从嵌套的用户态调用中 fork，父子进程都应回到 fork 的调用点
+    long expected = 0;
+    for (int i = 0; i <= DEPTH; i++) {
+        expected += i * 3 + 1;
+    }
+    pid_t pid = -1;
+    long sum = nested_fork(DEPTH, &pid);
+    if (pid == 0) {
+        _exit(sum == expected ? 0 : 1);
+    }
+    if (pid < 0) {
+        printf("clone_nested failed: fork\n");
+        return 1;
+    }
+    if (sum != expected) {
+        printf("clone_nested failed: parent sum %ld, expected %ld\n", sum, expected);
+        return 1;
+    }
+    int code = wait_exit(pid);
+    if (code != 0) {
+        printf("clone_nested failed: nested child exited with %d\n", code);
+        return 1;
+    }
+
+    // 从打断了 nanosleep 的信号处理函数中 fork
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = alarm_handler;
+    if (sigaction(SIGALRM, &sa, NULL) != 0) {
+        printf("clone_nested failed: sigaction\n");
+        return 1;
+    }
+    struct sigevent sev;
+    memset(&sev, 0, sizeof(sev));
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGALRM;
+    timer_t timer;
+    struct itimerspec its = {{0, 0}, {0, 20000000}};
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) != 0
+        || timer_settime(timer, 0, &its, NULL) != 0) {
+        printf("clone_nested failed: timer_create\n");
+        return 1;
+    }
+    struct timespec ts = {2, 0};
+    nanosleep(&ts, NULL);
+    if (handler_child <= 0) {
+        printf("clone_nested failed: handler did not fork\n");
+        return 1;
+    }
+    if (handler_status != 7) {
+        printf("clone_nested failed: handler child exited with %d\n", handler_status);
+        return 1;
+    }
+
+    // 子进程从 vfork 返回后应看到与父进程相同的栈帧
+    volatile int marker = 0x1234;
+    pid = vfork();
+    if (pid == 0) {
+        _exit(marker == 0x1234 ? 0 : 1);
+    }
+    code = pid > 0 ? wait_exit(pid) : -1;
+    if (code != 0) {
+        printf("clone_nested failed: vfork child exited with %d\n", code);
+        return 1;
+    }
+
+    printf("clone_nested passed!\n");
+    return 0;
+}
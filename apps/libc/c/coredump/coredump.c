@@ -0,0 +1,266 @@
+#include <elf.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/prctl.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define LAZY_PAGES 16
+
+// 子进程崩溃前写入的标记，在核心转储文件中查找
+static char marker[64];
+
+// 子进程的崩溃方式
+enum crash {
+    CRASH_SEGV,
+    CRASH_ABORT,
+    CRASH_TERM,
+};
+
+// fork 一个子进程，按 limit 设置 RLIMIT_CORE、按 dumpable 设置可转储标志后崩溃，
+// 返回 wait 得到的状态，pid 为子进程号
+static int crash_child(rlim_t limit, int dumpable, enum crash how, pid_t *pid)
+{
+    *pid = fork();
+    if (*pid < 0) {
+        printf("coredump failed: fork errno %d\n", errno);
+        exit(1);
+    }
+    if (*pid == 0) {
+        struct rlimit rl = {limit, RLIM_INFINITY};
+        if (setrlimit(RLIMIT_CORE, &rl) != 0 || prctl(PR_SET_DUMPABLE, dumpable, 0, 0, 0) != 0)
+            _exit(100);
+        snprintf(marker, sizeof(marker), "coredump marker of %d", getpid());
+        switch (how) {
+        case CRASH_SEGV:
+            *(volatile int *)0 = 1;
+            break;
+        case CRASH_ABORT:
+            abort();
+        case CRASH_TERM:
+            kill(getpid(), SIGTERM);
+            break;
+        }
+        _exit(101);
+    }
+    int status;
+    if (waitpid(*pid, &status, 0) != *pid) {
+        printf("coredump failed: waitpid errno %d\n", errno);
+        exit(1);
+    }
+    return status;
+}
+
+// 子进程的核心转储文件的路径
+static void core_path(pid_t pid, char *path, size_t size)
+{
+    snprintf(path, size, "core.%d", pid);
+}
+
+// 检查子进程没有转储核心
+static int expect_no_core(const char *what, int status, int signo, pid_t pid)
+{
+    char path[32];
+    core_path(pid, path, sizeof(path));
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != signo || WCOREDUMP(status)) {
+        printf("coredump failed: %s: status %#x\n", what, status);
+        return 1;
+    }
+    int fd = open(path, O_RDONLY);
+    if (fd >= 0 || errno != ENOENT) {
+        printf("coredump failed: %s: %s exists\n", what, path);
+        close(fd);
+        unlink(path);
+        return 1;
+    }
+    return 0;
+}
+
+static int read_at(int fd, off_t offset, void *buf, size_t len)
+{
+    if (lseek(fd, offset, SEEK_SET) != offset)
+        return -1;
+    return read(fd, buf, len) == (ssize_t)len ? 0 : -1;
+}
+
+// 在 PT_NOTE 段中查找类型为 type 的注释，返回其内容在文件中的偏移
+static off_t find_note(int fd, const Elf64_Phdr *note, unsigned type, size_t *size)
+{
+    off_t off = note->p_offset;
+    off_t end = note->p_offset + note->p_filesz;
+    while (off + (off_t)sizeof(Elf64_Nhdr) <= end) {
+        Elf64_Nhdr nhdr;
+        if (read_at(fd, off, &nhdr, sizeof(nhdr)) != 0)
+            return -1;
+        off_t desc = off + sizeof(nhdr) + ((nhdr.n_namesz + 3) & ~3u);
+        if (nhdr.n_type == type) {
+            *size = nhdr.n_descsz;
+            return desc;
+        }
+        off = desc + ((nhdr.n_descsz + 3) & ~3u);
+    }
+    return -1;
+}
+
+// 检查转储了核心的子进程的核心转储文件
+static int check_core(pid_t pid, int signo, const char *lazy)
+{
+    char path[32];
+    core_path(pid, path, sizeof(path));
+    int fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        printf("coredump failed: open %s errno %d\n", path, errno);
+        return 1;
+    }
+
+    Elf64_Ehdr ehdr;
+    if (read_at(fd, 0, &ehdr, sizeof(ehdr)) != 0 || memcmp(ehdr.e_ident, ELFMAG, SELFMAG) != 0 ||
+        ehdr.e_ident[EI_CLASS] != ELFCLASS64 || ehdr.e_type != ET_CORE ||
+        ehdr.e_phentsize != sizeof(Elf64_Phdr) || ehdr.e_phnum < 2) {
+        printf("coredump failed: bad ELF header\n");
+        return 1;
+    }
+    Elf64_Phdr phdrs[ehdr.e_phnum];
+    if (read_at(fd, ehdr.e_phoff, phdrs, sizeof(phdrs)) != 0 || phdrs[0].p_type != PT_NOTE) {
+        printf("coredump failed: bad program headers\n");
+        return 1;
+    }
+
+    // NT_PRSTATUS 中的 pr_cursig 和 pr_pid
+    size_t size;
+    off_t prstatus = find_note(fd, &phdrs[0], NT_PRSTATUS, &size);
+    unsigned short cursig;
+    int note_pid;
+    if (prstatus < 0 || size < 112 || read_at(fd, prstatus + 12, &cursig, sizeof(cursig)) != 0 ||
+        read_at(fd, prstatus + 32, &note_pid, sizeof(note_pid)) != 0 || cursig != signo ||
+        note_pid != pid) {
+        printf("coredump failed: bad NT_PRSTATUS\n");
+        return 1;
+    }
+    // NT_PRPSINFO 中的 pr_fname
+    char fname[16];
+    off_t prpsinfo = find_note(fd, &phdrs[0], NT_PRPSINFO, &size);
+    if (prpsinfo < 0 || size != 136 || read_at(fd, prpsinfo + 40, fname, sizeof(fname)) != 0 ||
+        strncmp(fname, "coredump", 8) != 0) {
+        printf("coredump failed: bad NT_PRPSINFO\n");
+        return 1;
+    }
+
+    // 标记所在的段中是子进程写入的内容，没有访问过的页为全零
+    char expected[64];
+    snprintf(expected, sizeof(expected), "coredump marker of %d", pid);
+    int found_marker = 0, found_lazy = 0;
+    for (int i = 1; i < ehdr.e_phnum; i++) {
+        Elf64_Phdr *ph = &phdrs[i];
+        if (ph->p_type != PT_LOAD || !(ph->p_flags & PF_R) || ph->p_offset % 4096 != 0) {
+            printf("coredump failed: bad PT_LOAD %d\n", i);
+            return 1;
+        }
+        uintptr_t addr = (uintptr_t)marker;
+        if (addr >= ph->p_vaddr && addr + sizeof(marker) <= ph->p_vaddr + ph->p_filesz) {
+            char buf[64];
+            if (read_at(fd, ph->p_offset + addr - ph->p_vaddr, buf, sizeof(buf)) != 0 ||
+                strcmp(buf, expected) != 0) {
+                printf("coredump failed: marker not in core\n");
+                return 1;
+            }
+            found_marker = 1;
+        }
+        if ((uintptr_t)lazy == ph->p_vaddr && ph->p_filesz == LAZY_PAGES * 4096) {
+            static char page[4096];
+            for (int p = 0; p < LAZY_PAGES; p++) {
+                if (read_at(fd, ph->p_offset + p * 4096, page, sizeof(page)) != 0) {
+                    printf("coredump failed: short lazy segment\n");
+                    return 1;
+                }
+                for (size_t j = 0; j < sizeof(page); j++) {
+                    if (page[j] != 0) {
+                        printf("coredump failed: lazy page not zero\n");
+                        return 1;
+                    }
+                }
+            }
+            found_lazy = 1;
+        }
+    }
+    if (!found_marker || !found_lazy) {
+        printf("coredump failed: segments missing (marker %d, lazy %d)\n", found_marker,
+               found_lazy);
+        return 1;
+    }
+    close(fd);
+    unlink(path);
+    return 0;
+}
+
+int main(void)
+{
+    // 与 Linux 一致，默认可以转储，但 RLIMIT_CORE 的软限制为 0
+    struct rlimit rl;
+    if (getrlimit(RLIMIT_CORE, &rl) != 0 || rl.rlim_cur != 0 || rl.rlim_max != RLIM_INFINITY) {
+        printf("coredump failed: default RLIMIT_CORE\n");
+        return 1;
+    }
+    if (prctl(PR_GET_DUMPABLE, 0, 0, 0, 0) != 1) {
+        printf("coredump failed: PR_GET_DUMPABLE\n");
+        return 1;
+    }
+    if (prctl(PR_SET_DUMPABLE, 2, 0, 0, 0) != -1 || errno != EINVAL) {
+        printf("coredump failed: PR_SET_DUMPABLE 2\n");
+        return 1;
+    }
+
+    // 一段从未访问过的匿名映射，转储时应按全零写入
+    char *lazy = mmap(NULL, LAZY_PAGES * 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS,
+                      -1, 0);
+    if (lazy == MAP_FAILED) {
+        printf("coredump failed: mmap errno %d\n", errno);
+        return 1;
+    }
+
+    pid_t pid;
+    int status = crash_child(0, 1, CRASH_SEGV, &pid);
+    if (expect_no_core("RLIMIT_CORE 0", status, SIGSEGV, pid))
+        return 1;
+    status = crash_child(RLIM_INFINITY, 0, CRASH_SEGV, &pid);
+    if (expect_no_core("not dumpable", status, SIGSEGV, pid))
+        return 1;
+    status = crash_child(RLIM_INFINITY, 1, CRASH_TERM, &pid);
+    if (expect_no_core("SIGTERM", status, SIGTERM, pid))
+        return 1;
+
+    // SIGSEGV 和 SIGABRT 转储核心
+    int signals[] = {SIGSEGV, SIGABRT};
+    enum crash crashes[] = {CRASH_SEGV, CRASH_ABORT};
+    for (int i = 0; i < 2; i++) {
+        status = crash_child(RLIM_INFINITY, 1, crashes[i], &pid);
+        if (!WIFSIGNALED(status) || WTERMSIG(status) != signals[i] || !WCOREDUMP(status)) {
+            printf("coredump failed: signal %d: status %#x\n", signals[i], status);
+            return 1;
+        }
+        if (check_core(pid, signals[i], lazy))
+            return 1;
+    }
+
+    // 超过 RLIMIT_CORE 的部分被截断
+    status = crash_child(4096, 1, CRASH_SEGV, &pid);
+    char path[32];
+    core_path(pid, path, sizeof(path));
+    struct stat st;
+    if (!WCOREDUMP(status) || stat(path, &st) != 0 || st.st_size != 4096) {
+        printf("coredump failed: truncated core: status %#x\n", status);
+        return 1;
+    }
+    unlink(path);
+
+    printf("coredump passed!\n");
+    return 0;
+}
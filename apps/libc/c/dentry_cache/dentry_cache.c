@@ -0,0 +1,175 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// 在根文件系统上测试，/tmp 不是 FAT
+#define DIR "/dentry_cache_test"
+#define ROUNDS 50
+
+// path 应存在且类型为 type（S_IFREG、S_IFDIR），type 为 0 时应不存在
+static int expect(const char *path, int type)
+{
+    struct stat st;
+    int ret = stat(path, &st);
+    if (type == 0 ? ret != -1 || errno != ENOENT : ret != 0 || (st.st_mode & S_IFMT) != type) {
+        printf("dentry_cache failed: %s %s\n", path,
+               type == 0 ? "still exists" : ret != 0 ? "missing" : "has wrong type");
+        return 1;
+    }
+    return 0;
+}
+
+static int create_file(const char *path, const char *data)
+{
+    int fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, data, strlen(data)) != (ssize_t)strlen(data)) {
+        printf("dentry_cache failed: create %s errno %d\n", path, errno);
+        return 1;
+    }
+    close(fd);
+    return 0;
+}
+
+// 文件的内容应为 data
+static int expect_data(const char *path, const char *data)
+{
+    char buf[64] = {0};
+    int fd = open(path, O_RDONLY);
+    if (fd < 0 || read(fd, buf, sizeof(buf) - 1) < 0 || strcmp(buf, data) != 0) {
+        printf("dentry_cache failed: %s contains \"%s\" instead of \"%s\"\n", path, buf, data);
+        if (fd >= 0)
+            close(fd);
+        return 1;
+    }
+    close(fd);
+    return 0;
+}
+
+// 读取 /proc/dentrycache 中的计数，没有该文件（如在 Linux 上）时返回 -1
+static long counter(const char *name)
+{
+    char buf[512];
+    int fd = open("/proc/dentrycache", O_RDONLY);
+    if (fd < 0)
+        return -1;
+    ssize_t n = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    if (n <= 0)
+        return -1;
+    buf[n] = '\0';
+    char *line = strstr(buf, name);
+    return line ? atol(line + strlen(name) + 1) : -1;
+}
+
+// 与重命名交替的查找总能看到重命名后的目录树
+static int check_renames(void)
+{
+    if (mkdir(DIR "/a", 0755) != 0 || mkdir(DIR "/a/d", 0755) != 0 ||
+        create_file(DIR "/a/d/e", "e"))
+        return 1;
+    for (int i = 0; i < ROUNDS; i++) {
+        // 先查找使路径被缓存，包括不存在的名字
+        if (expect(DIR "/a/d/e", S_IFREG) || expect(DIR "/b", 0) || expect(DIR "/b/d/e", 0))
+            return 1;
+        if (rename(DIR "/a", DIR "/b") != 0) {
+            printf("dentry_cache failed: rename errno %d\n", errno);
+            return 1;
+        }
+        // 目录下的路径随目录一起移动
+        if (expect(DIR "/a", 0) || expect(DIR "/a/d/e", 0) || expect(DIR "/b/d/e", S_IFREG) ||
+            expect_data(DIR "/b/d/e", "e"))
+            return 1;
+        if (rename(DIR "/b", DIR "/a") != 0) {
+            printf("dentry_cache failed: rename back errno %d\n", errno);
+            return 1;
+        }
+    }
+
+    // 重命名文件覆盖已有的文件
+    if (create_file(DIR "/x", "x") || create_file(DIR "/y", "y") || expect_data(DIR "/y", "y"))
+        return 1;
+    if (rename(DIR "/x", DIR "/y") != 0 || expect(DIR "/x", 0) || expect_data(DIR "/y", "x"))
+        return 1;
+    // 删除后新建同名但类型不同的项
+    if (unlink(DIR "/y") != 0 || expect(DIR "/y", 0) || mkdir(DIR "/y", 0755) != 0 ||
+        expect(DIR "/y", S_IFDIR) || rmdir(DIR "/y") != 0 || expect(DIR "/y", 0) ||
+        create_file(DIR "/y", "z") || expect(DIR "/y", S_IFREG) || expect_data(DIR "/y", "z"))
+        return 1;
+    return unlink(DIR "/y") || unlink(DIR "/a/d/e") || rmdir(DIR "/a/d") || rmdir(DIR "/a");
+}
+
+// 另一个进程重命名的同时查找，结束后看到的是最终的目录树
+static int check_concurrent(void)
+{
+    if (create_file(DIR "/p", "p"))
+        return 1;
+    pid_t pid = fork();
+    if (pid == 0) {
+        for (int i = 0; i < ROUNDS * 4; i++) {
+            if (rename(i % 2 ? DIR "/q" : DIR "/p", i % 2 ? DIR "/p" : DIR "/q") != 0)
+                _exit(1);
+        }
+        // 最后一次把 q 重命名为 p 后，再移动到 r
+        _exit(rename(DIR "/p", DIR "/r") != 0);
+    }
+    struct stat st;
+    int status;
+    while (waitpid(pid, &status, WNOHANG) == 0) {
+        stat(DIR "/p", &st);
+        stat(DIR "/q", &st);
+        stat(DIR "/r", &st);
+    }
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("dentry_cache failed: renaming child status %#x\n", status);
+        return 1;
+    }
+    if (expect(DIR "/p", 0) || expect(DIR "/q", 0) || expect_data(DIR "/r", "p"))
+        return 1;
+    return unlink(DIR "/r");
+}
+
+// 重复查找同一路径命中缓存，不存在的名字也被缓存
+static int check_counters(void)
+{
+    if (counter("Hits") < 0)
+        return 0;
+    if (create_file(DIR "/c", "c"))
+        return 1;
+    struct stat st;
+    stat(DIR "/c", &st);
+    stat(DIR "/missing", &st);
+    long hits = counter("Hits"), negative = counter("NegativeHits");
+    for (int i = 0; i < 10; i++) {
+        if (stat(DIR "/c", &st) != 0 || stat(DIR "/missing", &st) != -1) {
+            printf("dentry_cache failed: repeated lookups\n");
+            return 1;
+        }
+    }
+    if (counter("Hits") < hits + 10 || counter("NegativeHits") < negative + 10) {
+        printf("dentry_cache failed: hits %ld -> %ld, negative hits %ld -> %ld\n", hits,
+               counter("Hits"), negative, counter("NegativeHits"));
+        return 1;
+    }
+    return unlink(DIR "/c");
+}
+
+int main(void)
+{
+    if (mkdir(DIR, 0755) != 0 && errno != EEXIST) {
+        printf("dentry_cache failed: mkdir errno %d\n", errno);
+        return 1;
+    }
+    if (check_renames() || check_concurrent() || check_counters())
+        return 1;
+    if (rmdir(DIR) != 0) {
+        printf("dentry_cache failed: rmdir errno %d\n", errno);
+        return 1;
+    }
+    printf("dentry_cache passed!\n");
+    return 0;
+}
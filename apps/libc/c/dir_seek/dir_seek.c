@@ -0,0 +1,199 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define DIR_NAME "dir_seek_dir"
+#define NFILES 40
+
+struct linux_dirent64 {
+    uint64_t d_ino;
+    int64_t d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+// 按顺序读到的文件名和对应的 d_off
+static char names[NFILES + 1][16];
+static long long offs[NFILES + 1];
+
+static uint64_t buf[16];
+
+// 每次 getdents64 只读一个目录项，读到文件名为 name 的目录项时返回 1，读到末尾时返回 0
+static int next_entry(int fd, char *name, long long *off)
+{
+    for (;;) {
+        long n = syscall(SYS_getdents64, fd, buf, 40);
+        if (n <= 0)
+            return (int)n;
+        struct linux_dirent64 *ent = (struct linux_dirent64 *)buf;
+        if (strcmp(ent->d_name, ".") == 0 || strcmp(ent->d_name, "..") == 0)
+            continue;
+        strcpy(name, ent->d_name);
+        *off = ent->d_off;
+        return 1;
+    }
+}
+
+static void cleanup(void)
+{
+    char path[64];
+    for (int i = 0; i <= NFILES; i++) {
+        snprintf(path, sizeof(path), DIR_NAME "/f%d", i);
+        unlink(path);
+    }
+    rmdir(DIR_NAME);
+}
+
+int main(void)
+{
+    cleanup();
+    char path[64];
+    if (mkdir(DIR_NAME, 0755) != 0) {
+        printf("dir_seek failed: mkdir errno %d\n", errno);
+        return 1;
+    }
+    for (int i = 0; i < NFILES; i++) {
+        snprintf(path, sizeof(path), DIR_NAME "/f%d", i);
+        close(open(path, O_CREAT | O_WRONLY, 0644));
+    }
+
+    int fd = open(DIR_NAME, O_RDONLY | O_DIRECTORY);
+    if (fd < 0) {
+        printf("dir_seek failed: open errno %d\n", errno);
+        return 1;
+    }
+    int count = 0;
+    char name[16];
+    long long off;
+    while (count <= NFILES && next_entry(fd, names[count], &offs[count]) == 1)
+        count++;
+    if (count != NFILES) {
+        printf("dir_seek failed: read %d of %d entries\n", count, NFILES);
+        return 1;
+    }
+
+    // 回到开头后读到同样的目录项和 d_off
+    if (lseek(fd, 0, SEEK_SET) != 0) {
+        printf("dir_seek failed: rewind errno %d\n", errno);
+        return 1;
+    }
+    for (int i = 0; i < NFILES; i++) {
+        if (next_entry(fd, name, &off) != 1 || strcmp(name, names[i]) != 0 || off != offs[i]) {
+            printf("dir_seek failed: entry %d after rewind\n", i);
+            return 1;
+        }
+    }
+
+    // 定位到某个目录项的 d_off 后从它的下一个目录项继续
+    if (lseek(fd, offs[9], SEEK_SET) != offs[9] || next_entry(fd, name, &off) != 1 ||
+        strcmp(name, names[10]) != 0 || off != offs[10]) {
+        printf("dir_seek failed: resume after entry 9\n");
+        return 1;
+    }
+
+    // 中间增删目录项后，已有目录项的 d_off 不变，之前得到的 d_off 仍然有效
+    snprintf(path, sizeof(path), DIR_NAME "/%s", names[20]);
+    if (unlink(path) != 0) {
+        printf("dir_seek failed: unlink errno %d\n", errno);
+        return 1;
+    }
+    snprintf(path, sizeof(path), DIR_NAME "/f%d", NFILES);
+    close(open(path, O_CREAT | O_WRONLY, 0644));
+    if (lseek(fd, offs[19], SEEK_SET) != offs[19]) {
+        printf("dir_seek failed: seek to entry 19 errno %d\n", errno);
+        return 1;
+    }
+    int found_new = 0;
+    for (int i = 21; i < NFILES; i++) {
+        if (next_entry(fd, name, &off) != 1) {
+            printf("dir_seek failed: entries after 19 ended early\n");
+            return 1;
+        }
+        // 新建的文件可能出现在任何位置，但只出现一次
+        if (strcmp(name, path + strlen(DIR_NAME "/")) == 0) {
+            found_new++;
+            i--;
+            continue;
+        }
+        if (strcmp(name, names[i]) != 0 || off != offs[i]) {
+            printf("dir_seek failed: entry %d is %s after changes\n", i, name);
+            return 1;
+        }
+    }
+    // 被删除的目录项的 d_off 仍可以用于定位
+    if (lseek(fd, offs[20], SEEK_SET) != offs[20]) {
+        printf("dir_seek failed: seek to removed entry errno %d\n", errno);
+        return 1;
+    }
+    if (next_entry(fd, name, &off) != 1 || (strcmp(name, names[21]) != 0 && found_new == 0)) {
+        printf("dir_seek failed: resume after removed entry got %s\n", name);
+        return 1;
+    }
+
+    // 不是由 d_off 返回过的偏移无效
+    long long max_off = 0;
+    for (int i = 0; i < NFILES; i++)
+        max_off = offs[i] > max_off ? offs[i] : max_off;
+    errno = 0;
+    if (lseek(fd, max_off + 1000, SEEK_SET) != -1 || errno != EINVAL) {
+        printf("dir_seek failed: seek to unknown offset errno %d\n", errno);
+        return 1;
+    }
+    errno = 0;
+    if (lseek(fd, 0, SEEK_END) != -1 || errno != EINVAL) {
+        printf("dir_seek failed: SEEK_END errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    // telldir 和 seekdir 使用同样的偏移
+    DIR *d = opendir(DIR_NAME);
+    if (!d) {
+        printf("dir_seek failed: opendir errno %d\n", errno);
+        return 1;
+    }
+    struct dirent *ent;
+    long mark = -1;
+    char after_mark[16] = "";
+    int seen = 0;
+    while ((ent = readdir(d)) != NULL) {
+        if (ent->d_name[0] == '.')
+            continue;
+        seen++;
+        if (seen == 5)
+            mark = telldir(d);
+        else if (seen == 6)
+            strcpy(after_mark, ent->d_name);
+    }
+    if (seen != NFILES || mark < 0) {
+        printf("dir_seek failed: readdir saw %d entries\n", seen);
+        return 1;
+    }
+    seekdir(d, mark);
+    ent = readdir(d);
+    if (!ent || strcmp(ent->d_name, after_mark) != 0) {
+        printf("dir_seek failed: seekdir resumed at %s\n", ent ? ent->d_name : "(end)");
+        return 1;
+    }
+    rewinddir(d);
+    seen = 0;
+    while ((ent = readdir(d)) != NULL)
+        seen += ent->d_name[0] != '.';
+    closedir(d);
+    if (seen != NFILES) {
+        printf("dir_seek failed: rewinddir saw %d entries\n", seen);
+        return 1;
+    }
+
+    cleanup();
+    printf("dir_seek passed!\n");
+    return 0;
+}
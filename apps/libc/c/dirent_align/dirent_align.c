@@ -0,0 +1,156 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define DIR_NAME "dirent_align_dir"
+#define MAX_NAME 24
+
+struct linux_dirent64 {
+    uint64_t d_ino;
+    int64_t d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+// 用于读取目录项的缓冲区，与 glibc 一样按 8 字节对齐
+static uint64_t buf[512];
+
+// 长度为 len 的文件名
+static void make_name(char *name, int len)
+{
+    for (int i = 0; i < len; i++)
+        name[i] = 'a' + (len + i) % 26;
+    name[len] = '\0';
+}
+
+static int cleanup(void)
+{
+    char path[64 + MAX_NAME];
+    for (int len = 1; len <= MAX_NAME; len++) {
+        char name[MAX_NAME + 1];
+        make_name(name, len);
+        snprintf(path, sizeof(path), DIR_NAME "/%s", name);
+        unlink(path);
+    }
+    return rmdir(DIR_NAME);
+}
+
+int main(void)
+{
+    // 文件名长度覆盖所有的对齐情况
+    cleanup();
+    if (mkdir(DIR_NAME, 0755) != 0) {
+        printf("dirent_align failed: mkdir errno %d\n", errno);
+        return 1;
+    }
+    char path[64 + MAX_NAME];
+    for (int len = 1; len <= MAX_NAME; len++) {
+        char name[MAX_NAME + 1];
+        make_name(name, len);
+        snprintf(path, sizeof(path), DIR_NAME "/%s", name);
+        int fd = open(path, O_CREAT | O_WRONLY, 0644);
+        if (fd < 0) {
+            printf("dirent_align failed: create %s errno %d\n", name, errno);
+            return 1;
+        }
+        close(fd);
+    }
+
+    int dir = open(DIR_NAME, O_RDONLY | O_DIRECTORY);
+    if (dir < 0) {
+        printf("dirent_align failed: open errno %d\n", errno);
+        return 1;
+    }
+    // 无效的用户缓冲区返回 EFAULT
+    volatile unsigned long bad_addr = 8;
+    errno = 0;
+    if (syscall(SYS_getdents64, dir, (void *)bad_addr, sizeof(buf)) != -1 || errno != EFAULT) {
+        printf("dirent_align failed: bad buffer accepted\n");
+        return 1;
+    }
+    lseek(dir, 0, SEEK_SET);
+    int seen = 0;
+    for (;;) {
+        // 先填满缓冲区，检查填充字节被清零
+        memset(buf, 0xff, sizeof(buf));
+        long n = syscall(SYS_getdents64, dir, buf, sizeof(buf));
+        if (n < 0) {
+            printf("dirent_align failed: getdents64 errno %d\n", errno);
+            return 1;
+        }
+        if (n == 0)
+            break;
+        char *base = (char *)buf;
+        for (long pos = 0; pos < n;) {
+            // 与 glibc 的 readdir 一样直接访问目录项，要求每个目录项都按 8 字节对齐
+            struct linux_dirent64 *ent = (struct linux_dirent64 *)(base + pos);
+            if (pos % 8 != 0 || ent->d_reclen % 8 != 0 || pos + ent->d_reclen > n) {
+                printf("dirent_align failed: record at %ld with length %u\n", pos, ent->d_reclen);
+                return 1;
+            }
+            size_t max_len = ent->d_reclen - offsetof(struct linux_dirent64, d_name);
+            size_t len = strnlen(ent->d_name, max_len);
+            if (len == max_len) {
+                printf("dirent_align failed: name at %ld not terminated\n", pos);
+                return 1;
+            }
+            // 结尾的 '\0' 之后到下一个目录项之间都是 0
+            for (size_t i = len; i < max_len; i++) {
+                if (ent->d_name[i] != 0) {
+                    printf("dirent_align failed: padding of %s not zeroed\n", ent->d_name);
+                    return 1;
+                }
+            }
+            if (len >= 1 && len <= MAX_NAME) {
+                char name[MAX_NAME + 1];
+                make_name(name, len);
+                if (strcmp(name, ent->d_name) == 0)
+                    seen++;
+            }
+            pos += ent->d_reclen;
+        }
+    }
+    close(dir);
+    if (seen != MAX_NAME) {
+        printf("dirent_align failed: saw %d of %d files\n", seen, MAX_NAME);
+        return 1;
+    }
+
+    // 通过 readdir 读取同样能看到所有文件
+    DIR *d = opendir(DIR_NAME);
+    if (!d) {
+        printf("dirent_align failed: opendir errno %d\n", errno);
+        return 1;
+    }
+    seen = 0;
+    struct dirent *ent;
+    while ((ent = readdir(d)) != NULL) {
+        if (((uintptr_t)ent) % 8 != 0) {
+            printf("dirent_align failed: readdir returned unaligned %s\n", ent->d_name);
+            return 1;
+        }
+        if (ent->d_name[0] != '.')
+            seen++;
+    }
+    closedir(d);
+    if (seen != MAX_NAME) {
+        printf("dirent_align failed: readdir saw %d of %d files\n", seen, MAX_NAME);
+        return 1;
+    }
+
+    if (cleanup() != 0) {
+        printf("dirent_align failed: cleanup errno %d\n", errno);
+        return 1;
+    }
+    printf("dirent_align passed!\n");
+    return 0;
+}
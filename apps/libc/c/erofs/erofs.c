@@ -0,0 +1,79 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+// 期望调用失败且错误码为 EROFS
+static int expect_erofs(const char *what, int ret)
+{
+    if (ret != -1 || errno != EROFS) {
+        printf("erofs failed: %s returned %d, errno %d\n", what, ret, errno);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    char c;
+
+    int fd = openat(AT_FDCWD, "/tmp/erofs_file", O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0 || write(fd, "a", 1) != 1 || close(fd) != 0 ||
+        mkdirat(AT_FDCWD, "/tmp/erofs_dir", 0755) != 0) {
+        printf("erofs failed: setup errno %d\n", errno);
+        return 1;
+    }
+
+    // 把 /tmp 重新挂载为只读
+    if (mount("none", "/tmp", "", MS_REMOUNT | MS_RDONLY, NULL) != 0) {
+        printf("erofs failed: remount read-only errno %d\n", errno);
+        return 1;
+    }
+
+    // 只读地打开和读取不受影响
+    fd = openat(AT_FDCWD, "/tmp/erofs_file", O_RDONLY);
+    if (fd < 0 || read(fd, &c, 1) != 1 || c != 'a' || close(fd) != 0) {
+        printf("erofs failed: read-only open errno %d\n", errno);
+        return 1;
+    }
+
+    int failed = 0;
+    failed |= expect_erofs("open for writing", openat(AT_FDCWD, "/tmp/erofs_file", O_WRONLY));
+    failed |= expect_erofs("open with O_TRUNC",
+                           openat(AT_FDCWD, "/tmp/erofs_file", O_RDONLY | O_TRUNC));
+    failed |= expect_erofs("create", openat(AT_FDCWD, "/tmp/erofs_new", O_CREAT | O_RDWR, 0644));
+    failed |= expect_erofs("mkdirat", mkdirat(AT_FDCWD, "/tmp/erofs_new", 0755));
+    failed |= expect_erofs("mknodat", mknodat(AT_FDCWD, "/tmp/erofs_new", S_IFIFO | 0644, 0));
+    failed |= expect_erofs("unlinkat", unlinkat(AT_FDCWD, "/tmp/erofs_file", 0));
+    failed |= expect_erofs("rmdir", unlinkat(AT_FDCWD, "/tmp/erofs_dir", AT_REMOVEDIR));
+    failed |= expect_erofs("renameat",
+                           renameat(AT_FDCWD, "/tmp/erofs_file", AT_FDCWD, "/tmp/erofs_new"));
+    failed |= expect_erofs("linkat",
+                           linkat(AT_FDCWD, "/tmp/erofs_file", AT_FDCWD, "/tmp/erofs_new", 0));
+    failed |= expect_erofs("utimensat", utimensat(AT_FDCWD, "/tmp/erofs_file", NULL, 0));
+    if (failed) {
+        return 1;
+    }
+
+    // 文件没有被改动
+    if (openat(AT_FDCWD, "/tmp/erofs_new", O_RDONLY) >= 0 || errno != ENOENT) {
+        printf("erofs failed: file created on read-only mount\n");
+        return 1;
+    }
+
+    // 重新挂载为可写后恢复正常
+    if (mount("none", "/tmp", "", MS_REMOUNT, NULL) != 0) {
+        printf("erofs failed: remount read-write errno %d\n", errno);
+        return 1;
+    }
+    if (unlinkat(AT_FDCWD, "/tmp/erofs_file", 0) != 0 ||
+        unlinkat(AT_FDCWD, "/tmp/erofs_dir", AT_REMOVEDIR) != 0) {
+        printf("erofs failed: cleanup errno %d\n", errno);
+        return 1;
+    }
+
+    printf("erofs passed!\n");
+    return 0;
+}
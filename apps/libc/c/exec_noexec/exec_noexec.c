@@ -0,0 +1,81 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static const char *text_path = "exec_noexec_text";
+static const char *elf_path = "exec_noexec_elf";
+
+// 执行失败后进程的内存应保持不变
+static int canary = 0x5a5a5a5a;
+
+static int write_file(const char *path, const void *data, size_t len)
+{
+    int fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 0755);
+    if (fd < 0)
+        return -1;
+    ssize_t n = write(fd, data, len);
+    close(fd);
+    return n == (ssize_t)len ? 0 : -1;
+}
+
+// 复制自身，并将 ELF 头中的 e_machine 改为其他架构
+static int write_foreign_elf(const char *self)
+{
+    static char buf[1 << 20];
+    int fd = open(self, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    ssize_t len = 0, n;
+    while ((n = read(fd, buf + len, sizeof(buf) - len)) > 0)
+        len += n;
+    close(fd);
+    if (len < 64)
+        return -1;
+    // EM_SPARC
+    buf[18] = 2;
+    buf[19] = 0;
+    return write_file(elf_path, buf, len);
+}
+
+static int expect_errno(const char *what, int ret, int err)
+{
+    if (ret != -1 || errno != err) {
+        printf("exec_noexec failed: %s gives %d errno %d instead of %d\n", what, ret, errno, err);
+        return 1;
+    }
+    if (canary != 0x5a5a5a5a) {
+        printf("exec_noexec failed: memory lost after %s\n", what);
+        return 1;
+    }
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    (void)argc;
+    static const char text[] = "hello, this is not a program\n";
+    if (write_file(text_path, text, sizeof(text) - 1) || write_foreign_elf(argv[0])) {
+        printf("exec_noexec failed: cannot write files errno %d\n", errno);
+        return 1;
+    }
+    char *child_argv[] = {(char *)text_path, NULL};
+    char *envp[] = {NULL};
+    int failed = 0;
+    // 不是 ELF 文件，也不是脚本
+    failed |= expect_errno("execve text", execve(text_path, child_argv, envp), ENOEXEC);
+    // 其他架构的 ELF 文件
+    failed |= expect_errno("execve foreign elf", execve(elf_path, child_argv, envp), ENOEXEC);
+    // 参数数组或其中的字符串的地址无效
+    volatile unsigned long bad_addr = 8;
+    failed |= expect_errno("execve bad argv", execve(argv[0], (char **)bad_addr, envp), EFAULT);
+    char *bad_argv[] = {argv[0], (char *)bad_addr, NULL};
+    failed |= expect_errno("execve bad arg string", execve(argv[0], bad_argv, envp), EFAULT);
+    unlink(text_path);
+    unlink(elf_path);
+    if (failed)
+        return 1;
+    printf("exec_noexec passed!\n");
+    return 0;
+}
@@ -0,0 +1,190 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define NICE 5
+
+static void int_handler(int sig)
+{
+    (void)sig;
+}
+
+// 检查 /proc/self/status 中只有一个线程
+static int single_thread(void)
+{
+    char buf[1024];
+    int fd = open("/proc/self/status", O_RDONLY);
+    if (fd < 0) {
+        return 0;
+    }
+    ssize_t n = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    if (n <= 0) {
+        return 0;
+    }
+    buf[n] = '\0';
+    return strstr(buf, "Threads:\t1\n") != NULL;
+}
+
+// exec 之后的新程序：逐项检查进程状态
+static int check_after_exec(char *argv[])
+{
+    pid_t old_pid = atoi(argv[2]);
+    int keep_fd = atoi(argv[3]);
+    int cloexec_fd = atoi(argv[4]);
+    timer_t timer = (timer_t)strtol(argv[5], NULL, 10);
+
+    if (getpid() != old_pid) {
+        printf("exec_reset failed: pid changed from %d to %d\n", old_pid, getpid());
+        return 1;
+    }
+    if (!single_thread()) {
+        printf("exec_reset failed: more than one thread after exec\n");
+        return 1;
+    }
+
+    // 设置了处理函数的信号恢复默认，被忽略的信号保持忽略
+    struct sigaction sa;
+    if (sigaction(SIGINT, NULL, &sa) != 0 || sa.sa_handler != SIG_DFL) {
+        printf("exec_reset failed: SIGINT handler not reset\n");
+        return 1;
+    }
+    if (sigaction(SIGUSR1, NULL, &sa) != 0 || sa.sa_handler != SIG_IGN) {
+        printf("exec_reset failed: SIGUSR1 no longer ignored\n");
+        return 1;
+    }
+
+    // 信号掩码和未决信号保持不变
+    sigset_t set;
+    if (sigprocmask(SIG_BLOCK, NULL, &set) != 0 || !sigismember(&set, SIGUSR2)) {
+        printf("exec_reset failed: SIGUSR2 no longer blocked\n");
+        return 1;
+    }
+    if (sigpending(&set) != 0 || !sigismember(&set, SIGUSR2)) {
+        printf("exec_reset failed: pending SIGUSR2 lost\n");
+        return 1;
+    }
+
+    // 备用信号栈被清除
+    stack_t ss;
+    if (sigaltstack(NULL, &ss) != 0 || !(ss.ss_flags & SS_DISABLE)) {
+        printf("exec_reset failed: alternate signal stack kept\n");
+        return 1;
+    }
+
+    // POSIX 定时器被删除
+    struct itimerspec its;
+    if (timer_gettime(timer, &its) == 0 || errno != EINVAL) {
+        printf("exec_reset failed: timer survived exec\n");
+        return 1;
+    }
+
+    // 只关闭设置了 close-on-exec 的文件
+    if (fcntl(keep_fd, F_GETFD) < 0) {
+        printf("exec_reset failed: fd %d closed\n", keep_fd);
+        return 1;
+    }
+    if (fcntl(cloexec_fd, F_GETFD) >= 0 || errno != EBADF) {
+        printf("exec_reset failed: close-on-exec fd %d still open\n", cloexec_fd);
+        return 1;
+    }
+
+    // nice 值保持不变
+    errno = 0;
+    int nice = getpriority(PRIO_PROCESS, 0);
+    if (nice != NICE || errno != 0) {
+        printf("exec_reset failed: nice is %d, expected %d\n", nice, NICE);
+        return 1;
+    }
+
+    // SIGINT 的默认处理方式是终止进程
+    pid_t pid = fork();
+    if (pid == 0) {
+        raise(SIGINT);
+        _exit(0);
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status)
+        || WTERMSIG(status) != SIGINT) {
+        printf("exec_reset failed: SIGINT did not terminate the child\n");
+        return 1;
+    }
+
+    printf("exec_reset passed!\n");
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc == 6 && strcmp(argv[1], "child") == 0) {
+        return check_after_exec(argv);
+    }
+
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = int_handler;
+    if (sigaction(SIGINT, &sa, NULL) != 0) {
+        printf("exec_reset failed: sigaction SIGINT\n");
+        return 1;
+    }
+    sa.sa_handler = SIG_IGN;
+    if (sigaction(SIGUSR1, &sa, NULL) != 0) {
+        printf("exec_reset failed: sigaction SIGUSR1\n");
+        return 1;
+    }
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR2);
+    if (sigprocmask(SIG_BLOCK, &set, NULL) != 0 || raise(SIGUSR2) != 0) {
+        printf("exec_reset failed: cannot leave SIGUSR2 pending\n");
+        return 1;
+    }
+
+    static char altstack[8192];
+    stack_t ss = {.ss_sp = altstack, .ss_size = sizeof(altstack), .ss_flags = 0};
+    if (sigaltstack(&ss, NULL) != 0) {
+        printf("exec_reset failed: sigaltstack\n");
+        return 1;
+    }
+
+    struct sigevent sev;
+    memset(&sev, 0, sizeof(sev));
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGUSR1;
+    timer_t timer;
+    struct itimerspec its = {{0, 0}, {60, 0}};
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) != 0
+        || timer_settime(timer, 0, &its, NULL) != 0) {
+        printf("exec_reset failed: timer_create\n");
+        return 1;
+    }
+
+    int keep_fd = open("/proc/self/status", O_RDONLY);
+    int cloexec_fd = open("/proc/self/status", O_RDONLY | O_CLOEXEC);
+    if (keep_fd < 0 || cloexec_fd < 0) {
+        printf("exec_reset failed: open\n");
+        return 1;
+    }
+
+    if (setpriority(PRIO_PROCESS, 0, NICE) != 0) {
+        printf("exec_reset failed: setpriority\n");
+        return 1;
+    }
+
+    char pid_arg[16], keep_arg[16], cloexec_arg[16], timer_arg[32];
+    snprintf(pid_arg, sizeof(pid_arg), "%d", getpid());
+    snprintf(keep_arg, sizeof(keep_arg), "%d", keep_fd);
+    snprintf(cloexec_arg, sizeof(cloexec_arg), "%d", cloexec_fd);
+    snprintf(timer_arg, sizeof(timer_arg), "%ld", (long)timer);
+    char *child_argv[] = {argv[0], "child", pid_arg, keep_arg, cloexec_arg, timer_arg, NULL};
+    execv(argv[0], child_argv);
+    printf("exec_reset failed: execv: %s\n", strerror(errno));
+    return 1;
+}
@@ -0,0 +1,55 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#ifndef AT_EMPTY_PATH
+#define AT_EMPTY_PATH 0x1000
+#endif
+
+static const char *copy_path = "execveat_copy";
+
+// 将自身复制一份，避免删除原有的测例文件
+static int copy_self(const char *self)
+{
+    char buf[4096];
+    int src = open(self, O_RDONLY);
+    int dst = open(copy_path, O_WRONLY | O_CREAT | O_TRUNC, 0755);
+    if (src < 0 || dst < 0)
+        return -1;
+    ssize_t n;
+    while ((n = read(src, buf, sizeof(buf))) > 0) {
+        if (write(dst, buf, n) != n)
+            return -1;
+    }
+    close(src);
+    close(dst);
+    return n < 0 ? -1 : 0;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc > 1 && strcmp(argv[1], "child") == 0) {
+        printf("execveat passed!\n");
+        return 0;
+    }
+
+    if (copy_self(argv[0]) < 0) {
+        printf("execveat failed: cannot copy %s\n", argv[0]);
+        return 1;
+    }
+    int fd = open(copy_path, O_RDONLY);
+    if (fd < 0) {
+        printf("execveat failed: cannot open %s\n", copy_path);
+        return 1;
+    }
+    // 路径已被删除，只能通过文件描述符执行
+    unlink(copy_path);
+
+    char *child_argv[] = {argv[0], "child", NULL};
+    char *child_envp[] = {NULL};
+    syscall(SYS_execveat, fd, "", child_argv, child_envp, AT_EMPTY_PATH);
+    printf("execveat failed: execveat returned\n");
+    return 1;
+}
@@ -0,0 +1,127 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/sysinfo.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define CHILDREN 100
+#define BATCH 10
+#define MAP_SIZE (64 * 1024)
+
+static unsigned long long free_ram(void)
+{
+    struct sysinfo info;
+    if (sysinfo(&info) != 0) {
+        return 0;
+    }
+    return (unsigned long long)info.freeram * info.mem_unit;
+}
+
+static void sleep_ms(long ms)
+{
+    struct timespec ts = {ms / 1000, ms % 1000 * 1000000};
+    nanosleep(&ts, NULL);
+}
+
+// 子进程：打开管道、映射并访问匿名内存，然后以不同的方式退出，不主动释放任何资源
+static void child(int i)
+{
+    int fds[2];
+    pipe(fds);
+    write(fds[1], "x", 1);
+    char *p = mmap(NULL, MAP_SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p != MAP_FAILED) {
+        memset(p, i, MAP_SIZE);
+    }
+    switch (i % 3) {
+    case 0:
+        _exit(0);
+    case 1:
+        syscall(SYS_exit, 0);
+        break;
+    default:
+        raise(SIGTERM);
+        break;
+    }
+    _exit(1);
+}
+
+// 分批创建子进程并回收，返回 0 表示成功
+static int run_round(void)
+{
+    // 所有子进程都继承这个管道的写端，它们退出后父进程应读到文件结束
+    int shared[2];
+    if (pipe(shared) != 0) {
+        printf("exit_teardown failed: pipe errno=%d\n", errno);
+        return 1;
+    }
+    for (int i = 0; i < CHILDREN; i += BATCH) {
+        for (int j = i; j < i + BATCH; j++) {
+            pid_t pid = fork();
+            if (pid < 0) {
+                printf("exit_teardown failed: fork errno=%d\n", errno);
+                return 1;
+            }
+            if (pid == 0) {
+                close(shared[0]);
+                child(j);
+            }
+        }
+        for (int j = i; j < i + BATCH; j++) {
+            int status;
+            pid_t pid = wait(&status);
+            if (pid < 0) {
+                printf("exit_teardown failed: wait errno=%d\n", errno);
+                return 1;
+            }
+            int ok = (WIFEXITED(status) && WEXITSTATUS(status) == 0) ||
+                     (WIFSIGNALED(status) && WTERMSIG(status) == SIGTERM);
+            if (!ok) {
+                printf("exit_teardown failed: child %d status=%#x\n", pid, status);
+                return 1;
+            }
+        }
+    }
+    close(shared[1]);
+    char c;
+    ssize_t n = read(shared[0], &c, 1);
+    close(shared[0]);
+    if (n != 0) {
+        printf("exit_teardown failed: shared pipe read returned %zd\n", n);
+        return 1;
+    }
+    return 0;
+}
+
+int main()
+{
+    // 第一轮让内核堆等按需增长的部分达到稳定
+    if (run_round() != 0) {
+        return 1;
+    }
+    unsigned long long before = free_ram();
+    if (before == 0) {
+        printf("exit_teardown failed: sysinfo errno=%d\n", errno);
+        return 1;
+    }
+    if (run_round() != 0) {
+        return 1;
+    }
+    // 已退出任务的内核栈和页表在回收后才释放，给内核一点时间
+    unsigned long long after = free_ram();
+    for (int i = 0; i < 100 && after < before; i++) {
+        sleep_ms(10);
+        after = free_ram();
+    }
+    if (after < before) {
+        printf("exit_teardown failed: free memory %llu -> %llu\n", before, after);
+        return 1;
+    }
+    printf("exit_teardown passed!\n");
+    return 0;
+}
@@ -0,0 +1,103 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#define PATH "fadvise_file"
+#define SIZE (256 * 1024)
+
+static char buf[SIZE];
+
+// 从头读完整个文件，检查每个字节都是写入时的值
+static int check_contents(int fd)
+{
+    if (lseek(fd, 0, SEEK_SET) != 0)
+        return -1;
+    size_t done = 0;
+    while (done < SIZE) {
+        ssize_t n = read(fd, buf + done, 4096);
+        if (n <= 0)
+            return -1;
+        done += n;
+    }
+    for (size_t i = 0; i < SIZE; i++) {
+        if (buf[i] != (char)(i * 13 + i / 4096))
+            return -1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    for (size_t i = 0; i < SIZE; i++)
+        buf[i] = (char)(i * 13 + i / 4096);
+    int fd = open(PATH, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    if (fd < 0 || write(fd, buf, SIZE) != SIZE) {
+        printf("fadvise failed: create errno %d\n", errno);
+        return 1;
+    }
+
+    // 每种建议都不改变读到的数据
+    static const struct {
+        int advice;
+        const char *name;
+    } advices[] = {
+        {POSIX_FADV_SEQUENTIAL, "SEQUENTIAL"}, {POSIX_FADV_RANDOM, "RANDOM"},
+        {POSIX_FADV_WILLNEED, "WILLNEED"},     {POSIX_FADV_DONTNEED, "DONTNEED"},
+        {POSIX_FADV_NOREUSE, "NOREUSE"},       {POSIX_FADV_NORMAL, "NORMAL"},
+    };
+    for (size_t i = 0; i < sizeof(advices) / sizeof(advices[0]); i++) {
+        int ret = posix_fadvise(fd, 0, 0, advices[i].advice);
+        if (ret != 0) {
+            printf("fadvise failed: %s returned %d\n", advices[i].name, ret);
+            return 1;
+        }
+        if (check_contents(fd) != 0) {
+            printf("fadvise failed: contents after %s\n", advices[i].name);
+            return 1;
+        }
+    }
+
+    // 部分范围的 DONTNEED 之后写入，再读到的是新数据
+    if (posix_fadvise(fd, 4096, 8192, POSIX_FADV_DONTNEED) != 0 || lseek(fd, 5000, SEEK_SET) < 0 ||
+        write(fd, "new", 3) != 3 || lseek(fd, 5000, SEEK_SET) < 0 || read(fd, buf, 3) != 3 ||
+        memcmp(buf, "new", 3) != 0) {
+        printf("fadvise failed: write after DONTNEED\n");
+        return 1;
+    }
+
+    // 错误的参数
+    int ret = posix_fadvise(fd, 0, -1, POSIX_FADV_NORMAL);
+    if (ret != EINVAL) {
+        printf("fadvise failed: negative length returned %d\n", ret);
+        return 1;
+    }
+    ret = posix_fadvise(fd, 0, 0, 100);
+    if (ret != EINVAL) {
+        printf("fadvise failed: unknown advice returned %d\n", ret);
+        return 1;
+    }
+    close(fd);
+    ret = posix_fadvise(fd, 0, 0, POSIX_FADV_NORMAL);
+    if (ret != EBADF) {
+        printf("fadvise failed: closed fd returned %d\n", ret);
+        return 1;
+    }
+    int fds[2];
+    if (pipe(fds) != 0) {
+        printf("fadvise failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    ret = posix_fadvise(fds[0], 0, 0, POSIX_FADV_NORMAL);
+    if (ret != ESPIPE) {
+        printf("fadvise failed: pipe returned %d\n", ret);
+        return 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    unlink(PATH);
+    printf("fadvise passed!\n");
+    return 0;
+}
@@ -0,0 +1,79 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#define FILE_NAME "fcntl_flags_test_file"
+
+int main(void)
+{
+    int fd = open(FILE_NAME, O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0) {
+        printf("fcntl_flags failed: open errno %d\n", errno);
+        return 1;
+    }
+    int flags = fcntl(fd, F_GETFL);
+    if ((flags & O_ACCMODE) != O_WRONLY || (flags & (O_APPEND | O_CREAT | O_TRUNC))) {
+        printf("fcntl_flags failed: F_GETFL after open = %#x\n", flags);
+        return 1;
+    }
+
+    // 复制出的描述符共享同一个打开文件，能看到另一个描述符上设置的 O_APPEND
+    int dup_fd = dup(fd);
+    if (fcntl(fd, F_SETFL, flags | O_APPEND) != 0) {
+        printf("fcntl_flags failed: F_SETFL errno %d\n", errno);
+        return 1;
+    }
+    flags = fcntl(dup_fd, F_GETFL);
+    if (!(flags & O_APPEND) || (flags & O_ACCMODE) != O_WRONLY) {
+        printf("fcntl_flags failed: F_GETFL on dup = %#x\n", flags);
+        return 1;
+    }
+    if (write(fd, "ab", 2) != 2 || lseek(dup_fd, 0, SEEK_SET) != 0 || write(dup_fd, "cd", 2) != 2) {
+        printf("fcntl_flags failed: write errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    close(dup_fd);
+
+    char buf[8] = {0};
+    fd = open(FILE_NAME, O_RDONLY);
+    if (read(fd, buf, sizeof(buf)) != 4 || strcmp(buf, "abcd") != 0) {
+        printf("fcntl_flags failed: file content %s\n", buf);
+        return 1;
+    }
+    close(fd);
+    unlink(FILE_NAME);
+
+    // 非阻塞管道在没有数据时返回 EAGAIN
+    int fds[2];
+    if (pipe2(fds, O_NONBLOCK) != 0) {
+        printf("fcntl_flags failed: pipe2 errno %d\n", errno);
+        return 1;
+    }
+    if (!(fcntl(fds[0], F_GETFL) & O_NONBLOCK) || (fcntl(fds[1], F_GETFL) & O_ACCMODE) != O_WRONLY) {
+        printf("fcntl_flags failed: F_GETFL on pipe\n");
+        return 1;
+    }
+    if (read(fds[0], buf, sizeof(buf)) != -1 || errno != EAGAIN) {
+        printf("fcntl_flags failed: read on empty pipe errno %d\n", errno);
+        return 1;
+    }
+    // 清除 O_NONBLOCK 后有数据时正常读取
+    fcntl(fds[0], F_SETFL, 0);
+    if (fcntl(fds[0], F_GETFL) & O_NONBLOCK) {
+        printf("fcntl_flags failed: O_NONBLOCK not cleared\n");
+        return 1;
+    }
+    if (write(fds[1], "x", 1) != 1 || read(fds[0], buf, 1) != 1 || buf[0] != 'x') {
+        printf("fcntl_flags failed: pipe read/write errno %d\n", errno);
+        return 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    printf("fcntl_flags passed!\n");
+    return 0;
+}
@@ -0,0 +1,63 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#ifndef SYS_close_range
+#define SYS_close_range 436
+#endif
+
+#define FILE_NAME "fd_alloc_test_file"
+#define NFILES 100
+
+int main(void)
+{
+    // 关闭标准输入后，新打开的文件应使用最小的空闲描述符 0
+    close(0);
+    int fd = open(FILE_NAME, O_CREAT | O_RDWR, 0644);
+    if (fd != 0) {
+        printf("fd_alloc failed: open after closing stdin returned %d\n", fd);
+        return 1;
+    }
+
+    int fds[NFILES];
+    for (int i = 0; i < NFILES; i++) {
+        fds[i] = open(FILE_NAME, O_RDONLY);
+        if (fds[i] != 3 + i) {
+            printf("fd_alloc failed: open #%d returned %d, errno %d\n", i, fds[i], errno);
+            return 1;
+        }
+    }
+    // 关闭偶数位置的描述符后，重新打开的文件依次填补这些空位
+    for (int i = 0; i < NFILES; i += 2)
+        close(fds[i]);
+    for (int i = 0; i < NFILES; i += 2) {
+        int new_fd = open(FILE_NAME, O_RDONLY);
+        if (new_fd != fds[i]) {
+            printf("fd_alloc failed: reopen returned %d, expected %d\n", new_fd, fds[i]);
+            return 1;
+        }
+    }
+
+    // close_range 释放一大段描述符后，分配仍从最小的空闲描述符开始
+    if (syscall(SYS_close_range, fds[0], ~0U, 0) != 0) {
+        printf("fd_alloc failed: close_range errno %d\n", errno);
+        return 1;
+    }
+    if ((fd = dup(0)) != fds[0]) {
+        printf("fd_alloc failed: dup after close_range returned %d\n", fd);
+        return 1;
+    }
+    // F_DUPFD 返回不小于参数的最小空闲描述符
+    if ((fd = fcntl(0, F_DUPFD, 50)) != 50 || (fd = fcntl(0, F_DUPFD, 50)) != 51) {
+        printf("fd_alloc failed: F_DUPFD returned %d\n", fd);
+        return 1;
+    }
+    syscall(SYS_close_range, 3, ~0U, 0);
+    close(0);
+    unlink(FILE_NAME);
+
+    printf("fd_alloc passed!\n");
+    return 0;
+}
@@ -0,0 +1,134 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PATH "fd_caps_file"
+#define SIZE 8192
+
+// 检查返回值为 -1 且 errno 为 expected
+static int fails_with(long ret, int expected)
+{
+    return ret == -1 && errno == expected;
+}
+
+int main()
+{
+    static char data[SIZE], buf[SIZE];
+    for (int i = 0; i < SIZE; i++)
+        data[i] = 'a' + i % 26;
+    int fd = open(PATH, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    if (fd < 0 || write(fd, data, SIZE) != SIZE) {
+        printf("fd_caps failed: cannot create the file\n");
+        return 1;
+    }
+    close(fd);
+
+    // 只读打开的文件可以读，不能写、截断或可写地共享映射
+    int rd = open(PATH, O_RDONLY);
+    if (rd < 0 || read(rd, buf, SIZE) != SIZE || memcmp(buf, data, SIZE) != 0) {
+        printf("fd_caps failed: cannot read a file opened O_RDONLY\n");
+        return 1;
+    }
+    if (!fails_with(write(rd, "x", 1), EBADF)) {
+        printf("fd_caps failed: write to an O_RDONLY fd did not fail with EBADF\n");
+        return 1;
+    }
+    if (!fails_with(ftruncate(rd, 0), EINVAL)) {
+        printf("fd_caps failed: ftruncate of an O_RDONLY fd did not fail with EINVAL\n");
+        return 1;
+    }
+    void *map = mmap(NULL, SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, rd, 0);
+    if (map != MAP_FAILED || errno != EACCES) {
+        printf("fd_caps failed: writable MAP_SHARED of an O_RDONLY fd did not fail with EACCES\n");
+        return 1;
+    }
+    map = mmap(NULL, SIZE, PROT_READ, MAP_SHARED, rd, 0);
+    if (map == MAP_FAILED || memcmp(map, data, SIZE) != 0) {
+        printf("fd_caps failed: read-only MAP_SHARED of an O_RDONLY fd\n");
+        return 1;
+    }
+    munmap(map, SIZE);
+    // 私有映射的修改不会写回文件，可以可写地映射
+    char *priv = mmap(NULL, SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE, rd, 0);
+    if (priv == MAP_FAILED || memcmp(priv, data, SIZE) != 0) {
+        printf("fd_caps failed: writable MAP_PRIVATE of an O_RDONLY fd\n");
+        return 1;
+    }
+    priv[0] = 'z';
+    munmap(priv, SIZE);
+
+    // 复制的文件描述符和子进程继承的文件描述符有同样的权限
+    int dup_rd = dup(rd);
+    if (dup_rd < 0 || !fails_with(write(dup_rd, "x", 1), EBADF)) {
+        printf("fd_caps failed: write to a dup of an O_RDONLY fd did not fail with EBADF\n");
+        return 1;
+    }
+    pid_t pid = fork();
+    if (pid == 0)
+        _exit(fails_with(write(rd, "x", 1), EBADF) && lseek(rd, 0, SEEK_SET) == 0 && read(rd, buf, 1) == 1 ? 0 : 1);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("fd_caps failed: the child inherited wrong permissions\n");
+        return 1;
+    }
+    close(dup_rd);
+    close(rd);
+
+    // 只写打开的文件可以写，不能读或映射
+    int wr = open(PATH, O_WRONLY);
+    if (wr < 0 || write(wr, "A", 1) != 1) {
+        printf("fd_caps failed: cannot write a file opened O_WRONLY\n");
+        return 1;
+    }
+    if (!fails_with(read(wr, buf, 1), EBADF)) {
+        printf("fd_caps failed: read from an O_WRONLY fd did not fail with EBADF\n");
+        return 1;
+    }
+    map = mmap(NULL, SIZE, PROT_READ, MAP_SHARED, wr, 0);
+    if (map != MAP_FAILED || errno != EACCES) {
+        printf("fd_caps failed: mmap of an O_WRONLY fd did not fail with EACCES\n");
+        return 1;
+    }
+    close(wr);
+
+    // O_PATH 打开的文件既不能读也不能写，但可以获取属性
+    int path_fd = open(PATH, O_PATH);
+    struct stat st;
+    if (path_fd < 0 || !fails_with(read(path_fd, buf, 1), EBADF) || !fails_with(write(path_fd, "x", 1), EBADF) ||
+        fstat(path_fd, &st) != 0 || st.st_size != SIZE) {
+        printf("fd_caps failed: wrong permissions of an O_PATH fd\n");
+        return 1;
+    }
+    close(path_fd);
+
+    // 管道的读端不能写，写端不能读
+    int fds[2];
+    if (pipe(fds) != 0 || !fails_with(write(fds[0], "x", 1), EBADF) || !fails_with(read(fds[1], buf, 1), EBADF)) {
+        printf("fd_caps failed: wrong permissions of pipe ends\n");
+        return 1;
+    }
+    if (write(fds[1], "p", 1) != 1 || read(fds[0], buf, 1) != 1 || buf[0] != 'p') {
+        printf("fd_caps failed: pipe does not work\n");
+        return 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    // 可读写的文件两者都可以
+    fd = open(PATH, O_RDWR);
+    if (fd < 0 || read(fd, buf, 1) != 1 || buf[0] != 'A' || write(fd, "B", 1) != 1 || ftruncate(fd, 100) != 0) {
+        printf("fd_caps failed: cannot read and write a file opened O_RDWR\n");
+        return 1;
+    }
+    close(fd);
+    unlink(PATH);
+
+    printf("fd_caps passed!\n");
+    return 0;
+}
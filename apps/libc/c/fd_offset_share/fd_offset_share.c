@@ -0,0 +1,86 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PATH "fd_offset_share_file"
+
+int main(void)
+{
+    char buf[64];
+    for (int i = 0; i < 64; i++)
+        buf[i] = (char)i;
+    int fd = open(PATH, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    if (fd < 0 || write(fd, buf, 64) != 64 || lseek(fd, 0, SEEK_SET) != 0) {
+        printf("fd_offset_share failed: create errno %d\n", errno);
+        return 1;
+    }
+
+    // 父进程读 10 字节，fork 出的子进程再读 10 字节，父进程的偏移随之变为 20
+    if (read(fd, buf, 10) != 10) {
+        printf("fd_offset_share failed: parent read errno %d\n", errno);
+        return 1;
+    }
+    pid_t pid = fork();
+    if (pid == 0)
+        return read(fd, buf, 10) != 10 || buf[0] != 10;
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("fd_offset_share failed: child read\n");
+        return 1;
+    }
+    off_t off = lseek(fd, 0, SEEK_CUR);
+    if (off != 20) {
+        printf("fd_offset_share failed: offset after fork %ld\n", (long)off);
+        return 1;
+    }
+
+    // 子进程移动偏移后，父进程从新的位置继续读
+    pid = fork();
+    if (pid == 0)
+        return lseek(fd, 40, SEEK_SET) != 40;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0 ||
+        read(fd, buf, 1) != 1 || buf[0] != 40) {
+        printf("fd_offset_share failed: seek in child\n");
+        return 1;
+    }
+
+    // dup 出的描述符共享偏移
+    int dupfd = dup(fd);
+    int dupfd2 = fcntl(fd, F_DUPFD, 10);
+    if (dupfd < 0 || dupfd2 < 10 || read(dupfd, buf, 4) != 4 || buf[0] != 41 ||
+        lseek(fd, 0, SEEK_CUR) != 45 || read(dupfd2, buf, 1) != 1 || buf[0] != 45 ||
+        lseek(dupfd, 0, SEEK_CUR) != 46) {
+        printf("fd_offset_share failed: dup errno %d\n", errno);
+        return 1;
+    }
+    // 关闭一个副本不影响其他副本
+    close(dupfd);
+    if (lseek(dupfd2, 0, SEEK_CUR) != 46) {
+        printf("fd_offset_share failed: offset after close\n");
+        return 1;
+    }
+
+    // 独立打开的描述符有各自的偏移
+    int other = open(PATH, O_RDONLY);
+    if (other < 0 || read(other, buf, 5) != 5 || buf[0] != 0 || lseek(fd, 0, SEEK_CUR) != 46 ||
+        lseek(other, 0, SEEK_CUR) != 5) {
+        printf("fd_offset_share failed: independent open errno %d\n", errno);
+        return 1;
+    }
+
+    // 写入也推进共享的偏移
+    if (lseek(fd, 0, SEEK_END) != 64 || write(dupfd2, "xy", 2) != 2 || lseek(fd, 0, SEEK_CUR) != 66) {
+        printf("fd_offset_share failed: shared write offset\n");
+        return 1;
+    }
+
+    close(other);
+    close(dupfd2);
+    close(fd);
+    unlink(PATH);
+    printf("fd_offset_share passed!\n");
+    return 0;
+}
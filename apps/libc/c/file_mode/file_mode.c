@@ -0,0 +1,85 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define FILE_PATH "file_mode.tmp"
+#define DIR_PATH "file_mode.dir"
+
+// 以非 root 用户运行的子进程中的检查，返回非 0 表示失败
+static int check_non_owner(void)
+{
+    if (setuid(1000) != 0 || getuid() != 1000 || geteuid() != 1000)
+        return 2;
+    // 不再是 root，不能切换回去
+    if (setuid(0) != -1 || errno != EPERM)
+        return 3;
+    // 文件的权限为 0400，非 root 用户不能写
+    int fd = open(FILE_PATH, O_WRONLY);
+    if (fd >= 0 || errno != EACCES)
+        return 4;
+    if (access(FILE_PATH, W_OK) != -1 || errno != EACCES)
+        return 5;
+    return 0;
+}
+
+int main(void)
+{
+    umask(022);
+    unlink(FILE_PATH);
+    rmdir(DIR_PATH);
+
+    // 创建时的权限去掉文件创建掩码后记录下来，fstat 和 stat 都报告它
+    int fd = open(FILE_PATH, O_CREAT | O_EXCL | O_WRONLY, 0400);
+    if (fd < 0) {
+        printf("file_mode failed: create errno %d\n", errno);
+        return 1;
+    }
+    struct stat st;
+    if (fstat(fd, &st) != 0 || !S_ISREG(st.st_mode) || (st.st_mode & 07777) != 0400) {
+        printf("file_mode failed: fstat mode %o\n", st.st_mode);
+        return 1;
+    }
+    close(fd);
+    if (stat(FILE_PATH, &st) != 0 || (st.st_mode & 07777) != 0400) {
+        printf("file_mode failed: stat mode %o\n", st.st_mode);
+        return 1;
+    }
+    if (mkdir(DIR_PATH, 0777) != 0 || stat(DIR_PATH, &st) != 0 || !S_ISDIR(st.st_mode) ||
+        (st.st_mode & 07777) != 0755) {
+        printf("file_mode failed: mkdir mode %o\n", st.st_mode);
+        return 1;
+    }
+
+    // root 用户不受权限位的限制
+    if (getuid() != 0 || access(FILE_PATH, R_OK | W_OK) != 0) {
+        printf("file_mode failed: root access errno %d\n", errno);
+        return 1;
+    }
+    if (access("file_mode.missing", F_OK) != -1 || errno != ENOENT) {
+        printf("file_mode failed: access missing file\n");
+        return 1;
+    }
+
+    pid_t pid = fork();
+    if (pid == 0)
+        _exit(check_non_owner());
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFEXITED(status) ||
+        WEXITSTATUS(status) != 0) {
+        printf("file_mode failed: non-owner check status %#x\n", status);
+        return 1;
+    }
+    // 子进程修改用户 ID 不影响父进程
+    if (getuid() != 0 || geteuid() != 0) {
+        printf("file_mode failed: parent uid changed\n");
+        return 1;
+    }
+
+    unlink(FILE_PATH);
+    rmdir(DIR_PATH);
+    printf("file_mode passed!\n");
+    return 0;
+}
@@ -0,0 +1,107 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <time.h>
+#include <unistd.h>
+
+// a 是否晚于 b
+static int later(struct timespec a, struct timespec b)
+{
+    return a.tv_sec > b.tv_sec || (a.tv_sec == b.tv_sec && a.tv_nsec > b.tv_nsec);
+}
+
+static int same(struct timespec a, struct timespec b)
+{
+    return a.tv_sec == b.tv_sec && a.tv_nsec == b.tv_nsec;
+}
+
+int main(void)
+{
+    struct timespec nap = {0, 200 * 1000 * 1000};
+    struct stat st1, st2;
+    char c;
+
+    int fd = open("ft_file", O_CREAT | O_TRUNC | O_RDWR, 0644);
+    if (fd < 0 || write(fd, "a", 1) != 1 || fstat(fd, &st1) != 0) {
+        printf("file_times failed: create errno %d\n", errno);
+        return 1;
+    }
+
+    // 再次写入后修改时间和状态改变时间都前进
+    nanosleep(&nap, NULL);
+    if (write(fd, "b", 1) != 1 || fstat(fd, &st2) != 0) {
+        printf("file_times failed: write errno %d\n", errno);
+        return 1;
+    }
+    if (!later(st2.st_mtim, st1.st_mtim) || !later(st2.st_ctim, st1.st_ctim)) {
+        printf("file_times failed: mtime %ld.%09ld -> %ld.%09ld\n", (long)st1.st_mtim.tv_sec,
+               st1.st_mtim.tv_nsec, (long)st2.st_mtim.tv_sec, st2.st_mtim.tv_nsec);
+        return 1;
+    }
+
+    // 读取更新访问时间，但不改变修改时间
+    nanosleep(&nap, NULL);
+    if (lseek(fd, 0, SEEK_SET) != 0 || read(fd, &c, 1) != 1 || fstat(fd, &st1) != 0) {
+        printf("file_times failed: read errno %d\n", errno);
+        return 1;
+    }
+    if (!later(st1.st_atim, st2.st_mtim) || !same(st1.st_mtim, st2.st_mtim)) {
+        printf("file_times failed: atime not updated by read\n");
+        return 1;
+    }
+
+    // 截断更新修改时间
+    nanosleep(&nap, NULL);
+    if (ftruncate(fd, 1) != 0 || fstat(fd, &st2) != 0 || !later(st2.st_mtim, st1.st_mtim)) {
+        printf("file_times failed: ftruncate did not update mtime\n");
+        return 1;
+    }
+
+    // 创建链接只更新状态改变时间
+    nanosleep(&nap, NULL);
+    if (linkat(AT_FDCWD, "ft_file", AT_FDCWD, "ft_link", 0) != 0 || fstat(fd, &st1) != 0) {
+        printf("file_times failed: linkat errno %d\n", errno);
+        return 1;
+    }
+    if (!later(st1.st_ctim, st2.st_ctim) || !same(st1.st_mtim, st2.st_mtim)) {
+        printf("file_times failed: linkat did not update only ctime\n");
+        return 1;
+    }
+
+    // 显式设置的时间覆盖自动维护的时间
+    struct timespec times[2] = {{1000000000, 0}, {1234567890, 500}};
+    if (utimensat(AT_FDCWD, "ft_link", times, 0) != 0 || fstat(fd, &st2) != 0) {
+        printf("file_times failed: utimensat errno %d\n", errno);
+        return 1;
+    }
+    if (!same(st2.st_atim, times[0]) || !same(st2.st_mtim, times[1])) {
+        printf("file_times failed: utimensat times not applied\n");
+        return 1;
+    }
+    times[0].tv_nsec = UTIME_OMIT;
+    times[1].tv_nsec = UTIME_NOW;
+    if (futimens(fd, times) != 0 || fstat(fd, &st1) != 0) {
+        printf("file_times failed: futimens errno %d\n", errno);
+        return 1;
+    }
+    if (!same(st1.st_atim, st2.st_atim) || !later(st1.st_mtim, st2.st_mtim)) {
+        printf("file_times failed: UTIME_OMIT/UTIME_NOW not honored\n");
+        return 1;
+    }
+
+    // times 的地址无效时返回 EFAULT
+    volatile unsigned long bad_addr = 8;
+    errno = 0;
+    if (utimensat(AT_FDCWD, "ft_file", (const struct timespec *)bad_addr, 0) != -1 ||
+        errno != EFAULT) {
+        printf("file_times failed: bad times address accepted\n");
+        return 1;
+    }
+
+    close(fd);
+    unlinkat(AT_FDCWD, "ft_link", 0);
+    unlinkat(AT_FDCWD, "ft_file", 0);
+    printf("file_times passed!\n");
+    return 0;
+}
@@ -0,0 +1,67 @@
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#if defined(__riscv)
+
+#ifndef SYS_riscv_flush_icache
+#define SYS_riscv_flush_icache 259
+#endif
+
+// li a0, 1; ret
+#define LI_A0_1 0x00100513u
+#define LI_A0_2 0x00200513u
+#define RET 0x00008067u
+
+static int flush(void *start, void *end, unsigned long flags)
+{
+    return syscall(SYS_riscv_flush_icache, start, end, flags);
+}
+
+int main()
+{
+    uint32_t *code =
+        mmap(NULL, 4096, PROT_READ | PROT_WRITE | PROT_EXEC, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (code == MAP_FAILED) {
+        printf("flush_icache failed: mmap\n");
+        return 1;
+    }
+    int (*func)(void) = (int (*)(void))code;
+
+    code[0] = LI_A0_1;
+    code[1] = RET;
+    if (flush(code, code + 2, 0) != 0 || func() != 1) {
+        printf("flush_icache failed: original code\n");
+        return 1;
+    }
+
+    // 修改指令后刷新指令缓存，再次调用应执行新的代码
+    code[0] = LI_A0_2;
+    if (flush(code, code + 2, 0) != 0 || func() != 2) {
+        printf("flush_icache failed: patched code not observed\n");
+        return 1;
+    }
+
+    errno = 0;
+    if (flush(code, code + 2, 2) != -1 || errno != EINVAL) {
+        printf("flush_icache failed: unknown flags accepted\n");
+        return 1;
+    }
+
+    printf("flush_icache passed!\n");
+    return 0;
+}
+
+#else
+
+// 其他架构没有该系统调用
+int main()
+{
+    printf("flush_icache passed!\n");
+    return 0;
+}
+
+#endif
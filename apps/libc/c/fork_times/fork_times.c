@@ -0,0 +1,38 @@
+#include <stdio.h>
+#include <sys/times.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static void burn_cpu(void)
+{
+    volatile unsigned long x = 0;
+    for (unsigned long i = 0; i < 20000000; i++)
+        x += i;
+}
+
+int main()
+{
+    struct tms t;
+    clock_t last = 0;
+    for (int i = 0; i < 10; i++) {
+        pid_t pid = fork();
+        if (pid == 0) {
+            burn_cpu();
+            return 0;
+        }
+        waitpid(pid, NULL, 0);
+        times(&t);
+        // 回收子进程后 cutime 只增不减
+        if (t.tms_cutime < last) {
+            printf("fork_times failed: cutime %ld < %ld\n", (long)t.tms_cutime, (long)last);
+            return 1;
+        }
+        last = t.tms_cutime;
+    }
+    if (last == 0) {
+        printf("fork_times failed: cutime is zero\n");
+        return 1;
+    }
+    printf("fork_times passed!\n");
+    return 0;
+}
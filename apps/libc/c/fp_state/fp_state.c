@@ -0,0 +1,140 @@
+#include <fenv.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define ROUNDS 20
+// 至少被信号打断的次数
+#define MIN_ALARMS 5
+#define TERMS 200000
+
+static volatile int alarms;
+static volatile double handler_sink;
+
+// 处理函数改写浮点寄存器和舍入模式，返回后被打断的计算不应受影响
+static void alarm_handler(int sig)
+{
+    (void)sig;
+    fesetround(FE_UPWARD);
+    double x = 1.0 / 3.0;
+    for (int i = 0; i < 64; i++) {
+        x = x * 1.000001 + 0.25;
+    }
+    handler_sink = x;
+    alarms++;
+}
+
+// 创建每 2ms 发送一次 SIGALRM 的定时器，定时器不会被子进程继承
+static int start_timer(timer_t *timer)
+{
+    struct sigevent sev;
+    memset(&sev, 0, sizeof(sev));
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGALRM;
+    struct itimerspec its = {{0, 2000000}, {0, 2000000}};
+    if (timer_create(CLOCK_MONOTONIC, &sev, timer) != 0) {
+        return -1;
+    }
+    return timer_settime(*timer, 0, &its, NULL);
+}
+
+// 计算 sum(seed / k^2)，所有中间结果保存在浮点寄存器中
+static double series(double seed)
+{
+    double a = 0.0, b = 0.0, c = 0.0, d = 0.0;
+    for (int k = 1; k <= TERMS; k += 4) {
+        double k0 = k, k1 = k + 1, k2 = k + 2, k3 = k + 3;
+        a += seed / (k0 * k0);
+        b += seed / (k1 * k1);
+        c += seed / (k2 * k2);
+        d += seed / (k3 * k3);
+    }
+    return (a + b) + (c + d);
+}
+
+// 反复计算并与未被打断时的结果逐位比较，直到被信号打断足够多次，返回出错的轮数
+static int check_rounds(double seed, double expected)
+{
+    int errors = 0;
+    for (int i = 0; i < ROUNDS || alarms < MIN_ALARMS; i++) {
+        double value = series(seed);
+        if (memcmp(&value, &expected, sizeof(value)) != 0) {
+            errors++;
+        }
+        // 调用 fork 前后的浮点寄存器也要保持
+        if (fegetround() != FE_TONEAREST) {
+            errors++;
+        }
+    }
+    return errors;
+}
+
+int main(void)
+{
+    double parent_expected = series(1.0);
+    double child_expected = series(3.0);
+    char buf[32];
+    snprintf(buf, sizeof(buf), "%.6f", parent_expected);
+    if (strcmp(buf, "1.644929") != 0) {
+        printf("fp_state failed: series(1) printed as %s\n", buf);
+        return 1;
+    }
+
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = alarm_handler;
+    sa.sa_flags = SA_RESTART;
+    if (sigaction(SIGALRM, &sa, NULL) != 0) {
+        printf("fp_state failed: sigaction\n");
+        return 1;
+    }
+    timer_t timer;
+    if (start_timer(&timer) != 0) {
+        printf("fp_state failed: timer_create\n");
+        return 1;
+    }
+
+    // 在 fork 时寄存器中保存着正在计算的值
+    double live = parent_expected * 2.0;
+    pid_t pid = fork();
+    if (pid < 0) {
+        printf("fp_state failed: fork\n");
+        return 1;
+    }
+    if (pid == 0) {
+        alarms = 0;
+        if (start_timer(&timer) != 0) {
+            _exit(3);
+        }
+        if (live != parent_expected * 2.0) {
+            _exit(2);
+        }
+        int errors = check_rounds(3.0, child_expected);
+        _exit(errors == 0 ? 0 : 1);
+    }
+    if (live != parent_expected * 2.0) {
+        printf("fp_state failed: live value changed across fork\n");
+        return 1;
+    }
+    int errors = check_rounds(1.0, parent_expected);
+    timer_delete(timer);
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status)) {
+        printf("fp_state failed: waitpid\n");
+        return 1;
+    }
+    if (WEXITSTATUS(status) != 0) {
+        printf("fp_state failed: child exited with %d\n", WEXITSTATUS(status));
+        return 1;
+    }
+    if (errors != 0) {
+        printf("fp_state failed: %d wrong results in parent\n", errors);
+        return 1;
+    }
+    printf("fp_state passed!\n");
+    return 0;
+}
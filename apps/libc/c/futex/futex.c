@@ -0,0 +1,266 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <limits.h>
+#include <linux/futex.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define ROUNDS 100000
+#define LOCKERS 4
+#define LOCKS_PER_PROC 25000
+
+// 在进程之间共享的数据
+struct shared {
+    uint32_t word1, word2;
+    uint32_t ready;
+    uint32_t turn;
+    uint32_t lock;
+    uint32_t counter;
+    uint32_t lost;
+};
+
+static struct shared *sh;
+
+static long futex(uint32_t *uaddr, int op, uint32_t val, const struct timespec *timeout, uint32_t *uaddr2,
+                  uint32_t val3)
+{
+    return syscall(SYS_futex, uaddr, op, val, timeout, uaddr2, val3);
+}
+
+static uint32_t load(uint32_t *p)
+{
+    return __atomic_load_n(p, __ATOMIC_SEQ_CST);
+}
+
+static void store(uint32_t *p, uint32_t v)
+{
+    __atomic_store_n(p, v, __ATOMIC_SEQ_CST);
+}
+
+// 返回单调时钟的当前时间，单位为纳秒
+static long long now_ns(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+// 等待所有子进程正常退出
+static int reap(int n)
+{
+    for (int i = 0; i < n; i++) {
+        int status;
+        if (wait(&status) < 0 || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+            return 1;
+    }
+    return 0;
+}
+
+// 子进程登记后等待 `word` 离开 0，使用位集合 `bitset`
+static pid_t spawn_waiter(uint32_t *word, uint32_t bitset)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        __atomic_fetch_add(&sh->ready, 1, __ATOMIC_SEQ_CST);
+        while (load(word) == 0)
+            futex(word, FUTEX_WAIT_BITSET, 0, NULL, NULL, bitset);
+        _exit(0);
+    }
+    return pid;
+}
+
+// 等待 n 个子进程登记，再留出时间让它们进入等待
+static void wait_ready(uint32_t n)
+{
+    while (load(&sh->ready) < n)
+        usleep(1000);
+    usleep(100000);
+    store(&sh->ready, 0);
+}
+
+// 轮流执行：等待 turn 等于 me，然后交给对方，超时说明唤醒被遗漏
+static int ping_pong(uint32_t me)
+{
+    struct timespec timeout = {5, 0};
+    for (int i = 0; i < ROUNDS; i++) {
+        uint32_t turn;
+        while ((turn = load(&sh->turn)) != me) {
+            if (futex(&sh->turn, FUTEX_WAIT, turn, &timeout, NULL, 0) != 0 && errno == ETIMEDOUT) {
+                store(&sh->lost, 1);
+                return 1;
+            }
+            if (load(&sh->lost))
+                return 1;
+        }
+        store(&sh->turn, 1 - me);
+        futex(&sh->turn, FUTEX_WAKE, 1, NULL, NULL, 0);
+    }
+    return 0;
+}
+
+// 三态互斥锁：0 未加锁，1 加锁且无人等待，2 加锁且可能有人等待
+static void mutex_lock(uint32_t *m)
+{
+    uint32_t c = 0;
+    if (__atomic_compare_exchange_n(m, &c, 1, 0, __ATOMIC_ACQUIRE, __ATOMIC_RELAXED))
+        return;
+    if (c != 2)
+        c = __atomic_exchange_n(m, 2, __ATOMIC_ACQUIRE);
+    while (c != 0) {
+        futex(m, FUTEX_WAIT, 2, NULL, NULL, 0);
+        c = __atomic_exchange_n(m, 2, __ATOMIC_ACQUIRE);
+    }
+}
+
+static void mutex_unlock(uint32_t *m)
+{
+    if (__atomic_fetch_sub(m, 1, __ATOMIC_RELEASE) != 1) {
+        store(m, 0);
+        futex(m, FUTEX_WAKE, 1, NULL, NULL, 0);
+    }
+}
+
+int main()
+{
+    int fd = memfd_create("futex", 0);
+    if (fd < 0 || ftruncate(fd, 4096) != 0) {
+        printf("futex failed: cannot create the shared memory\n");
+        return 1;
+    }
+    sh = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (sh == MAP_FAILED) {
+        printf("futex failed: cannot map the shared memory\n");
+        return 1;
+    }
+
+    // 值不相等、参数错误和没有等待者
+    if (futex(&sh->word1, FUTEX_WAIT, 1, NULL, NULL, 0) != -1 || errno != EAGAIN) {
+        printf("futex failed: waiting on a different value did not fail with EAGAIN\n");
+        return 1;
+    }
+    if (futex((uint32_t *)((char *)&sh->word1 + 1), FUTEX_WAKE, 1, NULL, NULL, 0) != -1 || errno != EINVAL ||
+        futex(&sh->word1, FUTEX_WAIT_BITSET, 0, NULL, NULL, 0) != -1 || errno != EINVAL) {
+        printf("futex failed: unaligned address or empty bitset did not fail with EINVAL\n");
+        return 1;
+    }
+    if (futex(&sh->word1, FUTEX_WAKE, INT_MAX, NULL, NULL, 0) != 0) {
+        printf("futex failed: woke waiters that do not exist\n");
+        return 1;
+    }
+
+    // 相对超时和单调时钟、墙上时钟的绝对超时
+    struct timespec rel = {0, 20000000};
+    long long start = now_ns();
+    if (futex(&sh->word1, FUTEX_WAIT_PRIVATE, 0, &rel, NULL, 0) != -1 || errno != ETIMEDOUT ||
+        now_ns() - start < 20000000) {
+        printf("futex failed: relative timeout\n");
+        return 1;
+    }
+    struct timespec abs;
+    clock_gettime(CLOCK_MONOTONIC, &abs);
+    abs.tv_nsec += 20000000;
+    if (abs.tv_nsec >= 1000000000) {
+        abs.tv_sec++;
+        abs.tv_nsec -= 1000000000;
+    }
+    if (futex(&sh->word1, FUTEX_WAIT_BITSET, 0, &abs, NULL, FUTEX_BITSET_MATCH_ANY) != -1 || errno != ETIMEDOUT ||
+        now_ns() < abs.tv_sec * 1000000000LL + abs.tv_nsec) {
+        printf("futex failed: absolute monotonic timeout\n");
+        return 1;
+    }
+    struct timespec past = {1, 0};
+    if (futex(&sh->word1, FUTEX_WAIT_BITSET | FUTEX_CLOCK_REALTIME, 0, &past, NULL, FUTEX_BITSET_MATCH_ANY) != -1 ||
+        errno != ETIMEDOUT) {
+        printf("futex failed: absolute realtime timeout in the past\n");
+        return 1;
+    }
+    if (futex(&sh->word1, FUTEX_WAIT | FUTEX_CLOCK_REALTIME, 0, &rel, NULL, 0) != -1 || errno != ENOSYS) {
+        printf("futex failed: FUTEX_CLOCK_REALTIME with FUTEX_WAIT did not fail with ENOSYS\n");
+        return 1;
+    }
+
+    // 只唤醒位集合相交的等待者
+    spawn_waiter(&sh->word1, 1);
+    wait_ready(1);
+    if (futex(&sh->word1, FUTEX_WAKE_BITSET, INT_MAX, NULL, NULL, 2) != 0) {
+        printf("futex failed: FUTEX_WAKE_BITSET woke a waiter with another bitset\n");
+        return 1;
+    }
+    store(&sh->word1, 1);
+    if (futex(&sh->word1, FUTEX_WAKE_BITSET, INT_MAX, NULL, NULL, 3) != 1 || reap(1)) {
+        printf("futex failed: FUTEX_WAKE_BITSET did not wake the waiter\n");
+        return 1;
+    }
+
+    // FUTEX_WAKE_OP：word2 加 1，原值等于 5 时同时唤醒 word2 上的等待者
+    store(&sh->word1, 0);
+    store(&sh->word2, 0);
+    spawn_waiter(&sh->word1, FUTEX_BITSET_MATCH_ANY);
+    spawn_waiter(&sh->word2, FUTEX_BITSET_MATCH_ANY);
+    wait_ready(2);
+    store(&sh->word1, 1);
+    store(&sh->word2, 5);
+    long woken = futex(&sh->word1, FUTEX_WAKE_OP, 1, (struct timespec *)1, &sh->word2,
+                       FUTEX_OP(FUTEX_OP_ADD, 1, FUTEX_OP_CMP_EQ, 5));
+    if (woken != 2 || load(&sh->word2) != 6 || reap(2)) {
+        printf("futex failed: FUTEX_WAKE_OP woke %ld waiters, word2 is %u\n", woken, load(&sh->word2));
+        return 1;
+    }
+    if (futex(&sh->word1, FUTEX_WAKE_OP, 1, (struct timespec *)1, &sh->word2,
+              FUTEX_OP((FUTEX_OP_OR | FUTEX_OP_OPARG_SHIFT), 4, FUTEX_OP_CMP_GT, 100)) != 0 ||
+        load(&sh->word2) != 22) {
+        printf("futex failed: FUTEX_WAKE_OP with a shifted operand\n");
+        return 1;
+    }
+
+    // FUTEX_CMP_REQUEUE：唤醒一个等待者，另一个转移到 word2 上
+    store(&sh->word1, 0);
+    store(&sh->word2, 0);
+    spawn_waiter(&sh->word1, FUTEX_BITSET_MATCH_ANY);
+    spawn_waiter(&sh->word1, FUTEX_BITSET_MATCH_ANY);
+    wait_ready(2);
+    store(&sh->word1, 1);
+    if (futex(&sh->word1, FUTEX_CMP_REQUEUE, 1, (struct timespec *)INT_MAX, &sh->word2, 0) != -1 ||
+        errno != EAGAIN) {
+        printf("futex failed: FUTEX_CMP_REQUEUE with a different value did not fail with EAGAIN\n");
+        return 1;
+    }
+    woken = futex(&sh->word1, FUTEX_CMP_REQUEUE, 1, (struct timespec *)INT_MAX, &sh->word2, 1);
+    if (woken != 2 || futex(&sh->word2, FUTEX_WAKE, INT_MAX, NULL, NULL, 0) != 1 || reap(2)) {
+        printf("futex failed: FUTEX_CMP_REQUEUE returned %ld\n", woken);
+        return 1;
+    }
+
+    // 两个进程轮流执行，每一轮都要等待和唤醒，不能遗漏唤醒
+    pid_t pid = fork();
+    if (pid == 0)
+        _exit(ping_pong(1));
+    if (ping_pong(0) || reap(1)) {
+        printf("futex failed: a wakeup was lost in the ping-pong\n");
+        return 1;
+    }
+
+    // 多个进程争用同一个互斥锁
+    for (int i = 0; i < LOCKERS; i++) {
+        if (fork() == 0) {
+            for (int j = 0; j < LOCKS_PER_PROC; j++) {
+                mutex_lock(&sh->lock);
+                sh->counter++;
+                mutex_unlock(&sh->lock);
+            }
+            _exit(0);
+        }
+    }
+    if (reap(LOCKERS) || load(&sh->counter) != LOCKERS * LOCKS_PER_PROC) {
+        printf("futex failed: the counter protected by the mutex is %u\n", load(&sh->counter));
+        return 1;
+    }
+
+    printf("futex passed!\n");
+    return 0;
+}
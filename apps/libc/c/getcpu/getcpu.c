@@ -0,0 +1,35 @@
+#define _GNU_SOURCE
+#include <sched.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main()
+{
+    unsigned cpu = 0xffffffff, node = 0xffffffff;
+    if (syscall(SYS_getcpu, &cpu, &node, NULL) != 0) {
+        printf("getcpu failed: syscall returned an error\n");
+        return 1;
+    }
+    if (cpu >= 256 || node != 0) {
+        printf("getcpu failed: cpu=%u node=%u\n", cpu, node);
+        return 1;
+    }
+
+    // 任一参数都可以为空
+    if (syscall(SYS_getcpu, NULL, &node, NULL) != 0 || syscall(SYS_getcpu, &cpu, NULL, NULL) != 0 ||
+        syscall(SYS_getcpu, NULL, NULL, NULL) != 0) {
+        printf("getcpu failed: NULL arguments rejected\n");
+        return 1;
+    }
+
+    // 让出 CPU 之后重新查询，结果仍然有效
+    sched_yield();
+    if (sched_getcpu() < 0) {
+        printf("getcpu failed: sched_getcpu returned an error\n");
+        return 1;
+    }
+
+    printf("getcpu passed!\n");
+    return 0;
+}
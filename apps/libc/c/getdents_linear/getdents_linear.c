@@ -0,0 +1,153 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define DIR_NAME "getdents_linear_dir"
+#define NFILES 512
+
+struct linux_dirent64 {
+    unsigned long long d_ino;
+    long long d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+static char seen[NFILES];
+
+// 记录读到的一个目录项，返回文件的编号，不是测试创建的文件时返回 -1
+static int record(const struct linux_dirent64 *ent)
+{
+    int idx;
+    if (sscanf(ent->d_name, "f%d", &idx) != 1 || idx < 0 || idx >= NFILES)
+        return -1;
+    seen[idx]++;
+    return idx;
+}
+
+// 用只能容纳一个目录项的缓冲区读完目录，返回读到的测试文件数，出错时返回 -1
+static int read_one_by_one(int fd)
+{
+    char buf[40] __attribute__((aligned(8)));
+    int files = 0;
+    memset(seen, 0, sizeof(seen));
+    for (;;) {
+        long n = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+        if (n < 0) {
+            printf("getdents_linear failed: getdents64 returned an error\n");
+            return -1;
+        }
+        if (n == 0)
+            break;
+        struct linux_dirent64 *ent = (struct linux_dirent64 *)buf;
+        if (ent->d_reclen != n || ent->d_reclen % 8 != 0) {
+            printf("getdents_linear failed: %ld bytes for one entry of length %d\n", n,
+                   ent->d_reclen);
+            return -1;
+        }
+        if (lseek(fd, 0, SEEK_CUR) != ent->d_off) {
+            printf("getdents_linear failed: d_off differs from the directory position\n");
+            return -1;
+        }
+        if (record(ent) >= 0)
+            files++;
+    }
+    for (int i = 0; i < NFILES; i++) {
+        if (seen[i] != 1) {
+            printf("getdents_linear failed: f%d read %d times\n", i, seen[i]);
+            return -1;
+        }
+    }
+    return files;
+}
+
+int main(void)
+{
+    char name[300];
+    if (mkdir(DIR_NAME, 0755) != 0) {
+        printf("getdents_linear failed: mkdir\n");
+        return 1;
+    }
+    for (int i = 0; i < NFILES; i++) {
+        snprintf(name, sizeof(name), DIR_NAME "/f%d", i);
+        int fd = open(name, O_CREAT | O_WRONLY, 0644);
+        if (fd < 0) {
+            printf("getdents_linear failed: create %s\n", name);
+            return 1;
+        }
+        close(fd);
+    }
+
+    int fd = open(DIR_NAME, O_RDONLY | O_DIRECTORY);
+    if (fd < 0) {
+        printf("getdents_linear failed: open directory\n");
+        return 1;
+    }
+
+    // 每次只读一个目录项也能不重复、不遗漏地读完整个目录
+    if (read_one_by_one(fd) != NFILES)
+        return 1;
+
+    // 读到末尾后回到开头，可以再次读完，且能看到新创建的文件
+    if (lseek(fd, 0, SEEK_SET) != 0) {
+        printf("getdents_linear failed: rewind\n");
+        return 1;
+    }
+    if (read_one_by_one(fd) != NFILES) {
+        printf("getdents_linear failed: reading after rewind\n");
+        return 1;
+    }
+    if (lseek(fd, 0, SEEK_SET) != 0 || close(open(DIR_NAME "/extra", O_CREAT | O_WRONLY, 0644)) != 0) {
+        printf("getdents_linear failed: create extra\n");
+        return 1;
+    }
+    char buf[4096] __attribute__((aligned(8)));
+    int found_extra = 0;
+    long n;
+    while ((n = syscall(SYS_getdents64, fd, buf, sizeof(buf))) > 0) {
+        for (long off = 0; off < n;) {
+            struct linux_dirent64 *ent = (struct linux_dirent64 *)(buf + off);
+            found_extra |= strcmp(ent->d_name, "extra") == 0;
+            off += ent->d_reclen;
+        }
+    }
+    if (n != 0 || !found_extra) {
+        printf("getdents_linear failed: new file not listed after rewind\n");
+        return 1;
+    }
+
+    // 缓冲区放不下一个目录项
+    lseek(fd, 0, SEEK_SET);
+    if (syscall(SYS_getdents64, fd, buf, 8) != -1) {
+        printf("getdents_linear failed: tiny buffer accepted\n");
+        return 1;
+    }
+    close(fd);
+
+    // 用 readdir 遍历，同时删除文件
+    DIR *dir = opendir(DIR_NAME);
+    struct dirent *ent;
+    int removed = 0;
+    while (dir && (ent = readdir(dir)) != NULL) {
+        if (strcmp(ent->d_name, ".") == 0 || strcmp(ent->d_name, "..") == 0)
+            continue;
+        snprintf(name, sizeof(name), DIR_NAME "/%s", ent->d_name);
+        if (unlink(name) == 0)
+            removed++;
+    }
+    if (dir)
+        closedir(dir);
+    if (removed != NFILES + 1 || rmdir(DIR_NAME) != 0) {
+        printf("getdents_linear failed: removed %d files\n", removed);
+        return 1;
+    }
+
+    printf("getdents_linear passed!\n");
+    return 0;
+}
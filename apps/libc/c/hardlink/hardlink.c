@@ -0,0 +1,95 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+// 打开 path 并检查它的硬链接数和内容
+static int check(const char *path, nlink_t nlink, ino_t *ino)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        printf("hardlink failed: open %s errno %d\n", path, errno);
+        return -1;
+    }
+    struct stat st;
+    char c = 0;
+    if (fstat(fd, &st) != 0 || read(fd, &c, 1) != 1) {
+        printf("hardlink failed: fstat/read %s errno %d\n", path, errno);
+        return -1;
+    }
+    close(fd);
+    if (st.st_nlink != nlink || c != 'x' || (*ino && st.st_ino != *ino)) {
+        printf("hardlink failed: %s has nlink %ld ino %ld content %c\n", path, (long)st.st_nlink,
+               (long)st.st_ino, c);
+        return -1;
+    }
+    *ino = st.st_ino;
+    return 0;
+}
+
+int main(void)
+{
+    ino_t ino = 0;
+    int fd = open("hl_a", O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0 || write(fd, "x", 1) != 1) {
+        printf("hardlink failed: create errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    if (check("hl_a", 1, &ino) != 0)
+        return 1;
+
+    if (linkat(AT_FDCWD, "hl_a", AT_FDCWD, "hl_b", 0) != 0 ||
+        linkat(AT_FDCWD, "hl_a", AT_FDCWD, "hl_c", 0) != 0) {
+        printf("hardlink failed: linkat errno %d\n", errno);
+        return 1;
+    }
+    if (linkat(AT_FDCWD, "hl_a", AT_FDCWD, "hl_b", 0) == 0) {
+        printf("hardlink failed: linkat over an existing name succeeded\n");
+        return 1;
+    }
+    if (check("hl_a", 3, &ino) || check("hl_b", 3, &ino) || check("hl_c", 3, &ino))
+        return 1;
+
+    // 删除原来的名字后，文件仍可以通过其他名字访问
+    if (unlinkat(AT_FDCWD, "hl_a", 0) != 0) {
+        printf("hardlink failed: unlinkat errno %d\n", errno);
+        return 1;
+    }
+    if (open("hl_a", O_RDONLY) >= 0 || check("hl_b", 2, &ino) || check("hl_c", 2, &ino))
+        return 1;
+
+    // 重命名后链接数不变
+    if (renameat(AT_FDCWD, "hl_b", AT_FDCWD, "hl_d") != 0) {
+        printf("hardlink failed: renameat errno %d\n", errno);
+        return 1;
+    }
+    if (open("hl_b", O_RDONLY) >= 0 || check("hl_c", 2, &ino) || check("hl_d", 2, &ino))
+        return 1;
+    if (unlinkat(AT_FDCWD, "hl_c", 0) != 0 || check("hl_d", 1, &ino) ||
+        unlinkat(AT_FDCWD, "hl_d", 0) != 0 || open("hl_d", O_RDONLY) >= 0) {
+        printf("hardlink failed: removing the last names\n");
+        return 1;
+    }
+
+    // 目录的链接数是 2 加上子目录数
+    if (mkdirat(AT_FDCWD, "hl_dir", 0755) != 0 || mkdirat(AT_FDCWD, "hl_dir/s1", 0755) != 0 ||
+        mkdirat(AT_FDCWD, "hl_dir/s2", 0755) != 0) {
+        printf("hardlink failed: mkdirat errno %d\n", errno);
+        return 1;
+    }
+    struct stat st;
+    fd = open("hl_dir", O_RDONLY | O_DIRECTORY);
+    if (fd < 0 || fstat(fd, &st) != 0 || st.st_nlink != 4) {
+        printf("hardlink failed: directory nlink %ld, errno %d\n", (long)st.st_nlink, errno);
+        return 1;
+    }
+    close(fd);
+    unlinkat(AT_FDCWD, "hl_dir/s1", AT_REMOVEDIR);
+    unlinkat(AT_FDCWD, "hl_dir/s2", AT_REMOVEDIR);
+    unlinkat(AT_FDCWD, "hl_dir", AT_REMOVEDIR);
+
+    printf("hardlink passed!\n");
+    return 0;
+}
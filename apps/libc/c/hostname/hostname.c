@@ -0,0 +1,51 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/utsname.h>
+#include <unistd.h>
+
+int main()
+{
+    const char *name = "starry-test";
+    if (sethostname(name, strlen(name)) != 0) {
+        printf("hostname failed: sethostname errno=%d\n", errno);
+        return 1;
+    }
+
+    // uname 的 nodename 与 gethostname 都应反映新的主机名
+    struct utsname uts;
+    char buf[65];
+    uname(&uts);
+    if (strcmp(uts.nodename, name) != 0) {
+        printf("hostname failed: nodename is %s\n", uts.nodename);
+        return 1;
+    }
+    if (gethostname(buf, sizeof(buf)) != 0 || strcmp(buf, name) != 0) {
+        printf("hostname failed: gethostname mismatch\n");
+        return 1;
+    }
+
+    // 超过 64 字节的主机名被拒绝
+    char long_name[66];
+    memset(long_name, 'a', sizeof(long_name));
+    errno = 0;
+    if (sethostname(long_name, sizeof(long_name)) != -1 || errno != EINVAL) {
+        printf("hostname failed: overlong name accepted\n");
+        return 1;
+    }
+
+    // 地址无效时返回 EFAULT，主机名不变
+    errno = 0;
+    if (sethostname((const char *)8, 4) != -1 || errno != EFAULT) {
+        printf("hostname failed: bad address accepted\n");
+        return 1;
+    }
+    if (gethostname(buf, sizeof(buf)) != 0 || strcmp(buf, name) != 0) {
+        printf("hostname failed: hostname changed by a bad address\n");
+        return 1;
+    }
+
+    printf("hostname passed!\n");
+    return 0;
+}
@@ -0,0 +1,248 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/inotify.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define DIR_NAME "inotify_dir"
+#define FILE_A DIR_NAME "/a"
+#define FILE_B DIR_NAME "/b"
+#define OVERFLOW_ROUNDS 10000
+
+static char buf[4096] __attribute__((aligned(__alignof__(struct inotify_event))));
+static int buf_len, buf_pos;
+
+// 取出下一个事件，缓冲区中没有事件时读取，失败时返回 NULL
+static struct inotify_event *next_event(int fd)
+{
+    if (buf_pos >= buf_len) {
+        buf_len = read(fd, buf, sizeof(buf));
+        buf_pos = 0;
+        if (buf_len <= 0)
+            return NULL;
+    }
+    struct inotify_event *ev = (struct inotify_event *)(buf + buf_pos);
+    buf_pos += sizeof(struct inotify_event) + ev->len;
+    return ev;
+}
+
+// 检查下一个事件的监视描述符、事件和名字
+static int expect(int fd, int wd, unsigned mask, const char *name, const char *what)
+{
+    struct inotify_event *ev = next_event(fd);
+    if (!ev) {
+        printf("inotify failed: no event for %s, errno %d\n", what, errno);
+        return -1;
+    }
+    const char *got = ev->len ? ev->name : "";
+    if (ev->wd != wd || ev->mask != mask || strcmp(got, name) != 0) {
+        printf("inotify failed: %s got wd %d mask %#x name \"%s\"\n", what, ev->wd, ev->mask, got);
+        return -1;
+    }
+    return 0;
+}
+
+static void cleanup(void)
+{
+    unlink(FILE_A);
+    unlink(FILE_B);
+    rmdir(DIR_NAME "/sub");
+    rmdir(DIR_NAME);
+}
+
+int main(void)
+{
+    cleanup();
+    if (mkdir(DIR_NAME, 0755) != 0) {
+        printf("inotify failed: mkdir errno %d\n", errno);
+        return 1;
+    }
+    int fd = inotify_init1(IN_NONBLOCK | IN_CLOEXEC);
+    if (fd < 0 || !(fcntl(fd, F_GETFD) & FD_CLOEXEC) || !(fcntl(fd, F_GETFL) & O_NONBLOCK)) {
+        printf("inotify failed: init errno %d\n", errno);
+        return 1;
+    }
+    if (read(fd, buf, sizeof(buf)) != -1 || errno != EAGAIN) {
+        printf("inotify failed: empty read errno %d\n", errno);
+        return 1;
+    }
+    int wd = inotify_add_watch(fd, DIR_NAME, IN_CREATE | IN_DELETE);
+    if (wd < 0) {
+        printf("inotify failed: add_watch errno %d\n", errno);
+        return 1;
+    }
+
+    // 创建和删除文件，两个事件按顺序到达
+    int file = open(FILE_A, O_CREAT | O_WRONLY, 0644);
+    if (file < 0) {
+        printf("inotify failed: create errno %d\n", errno);
+        return 1;
+    }
+    close(file);
+    unlink(FILE_A);
+    if (expect(fd, wd, IN_CREATE, "a", "create") || expect(fd, wd, IN_DELETE, "a", "delete"))
+        return 1;
+    if (read(fd, buf, sizeof(buf)) != -1 || errno != EAGAIN) {
+        printf("inotify failed: extra events\n");
+        return 1;
+    }
+
+    // 再次监视同一个路径返回同一个监视描述符，并替换监视的事件
+    int mask = IN_MODIFY | IN_CLOSE_WRITE | IN_MOVED_FROM | IN_MOVED_TO | IN_CREATE | IN_DELETE;
+    if (inotify_add_watch(fd, DIR_NAME, mask) != wd) {
+        printf("inotify failed: watch the same path again errno %d\n", errno);
+        return 1;
+    }
+    errno = 0;
+    if (inotify_add_watch(fd, DIR_NAME, IN_CREATE | IN_MASK_CREATE) != -1 || errno != EEXIST) {
+        printf("inotify failed: IN_MASK_CREATE errno %d\n", errno);
+        return 1;
+    }
+    if (mkdir(DIR_NAME "/sub", 0755) != 0 || expect(fd, wd, IN_CREATE | IN_ISDIR, "sub", "mkdir"))
+        return 1;
+
+    // 写入后关闭
+    file = open(FILE_A, O_CREAT | O_WRONLY, 0644);
+    if (file < 0 || write(file, "hello", 5) != 5) {
+        printf("inotify failed: write errno %d\n", errno);
+        return 1;
+    }
+    close(file);
+    if (expect(fd, wd, IN_CREATE, "a", "create again") || expect(fd, wd, IN_MODIFY, "a", "modify") ||
+        expect(fd, wd, IN_CLOSE_WRITE, "a", "close write"))
+        return 1;
+
+    // 移动的两个事件带有相同的 cookie
+    if (rename(FILE_A, FILE_B) != 0) {
+        printf("inotify failed: rename errno %d\n", errno);
+        return 1;
+    }
+    struct inotify_event *from = next_event(fd);
+    unsigned cookie = from ? from->cookie : 0;
+    if (!from || from->mask != IN_MOVED_FROM || strcmp(from->name, "a") != 0 || cookie == 0) {
+        printf("inotify failed: moved from\n");
+        return 1;
+    }
+    struct inotify_event *to = next_event(fd);
+    if (!to || to->mask != IN_MOVED_TO || strcmp(to->name, "b") != 0 || to->cookie != cookie) {
+        printf("inotify failed: moved to\n");
+        return 1;
+    }
+
+    // 监视文件本身
+    int file_wd = inotify_add_watch(fd, FILE_B, IN_ATTRIB | IN_DELETE_SELF);
+    if (file_wd < 0 || file_wd == wd) {
+        printf("inotify failed: watch file errno %d\n", errno);
+        return 1;
+    }
+    errno = 0;
+    if (inotify_add_watch(fd, FILE_B, IN_ATTRIB | IN_ONLYDIR) != -1 || errno != ENOTDIR) {
+        printf("inotify failed: IN_ONLYDIR errno %d\n", errno);
+        return 1;
+    }
+    if (utimensat(AT_FDCWD, FILE_B, NULL, 0) != 0 || expect(fd, file_wd, IN_ATTRIB, "", "attrib"))
+        return 1;
+    // 删除被监视的文件后监视被删除
+    unlink(FILE_B);
+    struct inotify_event *ev;
+    int got_delete = 0, got_self = 0, got_ignored = 0;
+    while ((ev = next_event(fd)) != NULL) {
+        if (ev->wd == wd && ev->mask == IN_DELETE && strcmp(ev->name, "b") == 0)
+            got_delete++;
+        else if (ev->wd == file_wd && ev->mask == IN_DELETE_SELF)
+            got_self++;
+        else if (ev->wd == file_wd && ev->mask == IN_IGNORED)
+            got_ignored++;
+        else if (!(ev->wd == file_wd && ev->mask == IN_ATTRIB)) {
+            printf("inotify failed: unexpected event %#x after unlink\n", ev->mask);
+            return 1;
+        }
+    }
+    if (got_delete != 1 || got_self != 1 || got_ignored != 1) {
+        printf("inotify failed: unlink events %d %d %d\n", got_delete, got_self, got_ignored);
+        return 1;
+    }
+    errno = 0;
+    if (inotify_rm_watch(fd, file_wd) != -1 || errno != EINVAL) {
+        printf("inotify failed: removed watch errno %d\n", errno);
+        return 1;
+    }
+
+    // 缓冲区放不下一个事件
+    file = open(FILE_A, O_CREAT | O_WRONLY, 0644);
+    close(file);
+    errno = 0;
+    if (read(fd, buf, sizeof(struct inotify_event)) != -1 || errno != EINVAL) {
+        printf("inotify failed: small buffer errno %d\n", errno);
+        return 1;
+    }
+    buf_len = buf_pos = 0;
+    while (next_event(fd))
+        ;
+
+    // 阻塞的读取在事件到达时返回
+    int blocking = inotify_init1(0);
+    int dir_wd = inotify_add_watch(blocking, DIR_NAME, IN_CREATE);
+    pid_t pid = fork();
+    if (pid == 0) {
+        struct timespec ts = {0, 50 * 1000 * 1000};
+        nanosleep(&ts, NULL);
+        close(open(FILE_B, O_CREAT | O_WRONLY, 0644));
+        return 0;
+    }
+    if (dir_wd < 0 || expect(blocking, dir_wd, IN_CREATE, "b", "blocking read"))
+        return 1;
+    waitpid(pid, NULL, 0);
+
+    // 删除监视后报告 IN_IGNORED
+    if (inotify_rm_watch(blocking, dir_wd) != 0 || expect(blocking, dir_wd, IN_IGNORED, "", "rm_watch"))
+        return 1;
+    // 删除的监视描述符不会马上被重新使用
+    int new_wd = inotify_add_watch(blocking, DIR_NAME, IN_CREATE);
+    if (new_wd < 0 || new_wd == dir_wd) {
+        printf("inotify failed: reused wd %d\n", new_wd);
+        return 1;
+    }
+    close(blocking);
+
+    // 队列溢出时修改者不会阻塞，队列末尾是 IN_Q_OVERFLOW
+    int ov = inotify_init1(IN_NONBLOCK);
+    int ov_wd = inotify_add_watch(ov, DIR_NAME, IN_ATTRIB);
+    if (ov_wd < 0) {
+        printf("inotify failed: watch for overflow errno %d\n", errno);
+        return 1;
+    }
+    for (int i = 0; i < OVERFLOW_ROUNDS; i++) {
+        if (utimensat(AT_FDCWD, FILE_A, NULL, 0) != 0 || utimensat(AT_FDCWD, FILE_B, NULL, 0) != 0) {
+            printf("inotify failed: utimensat errno %d\n", errno);
+            return 1;
+        }
+    }
+    buf_len = buf_pos = 0;
+    int count = 0, overflow = 0;
+    while ((ev = next_event(ov)) != NULL) {
+        if (ev->mask == IN_Q_OVERFLOW && ev->wd == -1)
+            overflow++;
+        else if (ev->mask == IN_ATTRIB && ev->wd == ov_wd && !overflow)
+            count++;
+        else {
+            printf("inotify failed: event %#x after overflow\n", ev->mask);
+            return 1;
+        }
+    }
+    if (overflow != 1 || count >= 2 * OVERFLOW_ROUNDS) {
+        printf("inotify failed: %d events and %d overflows\n", count, overflow);
+        return 1;
+    }
+    close(ov);
+
+    close(fd);
+    cleanup();
+    printf("inotify passed!\n");
+    return 0;
+}
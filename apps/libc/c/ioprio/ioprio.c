@@ -0,0 +1,164 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define IOPRIO_CLASS_SHIFT 13
+#define IOPRIO_PRIO_VALUE(class, data) (((class) << IOPRIO_CLASS_SHIFT) | (data))
+#define IOPRIO_CLASS_NONE 0
+#define IOPRIO_CLASS_RT 1
+#define IOPRIO_CLASS_BE 2
+#define IOPRIO_CLASS_IDLE 3
+#define IOPRIO_WHO_PROCESS 1
+
+#define READ_PATH "ioprio_read"
+#define WRITE_PATH "ioprio_idle"
+#define READ_SIZE (256 * 1024)
+#define CHUNK (64 * 1024)
+
+static char buf[CHUNK];
+
+static int ioprio_get(int which, int who)
+{
+    return syscall(SYS_ioprio_get, which, who);
+}
+
+static int ioprio_set(int which, int who, int ioprio)
+{
+    return syscall(SYS_ioprio_set, which, who, ioprio);
+}
+
+int main(void)
+{
+    // 未设置过的进程为 NONE 类或尽力而为类
+    int prio = ioprio_get(IOPRIO_WHO_PROCESS, 0);
+    int class = prio >> IOPRIO_CLASS_SHIFT;
+    if (prio < 0 || (class != IOPRIO_CLASS_NONE && class != IOPRIO_CLASS_BE)) {
+        printf("ioprio failed: default ioprio %#x errno %d\n", prio, errno);
+        return 1;
+    }
+
+    // 设置后读回同样的值，pid 为 0 和当前进程的 pid 是同一个进程
+    int be7 = IOPRIO_PRIO_VALUE(IOPRIO_CLASS_BE, 7);
+    if (ioprio_set(IOPRIO_WHO_PROCESS, 0, be7) != 0 ||
+        ioprio_get(IOPRIO_WHO_PROCESS, getpid()) != be7) {
+        printf("ioprio failed: set and get errno %d\n", errno);
+        return 1;
+    }
+
+    // 无效的参数
+    static const struct {
+        int which, who, ioprio, err;
+    } invalid[] = {
+        {IOPRIO_WHO_PROCESS, 0, IOPRIO_PRIO_VALUE(4, 0), EINVAL},
+        {IOPRIO_WHO_PROCESS, 0, IOPRIO_PRIO_VALUE(IOPRIO_CLASS_NONE, 1), EINVAL},
+        {0, 0, IOPRIO_PRIO_VALUE(IOPRIO_CLASS_BE, 0), EINVAL},
+        {IOPRIO_WHO_PROCESS, 0x7fffffff, IOPRIO_PRIO_VALUE(IOPRIO_CLASS_BE, 0), ESRCH},
+    };
+    for (size_t i = 0; i < sizeof(invalid) / sizeof(invalid[0]); i++) {
+        if (ioprio_set(invalid[i].which, invalid[i].who, invalid[i].ioprio) != -1 ||
+            errno != invalid[i].err) {
+            printf("ioprio failed: invalid case %zu errno %d\n", i, errno);
+            return 1;
+        }
+    }
+    if (ioprio_get(IOPRIO_WHO_PROCESS, getpid()) != be7) {
+        printf("ioprio failed: changed by an invalid ioprio_set\n");
+        return 1;
+    }
+
+    // 子进程继承 I/O 优先级
+    pid_t pid = fork();
+    if (pid == 0)
+        return ioprio_get(IOPRIO_WHO_PROCESS, 0) != be7;
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("ioprio failed: not inherited by the child\n");
+        return 1;
+    }
+
+    // 准备实时类进程要读的文件
+    int fd = open(READ_PATH, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    if (fd < 0) {
+        printf("ioprio failed: open errno %d\n", errno);
+        return 1;
+    }
+    for (int i = 0; i < READ_SIZE / CHUNK; i++) {
+        for (int j = 0; j < CHUNK; j++)
+            buf[j] = (char)(i * 7 + j);
+        if (write(fd, buf, CHUNK) != CHUNK) {
+            printf("ioprio failed: write errno %d\n", errno);
+            return 1;
+        }
+    }
+
+    // 空闲类的子进程不停地写入另一个文件，占满块设备
+    int ready[2];
+    if (pipe(ready) != 0) {
+        printf("ioprio failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    pid = fork();
+    if (pid == 0) {
+        if (ioprio_set(IOPRIO_WHO_PROCESS, 0, IOPRIO_PRIO_VALUE(IOPRIO_CLASS_IDLE, 0)) != 0)
+            return 1;
+        int out = open(WRITE_PATH, O_CREAT | O_TRUNC | O_WRONLY, 0644);
+        if (out < 0)
+            return 1;
+        for (int n = 0;; n++) {
+            if (n % 16 == 0)
+                lseek(out, 0, SEEK_SET);
+            if (write(out, buf, CHUNK) != CHUNK)
+                return 1;
+            if (n == 0)
+                write(ready[1], "", 1);
+        }
+    }
+    char c;
+    if (read(ready[0], &c, 1) != 1) {
+        printf("ioprio failed: writer did not start\n");
+        return 1;
+    }
+
+    // 实时类的读者在写者的积压中仍能读完整个文件，且数据正确
+    if (ioprio_set(IOPRIO_WHO_PROCESS, 0, IOPRIO_PRIO_VALUE(IOPRIO_CLASS_RT, 0)) != 0) {
+        printf("ioprio failed: set RT errno %d\n", errno);
+        return 1;
+    }
+    posix_fadvise(fd, 0, 0, POSIX_FADV_DONTNEED);
+    lseek(fd, 0, SEEK_SET);
+    for (int i = 0; i < READ_SIZE / CHUNK; i++) {
+        if (read(fd, buf, CHUNK) != CHUNK) {
+            printf("ioprio failed: read errno %d\n", errno);
+            return 1;
+        }
+        for (int j = 0; j < CHUNK; j++) {
+            if (buf[j] != (char)(i * 7 + j)) {
+                printf("ioprio failed: data at %d\n", i * CHUNK + j);
+                return 1;
+            }
+        }
+    }
+
+    // 读完时写者仍在运行
+    if (waitpid(pid, &status, WNOHANG) != 0) {
+        printf("ioprio failed: writer exited with %#x\n", status);
+        return 1;
+    }
+    kill(pid, SIGKILL);
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status)) {
+        printf("ioprio failed: writer status %#x\n", status);
+        return 1;
+    }
+    close(fd);
+    close(ready[0]);
+    close(ready[1]);
+    unlink(READ_PATH);
+    unlink(WRITE_PATH);
+
+    printf("ioprio passed!\n");
+    return 0;
+}
@@ -0,0 +1,112 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile int winch_count;
+static int fds[2];
+
+static void winch_handler(int sig)
+{
+    (void)sig;
+    winch_count++;
+}
+
+static void sleep_ms(long ms)
+{
+    struct timespec ts = {ms / 1000, ms % 1000 * 1000000};
+    nanosleep(&ts, NULL);
+}
+
+// 子进程：准备好后通知父进程，收到 SIGWINCH 后再通知一次并退出
+static int winch_child(void)
+{
+    signal(SIGWINCH, winch_handler);
+    write(fds[1], "r", 1);
+    while (!winch_count) {
+        sleep_ms(10);
+    }
+    write(fds[1], "w", 1);
+    return 0;
+}
+
+int main()
+{
+    // SIGWINCH 的默认处理方式为忽略，广播它不会影响其他进程
+    signal(SIGWINCH, winch_handler);
+    pipe(fds);
+    pid_t pids[2];
+    for (int i = 0; i < 2; i++) {
+        pids[i] = fork();
+        if (pids[i] == 0) {
+            _exit(winch_child());
+        }
+    }
+    char buf[2];
+    if (read(fds[0], buf, 1) != 1 || read(fds[0], buf + 1, 1) != 1 || buf[0] != 'r' ||
+        buf[1] != 'r') {
+        printf("kill_all failed: children not ready\n");
+        return 1;
+    }
+
+    if (kill(-1, SIGWINCH) != 0) {
+        printf("kill_all failed: kill(-1) errno %d\n", errno);
+        return 1;
+    }
+    if (read(fds[0], buf, 1) != 1 || read(fds[0], buf + 1, 1) != 1 || buf[0] != 'w' ||
+        buf[1] != 'w') {
+        printf("kill_all failed: broadcast not received by children\n");
+        return 1;
+    }
+    // 广播不会发给调用者自身
+    if (winch_count != 0) {
+        printf("kill_all failed: caller received its own broadcast\n");
+        return 1;
+    }
+    for (int i = 0; i < 2; i++) {
+        int status;
+        if (waitpid(pids[i], &status, 0) != pids[i] || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0) {
+            printf("kill_all failed: child %d status %#x\n", i, status);
+            return 1;
+        }
+    }
+
+    // SIGKILL 无法被阻塞，睡眠中的进程立即被终止
+    pid_t pid = fork();
+    if (pid == 0) {
+        sigset_t all;
+        sigfillset(&all);
+        sigprocmask(SIG_BLOCK, &all, NULL);
+        sleep_ms(10000);
+        _exit(0);
+    }
+    sleep_ms(50);
+    kill(pid, SIGKILL);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL) {
+        printf("kill_all failed: SIGKILL while sleeping, status %#x\n", status);
+        return 1;
+    }
+
+    // 等待 vfork 的子进程是不可被打断的，子进程退出后父进程在返回用户态前被终止
+    pid = fork();
+    if (pid == 0) {
+        if (vfork() == 0) {
+            sleep_ms(200);
+            _exit(0);
+        }
+        _exit(1);
+    }
+    sleep_ms(50);
+    kill(pid, SIGKILL);
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL) {
+        printf("kill_all failed: SIGKILL during vfork, status %#x\n", status);
+        return 1;
+    }
+
+    printf("kill_all passed!\n");
+    return 0;
+}
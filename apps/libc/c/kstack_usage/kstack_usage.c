@@ -0,0 +1,73 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// 与内核配置中的 kernel-stack-size 一致
+#define KERNEL_STACK_KB 256
+
+static char buf[4096];
+
+// 读取 /proc/[pid]/status 中的一项，失败时返回 -1
+static long status_field(pid_t pid, const char *key)
+{
+    char path[64];
+    sprintf(path, "/proc/%d/status", pid);
+    int fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        return -1;
+    }
+    int len = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    if (len <= 0) {
+        return -1;
+    }
+    buf[len] = '\0';
+    char *line = strstr(buf, key);
+    return line ? atol(line + strlen(key)) : -1;
+}
+
+int main()
+{
+    // 当前进程已经执行过系统调用，内核栈的最高水位大于 0 且不超过栈的大小
+    long self = status_field(getpid(), "KStk:\t");
+    if (self <= 0 || self > KERNEL_STACK_KB) {
+        printf("kstack_usage failed: KStk of self is %ld kB\n", self);
+        return 1;
+    }
+
+    // 最高水位只增不减
+    for (int i = 0; i < 16; i++) {
+        getppid();
+    }
+    long again = status_field(getpid(), "KStk:\t");
+    if (again < self) {
+        printf("kstack_usage failed: KStk dropped from %ld to %ld kB\n", self, again);
+        return 1;
+    }
+
+    // 子进程的内核栈是新建的，也会被统计
+    int fds[2], ready[2];
+    pipe(fds);
+    pipe(ready);
+    pid_t pid = fork();
+    char c;
+    if (pid == 0) {
+        write(ready[1], "r", 1);
+        read(fds[0], &c, 1);
+        _exit(0);
+    }
+    read(ready[0], &c, 1);
+    long child = status_field(pid, "KStk:\t");
+    write(fds[1], "x", 1);
+    waitpid(pid, NULL, 0);
+    if (child <= 0 || child > KERNEL_STACK_KB) {
+        printf("kstack_usage failed: KStk of child is %ld kB\n", child);
+        return 1;
+    }
+
+    printf("kstack_usage passed!\n");
+    return 0;
+}
@@ -0,0 +1,51 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define MEMBARRIER_CMD_QUERY 0
+#define MEMBARRIER_CMD_GLOBAL (1 << 0)
+#define MEMBARRIER_CMD_PRIVATE_EXPEDITED (1 << 3)
+#define MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED (1 << 4)
+
+static long membarrier(int cmd, unsigned int flags)
+{
+    return syscall(SYS_membarrier, cmd, flags, 0);
+}
+
+int main()
+{
+    long mask = membarrier(MEMBARRIER_CMD_QUERY, 0);
+    if (mask < 0 || !(mask & MEMBARRIER_CMD_GLOBAL) || !(mask & MEMBARRIER_CMD_PRIVATE_EXPEDITED)) {
+        printf("membarrier failed: query returned %ld\n", mask);
+        return 1;
+    }
+
+    if (membarrier(MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED, 0) != 0 ||
+        membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED, 0) != 0 ||
+        membarrier(MEMBARRIER_CMD_GLOBAL, 0) != 0) {
+        printf("membarrier failed: barrier command returned an error\n");
+        return 1;
+    }
+
+    // 不支持的命令、多个命令的组合以及非零的 flags 都应返回 EINVAL
+    errno = 0;
+    if (membarrier(1 << 10, 0) != -1 || errno != EINVAL) {
+        printf("membarrier failed: unknown command accepted\n");
+        return 1;
+    }
+    errno = 0;
+    if (membarrier(MEMBARRIER_CMD_GLOBAL | MEMBARRIER_CMD_PRIVATE_EXPEDITED, 0) != -1 ||
+        errno != EINVAL) {
+        printf("membarrier failed: combined commands accepted\n");
+        return 1;
+    }
+    errno = 0;
+    if (membarrier(MEMBARRIER_CMD_GLOBAL, 1) != -1 || errno != EINVAL) {
+        printf("membarrier failed: nonzero flags accepted\n");
+        return 1;
+    }
+
+    printf("membarrier passed!\n");
+    return 0;
+}
@@ -0,0 +1,156 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define SIZE 8192
+
+static int test_shared(void)
+{
+    int fd = memfd_create("shared", MFD_CLOEXEC);
+    if (fd < 0) {
+        printf("memfd failed: memfd_create returned an error\n");
+        return 1;
+    }
+    if (fcntl(fd, F_GETFD) != FD_CLOEXEC) {
+        printf("memfd failed: MFD_CLOEXEC not set\n");
+        return 1;
+    }
+
+    struct stat st;
+    if (ftruncate(fd, SIZE) != 0 || fstat(fd, &st) != 0 || st.st_size != SIZE) {
+        printf("memfd failed: ftruncate did not change the size\n");
+        return 1;
+    }
+
+    // 读写与 lseek
+    char buf[16] = {0};
+    if (write(fd, "hello", 5) != 5 || lseek(fd, 0, SEEK_SET) != 0 || read(fd, buf, 5) != 5 ||
+        strcmp(buf, "hello") != 0) {
+        printf("memfd failed: data read back differs\n");
+        return 1;
+    }
+    if (lseek(fd, 0, SEEK_END) != SIZE) {
+        printf("memfd failed: SEEK_END returned a wrong offset\n");
+        return 1;
+    }
+
+    // 共享映射看到文件内容，fork 出的子进程写入的数据对父进程和文件都可见
+    char *p = mmap(NULL, SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (p == MAP_FAILED || memcmp(p, "hello", 5) != 0) {
+        printf("memfd failed: shared mapping does not show the file\n");
+        return 1;
+    }
+    pid_t pid = fork();
+    if (pid == 0) {
+        strcpy(p + 4096, "child");
+        _exit(0);
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid) {
+        printf("memfd failed: fork or wait returned an error\n");
+        return 1;
+    }
+    if (strcmp(p + 4096, "child") != 0) {
+        printf("memfd failed: write from child not visible\n");
+        return 1;
+    }
+    memset(buf, 0, sizeof(buf));
+    if (lseek(fd, 4096, SEEK_SET) != 4096 || read(fd, buf, 5) != 5 || strcmp(buf, "child") != 0) {
+        printf("memfd failed: write through mapping not visible to read\n");
+        return 1;
+    }
+
+    // 私有映射是文件的副本
+    char *q = mmap(NULL, SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    if (q == MAP_FAILED || memcmp(q, "hello", 5) != 0) {
+        printf("memfd failed: private mapping does not show the file\n");
+        return 1;
+    }
+    q[0] = 'j';
+    if (p[0] != 'h') {
+        printf("memfd failed: private mapping modified the file\n");
+        return 1;
+    }
+    munmap(q, SIZE);
+    munmap(p, SIZE);
+    close(fd);
+    return 0;
+}
+
+static int test_seals(void)
+{
+    int fd = memfd_create("sealed", MFD_ALLOW_SEALING);
+    if (fd < 0 || fcntl(fd, F_GETFD) != 0) {
+        printf("memfd failed: memfd_create with sealing returned an error\n");
+        return 1;
+    }
+    if (write(fd, "sealed", 6) != 6 || fcntl(fd, F_GET_SEALS) != 0) {
+        printf("memfd failed: unexpected initial seals\n");
+        return 1;
+    }
+
+    // 封印写入之后不能写入，也不能建立可写的共享映射
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE) != 0) {
+        printf("memfd failed: F_SEAL_WRITE rejected\n");
+        return 1;
+    }
+    if (write(fd, "x", 1) != -1 || errno != EPERM) {
+        printf("memfd failed: write allowed after F_SEAL_WRITE\n");
+        return 1;
+    }
+    if (mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0) != MAP_FAILED ||
+        errno != EPERM) {
+        printf("memfd failed: writable mapping allowed after F_SEAL_WRITE\n");
+        return 1;
+    }
+    char *p = mmap(NULL, 4096, PROT_READ, MAP_SHARED, fd, 0);
+    if (p == MAP_FAILED || memcmp(p, "sealed", 6) != 0) {
+        printf("memfd failed: read-only mapping rejected after F_SEAL_WRITE\n");
+        return 1;
+    }
+    munmap(p, 4096);
+
+    // 封印大小之后不能修改大小，封印 F_SEAL_SEAL 之后不能再添加封印
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_SEAL) != 0) {
+        printf("memfd failed: size seals rejected\n");
+        return 1;
+    }
+    if (ftruncate(fd, 1) != -1 || errno != EPERM || ftruncate(fd, SIZE) != -1 || errno != EPERM) {
+        printf("memfd failed: size changed after sealing\n");
+        return 1;
+    }
+    if (fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE) != -1 || errno != EPERM) {
+        printf("memfd failed: seal added after F_SEAL_SEAL\n");
+        return 1;
+    }
+    if (fcntl(fd, F_GET_SEALS) != (F_SEAL_WRITE | F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_SEAL)) {
+        printf("memfd failed: F_GET_SEALS returned wrong seals\n");
+        return 1;
+    }
+    close(fd);
+
+    // 未指定 MFD_ALLOW_SEALING 时不能添加封印
+    fd = memfd_create("unsealable", 0);
+    if (fcntl(fd, F_GET_SEALS) != F_SEAL_SEAL || fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE) != -1 ||
+        errno != EPERM) {
+        printf("memfd failed: sealing allowed without MFD_ALLOW_SEALING\n");
+        return 1;
+    }
+    close(fd);
+    return 0;
+}
+
+int main()
+{
+    if (test_shared() != 0 || test_seals() != 0) {
+        return 1;
+    }
+    printf("memfd passed!\n");
+    return 0;
+}
@@ -0,0 +1,134 @@
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static const char MSG[] = "hello fifo";
+
+// 目录中 name 的 d_type，找不到时返回 -1
+static int dir_type(const char *name)
+{
+    DIR *dir = opendir(".");
+    struct dirent *ent;
+    int type = -1;
+    if (!dir)
+        return -1;
+    while ((ent = readdir(dir)) != NULL) {
+        if (strcmp(ent->d_name, name) == 0) {
+            type = ent->d_type;
+            break;
+        }
+    }
+    closedir(dir);
+    return type;
+}
+
+// 写端：打开时等待读端，写入后关闭
+static int writer(void)
+{
+    int fd = open("mk_fifo", O_WRONLY);
+    if (fd < 0 || write(fd, MSG, sizeof(MSG)) != sizeof(MSG))
+        return 1;
+    close(fd);
+    return 0;
+}
+
+// 读端：读到写端关闭为止
+static int reader(void)
+{
+    char buf[64];
+    size_t len = 0;
+    ssize_t n;
+    int fd = open("mk_fifo", O_RDONLY);
+    if (fd < 0)
+        return 1;
+    struct stat st;
+    if (fstat(fd, &st) != 0 || !S_ISFIFO(st.st_mode))
+        return 2;
+    while ((n = read(fd, buf + len, sizeof(buf) - len)) > 0)
+        len += n;
+    close(fd);
+    return n == 0 && len == sizeof(MSG) && memcmp(buf, MSG, len) == 0 ? 0 : 3;
+}
+
+int main(void)
+{
+    int fd, status;
+    if (mknodat(AT_FDCWD, "mk_fifo", S_IFIFO | 0644, 0) != 0) {
+        printf("mknod failed: mknodat fifo errno %d\n", errno);
+        return 1;
+    }
+    if (mknodat(AT_FDCWD, "mk_fifo", S_IFIFO | 0644, 0) == 0 || errno != EEXIST) {
+        printf("mknod failed: mknodat over an existing name errno %d\n", errno);
+        return 1;
+    }
+    if (dir_type("mk_fifo") != DT_FIFO) {
+        printf("mknod failed: d_type of fifo is %d\n", dir_type("mk_fifo"));
+        return 1;
+    }
+
+    // 没有读端时，非阻塞地打开写端失败，非阻塞地打开读端成功
+    if (open("mk_fifo", O_WRONLY | O_NONBLOCK) >= 0 || errno != ENXIO) {
+        printf("mknod failed: nonblocking open for writing errno %d\n", errno);
+        return 1;
+    }
+    fd = open("mk_fifo", O_RDONLY | O_NONBLOCK);
+    if (fd < 0) {
+        printf("mknod failed: nonblocking open for reading errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    // 两个子进程只通过命名管道的路径通信
+    pid_t pids[2];
+    for (int i = 0; i < 2; i++) {
+        pids[i] = fork();
+        if (pids[i] == 0)
+            return i == 0 ? reader() : writer();
+    }
+    for (int i = 0; i < 2; i++) {
+        if (waitpid(pids[i], &status, 0) != pids[i] || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0) {
+            printf("mknod failed: %s exited with status %#x\n", i == 0 ? "reader" : "writer",
+                   status);
+            return 1;
+        }
+    }
+
+    // 重新创建 /dev/null
+    if (mknodat(AT_FDCWD, "mk_null", S_IFCHR | 0666, makedev(1, 3)) != 0) {
+        printf("mknod failed: mknodat chr errno %d\n", errno);
+        return 1;
+    }
+    char c;
+    struct stat st;
+    fd = open("mk_null", O_RDWR);
+    if (fd < 0 || write(fd, MSG, sizeof(MSG)) != sizeof(MSG) || read(fd, &c, 1) != 0) {
+        printf("mknod failed: mk_null does not behave as /dev/null errno %d\n", errno);
+        return 1;
+    }
+    if (fstat(fd, &st) != 0 || !S_ISCHR(st.st_mode) || st.st_rdev != makedev(1, 3)) {
+        printf("mknod failed: mk_null has mode %#o rdev %#lx\n", st.st_mode,
+               (unsigned long)st.st_rdev);
+        return 1;
+    }
+    close(fd);
+    if (dir_type("mk_null") != DT_CHR) {
+        printf("mknod failed: d_type of mk_null is %d\n", dir_type("mk_null"));
+        return 1;
+    }
+
+    unlinkat(AT_FDCWD, "mk_fifo", 0);
+    unlinkat(AT_FDCWD, "mk_null", 0);
+    if (dir_type("mk_fifo") != -1) {
+        printf("mknod failed: mk_fifo still exists after unlink\n");
+        return 1;
+    }
+    printf("mknod passed!\n");
+    return 0;
+}
@@ -0,0 +1,94 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define PAGES 4
+
+static int resident_pages(void *addr, size_t len, unsigned char *vec)
+{
+    if (mincore(addr, len, vec) != 0) {
+        return -1;
+    }
+    int count = 0;
+    for (size_t i = 0; i < len / 4096; i++) {
+        count += vec[i] & 1;
+    }
+    return count;
+}
+
+int main()
+{
+    size_t len = PAGES * 4096;
+    unsigned char vec[PAGES];
+    char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        printf("mlock failed: mmap returned an error\n");
+        return 1;
+    }
+
+    // 匿名映射延迟分配，访问之前不驻留在内存中
+    if (resident_pages(buf, len, vec) != 0) {
+        printf("mlock failed: fresh mapping is resident\n");
+        return 1;
+    }
+    buf[0] = 1;
+    if (resident_pages(buf, len, vec) != 1 || vec[0] != 1) {
+        printf("mlock failed: touched page is not resident\n");
+        return 1;
+    }
+
+    // 锁定之后整个区域立即驻留，已写入的数据保持不变
+    if (mlock(buf + 1, len - 1) != 0 || resident_pages(buf, len, vec) != PAGES || buf[0] != 1) {
+        printf("mlock failed: locked range is not resident\n");
+        return 1;
+    }
+    if (munlock(buf, len) != 0) {
+        printf("mlock failed: munlock returned an error\n");
+        return 1;
+    }
+
+    // 未映射的区域返回 ENOMEM，未对齐的地址返回 EINVAL
+    munmap(buf, len);
+    if (mlock(buf, len) != -1 || errno != ENOMEM) {
+        printf("mlock failed: unmapped range accepted by mlock\n");
+        return 1;
+    }
+    if (mincore(buf, len, vec) != -1 || errno != ENOMEM) {
+        printf("mlock failed: unmapped range accepted by mincore\n");
+        return 1;
+    }
+    if (mincore((char *)vec + 1, 1, vec) != -1 || errno != EINVAL) {
+        printf("mlock failed: unaligned address accepted by mincore\n");
+        return 1;
+    }
+
+    // MCL_FUTURE 之后新建的映射立即驻留
+    if (mlockall(MCL_CURRENT | MCL_FUTURE) != 0) {
+        printf("mlock failed: mlockall returned an error\n");
+        return 1;
+    }
+    buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED || resident_pages(buf, len, vec) != PAGES) {
+        printf("mlock failed: mapping after MCL_FUTURE is not resident\n");
+        return 1;
+    }
+    munmap(buf, len);
+
+    // munlockall 之后恢复延迟分配
+    munlockall();
+    buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED || resident_pages(buf, len, vec) != 0) {
+        printf("mlock failed: mapping after munlockall is resident\n");
+        return 1;
+    }
+    munmap(buf, len);
+
+    if (mlockall(0) != -1 || errno != EINVAL) {
+        printf("mlock failed: mlockall accepted empty flags\n");
+        return 1;
+    }
+
+    printf("mlock passed!\n");
+    return 0;
+}
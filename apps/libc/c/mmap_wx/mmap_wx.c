@@ -0,0 +1,194 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PAGE 4096
+#define PATH "mmap_wx_file"
+
+// 在 /proc/self/maps 中找到包含 addr 的行，取出权限和偏移，行内容写入 line
+static int find_map(void *addr, char perm[5], unsigned long *offset, char *line, int size)
+{
+    FILE *maps = fopen("/proc/self/maps", "r");
+    if (maps == NULL) {
+        printf("mmap_wx failed: open /proc/self/maps errno %d\n", errno);
+        return 1;
+    }
+    unsigned long start, end;
+    while (fgets(line, size, maps) != NULL) {
+        if (sscanf(line, "%lx-%lx %4s %lx", &start, &end, perm, offset) == 4 &&
+            start <= (unsigned long)addr && (unsigned long)addr < end) {
+            fclose(maps);
+            return 0;
+        }
+    }
+    fclose(maps);
+    printf("mmap_wx failed: %p not in /proc/self/maps\n", addr);
+    return 1;
+}
+
+// addr 所在映射的权限应为 expected
+static int expect_perm(void *addr, const char *expected)
+{
+    char perm[5], line[256];
+    unsigned long offset;
+    if (find_map(addr, perm, &offset, line, sizeof(line)))
+        return 1;
+    if (strcmp(perm, expected) != 0) {
+        printf("mmap_wx failed: %p is %s instead of %s\n", addr, perm, expected);
+        return 1;
+    }
+    return 0;
+}
+
+// 子进程写入 addr 时应被 SIGSEGV 终止
+static int expect_segv(char *addr)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        *(volatile char *)addr = 1;
+        _exit(0);
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status) || WTERMSIG(status) != SIGSEGV) {
+        printf("mmap_wx failed: write to %p gives status %#x\n", addr, status);
+        return 1;
+    }
+    return 0;
+}
+
+// 只读打开的文件不能有可写的共享映射，映射后也不能改为可写
+static int check_file(void)
+{
+    int fd = open(PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || ftruncate(fd, 3 * PAGE) != 0) {
+        printf("mmap_wx failed: create errno %d\n", errno);
+        return 1;
+    }
+    int rd = open(PATH, O_RDONLY);
+    if (mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_SHARED, rd, 0) != MAP_FAILED ||
+        errno != EACCES) {
+        printf("mmap_wx failed: writable shared mapping of O_RDONLY fd\n");
+        return 1;
+    }
+    char *map = mmap(NULL, 2 * PAGE, PROT_READ, MAP_SHARED, rd, PAGE);
+    if (map == MAP_FAILED) {
+        printf("mmap_wx failed: mmap read-only errno %d\n", errno);
+        return 1;
+    }
+    if (mprotect(map, PAGE, PROT_READ | PROT_WRITE) != -1 || errno != EACCES) {
+        printf("mmap_wx failed: made shared mapping of O_RDONLY fd writable\n");
+        return 1;
+    }
+    // 映射记录文件的路径和偏移，拆分出的第二页从文件的第 2 页开始
+    char perm[5], line[256];
+    unsigned long offset;
+    if (find_map(map, perm, &offset, line, sizeof(line)))
+        return 1;
+    if (strcmp(perm, "r--s") != 0 || offset != PAGE || strstr(line, PATH) == NULL) {
+        printf("mmap_wx failed: file mapping shown as %s", line);
+        return 1;
+    }
+    if (mprotect(map + PAGE, PAGE, PROT_NONE) != 0 ||
+        find_map(map + PAGE, perm, &offset, line, sizeof(line)))
+        return 1;
+    if (strcmp(perm, "---s") != 0 || offset != 2 * PAGE || strstr(line, PATH) == NULL) {
+        printf("mmap_wx failed: split file mapping shown as %s", line);
+        return 1;
+    }
+    munmap(map, 2 * PAGE);
+
+    // 私有映射的修改不写回文件，可以可写
+    map = mmap(NULL, PAGE, PROT_READ, MAP_PRIVATE, rd, 0);
+    if (map == MAP_FAILED || mprotect(map, PAGE, PROT_READ | PROT_WRITE) != 0) {
+        printf("mmap_wx failed: private mapping of O_RDONLY fd errno %d\n", errno);
+        return 1;
+    }
+    map[0] = 1;
+    munmap(map, PAGE);
+
+    // 可写的文件可以映射为可执行
+    map = mmap(NULL, PAGE, PROT_READ | PROT_EXEC, MAP_SHARED, fd, 0);
+    if (map == MAP_FAILED || expect_perm(map, "r-xs")) {
+        printf("mmap_wx failed: executable mapping of writable file errno %d\n", errno);
+        return 1;
+    }
+    munmap(map, PAGE);
+    close(rd);
+    close(fd);
+    unlink(PATH);
+    return 0;
+}
+
+// mprotect 拆分映射，/proc/self/maps 显示每部分当前的权限
+static int check_protect(void)
+{
+    char *map = mmap(NULL, 3 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (map == MAP_FAILED) {
+        printf("mmap_wx failed: mmap errno %d\n", errno);
+        return 1;
+    }
+    memset(map, 1, 3 * PAGE);
+    if (mprotect(map + PAGE, PAGE, PROT_READ) != 0) {
+        printf("mmap_wx failed: mprotect errno %d\n", errno);
+        return 1;
+    }
+    if (expect_perm(map, "rw-p") || expect_perm(map + PAGE, "r--p") ||
+        expect_perm(map + 2 * PAGE, "rw-p") || expect_segv(map + PAGE))
+        return 1;
+    if (map[PAGE] != 1) {
+        printf("mmap_wx failed: contents lost after mprotect\n");
+        return 1;
+    }
+    // 尚未访问的页按新的权限分配
+    char *lazy = mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (lazy == MAP_FAILED || mprotect(lazy, PAGE, PROT_READ) != 0 || lazy[0] != 0 ||
+        expect_segv(lazy)) {
+        printf("mmap_wx failed: mprotect of untouched page errno %d\n", errno);
+        return 1;
+    }
+    // 改回可写后可以写入
+    if (mprotect(map, 3 * PAGE, PROT_READ | PROT_WRITE) != 0 || expect_perm(map + PAGE, "rw-p")) {
+        printf("mmap_wx failed: mprotect back errno %d\n", errno);
+        return 1;
+    }
+    map[PAGE] = 2;
+
+    // 严格 W^X 模式下也可以在可写与可执行之间切换
+    if (mprotect(map, PAGE, PROT_READ | PROT_EXEC) != 0 || expect_perm(map, "r-xp") ||
+        mprotect(map, PAGE, PROT_READ | PROT_WRITE) != 0 || expect_perm(map, "rw-p")) {
+        printf("mmap_wx failed: flip between W and X errno %d\n", errno);
+        return 1;
+    }
+    // 同时可写和可执行只在严格模式下被拒绝
+    int ret = mprotect(map, PAGE, PROT_READ | PROT_WRITE | PROT_EXEC);
+    if (ret != 0 && errno != EPERM) {
+        printf("mmap_wx failed: mprotect RWX errno %d\n", errno);
+        return 1;
+    }
+
+    // 参数错误
+    if (mprotect(map + 1, PAGE, PROT_READ) != -1 || errno != EINVAL) {
+        printf("mmap_wx failed: unaligned mprotect\n");
+        return 1;
+    }
+    munmap(map, 3 * PAGE);
+    if (mprotect(map, PAGE, PROT_READ) != -1 || errno != ENOMEM) {
+        printf("mmap_wx failed: mprotect of unmapped range\n");
+        return 1;
+    }
+    munmap(lazy, PAGE);
+    return 0;
+}
+
+int main(void)
+{
+    if (check_file() || check_protect())
+        return 1;
+    printf("mmap_wx passed!\n");
+    return 0;
+}
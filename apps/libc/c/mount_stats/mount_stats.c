@@ -0,0 +1,167 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/statfs.h>
+#include <sys/statvfs.h>
+#include <unistd.h>
+
+#define MNT "/mount_stats_mnt"
+#define FILE_PATH MNT "/mount_stats.dat"
+#define CHUNK (32 * 1024)
+#define TOTAL (1024 * 1024)
+#define MSDOS_SUPER_MAGIC 0x4d44
+
+static char buf[CHUNK];
+
+// /proc/mounts 中挂载点一行的扩展列
+struct mount_stats {
+    char options[64];
+    unsigned long open_files;
+    unsigned long bytes_read;
+    unsigned long bytes_written;
+    char min_free[32];
+};
+
+// 从 /proc/mounts 中读取挂载点 path 的统计信息，找到时返回 0
+static int read_mount_stats(const char *path, struct mount_stats *stats)
+{
+    static char text[4096];
+    int fd = open("/proc/mounts", O_RDONLY);
+    if (fd < 0)
+        return -1;
+    ssize_t len = 0, n;
+    while (len < (ssize_t)sizeof(text) - 1 && (n = read(fd, text + len, sizeof(text) - 1 - len)) > 0)
+        len += n;
+    close(fd);
+    text[len] = '\0';
+
+    for (char *line = strtok(text, "\n"); line; line = strtok(NULL, "\n")) {
+        char source[64], target[64], type[16];
+        int matched = sscanf(line, "%63s %63s %15s %63s %*d %*d %lu %lu %lu %31s", source, target, type,
+                             stats->options, &stats->open_files, &stats->bytes_read, &stats->bytes_written,
+                             stats->min_free);
+        if (matched == 8 && strcmp(target, path) == 0)
+            return 0;
+    }
+    return -1;
+}
+
+int main(void)
+{
+    mkdir(MNT, 0755);
+    if (mount("/dev/vdb", MNT, "vfat", MS_NOSUID, NULL) != 0) {
+        printf("mount_stats failed: mount /dev/vdb\n");
+        return 1;
+    }
+
+    // statfs 报告文件系统类型和挂载参数
+    struct statfs sfs;
+    if (statfs(MNT, &sfs) != 0) {
+        printf("mount_stats failed: statfs\n");
+        return 1;
+    }
+    if (sfs.f_type != MSDOS_SUPER_MAGIC || sfs.f_bsize <= 0 || sfs.f_blocks == 0) {
+        printf("mount_stats failed: statfs type %lx bsize %ld blocks %lu\n", (unsigned long)sfs.f_type,
+               (long)sfs.f_bsize, (unsigned long)sfs.f_blocks);
+        return 1;
+    }
+    if (!(sfs.f_flags & ST_NOSUID) || (sfs.f_flags & ST_RDONLY)) {
+        printf("mount_stats failed: statfs flags %lx\n", (unsigned long)sfs.f_flags);
+        return 1;
+    }
+    struct statvfs svfs;
+    if (statvfs(MNT, &svfs) != 0 || !(svfs.f_flag & ST_NOSUID)) {
+        printf("mount_stats failed: statvfs\n");
+        return 1;
+    }
+
+    struct mount_stats before;
+    if (read_mount_stats(MNT, &before) != 0) {
+        printf("mount_stats failed: no entry in /proc/mounts\n");
+        return 1;
+    }
+    if (strcmp(before.options, "rw,nosuid") != 0) {
+        printf("mount_stats failed: options %s\n", before.options);
+        return 1;
+    }
+
+    // 磁盘只有 50 KiB，反复覆盖文件开头的 32 KiB，共写入 1 MiB
+    int fd = open(FILE_PATH, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    if (fd < 0) {
+        printf("mount_stats failed: open\n");
+        return 1;
+    }
+    memset(buf, 'm', sizeof(buf));
+    for (long written = 0; written < TOTAL; written += CHUNK) {
+        if (lseek(fd, 0, SEEK_SET) != 0 || write(fd, buf, CHUNK) != CHUNK) {
+            printf("mount_stats failed: write at %ld\n", written);
+            return 1;
+        }
+    }
+    if (lseek(fd, 0, SEEK_SET) != 0 || read(fd, buf, CHUNK) != CHUNK) {
+        printf("mount_stats failed: read\n");
+        return 1;
+    }
+
+    // 文件打开期间计入打开的文件数，读写的字节数都已累计
+    struct mount_stats after;
+    if (read_mount_stats(MNT, &after) != 0) {
+        printf("mount_stats failed: no entry in /proc/mounts after writing\n");
+        return 1;
+    }
+    if (after.open_files != before.open_files + 1) {
+        printf("mount_stats failed: open files %lu -> %lu\n", before.open_files, after.open_files);
+        return 1;
+    }
+    if (after.bytes_written - before.bytes_written < TOTAL) {
+        printf("mount_stats failed: bytes written %lu -> %lu\n", before.bytes_written, after.bytes_written);
+        return 1;
+    }
+    if (after.bytes_read - before.bytes_read < CHUNK) {
+        printf("mount_stats failed: bytes read %lu -> %lu\n", before.bytes_read, after.bytes_read);
+        return 1;
+    }
+    if (strcmp(after.min_free, "-") == 0) {
+        printf("mount_stats failed: no free space low-water mark\n");
+        return 1;
+    }
+
+    // fstatfs 通过文件描述符报告同一个文件系统
+    struct statfs fsfs;
+    if (fstatfs(fd, &fsfs) != 0 || fsfs.f_type != MSDOS_SUPER_MAGIC || !(fsfs.f_flags & ST_NOSUID)) {
+        printf("mount_stats failed: fstatfs\n");
+        return 1;
+    }
+    close(fd);
+    if (read_mount_stats(MNT, &after) != 0 || after.open_files != before.open_files) {
+        printf("mount_stats failed: open files after close %lu\n", after.open_files);
+        return 1;
+    }
+
+    // 重新挂载为只读后 f_flags 带 ST_RDONLY
+    if (mount(NULL, MNT, NULL, MS_REMOUNT | MS_RDONLY, NULL) != 0) {
+        printf("mount_stats failed: remount read-only\n");
+        return 1;
+    }
+    if (statfs(MNT, &sfs) != 0 || !(sfs.f_flags & ST_RDONLY)) {
+        printf("mount_stats failed: statfs after remount\n");
+        return 1;
+    }
+    if (read_mount_stats(MNT, &after) != 0 || strncmp(after.options, "ro", 2) != 0) {
+        printf("mount_stats failed: options after remount %s\n", after.options);
+        return 1;
+    }
+    if (mount(NULL, MNT, NULL, MS_REMOUNT, NULL) != 0) {
+        printf("mount_stats failed: remount read-write\n");
+        return 1;
+    }
+
+    unlink(FILE_PATH);
+    umount(MNT);
+    rmdir(MNT);
+
+    printf("mount_stats passed!\n");
+    return 0;
+}
@@ -0,0 +1,143 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <mqueue.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define NAME "/mqueue_test"
+#define MSGSIZE 64
+
+// 当前时间之后 ms 毫秒的绝对时间
+static struct timespec after_ms(long ms)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_REALTIME, &ts);
+    ts.tv_nsec += ms * 1000000;
+    ts.tv_sec += ts.tv_nsec / 1000000000;
+    ts.tv_nsec %= 1000000000;
+    return ts;
+}
+
+int main(void)
+{
+    char buf[MSGSIZE];
+    unsigned prio;
+
+    mq_unlink(NAME);
+    struct mq_attr attr = {.mq_maxmsg = 4, .mq_msgsize = MSGSIZE};
+    mqd_t mq = mq_open(NAME, O_CREAT | O_EXCL | O_RDWR, 0600, &attr);
+    if (mq == (mqd_t)-1) {
+        printf("mqueue failed: mq_open errno %d\n", errno);
+        return 1;
+    }
+    if (mq_open(NAME, O_CREAT | O_EXCL | O_RDWR, 0600, &attr) != (mqd_t)-1 || errno != EEXIST) {
+        printf("mqueue failed: O_EXCL errno %d\n", errno);
+        return 1;
+    }
+
+    // 按优先级从高到低接收，同一优先级先进先出
+    if (mq_send(mq, "low", 4, 1) || mq_send(mq, "high", 5, 5) || mq_send(mq, "high2", 6, 5) ||
+        mq_send(mq, "mid", 4, 3)) {
+        printf("mqueue failed: mq_send errno %d\n", errno);
+        return 1;
+    }
+    const char *order[] = {"high", "high2", "mid", "low"};
+    const unsigned prios[] = {5, 5, 3, 1};
+    struct mq_attr cur;
+    if (mq_getattr(mq, &cur) != 0 || cur.mq_curmsgs != 4 || cur.mq_maxmsg != 4 ||
+        cur.mq_msgsize != MSGSIZE) {
+        printf("mqueue failed: mq_getattr errno %d\n", errno);
+        return 1;
+    }
+
+    // 队列已满时超时的发送
+    struct timespec ts = after_ms(20);
+    if (mq_timedsend(mq, "x", 1, 0, &ts) != -1 || errno != ETIMEDOUT) {
+        printf("mqueue failed: mq_timedsend on a full queue errno %d\n", errno);
+        return 1;
+    }
+    if (mq_receive(mq, buf, MSGSIZE - 1, NULL) != -1 || errno != EMSGSIZE) {
+        printf("mqueue failed: short buffer errno %d\n", errno);
+        return 1;
+    }
+    for (int i = 0; i < 4; i++) {
+        ssize_t n = mq_receive(mq, buf, MSGSIZE, &prio);
+        if (n != (ssize_t)strlen(order[i]) + 1 || strcmp(buf, order[i]) != 0 || prio != prios[i]) {
+            printf("mqueue failed: message %d got %s prio %u\n", i, buf, prio);
+            return 1;
+        }
+    }
+
+    // 空队列：超时的接收，以及通过 mq_setattr 设置的非阻塞模式
+    ts = after_ms(20);
+    if (mq_timedreceive(mq, buf, MSGSIZE, NULL, &ts) != -1 || errno != ETIMEDOUT) {
+        printf("mqueue failed: mq_timedreceive errno %d\n", errno);
+        return 1;
+    }
+    struct mq_attr nonblock = {.mq_flags = O_NONBLOCK};
+    if (mq_setattr(mq, &nonblock, &cur) != 0 || cur.mq_flags != 0 ||
+        mq_receive(mq, buf, MSGSIZE, NULL) != -1 || errno != EAGAIN) {
+        printf("mqueue failed: O_NONBLOCK errno %d\n", errno);
+        return 1;
+    }
+    if (mq_getattr(mq, &cur) != 0 || cur.mq_flags != O_NONBLOCK) {
+        printf("mqueue failed: mq_flags %ld\n", cur.mq_flags);
+        return 1;
+    }
+    nonblock.mq_flags = 0;
+    mq_setattr(mq, &nonblock, NULL);
+
+    // 描述符可以被 dup，阻塞的接收者被另一个进程发送的消息唤醒
+    mqd_t dup_mq = dup(mq);
+    close(mq);
+    pid_t pid = fork();
+    if (pid == 0) {
+        if (mq_receive(dup_mq, buf, MSGSIZE, &prio) != 6 || strcmp(buf, "hello") != 0 || prio != 2)
+            return 2;
+        return 0;
+    }
+    usleep(20000);
+    if (mq_send(dup_mq, "hello", 6, 2) != 0) {
+        printf("mqueue failed: mq_send errno %d\n", errno);
+        return 1;
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("mqueue failed: child status %#x\n", status);
+        return 1;
+    }
+
+    // 删除名称后，已打开的描述符仍可使用队列
+    if (mq_unlink(NAME) != 0) {
+        printf("mqueue failed: mq_unlink errno %d\n", errno);
+        return 1;
+    }
+    if (mq_open(NAME, O_RDWR) != (mqd_t)-1 || errno != ENOENT) {
+        printf("mqueue failed: mq_open of an unlinked queue errno %d\n", errno);
+        return 1;
+    }
+    if (mq_send(dup_mq, "still", 6, 0) != 0 || mq_receive(dup_mq, buf, MSGSIZE, NULL) != 6) {
+        printf("mqueue failed: unlinked queue errno %d\n", errno);
+        return 1;
+    }
+    close(dup_mq);
+
+    // 只读打开的队列不能发送
+    mq = mq_open(NAME, O_CREAT | O_RDONLY, 0600, NULL);
+    if (mq == (mqd_t)-1 || mq_send(mq, "x", 1, 0) != -1 || errno != EBADF) {
+        printf("mqueue failed: send on a read-only queue errno %d\n", errno);
+        return 1;
+    }
+    if (mq_getattr(mq, &cur) != 0 || cur.mq_maxmsg != 10 || cur.mq_msgsize != 8192) {
+        printf("mqueue failed: default attributes %ld %ld\n", cur.mq_maxmsg, cur.mq_msgsize);
+        return 1;
+    }
+    close(mq);
+    mq_unlink(NAME);
+
+    printf("mqueue passed!\n");
+    return 0;
+}
@@ -0,0 +1,172 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/msg.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define ROUNDS 100
+
+struct msg {
+    long mtype;
+    char mtext[64];
+};
+
+static int send(int id, long type, const char *text, int flags)
+{
+    struct msg m = {.mtype = type};
+    strcpy(m.mtext, text);
+    return msgsnd(id, &m, strlen(text) + 1, flags);
+}
+
+int main(void)
+{
+    struct msg m;
+    struct msqid_ds ds;
+
+    int id = msgget(IPC_PRIVATE, IPC_CREAT | 0600);
+    if (id < 0) {
+        printf("msg_queue failed: msgget errno %d\n", errno);
+        return 1;
+    }
+
+    // 父子进程通过类型 1（父到子）和类型 2（子到父）的消息轮流传递计数
+    pid_t pid = fork();
+    if (pid == 0) {
+        for (int i = 0; i < ROUNDS; i++) {
+            if (msgrcv(id, &m, sizeof(m.mtext), 1, 0) < 0)
+                return 2;
+            int n;
+            if (sscanf(m.mtext, "%d", &n) != 1 || n != i)
+                return 3;
+            snprintf(m.mtext, sizeof(m.mtext), "%d", n + 1);
+            if (send(id, 2, m.mtext, 0) != 0)
+                return 4;
+        }
+        return 0;
+    }
+    for (int i = 0; i < ROUNDS; i++) {
+        snprintf(m.mtext, sizeof(m.mtext), "%d", i);
+        if (send(id, 1, m.mtext, 0) != 0) {
+            printf("msg_queue failed: msgsnd errno %d\n", errno);
+            return 1;
+        }
+        int n;
+        if (msgrcv(id, &m, sizeof(m.mtext), 2, 0) < 0 || sscanf(m.mtext, "%d", &n) != 1 ||
+            n != i + 1) {
+            printf("msg_queue failed: round %d reply %s errno %d\n", i, m.mtext, errno);
+            return 1;
+        }
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("msg_queue failed: child status %#x\n", status);
+        return 1;
+    }
+
+    // 空队列上不等待的接收
+    if (msgrcv(id, &m, sizeof(m.mtext), 0, IPC_NOWAIT) != -1 || errno != ENOMSG) {
+        printf("msg_queue failed: IPC_NOWAIT on empty queue errno %d\n", errno);
+        return 1;
+    }
+
+    // 按类型选择消息：正数选该类型，负数选不超过其绝对值的最小类型，0 选第一条
+    if (send(id, 5, "five", 0) || send(id, 3, "three", 0) || send(id, 7, "seven", 0) ||
+        send(id, 3, "three again", 0)) {
+        printf("msg_queue failed: msgsnd errno %d\n", errno);
+        return 1;
+    }
+    if (msgrcv(id, &m, sizeof(m.mtext), 7, 0) < 0 || strcmp(m.mtext, "seven") != 0) {
+        printf("msg_queue failed: positive type got %s\n", m.mtext);
+        return 1;
+    }
+    if (msgrcv(id, &m, sizeof(m.mtext), -4, 0) < 0 || strcmp(m.mtext, "three") != 0) {
+        printf("msg_queue failed: negative type got %s\n", m.mtext);
+        return 1;
+    }
+    if (msgrcv(id, &m, sizeof(m.mtext), 3, MSG_EXCEPT) < 0 || strcmp(m.mtext, "five") != 0) {
+        printf("msg_queue failed: MSG_EXCEPT got %s\n", m.mtext);
+        return 1;
+    }
+    // 缓冲区太小时消息留在队列中，MSG_NOERROR 截断
+    if (msgrcv(id, &m, 4, 0, 0) != -1 || errno != E2BIG) {
+        printf("msg_queue failed: short buffer errno %d\n", errno);
+        return 1;
+    }
+    if (msgrcv(id, &m, 5, 0, MSG_NOERROR) != 5 || memcmp(m.mtext, "three", 5) != 0) {
+        printf("msg_queue failed: MSG_NOERROR\n");
+        return 1;
+    }
+
+    // 队列满时不等待的发送返回 EAGAIN
+    if (msgctl(id, IPC_STAT, &ds) != 0 || ds.msg_qnum != 0 || ds.msg_cbytes != 0) {
+        printf("msg_queue failed: IPC_STAT errno %d\n", errno);
+        return 1;
+    }
+    ds.msg_qbytes = 10;
+    if (msgctl(id, IPC_SET, &ds) != 0) {
+        printf("msg_queue failed: IPC_SET errno %d\n", errno);
+        return 1;
+    }
+    if (send(id, 1, "12345678", IPC_NOWAIT) != 0 || send(id, 1, "abc", IPC_NOWAIT) != -1 ||
+        errno != EAGAIN) {
+        printf("msg_queue failed: full queue errno %d\n", errno);
+        return 1;
+    }
+    if (msgctl(id, IPC_STAT, &ds) != 0 || ds.msg_qnum != 1 || ds.msg_cbytes != 9 ||
+        ds.msg_qbytes != 10 || ds.msg_lspid != getpid()) {
+        printf("msg_queue failed: IPC_STAT after send\n");
+        return 1;
+    }
+
+    if (msgrcv(id, &m, sizeof(m.mtext), 0, 0) != 9) {
+        printf("msg_queue failed: msgrcv errno %d\n", errno);
+        return 1;
+    }
+
+    // 删除队列唤醒阻塞的接收者，它返回 EIDRM
+    pid = fork();
+    if (pid == 0) {
+        if (send(id, 50, "r", 0) != 0)
+            return 3;
+        if (msgrcv(id, &m, sizeof(m.mtext), 99, 0) != -1 || errno != EIDRM)
+            return 2;
+        return 0;
+    }
+    // 等子进程开始等待后再删除队列
+    if (msgrcv(id, &m, sizeof(m.mtext), 50, 0) < 0) {
+        printf("msg_queue failed: msgrcv errno %d\n", errno);
+        return 1;
+    }
+    usleep(50000);
+    if (msgctl(id, IPC_RMID, NULL) != 0) {
+        printf("msg_queue failed: IPC_RMID errno %d\n", errno);
+        return 1;
+    }
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("msg_queue failed: blocked receiver status %#x\n", status);
+        return 1;
+    }
+    if (msgsnd(id, &m, 1, 0) != -1 || errno != EINVAL) {
+        printf("msg_queue failed: send to removed queue errno %d\n", errno);
+        return 1;
+    }
+
+    // 有键的队列：IPC_EXCL 与已存在的键冲突，不带 IPC_CREAT 时找不到返回 ENOENT
+    key_t key = 0x5a17;
+    id = msgget(key, IPC_CREAT | 0600);
+    if (id < 0 || msgget(key, 0) != id || msgget(key, IPC_CREAT | IPC_EXCL) != -1 ||
+        errno != EEXIST) {
+        printf("msg_queue failed: keyed msgget errno %d\n", errno);
+        return 1;
+    }
+    msgctl(id, IPC_RMID, NULL);
+    if (msgget(key, 0) != -1 || errno != ENOENT) {
+        printf("msg_queue failed: msgget of a removed key errno %d\n", errno);
+        return 1;
+    }
+
+    printf("msg_queue passed!\n");
+    return 0;
+}
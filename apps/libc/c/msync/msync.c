@@ -0,0 +1,142 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PAGE 4096
+#define FILE_SIZE (2 * PAGE + 100)
+#define PATH "msync_file"
+
+static char buf[FILE_SIZE];
+
+// 从文件的 offset 处用 read 读取 len 字节
+static int read_file(off_t offset, char *dst, size_t len)
+{
+    int fd = open(PATH, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    if (lseek(fd, offset, SEEK_SET) != offset || read(fd, dst, len) != (ssize_t)len) {
+        close(fd);
+        return -1;
+    }
+    return close(fd);
+}
+
+static char *map_file(int *fd)
+{
+    *fd = open(PATH, O_RDWR);
+    if (*fd < 0)
+        return MAP_FAILED;
+    return mmap(NULL, FILE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, *fd, 0);
+}
+
+int main(void)
+{
+    int fd = open(PATH, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    memset(buf, 'a', sizeof(buf));
+    if (fd < 0 || write(fd, buf, sizeof(buf)) != sizeof(buf) || close(fd) != 0) {
+        printf("msync failed: create errno %d\n", errno);
+        return 1;
+    }
+
+    // 只读打开的文件不能建立可写的共享映射
+    fd = open(PATH, O_RDONLY);
+    if (mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0) != MAP_FAILED ||
+        errno != EACCES) {
+        printf("msync failed: writable mapping of a read-only fd errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    char *map = map_file(&fd);
+    if (map == MAP_FAILED) {
+        printf("msync failed: mmap errno %d\n", errno);
+        return 1;
+    }
+
+    // 子进程单独映射同一文件，修改后用 MS_SYNC 写回
+    pid_t pid = fork();
+    if (pid == 0) {
+        int child_fd;
+        char *child = map_file(&child_fd);
+        if (child == MAP_FAILED)
+            return 2;
+        memcpy(child + PAGE + 10, "hello", 5);
+        if (msync(child, FILE_SIZE, MS_SYNC) != 0)
+            return 3;
+        munmap(child, FILE_SIZE);
+        close(child_fd);
+        return 0;
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFEXITED(status) ||
+        WEXITSTATUS(status) != 0) {
+        printf("msync failed: child status %#x\n", status);
+        return 1;
+    }
+    if (read_file(PAGE + 10, buf, 5) != 0 || memcmp(buf, "hello", 5) != 0) {
+        printf("msync failed: read after MS_SYNC got %.5s\n", buf);
+        return 1;
+    }
+    // 两个进程的共享映射看到同一份数据
+    if (memcmp(map + PAGE + 10, "hello", 5) != 0) {
+        printf("msync failed: mapping does not see the other process\n");
+        return 1;
+    }
+
+    // MS_ASYNC 之后的 MS_SYNC 保证写回完成
+    memcpy(map, "async", 5);
+    if (msync(map, PAGE, MS_ASYNC) != 0 || msync(map, PAGE, MS_SYNC) != 0) {
+        printf("msync failed: MS_ASYNC errno %d\n", errno);
+        return 1;
+    }
+    if (read_file(0, buf, 5) != 0 || memcmp(buf, "async", 5) != 0) {
+        printf("msync failed: read after MS_ASYNC got %.5s\n", buf);
+        return 1;
+    }
+
+    // 最后一页超出文件末尾的部分不会写回，文件大小不变
+    map[2 * PAGE + 200] = 'z';
+    struct stat st;
+    if (msync(map, FILE_SIZE, MS_SYNC) != 0 || stat(PATH, &st) != 0 || st.st_size != FILE_SIZE) {
+        printf("msync failed: size after writing beyond the end %ld\n", (long)st.st_size);
+        return 1;
+    }
+
+    // 用 write 修改文件后，MS_INVALIDATE 使映射看到新内容
+    int wfd = open(PATH, O_WRONLY);
+    if (wfd < 0 || lseek(wfd, 2 * PAGE, SEEK_SET) != 2 * PAGE || write(wfd, "XYZ", 3) != 3) {
+        printf("msync failed: write errno %d\n", errno);
+        return 1;
+    }
+    close(wfd);
+    if (msync(map + 2 * PAGE, PAGE, MS_INVALIDATE) != 0 ||
+        memcmp(map + 2 * PAGE, "XYZ", 3) != 0) {
+        printf("msync failed: MS_INVALIDATE errno %d\n", errno);
+        return 1;
+    }
+
+    // 参数错误
+    if (msync(map + 1, PAGE, MS_SYNC) != -1 || errno != EINVAL) {
+        printf("msync failed: misaligned address errno %d\n", errno);
+        return 1;
+    }
+    if (msync(map, PAGE, MS_SYNC | MS_ASYNC) != -1 || errno != EINVAL) {
+        printf("msync failed: MS_SYNC | MS_ASYNC errno %d\n", errno);
+        return 1;
+    }
+    munmap(map, FILE_SIZE);
+    close(fd);
+    if (msync(map, PAGE, MS_SYNC) != -1 || errno != ENOMEM) {
+        printf("msync failed: unmapped range errno %d\n", errno);
+        return 1;
+    }
+
+    unlink(PATH);
+    printf("msync passed!\n");
+    return 0;
+}
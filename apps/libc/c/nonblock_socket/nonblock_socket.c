@@ -0,0 +1,178 @@
+#define _GNU_SOURCE
+#include <arpa/inet.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <time.h>
+#include <unistd.h>
+
+// 事件循环的最长等待时间
+#define LOOP_TIMEOUT_SEC 5
+
+static struct sockaddr_in loopback(unsigned short port)
+{
+    struct sockaddr_in addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.sin_family = AF_INET;
+    addr.sin_port = htons(port);
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+    return addr;
+}
+
+// 用 ppoll 等待单个描述符上的事件，返回 revents，超时返回 0
+static int wait_fd(int fd, short events)
+{
+    struct pollfd pfd = {.fd = fd, .events = events};
+    struct timespec timeout = {.tv_sec = LOOP_TIMEOUT_SEC};
+    if (ppoll(&pfd, 1, &timeout, NULL) != 1)
+        return 0;
+    return pfd.revents;
+}
+
+// 由事件循环驱动非阻塞的 connect 与 accept，返回服务端的新连接
+static int handshake(int listener, int client)
+{
+    int server = -1, connected = 0;
+    struct timespec timeout = {.tv_sec = LOOP_TIMEOUT_SEC};
+    while (server < 0 || !connected) {
+        struct pollfd pfds[2] = {
+            {.fd = server < 0 ? listener : -1, .events = POLLIN},
+            {.fd = connected ? -1 : client, .events = POLLOUT},
+        };
+        int n = ppoll(pfds, 2, &timeout, NULL);
+        if (n <= 0) {
+            printf("nonblock_socket failed: ppoll returned %d while connecting\n", n);
+            return -1;
+        }
+        if (pfds[0].revents & POLLIN) {
+            server = accept4(listener, NULL, NULL, SOCK_NONBLOCK | SOCK_CLOEXEC);
+            if (server < 0) {
+                printf("nonblock_socket failed: accept4 after POLLIN: %d\n", errno);
+                return -1;
+            }
+        }
+        if (pfds[1].revents & POLLOUT) {
+            int err = -1;
+            socklen_t len = sizeof(err);
+            if (getsockopt(client, SOL_SOCKET, SO_ERROR, &err, &len) != 0 || err != 0) {
+                printf("nonblock_socket failed: SO_ERROR after connect: %d\n", err);
+                return -1;
+            }
+            connected = 1;
+        }
+    }
+    return server;
+}
+
+int main(void)
+{
+    int listener = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK | SOCK_CLOEXEC, 0);
+    if (listener < 0 || !(fcntl(listener, F_GETFL) & O_NONBLOCK) ||
+        !(fcntl(listener, F_GETFD) & FD_CLOEXEC)) {
+        printf("nonblock_socket failed: socket with SOCK_NONBLOCK | SOCK_CLOEXEC\n");
+        return 1;
+    }
+    struct sockaddr_in addr = loopback(0);
+    socklen_t addrlen = sizeof(addr);
+    if (bind(listener, (struct sockaddr *)&addr, sizeof(addr)) != 0 || listen(listener, 8) != 0 ||
+        getsockname(listener, (struct sockaddr *)&addr, &addrlen) != 0) {
+        printf("nonblock_socket failed: bind and listen: %d\n", errno);
+        return 1;
+    }
+
+    // 没有待接受的连接时 accept 立即返回 EAGAIN，监听套接字不可读
+    if (accept4(listener, NULL, NULL, 0) != -1 || errno != EAGAIN) {
+        printf("nonblock_socket failed: accept without a pending connection\n");
+        return 1;
+    }
+    struct pollfd pfd = {.fd = listener, .events = POLLIN};
+    struct timespec zero = {0};
+    if (ppoll(&pfd, 1, &zero, NULL) != 0 || pfd.revents != 0) {
+        printf("nonblock_socket failed: listener readable without a connection\n");
+        return 1;
+    }
+
+    // 非阻塞的 connect 立即返回 EINPROGRESS
+    int client = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (client < 0 || connect(client, (struct sockaddr *)&addr, sizeof(addr)) != -1 ||
+        errno != EINPROGRESS) {
+        printf("nonblock_socket failed: nonblocking connect: %d\n", errno);
+        return 1;
+    }
+    int server = handshake(listener, client);
+    if (server < 0)
+        return 1;
+    if (!(fcntl(server, F_GETFL) & O_NONBLOCK) || !(fcntl(server, F_GETFD) & FD_CLOEXEC)) {
+        printf("nonblock_socket failed: accept4 flags\n");
+        return 1;
+    }
+    /* 连接完成后，再次 connect 先报告一次成功，之后返回 EISCONN */
+    if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) != 0) {
+        printf("nonblock_socket failed: connect after completion: %d\n", errno);
+        return 1;
+    }
+    if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) != -1 || errno != EISCONN) {
+        printf("nonblock_socket failed: connect on a connected socket: %d\n", errno);
+        return 1;
+    }
+
+    // 没有数据时 recv 返回 EAGAIN，数据到达后可读
+    char buf[16];
+    if (recv(server, buf, sizeof(buf), 0) != -1 || errno != EAGAIN) {
+        printf("nonblock_socket failed: recv without data\n");
+        return 1;
+    }
+    if (send(client, "ping", 4, 0) != 4) {
+        printf("nonblock_socket failed: send: %d\n", errno);
+        return 1;
+    }
+    if (!(wait_fd(server, POLLIN) & POLLIN)) {
+        printf("nonblock_socket failed: server not readable after send\n");
+        return 1;
+    }
+    memset(buf, 0, sizeof(buf));
+    if (recv(server, buf, sizeof(buf), 0) != 4 || strcmp(buf, "ping") != 0) {
+        printf("nonblock_socket failed: recv after POLLIN\n");
+        return 1;
+    }
+    if (recv(server, buf, sizeof(buf), 0) != -1 || errno != EAGAIN) {
+        printf("nonblock_socket failed: recv after the data was read\n");
+        return 1;
+    }
+
+    // 连接到没有监听的端口：完成后可写，SO_ERROR 报告 ECONNREFUSED
+    close(server);
+    close(client);
+    close(listener);
+    int refused = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    int ret = connect(refused, (struct sockaddr *)&addr, sizeof(addr));
+    if (ret == 0 || (errno != EINPROGRESS && errno != ECONNREFUSED)) {
+        printf("nonblock_socket failed: connect to a closed port: %d\n", errno);
+        return 1;
+    }
+    if (errno == EINPROGRESS) {
+        if (!(wait_fd(refused, POLLOUT) & (POLLOUT | POLLERR))) {
+            printf("nonblock_socket failed: refused connect never finished\n");
+            return 1;
+        }
+        int err = 0;
+        socklen_t len = sizeof(err);
+        if (getsockopt(refused, SOL_SOCKET, SO_ERROR, &err, &len) != 0 || err != ECONNREFUSED) {
+            printf("nonblock_socket failed: SO_ERROR of a refused connect: %d\n", err);
+            return 1;
+        }
+        // SO_ERROR 读取后被清除
+        if (getsockopt(refused, SOL_SOCKET, SO_ERROR, &err, &len) != 0 || err != 0) {
+            printf("nonblock_socket failed: SO_ERROR not cleared\n");
+            return 1;
+        }
+    }
+    close(refused);
+
+    printf("nonblock_socket passed!\n");
+    return 0;
+}
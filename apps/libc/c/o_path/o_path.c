@@ -0,0 +1,110 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main(void)
+{
+    char buf[64];
+    struct stat st;
+
+    if (mkdirat(AT_FDCWD, "op_dir", 0755) != 0) {
+        printf("o_path failed: mkdir errno %d\n", errno);
+        return 1;
+    }
+    int fd = openat(AT_FDCWD, "op_dir/file", O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0 || write(fd, "hello", 5) != 5 || close(fd) != 0) {
+        printf("o_path failed: create errno %d\n", errno);
+        return 1;
+    }
+
+    // O_PATH 打开的目录可以作为 *at 系统调用的 dirfd
+    int dir = open("op_dir", O_PATH | O_DIRECTORY);
+    if (dir < 0) {
+        printf("o_path failed: open dir errno %d\n", errno);
+        return 1;
+    }
+    fd = openat(dir, "file", O_RDONLY);
+    if (fd < 0 || read(fd, buf, sizeof(buf)) != 5 || memcmp(buf, "hello", 5) != 0) {
+        printf("o_path failed: openat through O_PATH dir errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    if (fstatat(dir, "file", &st, 0) != 0 || st.st_size != 5) {
+        printf("o_path failed: fstatat through O_PATH dir errno %d\n", errno);
+        return 1;
+    }
+    if (fstat(dir, &st) != 0 || !S_ISDIR(st.st_mode)) {
+        printf("o_path failed: fstat dir errno %d\n", errno);
+        return 1;
+    }
+
+    // 目录不能被列出
+    if (syscall(SYS_getdents64, dir, buf, sizeof(buf)) != -1 || errno != EBADF) {
+        printf("o_path failed: getdents64 errno %d\n", errno);
+        return 1;
+    }
+
+    // O_PATH 打开的文件不能读写，其他标志被忽略，不会截断文件
+    int file = openat(dir, "file", O_PATH | O_RDWR | O_TRUNC);
+    if (file < 0) {
+        printf("o_path failed: open file errno %d\n", errno);
+        return 1;
+    }
+    if (read(file, buf, sizeof(buf)) != -1 || errno != EBADF) {
+        printf("o_path failed: read errno %d\n", errno);
+        return 1;
+    }
+    if (write(file, "x", 1) != -1 || errno != EBADF) {
+        printf("o_path failed: write errno %d\n", errno);
+        return 1;
+    }
+    if (fstat(file, &st) != 0 || !S_ISREG(st.st_mode) || st.st_size != 5) {
+        printf("o_path failed: fstat file size %ld errno %d\n", (long)st.st_size, errno);
+        return 1;
+    }
+    int flags = fcntl(file, F_GETFL);
+    if (flags == -1 || !(flags & O_PATH)) {
+        printf("o_path failed: F_GETFL returned %#x\n", flags);
+        return 1;
+    }
+
+    // 复制得到的描述符同样只是一个位置
+    int dup_fd = fcntl(file, F_DUPFD, 0);
+    if (dup_fd < 0 || read(dup_fd, buf, sizeof(buf)) != -1 || errno != EBADF) {
+        printf("o_path failed: F_DUPFD errno %d\n", errno);
+        return 1;
+    }
+    if (close(dup_fd) != 0 || close(file) != 0) {
+        printf("o_path failed: close errno %d\n", errno);
+        return 1;
+    }
+
+    // O_PATH | O_NOFOLLOW 打开符号链接本身
+    if (symlinkat("file", dir, "link") != 0) {
+        printf("o_path failed: symlinkat errno %d\n", errno);
+        return 1;
+    }
+    int link = openat(dir, "link", O_PATH | O_NOFOLLOW);
+    if (link < 0) {
+        printf("o_path failed: open link errno %d\n", errno);
+        return 1;
+    }
+    if (fstat(link, &st) != 0 || !S_ISLNK(st.st_mode)) {
+        printf("o_path failed: fstat link mode %o errno %d\n", st.st_mode, errno);
+        return 1;
+    }
+    close(link);
+
+    close(dir);
+    unlinkat(AT_FDCWD, "op_dir/link", 0);
+    unlinkat(AT_FDCWD, "op_dir/file", 0);
+    unlinkat(AT_FDCWD, "op_dir", AT_REMOVEDIR);
+
+    printf("o_path passed!\n");
+    return 0;
+}
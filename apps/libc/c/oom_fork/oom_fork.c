@@ -0,0 +1,127 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/sysinfo.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define MAX_CHILDREN 64
+#define CHILD_MEM (4 << 20)
+
+// 读取 /proc/meminfo 中的一项，单位为 kB，失败时返回 -1
+static long meminfo_kb(const char *name)
+{
+    FILE *f = fopen("/proc/meminfo", "r");
+    if (!f)
+        return -1;
+    char line[128];
+    long kb = -1;
+    size_t len = strlen(name);
+    while (fgets(line, sizeof(line), f)) {
+        if (strncmp(line, name, len) == 0 && line[len] == ':') {
+            sscanf(line + len + 1, "%ld", &kb);
+            break;
+        }
+    }
+    fclose(f);
+    return kb;
+}
+
+// 子进程锁定一块内存后报告结果，然后等待父进程关闭 release 管道再退出
+static void child(int ready, int release)
+{
+    char result = 'y';
+    void *mem = mmap(NULL, CHILD_MEM, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (mem == MAP_FAILED || mlock(mem, CHILD_MEM) != 0)
+        result = errno == ENOMEM || errno == EAGAIN ? 'n' : 'x';
+    else
+        memset(mem, 1, CHILD_MEM);
+    write(ready, &result, 1);
+    char c;
+    read(release, &c, 1);
+    _exit(0);
+}
+
+int main()
+{
+    struct sysinfo info;
+    if (sysinfo(&info) != 0 || info.totalram == 0 || info.freeram > info.totalram) {
+        printf("oom_fork failed: sysinfo reports no memory\n");
+        return 1;
+    }
+    long total = meminfo_kb("MemTotal");
+    long free = meminfo_kb("MemFree");
+    long available = meminfo_kb("MemAvailable");
+    if (total <= 0 || free < 0 || free > total || available < 0) {
+        printf("oom_fork failed: /proc/meminfo is wrong\n");
+        return 1;
+    }
+    if ((unsigned long long)total * 1024 != (unsigned long long)info.totalram * info.mem_unit) {
+        printf("oom_fork failed: MemTotal %ld kB differs from sysinfo\n", total);
+        return 1;
+    }
+
+    // 远超物理内存的可写私有映射无法满足
+    void *huge = mmap(NULL, 1UL << 40, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (huge != MAP_FAILED || errno != ENOMEM) {
+        printf("oom_fork failed: a 1 TiB mapping did not fail with ENOMEM\n");
+        return 1;
+    }
+
+    // 不断创建占用内存的子进程，直到 fork 或子进程分配内存失败
+    int ready[2], release[2];
+    if (pipe(ready) != 0 || pipe(release) != 0) {
+        printf("oom_fork failed: pipe returned an error\n");
+        return 1;
+    }
+    int children = 0;
+    while (children < MAX_CHILDREN) {
+        pid_t pid = fork();
+        if (pid < 0) {
+            if (errno != ENOMEM && errno != EAGAIN) {
+                printf("oom_fork failed: fork failed with errno %d\n", errno);
+                return 1;
+            }
+            break;
+        }
+        if (pid == 0) {
+            close(ready[0]);
+            close(release[1]);
+            child(ready[1], release[0]);
+        }
+        children++;
+        char result;
+        if (read(ready[0], &result, 1) != 1 || result == 'x') {
+            printf("oom_fork failed: child %d failed to allocate without ENOMEM\n", children);
+            return 1;
+        }
+        if (result == 'n')
+            break;
+    }
+
+    // 释放所有子进程，之后内存回收，fork 重新可用
+    close(release[1]);
+    for (int i = 0; i < children; i++) {
+        int status;
+        if (wait(&status) < 0 || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+            printf("oom_fork failed: child did not exit normally\n");
+            return 1;
+        }
+    }
+    pid_t pid = fork();
+    if (pid < 0) {
+        printf("oom_fork failed: fork after the children exited\n");
+        return 1;
+    }
+    if (pid == 0)
+        _exit(7);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 7) {
+        printf("oom_fork failed: wrong status of the last child\n");
+        return 1;
+    }
+
+    printf("oom_fork passed!\n");
+    return 0;
+}
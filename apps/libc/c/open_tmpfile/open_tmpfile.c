@@ -0,0 +1,134 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR_PATH "open_tmpfile_dir"
+#define LINK_PATH DIR_PATH "/named"
+
+// 统计目录中除 . 和 .. 以外的项数
+static int count_entries(const char *path)
+{
+    DIR *dir = opendir(path);
+    if (!dir)
+        return -1;
+    int n = 0;
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (strcmp(entry->d_name, ".") != 0 && strcmp(entry->d_name, "..") != 0)
+            n++;
+    }
+    closedir(dir);
+    return n;
+}
+
+int main(void)
+{
+    char buf[16];
+    mkdir(DIR_PATH, 0755);
+    unlink(LINK_PATH);
+    if (count_entries(DIR_PATH) != 0) {
+        printf("open_tmpfile failed: directory not empty\n");
+        return 1;
+    }
+
+    // O_TMPFILE 创建的文件没有名字，不出现在目录中
+    int fd = open(DIR_PATH, O_TMPFILE | O_RDWR, 0600);
+    if (fd < 0 || write(fd, "hello", 5) != 5) {
+        printf("open_tmpfile failed: O_TMPFILE errno %d\n", errno);
+        return 1;
+    }
+    if (count_entries(DIR_PATH) != 0) {
+        printf("open_tmpfile failed: unnamed file is listed\n");
+        return 1;
+    }
+
+    // 用 linkat 的 AT_EMPTY_PATH 给它一个名字，之后通过名字和描述符访问的是同一个文件
+    if (linkat(fd, "", AT_FDCWD, LINK_PATH, AT_EMPTY_PATH) != 0) {
+        printf("open_tmpfile failed: linkat errno %d\n", errno);
+        return 1;
+    }
+    if (count_entries(DIR_PATH) != 1 || write(fd, " world", 6) != 6) {
+        printf("open_tmpfile failed: after linkat errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    int named = open(LINK_PATH, O_RDONLY);
+    memset(buf, 0, sizeof(buf));
+    if (named < 0 || read(named, buf, sizeof(buf)) != 11 || strcmp(buf, "hello world") != 0) {
+        printf("open_tmpfile failed: read back \"%s\" errno %d\n", buf, errno);
+        return 1;
+    }
+    close(named);
+
+    // 名字已存在时 linkat 失败
+    fd = open(DIR_PATH, O_TMPFILE | O_WRONLY, 0600);
+    if (fd < 0 || linkat(fd, "", AT_FDCWD, LINK_PATH, AT_EMPTY_PATH) != -1 || errno != EEXIST) {
+        printf("open_tmpfile failed: link to an existing name errno %d\n", errno);
+        return 1;
+    }
+    // 没有链接的文件在关闭后被删除
+    close(fd);
+    if (count_entries(DIR_PATH) != 1) {
+        printf("open_tmpfile failed: unlinked file left behind\n");
+        return 1;
+    }
+
+    // 带 O_EXCL 的匿名文件不能被链接
+    fd = open(DIR_PATH, O_TMPFILE | O_EXCL | O_RDWR, 0600);
+    if (fd < 0 || linkat(fd, "", AT_FDCWD, DIR_PATH "/excl", AT_EMPTY_PATH) != -1 ||
+        errno != ENOENT) {
+        printf("open_tmpfile failed: O_EXCL linkat errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    // 无效的 O_TMPFILE 用法
+    errno = 0;
+    if (open(DIR_PATH, O_TMPFILE | O_RDONLY, 0600) != -1 || errno != EINVAL) {
+        printf("open_tmpfile failed: read-only O_TMPFILE errno %d\n", errno);
+        return 1;
+    }
+    errno = 0;
+    if (open(LINK_PATH, O_TMPFILE | O_RDWR, 0600) != -1 || errno != ENOTDIR) {
+        printf("open_tmpfile failed: O_TMPFILE on a file errno %d\n", errno);
+        return 1;
+    }
+
+    // O_CREAT | O_EXCL 不能打开已有的文件
+    errno = 0;
+    if (open(LINK_PATH, O_CREAT | O_EXCL | O_RDWR, 0644) != -1 || errno != EEXIST) {
+        printf("open_tmpfile failed: O_EXCL on an existing file errno %d\n", errno);
+        return 1;
+    }
+
+    // O_CLOEXEC 设置新描述符的 close-on-exec 标志，重用同一个描述符号时不会残留
+    fd = open(LINK_PATH, O_RDONLY | O_CLOEXEC);
+    if (fd < 0 || fcntl(fd, F_GETFD) != FD_CLOEXEC) {
+        printf("open_tmpfile failed: O_CLOEXEC errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    int again = open(LINK_PATH, O_RDONLY);
+    if (again != fd || fcntl(again, F_GETFD) != 0) {
+        printf("open_tmpfile failed: stale close-on-exec flag\n");
+        return 1;
+    }
+    close(again);
+
+    // umask 返回原先的掩码
+    umask(027);
+    if (umask(022) != 027) {
+        printf("open_tmpfile failed: umask\n");
+        return 1;
+    }
+
+    unlink(LINK_PATH);
+    rmdir(DIR_PATH);
+    printf("open_tmpfile passed!\n");
+    return 0;
+}
@@ -0,0 +1,160 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define FILE_SIZE (256 * 1024)
+#define MNT "/page_cache_mnt"
+
+struct cache_stats {
+    long capacity;
+    long hits;
+    long misses;
+};
+
+static char buf[FILE_SIZE];
+
+static unsigned char pattern(long pos, int seed)
+{
+    return (unsigned char)(pos * 131 + pos / 4096 + seed);
+}
+
+// 读取 /proc/blockcache 中的计数
+static int read_stats(struct cache_stats *stats)
+{
+    FILE *f = fopen("/proc/blockcache", "r");
+    if (!f)
+        return -1;
+    char line[64];
+    long value;
+    memset(stats, 0, sizeof(*stats));
+    while (fgets(line, sizeof(line), f)) {
+        if (sscanf(line, "Capacity: %ld", &value) == 1)
+            stats->capacity = value;
+        else if (sscanf(line, "Hits: %ld", &value) == 1)
+            stats->hits = value;
+        else if (sscanf(line, "Misses: %ld", &value) == 1)
+            stats->misses = value;
+    }
+    fclose(f);
+    return 0;
+}
+
+// 以 4 KiB 为单位写入 size 字节的文件，写入直接到达磁盘
+static int write_file(const char *path, long size, int seed)
+{
+    int fd = open(path, O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0)
+        return -1;
+    for (long i = 0; i < size; i++)
+        buf[i] = pattern(i, seed);
+    for (long off = 0; off < size; off += 4096) {
+        if (write(fd, buf + off, 4096) != 4096) {
+            close(fd);
+            return -1;
+        }
+    }
+    return close(fd);
+}
+
+// 以 chunk 字节为单位顺序读取 size 字节的文件并检查内容，返回不一致的位置，一致时返回 -1
+static long check_file(const char *path, long size, int seed, long chunk)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return 0;
+    long total = 0;
+    ssize_t n;
+    memset(buf, 0, sizeof(buf));
+    while (total < size && (n = read(fd, buf + total, chunk)) > 0)
+        total += n;
+    close(fd);
+    for (long i = 0; i < size; i++) {
+        if (i >= total || (unsigned char)buf[i] != pattern(i, seed))
+            return i;
+    }
+    return -1;
+}
+
+int main(void)
+{
+    const char *path = "page_cache.dat";
+    long bad;
+
+    // 写入后用不同大小的读取顺序读回
+    if (write_file(path, FILE_SIZE, 1) != 0) {
+        printf("page_cache failed: write %s\n", path);
+        return 1;
+    }
+    struct cache_stats before, after;
+    if (read_stats(&before) != 0) {
+        printf("page_cache failed: cannot read /proc/blockcache\n");
+        return 1;
+    }
+    long chunks[] = {1000, 4096, 65536, 777};
+    for (int i = 0; i < 4; i++) {
+        if ((bad = check_file(path, FILE_SIZE, 1, chunks[i])) >= 0) {
+            printf("page_cache failed: data differs at %ld reading %ld bytes at a time\n", bad,
+                   chunks[i]);
+            return 1;
+        }
+    }
+    read_stats(&after);
+    // 文件系统逐个扇区读取，同一页中除第一个扇区外都应命中缓存
+    if (before.capacity > 0 && (after.hits - before.hits < after.misses - before.misses ||
+                                after.hits + after.misses == before.hits + before.misses)) {
+        printf("page_cache failed: %ld hits and %ld misses\n", after.hits - before.hits,
+               after.misses - before.misses);
+        return 1;
+    }
+
+    // 覆盖写入中间的一部分后读回
+    int fd = open(path, O_WRONLY);
+    memset(buf, 0x5a, 10000);
+    if (fd < 0 || lseek(fd, 12345, SEEK_SET) != 12345 || write(fd, buf, 10000) != 10000) {
+        printf("page_cache failed: overwrite\n");
+        return 1;
+    }
+    close(fd);
+    fd = open(path, O_RDONLY);
+    if (fd < 0 || read(fd, buf, FILE_SIZE) != FILE_SIZE) {
+        printf("page_cache failed: read after overwrite\n");
+        return 1;
+    }
+    close(fd);
+    for (long i = 0; i < FILE_SIZE; i++) {
+        unsigned char expected = i >= 12345 && i < 22345 ? 0x5a : pattern(i, 1);
+        if ((unsigned char)buf[i] != expected) {
+            printf("page_cache failed: data differs at %ld after overwrite\n", i);
+            return 1;
+        }
+    }
+    unlink(path);
+
+    // 卸载并重新挂载后，数据与写入的一致。第二块磁盘上的文件系统只有 50 KiB
+    if (mount("/dev/vdb", MNT, "vfat", 0, NULL) != 0) {
+        printf("page_cache failed: mount /dev/vdb\n");
+        return 1;
+    }
+    if (write_file(MNT "/page_cache.dat", 16384, 2) != 0) {
+        printf("page_cache failed: write on the mounted filesystem\n");
+        return 1;
+    }
+    if (umount(MNT) != 0 || mount("/dev/vdb", MNT, "vfat", 0, NULL) != 0) {
+        printf("page_cache failed: remount\n");
+        return 1;
+    }
+    if ((bad = check_file(MNT "/page_cache.dat", 16384, 2, 4096)) >= 0) {
+        printf("page_cache failed: data differs at %ld after remount\n", bad);
+        return 1;
+    }
+    unlink(MNT "/page_cache.dat");
+    umount(MNT);
+    rmdir(MNT);
+
+    printf("page_cache passed!\n");
+    return 0;
+}
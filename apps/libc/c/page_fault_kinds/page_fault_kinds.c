@@ -0,0 +1,113 @@
+#define _GNU_SOURCE
+#include <setjmp.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define PAGE 4096
+
+static sigjmp_buf env;
+static volatile int got_signo;
+static volatile int got_code;
+static void *volatile got_addr;
+
+static void handler(int signo, siginfo_t *info, void *ucontext)
+{
+    (void)ucontext;
+    got_signo = signo;
+    got_code = info->si_code;
+    got_addr = info->si_addr;
+    siglongjmp(env, 1);
+}
+
+// 访问 addr，返回收到的信号，没有收到信号时返回 0
+static int touch(volatile char *addr, int write)
+{
+    got_signo = 0;
+    if (sigsetjmp(env, 1) == 0) {
+        if (write)
+            *addr = 1;
+        else
+            (void)*addr;
+    }
+    return got_signo;
+}
+
+static int expect(const char *what, volatile char *addr, int write, int signo, int code)
+{
+    if (touch(addr, write) != signo) {
+        printf("page_fault_kinds failed: %s: got signal %d, expected %d\n", what, got_signo, signo);
+        return 1;
+    }
+    if (signo && (got_code != code || got_addr != (void *)addr)) {
+        printf("page_fault_kinds failed: %s: si_code %d si_addr %p\n", what, got_code, got_addr);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_sigaction = handler;
+    sa.sa_flags = SA_SIGINFO;
+    if (sigaction(SIGSEGV, &sa, NULL) != 0 || sigaction(SIGBUS, &sa, NULL) != 0) {
+        printf("page_fault_kinds failed: sigaction\n");
+        return 1;
+    }
+
+    // 匿名映射在首次访问时分配页面
+    char *anon = mmap(NULL, 4 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (anon == MAP_FAILED) {
+        printf("page_fault_kinds failed: mmap anonymous\n");
+        return 1;
+    }
+    if (expect("lazy read", anon + PAGE, 0, 0, 0) || expect("lazy write", anon + 2 * PAGE + 7, 1, 0, 0))
+        return 1;
+    if (anon[PAGE] != 0 || anon[2 * PAGE + 7] != 1) {
+        printf("page_fault_kinds failed: lazily allocated page has wrong contents\n");
+        return 1;
+    }
+
+    // 访问已解除映射的地址
+    if (munmap(anon + 3 * PAGE, PAGE) != 0 ||
+        expect("unmapped", anon + 3 * PAGE, 0, SIGSEGV, SEGV_MAPERR))
+        return 1;
+
+    // 写只读的映射，不论页面是否已经分配
+    char *ro = mmap(NULL, 2 * PAGE, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (ro == MAP_FAILED) {
+        printf("page_fault_kinds failed: mmap read-only\n");
+        return 1;
+    }
+    if (expect("read read-only", ro + PAGE + 7, 0, 0, 0) ||
+        expect("write read-only", ro + PAGE, 1, SIGSEGV, SEGV_ACCERR) ||
+        expect("write read-only lazy", ro, 1, SIGSEGV, SEGV_ACCERR))
+        return 1;
+
+    // 没有任何权限的映射
+    char *none = mmap(NULL, PAGE, PROT_NONE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (none == MAP_FAILED || expect("PROT_NONE", none, 0, SIGSEGV, SEGV_ACCERR))
+        return 1;
+
+    // 共享映射超出 memfd 末尾的部分没有页面
+    int fd = memfd_create("page_fault_kinds", 0);
+    if (fd < 0 || ftruncate(fd, PAGE) != 0) {
+        printf("page_fault_kinds failed: memfd\n");
+        return 1;
+    }
+    char *shared = mmap(NULL, 2 * PAGE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (shared == MAP_FAILED) {
+        printf("page_fault_kinds failed: mmap memfd\n");
+        return 1;
+    }
+    if (expect("within file", shared + PAGE - 1, 1, 0, 0) ||
+        expect("beyond end of file", shared + PAGE, 0, SIGBUS, BUS_ADRERR))
+        return 1;
+
+    printf("page_fault_kinds passed!\n");
+    return 0;
+}
@@ -0,0 +1,73 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/personality.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#ifndef ADDR_NO_RANDOMIZE
+#define ADDR_NO_RANDOMIZE 0x0040000
+#endif
+
+// 子进程：确认执行域被继承，并把匿名映射的地址写到标准输出
+static int child(void)
+{
+    if (!(personality(0xffffffff) & ADDR_NO_RANDOMIZE))
+        return 1;
+    void *p = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p == MAP_FAILED)
+        return 1;
+    printf("%p", p);
+    return 0;
+}
+
+// 在子进程中重新执行自身，返回其输出的映射地址
+static int run_child(const char *self, char *out, size_t len)
+{
+    int fds[2];
+    if (pipe(fds) < 0)
+        return -1;
+    pid_t pid = fork();
+    if (pid == 0) {
+        close(fds[0]);
+        dup2(fds[1], STDOUT_FILENO);
+        char *argv[] = {(char *)self, "child", NULL};
+        execve(self, argv, NULL);
+        _exit(1);
+    }
+    close(fds[1]);
+    ssize_t n = read(fds[0], out, len - 1);
+    close(fds[0]);
+    int status;
+    waitpid(pid, &status, 0);
+    if (n <= 0 || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return -1;
+    out[n] = '\0';
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc > 1 && strcmp(argv[1], "child") == 0)
+        return child();
+
+    int old = personality(ADDR_NO_RANDOMIZE);
+    if (old != 0 || personality(0xffffffff) != ADDR_NO_RANDOMIZE) {
+        printf("personality failed: unexpected persona %#x\n", old);
+        return 1;
+    }
+
+    char first[32], second[32];
+    if (run_child(argv[0], first, sizeof(first)) < 0 ||
+        run_child(argv[0], second, sizeof(second)) < 0) {
+        printf("personality failed: child did not run\n");
+        return 1;
+    }
+    if (strcmp(first, second) != 0) {
+        printf("personality failed: mmap at %s then %s\n", first, second);
+        return 1;
+    }
+
+    printf("personality passed!\n");
+    return 0;
+}
@@ -0,0 +1,54 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// musl 的 sched_getscheduler 总是返回 ENOSYS，这里直接发起系统调用
+static long lookup(pid_t pid)
+{
+    return syscall(SYS_sched_getscheduler, pid);
+}
+
+int main()
+{
+    pid_t self = getpid();
+    pid_t pid = fork();
+    if (pid == 0) {
+        // 子进程看到的父进程号应与父进程的 getpid 一致
+        return getppid() == self ? 0 : 1;
+    }
+
+    // 尚未回收的子进程可以被查到
+    if (lookup(pid) < 0) {
+        printf("pid_table failed: live pid %d not found\n", pid);
+        return 1;
+    }
+
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("pid_table failed: getppid mismatch in child\n");
+        return 1;
+    }
+
+    // 回收后的进程号查找失败
+    errno = 0;
+    if (lookup(pid) != -1 || errno != ESRCH) {
+        printf("pid_table failed: reaped pid %d still found\n", pid);
+        return 1;
+    }
+
+    // 进程号不会被复用
+    pid_t next = fork();
+    if (next == 0)
+        return 0;
+    waitpid(next, NULL, 0);
+    if (next <= pid) {
+        printf("pid_table failed: pid %d reused after %d\n", next, pid);
+        return 1;
+    }
+
+    printf("pid_table passed!\n");
+    return 0;
+}
@@ -0,0 +1,164 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/uio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+// 远大于管道容量的写入长度
+#define TOTAL (1 << 20)
+
+static volatile int caught;
+
+static void handler(int signo)
+{
+    (void)signo;
+    caught++;
+}
+
+static unsigned char pattern(size_t i)
+{
+    return (unsigned char)(i * 7 % 251);
+}
+
+// fork 一个子进程，睡眠 ms 毫秒后向父进程发送 SIGUSR1
+static pid_t signal_later(int ms)
+{
+    pid_t parent = getpid();
+    pid_t pid = fork();
+    if (pid == 0) {
+        struct timespec ts = {ms / 1000, (ms % 1000) * 1000000L};
+        nanosleep(&ts, NULL);
+        kill(parent, SIGUSR1);
+        _exit(0);
+    }
+    return pid;
+}
+
+static int reap(pid_t pid, const char *what)
+{
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFEXITED(status) ||
+        WEXITSTATUS(status) != 0) {
+        printf("pipe_eintr failed: %s\n", what);
+        return 1;
+    }
+    return 0;
+}
+
+// 读出管道中的全部数据并检查内容，读到 TOTAL 字节且内容正确时以 0 退出
+static void reader(int fd)
+{
+    static unsigned char buf[4096];
+    size_t total = 0;
+    for (;;) {
+        ssize_t n = read(fd, buf, sizeof(buf));
+        if (n < 0)
+            _exit(2);
+        if (n == 0)
+            break;
+        for (ssize_t i = 0; i < n; i++) {
+            if (buf[i] != pattern(total + i))
+                _exit(3);
+        }
+        total += n;
+    }
+    _exit(total == TOTAL ? 0 : 4);
+}
+
+int main(void)
+{
+    // 不设置 SA_RESTART，被打断的系统调用不会重新执行
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = handler;
+    sigemptyset(&sa.sa_mask);
+    if (sigaction(SIGUSR1, &sa, NULL) != 0) {
+        printf("pipe_eintr failed: sigaction errno %d\n", errno);
+        return 1;
+    }
+
+    unsigned char *data = malloc(TOTAL);
+    if (data == NULL) {
+        printf("pipe_eintr failed: malloc\n");
+        return 1;
+    }
+    for (size_t i = 0; i < TOTAL; i++)
+        data[i] = pattern(i);
+
+    int fds[2];
+    if (pipe(fds) != 0) {
+        printf("pipe_eintr failed: pipe errno %d\n", errno);
+        return 1;
+    }
+
+    // readv 读到管道中已有的数据后返回，不等待填满所有缓冲区
+    char a[8], b[8];
+    struct iovec iov[2] = {{a, sizeof(a)}, {b, sizeof(b)}};
+    if (write(fds[1], "0123456789", 10) != 10 || readv(fds[0], iov, 2) != 10 ||
+        memcmp(a, "01234567", 8) != 0 || memcmp(b, "89", 2) != 0) {
+        printf("pipe_eintr failed: short readv\n");
+        return 1;
+    }
+    // 第一个缓冲区读满后管道已空，readv 不再等待
+    if (write(fds[1], "01234567", 8) != 8 || readv(fds[0], iov, 2) != 8) {
+        printf("pipe_eintr failed: readv waited for more data\n");
+        return 1;
+    }
+
+    // 空管道上的读取在读到数据前被打断，返回 EINTR
+    pid_t pid = signal_later(100);
+    char c;
+    if (read(fds[0], &c, 1) != -1 || errno != EINTR || caught != 1) {
+        printf("pipe_eintr failed: read not interrupted (caught %d)\n", caught);
+        return 1;
+    }
+    if (reap(pid, "signal sender"))
+        return 1;
+
+    // 写满管道后被打断，返回已写入的字节数
+    pid = signal_later(100);
+    ssize_t written = write(fds[1], data, TOTAL);
+    if (written <= 0 || written >= TOTAL || caught != 2) {
+        printf("pipe_eintr failed: partial write returned %zd errno %d (caught %d)\n", written,
+               errno, caught);
+        return 1;
+    }
+    if (reap(pid, "signal sender"))
+        return 1;
+
+    // 管道已满，一个字节也没有写入时被打断，返回 EINTR
+    pid = signal_later(100);
+    if (write(fds[1], data + written, TOTAL - written) != -1 || errno != EINTR || caught != 3) {
+        printf("pipe_eintr failed: full pipe write not interrupted (caught %d)\n", caught);
+        return 1;
+    }
+    if (reap(pid, "signal sender"))
+        return 1;
+
+    // 从中断处继续写入剩余的数据，读者收到完整且有序的数据
+    pid_t rpid = fork();
+    if (rpid == 0) {
+        close(fds[1]);
+        reader(fds[0]);
+    }
+    close(fds[0]);
+    size_t done = written;
+    while (done < TOTAL) {
+        ssize_t n = write(fds[1], data + done, TOTAL - done);
+        if (n <= 0) {
+            printf("pipe_eintr failed: resumed write returned %zd errno %d\n", n, errno);
+            return 1;
+        }
+        done += n;
+    }
+    close(fds[1]);
+    if (reap(rpid, "reader got wrong data"))
+        return 1;
+
+    printf("pipe_eintr passed!\n");
+    return 0;
+}
@@ -0,0 +1,141 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define FILE_NAME "poll_hup_file"
+
+// 以 timeout 毫秒检查 fd 上的 events，返回 revents，出错时返回 -1
+static int poll_one(int fd, short events, int timeout)
+{
+    struct pollfd pfd = {.fd = fd, .events = events};
+    int n = poll(&pfd, 1, timeout);
+    if (n < 0)
+        return -1;
+    if (n != (pfd.revents != 0))
+        return -1;
+    return pfd.revents;
+}
+
+static int check_pipe(void)
+{
+    int fds[2];
+    if (pipe(fds) != 0) {
+        printf("poll_hup failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    // 空管道不可读，写端可写
+    int r = poll_one(fds[0], POLLIN, 0);
+    int w = poll_one(fds[1], POLLOUT, 0);
+    if (r != 0 || w != POLLOUT) {
+        printf("poll_hup failed: empty pipe %#x %#x\n", r, w);
+        return 1;
+    }
+
+    // 写端关闭后缓冲区中仍有数据：既可读又挂断
+    if (write(fds[1], "abc", 3) != 3 || close(fds[1]) != 0) {
+        printf("poll_hup failed: write errno %d\n", errno);
+        return 1;
+    }
+    r = poll_one(fds[0], POLLIN, 0);
+    if (r != (POLLIN | POLLHUP)) {
+        printf("poll_hup failed: closed pipe with data %#x\n", r);
+        return 1;
+    }
+    // 没有请求的事件时仍报告 POLLHUP
+    r = poll_one(fds[0], 0, 0);
+    if (r != POLLHUP) {
+        printf("poll_hup failed: POLLHUP not requested %#x\n", r);
+        return 1;
+    }
+    // 读完数据后只剩挂断，读到文件末尾
+    char buf[8];
+    if (read(fds[0], buf, sizeof(buf)) != 3) {
+        printf("poll_hup failed: read errno %d\n", errno);
+        return 1;
+    }
+    r = poll_one(fds[0], POLLIN, 0);
+    if (r != POLLHUP || read(fds[0], buf, sizeof(buf)) != 0) {
+        printf("poll_hup failed: drained pipe %#x\n", r);
+        return 1;
+    }
+    close(fds[0]);
+
+    // 读端关闭后写端报告 POLLERR
+    if (pipe(fds) != 0) {
+        printf("poll_hup failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    close(fds[0]);
+    w = poll_one(fds[1], POLLOUT, 0);
+    if (w != (POLLOUT | POLLERR)) {
+        printf("poll_hup failed: pipe without reader %#x\n", w);
+        return 1;
+    }
+    close(fds[1]);
+    return 0;
+}
+
+static int check_file(void)
+{
+    // 普通文件总是既可读又可写，与打开方式无关
+    int fd = open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0) {
+        printf("poll_hup failed: open errno %d\n", errno);
+        return 1;
+    }
+    int rw = poll_one(fd, POLLIN | POLLOUT, 0);
+    close(fd);
+    fd = open(FILE_NAME, O_RDONLY);
+    int ro = poll_one(fd, POLLIN | POLLOUT, 0);
+    close(fd);
+    unlink(FILE_NAME);
+    if (rw != (POLLIN | POLLOUT) || ro != (POLLIN | POLLOUT)) {
+        printf("poll_hup failed: regular file %#x %#x\n", rw, ro);
+        return 1;
+    }
+    return 0;
+}
+
+// 阻塞的 poll 在另一个进程写入管道或关闭写端后返回
+static int check_blocking(void)
+{
+    int fds[2];
+    if (pipe(fds) != 0) {
+        printf("poll_hup failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    pid_t pid = fork();
+    if (pid == 0) {
+        close(fds[0]);
+        struct timespec ts = {0, 50 * 1000 * 1000};
+        nanosleep(&ts, NULL);
+        write(fds[1], "x", 1);
+        nanosleep(&ts, NULL);
+        _exit(0);
+    }
+    close(fds[1]);
+    int r1 = poll_one(fds[0], POLLIN, -1);
+    char c;
+    read(fds[0], &c, 1);
+    int r2 = poll_one(fds[0], POLLIN, -1);
+    int status;
+    waitpid(pid, &status, 0);
+    close(fds[0]);
+    if (r1 != POLLIN || r2 != POLLHUP) {
+        printf("poll_hup failed: blocking poll %#x %#x\n", r1, r2);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    if (check_pipe() || check_file() || check_blocking())
+        return 1;
+    printf("poll_hup passed!\n");
+    return 0;
+}
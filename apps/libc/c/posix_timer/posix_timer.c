@@ -0,0 +1,111 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile int expirations;
+static volatile int bad_info;
+
+static void alarm_handler(int sig, siginfo_t *info, void *ucontext)
+{
+    (void)sig;
+    (void)ucontext;
+    if (info->si_code != SI_TIMER || info->si_value.sival_int != 42)
+        bad_info = 1;
+    expirations++;
+}
+
+int main()
+{
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_sigaction = alarm_handler;
+    sa.sa_flags = SA_SIGINFO;
+    sigaction(SIGALRM, &sa, NULL);
+
+    sigset_t block, old;
+    sigemptyset(&block);
+    sigaddset(&block, SIGALRM);
+    sigprocmask(SIG_BLOCK, &block, &old);
+
+    // 10ms 的周期定时器，数到 10 次
+    struct sigevent sev;
+    memset(&sev, 0, sizeof(sev));
+    sev.sigev_notify = SIGEV_SIGNAL;
+    sev.sigev_signo = SIGALRM;
+    sev.sigev_value.sival_int = 42;
+    timer_t timer;
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) != 0) {
+        printf("posix_timer failed: timer_create\n");
+        return 1;
+    }
+    struct itimerspec its = {{0, 10000000}, {0, 10000000}};
+    if (timer_settime(timer, 0, &its, NULL) != 0) {
+        printf("posix_timer failed: timer_settime\n");
+        return 1;
+    }
+    while (expirations < 10)
+        sigsuspend(&old);
+    if (bad_info) {
+        printf("posix_timer failed: unexpected siginfo\n");
+        return 1;
+    }
+
+    struct itimerspec cur;
+    if (timer_gettime(timer, &cur) != 0 || cur.it_interval.tv_nsec != 10000000 ||
+        cur.it_value.tv_sec != 0 || cur.it_value.tv_nsec > 10000000) {
+        printf("posix_timer failed: timer_gettime\n");
+        return 1;
+    }
+
+    // 信号被阻塞期间错过的到期被计入 overrun
+    usleep(50000);
+    int count = expirations;
+    sigsuspend(&old);
+    if (expirations != count + 1 || timer_getoverrun(timer) < 1) {
+        printf("posix_timer failed: overrun=%d\n", timer_getoverrun(timer));
+        return 1;
+    }
+
+    // 删除定时器时丢弃尚未递送的信号
+    usleep(20000);
+    if (timer_delete(timer) != 0) {
+        printf("posix_timer failed: timer_delete\n");
+        return 1;
+    }
+    sigset_t pending;
+    sigpending(&pending);
+    if (sigismember(&pending, SIGALRM)) {
+        printf("posix_timer failed: expiration still pending after delete\n");
+        return 1;
+    }
+    errno = 0;
+    if (timer_gettime(timer, &cur) != -1 || errno != EINVAL) {
+        printf("posix_timer failed: deleted timer still exists\n");
+        return 1;
+    }
+
+    // 一次性定时器到期后停止
+    if (timer_create(CLOCK_REALTIME, NULL, &timer) != 0) {
+        printf("posix_timer failed: timer_create with NULL sevp\n");
+        return 1;
+    }
+    struct itimerspec once = {{0, 0}, {0, 1000000}};
+    timer_settime(timer, 0, &once, NULL);
+    siginfo_t info;
+    if (sigwaitinfo(&block, &info) != SIGALRM || info.si_code != SI_TIMER) {
+        printf("posix_timer failed: one-shot timer did not fire\n");
+        return 1;
+    }
+    timer_gettime(timer, &cur);
+    if (cur.it_value.tv_sec != 0 || cur.it_value.tv_nsec != 0) {
+        printf("posix_timer failed: one-shot timer still armed\n");
+        return 1;
+    }
+    timer_delete(timer);
+
+    printf("posix_timer passed!\n");
+    return 0;
+}
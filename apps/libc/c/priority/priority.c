@@ -0,0 +1,135 @@
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define _LINUX_CAPABILITY_VERSION_3 0x20080522
+#define CAP_SYS_NICE 23
+
+struct cap_header {
+    uint32_t version;
+    int pid;
+};
+
+struct cap_data {
+    uint32_t effective;
+    uint32_t permitted;
+    uint32_t inheritable;
+};
+
+static double now(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec + ts.tv_nsec / 1e9;
+}
+
+// 子进程：丢弃 CAP_SYS_NICE 后只能提高 nice 值
+static int unprivileged_child(void)
+{
+    struct cap_header hdr = {_LINUX_CAPABILITY_VERSION_3, 0};
+    struct cap_data data[2];
+    if (syscall(SYS_capget, &hdr, data) != 0)
+        return 1;
+    data[0].effective &= ~(1u << CAP_SYS_NICE);
+    data[0].permitted &= ~(1u << CAP_SYS_NICE);
+    if (syscall(SYS_capset, &hdr, data) != 0)
+        return 2;
+    if (setpriority(PRIO_PROCESS, 0, 10) != 0)
+        return 3;
+    errno = 0;
+    if (setpriority(PRIO_PROCESS, 0, 5) != -1 || errno != EACCES)
+        return 4;
+    if (nice(1) != 11)
+        return 5;
+    return 0;
+}
+
+// 以 `prio` 运算到 `deadline`，返回前不做任何系统调用以外的让出
+static void spin_child(int prio, double deadline)
+{
+    setpriority(PRIO_PROCESS, 0, prio);
+    while (now() < deadline) {
+        for (volatile int i = 0; i < 10000; i++) {
+        }
+    }
+    _exit(0);
+}
+
+// 回收子进程，返回它在用户态的运行时间（秒）
+static double reap_utime(pid_t pid)
+{
+    struct rusage before, after;
+    getrusage(RUSAGE_CHILDREN, &before);
+    waitpid(pid, NULL, 0);
+    getrusage(RUSAGE_CHILDREN, &after);
+    return (after.ru_utime.tv_sec - before.ru_utime.tv_sec) +
+           (after.ru_utime.tv_usec - before.ru_utime.tv_usec) / 1e6;
+}
+
+int main()
+{
+    if (getpriority(PRIO_PROCESS, 0) != 0) {
+        printf("priority failed: initial nice is not 0\n");
+        return 1;
+    }
+    // 系统调用本身返回 20 - nice，不会被当作错误
+    if (setpriority(PRIO_PROCESS, getpid(), 5) != 0 || syscall(SYS_getpriority, PRIO_PROCESS, 0) != 15) {
+        printf("priority failed: raw getpriority\n");
+        return 1;
+    }
+    // 超出范围的值被截断
+    setpriority(PRIO_PROCESS, 0, 100);
+    if (getpriority(PRIO_PROCESS, 0) != 19) {
+        printf("priority failed: nice is not clamped to 19\n");
+        return 1;
+    }
+    errno = 0;
+    if (setpriority(3, 0, 0) != -1 || errno != EINVAL) {
+        printf("priority failed: invalid which\n");
+        return 1;
+    }
+    errno = 0;
+    if (getpriority(PRIO_PROCESS, 0x7fffffff) != -1 || errno != ESRCH) {
+        printf("priority failed: missing process\n");
+        return 1;
+    }
+
+    // 拥有 CAP_SYS_NICE 时可以降低 nice 值，子进程继承 nice 值
+    if (setpriority(PRIO_PROCESS, 0, -5) != 0) {
+        printf("priority failed: privileged process cannot lower nice\n");
+        return 1;
+    }
+    pid_t pid = fork();
+    if (pid == 0)
+        _exit(getpriority(PRIO_PROCESS, 0) == -5 ? unprivileged_child() : 9);
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("priority failed: unprivileged child step %d\n", WEXITSTATUS(status));
+        return 1;
+    }
+
+    // 两个同时运算的子进程，nice 值为 10 的应得到明显更少的 CPU 时间
+    double deadline = now() + 2.0;
+    pid_t normal = fork();
+    if (normal == 0)
+        spin_child(0, deadline);
+    pid_t niced = fork();
+    if (niced == 0)
+        spin_child(10, deadline);
+    double normal_time = reap_utime(normal);
+    double niced_time = reap_utime(niced);
+    if (normal_time < 2 * niced_time) {
+        printf("priority failed: utime %.2f s at nice 0, %.2f s at nice 10\n", normal_time,
+               niced_time);
+        return 1;
+    }
+
+    printf("priority passed!\n");
+    return 0;
+}
@@ -0,0 +1,206 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define FILE_PATH "/tmp/proc_fd_test"
+
+// 返回 /proc/self/fd 中 fd 的路径
+static const char *fd_path(int fd)
+{
+    static char path[64];
+    snprintf(path, sizeof(path), "/proc/self/fd/%d", fd);
+    return path;
+}
+
+// 通过 /proc/self/fd 以不同的访问模式重新打开普通文件
+static int check_reopen(void)
+{
+    int fd = open(FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, "hello", 5) != 5) {
+        printf("proc_fd failed: create errno %d\n", errno);
+        return 1;
+    }
+    struct stat st, st2;
+    fstat(fd, &st);
+
+    // 只读打开的新描述符从头读取，不能写入
+    int ro = open(fd_path(fd), O_RDONLY);
+    char buf[16] = {0};
+    if (ro < 0 || read(ro, buf, sizeof(buf)) != 5 || memcmp(buf, "hello", 5) != 0) {
+        printf("proc_fd failed: reopen read-only errno %d\n", errno);
+        return 1;
+    }
+    if (write(ro, "x", 1) != -1 || errno != EBADF) {
+        printf("proc_fd failed: wrote to read-only reopened fd\n");
+        return 1;
+    }
+    // 重新打开的是同一个文件
+    if (fstat(ro, &st2) != 0 || st.st_ino != st2.st_ino || st.st_dev != st2.st_dev) {
+        printf("proc_fd failed: reopened a different file\n");
+        return 1;
+    }
+
+    // 只写打开后写入的内容通过原来的描述符可见
+    int wo = open(fd_path(ro), O_WRONLY | O_APPEND);
+    if (wo < 0 || write(wo, " world", 6) != 6) {
+        printf("proc_fd failed: reopen write-only errno %d\n", errno);
+        return 1;
+    }
+    if (read(wo, buf, 1) != -1 || errno != EBADF) {
+        printf("proc_fd failed: read from write-only reopened fd\n");
+        return 1;
+    }
+    memset(buf, 0, sizeof(buf));
+    if (lseek(fd, 0, SEEK_SET) != 0 || read(fd, buf, sizeof(buf)) != 11 ||
+        strcmp(buf, "hello world") != 0) {
+        printf("proc_fd failed: write through reopened fd not visible: %s\n", buf);
+        return 1;
+    }
+    close(ro);
+    close(wo);
+
+    // 链接的目标是文件的路径，文件被删除后仍能重新打开
+    char target[128];
+    ssize_t n = readlink(fd_path(fd), target, sizeof(target) - 1);
+    if (n < 0 || (target[n] = '\0', strcmp(target, FILE_PATH) != 0)) {
+        printf("proc_fd failed: readlink gives %s\n", n < 0 ? "error" : target);
+        return 1;
+    }
+    if (unlink(FILE_PATH) != 0) {
+        printf("proc_fd failed: unlink errno %d\n", errno);
+        return 1;
+    }
+    n = readlink(fd_path(fd), target, sizeof(target) - 1);
+    if (n < 0 || (target[n] = '\0', strstr(target, "(deleted)") == NULL)) {
+        printf("proc_fd failed: readlink of deleted file gives %s\n", n < 0 ? "error" : target);
+        return 1;
+    }
+    ro = open(fd_path(fd), O_RDONLY);
+    memset(buf, 0, sizeof(buf));
+    if (ro < 0 || read(ro, buf, sizeof(buf)) != 11 || strcmp(buf, "hello world") != 0) {
+        printf("proc_fd failed: reopen deleted file errno %d\n", errno);
+        return 1;
+    }
+    close(ro);
+    close(fd);
+    return 0;
+}
+
+// 管道的链接目标是 pipe:[id]，重新打开得到同一个管道
+static int check_pipe(void)
+{
+    int fds[2];
+    if (pipe(fds) != 0) {
+        printf("proc_fd failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    char target[64];
+    ssize_t n = readlink(fd_path(fds[0]), target, sizeof(target) - 1);
+    if (n < 0 || (target[n] = '\0', strncmp(target, "pipe:[", 6) != 0)) {
+        printf("proc_fd failed: pipe link gives %s\n", n < 0 ? "error" : target);
+        return 1;
+    }
+    int wr = open(fd_path(fds[1]), O_WRONLY);
+    char c = 0;
+    if (wr < 0 || write(wr, "p", 1) != 1 || read(fds[0], &c, 1) != 1 || c != 'p') {
+        printf("proc_fd failed: reopen pipe errno %d\n", errno);
+        return 1;
+    }
+    close(wr);
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
+
+// /proc/self/fd 列出所有打开的描述符
+static int check_list(void)
+{
+    int fd = open("/proc/self", O_RDONLY | O_DIRECTORY);
+    int high = dup2(fd, 40);
+    if (fd < 0 || high != 40) {
+        printf("proc_fd failed: open /proc/self errno %d\n", errno);
+        return 1;
+    }
+    DIR *dir = opendir("/proc/self/fd");
+    if (dir == NULL) {
+        printf("proc_fd failed: opendir errno %d\n", errno);
+        return 1;
+    }
+    int found = 0;
+    struct dirent *ent;
+    while ((ent = readdir(dir)) != NULL) {
+        if (ent->d_name[0] == '.')
+            continue;
+        int n = atoi(ent->d_name);
+        if (n == 0 || n == 1 || n == fd || n == high)
+            found++;
+        if (ent->d_type != DT_LNK && ent->d_type != DT_UNKNOWN) {
+            printf("proc_fd failed: %s is not a link\n", ent->d_name);
+            return 1;
+        }
+    }
+    closedir(dir);
+    if (found != 4) {
+        printf("proc_fd failed: /proc/self/fd lists %d of 4 descriptors\n", found);
+        return 1;
+    }
+    close(high);
+    if (access(fd_path(high), F_OK) != -1 || errno != ENOENT) {
+        printf("proc_fd failed: closed descriptor still listed\n");
+        return 1;
+    }
+    close(fd);
+    return 0;
+}
+
+// 空路径加 AT_EMPTY_PATH 操作 dirfd 本身
+static int check_empty_path(void)
+{
+    int fd = open(FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    struct stat st, st2;
+    if (fd < 0 || fstat(fd, &st) != 0) {
+        printf("proc_fd failed: create errno %d\n", errno);
+        return 1;
+    }
+    if (fstatat(fd, "", &st2, AT_EMPTY_PATH) != 0 || st.st_ino != st2.st_ino) {
+        printf("proc_fd failed: fstatat empty path errno %d\n", errno);
+        return 1;
+    }
+    if (fstatat(fd, "", &st2, 0) != -1 || errno != ENOENT) {
+        printf("proc_fd failed: fstatat empty path without AT_EMPTY_PATH\n");
+        return 1;
+    }
+    struct timespec times[2] = {{1000, 0}, {2000, 0}};
+    if (utimensat(fd, "", times, AT_EMPTY_PATH) != 0 || fstat(fd, &st2) != 0 ||
+        st2.st_mtime != 2000) {
+        printf("proc_fd failed: utimensat empty path errno %d\n", errno);
+        return 1;
+    }
+    char buf[16];
+    if (readlinkat(fd, "", buf, sizeof(buf)) != -1 || errno != ENOENT) {
+        printf("proc_fd failed: readlinkat empty path of regular file\n");
+        return 1;
+    }
+    // 跟随 /proc/self/fd 的链接与 AT_EMPTY_PATH 得到同一个文件
+    if (stat(fd_path(fd), &st2) != 0 || st.st_ino != st2.st_ino) {
+        printf("proc_fd failed: stat through fd link errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+int main(void)
+{
+    if (check_reopen() || check_pipe() || check_list() || check_empty_path())
+        return 1;
+    printf("proc_fd passed!\n");
+    return 0;
+}
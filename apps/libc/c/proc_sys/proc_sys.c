@@ -0,0 +1,217 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/sysinfo.h>
+#include <sys/utsname.h>
+#include <unistd.h>
+
+// 读取 /proc/sys 下的参数，返回读到的长度，失败时返回 -1
+static int read_sysctl(const char *name, char *buf, size_t size)
+{
+    char path[128];
+    snprintf(path, sizeof(path), "/proc/sys/%s", name);
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    ssize_t n = read(fd, buf, size - 1);
+    close(fd);
+    if (n < 0)
+        return -1;
+    buf[n] = '\0';
+    return n;
+}
+
+// 写入 /proc/sys 下的参数，失败时返回 -1 并设置 errno
+static int write_sysctl(const char *name, const char *value)
+{
+    char path[128];
+    snprintf(path, sizeof(path), "/proc/sys/%s", name);
+    int fd = open(path, O_WRONLY | O_TRUNC);
+    if (fd < 0)
+        return -1;
+    ssize_t n = write(fd, value, strlen(value));
+    int err = errno;
+    close(fd);
+    errno = err;
+    return n == (ssize_t)strlen(value) ? 0 : -1;
+}
+
+// 参数的值应为 expected 加上换行符
+static int expect_sysctl(const char *name, const char *expected)
+{
+    char buf[256], want[256];
+    snprintf(want, sizeof(want), "%s\n", expected);
+    if (read_sysctl(name, buf, sizeof(buf)) < 0 || strcmp(buf, want) != 0) {
+        printf("proc_sys failed: %s is not %s\n", name, expected);
+        return 1;
+    }
+    return 0;
+}
+
+// 写入无效的值应失败并返回 EINVAL
+static int expect_invalid(const char *name, const char *value)
+{
+    if (write_sysctl(name, value) != -1 || errno != EINVAL) {
+        printf("proc_sys failed: %s accepted %s\n", name, value);
+        return 1;
+    }
+    return 0;
+}
+
+static int check_names(void)
+{
+    struct utsname uts;
+    char hostname[256], domainname[256];
+    if (uname(&uts) != 0 || expect_sysctl("kernel/osrelease", uts.release) ||
+        expect_sysctl("kernel/hostname", uts.nodename) ||
+        expect_sysctl("kernel/domainname", uts.domainname))
+        return 1;
+    strcpy(hostname, uts.nodename);
+    strcpy(domainname, uts.domainname);
+
+    // 写入的主机名立即生效，末尾的换行符不属于主机名
+    if (write_sysctl("kernel/hostname", "proc-sys-test\n") != 0 || uname(&uts) != 0 ||
+        strcmp(uts.nodename, "proc-sys-test") != 0) {
+        printf("proc_sys failed: hostname not written\n");
+        return 1;
+    }
+    // sethostname 和 /proc/sys/kernel/hostname 是同一个值
+    if (sethostname("sethostname", 11) != 0 || expect_sysctl("kernel/hostname", "sethostname"))
+        return 1;
+    // 过长的主机名被截断为 64 字节
+    char long_name[80];
+    memset(long_name, 'a', sizeof(long_name) - 1);
+    long_name[sizeof(long_name) - 1] = '\0';
+    if (write_sysctl("kernel/hostname", long_name) != 0 || uname(&uts) != 0 ||
+        strlen(uts.nodename) != 64) {
+        printf("proc_sys failed: long hostname not truncated\n");
+        return 1;
+    }
+    if (write_sysctl("kernel/domainname", "proc-sys-domain") != 0 || uname(&uts) != 0 ||
+        strcmp(uts.domainname, "proc-sys-domain") != 0) {
+        printf("proc_sys failed: domainname not written\n");
+        return 1;
+    }
+    if (write_sysctl("kernel/hostname", hostname) != 0 ||
+        write_sysctl("kernel/domainname", domainname) != 0) {
+        printf("proc_sys failed: restore names errno %d\n", errno);
+        return 1;
+    }
+
+    // osrelease 是只读的
+    if (write_sysctl("kernel/osrelease", "1.0") != -1) {
+        printf("proc_sys failed: osrelease written\n");
+        return 1;
+    }
+    return 0;
+}
+
+static int check_integers(void)
+{
+    char pid_max[32], file_max[32];
+    if (read_sysctl("kernel/pid_max", pid_max, sizeof(pid_max)) < 0 ||
+        read_sysctl("fs/file-max", file_max, sizeof(file_max)) < 0) {
+        printf("proc_sys failed: read integers errno %d\n", errno);
+        return 1;
+    }
+    if (expect_invalid("kernel/pid_max", "abc") || expect_invalid("kernel/pid_max", "100") ||
+        expect_invalid("kernel/pid_max", "4194305") || expect_invalid("fs/file-max", "-1") ||
+        expect_invalid("vm/overcommit_memory", "3"))
+        return 1;
+    if (write_sysctl("kernel/pid_max", "65536") != 0 || expect_sysctl("kernel/pid_max", "65536") ||
+        write_sysctl("kernel/pid_max", pid_max) != 0) {
+        printf("proc_sys failed: pid_max not written\n");
+        return 1;
+    }
+    if (write_sysctl("fs/file-max", "100000") != 0 || expect_sysctl("fs/file-max", "100000") ||
+        write_sysctl("fs/file-max", file_max) != 0) {
+        printf("proc_sys failed: file-max not written\n");
+        return 1;
+    }
+    return 0;
+}
+
+// 按 vm/overcommit_memory 的策略映射远大于物理内存的区域
+static int check_overcommit(void)
+{
+    char saved[32];
+    struct sysinfo si;
+    if (read_sysctl("vm/overcommit_memory", saved, sizeof(saved)) < 0 || sysinfo(&si) != 0) {
+        printf("proc_sys failed: overcommit_memory errno %d\n", errno);
+        return 1;
+    }
+    size_t huge = (size_t)(si.totalram + si.totalswap) * si.mem_unit * 2;
+    const char *modes[] = {"0", "1", "2"};
+    int expect_ok[] = {0, 1, 0};
+    for (int i = 0; i < 3; i++) {
+        if (write_sysctl("vm/overcommit_memory", modes[i]) != 0) {
+            printf("proc_sys failed: overcommit_memory %s errno %d\n", modes[i], errno);
+            return 1;
+        }
+        void *p = mmap(NULL, huge, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+        int ok = p != MAP_FAILED;
+        if (ok)
+            munmap(p, huge);
+        if (ok != expect_ok[i] || (!ok && errno != ENOMEM)) {
+            printf("proc_sys failed: overcommit_memory %s mmap %s\n", modes[i],
+                   ok ? "succeeded" : "failed");
+            write_sysctl("vm/overcommit_memory", saved);
+            return 1;
+        }
+    }
+    if (write_sysctl("vm/overcommit_memory", saved) != 0) {
+        printf("proc_sys failed: restore overcommit_memory\n");
+        return 1;
+    }
+    return 0;
+}
+
+static int check_tree(void)
+{
+    struct stat st;
+    if (stat("/proc/sys/vm", &st) != 0 || !S_ISDIR(st.st_mode)) {
+        printf("proc_sys failed: /proc/sys/vm is not a directory\n");
+        return 1;
+    }
+    if (stat("/proc/sys/kernel/hostname", &st) != 0 || (st.st_mode & 0777) != 0644 ||
+        st.st_uid != 0 || stat("/proc/sys/kernel/osrelease", &st) != 0 ||
+        (st.st_mode & 0777) != 0444) {
+        printf("proc_sys failed: bad file modes\n");
+        return 1;
+    }
+    if (open("/proc/sys/kernel/no_such_tunable", O_RDONLY) != -1 || errno != ENOENT) {
+        printf("proc_sys failed: missing tunable opened\n");
+        return 1;
+    }
+
+    DIR *dir = opendir("/proc/sys/kernel");
+    if (dir == NULL) {
+        printf("proc_sys failed: opendir errno %d\n", errno);
+        return 1;
+    }
+    int found = 0;
+    struct dirent *ent;
+    while ((ent = readdir(dir)) != NULL) {
+        if (strcmp(ent->d_name, "hostname") == 0 || strcmp(ent->d_name, "pid_max") == 0)
+            found++;
+    }
+    closedir(dir);
+    if (found != 2) {
+        printf("proc_sys failed: /proc/sys/kernel lists %d of 2 entries\n", found);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    if (check_tree() || check_names() || check_integers() || check_overcommit())
+        return 1;
+    printf("proc_sys passed!\n");
+    return 0;
+}
@@ -0,0 +1,107 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/uio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char child_data[64];
+static char child_inbox[64];
+
+static ssize_t read_from(pid_t pid, void *dst, void *src, size_t len)
+{
+    struct iovec local = {dst, len};
+    struct iovec remote = {src, len};
+    return process_vm_readv(pid, &local, 1, &remote, 1, 0);
+}
+
+static int test_self(void)
+{
+    // 读取自身未访问过的延迟分配内存，得到的是 0
+    char *lazy = mmap(NULL, 8192, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    char buf[8192];
+    memset(buf, 1, sizeof(buf));
+    if (lazy == MAP_FAILED || read_from(getpid(), buf, lazy, 16) != 16 || buf[0] != 0) {
+        printf("process_vm failed: reading lazy memory of self\n");
+        return 1;
+    }
+
+    // 分散到多个 iovec 的数据按顺序复制
+    strcpy(lazy, "scattered");
+    char a[4] = {0}, b[8] = {0};
+    struct iovec local[2] = {{a, 3}, {b, 6}};
+    struct iovec remote = {lazy, 9};
+    if (process_vm_readv(getpid(), local, 2, &remote, 1, 0) != 9 || strcmp(a, "sca") != 0 ||
+        strcmp(b, "ttered") != 0) {
+        printf("process_vm failed: scattered read\n");
+        return 1;
+    }
+
+    // 遇到未映射的页时返回已复制的字节数，一个字节都没有复制时返回 EFAULT
+    munmap(lazy + 4096, 4096);
+    if (read_from(getpid(), buf, lazy, 8192) != 4096) {
+        printf("process_vm failed: partial read\n");
+        return 1;
+    }
+    if (read_from(getpid(), buf, lazy + 4096, 16) != -1 || errno != EFAULT) {
+        printf("process_vm failed: unmapped memory read\n");
+        return 1;
+    }
+    munmap(lazy, 4096);
+
+    if (process_vm_readv(getpid(), NULL, 0, NULL, 0, 1) != -1 || errno != EINVAL) {
+        printf("process_vm failed: flags accepted\n");
+        return 1;
+    }
+    return 0;
+}
+
+int main()
+{
+    if (test_self() != 0) {
+        return 1;
+    }
+
+    int ready[2], go[2];
+    pipe(ready);
+    pipe(go);
+    pid_t pid = fork();
+    if (pid == 0) {
+        char c;
+        strcpy(child_data, "from child");
+        write(ready[1], "r", 1);
+        read(go[0], &c, 1);
+        _exit(strcmp(child_inbox, "from parent") == 0 ? 0 : 1);
+    }
+
+    char c, buf[64] = {0};
+    read(ready[0], &c, 1);
+    if (read_from(pid, buf, child_data, sizeof(buf)) != sizeof(buf) ||
+        strcmp(buf, "from child") != 0) {
+        printf("process_vm failed: reading child memory\n");
+        return 1;
+    }
+    char msg[] = "from parent";
+    struct iovec local = {msg, sizeof(msg)};
+    struct iovec remote = {child_inbox, sizeof(msg)};
+    if (process_vm_writev(pid, &local, 1, &remote, 1, 0) != sizeof(msg)) {
+        printf("process_vm failed: writing child memory\n");
+        return 1;
+    }
+    write(go[1], "g", 1);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("process_vm failed: child did not see the written data\n");
+        return 1;
+    }
+
+    if (read_from(pid, buf, child_data, 1) != -1 || errno != ESRCH) {
+        printf("process_vm failed: reaped child still accessible\n");
+        return 1;
+    }
+
+    printf("process_vm passed!\n");
+    return 0;
+}
@@ -0,0 +1,91 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char buf[4096];
+
+static int read_file(const char *path)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    int len = read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    if (len >= 0)
+        buf[len] = 0;
+    return len;
+}
+
+static long status_field(const char *path, const char *key)
+{
+    if (read_file(path) < 0)
+        return -1;
+    char *line = strstr(buf, key);
+    return line ? atol(line + strlen(key)) : -1;
+}
+
+int main(int argc, char **argv)
+{
+    // stat 以 "pid (comm) state ppid" 开头
+    int pid, ppid;
+    char state;
+    if (read_file("/proc/self/stat") <= 0 ||
+        sscanf(strrchr(buf, ')') + 2, "%c %d", &state, &ppid) != 2 ||
+        sscanf(buf, "%d", &pid) != 1 || pid != getpid() || ppid != getppid() || state != 'R') {
+        printf("procfs failed: /proc/self/stat: %s\n", buf);
+        return 1;
+    }
+    int fields = 0;
+    for (char *p = strrchr(buf, ')') + 1; *p; p++)
+        if (*p == ' ')
+            fields++;
+    if (fields != 50) {
+        printf("procfs failed: stat has %d fields\n", fields + 2);
+        return 1;
+    }
+
+    // cmdline 与 argv 一致
+    int len = read_file("/proc/self/cmdline");
+    if (len != (int)strlen(argv[0]) + 1 || strcmp(buf, argv[0]) != 0) {
+        printf("procfs failed: /proc/self/cmdline\n");
+        return 1;
+    }
+
+    // 按 PID 访问的目录与 self 相同，内容在读取时生成
+    char path[64];
+    sprintf(path, "/proc/%d/status", getpid());
+    if (status_field(path, "Pid:\t") != getpid() || status_field(path, "Uid:\t") != 0) {
+        printf("procfs failed: %s\n", path);
+        return 1;
+    }
+    long rss = status_field(path, "VmRSS:\t");
+    char *mem = mmap(NULL, 1 << 20, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    memset(mem, 1, 1 << 20);
+    if (status_field(path, "VmRSS:\t") < rss + 1024) {
+        printf("procfs failed: VmRSS does not grow\n");
+        return 1;
+    }
+
+    // 退出但未被回收的子进程处于僵尸状态
+    pid_t child = fork();
+    if (child == 0)
+        _exit(0);
+    sleep(1);
+    sprintf(path, "/proc/%d/stat", child);
+    if (read_file(path) <= 0 || strrchr(buf, ')')[2] != 'Z') {
+        printf("procfs failed: child is not a zombie\n");
+        return 1;
+    }
+    waitpid(child, NULL, 0);
+    if (open(path, O_RDONLY) >= 0) {
+        printf("procfs failed: reaped child is still visible\n");
+        return 1;
+    }
+
+    printf("procfs passed!\n");
+    return 0;
+}
@@ -0,0 +1,157 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/sysmacros.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// 在目录 path 中查找名为 name 的目录项，返回它的 d_type，找不到时返回 -1
+static int find_entry(const char *path, const char *name)
+{
+    DIR *dir = opendir(path);
+    if (!dir)
+        return -2;
+    int type = -1;
+    struct dirent *ent;
+    while ((ent = readdir(dir)) != NULL) {
+        if (strcmp(ent->d_name, name) == 0) {
+            type = ent->d_type;
+            break;
+        }
+    }
+    closedir(dir);
+    return type;
+}
+
+static int check_proc_root(void)
+{
+    char pid[16];
+    snprintf(pid, sizeof(pid), "%d", getpid());
+    if (find_entry("/proc", pid) != DT_DIR) {
+        printf("procfs_dir failed: own pid %s not listed\n", pid);
+        return 1;
+    }
+    if (find_entry("/proc", "meminfo") != DT_REG || find_entry("/proc", "sys") != DT_DIR) {
+        printf("procfs_dir failed: static entries\n");
+        return 1;
+    }
+    if (find_entry("/proc/self", "status") != DT_REG) {
+        printf("procfs_dir failed: /proc/self/status not listed\n");
+        return 1;
+    }
+    struct stat st;
+    if (stat("/proc/self", &st) != 0 || !S_ISDIR(st.st_mode) || st.st_uid != geteuid()) {
+        printf("procfs_dir failed: stat /proc/self uid %d\n", st.st_uid);
+        return 1;
+    }
+    return 0;
+}
+
+static int check_dev(void)
+{
+    if (find_entry("/dev", "null") != DT_CHR || find_entry("/dev", "zero") != DT_CHR) {
+        printf("procfs_dir failed: /dev entries\n");
+        return 1;
+    }
+    struct stat st;
+    if (stat("/dev/null", &st) != 0 || !S_ISCHR(st.st_mode) || major(st.st_rdev) != 1 ||
+        minor(st.st_rdev) != 3) {
+        printf("procfs_dir failed: stat /dev/null mode %o rdev %#lx\n", st.st_mode,
+               (unsigned long)st.st_rdev);
+        return 1;
+    }
+    if (stat("/dev/zero", &st) != 0 || !S_ISCHR(st.st_mode) || major(st.st_rdev) != 1 ||
+        minor(st.st_rdev) != 5) {
+        printf("procfs_dir failed: stat /dev/zero\n");
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    if (check_proc_root() || check_dev())
+        return 1;
+
+    // 子进程切换到另一个用户后等待父进程关闭管道
+    int ready[2], hold[2];
+    if (pipe(ready) != 0 || pipe(hold) != 0) {
+        printf("procfs_dir failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    pid_t child = fork();
+    if (child < 0) {
+        printf("procfs_dir failed: fork errno %d\n", errno);
+        return 1;
+    }
+    if (child == 0) {
+        close(hold[1]);
+        if (setuid(1000) != 0)
+            _exit(1);
+        char c = 0;
+        write(ready[1], &c, 1);
+        read(hold[0], &c, 1);
+        _exit(0);
+    }
+    close(hold[0]);
+    char c;
+    if (read(ready[0], &c, 1) != 1) {
+        printf("procfs_dir failed: child not ready\n");
+        return 1;
+    }
+
+    char name[16], path[32];
+    snprintf(name, sizeof(name), "%d", child);
+    snprintf(path, sizeof(path), "/proc/%d", child);
+    if (find_entry("/proc", name) != DT_DIR) {
+        printf("procfs_dir failed: child %s not listed\n", name);
+        return 1;
+    }
+    // 进程目录属于进程的有效用户
+    struct stat st;
+    if (stat(path, &st) != 0 || !S_ISDIR(st.st_mode) || st.st_uid != 1000) {
+        printf("procfs_dir failed: stat %s uid %d\n", path, st.st_uid);
+        return 1;
+    }
+    int dirfd = open(path, O_RDONLY | O_DIRECTORY);
+    if (dirfd < 0) {
+        printf("procfs_dir failed: open %s errno %d\n", path, errno);
+        return 1;
+    }
+
+    // 子进程退出并被回收后，它的目录从列表中消失，已打开的目录中也没有目录项
+    close(hold[1]);
+    int status;
+    if (waitpid(child, &status, 0) != child || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("procfs_dir failed: child status %#x\n", status);
+        return 1;
+    }
+    if (find_entry("/proc", name) != -1) {
+        printf("procfs_dir failed: reaped child %s still listed\n", name);
+        return 1;
+    }
+    if (stat(path, &st) != -1 || errno != ENOENT) {
+        printf("procfs_dir failed: stat reaped %s\n", path);
+        return 1;
+    }
+    char buf[1024];
+    long n = syscall(SYS_getdents64, dirfd, buf, sizeof(buf));
+    if (n > 0 || (n < 0 && errno != ENOENT && errno != ESRCH)) {
+        printf("procfs_dir failed: getdents64 of reaped process returned %ld\n", n);
+        return 1;
+    }
+    int fd = openat(dirfd, "stat", O_RDONLY);
+    if (fd >= 0 || (errno != ENOENT && errno != ESRCH)) {
+        printf("procfs_dir failed: openat in reaped process directory\n");
+        return 1;
+    }
+    close(dirfd);
+
+    printf("procfs_dir passed!\n");
+    return 0;
+}
@@ -0,0 +1,85 @@
+#include <elf.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/ptrace.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// PTRACE_GETREGSET 得到的是内核保存的陷入帧，按各架构的布局取出系统调用号和返回值
+#if defined(__x86_64__)
+#define SYSNO_REG 0 // rax
+#define RET_REG 0   // rax
+#elif defined(__riscv)
+#define SYSNO_REG 16 // a7
+#define RET_REG 9    // a0
+#elif defined(__aarch64__)
+#define SYSNO_REG 8 // x8
+#define RET_REG 0   // x0
+#endif
+
+static long secret = 0x1234;
+
+static int wait_stop(pid_t pid, int sig)
+{
+    int status;
+    return waitpid(pid, &status, 0) == pid && WIFSTOPPED(status) && WSTOPSIG(status) == sig;
+}
+
+static long get_reg(pid_t pid, int index)
+{
+    unsigned long regs[64];
+    struct iovec iov = {regs, sizeof(regs)};
+    if (ptrace(PTRACE_GETREGSET, pid, NT_PRSTATUS, &iov) != 0 || iov.iov_len <= index * sizeof(long))
+        return -1;
+    return regs[index];
+}
+
+int main()
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        ptrace(PTRACE_TRACEME, 0, 0, 0);
+        kill(getpid(), SIGSTOP);
+        pid_t self = getpid();
+        _exit(secret == 0x5678 && self == getpid() ? 0 : 1);
+    }
+
+    // 子进程在递送 SIGSTOP 时停下
+    if (!wait_stop(pid, SIGSTOP)) {
+        printf("ptrace failed: child did not stop\n");
+        return 1;
+    }
+
+    // 读写子进程的内存
+    if (ptrace(PTRACE_PEEKDATA, pid, &secret, 0) != 0x1234 ||
+        ptrace(PTRACE_POKEDATA, pid, &secret, (void *)0x5678) != 0) {
+        printf("ptrace failed: peek or poke\n");
+        return 1;
+    }
+
+    // 在 getpid 的入口和出口处各停一次
+    ptrace(PTRACE_SETOPTIONS, pid, 0, PTRACE_O_TRACESYSGOOD);
+    ptrace(PTRACE_SYSCALL, pid, 0, 0);
+    if (!wait_stop(pid, SIGTRAP | 0x80) || get_reg(pid, SYSNO_REG) != SYS_getpid) {
+        printf("ptrace failed: syscall entry stop\n");
+        return 1;
+    }
+    ptrace(PTRACE_SYSCALL, pid, 0, 0);
+    if (!wait_stop(pid, SIGTRAP | 0x80) || get_reg(pid, RET_REG) != pid) {
+        printf("ptrace failed: syscall exit stop\n");
+        return 1;
+    }
+
+    // 继续运行直到退出
+    ptrace(PTRACE_CONT, pid, 0, 0);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("ptrace failed: child exit status %d\n", status);
+        return 1;
+    }
+
+    printf("ptrace passed!\n");
+    return 0;
+}
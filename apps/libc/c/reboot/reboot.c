@@ -0,0 +1,34 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/reboot.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define LINUX_REBOOT_MAGIC1 0xfee1dead
+#define LINUX_REBOOT_MAGIC2 672274793
+#define LINUX_REBOOT_CMD_CAD_OFF 0
+
+int main()
+{
+    // 魔数错误时拒绝执行
+    errno = 0;
+    if (syscall(SYS_reboot, 0x12345678, LINUX_REBOOT_MAGIC2, RB_POWER_OFF, 0) != -1 || errno != EINVAL) {
+        printf("reboot failed: bad magic accepted\n");
+        return 1;
+    }
+    // 未知命令
+    errno = 0;
+    if (syscall(SYS_reboot, LINUX_REBOOT_MAGIC1, LINUX_REBOOT_MAGIC2, 0x1234, 0) != -1 || errno != EINVAL) {
+        printf("reboot failed: bad command accepted\n");
+        return 1;
+    }
+    // 不会真正关机的命令
+    if (syscall(SYS_reboot, LINUX_REBOOT_MAGIC1, LINUX_REBOOT_MAGIC2, LINUX_REBOOT_CMD_CAD_OFF, 0) != 0) {
+        printf("reboot failed: CAD_OFF errno=%d\n", errno);
+        return 1;
+    }
+    sync();
+
+    printf("reboot passed!\n");
+    return 0;
+}
@@ -0,0 +1,80 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define FILE_NAME "rlimit_test_file"
+
+int main(void)
+{
+    struct rlimit lim = {16, 16};
+    if (setrlimit(RLIMIT_NOFILE, &lim) != 0) {
+        printf("rlimit failed: setrlimit(RLIMIT_NOFILE) errno %d\n", errno);
+        return 1;
+    }
+    lim.rlim_cur = lim.rlim_max = 0;
+    if (getrlimit(RLIMIT_NOFILE, &lim) != 0 || lim.rlim_cur != 16 || lim.rlim_max != 16) {
+        printf("rlimit failed: getrlimit(RLIMIT_NOFILE) = %ld %ld\n", (long)lim.rlim_cur,
+               (long)lim.rlim_max);
+        return 1;
+    }
+    // 软限制不能超过硬限制
+    lim.rlim_cur = 32;
+    if (setrlimit(RLIMIT_NOFILE, &lim) != -1 || errno != EINVAL) {
+        printf("rlimit failed: soft limit above hard limit, errno %d\n", errno);
+        return 1;
+    }
+
+    int fd = open(FILE_NAME, O_CREAT | O_RDWR, 0644);
+    if (fd < 0) {
+        printf("rlimit failed: open errno %d\n", errno);
+        return 1;
+    }
+    int last = fd;
+    while ((fd = open(FILE_NAME, O_RDONLY)) >= 0)
+        last = fd;
+    if (errno != EMFILE || last != 15) {
+        printf("rlimit failed: open stopped at fd %d with errno %d\n", last, errno);
+        return 1;
+    }
+    int p[2];
+    if (pipe(p) != -1 || errno != EMFILE || dup(0) != -1 || errno != EMFILE) {
+        printf("rlimit failed: pipe or dup beyond the limit, errno %d\n", errno);
+        return 1;
+    }
+
+    // 替换已经打开的 fd 15 仍然可以，fd 16 超出了限制
+    if (dup2(3, 15) != 15) {
+        printf("rlimit failed: dup2 to fd 15 errno %d\n", errno);
+        return 1;
+    }
+    if (dup2(3, 16) != -1 || errno != EBADF) {
+        printf("rlimit failed: dup2 to fd 16 errno %d\n", errno);
+        return 1;
+    }
+    for (fd = 3; fd <= 15; fd++)
+        close(fd);
+    unlink(FILE_NAME);
+
+    // 地址空间的限制在子进程中设置，避免影响父进程的内存分配
+    pid_t pid = fork();
+    if (pid == 0) {
+        struct rlimit as = {64 << 20, 64 << 20};
+        if (setrlimit(RLIMIT_AS, &as) != 0)
+            _exit(1);
+        void *p = mmap(NULL, 128 << 20, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+        _exit(p == MAP_FAILED && errno == ENOMEM ? 0 : 2);
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFEXITED(status) ||
+        WEXITSTATUS(status) != 0) {
+        printf("rlimit failed: mmap beyond RLIMIT_AS, status %d\n", status);
+        return 1;
+    }
+
+    printf("rlimit passed!\n");
+    return 0;
+}
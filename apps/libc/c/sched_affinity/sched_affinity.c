@@ -0,0 +1,133 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define SPIN_LOOPS 50000000UL
+
+// 返回单调时钟的当前时间，单位为秒
+static double now(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec + ts.tv_nsec / 1e9;
+}
+
+// 只消耗 CPU 的子进程，结束后退出
+static pid_t spawn_spinner(void)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        for (volatile unsigned long i = 0; i < SPIN_LOOPS; i++)
+            ;
+        _exit(0);
+    }
+    return pid;
+}
+
+// 同时运行 n 个消耗 CPU 的子进程，返回全部结束所用的时间，失败时返回负数
+static double run_spinners(int n)
+{
+    double start = now();
+    for (int i = 0; i < n; i++) {
+        if (spawn_spinner() < 0)
+            return -1;
+    }
+    for (int i = 0; i < n; i++) {
+        int status;
+        if (wait(&status) < 0 || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+            return -1;
+    }
+    return now() - start;
+}
+
+int main()
+{
+    cpu_set_t all, set;
+    CPU_ZERO(&all);
+    if (sched_getaffinity(0, sizeof(all), &all) != 0 || CPU_COUNT(&all) < 1) {
+        printf("sched_affinity failed: cannot get the affinity of itself\n");
+        return 1;
+    }
+    int first = 0;
+    while (!CPU_ISSET(first, &all))
+        first++;
+
+    // 内核返回写入的字节数；掩码的大小不是 long 的整数倍时返回 EINVAL
+    unsigned long raw[16];
+    long ret = syscall(SYS_sched_getaffinity, 0, sizeof(raw), raw);
+    if (ret <= 0 || ret % sizeof(long) != 0) {
+        printf("sched_affinity failed: raw sched_getaffinity returned %ld\n", ret);
+        return 1;
+    }
+    if (syscall(SYS_sched_getaffinity, 0, 4, raw) != -1 || errno != EINVAL) {
+        printf("sched_affinity failed: a 4-byte mask did not fail with EINVAL\n");
+        return 1;
+    }
+
+    // 绑定到第一个 CPU 后只在该 CPU 上运行
+    CPU_ZERO(&set);
+    CPU_SET(first, &set);
+    if (sched_setaffinity(0, sizeof(set), &set) != 0) {
+        printf("sched_affinity failed: cannot bind to CPU %d\n", first);
+        return 1;
+    }
+    cpu_set_t got;
+    if (sched_getaffinity(0, sizeof(got), &got) != 0 || !CPU_EQUAL(&got, &set)) {
+        printf("sched_affinity failed: the affinity was not changed\n");
+        return 1;
+    }
+    sched_yield();
+    if (sched_getcpu() != first) {
+        printf("sched_affinity failed: running on CPU %d instead of %d\n", sched_getcpu(), first);
+        return 1;
+    }
+
+    // 子进程继承绑定的 CPU
+    pid_t pid = fork();
+    if (pid == 0) {
+        cpu_set_t child;
+        _exit(sched_getaffinity(0, sizeof(child), &child) == 0 && CPU_EQUAL(&child, &set) ? 0 : 1);
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("sched_affinity failed: child did not inherit the affinity\n");
+        return 1;
+    }
+
+    // 空的掩码和不存在的进程
+    CPU_ZERO(&set);
+    if (sched_setaffinity(0, sizeof(set), &set) != -1 || errno != EINVAL) {
+        printf("sched_affinity failed: an empty mask did not fail with EINVAL\n");
+        return 1;
+    }
+    if (sched_getaffinity(999999, sizeof(set), &set) != -1 || errno != ESRCH) {
+        printf("sched_affinity failed: a missing pid did not fail with ESRCH\n");
+        return 1;
+    }
+    if (sched_setaffinity(0, sizeof(all), &all) != 0) {
+        printf("sched_affinity failed: cannot restore the affinity\n");
+        return 1;
+    }
+
+    // 有多个 CPU 时，两个消耗 CPU 的进程应当并行运行，用时接近单个进程
+    if (CPU_COUNT(&all) >= 2) {
+        double one = run_spinners(1);
+        double two = run_spinners(2);
+        if (one < 0 || two < 0) {
+            printf("sched_affinity failed: spinner did not exit normally\n");
+            return 1;
+        }
+        if (two > one * 1.5) {
+            printf("sched_affinity failed: two spinners took %.3fs, one took %.3fs\n", two, one);
+            return 1;
+        }
+    }
+
+    printf("sched_affinity passed!\n");
+    return 0;
+}
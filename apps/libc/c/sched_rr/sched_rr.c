@@ -0,0 +1,212 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+// 每个子进程从开始计数起运行的时间，是 SCHED_RR 时间片的数倍
+#define RUN_MS 300
+
+// musl 的 sched_setscheduler 等函数直接返回 ENOSYS，因此使用系统调用
+static int set_scheduler(pid_t pid, int policy, int priority)
+{
+    struct sched_param param = {.sched_priority = priority};
+    return syscall(SYS_sched_setscheduler, pid, policy, &param);
+}
+
+static int get_scheduler(pid_t pid)
+{
+    return syscall(SYS_sched_getscheduler, pid);
+}
+
+// 两个子进程共享的状态
+struct shared {
+    volatile int ready[2];
+    volatile int go;
+    volatile unsigned long count[2];
+    // 子进程计数期间另一个子进程是否也在计数
+    volatile int overlap[2];
+};
+
+static struct shared *sh;
+
+static long now_ms(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec * 1000 + ts.tv_nsec / 1000000;
+}
+
+static void child(int i, int policy)
+{
+    if (set_scheduler(0, policy, 10) != 0 || get_scheduler(0) != policy)
+        _exit(2);
+    sh->ready[i] = 1;
+    while (!sh->go)
+        sched_yield();
+    unsigned long other = sh->count[1 - i];
+    long start = now_ms();
+    while (now_ms() - start < RUN_MS)
+        sh->count[i]++;
+    sh->overlap[i] = sh->count[1 - i] != other;
+    _exit(0);
+}
+
+// 在 CPU 0 上运行两个策略为 policy、优先级相同的子进程
+static int run_pair(int policy)
+{
+    sh->ready[0] = sh->ready[1] = 0;
+    sh->go = 0;
+    sh->count[0] = sh->count[1] = 0;
+    sh->overlap[0] = sh->overlap[1] = -1;
+    pid_t pids[2];
+    for (int i = 0; i < 2; i++) {
+        pids[i] = fork();
+        if (pids[i] < 0) {
+            printf("sched_rr failed: fork errno %d\n", errno);
+            return 1;
+        }
+        if (pids[i] == 0)
+            child(i, policy);
+    }
+    while (!sh->ready[0] || !sh->ready[1])
+        sched_yield();
+    sh->go = 1;
+    for (int i = 0; i < 2; i++) {
+        int status;
+        if (waitpid(pids[i], &status, 0) != pids[i] || !WIFEXITED(status) ||
+            WEXITSTATUS(status) != 0) {
+            printf("sched_rr failed: policy %d child %d status %#x\n", policy, i, status);
+            return 1;
+        }
+    }
+    if (sh->count[0] == 0 || sh->count[1] == 0) {
+        printf("sched_rr failed: policy %d child made no progress\n", policy);
+        return 1;
+    }
+    return 0;
+}
+
+static int check_params(void)
+{
+    if (sched_get_priority_min(SCHED_RR) != 1 || sched_get_priority_max(SCHED_RR) != 99 ||
+        sched_get_priority_min(SCHED_FIFO) != 1 || sched_get_priority_max(SCHED_FIFO) != 99 ||
+        sched_get_priority_min(SCHED_OTHER) != 0 || sched_get_priority_max(SCHED_OTHER) != 0) {
+        printf("sched_rr failed: priority range\n");
+        return 1;
+    }
+    if (sched_get_priority_max(12345) != -1 || errno != EINVAL) {
+        printf("sched_rr failed: invalid policy accepted\n");
+        return 1;
+    }
+    if (get_scheduler(0) != SCHED_OTHER) {
+        printf("sched_rr failed: default policy %d\n", get_scheduler(0));
+        return 1;
+    }
+    // 优先级不在策略的范围内
+    if (set_scheduler(0, SCHED_FIFO, 0) != -1 || errno != EINVAL ||
+        set_scheduler(0, SCHED_OTHER, 1) != -1 || errno != EINVAL ||
+        set_scheduler(0, 12345, 0) != -1 || errno != EINVAL) {
+        printf("sched_rr failed: invalid parameters accepted\n");
+        return 1;
+    }
+
+    struct timespec ts;
+    if (sched_rr_get_interval(0, &ts) != 0) {
+        printf("sched_rr failed: SCHED_OTHER interval\n");
+        return 1;
+    }
+
+    // 修改策略后 fork 的子进程继承策略和优先级
+    if (set_scheduler(0, SCHED_RR, 5) != 0) {
+        printf("sched_rr failed: sched_setscheduler errno %d\n", errno);
+        return 1;
+    }
+    struct sched_param param;
+    if (syscall(SYS_sched_getparam, 0, &param) != 0 || param.sched_priority != 5 ||
+        sched_rr_get_interval(0, &ts) != 0 || ts.tv_sec != 0 || ts.tv_nsec != 100000000) {
+        printf("sched_rr failed: SCHED_RR parameters\n");
+        return 1;
+    }
+    pid_t pid = fork();
+    if (pid == 0) {
+        struct sched_param p;
+        _exit(get_scheduler(0) == SCHED_RR && syscall(SYS_sched_getparam, 0, &p) == 0 &&
+                      p.sched_priority == 5
+                  ? 0
+                  : 1);
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFEXITED(status) ||
+        WEXITSTATUS(status) != 0) {
+        printf("sched_rr failed: policy not inherited\n");
+        return 1;
+    }
+    // sched_setparam 只修改优先级
+    param.sched_priority = 7;
+    if (syscall(SYS_sched_setparam, 0, &param) != 0 || get_scheduler(0) != SCHED_RR ||
+        syscall(SYS_sched_getparam, 0, &param) != 0 || param.sched_priority != 7) {
+        printf("sched_rr failed: sched_setparam\n");
+        return 1;
+    }
+    if (set_scheduler(0, SCHED_FIFO, 5) != 0 || sched_rr_get_interval(0, &ts) != 0 ||
+        ts.tv_sec != 0 || ts.tv_nsec != 0) {
+        printf("sched_rr failed: SCHED_FIFO interval\n");
+        return 1;
+    }
+    if (set_scheduler(0, SCHED_OTHER, 0) != 0) {
+        printf("sched_rr failed: restore SCHED_OTHER\n");
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    if (check_params())
+        return 1;
+
+    int fd = memfd_create("sched_rr", 0);
+    if (fd < 0 || ftruncate(fd, 4096) != 0) {
+        printf("sched_rr failed: memfd errno %d\n", errno);
+        return 1;
+    }
+    sh = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (sh == MAP_FAILED) {
+        printf("sched_rr failed: mmap errno %d\n", errno);
+        return 1;
+    }
+    // 子进程继承 CPU 亲和性，都在 CPU 0 上运行
+    cpu_set_t set;
+    CPU_ZERO(&set);
+    CPU_SET(0, &set);
+    if (sched_setaffinity(0, sizeof(set), &set) != 0) {
+        printf("sched_rr failed: sched_setaffinity errno %d\n", errno);
+        return 1;
+    }
+
+    // SCHED_RR：时间片用完后轮到另一个子进程，两者的计数交替增长
+    if (run_pair(SCHED_RR))
+        return 1;
+    if (!sh->overlap[0] || !sh->overlap[1]) {
+        printf("sched_rr failed: SCHED_RR tasks did not alternate (%d, %d)\n", sh->overlap[0],
+               sh->overlap[1]);
+        return 1;
+    }
+
+    // SCHED_FIFO：先运行的子进程一直运行到退出，另一个子进程在此期间没有运行
+    if (run_pair(SCHED_FIFO))
+        return 1;
+    if (sh->overlap[0] || sh->overlap[1]) {
+        printf("sched_rr failed: SCHED_FIFO tasks interleaved (%d, %d)\n", sh->overlap[0],
+               sh->overlap[1]);
+        return 1;
+    }
+
+    printf("sched_rr passed!\n");
+    return 0;
+}
@@ -0,0 +1,156 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/sem.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define ROUNDS 500
+
+union semun {
+    int val;
+    struct semid_ds *buf;
+    unsigned short *array;
+};
+
+static int op(int id, unsigned short num, short value, short flags)
+{
+    struct sembuf sop = {.sem_num = num, .sem_op = value, .sem_flg = flags};
+    return semop(id, &sop, 1);
+}
+
+static int wait_child(pid_t pid)
+{
+    int status;
+    return waitpid(pid, &status, 0) == pid && WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+int main(void)
+{
+    int id = semget(IPC_PRIVATE, 2, IPC_CREAT | 0600);
+    if (id < 0) {
+        printf("sem_undo failed: semget errno %d\n", errno);
+        return 1;
+    }
+
+    // 两个进程用信号量 0 作为互斥锁，轮流增加共享内存中的计数
+    int fd = memfd_create("sem_undo", 0);
+    if (fd < 0 || ftruncate(fd, sizeof(int)) != 0) {
+        printf("sem_undo failed: memfd errno %d\n", errno);
+        return 1;
+    }
+    volatile int *counter = mmap(NULL, sizeof(int), PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if (counter == MAP_FAILED) {
+        printf("sem_undo failed: mmap errno %d\n", errno);
+        return 1;
+    }
+    if (semctl(id, 0, SETVAL, (union semun){.val = 1}) != 0) {
+        printf("sem_undo failed: SETVAL errno %d\n", errno);
+        return 1;
+    }
+    pid_t pid = fork();
+    for (int i = 0; i < ROUNDS; i++) {
+        if (op(id, 0, -1, SEM_UNDO) != 0) {
+            printf("sem_undo failed: lock errno %d\n", errno);
+            return 1;
+        }
+        int value = *counter;
+        sched_yield();
+        *counter = value + 1;
+        if (op(id, 0, 1, SEM_UNDO) != 0) {
+            printf("sem_undo failed: unlock errno %d\n", errno);
+            return 1;
+        }
+    }
+    if (pid == 0)
+        return 0;
+    if (wait_child(pid) != 0 || *counter != 2 * ROUNDS) {
+        printf("sem_undo failed: counter %d\n", *counter);
+        return 1;
+    }
+
+    // 持有锁的进程退出后，SEM_UNDO 释放它
+    pid = fork();
+    if (pid == 0)
+        return op(id, 0, -1, SEM_UNDO) != 0;
+    if (wait_child(pid) != 0 || semctl(id, 0, GETVAL) != 1 || semctl(id, 0, GETPID) != pid) {
+        printf("sem_undo failed: value after exit %d\n", semctl(id, 0, GETVAL));
+        return 1;
+    }
+
+    // 一组操作中有一个需要等待时，其他操作也不执行
+    unsigned short vals[2] = {3, 0};
+    if (semctl(id, 0, SETALL, (union semun){.array = vals}) != 0) {
+        printf("sem_undo failed: SETALL errno %d\n", errno);
+        return 1;
+    }
+    struct sembuf sops[2] = {{0, -1, 0}, {1, -1, IPC_NOWAIT}};
+    if (semop(id, sops, 2) != -1 || errno != EAGAIN || semctl(id, 0, GETVAL) != 3) {
+        printf("sem_undo failed: partial semop errno %d\n", errno);
+        return 1;
+    }
+
+    // 等待的进程在另一个进程增大信号量后完成整组操作
+    pid = fork();
+    if (pid == 0) {
+        sops[1].sem_flg = 0;
+        return semop(id, sops, 2) != 0;
+    }
+    while (semctl(id, 1, GETNCNT) != 1)
+        sched_yield();
+    if (op(id, 1, 1, 0) != 0 || wait_child(pid) != 0) {
+        printf("sem_undo failed: blocked semop\n");
+        return 1;
+    }
+    if (semctl(id, 0, GETALL, (union semun){.array = vals}) != 0 || vals[0] != 2 || vals[1] != 0) {
+        printf("sem_undo failed: GETALL %d %d\n", vals[0], vals[1]);
+        return 1;
+    }
+
+    // 等待信号量变为 0，超时返回 EAGAIN
+    struct timespec timeout = {0, 20000000};
+    struct timespec start, end;
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    struct sembuf zero = {0, 0, 0};
+    if (semtimedop(id, &zero, 1, &timeout) != -1 || errno != EAGAIN) {
+        printf("sem_undo failed: semtimedop errno %d\n", errno);
+        return 1;
+    }
+    clock_gettime(CLOCK_MONOTONIC, &end);
+    long waited = (end.tv_sec - start.tv_sec) * 1000000000L + end.tv_nsec - start.tv_nsec;
+    if (waited < 20000000) {
+        printf("sem_undo failed: semtimedop returned after %ld ns\n", waited);
+        return 1;
+    }
+
+    // 参数错误
+    if (semctl(id, 0, SETVAL, (union semun){.val = 40000}) != -1 || errno != ERANGE) {
+        printf("sem_undo failed: SETVAL out of range errno %d\n", errno);
+        return 1;
+    }
+    if (op(id, 2, 1, 0) != -1 || errno != EFBIG) {
+        printf("sem_undo failed: semop on a missing semaphore errno %d\n", errno);
+        return 1;
+    }
+
+    // 删除集合唤醒等待者，它返回 EIDRM
+    pid = fork();
+    if (pid == 0)
+        return !(semtimedop(id, &zero, 1, NULL) == -1 && errno == EIDRM);
+    while (semctl(id, 0, GETZCNT) != 1)
+        sched_yield();
+    if (semctl(id, 0, IPC_RMID) != 0 || wait_child(pid) != 0) {
+        printf("sem_undo failed: IPC_RMID errno %d\n", errno);
+        return 1;
+    }
+    if (semctl(id, 0, GETVAL) != -1 || errno != EINVAL) {
+        printf("sem_undo failed: GETVAL of a removed set errno %d\n", errno);
+        return 1;
+    }
+
+    printf("sem_undo passed!\n");
+    return 0;
+}
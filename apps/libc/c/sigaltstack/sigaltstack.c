@@ -0,0 +1,90 @@
+#include <errno.h>
+#include <setjmp.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static char altstack[64 * 1024];
+static sigjmp_buf env;
+static volatile int usr1_count;
+static volatile int on_altstack;
+static volatile int change_refused;
+
+static void usr1_handler(int sig)
+{
+    (void)sig;
+    usr1_count++;
+}
+
+static void segv_handler(int sig)
+{
+    (void)sig;
+    stack_t cur;
+    sigaltstack(NULL, &cur);
+    on_altstack = (cur.ss_flags & SS_ONSTACK) != 0;
+
+    // 正在备用信号栈上执行时不能修改它
+    stack_t ss = {.ss_sp = altstack, .ss_size = sizeof(altstack), .ss_flags = 0};
+    errno = 0;
+    change_refused = sigaltstack(&ss, NULL) == -1 && errno == EPERM;
+    siglongjmp(env, 1);
+}
+
+// 不断递归直到栈溢出
+static int recurse(int depth)
+{
+    volatile char buf[1024];
+    buf[0] = (char)depth;
+    return recurse(depth + 1) + buf[0];
+}
+
+int main()
+{
+    // 普通的信号处理函数返回后，程序从被打断处继续执行
+    signal(SIGUSR1, usr1_handler);
+    kill(getpid(), SIGUSR1);
+    if (usr1_count != 1) {
+        printf("sigaltstack failed: SIGUSR1 handler ran %d times\n", usr1_count);
+        return 1;
+    }
+
+    stack_t ss = {.ss_sp = altstack, .ss_size = MINSIGSTKSZ - 1, .ss_flags = 0};
+    errno = 0;
+    if (sigaltstack(&ss, NULL) != -1 || errno != ENOMEM) {
+        printf("sigaltstack failed: undersized stack accepted\n");
+        return 1;
+    }
+    ss.ss_size = sizeof(altstack);
+    if (sigaltstack(&ss, NULL) != 0) {
+        printf("sigaltstack failed: cannot install the alternate stack\n");
+        return 1;
+    }
+
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = segv_handler;
+    sa.sa_flags = SA_ONSTACK;
+    sigaction(SIGSEGV, &sa, NULL);
+
+    if (sigsetjmp(env, 1) == 0) {
+        recurse(0);
+        printf("sigaltstack failed: recursion returned\n");
+        return 1;
+    }
+    if (!on_altstack || !change_refused) {
+        printf("sigaltstack failed: on_altstack=%d change_refused=%d\n", on_altstack,
+               change_refused);
+        return 1;
+    }
+
+    // 离开处理函数之后，不再处于备用信号栈上
+    stack_t cur;
+    if (sigaltstack(NULL, &cur) != 0 || (cur.ss_flags & SS_ONSTACK) || cur.ss_sp != altstack) {
+        printf("sigaltstack failed: unexpected state after the handler\n");
+        return 1;
+    }
+
+    printf("sigaltstack passed!\n");
+    return 0;
+}
@@ -0,0 +1,85 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define CHILDREN 5
+
+static volatile int chld_count;
+static volatile int status_sum;
+static volatile int bad_info;
+
+static void chld_handler(int sig, siginfo_t *info, void *ucontext)
+{
+    (void)sig;
+    (void)ucontext;
+    if (info->si_code != CLD_EXITED || info->si_pid <= 0)
+        bad_info = 1;
+    chld_count++;
+    status_sum += info->si_status;
+}
+
+int main()
+{
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_sigaction = chld_handler;
+    sa.sa_flags = SA_SIGINFO;
+    sigaction(SIGCHLD, &sa, NULL);
+
+    // 阻塞 SIGCHLD，只在 sigsuspend 中接收，避免检查与睡眠之间的竞争
+    sigset_t block, old;
+    sigemptyset(&block);
+    sigaddset(&block, SIGCHLD);
+    sigprocmask(SIG_BLOCK, &block, &old);
+
+    // 普通信号不会排队，因此逐个创建子进程，每次等到 SIGCHLD 再创建下一个
+    for (int i = 1; i <= CHILDREN; i++) {
+        if (fork() == 0)
+            _exit(i);
+        while (chld_count < i)
+            sigsuspend(&old);
+    }
+    if (chld_count != CHILDREN || status_sum != 15 || bad_info) {
+        printf("sigchld failed: count=%d sum=%d bad_info=%d\n", chld_count, status_sum,
+               bad_info);
+        return 1;
+    }
+
+    // 设置了处理函数的子进程仍是僵尸进程，可以被回收
+    int reaped = 0;
+    while (waitpid(-1, NULL, WNOHANG) > 0)
+        reaped++;
+    if (reaped != CHILDREN) {
+        printf("sigchld failed: reaped %d zombies\n", reaped);
+        return 1;
+    }
+
+    // SIGCHLD 被忽略时子进程被自动回收，wait 在其退出后返回 ECHILD
+    signal(SIGCHLD, SIG_IGN);
+    if (fork() == 0)
+        _exit(0);
+    errno = 0;
+    if (wait(NULL) != -1 || errno != ECHILD) {
+        printf("sigchld failed: wait did not return ECHILD with SIG_IGN\n");
+        return 1;
+    }
+
+    // SA_NOCLDWAIT 同样使子进程被自动回收
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = SIG_DFL;
+    sa.sa_flags = SA_NOCLDWAIT;
+    sigaction(SIGCHLD, &sa, NULL);
+    if (fork() == 0)
+        _exit(0);
+    errno = 0;
+    if (wait(NULL) != -1 || errno != ECHILD) {
+        printf("sigchld failed: wait did not return ECHILD with SA_NOCLDWAIT\n");
+        return 1;
+    }
+
+    printf("sigchld passed!\n");
+    return 0;
+}
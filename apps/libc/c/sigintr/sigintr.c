@@ -0,0 +1,170 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile int alarm_count;
+static timer_t timer;
+
+static void alarm_handler(int sig)
+{
+    (void)sig;
+    alarm_count++;
+}
+
+// 设置 SIGALRM 的处理函数，flags 决定被打断的系统调用是否自动重新执行
+static void set_handler(int flags)
+{
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = alarm_handler;
+    sa.sa_flags = flags;
+    sigaction(SIGALRM, &sa, NULL);
+}
+
+// ms 毫秒后向自身发送一次 SIGALRM
+static void arm(long ms)
+{
+    struct itimerspec its;
+    memset(&its, 0, sizeof(its));
+    its.it_value.tv_sec = ms / 1000;
+    its.it_value.tv_nsec = ms % 1000 * 1000000;
+    timer_settime(timer, 0, &its, NULL);
+}
+
+static void sleep_ms(long ms)
+{
+    struct timespec ts = {ms / 1000, ms % 1000 * 1000000};
+    nanosleep(&ts, NULL);
+}
+
+int main()
+{
+    if (timer_create(CLOCK_MONOTONIC, NULL, &timer) != 0) {
+        printf("sigintr failed: timer_create errno %d\n", errno);
+        return 1;
+    }
+    set_handler(0);
+
+    // nanosleep 被打断后返回 EINTR，并报告剩余时间
+    struct timespec req = {2, 0}, rem = {0, 0};
+    arm(50);
+    errno = 0;
+    if (nanosleep(&req, &rem) != -1 || errno != EINTR || alarm_count != 1) {
+        printf("sigintr failed: nanosleep errno %d, handler ran %d times\n", errno, alarm_count);
+        return 1;
+    }
+    if (rem.tv_sec < 1) {
+        printf("sigintr failed: nanosleep remaining %lds\n", (long)rem.tv_sec);
+        return 1;
+    }
+
+    // 没有数据的管道读端被打断
+    int fds[2];
+    char c;
+    pipe(fds);
+    arm(50);
+    errno = 0;
+    if (read(fds[0], &c, 1) != -1 || errno != EINTR || alarm_count != 2) {
+        printf("sigintr failed: pipe read errno %d, handler ran %d times\n", errno, alarm_count);
+        return 1;
+    }
+
+    // 等待子进程被打断，之后仍能回收它
+    pid_t pid = fork();
+    if (pid == 0) {
+        sleep_ms(300);
+        _exit(7);
+    }
+    arm(50);
+    int status;
+    errno = 0;
+    if (waitpid(pid, &status, 0) != -1 || errno != EINTR || alarm_count != 3) {
+        printf("sigintr failed: waitpid errno %d, handler ran %d times\n", errno, alarm_count);
+        return 1;
+    }
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 7) {
+        printf("sigintr failed: child not reaped after EINTR\n");
+        return 1;
+    }
+
+    // 设置 SA_RESTART 后，读管道在处理函数返回后继续等待，直到子进程写入数据
+    set_handler(SA_RESTART);
+    pid = fork();
+    if (pid == 0) {
+        sleep_ms(200);
+        write(fds[1], "x", 1);
+        _exit(0);
+    }
+    arm(50);
+    if (read(fds[0], &c, 1) != 1 || c != 'x' || alarm_count != 4) {
+        printf("sigintr failed: pipe read not restarted, handler ran %d times\n", alarm_count);
+        return 1;
+    }
+    waitpid(pid, &status, 0);
+
+#ifdef SYS_pause
+    // pause 即使在 SA_RESTART 时也返回 EINTR
+    arm(50);
+    errno = 0;
+    if (syscall(SYS_pause) != -1 || errno != EINTR || alarm_count != 5) {
+        printf("sigintr failed: pause errno %d, handler ran %d times\n", errno, alarm_count);
+        return 1;
+    }
+#endif
+
+    // 处理方式为终止进程的信号结束阻塞中的子进程
+    pid = fork();
+    if (pid == 0) {
+        read(fds[0], &c, 1);
+        _exit(0);
+    }
+    sleep_ms(50);
+    kill(pid, SIGTERM);
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status) || WTERMSIG(status) != SIGTERM) {
+        printf("sigintr failed: blocked child not killed by SIGTERM\n");
+        return 1;
+    }
+
+    // sigqueue 发送的附加数据可被 sigwaitinfo 取出
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    union sigval value = {.sival_int = 42};
+    if (sigqueue(getpid(), SIGUSR1, value) != 0) {
+        printf("sigintr failed: sigqueue errno %d\n", errno);
+        return 1;
+    }
+    siginfo_t info;
+    if (sigwaitinfo(&set, &info) != SIGUSR1 || info.si_code != SI_QUEUE ||
+        info.si_value.sival_int != 42 || info.si_pid != getpid()) {
+        printf("sigintr failed: sigwaitinfo got code %d value %d\n", info.si_code,
+               info.si_value.sival_int);
+        return 1;
+    }
+
+    // 向其他进程发送时不能冒充 kill
+    pid = fork();
+    if (pid == 0) {
+        sleep_ms(50);
+        _exit(0);
+    }
+    memset(&info, 0, sizeof(info));
+    info.si_code = SI_USER;
+    errno = 0;
+    if (syscall(SYS_rt_sigqueueinfo, pid, SIGUSR1, &info) != -1 || errno != EPERM) {
+        printf("sigintr failed: forged SI_USER errno %d\n", errno);
+        return 1;
+    }
+    waitpid(pid, &status, 0);
+
+    timer_delete(timer);
+    printf("sigintr passed!\n");
+    return 0;
+}
@@ -0,0 +1,114 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile int usr1_count;
+static volatile int usr2_count;
+
+static void usr1_handler(int sig)
+{
+    (void)sig;
+    usr1_count++;
+}
+
+static void usr2_handler(int sig)
+{
+    (void)sig;
+    usr2_count++;
+}
+
+// 在子进程中阻塞 SIGUSR2，通知父进程后用 sigsuspend 等待它
+static int suspend_child(int fd)
+{
+    sigset_t block, empty, cur;
+    sigemptyset(&block);
+    sigaddset(&block, SIGUSR2);
+    sigprocmask(SIG_BLOCK, &block, NULL);
+    signal(SIGUSR2, usr2_handler);
+    write(fd, "x", 1);
+
+    sigemptyset(&empty);
+    errno = 0;
+    if (sigsuspend(&empty) != -1 || errno != EINTR) {
+        printf("sigwait failed: sigsuspend did not return EINTR\n");
+        return 1;
+    }
+    if (usr2_count != 1) {
+        printf("sigwait failed: SIGUSR2 handler ran %d times\n", usr2_count);
+        return 1;
+    }
+    // 返回后恢复原先的掩码
+    sigprocmask(SIG_BLOCK, NULL, &cur);
+    if (!sigismember(&cur, SIGUSR2)) {
+        printf("sigwait failed: mask not restored after sigsuspend\n");
+        return 1;
+    }
+    return 0;
+}
+
+int main()
+{
+    signal(SIGUSR1, usr1_handler);
+
+    // 被阻塞的信号保持待处理状态
+    sigset_t set, pending;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    kill(getpid(), SIGUSR1);
+    sigemptyset(&pending);
+    if (sigpending(&pending) != 0 || !sigismember(&pending, SIGUSR1) || usr1_count != 0) {
+        printf("sigwait failed: SIGUSR1 is not pending\n");
+        return 1;
+    }
+
+    // sigtimedwait 取出信号而不执行处理函数
+    struct timespec zero = {0, 0};
+    siginfo_t info;
+    if (sigtimedwait(&set, &info, &zero) != SIGUSR1) {
+        printf("sigwait failed: sigtimedwait did not return SIGUSR1\n");
+        return 1;
+    }
+    if (info.si_signo != SIGUSR1 || info.si_code != SI_USER || info.si_pid != getpid() ||
+        usr1_count != 0) {
+        printf("sigwait failed: unexpected siginfo\n");
+        return 1;
+    }
+    sigpending(&pending);
+    if (sigismember(&pending, SIGUSR1)) {
+        printf("sigwait failed: SIGUSR1 still pending\n");
+        return 1;
+    }
+
+    // 没有信号到达时超时
+    struct timespec short_wait = {0, 10 * 1000 * 1000};
+    errno = 0;
+    if (sigtimedwait(&set, &info, &short_wait) != -1 || errno != EAGAIN) {
+        printf("sigwait failed: sigtimedwait did not time out\n");
+        return 1;
+    }
+
+    int fds[2];
+    pipe(fds);
+    pid_t pid = fork();
+    if (pid == 0) {
+        close(fds[0]);
+        return suspend_child(fds[1]);
+    }
+    close(fds[1]);
+    char c;
+    read(fds[0], &c, 1);
+    kill(pid, SIGUSR2);
+    int status;
+    waitpid(pid, &status, 0);
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("sigwait failed: child status %d\n", status);
+        return 1;
+    }
+
+    printf("sigwait passed!\n");
+    return 0;
+}
@@ -0,0 +1,276 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#ifndef TCP_NODELAY
+#define TCP_NODELAY 1
+#endif
+#ifndef IPPROTO_TCP
+#define IPPROTO_TCP 6
+#endif
+
+#define FILE_PATH "/socket_rights.tmp"
+#define CONTENT "from the parent"
+
+static volatile int sigpipe_count;
+
+static void on_sigpipe(int sig)
+{
+    (void)sig;
+    sigpipe_count++;
+}
+
+// 通过 SCM_RIGHTS 发送文件描述符 fd，附带一个字节的数据 byte
+static int send_fd(int sock, int fd, char byte)
+{
+    struct iovec iov = {.iov_base = &byte, .iov_len = 1};
+    char control[CMSG_SPACE(sizeof(int))];
+    memset(control, 0, sizeof(control));
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_RIGHTS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+    memcpy(CMSG_DATA(cmsg), &fd, sizeof(int));
+    return sendmsg(sock, &msg, 0) == 1 ? 0 : -1;
+}
+
+// 接收一个通过 SCM_RIGHTS 传递的文件描述符，附带的字节写入 byte，失败时返回 -1
+static int recv_fd(int sock, char *byte, int flags)
+{
+    struct iovec iov = {.iov_base = byte, .iov_len = 1};
+    char control[CMSG_SPACE(sizeof(int))];
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+    if (recvmsg(sock, &msg, flags) != 1 || (msg.msg_flags & MSG_CTRUNC))
+        return -1;
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+    if (!cmsg || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS ||
+        cmsg->cmsg_len != CMSG_LEN(sizeof(int)))
+        return -1;
+    int fd;
+    memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+    return fd;
+}
+
+// 子进程：接收文件和管道写端，通过它们与父进程通信，最后半关闭套接字
+static int child(int sock)
+{
+    char byte;
+    int file = recv_fd(sock, &byte, 0);
+    if (file < 0 || byte != 'f') {
+        printf("socket_rights failed: child recv file fd\n");
+        return 1;
+    }
+    char buf[64] = {0};
+    if (lseek(file, 0, SEEK_SET) != 0 || read(file, buf, sizeof(buf)) != (ssize_t)strlen(CONTENT) ||
+        strcmp(buf, CONTENT) != 0) {
+        printf("socket_rights failed: child read passed file: %s\n", buf);
+        return 1;
+    }
+    close(file);
+
+    // 带 MSG_CMSG_CLOEXEC 收到的描述符设置了 close-on-exec
+    int pipe_w = recv_fd(sock, &byte, MSG_CMSG_CLOEXEC);
+    if (pipe_w < 0 || byte != 'p' || !(fcntl(pipe_w, F_GETFD) & FD_CLOEXEC)) {
+        printf("socket_rights failed: child recv pipe fd\n");
+        return 1;
+    }
+    if (write(pipe_w, "ok", 2) != 2) {
+        printf("socket_rights failed: child write to passed pipe\n");
+        return 1;
+    }
+    close(pipe_w);
+
+    // 半关闭：不再发送，但仍能接收父进程的数据
+    if (send(sock, "bye", 3, 0) != 3 || shutdown(sock, SHUT_WR) != 0) {
+        printf("socket_rights failed: child shutdown\n");
+        return 1;
+    }
+    if (send(sock, "x", 1, MSG_NOSIGNAL) != -1 || errno != EPIPE) {
+        printf("socket_rights failed: send after SHUT_WR\n");
+        return 1;
+    }
+    memset(buf, 0, sizeof(buf));
+    if (recv(sock, buf, sizeof(buf), 0) != 3 || strcmp(buf, "ack") != 0) {
+        printf("socket_rights failed: child recv after SHUT_WR\n");
+        return 1;
+    }
+    return 0;
+}
+
+// 检查套接字选项
+static int check_options(int sock)
+{
+    int val = 8192;
+    socklen_t len = sizeof(val);
+    if (setsockopt(sock, SOL_SOCKET, SO_SNDBUF, &val, sizeof(val)) != 0 ||
+        getsockopt(sock, SOL_SOCKET, SO_SNDBUF, &val, &len) != 0 || len != sizeof(val) || val != 16384) {
+        printf("socket_rights failed: SO_SNDBUF %d\n", val);
+        return 1;
+    }
+    val = 8192;
+    len = sizeof(val);
+    if (setsockopt(sock, SOL_SOCKET, SO_RCVBUF, &val, sizeof(val)) != 0 ||
+        getsockopt(sock, SOL_SOCKET, SO_RCVBUF, &val, &len) != 0 || val != 16384) {
+        printf("socket_rights failed: SO_RCVBUF %d\n", val);
+        return 1;
+    }
+    len = sizeof(val);
+    if (getsockopt(sock, SOL_SOCKET, SO_ERROR, &val, &len) != 0 || val != 0) {
+        printf("socket_rights failed: SO_ERROR %d\n", val);
+        return 1;
+    }
+    len = sizeof(val);
+    if (getsockopt(sock, SOL_SOCKET, SO_TYPE, &val, &len) != 0 || val != SOCK_STREAM) {
+        printf("socket_rights failed: SO_TYPE %d\n", val);
+        return 1;
+    }
+    struct linger linger = {.l_onoff = 1, .l_linger = 5};
+    len = sizeof(linger);
+    if (setsockopt(sock, SOL_SOCKET, SO_LINGER, &linger, sizeof(linger)) != 0) {
+        printf("socket_rights failed: set SO_LINGER\n");
+        return 1;
+    }
+    memset(&linger, 0, sizeof(linger));
+    if (getsockopt(sock, SOL_SOCKET, SO_LINGER, &linger, &len) != 0 || len != sizeof(linger) ||
+        linger.l_onoff != 1 || linger.l_linger != 5) {
+        printf("socket_rights failed: get SO_LINGER\n");
+        return 1;
+    }
+    // Unix 域套接字没有 TCP 选项
+    val = 1;
+    if (setsockopt(sock, IPPROTO_TCP, TCP_NODELAY, &val, sizeof(val)) != -1 || errno != EOPNOTSUPP) {
+        printf("socket_rights failed: TCP_NODELAY on a Unix socket\n");
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    int sv[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sv) != 0) {
+        printf("socket_rights failed: socketpair\n");
+        return 1;
+    }
+    if (check_options(sv[0]) != 0)
+        return 1;
+
+    // MSG_DONTWAIT 与 MSG_PEEK
+    char buf[64];
+    if (recv(sv[1], buf, sizeof(buf), MSG_DONTWAIT) != -1 || errno != EAGAIN) {
+        printf("socket_rights failed: MSG_DONTWAIT on an empty socket\n");
+        return 1;
+    }
+    // 缓冲区的地址无效时返回 EFAULT，不发送任何数据
+    volatile unsigned long bad_addr = 8;
+    errno = 0;
+    if (send(sv[0], (const void *)bad_addr, 5, 0) != -1 || errno != EFAULT) {
+        printf("socket_rights failed: send from a bad address\n");
+        return 1;
+    }
+    if (send(sv[0], "hello", 5, 0) != 5) {
+        printf("socket_rights failed: send\n");
+        return 1;
+    }
+    memset(buf, 0, sizeof(buf));
+    if (recv(sv[1], buf, sizeof(buf), MSG_PEEK) != 5 || strcmp(buf, "hello") != 0) {
+        printf("socket_rights failed: MSG_PEEK\n");
+        return 1;
+    }
+    memset(buf, 0, sizeof(buf));
+    if (recv(sv[1], buf, sizeof(buf), MSG_DONTWAIT) != 5 || strcmp(buf, "hello") != 0) {
+        printf("socket_rights failed: recv after MSG_PEEK\n");
+        return 1;
+    }
+
+    // 通过 SCM_RIGHTS 把文件和管道写端传给子进程
+    int file = open(FILE_PATH, O_CREAT | O_TRUNC | O_RDWR, 0644);
+    int pipefd[2];
+    if (file < 0 || pipe(pipefd) != 0 || write(file, CONTENT, strlen(CONTENT)) != (ssize_t)strlen(CONTENT)) {
+        printf("socket_rights failed: create file and pipe\n");
+        return 1;
+    }
+    pid_t pid = fork();
+    if (pid == 0) {
+        close(sv[0]);
+        close(file);
+        close(pipefd[0]);
+        close(pipefd[1]);
+        return child(sv[1]);
+    }
+    close(sv[1]);
+    if (send_fd(sv[0], file, 'f') != 0 || send_fd(sv[0], pipefd[1], 'p') != 0) {
+        printf("socket_rights failed: sendmsg SCM_RIGHTS\n");
+        return 1;
+    }
+    // 父进程关闭自己的描述符后，文件仍由消息中的引用保持打开
+    close(file);
+    close(pipefd[1]);
+    unlink(FILE_PATH);
+
+    // 子进程通过收到的管道写端写入，关闭后管道读端读到文件结束
+    memset(buf, 0, sizeof(buf));
+    if (read(pipefd[0], buf, sizeof(buf)) != 2 || strcmp(buf, "ok") != 0) {
+        printf("socket_rights failed: read from the pipe passed to the child\n");
+        return 1;
+    }
+    if (read(pipefd[0], buf, sizeof(buf)) != 0) {
+        printf("socket_rights failed: pipe not closed by the child\n");
+        return 1;
+    }
+
+    // 子进程半关闭后，父进程读完数据读到 0，但仍可以发送
+    memset(buf, 0, sizeof(buf));
+    if (recv(sv[0], buf, sizeof(buf), 0) != 3 || strcmp(buf, "bye") != 0) {
+        printf("socket_rights failed: recv before the half-close\n");
+        return 1;
+    }
+    if (recv(sv[0], buf, sizeof(buf), 0) != 0) {
+        printf("socket_rights failed: no end of stream after SHUT_WR\n");
+        return 1;
+    }
+    if (send(sv[0], "ack", 3, 0) != 3) {
+        printf("socket_rights failed: send after the peer's SHUT_WR\n");
+        return 1;
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("socket_rights failed: child exited with %d\n", status);
+        return 1;
+    }
+
+    // 对端关闭后发送返回 EPIPE，不带 MSG_NOSIGNAL 时产生 SIGPIPE
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = on_sigpipe;
+    sigaction(SIGPIPE, &sa, NULL);
+    if (send(sv[0], "x", 1, MSG_NOSIGNAL) != -1 || errno != EPIPE || sigpipe_count != 0) {
+        printf("socket_rights failed: MSG_NOSIGNAL\n");
+        return 1;
+    }
+    if (send(sv[0], "x", 1, 0) != -1 || errno != EPIPE || sigpipe_count != 1) {
+        printf("socket_rights failed: SIGPIPE count %d\n", sigpipe_count);
+        return 1;
+    }
+    close(sv[0]);
+    close(pipefd[0]);
+
+    printf("socket_rights passed!\n");
+    return 0;
+}
@@ -0,0 +1,55 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static const char *path = "stat_fault_file";
+
+static int expect_errno(const char *what, long ret, int err)
+{
+    if (ret != -1 || errno != err) {
+        printf("stat_fault failed: %s gives %ld errno %d instead of %d\n", what, ret, errno, err);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    int fd = open(path, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, "hello", 5) != 5) {
+        printf("stat_fault failed: create errno %d\n", errno);
+        return 1;
+    }
+    volatile unsigned long bad_addr = 8;
+    struct stat *bad = (struct stat *)bad_addr;
+    struct stat st;
+    int failed = 0;
+
+    // 结果的地址无效时返回 EFAULT。libc 可能先写入自己的缓冲区再转换，因此直接发起系统调用
+    errno = 0;
+    failed |= expect_errno("fstat", syscall(SYS_fstat, fd, bad), EFAULT);
+    errno = 0;
+    failed |= expect_errno("fstatat path", syscall(SYS_newfstatat, AT_FDCWD, path, bad, 0), EFAULT);
+    errno = 0;
+    failed |= expect_errno("fstatat empty path", syscall(SYS_newfstatat, fd, "", bad, AT_EMPTY_PATH), EFAULT);
+    // 文件描述符无效时返回 EBADF
+    errno = 0;
+    failed |= expect_errno("fstat bad fd", fstat(1000, &st), EBADF);
+    errno = 0;
+    failed |= expect_errno("fstatat bad fd", fstatat(1000, "", &st, AT_EMPTY_PATH), EBADF);
+
+    if (fstatat(fd, "", &st, AT_EMPTY_PATH) != 0 || st.st_size != 5) {
+        printf("stat_fault failed: fstatat with AT_EMPTY_PATH errno %d\n", errno);
+        failed = 1;
+    }
+    close(fd);
+    unlink(path);
+    if (failed)
+        return 1;
+    printf("stat_fault passed!\n");
+    return 0;
+}
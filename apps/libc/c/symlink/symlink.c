@@ -0,0 +1,102 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main(void)
+{
+    char buf[64];
+    struct stat st, lst;
+
+    int fd = openat(AT_FDCWD, "sl_file", O_CREAT | O_TRUNC | O_WRONLY, 0644);
+    if (fd < 0 || write(fd, "hello", 5) != 5 || close(fd) != 0) {
+        printf("symlink failed: create errno %d\n", errno);
+        return 1;
+    }
+
+    // 三个符号链接组成的链：sl_link3 -> sl_link2 -> sl_link1 -> sl_file
+    if (symlinkat("sl_file", AT_FDCWD, "sl_link1") != 0 ||
+        symlinkat("sl_link1", AT_FDCWD, "sl_link2") != 0 ||
+        symlinkat("sl_link2", AT_FDCWD, "sl_link3") != 0) {
+        printf("symlink failed: symlinkat errno %d\n", errno);
+        return 1;
+    }
+    if (symlinkat("sl_file", AT_FDCWD, "sl_link1") != -1 || errno != EEXIST) {
+        printf("symlink failed: duplicate symlinkat errno %d\n", errno);
+        return 1;
+    }
+
+    ssize_t n = readlinkat(AT_FDCWD, "sl_link3", buf, sizeof(buf));
+    if (n != 8 || memcmp(buf, "sl_link2", 8) != 0) {
+        printf("symlink failed: readlinkat returned %zd\n", n);
+        return 1;
+    }
+    if (readlinkat(AT_FDCWD, "sl_file", buf, sizeof(buf)) != -1 || errno != EINVAL) {
+        printf("symlink failed: readlinkat on a regular file errno %d\n", errno);
+        return 1;
+    }
+
+    // 通过链打开得到原文件的内容
+    fd = openat(AT_FDCWD, "sl_link3", O_RDONLY);
+    if (fd < 0 || read(fd, buf, sizeof(buf)) != 5 || memcmp(buf, "hello", 5) != 0) {
+        printf("symlink failed: open through chain errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+
+    // O_NOFOLLOW 打开符号链接失败
+    if (openat(AT_FDCWD, "sl_link1", O_RDONLY | O_NOFOLLOW) != -1 || errno != ELOOP) {
+        printf("symlink failed: O_NOFOLLOW errno %d\n", errno);
+        return 1;
+    }
+
+    // stat 跟随链接，lstat 返回链接本身
+    if (fstatat(AT_FDCWD, "sl_link3", &st, 0) != 0 ||
+        fstatat(AT_FDCWD, "sl_link3", &lst, AT_SYMLINK_NOFOLLOW) != 0) {
+        printf("symlink failed: fstatat errno %d\n", errno);
+        return 1;
+    }
+    if (!S_ISREG(st.st_mode) || st.st_size != 5) {
+        printf("symlink failed: stat mode %o size %ld\n", st.st_mode, (long)st.st_size);
+        return 1;
+    }
+    if (!S_ISLNK(lst.st_mode) || lst.st_size != 8) {
+        printf("symlink failed: lstat mode %o size %ld\n", lst.st_mode, (long)lst.st_size);
+        return 1;
+    }
+
+    // 指向自身的链接
+    if (symlinkat("sl_loop", AT_FDCWD, "sl_loop") != 0) {
+        printf("symlink failed: symlinkat loop errno %d\n", errno);
+        return 1;
+    }
+    if (openat(AT_FDCWD, "sl_loop", O_RDONLY) != -1 || errno != ELOOP) {
+        printf("symlink failed: open loop errno %d\n", errno);
+        return 1;
+    }
+    if (fstatat(AT_FDCWD, "sl_loop", &st, 0) != -1 || errno != ELOOP) {
+        printf("symlink failed: stat loop errno %d\n", errno);
+        return 1;
+    }
+
+    // 删除链接不影响它指向的文件
+    if (unlinkat(AT_FDCWD, "sl_link1", 0) != 0 || fstatat(AT_FDCWD, "sl_file", &st, 0) != 0) {
+        printf("symlink failed: unlink link errno %d\n", errno);
+        return 1;
+    }
+    // 链中间的链接被删除后，链变为悬空链接
+    if (openat(AT_FDCWD, "sl_link3", O_RDONLY) != -1 || errno != ENOENT) {
+        printf("symlink failed: dangling link errno %d\n", errno);
+        return 1;
+    }
+
+    unlinkat(AT_FDCWD, "sl_link2", 0);
+    unlinkat(AT_FDCWD, "sl_link3", 0);
+    unlinkat(AT_FDCWD, "sl_loop", 0);
+    unlinkat(AT_FDCWD, "sl_file", 0);
+
+    printf("symlink passed!\n");
+    return 0;
+}
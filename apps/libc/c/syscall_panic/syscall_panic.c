@@ -0,0 +1,39 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// 本内核特有的调试系统调用，使内核在系统调用中 panic
+#define SYS_debug_panic 502
+
+int main(void)
+{
+    pid_t pid = fork();
+    if (pid < 0) {
+        printf("syscall_panic failed: fork errno %d\n", errno);
+        return 1;
+    }
+    if (pid == 0) {
+        syscall(SYS_debug_panic);
+        _exit(0);
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid) {
+        printf("syscall_panic failed: waitpid errno %d\n", errno);
+        return 1;
+    }
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGSYS) {
+        printf("syscall_panic failed: child status %#x\n", status);
+        return 1;
+    }
+    // 内核仍在运行，其他系统调用不受影响
+    if (getpid() <= 0) {
+        printf("syscall_panic failed: getpid\n");
+        return 1;
+    }
+    printf("syscall_panic passed!\n");
+    return 0;
+}
@@ -0,0 +1,107 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define CALLS 1000
+#define BUCKETS 8
+
+// 向 /proc/syscalls 写入控制命令，返回 write 的结果
+static int control(const char *cmd)
+{
+    int fd = open("/proc/syscalls", O_WRONLY);
+    if (fd < 0)
+        return -1;
+    int ret = write(fd, cmd, strlen(cmd));
+    int saved = errno;
+    close(fd);
+    errno = saved;
+    return ret;
+}
+
+// 读取 getpid 的调用次数和各个桶中次数的和，没有调用过时为 0，失败时返回 -1
+static long getpid_count(long *hist_sum)
+{
+    FILE *f = fopen("/proc/syscalls", "r");
+    if (!f)
+        return -1;
+    char line[512];
+    long count = 0;
+    *hist_sum = 0;
+    while (fgets(line, sizeof(line), f)) {
+        char name[32];
+        long calls, nanos, hist[BUCKETS];
+        if (line[0] == '#')
+            continue;
+        if (sscanf(line, "%31s %ld %ld %ld %ld %ld %ld %ld %ld %ld %ld", name, &calls, &nanos, &hist[0], &hist[1],
+                   &hist[2], &hist[3], &hist[4], &hist[5], &hist[6], &hist[7]) != 3 + BUCKETS) {
+            count = -1;
+            break;
+        }
+        if (strcmp(name, "getpid") == 0) {
+            count = calls;
+            for (int i = 0; i < BUCKETS; i++)
+                *hist_sum += hist[i];
+        }
+    }
+    fclose(f);
+    return count;
+}
+
+static void call_getpid(void)
+{
+    for (int i = 0; i < CALLS; i++)
+        syscall(SYS_getpid);
+}
+
+int main()
+{
+    long hist;
+    if (control("1\n") < 0) {
+        printf("syscall_stats failed: cannot enable the statistics\n");
+        return 1;
+    }
+
+    // 打开统计后的每次 getpid 都被计入，耗时分布的总数与次数相同
+    long before = getpid_count(&hist);
+    call_getpid();
+    long after = getpid_count(&hist);
+    if (before < 0 || after < 0) {
+        printf("syscall_stats failed: cannot parse /proc/syscalls\n");
+        return 1;
+    }
+    if (after - before < CALLS || after - before > CALLS + 100) {
+        printf("syscall_stats failed: %d getpid calls counted as %ld\n", CALLS, after - before);
+        return 1;
+    }
+    if (hist != after) {
+        printf("syscall_stats failed: the histogram has %ld calls, the counter %ld\n", hist, after);
+        return 1;
+    }
+
+    // 关闭统计后不再计数
+    if (control("0") < 0) {
+        printf("syscall_stats failed: cannot disable the statistics\n");
+        return 1;
+    }
+    call_getpid();
+    if (getpid_count(&hist) != after) {
+        printf("syscall_stats failed: calls are counted after disabling\n");
+        return 1;
+    }
+
+    // 清零后没有 getpid 的记录，无法识别的命令返回 EINVAL
+    if (control("reset") < 0 || getpid_count(&hist) != 0) {
+        printf("syscall_stats failed: reset did not clear the counters\n");
+        return 1;
+    }
+    if (control("bogus") != -1 || errno != EINVAL) {
+        printf("syscall_stats failed: an unknown command did not fail with EINVAL\n");
+        return 1;
+    }
+
+    printf("syscall_stats passed!\n");
+    return 0;
+}
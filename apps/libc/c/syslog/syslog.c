@@ -0,0 +1,43 @@
+#include <stdio.h>
+#include <sys/klog.h>
+
+#define SYSLOG_ACTION_READ 2
+#define SYSLOG_ACTION_READ_ALL 3
+#define SYSLOG_ACTION_CLEAR 5
+#define SYSLOG_ACTION_SIZE_BUFFER 10
+
+static char buf[1 << 16];
+
+int main()
+{
+    int size = klogctl(SYSLOG_ACTION_SIZE_BUFFER, NULL, 0);
+    if (size <= 0) {
+        printf("syslog failed: buffer size %d\n", size);
+        return 1;
+    }
+
+    // 读取到的内容总是由完整的行组成
+    int n = klogctl(SYSLOG_ACTION_READ_ALL, buf, sizeof(buf));
+    if (n < 0 || (n > 0 && buf[n - 1] != '\n')) {
+        printf("syslog failed: read_all returned %d bytes\n", n);
+        return 1;
+    }
+    n = klogctl(SYSLOG_ACTION_READ, buf, 100);
+    if (n < 0 || n > 100 || (n > 0 && buf[n - 1] != '\n')) {
+        printf("syslog failed: read returned %d bytes\n", n);
+        return 1;
+    }
+
+    // 清空后 READ_ALL 不再返回旧的日志
+    if (klogctl(SYSLOG_ACTION_CLEAR, NULL, 0) != 0) {
+        printf("syslog failed: clear\n");
+        return 1;
+    }
+    if (klogctl(SYSLOG_ACTION_READ_ALL, buf, 0) != 0) {
+        printf("syslog failed: read_all with zero length\n");
+        return 1;
+    }
+
+    printf("syslog passed!\n");
+    return 0;
+}
@@ -0,0 +1,65 @@
+#include <stdio.h>
+#include <sys/resource.h>
+#include <sys/utsname.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static long to_usec(struct timeval tv)
+{
+    return tv.tv_sec * 1000000L + tv.tv_usec;
+}
+
+// 在用户态空转
+static int spin_user(void)
+{
+    volatile unsigned long x = 0;
+    for (unsigned long i = 0; i < 100000000; i++)
+        x += i;
+
+    struct rusage ru;
+    getrusage(RUSAGE_SELF, &ru);
+    long utime = to_usec(ru.ru_utime), stime = to_usec(ru.ru_stime);
+    if (utime <= 4 * stime) {
+        printf("time_split failed: user spinner utime=%ld stime=%ld\n", utime, stime);
+        return 1;
+    }
+    return 0;
+}
+
+// 反复执行系统调用
+static int spin_kernel(void)
+{
+    struct utsname buf;
+    for (int i = 0; i < 200000; i++)
+        uname(&buf);
+
+    struct rusage ru;
+    getrusage(RUSAGE_SELF, &ru);
+    long utime = to_usec(ru.ru_utime), stime = to_usec(ru.ru_stime);
+    if (stime <= 4 * utime) {
+        printf("time_split failed: syscall loop utime=%ld stime=%ld\n", utime, stime);
+        return 1;
+    }
+    return 0;
+}
+
+int main()
+{
+    // 两个子进程同时运行，使调度切换穿插在两者之间
+    pid_t user_pid = fork();
+    if (user_pid == 0)
+        return spin_user();
+    pid_t kernel_pid = fork();
+    if (kernel_pid == 0)
+        return spin_kernel();
+
+    int failed = 0, status;
+    waitpid(user_pid, &status, 0);
+    failed |= !WIFEXITED(status) || WEXITSTATUS(status) != 0;
+    waitpid(kernel_pid, &status, 0);
+    failed |= !WIFEXITED(status) || WEXITSTATUS(status) != 0;
+    if (failed)
+        return 1;
+    printf("time_split passed!\n");
+    return 0;
+}
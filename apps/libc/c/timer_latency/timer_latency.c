@@ -0,0 +1,108 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <limits.h>
+#include <linux/futex.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+#define ROUNDS 20
+// 睡眠 10 毫秒
+#define SLEEP_NS 10000000L
+// 时钟中断的间隔，内核每秒 100 次
+#define TICK_NS 10000000L
+
+static long now_ns(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec * 1000000000L + ts.tv_nsec;
+}
+
+static const struct timespec sleep_ts = {0, SLEEP_NS};
+
+static int do_nanosleep(void)
+{
+    return nanosleep(&sleep_ts, NULL);
+}
+
+static int do_ppoll(void)
+{
+    return ppoll(NULL, 0, &sleep_ts, NULL) == 0 ? 0 : -1;
+}
+
+static int do_futex(void)
+{
+    int word = 0;
+    long ret = syscall(SYS_futex, &word, FUTEX_WAIT_PRIVATE, 0, &sleep_ts, NULL, 0);
+    return ret == -1 && errno == ETIMEDOUT ? 0 : -1;
+}
+
+static timer_t timer;
+
+static int do_posix_timer(void)
+{
+    struct itimerspec its = {.it_value = sleep_ts};
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGALRM);
+    if (timer_settime(timer, 0, &its, NULL) != 0)
+        return -1;
+    return sigwaitinfo(&set, NULL) == SIGALRM ? 0 : -1;
+}
+
+static int cmp_long(const void *a, const void *b)
+{
+    long x = *(const long *)a, y = *(const long *)b;
+    return (x > y) - (x < y);
+}
+
+// 重复等待 10 毫秒，不能提前醒来，超出的时间的中位数应小于一个时钟中断的间隔
+static int measure(const char *name, int (*wait)(void))
+{
+    long late[ROUNDS];
+    for (int i = 0; i < ROUNDS; i++) {
+        long start = now_ns();
+        if (wait() != 0) {
+            printf("timer_latency failed: %s errno %d\n", name, errno);
+            return 1;
+        }
+        late[i] = now_ns() - start - SLEEP_NS;
+        if (late[i] < 0) {
+            printf("timer_latency failed: %s woke up %ld ns early\n", name, -late[i]);
+            return 1;
+        }
+    }
+    qsort(late, ROUNDS, sizeof(late[0]), cmp_long);
+    long median = late[ROUNDS / 2], max = late[ROUNDS - 1];
+    printf("timer_latency: %s(10ms) late by %ld us (median), %ld us (max)\n", name,
+           median / 1000, max / 1000);
+    if (median >= TICK_NS) {
+        printf("timer_latency failed: %s late by more than one tick\n", name);
+        return 1;
+    }
+    return 0;
+}
+
+int main(void)
+{
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGALRM);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    struct sigevent sev = {.sigev_notify = SIGEV_SIGNAL, .sigev_signo = SIGALRM};
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) != 0) {
+        printf("timer_latency failed: timer_create errno %d\n", errno);
+        return 1;
+    }
+    if (measure("nanosleep", do_nanosleep) || measure("ppoll", do_ppoll) ||
+        measure("futex", do_futex) || measure("timer", do_posix_timer))
+        return 1;
+    timer_delete(timer);
+    printf("timer_latency passed!\n");
+    return 0;
+}
@@ -0,0 +1,22 @@
+#include <stdio.h>
+#include <sys/times.h>
+#include <unistd.h>
+
+int main()
+{
+    long hz = sysconf(_SC_CLK_TCK);
+    struct tms start, end;
+    clock_t t0 = times(&start);
+    sleep(1);
+    clock_t t1 = times(&end);
+
+    long elapsed = t1 - t0;
+    long cpu = (end.tms_utime - start.tms_utime) + (end.tms_stime - start.tms_stime);
+    // 睡眠期间不占用 CPU，经过的时间应接近 1 秒
+    if (hz != 100 || elapsed < hz * 9 / 10 || elapsed > hz * 3 / 2 || cpu < 0 || cpu > hz / 10) {
+        printf("times failed: hz=%ld elapsed=%ld cpu=%ld\n", hz, elapsed, cpu);
+        return 1;
+    }
+    printf("times passed!\n");
+    return 0;
+}
@@ -0,0 +1,77 @@
+#include <signal.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+// 一直在用户态运算的子进程数，多于 CPU 数时才能检验抢占
+#define SPINNERS 2
+#define TICKS 10
+
+static double now(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec + ts.tv_nsec / 1e9;
+}
+
+static void sleep_ms(long ms)
+{
+    struct timespec ts = {ms / 1000, ms % 1000 * 1000000};
+    nanosleep(&ts, NULL);
+}
+
+int main()
+{
+    pid_t spinners[SPINNERS];
+    for (int i = 0; i < SPINNERS; i++) {
+        spinners[i] = fork();
+        if (spinners[i] == 0) {
+            volatile unsigned long n = 0;
+            for (;;) {
+                n++;
+            }
+        }
+    }
+
+    // 周期性地报告进度的子进程，不应被一直运算的子进程饿死
+    int fds[2];
+    pipe(fds);
+    pid_t ticker = fork();
+    if (ticker == 0) {
+        for (int i = 0; i < TICKS; i++) {
+            sleep_ms(10);
+            write(fds[1], "t", 1);
+        }
+        _exit(0);
+    }
+    close(fds[1]);
+
+    double start = now();
+    int ticks = 0;
+    char c;
+    while (read(fds[0], &c, 1) == 1) {
+        ticks++;
+    }
+    double elapsed = now() - start;
+
+    for (int i = 0; i < SPINNERS; i++) {
+        kill(spinners[i], SIGKILL);
+    }
+    for (int i = 0; i < SPINNERS; i++) {
+        int status;
+        if (waitpid(spinners[i], &status, 0) != spinners[i] || !WIFSIGNALED(status)) {
+            printf("timeslice failed: spinner %d was not killed\n", spinners[i]);
+            return 1;
+        }
+    }
+    waitpid(ticker, NULL, 0);
+
+    // 每轮最多等待所有运算的子进程各用完一个时间片，10 秒足够宽松
+    if (ticks != TICKS || elapsed > 10.0) {
+        printf("timeslice failed: %d ticks in %.2f s\n", ticks, elapsed);
+        return 1;
+    }
+    printf("timeslice passed!\n");
+    return 0;
+}
@@ -0,0 +1,53 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/utsname.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define BAD_PTR ((void *)1)
+
+int main(void)
+{
+    // 无效的用户地址不会使内核崩溃，而是返回 EFAULT
+    errno = 0;
+    if (syscall(SYS_uname, BAD_PTR) != -1 || errno != EFAULT) {
+        printf("uaccess_efault failed: uname errno %d\n", errno);
+        return 1;
+    }
+    errno = 0;
+    if (syscall(SYS_times, BAD_PTR) != -1 || errno != EFAULT) {
+        printf("uaccess_efault failed: times errno %d\n", errno);
+        return 1;
+    }
+    errno = 0;
+    if (syscall(SYS_gettimeofday, BAD_PTR, NULL) != -1 || errno != EFAULT) {
+        printf("uaccess_efault failed: gettimeofday errno %d\n", errno);
+        return 1;
+    }
+
+    // 与 Linux 一致，wait4 写入状态失败时子进程仍被回收
+    pid_t pid = fork();
+    if (pid == 0)
+        return 7;
+    errno = 0;
+    if (syscall(SYS_wait4, pid, BAD_PTR, 0, NULL) != -1 || errno != EFAULT) {
+        printf("uaccess_efault failed: wait4 errno %d\n", errno);
+        return 1;
+    }
+    errno = 0;
+    if (waitpid(pid, NULL, WNOHANG) != -1 || errno != ECHILD) {
+        printf("uaccess_efault failed: child not reaped, errno %d\n", errno);
+        return 1;
+    }
+
+    // 有效的地址仍然正常工作
+    struct utsname name;
+    if (uname(&name) != 0 || syscall(SYS_times, NULL) == -1) {
+        printf("uaccess_efault failed: valid buffers errno %d\n", errno);
+        return 1;
+    }
+
+    printf("uaccess_efault passed!\n");
+    return 0;
+}
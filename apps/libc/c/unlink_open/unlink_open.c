@@ -0,0 +1,141 @@
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define PATH "unlink_open_file"
+#define RENAMED "unlink_open_renamed"
+#define SIZE (64 * 1024)
+
+static char buf[SIZE];
+
+// 读出整个文件并检查内容，第 i 个字节应为 (char)(i * 7 + seed)
+static int check_contents(int fd, size_t size, int seed)
+{
+    if (lseek(fd, 0, SEEK_SET) != 0)
+        return -1;
+    size_t done = 0;
+    while (done < size) {
+        ssize_t n = read(fd, buf + done, size - done);
+        if (n <= 0)
+            return -1;
+        done += n;
+    }
+    for (size_t i = 0; i < size; i++) {
+        if (buf[i] != (char)(i * 7 + seed))
+            return -1;
+    }
+    return 0;
+}
+
+static int write_contents(int fd, size_t size, int seed)
+{
+    for (size_t i = 0; i < size; i++)
+        buf[i] = (char)(i * 7 + seed);
+    return lseek(fd, 0, SEEK_SET) == 0 && write(fd, buf, size) == (ssize_t)size ? 0 : -1;
+}
+
+// 当前目录中是否有名为 name 的项
+static int in_cwd(const char *name)
+{
+    DIR *dir = opendir(".");
+    struct dirent *entry;
+    int found = 0;
+    while (dir && (entry = readdir(dir)) != NULL) {
+        if (strcmp(entry->d_name, name) == 0)
+            found = 1;
+    }
+    if (dir)
+        closedir(dir);
+    return found;
+}
+
+int main(void)
+{
+    unlink(PATH);
+    unlink(RENAMED);
+
+    // 删除打开的文件后，名字消失，链接数为 0，但数据仍可读写
+    int fd = open(PATH, O_CREAT | O_RDWR, 0644);
+    int fd2 = open(PATH, O_RDWR);
+    if (fd < 0 || fd2 < 0 || write_contents(fd, 100, 1) != 0) {
+        printf("unlink_open failed: create errno %d\n", errno);
+        return 1;
+    }
+    if (unlink(PATH) != 0) {
+        printf("unlink_open failed: unlink errno %d\n", errno);
+        return 1;
+    }
+    struct stat st;
+    if (fstat(fd, &st) != 0 || st.st_nlink != 0 || st.st_size != 100) {
+        printf("unlink_open failed: fstat nlink %ld size %ld\n", (long)st.st_nlink,
+               (long)st.st_size);
+        return 1;
+    }
+    if (open(PATH, O_RDWR) != -1 || errno != ENOENT || in_cwd(PATH)) {
+        printf("unlink_open failed: name still exists\n");
+        return 1;
+    }
+
+    // 删除后写入更多数据，通过另一个描述符读到同样的内容
+    if (write_contents(fd, SIZE, 3) != 0 || fstat(fd2, &st) != 0 || st.st_size != SIZE ||
+        check_contents(fd2, SIZE, 3) != 0) {
+        printf("unlink_open failed: write after unlink errno %d\n", errno);
+        return 1;
+    }
+    if (ftruncate(fd2, SIZE / 2) != 0 || fstat(fd, &st) != 0 || st.st_size != SIZE / 2) {
+        printf("unlink_open failed: ftruncate after unlink errno %d\n", errno);
+        return 1;
+    }
+
+    // 同名的新文件与已删除的文件互不影响
+    int fd3 = open(PATH, O_CREAT | O_EXCL | O_RDWR, 0644);
+    if (fd3 < 0 || write_contents(fd3, 10, 5) != 0) {
+        printf("unlink_open failed: recreate errno %d\n", errno);
+        return 1;
+    }
+    if (fstat(fd3, &st) != 0 || st.st_nlink != 1 || st.st_size != 10 ||
+        check_contents(fd, SIZE / 2, 3) != 0) {
+        printf("unlink_open failed: recreated file nlink %ld size %ld\n", (long)st.st_nlink,
+               (long)st.st_size);
+        return 1;
+    }
+
+    // 关闭一个描述符后另一个仍可使用，全部关闭后文件被释放
+    close(fd);
+    if (check_contents(fd2, SIZE / 2, 3) != 0) {
+        printf("unlink_open failed: read after closing the other fd\n");
+        return 1;
+    }
+    close(fd2);
+    close(fd3);
+    if (check_contents(fd2, 1, 0) == 0) {
+        printf("unlink_open failed: closed fd still readable\n");
+        return 1;
+    }
+
+    // 重命名打开的文件后继续写入，新名字下的文件有完整的内容
+    fd = open(PATH, O_RDWR);
+    if (fd < 0 || rename(PATH, RENAMED) != 0 || write_contents(fd, SIZE, 9) != 0) {
+        printf("unlink_open failed: write after rename errno %d\n", errno);
+        return 1;
+    }
+    close(fd);
+    fd = open(RENAMED, O_RDONLY);
+    if (fd < 0 || fstat(fd, &st) != 0 || st.st_size != SIZE || check_contents(fd, SIZE, 9) != 0) {
+        printf("unlink_open failed: renamed file size %ld\n", (long)st.st_size);
+        return 1;
+    }
+    close(fd);
+    if (in_cwd(PATH) || !in_cwd(RENAMED)) {
+        printf("unlink_open failed: directory after rename\n");
+        return 1;
+    }
+    unlink(RENAMED);
+
+    printf("unlink_open passed!\n");
+    return 0;
+}
@@ -0,0 +1,125 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define DIR_NAME "unshare_dir"
+
+static char start[256];
+
+// 以 flags 创建子进程，不指定新的栈，子进程从 clone 返回处继续执行
+static pid_t clone_with(unsigned long flags)
+{
+    return syscall(SYS_clone, flags | SIGCHLD, 0, 0, 0, 0);
+}
+
+static int wait_ok(pid_t pid)
+{
+    int status;
+    return waitpid(pid, &status, 0) == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0;
+}
+
+// 当前工作目录是否为 start 下的 DIR_NAME
+static int in_dir(void)
+{
+    char cwd[256];
+    size_t len = strlen(start);
+    return getcwd(cwd, sizeof(cwd)) && strncmp(cwd, start, len) == 0 &&
+           strcmp(cwd + len + (start[len - 1] != '/'), DIR_NAME) == 0;
+}
+
+static int at_start(void)
+{
+    char cwd[256];
+    return getcwd(cwd, sizeof(cwd)) && strcmp(cwd, start) == 0;
+}
+
+int main(void)
+{
+    if (!getcwd(start, sizeof(start))) {
+        printf("unshare failed: getcwd errno %d\n", errno);
+        return 1;
+    }
+    if (mkdirat(AT_FDCWD, DIR_NAME, 0755) != 0 && errno != EEXIST) {
+        printf("unshare failed: mkdir errno %d\n", errno);
+        return 1;
+    }
+
+    // 没有与其他进程共享的资源，unshare 什么也不做
+    if (unshare(0) != 0 || unshare(CLONE_FILES) != 0 || unshare(CLONE_FS) != 0 ||
+        unshare(CLONE_FILES | CLONE_FS) != 0 || !at_start()) {
+        printf("unshare failed: unshare without sharing errno %d\n", errno);
+        return 1;
+    }
+
+    // 带 CLONE_FS 创建的子进程修改当前工作目录，父进程也能看到
+    pid_t pid = clone_with(CLONE_FS);
+    if (pid == 0)
+        _exit(chdir(DIR_NAME) == 0 ? 0 : 1);
+    if (pid < 0 || !wait_ok(pid) || !in_dir()) {
+        printf("unshare failed: CLONE_FS not shared\n");
+        return 1;
+    }
+    if (chdir(start) != 0) {
+        printf("unshare failed: chdir back errno %d\n", errno);
+        return 1;
+    }
+
+    // 子进程 unshare(CLONE_FS) 后修改当前工作目录，父进程不受影响，
+    // 而子进程的文件描述符不变
+    int fds[2];
+    if (pipe(fds) != 0) {
+        printf("unshare failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    pid = clone_with(CLONE_FS);
+    if (pid == 0) {
+        if (unshare(CLONE_FS) != 0 || !at_start() || chdir(DIR_NAME) != 0 || !in_dir())
+            _exit(1);
+        _exit(write(fds[1], "x", 1) == 1 ? 0 : 2);
+    }
+    char c;
+    if (pid < 0 || !wait_ok(pid) || read(fds[0], &c, 1) != 1 || c != 'x') {
+        printf("unshare failed: child after unshare(CLONE_FS)\n");
+        return 1;
+    }
+    if (!at_start()) {
+        printf("unshare failed: cwd changed after child unshared\n");
+        return 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    // 子进程 unshare(CLONE_FILES) 后关闭的文件描述符在父进程中仍然有效
+    if (pipe(fds) != 0) {
+        printf("unshare failed: pipe errno %d\n", errno);
+        return 1;
+    }
+    pid = clone_with(0);
+    if (pid == 0)
+        _exit(unshare(CLONE_FILES) == 0 && close(fds[1]) == 0 ? 0 : 1);
+    if (pid < 0 || !wait_ok(pid) || write(fds[1], "y", 1) != 1 || read(fds[0], &c, 1) != 1 ||
+        c != 'y') {
+        printf("unshare failed: unshare(CLONE_FILES)\n");
+        return 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    // 不支持的标志
+    if (unshare(CLONE_VFORK) != -1 || errno != EINVAL) {
+        printf("unshare failed: invalid flags accepted\n");
+        return 1;
+    }
+
+    unlinkat(AT_FDCWD, DIR_NAME, AT_REMOVEDIR);
+    printf("unshare passed!\n");
+    return 0;
+}
@@ -0,0 +1,131 @@
+#include <elf.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/auxv.h>
+#include <sys/syscall.h>
+#include <sys/time.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define ITERATIONS 10000
+#define NSEC_PER_SEC 1000000000LL
+// 时钟中断可能被推迟的时间
+#define IRQ_SLACK_NS 1000000LL
+
+// 内核在 vDSO 前一页提供的时间参数
+struct vdso_data {
+    volatile uint32_t seq;
+    volatile uint32_t tick_nanos;
+    volatile uint64_t monotonic_nanos;
+    volatile uint64_t realtime_offset_nanos;
+    volatile uint64_t ticks;
+    volatile uint64_t ticks_per_sec;
+};
+
+static const struct vdso_data *data;
+
+// 按顺序锁的协议从时间参数页读取时间，单位为纳秒
+static long long vdso_time(clockid_t clock)
+{
+    uint32_t seq;
+    uint64_t mono, offset;
+    do {
+        while ((seq = data->seq) & 1)
+            ;
+        __atomic_thread_fence(__ATOMIC_ACQUIRE);
+        mono = data->monotonic_nanos;
+        offset = data->realtime_offset_nanos;
+        __atomic_thread_fence(__ATOMIC_ACQUIRE);
+    } while (seq != data->seq);
+    return clock == CLOCK_REALTIME ? (long long)(mono + offset) : (long long)mono;
+}
+
+// 不经过 vDSO，直接用系统调用读取时间，单位为纳秒
+static long long syscall_time(clockid_t clock)
+{
+    struct timespec ts;
+    syscall(SYS_clock_gettime, clock, &ts);
+    return ts.tv_sec * NSEC_PER_SEC + ts.tv_nsec;
+}
+
+// 用 libc 读取时间，有完整的 vDSO 时不会陷入内核
+static long long libc_time(clockid_t clock)
+{
+    struct timespec ts;
+    clock_gettime(clock, &ts);
+    return ts.tv_sec * NSEC_PER_SEC + ts.tv_nsec;
+}
+
+// 多次比较快速路径与系统调用读到的时间，快速路径最多落后 lag 纳秒，且不能超前
+static int compare(const char *name, clockid_t clock, long long (*fast)(clockid_t), long long lag)
+{
+    long long last = 0;
+    for (int i = 0; i < ITERATIONS; i++) {
+        long long before = syscall_time(clock);
+        long long now = fast(clock);
+        long long after = syscall_time(clock);
+        if (now + lag < before || now > after) {
+            printf("vdso_time failed: %s of clock %d is %lld, syscall gives %lld and %lld\n", name, clock,
+                   now, before, after);
+            return 1;
+        }
+        if (clock == CLOCK_MONOTONIC && now < last) {
+            printf("vdso_time failed: %s of the monotonic clock went back from %lld to %lld\n", name, last,
+                   now);
+            return 1;
+        }
+        last = now;
+    }
+    return 0;
+}
+
+int main()
+{
+    const Elf64_Ehdr *ehdr = (const Elf64_Ehdr *)getauxval(AT_SYSINFO_EHDR);
+    if (!ehdr || memcmp(ehdr->e_ident, ELFMAG, SELFMAG) != 0 || ehdr->e_ident[EI_CLASS] != ELFCLASS64) {
+        printf("vdso_time failed: AT_SYSINFO_EHDR does not point to an ELF header\n");
+        return 1;
+    }
+
+    // 没有程序头的 vDSO 只提供时间参数页，libc 仍然使用系统调用
+    if (ehdr->e_phnum == 0) {
+        data = (const struct vdso_data *)((const char *)ehdr - 4096);
+        if (data->tick_nanos == 0 || data->ticks_per_sec == 0) {
+            printf("vdso_time failed: the time parameters are not set\n");
+            return 1;
+        }
+        long long lag = data->tick_nanos + IRQ_SLACK_NS;
+        if (compare("vdso data", CLOCK_MONOTONIC, vdso_time, lag) ||
+            compare("vdso data", CLOCK_REALTIME, vdso_time, lag))
+            return 1;
+
+        // 子进程共享同一个时间参数页
+        pid_t pid = fork();
+        if (pid == 0)
+            _exit(compare("vdso data in child", CLOCK_MONOTONIC, vdso_time, lag));
+        int status;
+        if (pid < 0 || waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+            printf("vdso_time failed: child cannot read the time parameters\n");
+            return 1;
+        }
+    }
+    if (compare("clock_gettime", CLOCK_MONOTONIC, libc_time, IRQ_SLACK_NS) ||
+        compare("clock_gettime", CLOCK_REALTIME, libc_time, IRQ_SLACK_NS))
+        return 1;
+
+    // gettimeofday 与系统调用读到的墙上时间一致
+    struct timeval tv;
+    long long before = syscall_time(CLOCK_REALTIME) / 1000;
+    gettimeofday(&tv, NULL);
+    long long after = syscall_time(CLOCK_REALTIME) / 1000;
+    long long usec = tv.tv_sec * 1000000LL + tv.tv_usec;
+    if (usec < before || usec > after) {
+        printf("vdso_time failed: gettimeofday gives %lld us, syscall gives %lld and %lld\n", usec, before, after);
+        return 1;
+    }
+
+    printf("vdso_time passed!\n");
+    return 0;
+}
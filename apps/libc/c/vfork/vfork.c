@@ -0,0 +1,35 @@
+#include <stdio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile int child_done = 0;
+
+int main()
+{
+    pid_t pid = vfork();
+    if (pid == 0) {
+        // 子进程睡眠期间父进程不应被调度
+        struct timespec ts = {0, 100000000};
+        nanosleep(&ts, NULL);
+        // 与父进程共享地址空间，父进程能看到这次修改
+        child_done = 1;
+        _exit(0);
+    }
+    if (pid < 0) {
+        printf("vfork failed: vfork returned %d\n", pid);
+        return 1;
+    }
+    if (!child_done) {
+        printf("vfork failed: parent resumed before child exited\n");
+        return 1;
+    }
+
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("vfork failed: bad child status\n");
+        return 1;
+    }
+    printf("vfork passed!\n");
+    return 0;
+}
@@ -0,0 +1,142 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define TASKS 8
+#define ROUNDS 500
+
+static int pipes[TASKS][2];
+
+// 第 i 个进程从 pipes[i] 读出令牌，加一后写入 pipes[(i + 1) % TASKS]
+static int relay(int i)
+{
+    int in = pipes[i][0], out = pipes[(i + 1) % TASKS][1];
+    for (int round = 0; round < ROUNDS; round++) {
+        int token;
+        if (read(in, &token, sizeof(token)) != sizeof(token))
+            return 1;
+        if (token != round * TASKS + i)
+            return 2;
+        token++;
+        if (i == TASKS - 1 && round == ROUNDS - 1)
+            break;
+        if (write(out, &token, sizeof(token)) != sizeof(token))
+            return 3;
+    }
+    return 0;
+}
+
+// 8 个进程在管道组成的环中快速传递令牌，每次传递都是一次唤醒与等待
+static int check_ring(void)
+{
+    for (int i = 0; i < TASKS; i++) {
+        if (pipe(pipes[i]) != 0) {
+            printf("wait_queue failed: pipe errno %d\n", errno);
+            return 1;
+        }
+    }
+    pid_t pids[TASKS];
+    for (int i = 0; i < TASKS; i++) {
+        pids[i] = fork();
+        if (pids[i] < 0) {
+            printf("wait_queue failed: fork errno %d\n", errno);
+            return 1;
+        }
+        if (pids[i] == 0)
+            _exit(relay(i));
+    }
+    int token = 0;
+    if (write(pipes[0][1], &token, sizeof(token)) != sizeof(token)) {
+        printf("wait_queue failed: write errno %d\n", errno);
+        return 1;
+    }
+    // 逆序等待，使父进程多次阻塞在 wait4 中
+    for (int i = TASKS - 1; i >= 0; i--) {
+        int status;
+        if (waitpid(pids[i], &status, 0) != pids[i]) {
+            printf("wait_queue failed: waitpid errno %d\n", errno);
+            return 1;
+        }
+        if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+            printf("wait_queue failed: task %d status %#x\n", i, status);
+            return 1;
+        }
+    }
+    for (int i = 0; i < TASKS; i++) {
+        close(pipes[i][0]);
+        close(pipes[i][1]);
+    }
+    return 0;
+}
+
+static volatile int alarms;
+
+static void on_alarm(int signo)
+{
+    (void)signo;
+    alarms++;
+}
+
+// 20 毫秒后产生 SIGALRM
+static int arm_alarm(timer_t timer)
+{
+    struct itimerspec its = {.it_value = {0, 20000000}};
+    return timer_settime(timer, 0, &its, NULL);
+}
+
+// 没有 SA_RESTART 时，阻塞的管道读取和 wait4 被信号打断，返回 EINTR
+static int check_interrupt(void)
+{
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = on_alarm;
+    sigaction(SIGALRM, &sa, NULL);
+    struct sigevent sev = {.sigev_notify = SIGEV_SIGNAL, .sigev_signo = SIGALRM};
+    timer_t timer;
+    if (timer_create(CLOCK_MONOTONIC, &sev, &timer) != 0) {
+        printf("wait_queue failed: timer_create errno %d\n", errno);
+        return 1;
+    }
+
+    int fds[2];
+    pipe(fds);
+    char c;
+    arm_alarm(timer);
+    if (read(fds[0], &c, 1) != -1 || errno != EINTR || alarms != 1) {
+        printf("wait_queue failed: read not interrupted\n");
+        return 1;
+    }
+
+    // 子进程一直阻塞在读取管道中，直到管道被关闭
+    pid_t pid = fork();
+    if (pid == 0) {
+        close(fds[1]);
+        _exit(read(fds[0], &c, 1) == 0 ? 0 : 1);
+    }
+    arm_alarm(timer);
+    if (waitpid(pid, NULL, 0) != -1 || errno != EINTR || alarms != 2) {
+        printf("wait_queue failed: waitpid not interrupted\n");
+        return 1;
+    }
+    close(fds[1]);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("wait_queue failed: child not woken up by closing the pipe\n");
+        return 1;
+    }
+    close(fds[0]);
+    timer_delete(timer);
+    return 0;
+}
+
+int main(void)
+{
+    if (check_ring() || check_interrupt())
+        return 1;
+    printf("wait_queue passed!\n");
+    return 0;
+}
@@ -0,0 +1,154 @@
+#include <errno.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+// 同时退出的子进程数
+#define CHILDREN 100
+// 轮流使用的等待方式数
+#define WAITERS 4
+
+static pid_t pids[CHILDREN];
+// 每个子进程被回收的次数
+static volatile int reaped[CHILDREN];
+
+static int index_of(pid_t pid)
+{
+    for (int i = 0; i < CHILDREN; i++) {
+        if (pids[i] == pid)
+            return i;
+    }
+    return -1;
+}
+
+static void record(pid_t pid, int status)
+{
+    int i = index_of(pid);
+    // 退出码是子进程的序号，用来确认状态属于这个子进程
+    if (i >= 0 && WIFEXITED(status) && WEXITSTATUS(status) == i % 256)
+        reaped[i]++;
+    else if (i >= 0)
+        reaped[i] += 100;
+}
+
+// SIGCHLD 处理函数在主流程的 wait4 期间同样回收子进程
+static void handler(int signo)
+{
+    (void)signo;
+    int saved = errno;
+    int status;
+    pid_t pid;
+    while ((pid = waitpid(-1, &status, WNOHANG)) > 0) {
+        record(pid, status);
+    }
+    errno = saved;
+}
+
+static void child(int i)
+{
+    // 一部分子进程自己也创建子进程后立即退出，使退出与重新指定父进程同时发生
+    if (i % 5 == 0 && fork() == 0) {
+        struct timespec ts = {0, 1000000};
+        nanosleep(&ts, NULL);
+        _exit(0);
+    }
+    if (i % 3 == 0) {
+        struct timespec ts = {0, (i % 7) * 1000000L};
+        nanosleep(&ts, NULL);
+    }
+    _exit(i % 256);
+}
+
+// 按第 waiter 种方式回收一个子进程，返回回收的 PID
+static pid_t wait_one(int waiter, int *status)
+{
+    switch (waiter) {
+    case 0:
+        return wait(status);
+    case 1:
+        return waitpid(-1, status, 0);
+    case 2: {
+        // 等待指定的子进程，它可能已被信号处理函数回收
+        for (int i = 0; i < CHILDREN; i++) {
+            if (reaped[i] == 0)
+                return waitpid(pids[i], status, 0);
+        }
+        return waitpid(-1, status, 0);
+    }
+    default: {
+        pid_t pid;
+        while ((pid = waitpid(-1, status, WNOHANG)) == 0)
+            sched_yield();
+        return pid;
+    }
+    }
+}
+
+int main(void)
+{
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = handler;
+    sa.sa_flags = SA_RESTART;
+    sigemptyset(&sa.sa_mask);
+    if (sigaction(SIGCHLD, &sa, NULL) != 0) {
+        printf("wait_race failed: sigaction errno %d\n", errno);
+        return 1;
+    }
+
+    // 创建子进程期间屏蔽 SIGCHLD，保证 pids 填好后才开始回收
+    sigset_t chld, old;
+    sigemptyset(&chld);
+    sigaddset(&chld, SIGCHLD);
+    sigprocmask(SIG_BLOCK, &chld, &old);
+    for (int i = 0; i < CHILDREN; i++) {
+        pids[i] = fork();
+        if (pids[i] < 0) {
+            printf("wait_race failed: fork errno %d\n", errno);
+            return 1;
+        }
+        if (pids[i] == 0)
+            child(i);
+    }
+    sigprocmask(SIG_SETMASK, &old, NULL);
+
+    // 主流程轮流以不同方式等待，与信号处理函数争抢同一批子进程
+    int waiter = 0;
+    for (;;) {
+        int status;
+        pid_t pid = wait_one(waiter++ % WAITERS, &status);
+        if (pid > 0) {
+            record(pid, status);
+            continue;
+        }
+        // 指定的子进程已被信号处理函数回收
+        if (errno == ECHILD || errno == EINTR) {
+            int done = 1;
+            for (int i = 0; i < CHILDREN; i++)
+                done &= reaped[i] != 0;
+            if (done)
+                break;
+            continue;
+        }
+        printf("wait_race failed: wait errno %d\n", errno);
+        return 1;
+    }
+
+    for (int i = 0; i < CHILDREN; i++) {
+        if (reaped[i] != 1) {
+            printf("wait_race failed: child %d reaped %d times\n", i, reaped[i]);
+            return 1;
+        }
+    }
+    // 所有子进程都已回收，再次等待返回 ECHILD
+    if (wait(NULL) != -1 || errno != ECHILD) {
+        printf("wait_race failed: wait after reaping all children errno %d\n", errno);
+        return 1;
+    }
+    printf("wait_race passed!\n");
+    return 0;
+}
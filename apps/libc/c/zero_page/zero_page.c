@@ -0,0 +1,134 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PAGE 4096
+#define PAGES 256
+
+// 读取 /proc/self/status 中的 VmRSS，单位为 kB，失败时返回 -1
+static long rss_kb(void)
+{
+    FILE *f = fopen("/proc/self/status", "r");
+    if (!f)
+        return -1;
+    char line[128];
+    long kb = -1;
+    while (fgets(line, sizeof(line), f)) {
+        if (sscanf(line, "VmRSS: %ld kB", &kb) == 1)
+            break;
+    }
+    fclose(f);
+    return kb;
+}
+
+// 返回 [addr, addr + len) 中驻留在内存中的页数，失败时返回 -1
+static int resident_pages(void *addr, size_t len)
+{
+    static unsigned char vec[PAGES];
+    if (mincore(addr, len, vec) != 0)
+        return -1;
+    int count = 0;
+    for (size_t i = 0; i < len / PAGE; i++)
+        count += vec[i] & 1;
+    return count;
+}
+
+int main()
+{
+    size_t len = PAGES * PAGE;
+    volatile char *buf = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED) {
+        printf("zero_page failed: mmap returned an error\n");
+        return 1;
+    }
+    long before = rss_kb();
+    if (before < 0) {
+        printf("zero_page failed: cannot read VmRSS\n");
+        return 1;
+    }
+
+    // 只读过的页读到 0，且都映射到共享的零页，不占用内存
+    for (size_t i = 0; i < len; i += 64) {
+        if (buf[i] != 0) {
+            printf("zero_page failed: byte %zu of a fresh mapping is %d\n", i, buf[i]);
+            return 1;
+        }
+    }
+    long after = rss_kb();
+    if (after - before >= PAGES * PAGE / 1024 / 2) {
+        printf("zero_page failed: reading %d pages grew VmRSS from %ld kB to %ld kB\n", PAGES, before, after);
+        return 1;
+    }
+    // 与 Linux 相同，mincore 认为映射零页的页驻留在内存中
+    if (resident_pages((void *)buf, len) != PAGES) {
+        printf("zero_page failed: pages read are not resident\n");
+        return 1;
+    }
+
+    // 写入时得到私有的页，其他映射零页的页仍然读到 0
+    buf[0] = 1;
+    buf[2 * PAGE + 7] = 2;
+    if (buf[0] != 1 || buf[2 * PAGE + 7] != 2 || buf[PAGE] != 0 || buf[3 * PAGE] != 0) {
+        printf("zero_page failed: write to a zero page leaked into others\n");
+        return 1;
+    }
+
+    // 内核直接写入映射零页的页
+    int fds[2];
+    if (pipe(fds) != 0 || write(fds[1], "zero", 4) != 4) {
+        printf("zero_page failed: pipe returned an error\n");
+        return 1;
+    }
+    if (read(fds[0], (char *)buf + 4 * PAGE, 4) != 4 || memcmp((char *)buf + 4 * PAGE, "zero", 4) != 0) {
+        printf("zero_page failed: read into a zero page\n");
+        return 1;
+    }
+    if (buf[5 * PAGE] != 0) {
+        printf("zero_page failed: read into a zero page changed the zero page\n");
+        return 1;
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    // fork 之后父子进程各自写入映射零页的页，互不影响
+    pid_t pid = fork();
+    if (pid < 0) {
+        printf("zero_page failed: fork returned an error\n");
+        return 1;
+    }
+    if (pid == 0) {
+        if (buf[6 * PAGE] != 0 || buf[0] != 1 || buf[2 * PAGE + 7] != 2)
+            _exit(1);
+        buf[6 * PAGE] = 3;
+        buf[0] = 4;
+        _exit(buf[6 * PAGE] == 3 && buf[7 * PAGE] == 0 ? 0 : 1);
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("zero_page failed: child saw wrong data\n");
+        return 1;
+    }
+    if (buf[6 * PAGE] != 0 || buf[0] != 1) {
+        printf("zero_page failed: child write is visible to the parent\n");
+        return 1;
+    }
+    buf[6 * PAGE] = 5;
+    if (buf[6 * PAGE] != 5 || buf[7 * PAGE] != 0) {
+        printf("zero_page failed: parent write after fork\n");
+        return 1;
+    }
+
+    // 只读映射中的页同样读到 0
+    volatile char *ro = mmap(NULL, PAGE, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (ro == MAP_FAILED || ro[100] != 0) {
+        printf("zero_page failed: read-only mapping is not zero\n");
+        return 1;
+    }
+    munmap((void *)ro, PAGE);
+    munmap((void *)buf, len);
+
+    printf("zero_page passed!\n");
+    return 0;
+}
@@ -0,0 +1,109 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/sysinfo.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define CHILDREN 64
+// 每个未回收的子进程允许占用的内存，远小于一个内核栈
+#define PER_ZOMBIE (32 * 1024ULL)
+
+static volatile int chld_count;
+
+static void chld_handler(int sig)
+{
+    (void)sig;
+    chld_count++;
+}
+
+static unsigned long long free_ram(void)
+{
+    struct sysinfo info;
+    if (sysinfo(&info) != 0) {
+        return 0;
+    }
+    return (unsigned long long)info.freeram * info.mem_unit;
+}
+
+static void sleep_ms(long ms)
+{
+    struct timespec ts = {ms / 1000, ms % 1000 * 1000000};
+    nanosleep(&ts, NULL);
+}
+
+// 创建一个以 `code` 退出的子进程，并等到它发出 SIGCHLD，返回其 PID
+static pid_t spawn_and_wait_exit(int code, sigset_t *old)
+{
+    int before = chld_count;
+    pid_t pid = fork();
+    if (pid == 0) {
+        _exit(code);
+    }
+    while (pid > 0 && chld_count == before) {
+        sigsuspend(old);
+    }
+    return pid;
+}
+
+int main()
+{
+    struct sigaction sa = {0};
+    sa.sa_handler = chld_handler;
+    sigaction(SIGCHLD, &sa, NULL);
+    // 阻塞 SIGCHLD，只在 sigsuspend 中接收
+    sigset_t block, old;
+    sigemptyset(&block);
+    sigaddset(&block, SIGCHLD);
+    sigprocmask(SIG_BLOCK, &block, &old);
+
+    // 逐个创建并立即回收，让内核堆增长到稳定的大小
+    for (int i = 0; i < CHILDREN; i++) {
+        pid_t pid = spawn_and_wait_exit(0, &old);
+        if (pid < 0 || waitpid(pid, NULL, 0) != pid) {
+            printf("zombie_reclaim failed: warm-up child errno=%d\n", errno);
+            return 1;
+        }
+    }
+    sleep_ms(50);
+
+    // 子进程全部退出后暂不回收，它们的内核栈和地址空间应已释放
+    unsigned long long before = free_ram();
+    pid_t pids[CHILDREN];
+    for (int i = 0; i < CHILDREN; i++) {
+        pids[i] = spawn_and_wait_exit(i, &old);
+        if (pids[i] < 0) {
+            printf("zombie_reclaim failed: fork errno=%d\n", errno);
+            return 1;
+        }
+    }
+    unsigned long long after = free_ram();
+    for (int i = 0; i < 100 && after + CHILDREN * PER_ZOMBIE < before; i++) {
+        sleep_ms(10);
+        after = free_ram();
+    }
+    if (before == 0 || after + CHILDREN * PER_ZOMBIE < before) {
+        printf("zombie_reclaim failed: %d zombies hold %llu bytes\n", CHILDREN, before - after);
+        return 1;
+    }
+
+    // 僵尸进程仍可按 PID 回收，得到正确的退出码
+    for (int i = CHILDREN - 1; i >= 0; i--) {
+        int status;
+        if (waitpid(pids[i], &status, WNOHANG) != pids[i]) {
+            printf("zombie_reclaim failed: waitpid(%d) errno=%d\n", pids[i], errno);
+            return 1;
+        }
+        if (!WIFEXITED(status) || WEXITSTATUS(status) != i) {
+            printf("zombie_reclaim failed: child %d status=%#x\n", i, status);
+            return 1;
+        }
+    }
+    if (waitpid(-1, NULL, WNOHANG) != -1 || errno != ECHILD) {
+        printf("zombie_reclaim failed: children left after reaping\n");
+        return 1;
+    }
+    printf("zombie_reclaim passed!\n");
+    return 0;
+}
@@ -152,6 +152,12 @@ pub const EAI_MEMORY: i32 = -10;
 pub const EAI_SYSTEM: i32 = -11;
 pub const EAI_OVERFLOW: i32 = -12;
 pub const MAXADDRS: u32 = 48;
+pub const POLLIN: u32 = 1;
+pub const POLLPRI: u32 = 2;
+pub const POLLOUT: u32 = 4;
+pub const POLLERR: u32 = 8;
+pub const POLLHUP: u32 = 16;
+pub const POLLNVAL: u32 = 32;
 pub const CLOCK_REALTIME: u32 = 0;
 pub const CLOCK_MONOTONIC: u32 = 1;
 pub const EPOLL_CLOEXEC: u32 = 524288;
@@ -968,6 +974,59 @@ impl Default for aibuf {
         }
     }
 }
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct pollfd {
+    pub fd: ::core::ffi::c_int,
+    pub events: ::core::ffi::c_short,
+    pub revents: ::core::ffi::c_short,
+}
+#[test]
+fn bindgen_test_layout_pollfd() {
+    const UNINIT: ::core::mem::MaybeUninit<pollfd> = ::core::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::core::mem::size_of::<pollfd>(),
+        8usize,
+        concat!("Size of: ", stringify!(pollfd))
+    );
+    assert_eq!(
+        ::core::mem::align_of::<pollfd>(),
+        4usize,
+        concat!("Alignment of ", stringify!(pollfd))
+    );
+    assert_eq!(
+        unsafe { ::core::ptr::addr_of!((*ptr).fd) as usize - ptr as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pollfd),
+            "::",
+            stringify!(fd)
+        )
+    );
+    assert_eq!(
+        unsafe { ::core::ptr::addr_of!((*ptr).events) as usize - ptr as usize },
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pollfd),
+            "::",
+            stringify!(events)
+        )
+    );
+    assert_eq!(
+        unsafe { ::core::ptr::addr_of!((*ptr).revents) as usize - ptr as usize },
+        6usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pollfd),
+            "::",
+            stringify!(revents)
+        )
+    );
+}
+pub type nfds_t = ::core::ffi::c_ulong;
 pub type time_t = ::core::ffi::c_longlong;
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
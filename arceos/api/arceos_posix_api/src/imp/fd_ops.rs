@@ -1,53 +1,289 @@
-use alloc::sync::Arc;
+use alloc::{collections::BTreeSet, string::String, sync::Arc, vec::Vec};
 use core::ffi::c_int;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 
 use axerrno::{LinuxError, LinuxResult};
-use axio::PollState;
 use axns::{def_resource, AxResource};
+use cap_access::{Cap, WithCap};
 use flatten_objects::FlattenObjects;
 use spin::RwLock;
 
+use super::pollable::Pollable;
 use crate::ctypes;
 
 pub const AX_FILE_LIMIT: usize = 1024;
 
+/// The flags of `open` that only affect how the file is opened, which are not
+/// kept in the open file description.
+const OPEN_ONLY_FLAGS: u32 =
+    ctypes::O_CREAT | ctypes::O_EXCL | ctypes::O_NOCTTY | ctypes::O_TRUNC | ctypes::O_CLOEXEC;
+/// The file status flags that can be changed by `F_SETFL`.
+const SETFL_FLAGS: u32 = ctypes::O_APPEND | ctypes::O_NONBLOCK;
+
+/// The access mode and file status flags of an open file description.
+///
+/// It is stored in the file-like object, so file descriptors duplicated from
+/// each other share it.
+pub struct StatusFlags(AtomicU32);
+
+impl StatusFlags {
+    /// Creates the flags from the `flags` argument of `open`.
+    pub fn new(flags: u32) -> Self {
+        Self(AtomicU32::new(flags & !OPEN_ONLY_FLAGS))
+    }
+
+    /// Returns the flags, as reported by `F_GETFL`.
+    pub fn get(&self) -> u32 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Replaces the flags that can be changed by `F_SETFL`, other bits of
+    /// `flags` are ignored.
+    pub fn set(&self, flags: u32) {
+        let flags = flags & SETFL_FLAGS;
+        self.0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+                Some(old & !SETFL_FLAGS | flags)
+            })
+            .ok();
+    }
+
+    /// Whether the file was opened for reading.
+    pub fn readable(&self) -> bool {
+        !self.path_only() && self.get() & 0b11 != ctypes::O_WRONLY
+    }
+
+    /// Whether the file was opened for writing.
+    pub fn writable(&self) -> bool {
+        !self.path_only() && self.get() & 0b11 != ctypes::O_RDONLY
+    }
+
+    /// Whether the file was opened only as a location in the filesystem tree
+    /// (`O_PATH`), so it can be neither read nor written.
+    pub fn path_only(&self) -> bool {
+        self.get() & ctypes::O_PATH != 0
+    }
+
+    /// Returns the capabilities granted by the access mode: [`Cap::READ`] and
+    /// [`Cap::WRITE`] as it allows, and none for `O_PATH`.
+    pub fn cap(&self) -> Cap {
+        let mut cap = Cap::empty();
+        if self.readable() {
+            cap |= Cap::READ;
+        }
+        if self.writable() {
+            cap |= Cap::WRITE;
+        }
+        cap
+    }
+
+    /// Whether writes always go to the end of the file (`O_APPEND`).
+    pub fn append(&self) -> bool {
+        self.get() & ctypes::O_APPEND != 0
+    }
+
+    /// Whether reads and writes fail with `EAGAIN` instead of blocking
+    /// (`O_NONBLOCK`).
+    pub fn nonblocking(&self) -> bool {
+        self.get() & ctypes::O_NONBLOCK != 0
+    }
+}
+
 #[allow(dead_code)]
-pub trait FileLike: Send + Sync {
+pub trait FileLike: Pollable + Send + Sync {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize>;
     fn write(&self, buf: &[u8]) -> LinuxResult<usize>;
     fn stat(&self) -> LinuxResult<ctypes::stat>;
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
-    fn poll(&self) -> LinuxResult<PollState>;
+    /// Returns the access mode and file status flags of the open file
+    /// description.
+    fn status_flags(&self) -> &StatusFlags;
+    /// Called after `O_NONBLOCK` is changed by `F_SETFL`, for objects that keep
+    /// their own non-blocking state.
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+    /// Returns the target of the link to the object in `/proc/[pid]/fd`: the
+    /// path of a file, or a name like `pipe:[id]` for other objects.
+    fn link_target(&self) -> String;
+    /// Opens the object again with the `open` flags `flags`, as if by opening
+    /// its link in `/proc/[pid]/fd`, which makes a new open file description
+    /// of the same object. Fails with `ENXIO` if the object cannot be opened
+    /// again.
+    fn reopen(self: Arc<Self>, _flags: c_int) -> LinuxResult<Arc<dyn FileLike>> {
+        Err(LinuxError::ENXIO)
+    }
+}
+
+/// An entry of the file descriptor table: the open file description with the
+/// capabilities granted by its access mode.
+///
+/// The capabilities are fixed when the descriptor is made, as the access mode
+/// cannot be changed afterwards. System calls that read or write through the
+/// descriptor obtain the file with [`access_file_like`], so the check is the
+/// same for every kind of file.
+pub type FdEntry = WithCap<Arc<dyn FileLike>>;
+
+/// Makes a table entry for `f`, with the capabilities of its access mode.
+pub(crate) fn new_entry(f: Arc<dyn FileLike>) -> FdEntry {
+    let cap = f.status_flags().cap();
+    WithCap::new(f, cap)
 }
 
 def_resource! {
+    /// The file descriptors of the process.
+    ///
+    /// Each entry is an open file description: the object holds the file
+    /// offset and status flags, so descriptors that share the `Arc` (those made
+    /// by `dup` or inherited on `fork`) share them, while each `open` makes a
+    /// new one.
     #[allow(non_camel_case_types)]
-    pub static FD_TABLE: AxResource<RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>>> = AxResource::new();
+    pub static FD_TABLE: AxResource<RwLock<FlattenObjects<FdEntry, AX_FILE_LIMIT>>> = AxResource::new();
 }
 
 impl FD_TABLE {
-    pub fn copy_inner(&self) -> RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>> {
+    /// Copies the table for a new process. The copy refers to the same open
+    /// file descriptions as the original.
+    pub fn copy_inner(&self) -> RwLock<FlattenObjects<FdEntry, AX_FILE_LIMIT>> {
         let table = self.read();
         let mut new_table = FlattenObjects::new();
-        let count = table.count();
-        for i in 0..count {
-            new_table.add_at(i, table.get(i).unwrap().clone());
+        for fd in 0..table.capacity() {
+            if let Some(f) = table.get(fd) {
+                new_table.add_at(fd, WithCap::new(file_of(f).clone(), f.cap()));
+            }
         }
         RwLock::new(new_table)
     }
 }
 
+/// Returns the file of a table entry. Nothing can be done with it that needs a
+/// capability.
+fn file_of(entry: &FdEntry) -> &Arc<dyn FileLike> {
+    entry.access(Cap::empty()).unwrap()
+}
+
+def_resource! {
+    /// The soft limit on the number of file descriptors (`RLIMIT_NOFILE`).
+    ///
+    /// New file descriptors are always below it. It never exceeds [`AX_FILE_LIMIT`].
+    #[allow(non_camel_case_types)]
+    pub static FD_LIMIT: AxResource<AtomicUsize> = AxResource::new();
+}
+
+impl FD_LIMIT {
+    pub fn copy_inner(&self) -> AtomicUsize {
+        AtomicUsize::new(self.load(Ordering::Relaxed))
+    }
+}
+
+/// The limit on open files in the system (`/proc/sys/fs/file-max`), which
+/// defaults to the largest value as on Linux.
+///
+/// Open files are not counted across processes, so it limits the file
+/// descriptors of each process alongside [`FD_LIMIT`], failing with `ENFILE`
+/// rather than `EMFILE`.
+pub static FILE_MAX: AtomicUsize = AtomicUsize::new(isize::MAX as usize);
+
+/// Returns the soft limit on the number of file descriptors.
+pub fn fd_limit() -> usize {
+    FD_LIMIT.load(Ordering::Relaxed)
+}
+
+def_resource! {
+    /// File descriptors with the close-on-exec flag set.
+    #[allow(non_camel_case_types)]
+    pub static FD_CLOEXEC: AxResource<RwLock<BTreeSet<c_int>>> = AxResource::new();
+}
+
+impl FD_CLOEXEC {
+    pub fn copy_inner(&self) -> RwLock<BTreeSet<c_int>> {
+        RwLock::new(self.read().clone())
+    }
+}
+
+/// Sets or clears the close-on-exec flag of `fd`.
+pub fn set_cloexec(fd: c_int, cloexec: bool) {
+    if cloexec {
+        FD_CLOEXEC.write().insert(fd);
+    } else {
+        FD_CLOEXEC.write().remove(&fd);
+    }
+}
+
+/// Closes all file descriptors with the close-on-exec flag set.
+pub fn close_on_exec() {
+    let fds = core::mem::take(&mut *FD_CLOEXEC.write());
+    let mut table = FD_TABLE.write();
+    for fd in fds {
+        table.remove(fd as usize);
+    }
+}
+
+/// Closes all file descriptors, used when the process exits.
+pub fn close_all() {
+    FD_CLOEXEC.write().clear();
+    let mut table = FD_TABLE.write();
+    let files: Vec<_> = (0..table.capacity())
+        .filter_map(|fd| table.remove(fd))
+        .collect();
+    drop(table);
+    // Files such as pipes may wake up other tasks when dropped.
+    drop(files);
+}
+
+/// Returns the file of `fd`, for operations that neither read nor write it
+/// through the descriptor.
 pub fn get_file_like(fd: c_int) -> LinuxResult<Arc<dyn FileLike>> {
+    access_file_like(fd, Cap::empty(), LinuxError::EBADF)
+}
+
+/// Returns the file of `fd` if the descriptor grants all of `cap`.
+///
+/// Returns `EBADF` if `fd` is not open, and `err` if it lacks a capability,
+/// which is `EBADF` for `read` and `write` but differs for others such as
+/// `EACCES` for `mmap`.
+pub fn access_file_like(fd: c_int, cap: Cap, err: LinuxError) -> LinuxResult<Arc<dyn FileLike>> {
     FD_TABLE
         .read()
         .get(fd as usize)
+        .ok_or(LinuxError::EBADF)?
+        .access_or_err(cap, err)
         .cloned()
-        .ok_or(LinuxError::EBADF)
 }
 
+/// Adds `f` to the lowest-numbered free file descriptor, as POSIX requires for
+/// `open`, `dup`, `pipe` and the like.
 pub fn add_file_like(f: Arc<dyn FileLike>) -> LinuxResult<c_int> {
-    Ok(FD_TABLE.write().add(f).ok_or(LinuxError::EMFILE)? as c_int)
+    add_file_like_from(f, 0)
+}
+
+/// Adds `f` to the lowest-numbered free file descriptor not less than `min_fd`.
+fn add_file_like_from(f: Arc<dyn FileLike>, min_fd: usize) -> LinuxResult<c_int> {
+    let limit = fd_limit();
+    let file_max = FILE_MAX.load(Ordering::Relaxed);
+    let mut table = FD_TABLE.write();
+    let fd = if min_fd == 0 {
+        // The table keeps a bitmap of used slots, which finds the first free
+        // one a word at a time.
+        let fd = table.add(new_entry(f)).ok_or(LinuxError::EMFILE)?;
+        if fd < limit && fd < file_max {
+            return Ok(fd as c_int);
+        }
+        table.remove(fd);
+        fd
+    } else {
+        let fd = (min_fd..limit)
+            .find(|&fd| !table.is_assigned(fd))
+            .ok_or(LinuxError::EMFILE)?;
+        if fd < file_max {
+            table.add_at(fd, new_entry(f)).ok_or(LinuxError::EMFILE)?;
+            return Ok(fd as c_int);
+        }
+        fd
+    };
+    if fd >= file_max {
+        Err(LinuxError::ENFILE)
+    } else {
+        Err(LinuxError::EMFILE)
+    }
 }
 
 pub fn close_file_like(fd: c_int) -> LinuxResult {
@@ -55,6 +291,7 @@ pub fn close_file_like(fd: c_int) -> LinuxResult {
         .write()
         .remove(fd as usize)
         .ok_or(LinuxError::EBADF)?;
+    set_cloexec(fd, false);
     drop(f);
     Ok(())
 }
@@ -62,27 +299,59 @@ pub fn close_file_like(fd: c_int) -> LinuxResult {
 /// Close a file by `fd`.
 pub fn sys_close(fd: c_int) -> c_int {
     debug!("sys_close <= {}", fd);
-    if (0..=2).contains(&fd) {
-        return 0; // stdin, stdout, stderr
-    }
     syscall_body!(sys_close, close_file_like(fd).map(|_| 0))
 }
 
-fn dup_fd(old_fd: c_int) -> LinuxResult<c_int> {
+/// Close all file descriptors from `first` to `last` (inclusive).
+///
+/// With `CLOSE_RANGE_CLOEXEC` in `flags`, they are marked close-on-exec instead.
+/// `CLOSE_RANGE_UNSHARE` is accepted but has no effect, as the file descriptor
+/// table is never shared between processes.
+pub fn sys_close_range(first: u32, last: u32, flags: u32) -> c_int {
+    const CLOSE_RANGE_UNSHARE: u32 = 1 << 1;
+    const CLOSE_RANGE_CLOEXEC: u32 = 1 << 2;
+
+    debug!("sys_close_range <= {} {} {:#x}", first, last, flags);
+    syscall_body!(sys_close_range, {
+        if flags & !(CLOSE_RANGE_UNSHARE | CLOSE_RANGE_CLOEXEC) != 0 || first > last {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut table = FD_TABLE.write();
+        let fds = first as usize..(last as usize + 1).min(table.capacity());
+        let fds = fds.filter(|&fd| table.is_assigned(fd));
+        if flags & CLOSE_RANGE_CLOEXEC != 0 {
+            FD_CLOEXEC.write().extend(fds.map(|fd| fd as c_int));
+            return Ok(0);
+        }
+        let fds: Vec<_> = fds.collect();
+        let files: Vec<_> = fds.iter().filter_map(|&fd| table.remove(fd)).collect();
+        drop(table);
+        let mut cloexec = FD_CLOEXEC.write();
+        for fd in fds {
+            cloexec.remove(&(fd as c_int));
+        }
+        drop(cloexec);
+        // Files such as pipes may wake up other tasks when dropped.
+        drop(files);
+        Ok(0)
+    })
+}
+
+fn dup_fd(old_fd: c_int, min_fd: usize) -> LinuxResult<c_int> {
     let f = get_file_like(old_fd)?;
-    let new_fd = add_file_like(f)?;
+    let new_fd = add_file_like_from(f, min_fd)?;
     Ok(new_fd)
 }
 
 /// Duplicate a file descriptor.
 pub fn sys_dup(old_fd: c_int) -> c_int {
     debug!("sys_dup <= {}", old_fd);
-    syscall_body!(sys_dup, dup_fd(old_fd))
+    syscall_body!(sys_dup, dup_fd(old_fd, 0))
 }
 
 /// Duplicate a file descriptor, but it uses the file descriptor number specified in `new_fd`.
 ///
-/// TODO: `dup2` should forcibly close new_fd if it is already opened.
+/// If `new_fd` is already opened, it is closed first.
 pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
     debug!("sys_dup2 <= old_fd: {}, new_fd: {}", old_fd, new_fd);
     syscall_body!(sys_dup2, {
@@ -94,15 +363,19 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
                 return Ok(r);
             }
         }
-        if new_fd as usize >= AX_FILE_LIMIT {
+        if new_fd < 0 || new_fd as usize >= fd_limit() {
             return Err(LinuxError::EBADF);
         }
 
         let f = get_file_like(old_fd)?;
-        FD_TABLE
-            .write()
-            .add_at(new_fd as usize, f)
+        let mut table = FD_TABLE.write();
+        let old = table.remove(new_fd as usize);
+        table
+            .add_at(new_fd as usize, new_entry(f))
             .ok_or(LinuxError::EMFILE)?;
+        drop(table);
+        drop(old);
+        set_cloexec(new_fd, false);
 
         Ok(new_fd)
     })
@@ -110,21 +383,34 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
 
 /// Manipulate file descriptor.
 ///
-/// TODO: `SET/GET` command is ignored, hard-code stdin/stdout
+/// Only `O_APPEND` and `O_NONBLOCK` can be changed by `F_SETFL`.
 pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     debug!("sys_fcntl <= fd: {} cmd: {} arg: {}", fd, cmd, arg);
     syscall_body!(sys_fcntl, {
         match cmd as u32 {
-            ctypes::F_DUPFD => dup_fd(fd),
+            ctypes::F_DUPFD | ctypes::F_DUPFD_CLOEXEC if arg >= fd_limit() => {
+                Err(LinuxError::EINVAL)
+            }
+            ctypes::F_DUPFD => dup_fd(fd, arg),
             ctypes::F_DUPFD_CLOEXEC => {
-                // TODO: Change fd flags
-                dup_fd(fd)
+                let new_fd = dup_fd(fd, arg)?;
+                set_cloexec(new_fd, true);
+                Ok(new_fd)
+            }
+            ctypes::F_GETFD => {
+                get_file_like(fd)?;
+                Ok(FD_CLOEXEC.read().contains(&fd) as c_int)
+            }
+            ctypes::F_SETFD => {
+                get_file_like(fd)?;
+                set_cloexec(fd, arg & ctypes::FD_CLOEXEC as usize != 0);
+                Ok(0)
             }
+            ctypes::F_GETFL => Ok(get_file_like(fd)?.status_flags().get() as c_int),
             ctypes::F_SETFL => {
-                if fd == 0 || fd == 1 || fd == 2 {
-                    return Ok(0);
-                }
-                get_file_like(fd)?.set_nonblocking(arg & (ctypes::O_NONBLOCK as usize) > 0)?;
+                let f = get_file_like(fd)?;
+                f.status_flags().set(arg as u32);
+                f.set_nonblocking(f.status_flags().nonblocking())?;
                 Ok(0)
             }
             _ => {
@@ -0,0 +1,54 @@
+use alloc::{collections::BTreeMap, string::String};
+use spin::RwLock;
+
+use super::path_link::replace_prefix;
+
+pub static FILE_MODES: FileModesManager = FileModesManager::new();
+
+/// 文件权限管理器
+///
+/// FAT 等文件系统不能记录文件的权限，因此创建文件和目录时请求的权限（已去掉文件创建掩码）
+/// 由这里按文件的实际路径（见 [`FilePath`](super::path_link::FilePath)）记录，`stat` 和
+/// 权限检查都使用记录的权限。没有记录的文件使用文件系统报告的权限。
+pub struct FileModesManager {
+    modes: RwLock<BTreeMap<String, u32>>,
+}
+
+impl FileModesManager {
+    pub const fn new() -> Self {
+        Self {
+            modes: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// 返回文件 `path` 的权限位，没有记录时为文件系统报告的 `fs_perm`
+    pub fn perm(&self, path: &str, fs_perm: u32) -> u32 {
+        self.modes.read().get(key(path)).copied().unwrap_or(fs_perm)
+    }
+
+    /// 文件被创建，记录它的权限位
+    pub fn create(&self, path: &str, mode: u32) {
+        self.modes.write().insert(key(path).into(), mode & 0o7777);
+    }
+
+    /// 文件或目录 `old` 被移动到 `new` 后，把 `old` 及其下所有文件的权限移到新路径
+    pub fn rename(&self, old: &str, new: &str) {
+        let (old, new) = (key(old), key(new));
+        let mut modes = self.modes.write();
+        *modes = core::mem::take(&mut *modes)
+            .into_iter()
+            .filter(|(path, _)| path != new)
+            .map(|(path, m)| (replace_prefix(&path, old, new).unwrap_or(path), m))
+            .collect();
+    }
+
+    /// 文件或目录被删除后不再记录它的权限
+    pub fn remove(&self, path: &str) {
+        self.modes.write().remove(key(path));
+    }
+}
+
+/// 目录的路径可能以 '/' 结尾，去掉后作为记录的键
+fn key(path: &str) -> &str {
+    path.trim_end_matches('/')
+}
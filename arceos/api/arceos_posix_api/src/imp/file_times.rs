@@ -0,0 +1,133 @@
+use alloc::{collections::BTreeMap, string::String};
+use core::time::Duration;
+use spin::RwLock;
+
+use super::path_link::replace_prefix;
+use super::time::realtime;
+
+/// 距上次访问超过这个时间后，读取文件总是更新访问时间
+const RELATIME_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 文件的访问、修改和状态改变时间，均为 `CLOCK_REALTIME` 的时间
+#[derive(Clone, Copy, Debug)]
+pub struct FileTimes {
+    pub atime: Duration,
+    pub mtime: Duration,
+    pub ctime: Duration,
+}
+
+pub static FILE_TIMES: FileTimesManager = FileTimesManager::new();
+
+/// 文件时间戳管理器
+///
+/// FAT 只记录精确到 2 秒的修改时间和精确到天的访问日期，因此 `stat` 报告的时间戳由
+/// 这里按文件的实际路径（见 [`FilePath`](super::path_link::FilePath)）记录。
+/// 启动后没有变化过的文件报告启动时间。
+pub struct FileTimesManager {
+    times: RwLock<BTreeMap<String, FileTimes>>,
+}
+
+impl FileTimesManager {
+    pub const fn new() -> Self {
+        Self {
+            times: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// 返回文件 `path` 的时间戳
+    pub fn get(&self, path: &str) -> FileTimes {
+        self.times
+            .read()
+            .get(key(path))
+            .copied()
+            .unwrap_or_else(|| {
+                // 启动时尚未设置过时钟；之后设置时钟也不会改变这些文件的时间戳
+                let boot = Duration::from_nanos(axhal::time::epochoffset_nanos());
+                FileTimes {
+                    atime: boot,
+                    mtime: boot,
+                    ctime: boot,
+                }
+            })
+    }
+
+    /// 文件被创建，三个时间都设为当前时间
+    pub fn create(&self, path: &str) {
+        let now = realtime();
+        self.times.write().insert(
+            key(path).into(),
+            FileTimes {
+                atime: now,
+                mtime: now,
+                ctime: now,
+            },
+        );
+    }
+
+    /// 文件内容被修改，如写入和截断，更新修改时间和状态改变时间
+    pub fn modify(&self, path: &str) {
+        self.update(path, |times, now| {
+            times.mtime = now;
+            times.ctime = now;
+        });
+    }
+
+    /// 文件的元数据被修改，如创建链接和重命名，更新状态改变时间
+    pub fn change(&self, path: &str) {
+        self.update(path, |times, now| times.ctime = now);
+    }
+
+    /// 文件被读取，按 `relatime` 的规则更新访问时间：只在访问时间早于修改时间或状态
+    /// 改变时间，或距今超过一天时更新，避免每次读取都更新
+    pub fn access(&self, path: &str) {
+        let times = self.get(path);
+        let now = realtime();
+        if times.atime <= times.mtime
+            || times.atime <= times.ctime
+            || now.saturating_sub(times.atime) >= RELATIME_INTERVAL
+        {
+            self.update(path, |times, now| times.atime = now);
+        }
+    }
+
+    /// 显式设置访问时间和修改时间，如 `utimensat`，为 `None` 的时间不变。
+    /// 状态改变时间总是更新为当前时间。
+    pub fn set(&self, path: &str, atime: Option<Duration>, mtime: Option<Duration>) {
+        self.update(path, |times, now| {
+            if let Some(atime) = atime {
+                times.atime = atime;
+            }
+            if let Some(mtime) = mtime {
+                times.mtime = mtime;
+            }
+            times.ctime = now;
+        });
+    }
+
+    /// 文件或目录 `old` 被移动到 `new` 后，把 `old` 及其下所有文件的时间戳移到新路径
+    pub fn rename(&self, old: &str, new: &str) {
+        let (old, new) = (key(old), key(new));
+        let mut times = self.times.write();
+        *times = core::mem::take(&mut *times)
+            .into_iter()
+            .filter(|(path, _)| path != new)
+            .map(|(path, t)| (replace_prefix(&path, old, new).unwrap_or(path), t))
+            .collect();
+    }
+
+    /// 文件被删除后不再记录它的时间戳
+    pub fn remove(&self, path: &str) {
+        self.times.write().remove(key(path));
+    }
+
+    fn update(&self, path: &str, f: impl FnOnce(&mut FileTimes, Duration)) {
+        let times = self.get(path);
+        let mut map = self.times.write();
+        f(map.entry(key(path).into()).or_insert(times), realtime());
+    }
+}
+
+/// 目录的路径可能以 '/' 结尾，去掉后作为记录的键
+fn key(path: &str) -> &str {
+    path.trim_end_matches('/')
+}
@@ -1,73 +1,241 @@
 use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
     string::{String, ToString},
     sync::Arc,
+    vec::Vec,
 };
 use core::ffi::{c_char, c_int};
 
-use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
-use axio::{PollState, SeekFrom};
+use axdriver::prelude::DevError;
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axfs::{fops::OpenOptions, FsEvent};
+use axio::SeekFrom;
 use axsync::Mutex;
 
-use super::fd_ops::{get_file_like, FileLike};
-use crate::{ctypes, utils::char_ptr_to_str, FilePath, AT_FDCWD};
+use super::fd_ops::{get_file_like, FileLike, StatusFlags};
+use super::file_modes::FILE_MODES;
+use super::file_times::FILE_TIMES;
+use super::pollable::{PollState, Pollable};
+use super::special_file::{SpecialFile, SPECIAL_FILES};
+use super::symlink::S_IFLNK;
+use crate::{ctypes, resolve_symlinks, utils::char_ptr_to_str, FilePath, HARDLINK_MANAGER};
+
+/// The paths of the unnamed files created with `O_TMPFILE`, which are hidden
+/// from the listings of their directories.
+static UNNAMED_FILES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Whether the file at `path` is an unnamed file created with `O_TMPFILE`.
+pub fn is_unnamed(path: &str) -> bool {
+    UNNAMED_FILES.lock().contains(path)
+}
+
+/// How an open file is named in the filesystem tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Naming {
+    /// The file has a name in its directory.
+    Named,
+    /// The file was created with `O_TMPFILE`. It is kept under a hidden name
+    /// until it is linked into the tree, and removed when closed otherwise.
+    /// Without `linkable` (`O_EXCL`), it can never be linked.
+    Unnamed { linkable: bool },
+}
 
 pub struct File {
     inner: Mutex<axfs::fops::File>,
-    path: String,
+    path: Mutex<String>,
+    flags: StatusFlags,
+    naming: Mutex<Naming>,
 }
 
 impl File {
-    fn new(inner: axfs::fops::File, path: String) -> Self {
+    fn new(inner: axfs::fops::File, path: String, flags: c_int) -> Self {
         Self {
             inner: Mutex::new(inner),
-            path,
+            path: Mutex::new(path),
+            flags: StatusFlags::new(flags as u32),
+            naming: Mutex::new(Naming::Named),
         }
     }
 
-    fn add_to_fd_table(self) -> LinuxResult<c_int> {
-        super::fd_ops::add_file_like(Arc::new(self))
-    }
-
-    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+    pub fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
         let f = super::fd_ops::get_file_like(fd)?;
         f.into_any()
             .downcast::<Self>()
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    pub fn path(&self) -> &str {
-        &self.path
+    pub fn path(&self) -> String {
+        self.path.lock().clone()
     }
 
     pub fn inner(&self) -> &Mutex<axfs::fops::File> {
         &self.inner
     }
+
+    /// Whether the file was opened with `O_PATH`, so its contents cannot be
+    /// accessed through it.
+    pub fn path_only(&self) -> bool {
+        self.flags.path_only()
+    }
+
+    /// Returns `EROFS` if the file is on a filesystem mounted read-only.
+    fn check_writable(&self) -> LinuxResult {
+        FilePath::new(self.path())?.check_writable()
+    }
+
+    /// Makes the newly created file an unnamed file of `O_TMPFILE`, see
+    /// [`Naming::Unnamed`].
+    pub fn set_unnamed(&self, linkable: bool) {
+        UNNAMED_FILES.lock().insert(real_path(&self.path()));
+        *self.naming.lock() = Naming::Unnamed { linkable };
+    }
+
+    /// Whether the file is an unnamed file of `O_TMPFILE`.
+    pub fn is_unnamed(&self) -> bool {
+        matches!(*self.naming.lock(), Naming::Unnamed { .. })
+    }
+
+    /// Links the unnamed file into the tree at `new`, after which it is an
+    /// ordinary file.
+    ///
+    /// Returns `ENOENT` if the file cannot be linked, and `EEXIST` if `new`
+    /// already exists.
+    pub fn link_unnamed(&self, new: &FilePath) -> LinuxResult {
+        let mut naming = self.naming.lock();
+        if *naming != (Naming::Unnamed { linkable: true }) {
+            return Err(LinuxError::ENOENT);
+        }
+        if new.exists() || HARDLINK_MANAGER.real_path(new) != new.as_str() {
+            return Err(LinuxError::EEXIST);
+        }
+        let mut path = self.path.lock();
+        let old = real_path(&path);
+        HARDLINK_MANAGER.rename(&FilePath::new_link(&old)?, new)?;
+        UNNAMED_FILES.lock().remove(&old);
+        *path = new.to_string();
+        *naming = Naming::Named;
+        Ok(())
+    }
+
+    /// Truncates or extends the file to `size` bytes.
+    pub fn truncate(&self, size: u64) -> LinuxResult {
+        self.check_writable()?;
+        self.inner.lock().truncate(size)?;
+        self.modified();
+        Ok(())
+    }
+
+    /// Performs the `ioctl` request `cmd` with the argument `arg` through the
+    /// driver of the character device that the file is.
+    ///
+    /// Returns `None` if the file is not a character device registered by a
+    /// driver, and `ENOTTY` if the driver does not support `cmd`.
+    pub fn device_ioctl(&self, cmd: u32, arg: usize) -> Option<LinuxResult<usize>> {
+        let real_path = real_path(&self.path());
+        let special = SPECIAL_FILES
+            .get(&real_path)
+            .or_else(|| SpecialFile::from_device_path(&real_path));
+        let Some(SpecialFile::CharDevice(major, minor)) = special else {
+            return None;
+        };
+        let dev = axdriver::chardev::find_char_device(major, minor)?;
+        if self.flags.path_only() {
+            return Some(Err(LinuxError::EBADF));
+        }
+        Some(dev.driver.ioctl(cmd, arg).map_err(|err| match err {
+            DevError::Unsupported => LinuxError::ENOTTY,
+            DevError::InvalidParam => LinuxError::EINVAL,
+            DevError::Again => LinuxError::EAGAIN,
+            DevError::NoMemory => LinuxError::ENOMEM,
+            DevError::ResourceBusy => LinuxError::EBUSY,
+            _ => LinuxError::EIO,
+        }))
+    }
+
+    /// Updates the modification time after the contents are changed through
+    /// the file.
+    fn modified(&self) {
+        let path = real_path(&self.path());
+        FILE_TIMES.modify(&path);
+        axfs::notify(FsEvent::Modified { path: &path });
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let path = real_path(&self.path());
+        if self.is_unnamed() {
+            UNNAMED_FILES.lock().remove(&path);
+            if let Ok(name) = FilePath::new_link(&path) {
+                HARDLINK_MANAGER.remove_link(&name);
+            }
+            return;
+        }
+        if self.flags.writable() {
+            axfs::notify(FsEvent::ClosedWrite { path: &path });
+        }
+    }
 }
 
 impl FileLike for File {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        Ok(self.inner.lock().read(buf)?)
+        let n = self.inner.lock().read(buf)?;
+        FILE_TIMES.access(&real_path(&self.path()));
+        Ok(n)
     }
 
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        Ok(self.inner.lock().write(buf)?)
+        self.check_writable()?;
+        let mut inner = self.inner.lock();
+        // `O_APPEND` may have been changed by `F_SETFL` since the file was opened.
+        inner.set_append(self.flags.append());
+        let n = inner.write(buf)?;
+        drop(inner);
+        if n > 0 {
+            self.modified();
+        }
+        Ok(n)
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
+        // A symbolic link opened with `O_PATH | O_NOFOLLOW` is the link itself.
+        if self.flags.path_only() {
+            if let Ok(link) = FilePath::new_link(self.path()) {
+                if link.symlink_target().is_some() {
+                    return stat_path(&link);
+                }
+            }
+        }
         let metadata = self.inner.lock().get_attr()?;
         let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
+        let real_path = real_path(&self.path());
+        let perm = FILE_MODES.perm(&real_path, metadata.perm().bits() as u32);
+        let mut st_mode = ((ty as u32) << 12) | perm;
+        let times = FILE_TIMES.get(&real_path);
+        // A node created by `mknod` is an empty regular file in the filesystem.
+        let special = SPECIAL_FILES.get(&real_path);
+        if let Some(special) = special {
+            st_mode = special.file_type() | perm;
+        }
+        // The device files in `/dev` have the numbers of the devices.
+        let rdev = special
+            .or_else(|| SpecialFile::from_device_path(&real_path))
+            .map_or(0, |special| special.rdev());
+        let (st_uid, st_gid) = axfs::owner(&real_path).unwrap_or((1000, 1000));
         Ok(ctypes::stat {
             st_ino: 1,
             st_nlink: 1,
             st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
+            st_uid,
+            st_gid,
+            st_rdev: rdev,
             st_size: metadata.size() as _,
             st_blocks: metadata.blocks() as _,
             st_blksize: 512,
+            st_atime: times.atime.into(),
+            st_mtime: times.mtime.into(),
+            st_ctime: times.ctime.into(),
             ..Default::default()
         })
     }
@@ -76,22 +244,58 @@ impl FileLike for File {
         self
     }
 
-    fn poll(&self) -> LinuxResult<PollState> {
-        Ok(PollState {
-            readable: true,
-            writable: true,
-        })
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
     }
 
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn link_target(&self) -> String {
+        let path = self.path();
+        if self.is_unnamed() || !axfs::api::absolute_path_exists(&path) {
+            format!("{} (deleted)", path)
+        } else {
+            path
+        }
+    }
+
+    /// Opens the same file again, even if it has been removed since it was
+    /// opened.
+    fn reopen(self: Arc<Self>, flags: c_int) -> LinuxResult<Arc<dyn FileLike>> {
+        let open_flags = flags as u32;
+        if open_flags & ctypes::O_DIRECTORY != 0 {
+            return Err(LinuxError::ENOTDIR);
+        }
+        if open_flags & 0b11 != ctypes::O_RDONLY || open_flags & ctypes::O_TRUNC != 0 {
+            self.check_writable()?;
+        }
+        let inner = self.inner.lock().reopen(&flags_to_options(flags, 0))?;
+        let file = File::new(inner, self.path(), flags);
+        if open_flags & ctypes::O_TRUNC != 0 {
+            file.modified();
+        }
+        Ok(Arc::new(file))
+    }
+}
+
+impl Pollable for File {
+    fn poll(&self) -> PollState {
+        PollState::ALWAYS_READY
+    }
 }
 
 /// Convert open flags to [`OpenOptions`].
 fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
     let mut options = OpenOptions::new();
+    if flags & ctypes::O_PATH != 0 {
+        // Only the location is opened, and flags other than `O_DIRECTORY` are ignored.
+        options.path(true);
+        options.directory(flags & ctypes::O_DIRECTORY != 0);
+        return options;
+    }
     match flags & 0b11 {
         ctypes::O_RDONLY => options.read(true),
         ctypes::O_WRONLY => options.write(true),
@@ -109,11 +313,6 @@ fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     if flags & ctypes::O_CREAT != 0 {
         options.create(true);
     }
-    if flags & ctypes::O_EXEC != 0 {
-        // options.create_new(true);
-        options.execute(true);
-        // options.directory(true);
-    }
     if flags & ctypes::O_DIRECTORY != 0 {
         options.directory(true);
     }
@@ -130,91 +329,212 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
 
     syscall_body!(sys_open, {
-        let options = flags_to_options(flags, mode);
-        if options.has_directory() {
-            return Directory::from_path(filename?.into(), &options)
-                .and_then(Directory::add_to_fd_table);
-        }
-        add_file_or_directory_fd(
-            axfs::fops::File::open,
-            axfs::fops::Directory::open_dir,
-            filename?,
-            &options,
-        )
+        let path = axfs::path::canonicalize(&axfs::CURRENT_DIR_PATH.lock(), filename?)?;
+        add_open_file(open_path(&path, flags, mode)?, flags)
     })
 }
 
+/// Adds the file opened with `flags` to the file descriptor table, setting the
+/// close-on-exec flag of the new descriptor from `O_CLOEXEC`.
+pub fn add_open_file(file: Arc<dyn FileLike>, flags: c_int) -> LinuxResult<c_int> {
+    let fd = super::fd_ops::add_file_like(file)?;
+    super::fd_ops::set_cloexec(fd, flags as u32 & ctypes::O_CLOEXEC != 0);
+    Ok(fd)
+}
+
+/// Opens the file at the canonical absolute path `path` after expanding the
+/// symbolic links in it. The file records the path with the links expanded.
+///
+/// With `O_NOFOLLOW`, fails with `ELOOP` if the final component is a symbolic
+/// link, unless `O_PATH` is also given, which opens the link itself. With
+/// `O_CREAT | O_EXCL`, fails with `EEXIST` if the name already exists.
+///
+/// The file is not added to the file descriptor table, see [`add_open_file`].
+pub fn open_path(path: &str, flags: c_int, mode: ctypes::mode_t) -> LinuxResult<Arc<dyn FileLike>> {
+    let mut flags = flags as u32;
+    if flags & ctypes::O_PATH != 0 {
+        // Other flags have no effect on a descriptor that only names a location.
+        flags &= ctypes::O_PATH | ctypes::O_DIRECTORY | ctypes::O_NOFOLLOW | ctypes::O_CLOEXEC;
+    }
+    let flags = flags as c_int;
+    let path_only = flags as u32 & ctypes::O_PATH != 0;
+    let nofollow = flags as u32 & ctypes::O_NOFOLLOW != 0;
+    // With `O_EXCL`, nothing may exist under the name, not even a symbolic link.
+    let excl = ctypes::O_CREAT | ctypes::O_EXCL;
+    if flags as u32 & excl == excl {
+        let name = resolve_symlinks(path, false)?;
+        if axfs::api::absolute_path_exists(&name) || HARDLINK_MANAGER.real_path(&name) != name {
+            return Err(LinuxError::EEXIST);
+        }
+    }
+    let resolved = resolve_symlinks(path, !nofollow)?;
+    let filename = resolved.as_str();
+    if nofollow
+        && !path_only
+        && FilePath::new_link(filename).is_ok_and(|path| path.symlink_target().is_some())
+    {
+        return Err(LinuxError::ELOOP);
+    }
+
+    let options = flags_to_options(flags, mode);
+    if options.has_directory() {
+        return Directory::from_path(filename.into(), &options, flags)
+            .map(|d| Arc::new(d) as Arc<dyn FileLike>);
+    }
+    // A hard link is opened as the file it points to.
+    let target = FilePath::new_link(filename)
+        .map(|link| HARDLINK_MANAGER.real_path(&link))
+        .ok();
+    let filename = match &target {
+        Some(target) if HARDLINK_MANAGER.link_count(&FilePath::new(target)?) > 1 => target,
+        _ => filename,
+    };
+    open_file_or_directory(
+        axfs::fops::File::open,
+        axfs::fops::Directory::open_dir,
+        filename,
+        filename.into(),
+        &options,
+        flags,
+        mode,
+    )
+}
+
 /// 功能：打开或创建一个文件；
 /// 输入：
 ///    fd：文件所在目录的文件描述符。
 ///    filename：要打开或创建的文件名。如为绝对路径，则忽略fd。如为相对路径，且fd是AT_FDCWD，则filename是相对于当前工作目录来说的。如为相对路径，且fd是一个文件描述符，则filename是相对于fd所指向的目录来说的。
 ///    flags：必须包含如下访问模式的其中一种：O_RDONLY，O_WRONLY，O_RDWR。还可以包含文件创建标志和文件状态标志。
 ///    mode：文件的所有权描述。详见man 7 inode 。
-/// 返回值：成功执行，返回新的文件描述符。失败，返回-1。
+/// 返回值：成功执行，返回新的文件描述符。失败，返回负的错误码。
 pub fn sys_openat(
     dirfd: c_int,
     filename: *const c_char,
     flags: c_int,
     mode: ctypes::mode_t,
 ) -> c_int {
-    let filename = match char_ptr_to_str(filename) {
-        Ok(s) => s,
-        Err(_) => return -1,
-    };
+    let filename = char_ptr_to_str(filename);
 
     debug!(
         "sys_openat <= {} {:?} {:#o} {:#o}",
         dirfd, filename, flags, mode
     );
 
-    let options = flags_to_options(flags, mode);
-
-    if filename.starts_with('/') || dirfd == AT_FDCWD as _ {
-        return sys_open(filename.as_ptr() as _, flags, mode);
-    }
-
-    match Directory::from_fd(dirfd).and_then(|dir| {
-        add_file_or_directory_fd(
-            |filename, options| dir.inner.lock().open_file_at(filename, options),
-            |filename, options| dir.inner.lock().open_dir_at(filename, options),
-            &filename,
-            &options,
-        )
-    }) {
-        Ok(fd) => fd,
-        Err(e) => {
-            debug!("sys_openat => {}", e);
-            -1
-        }
-    }
+    syscall_body!(sys_openat, {
+        let filename = filename?;
+        // The directory may have been opened with `O_PATH`, so open by the
+        // absolute path instead of through the directory.
+        let base = if filename.starts_with('/') {
+            axfs::CURRENT_DIR_PATH.lock().clone()
+        } else {
+            super::path_link::base_dir(dirfd as isize)?
+        };
+        let path = axfs::path::canonicalize(&base, filename)?;
+        add_open_file(open_path(&path, flags, mode)?, flags)
+    })
 }
 
-// 使用给定的函数打开文件或目录，并将其添加到文件描述符表中。
+// 使用给定的函数打开文件或目录。
 // 先尝试打开文件，如果失败，再尝试打开目录。
-fn add_file_or_directory_fd<F, D, E>(
+// `path` 是记录在文件描述符中的路径，`filename` 相对于 `open_file` 和 `open_dir` 所在的目录。
+// 新创建的文件的权限为 `mode`。
+fn open_file_or_directory<F, D, E>(
     open_file: F,
     open_dir: D,
     filename: &str,
+    path: String,
     options: &OpenOptions,
-) -> LinuxResult<c_int>
+    flags: c_int,
+    mode: ctypes::mode_t,
+) -> LinuxResult<Arc<dyn FileLike>>
 where
     E: Into<LinuxError>,
     F: FnOnce(&str, &OpenOptions) -> Result<axfs::fops::File, E>,
     D: FnOnce(&str, &OpenOptions) -> Result<axfs::fops::Directory, E>,
 {
+    let times_path = real_path(&path);
+    let open_flags = flags as u32;
+    // A node created by `mknod` opened with `O_PATH` is only its location.
+    let path_only = open_flags & ctypes::O_PATH != 0;
+    if let Some(special) = SPECIAL_FILES.get(&times_path).filter(|_| !path_only) {
+        return open_special(special, &times_path, path, options, flags);
+    }
+    let existed = axfs::api::absolute_path_exists(&times_path);
+    if open_flags & 0b11 != ctypes::O_RDONLY
+        || open_flags & ctypes::O_TRUNC != 0
+        || (open_flags & ctypes::O_CREAT != 0 && !existed)
+    {
+        FilePath::new(&times_path)?.check_writable()?;
+    }
     open_file(filename, options)
         .map_err(Into::into)
-        .map(|f| File::new(f, filename.into()))
-        .and_then(File::add_to_fd_table)
+        .map(|f| {
+            if !existed {
+                FILE_TIMES.create(&times_path);
+                FILE_MODES.create(&times_path, mode);
+            } else if open_flags & ctypes::O_TRUNC != 0 {
+                FILE_TIMES.modify(&times_path);
+                axfs::notify(FsEvent::Modified { path: &times_path });
+            }
+            Arc::new(File::new(f, path.clone(), flags)) as Arc<dyn FileLike>
+        })
         .or_else(|e| match e {
             LinuxError::EISDIR => open_dir(filename, options)
                 .map_err(Into::into)
-                .map(|d| Directory::new(d, filename.into()))
-                .and_then(Directory::add_to_fd_table),
+                .map(|d| Arc::new(Directory::new(d, path, flags)) as Arc<dyn FileLike>),
             _ => Err(e.into()),
         })
 }
 
+/// Opens the FIFO or device node `special` created by `mknod` at `path`, whose
+/// real path is `real_path`.
+///
+/// A FIFO is opened as an end of the pipe shared by all its opens, a device
+/// node as the device file in `/dev` with the same device number.
+fn open_special(
+    special: SpecialFile,
+    real_path: &str,
+    path: String,
+    options: &OpenOptions,
+    flags: c_int,
+) -> LinuxResult<Arc<dyn FileLike>> {
+    if options.has_directory() {
+        return Err(LinuxError::ENOTDIR);
+    }
+    match special {
+        #[cfg(feature = "pipe")]
+        SpecialFile::Fifo => {
+            let fifo = super::pipe::Pipe::open_fifo(real_path, flags as u32)?;
+            Ok(Arc::new(fifo))
+        }
+        #[cfg(not(feature = "pipe"))]
+        SpecialFile::Fifo => Err(LinuxError::ENXIO),
+        _ => {
+            let device = special.device_path().ok_or(LinuxError::ENXIO)?;
+            let file = axfs::fops::File::open(&device, options)?;
+            Ok(Arc::new(File::new(file, path, flags)))
+        }
+    }
+}
+
+/// Returns the real path of the file at `path`, under which its timestamps are
+/// recorded, or `path` itself if it cannot be resolved.
+fn real_path(path: &str) -> String {
+    FilePath::new(path).map_or_else(|_| path.to_string(), |path| path.to_string())
+}
+
+/// Commit filesystem caches to disk.
+///
+/// All files opened for writing by any task are flushed, see
+/// [`axfs::sync_all`].
+pub fn sys_sync() -> c_int {
+    debug!("sys_sync <=");
+    syscall_body!(sys_sync, {
+        axfs::sync_all();
+        Ok(0)
+    })
+}
+
 /// Set the position of the file indicated by `fd`.
 ///
 /// Return its position after seek.
@@ -227,7 +547,10 @@ pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off
             2 => SeekFrom::End(offset as _),
             _ => return Err(LinuxError::EINVAL),
         };
-        let off = File::from_fd(fd)?.inner.lock().seek(pos)?;
+        let off = match File::from_fd(fd) {
+            Ok(file) => file.inner.lock().seek(pos)?,
+            Err(_) => Directory::from_fd(fd)?.seek(pos)?,
+        };
         Ok(off)
     })
 }
@@ -245,12 +568,51 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         let mut options = OpenOptions::new();
         options.read(true);
         let file = axfs::fops::File::open(path?, &options)?;
-        let st = File::new(file, path?.to_string()).stat()?;
+        let st = File::new(file, path?.to_string(), ctypes::O_RDONLY as _).stat()?;
         unsafe { *buf = st };
         Ok(0)
     })
 }
 
+/// Gets the metadata of the file at `path`.
+///
+/// `path` is not resolved any further, so a symbolic link reports its own
+/// metadata with `S_IFLNK`.
+pub fn stat_path(path: &FilePath) -> LinuxResult<ctypes::stat> {
+    let times = FILE_TIMES.get(&real_path(path));
+    if let Some(target) = path.symlink_target() {
+        return Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: path.link_count() as u32,
+            st_mode: S_IFLNK | 0o777,
+            st_uid: 1000,
+            st_gid: 1000,
+            st_size: target.len() as _,
+            st_blksize: 512,
+            st_atime: times.atime.into(),
+            st_mtime: times.mtime.into(),
+            st_ctime: times.ctime.into(),
+            ..Default::default()
+        });
+    }
+
+    // A hard link is stated as the file it points to.
+    let real = FilePath::new(path.as_str())?;
+    let mut options = OpenOptions::new();
+    options.read(true);
+    match axfs::fops::File::open(&real, &options) {
+        Ok(file) => {
+            let mut st = File::new(file, real.to_string(), ctypes::O_RDONLY as _).stat()?;
+            st.st_nlink = real.link_count() as u32;
+            Ok(st)
+        }
+        Err(AxError::IsADirectory) => {
+            Directory::from_path(real.to_string(), &options, ctypes::O_RDONLY as _)?.stat()
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
 /// Get file metadata by `fd` and write into `buf`.
 ///
 /// Return 0 if success.
@@ -263,8 +625,10 @@ pub unsafe fn sys_fstat(fd: c_int, buf: *mut ctypes::stat) -> c_int {
 
         let file = get_file_like(fd)?;
         let mut stat = file.stat()?;
-        let nlinks = FilePath::new(File::from_fd(fd)?.path())?.link_count();
-        stat.st_nlink = nlinks as u32;
+        // Only regular files have hard links, other file-like objects report their own count.
+        if let Ok(file) = file.into_any().downcast::<File>() {
+            stat.st_nlink = FilePath::new(file.path())?.link_count() as u32;
+        }
         unsafe { *buf = stat };
         Ok(0)
     })
@@ -322,24 +686,69 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
 pub struct Directory {
     inner: Mutex<axfs::fops::Directory>,
     path: String,
+    flags: StatusFlags,
+    listing: Mutex<DirListing>,
+}
+
+/// The position of a directory and the entries read from it, see
+/// [`Directory::read_entries`].
+///
+/// Each entry gets an offset when it is first read through the open directory,
+/// and keeps it for as long as the directory is open and the entry exists, so
+/// an offset returned in `d_off` stays valid across later reads even when
+/// other entries are added or removed. Entries are listed in the order of
+/// their offsets, which puts the entries added after the directory was opened
+/// at the end.
+#[derive(Default)]
+struct DirListing {
+    /// Offset of the last entry returned, the next one is the first entry with
+    /// a larger offset. `0` is the start of the directory.
+    pos: u64,
+    /// Names, types and offsets of the entries in the order of their offsets,
+    /// `None` until they are read.
+    entries: Option<Vec<(String, axfs::fops::FileType, u64)>>,
+    /// Offsets given to the names in the directory.
+    offsets: BTreeMap<String, u64>,
+    /// The offset to give to the next new entry.
+    next_offset: u64,
+    /// Generation of the directory when the entries were read.
+    generation: u64,
+}
+
+impl DirListing {
+    /// Replaces the entries with those read from the filesystem. Names that
+    /// were already listed keep their offsets, and new ones get new offsets.
+    fn update(&mut self, entries: Vec<(String, axfs::fops::FileType)>) {
+        let mut offsets = BTreeMap::new();
+        let mut listed = Vec::with_capacity(entries.len());
+        for (name, ty) in entries {
+            let offset = self.offsets.get(&name).copied().unwrap_or_else(|| {
+                self.next_offset += 1;
+                self.next_offset
+            });
+            offsets.insert(name.clone(), offset);
+            listed.push((name, ty, offset));
+        }
+        listed.sort_unstable_by_key(|&(_, _, offset)| offset);
+        self.offsets = offsets;
+        self.entries = Some(listed);
+    }
 }
 
 impl Directory {
-    fn new(inner: axfs::fops::Directory, path: String) -> Self {
+    fn new(inner: axfs::fops::Directory, path: String, flags: c_int) -> Self {
         Self {
             inner: Mutex::new(inner),
             path,
+            flags: StatusFlags::new(flags as u32),
+            listing: Mutex::new(DirListing::default()),
         }
     }
 
-    fn from_path(path: String, options: &OpenOptions) -> LinuxResult<Self> {
+    fn from_path(path: String, options: &OpenOptions, flags: c_int) -> LinuxResult<Self> {
         axfs::fops::Directory::open_dir(&path, options)
             .map_err(Into::into)
-            .map(|d| Self::new(d, path))
-    }
-
-    fn add_to_fd_table(self) -> LinuxResult<c_int> {
-        super::fd_ops::add_file_like(Arc::new(self))
+            .map(|d| Self::new(d, path, flags))
     }
 
     pub fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
@@ -352,6 +761,91 @@ impl Directory {
     pub fn path(&self) -> &str {
         &self.path
     }
+
+    /// Calls `f` with the name, the type and the offset of the following entry
+    /// for each entry from the current position on, except `.` and `..`, until
+    /// `f` returns `false`. The position moves past the entries accepted by `f`.
+    ///
+    /// The entries are read from the filesystem once and reused until entries
+    /// in the directory change or it is rewound to the start, so reading a
+    /// directory in many small pieces takes time linear in its size. The
+    /// offsets are stable, see [`DirListing`].
+    pub fn read_entries(
+        &self,
+        mut f: impl FnMut(&str, axfs::fops::FileType, u64) -> bool,
+    ) -> LinuxResult {
+        if !self.flags.readable() {
+            return Err(LinuxError::EBADF);
+        }
+        let mut listing = self.listing.lock();
+        let generation = axfs::dir_generation(&self.path);
+        if listing.entries.is_none() || listing.generation != generation {
+            let entries = self.read_all_entries()?;
+            listing.update(entries);
+            listing.generation = generation;
+        }
+        let DirListing { pos, entries, .. } = &mut *listing;
+        let entries = entries.as_deref().unwrap_or_default();
+        let start = entries.partition_point(|&(_, _, offset)| offset <= *pos);
+        for (name, ty, offset) in &entries[start..] {
+            if !f(name, *ty, *offset) {
+                break;
+            }
+            *pos = *offset;
+        }
+        Ok(())
+    }
+
+    fn read_all_entries(&self) -> LinuxResult<Vec<(String, axfs::fops::FileType)>> {
+        const EMPTY: axfs::fops::DirEntry = axfs::fops::DirEntry::default();
+        let inner = self.inner.lock();
+        let mut entries = Vec::new();
+        let mut buf = Vec::new();
+        // Each read skips the entries before it, so read in growing chunks to
+        // keep the total time linear.
+        let mut chunk = 32;
+        loop {
+            buf.resize_with(chunk, || EMPTY);
+            let n = inner.read_dir_at(entries.len(), &mut buf)?;
+            entries.extend(buf[..n].iter().map(|entry| {
+                let name = String::from_utf8_lossy(entry.name_as_bytes()).into_owned();
+                (name, entry.entry_type())
+            }));
+            if n < chunk {
+                break;
+            }
+            chunk *= 2;
+        }
+        let dir = self.path.trim_end_matches('/');
+        entries.retain(|(name, _)| {
+            !matches!(name.as_str(), "." | "..") && !is_unnamed(&format!("{dir}/{name}"))
+        });
+        Ok(entries)
+    }
+
+    /// Moves the position to the offset given by `pos`, and returns the new
+    /// offset.
+    ///
+    /// The offset must be `0`, which rewinds to the start and reads the entries
+    /// again, or one returned as `d_off` by this open directory, which resumes
+    /// after that entry even if it has been removed since. Other offsets are
+    /// `EINVAL`.
+    pub fn seek(&self, pos: SeekFrom) -> LinuxResult<u64> {
+        let mut listing = self.listing.lock();
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(off) => listing.pos.checked_add_signed(off),
+            SeekFrom::End(_) => None,
+        };
+        let new_pos = new_pos
+            .filter(|&pos| pos <= listing.next_offset)
+            .ok_or(LinuxError::EINVAL)?;
+        if new_pos == 0 {
+            listing.entries = None;
+        }
+        listing.pos = new_pos;
+        Ok(new_pos)
+    }
 }
 
 impl FileLike for Directory {
@@ -364,21 +858,85 @@ impl FileLike for Directory {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        Err(LinuxError::EBADF)
+        let inner = self.inner.lock();
+        let metadata = inner.get_attr()?;
+        let real_path = real_path(&self.path);
+        let perm = FILE_MODES.perm(&real_path, metadata.perm().bits() as u32);
+        let st_mode = ((metadata.file_type() as u32) << 12) | perm;
+        // A directory opened with `O_PATH` cannot be listed, so its entries
+        // are counted through another open of it.
+        let reader;
+        let dir = if self.flags.path_only() {
+            let mut options = OpenOptions::new();
+            options.read(true);
+            reader = axfs::fops::Directory::open_dir(&self.path, &options)?;
+            &reader
+        } else {
+            &*inner
+        };
+        // A directory is linked from its parent, from its own `.` and from the
+        // `..` of each subdirectory.
+        const EMPTY: axfs::fops::DirEntry = axfs::fops::DirEntry::default();
+        let mut entries = [EMPTY; 16];
+        let (mut idx, mut subdirs) = (0, 0);
+        loop {
+            let n = dir.read_dir_at(idx, &mut entries)?;
+            if n == 0 {
+                break;
+            }
+            idx += n;
+            subdirs += entries[..n]
+                .iter()
+                .filter(|entry| entry.entry_type().is_dir())
+                .filter(|entry| !matches!(entry.name_as_bytes(), b"." | b".."))
+                .count();
+        }
+        let times = FILE_TIMES.get(&real_path);
+        let (st_uid, st_gid) = axfs::owner(&real_path).unwrap_or((1000, 1000));
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 2 + subdirs as u32,
+            st_mode,
+            st_uid,
+            st_gid,
+            st_size: metadata.size() as _,
+            st_blocks: metadata.blocks() as _,
+            st_blksize: 512,
+            st_atime: times.atime.into(),
+            st_mtime: times.mtime.into(),
+            st_ctime: times.ctime.into(),
+            ..Default::default()
+        })
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
         self
     }
 
-    fn poll(&self) -> LinuxResult<PollState> {
-        Ok(PollState {
-            readable: true,
-            writable: false,
-        })
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
     }
 
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn link_target(&self) -> String {
+        self.path.clone()
+    }
+
+    fn reopen(self: Arc<Self>, flags: c_int) -> LinuxResult<Arc<dyn FileLike>> {
+        let options = flags_to_options(flags | ctypes::O_DIRECTORY as c_int, 0);
+        Ok(Arc::new(Self::from_path(
+            self.path.clone(),
+            &options,
+            flags,
+        )?))
+    }
+}
+
+impl Pollable for Directory {
+    fn poll(&self) -> PollState {
+        PollState::ALWAYS_READY
+    }
 }
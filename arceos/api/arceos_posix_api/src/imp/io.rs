@@ -1,11 +1,13 @@
 use crate::ctypes;
-use axerrno::LinuxError;
+use axerrno::{LinuxError, LinuxResult};
 use core::ffi::{c_int, c_void};
 
 #[cfg(feature = "fd")]
-use crate::imp::fd_ops::get_file_like;
+use crate::imp::fd_ops::access_file_like;
 #[cfg(not(feature = "fd"))]
 use axio::prelude::*;
+#[cfg(feature = "fd")]
+use cap_access::Cap;
 
 /// Read data from the file indicated by `fd`.
 ///
@@ -17,16 +19,7 @@ pub fn sys_read(fd: c_int, buf: *mut c_void, count: usize) -> ctypes::ssize_t {
             return Err(LinuxError::EFAULT);
         }
         let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, count) };
-        #[cfg(feature = "fd")]
-        {
-            Ok(get_file_like(fd)?.read(dst)? as ctypes::ssize_t)
-        }
-        #[cfg(not(feature = "fd"))]
-        match fd {
-            0 => Ok(super::stdio::stdin().read(dst)? as ctypes::ssize_t),
-            1 | 2 => Err(LinuxError::EPERM),
-            _ => Err(LinuxError::EBADF),
-        }
+        Ok(read_fd(fd, dst)? as ctypes::ssize_t)
     })
 }
 
@@ -40,16 +33,27 @@ pub fn sys_write(fd: c_int, buf: *const c_void, count: usize) -> ctypes::ssize_t
             return Err(LinuxError::EFAULT);
         }
         let src = unsafe { core::slice::from_raw_parts(buf as *const u8, count) };
-        #[cfg(feature = "fd")]
-        {
-            Ok(get_file_like(fd)?.write(src)? as ctypes::ssize_t)
-        }
-        #[cfg(not(feature = "fd"))]
-        match fd {
-            0 => Err(LinuxError::EPERM),
-            1 | 2 => Ok(super::stdio::stdout().write(src)? as ctypes::ssize_t),
-            _ => Err(LinuxError::EBADF),
-        }
+        Ok(write_fd(fd, src)? as ctypes::ssize_t)
+    })
+}
+
+/// Read data into a vector of buffers, filling each before the next.
+///
+/// Only the first read may wait for data, the later ones are skipped once
+/// reading would block. Return the read size if success.
+pub unsafe fn sys_readv(fd: c_int, iov: *const ctypes::iovec, iocnt: c_int) -> ctypes::ssize_t {
+    debug!("sys_readv <= fd: {}", fd);
+    syscall_body!(sys_readv, {
+        let iovs = unsafe { iovecs(iov, iocnt)? };
+        let mut started = false;
+        transfer_iovecs(iovs, |iov| {
+            if core::mem::replace(&mut started, true) && !readable(fd)? {
+                return Ok(0);
+            }
+            let dst =
+                unsafe { core::slice::from_raw_parts_mut(iov.iov_base as *mut u8, iov.iov_len) };
+            read_fd(fd, dst)
+        })
     })
 }
 
@@ -57,16 +61,103 @@ pub fn sys_write(fd: c_int, buf: *const c_void, count: usize) -> ctypes::ssize_t
 pub unsafe fn sys_writev(fd: c_int, iov: *const ctypes::iovec, iocnt: c_int) -> ctypes::ssize_t {
     debug!("sys_writev <= fd: {}", fd);
     syscall_body!(sys_writev, {
-        if !(0..=1024).contains(&iocnt) {
-            return Err(LinuxError::EINVAL);
-        }
+        let iovs = unsafe { iovecs(iov, iocnt)? };
+        transfer_iovecs(iovs, |iov| {
+            let src =
+                unsafe { core::slice::from_raw_parts(iov.iov_base as *const u8, iov.iov_len) };
+            write_fd(fd, src)
+        })
+    })
+}
 
-        let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
-        let mut ret = 0;
-        for iov in iovs.iter() {
-            ret += sys_write(fd, iov.iov_base, iov.iov_len);
+fn read_fd(fd: c_int, dst: &mut [u8]) -> LinuxResult<usize> {
+    #[cfg(feature = "fd")]
+    {
+        access_file_like(fd, Cap::READ, LinuxError::EBADF)?.read(dst)
+    }
+    #[cfg(not(feature = "fd"))]
+    match fd {
+        0 => Ok(super::stdio::stdin().read(dst)?),
+        1 | 2 => Err(LinuxError::EPERM),
+        _ => Err(LinuxError::EBADF),
+    }
+}
+
+/// Whether reading `fd` would return at once.
+fn readable(fd: c_int) -> LinuxResult<bool> {
+    #[cfg(feature = "fd")]
+    {
+        let state = access_file_like(fd, Cap::READ, LinuxError::EBADF)?.poll();
+        Ok(state.readable || state.hangup)
+    }
+    #[cfg(not(feature = "fd"))]
+    {
+        let _ = fd;
+        Ok(true)
+    }
+}
+
+fn write_fd(fd: c_int, src: &[u8]) -> LinuxResult<usize> {
+    #[cfg(feature = "fd")]
+    {
+        access_file_like(fd, Cap::WRITE, LinuxError::EBADF)?.write(src)
+    }
+    #[cfg(not(feature = "fd"))]
+    match fd {
+        0 => Err(LinuxError::EPERM),
+        1 | 2 => Ok(super::stdio::stdout().write(src)?),
+        _ => Err(LinuxError::EBADF),
+    }
+}
+
+/// Returns the `iocnt` buffers at `iov`, checking them as Linux does.
+unsafe fn iovecs<'a>(iov: *const ctypes::iovec, iocnt: c_int) -> LinuxResult<&'a [ctypes::iovec]> {
+    if !(0..=1024).contains(&iocnt) {
+        return Err(LinuxError::EINVAL);
+    }
+    if iocnt == 0 {
+        return Ok(&[]);
+    }
+    if iov.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
+    let mut total = 0usize;
+    for iov in iovs {
+        total = total
+            .checked_add(iov.iov_len)
+            .filter(|&total| total <= isize::MAX as usize)
+            .ok_or(LinuxError::EINVAL)?;
+        if iov.iov_base.is_null() && iov.iov_len != 0 {
+            return Err(LinuxError::EFAULT);
         }
+    }
+    Ok(iovs)
+}
 
-        Ok(ret)
-    })
+/// Transfers the buffers in order with `transfer`, which returns the number
+/// of bytes transferred for one buffer.
+///
+/// It stops at the first buffer not transferred completely. Once some bytes
+/// have been transferred, an error (e.g. `EINTR` or `EAGAIN`) ends the
+/// transfer with the count so far instead of being returned, as the data
+/// cannot be taken back.
+fn transfer_iovecs(
+    iovs: &[ctypes::iovec],
+    mut transfer: impl FnMut(&ctypes::iovec) -> LinuxResult<usize>,
+) -> LinuxResult<ctypes::ssize_t> {
+    let mut total = 0;
+    for iov in iovs.iter().filter(|iov| iov.iov_len != 0) {
+        match transfer(iov) {
+            Ok(n) => {
+                total += n;
+                if n < iov.iov_len {
+                    break;
+                }
+            }
+            Err(err) if total == 0 => return Err(err),
+            Err(_) => break,
+        }
+    }
+    Ok(total as ctypes::ssize_t)
 }
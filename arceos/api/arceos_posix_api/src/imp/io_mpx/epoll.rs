@@ -4,7 +4,9 @@
 
 use alloc::collections::btree_map::Entry;
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::{ffi::c_int, time::Duration};
 
 use axerrno::{LinuxError, LinuxResult};
@@ -12,10 +14,15 @@ use axhal::time::wall_time;
 use axsync::Mutex;
 
 use crate::ctypes;
-use crate::imp::fd_ops::{add_file_like, get_file_like, FileLike};
+use crate::imp::fd_ops::{add_file_like, get_file_like, FileLike, StatusFlags};
+use crate::imp::pollable::{PollState, PollWaker, Pollable};
+
+/// How deep epoll instances may be nested in each other, as on Linux.
+const EPOLL_MAX_NESTS: usize = 4;
 
 pub struct EpollInstance {
     events: Mutex<BTreeMap<usize, ctypes::epoll_event>>,
+    flags: StatusFlags,
 }
 
 unsafe impl Send for ctypes::epoll_event {}
@@ -26,6 +33,7 @@ impl EpollInstance {
     pub fn new(_flags: usize) -> Self {
         Self {
             events: Mutex::new(BTreeMap::new()),
+            flags: StatusFlags::new(ctypes::O_RDWR),
         }
     }
 
@@ -36,14 +44,44 @@ impl EpollInstance {
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    fn control(&self, op: usize, fd: usize, event: &ctypes::epoll_event) -> LinuxResult<usize> {
-        match get_file_like(fd as c_int) {
-            Ok(_) => {}
-            Err(e) => return Err(e),
+    /// The file descriptors in the interest list. The list is not locked while
+    /// they are used, as a nested instance may be polled.
+    fn watched_fds(&self) -> Vec<usize> {
+        self.events.lock().keys().copied().collect()
+    }
+
+    /// Whether `other` is this instance or is watched by it, directly or
+    /// through at most `depth` nested instances.
+    fn reaches(&self, other: &EpollInstance, depth: usize) -> bool {
+        if core::ptr::eq(self, other) {
+            return true;
         }
+        if depth == 0 {
+            return false;
+        }
+        self.watched_fds().into_iter().any(|fd| {
+            get_file_like(fd as c_int)
+                .ok()
+                .and_then(|f| f.into_any().downcast::<EpollInstance>().ok())
+                .is_some_and(|inner| inner.reaches(other, depth - 1))
+        })
+    }
+
+    fn control(&self, op: usize, fd: usize, event: &ctypes::epoll_event) -> LinuxResult<usize> {
+        let file = get_file_like(fd as c_int)?;
 
         match op as u32 {
             ctypes::EPOLL_CTL_ADD => {
+                // An instance cannot watch itself, and watching another one
+                // must not make a loop, so that polling ends.
+                if let Ok(inner) = file.into_any().downcast::<EpollInstance>() {
+                    if core::ptr::eq(&*inner, self) {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    if inner.reaches(self, EPOLL_MAX_NESTS) {
+                        return Err(LinuxError::ELOOP);
+                    }
+                }
                 if let Entry::Vacant(e) = self.events.lock().entry(fd) {
                     e.insert(*event);
                 } else {
@@ -73,38 +111,56 @@ impl EpollInstance {
         Ok(0)
     }
 
+    /// Reports the ready files in `events`, one entry for each with all its
+    /// events, and returns the number of entries.
+    ///
+    /// `EPOLLHUP` and `EPOLLERR` are reported whether requested or not.
     fn poll_all(&self, events: &mut [ctypes::epoll_event]) -> LinuxResult<usize> {
         let ready_list = self.events.lock();
         let mut events_num = 0;
 
         for (infd, ev) in ready_list.iter() {
-            match get_file_like(*infd as c_int)?.poll() {
-                Err(_) => {
-                    if (ev.events & ctypes::EPOLLERR) != 0 {
-                        events[events_num].events = ctypes::EPOLLERR;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-                }
-                Ok(state) => {
-                    if state.readable && (ev.events & ctypes::EPOLLIN != 0) {
-                        events[events_num].events = ctypes::EPOLLIN;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-
-                    if state.writable && (ev.events & ctypes::EPOLLOUT != 0) {
-                        events[events_num].events = ctypes::EPOLLOUT;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-                }
+            if events_num == events.len() {
+                break;
+            }
+            let revents = get_file_like(*infd as c_int)?.poll().events(ev.events);
+            if revents != 0 {
+                events[events_num].events = revents;
+                events[events_num].data = ev.data;
+                events_num += 1;
             }
         }
         Ok(events_num)
     }
 }
 
+impl Pollable for EpollInstance {
+    /// The instance is readable when any of the files it watches is ready.
+    fn poll(&self) -> PollState {
+        let watched = self
+            .events
+            .lock()
+            .iter()
+            .map(|(fd, ev)| (*fd, ev.events))
+            .collect::<Vec<_>>();
+        let readable = watched.into_iter().any(|(fd, events)| {
+            get_file_like(fd as c_int).is_ok_and(|file| file.poll().events(events) != 0)
+        });
+        PollState {
+            readable,
+            ..Default::default()
+        }
+    }
+
+    fn register_waker(&self, waker: &Arc<dyn PollWaker>) {
+        for fd in self.watched_fds() {
+            if let Ok(file) = get_file_like(fd as c_int) {
+                file.register_waker(waker);
+            }
+        }
+    }
+}
+
 impl FileLike for EpollInstance {
     fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
         Err(LinuxError::ENOSYS)
@@ -128,13 +184,17 @@ impl FileLike for EpollInstance {
         self
     }
 
-    fn poll(&self) -> LinuxResult<axio::PollState> {
-        Err(LinuxError::ENOSYS)
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
     }
 
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn link_target(&self) -> String {
+        "anon_inode:[eventpoll]".into()
+    }
 }
 
 /// Creates a new epoll instance.
@@ -0,0 +1,77 @@
+use alloc::sync::Arc;
+use core::ffi::c_int;
+use core::time::Duration;
+
+use axerrno::LinuxError;
+use axhal::time::wall_time;
+
+use crate::ctypes;
+use crate::imp::fd_ops::get_file_like;
+use crate::imp::pollable::PollWaker;
+
+/// Polls each of `fds` once and sets its `revents`. Returns the number of
+/// entries with events.
+///
+/// Entries with a negative file descriptor are skipped. Invalid file
+/// descriptors report `POLLNVAL`, and `POLLHUP` and `POLLERR` are reported
+/// whether requested or not. If `waker` is given, it is registered with each
+/// file before the file is polled.
+pub fn poll_fds(fds: &mut [ctypes::pollfd], waker: Option<&Arc<dyn PollWaker>>) -> usize {
+    #[cfg(feature = "net")]
+    axnet::poll_interfaces();
+    let mut res_num = 0;
+    for pollfd in fds.iter_mut() {
+        pollfd.revents = 0;
+        if pollfd.fd < 0 {
+            continue;
+        }
+        let revents = match get_file_like(pollfd.fd) {
+            Ok(file) => {
+                if let Some(waker) = waker {
+                    file.register_waker(waker);
+                }
+                file.poll().events(pollfd.events as u16 as u32)
+            }
+            Err(_) => ctypes::POLLNVAL,
+        };
+        pollfd.revents = revents as _;
+        if revents != 0 {
+            res_num += 1;
+        }
+    }
+    res_num
+}
+
+/// Wait for one of a set of file descriptors to become ready to perform I/O
+///
+/// `timeout` is in milliseconds, negative for no timeout.
+pub unsafe fn sys_poll(fds: *mut ctypes::pollfd, nfds: ctypes::nfds_t, timeout: c_int) -> c_int {
+    debug!("sys_poll <= {:#x} {} {}", fds as usize, nfds, timeout);
+    syscall_body!(sys_poll, {
+        if fds.is_null() && nfds != 0 {
+            return Err(LinuxError::EFAULT);
+        }
+        let fds = if nfds == 0 {
+            &mut []
+        } else {
+            unsafe { core::slice::from_raw_parts_mut(fds, nfds as usize) }
+        };
+        let deadline = (timeout >= 0).then(|| wall_time() + Duration::from_millis(timeout as u64));
+
+        loop {
+            let res = poll_fds(fds, None);
+            if res > 0 {
+                return Ok(res);
+            }
+
+            if deadline.map_or(false, |ddl| wall_time() >= ddl) {
+                debug!("    timeout!");
+                return Ok(0);
+            }
+            if crate::imp::task::interrupted() {
+                return Err(LinuxError::EINTR);
+            }
+            crate::sys_sched_yield();
+        }
+    })
+}
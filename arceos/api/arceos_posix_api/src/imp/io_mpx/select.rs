@@ -80,24 +80,20 @@ impl FdSets {
                     continue;
                 }
                 let fd = i + j;
-                match get_file_like(fd as _)?.poll() {
-                    Ok(state) => {
-                        if state.readable && read_bits & bit != 0 {
-                            unsafe { set_fd_set(res_read_fds, fd) };
-                            res_num += 1;
-                        }
-                        if state.writable && write_bits & bit != 0 {
-                            unsafe { set_fd_set(res_write_fds, fd) };
-                            res_num += 1;
-                        }
-                    }
-                    Err(e) => {
-                        debug!("    except: {} {:?}", fd, e);
-                        if except_bits & bit != 0 {
-                            unsafe { set_fd_set(res_except_fds, fd) };
-                            res_num += 1;
-                        }
-                    }
+                // As on Linux, a hangup or an error ends the wait for reading,
+                // and an error the wait for writing.
+                let state = get_file_like(fd as _)?.poll();
+                if (state.readable || state.hangup || state.error) && read_bits & bit != 0 {
+                    unsafe { set_fd_set(res_read_fds, fd) };
+                    res_num += 1;
+                }
+                if (state.writable || state.error) && write_bits & bit != 0 {
+                    unsafe { set_fd_set(res_write_fds, fd) };
+                    res_num += 1;
+                }
+                if state.error && except_bits & bit != 0 {
+                    unsafe { set_fd_set(res_except_fds, fd) };
+                    res_num += 1;
                 }
                 j += 1;
             }
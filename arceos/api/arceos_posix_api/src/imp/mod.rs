@@ -9,27 +9,45 @@ pub mod time;
 #[cfg(feature = "fd")]
 pub mod fd_ops;
 #[cfg(feature = "fs")]
-pub mod fs;
+pub mod file_modes;
 #[cfg(feature = "fs")]
-pub mod path_link;
-#[cfg(any(feature = "select", feature = "epoll"))]
+pub mod file_times;
+#[cfg(feature = "fs")]
+pub mod fs;
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 pub mod io_mpx;
 #[cfg(feature = "net")]
 pub mod net;
+#[cfg(feature = "fs")]
+pub mod path_link;
 #[cfg(feature = "pipe")]
 pub mod pipe;
+#[cfg(feature = "fd")]
+pub mod pollable;
 #[cfg(feature = "multitask")]
 pub mod pthread;
+#[cfg(feature = "fd")]
+pub mod socket;
+#[cfg(feature = "fs")]
+pub mod special_file;
+#[cfg(feature = "fs")]
+pub mod symlink;
+#[cfg(feature = "fd")]
+pub mod unix;
+#[cfg(feature = "multitask")]
+pub mod wait_queue;
 
 #[ctor_bare::register_ctor]
 #[cfg(feature = "fd")]
 fn init_stdio() {
-    use crate::imp::fd_ops::FD_TABLE;
-    use alloc::sync::Arc;
+    use crate::imp::fd_ops::{new_entry, AX_FILE_LIMIT, FD_CLOEXEC, FD_LIMIT, FD_TABLE};
+    use alloc::{collections::BTreeSet, sync::Arc};
     use stdio::{stdin, stdout};
     let mut fd_table = flatten_objects::FlattenObjects::new();
-    fd_table.add_at(0, Arc::new(stdin()) as _).unwrap(); // stdin
-    fd_table.add_at(1, Arc::new(stdout()) as _).unwrap(); // stdout
-    fd_table.add_at(2, Arc::new(stdout()) as _).unwrap(); // stderr
+    fd_table.add_at(0, new_entry(Arc::new(stdin()))).unwrap(); // stdin
+    fd_table.add_at(1, new_entry(Arc::new(stdout()))).unwrap(); // stdout
+    fd_table.add_at(2, new_entry(Arc::new(stdout()))).unwrap(); // stderr
     FD_TABLE.init_new(spin::RwLock::new(fd_table));
+    FD_CLOEXEC.init_new(spin::RwLock::new(BTreeSet::new()));
+    FD_LIMIT.init_new(core::sync::atomic::AtomicUsize::new(AX_FILE_LIMIT));
 }
@@ -1,23 +1,58 @@
-use alloc::{sync::Arc, vec, vec::Vec};
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
 use core::ffi::{c_char, c_int, c_void};
 use core::mem::size_of;
 use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use axerrno::{LinuxError, LinuxResult};
-use axio::PollState;
 use axnet::{TcpSocket, UdpSocket};
 use axsync::Mutex;
 
-use super::fd_ops::FileLike;
+use super::fd_ops::{FileLike, StatusFlags};
+use super::pollable::{PollState, Pollable};
+use super::socket::{
+    option_bytes, option_value, socket_from_fd, SocketOps, SocketOptions, MSG_DONTWAIT, MSG_PEEK,
+    SHUT_RD, SHUT_RDWR, SHUT_WR, TCP_NODELAY,
+};
 use crate::ctypes;
 use crate::utils::char_ptr_to_str;
 
-pub enum Socket {
+enum SocketInner {
     Udp(Mutex<UdpSocket>),
     Tcp(Mutex<TcpSocket>),
 }
 
+pub struct Socket {
+    inner: SocketInner,
+    flags: StatusFlags,
+    options: SocketOptions,
+    /// Receiving was shut down, so receiving returns the end of the stream.
+    read_shut: AtomicBool,
+    /// Sending was shut down, so sending fails with `EPIPE`.
+    write_shut: AtomicBool,
+    /// A nonblocking `connect` is in progress, or has succeeded but not been
+    /// reported by another `connect` yet. A failure is reported by `poll` and
+    /// `SO_ERROR`.
+    connecting: AtomicBool,
+}
+
 impl Socket {
+    fn new(inner: SocketInner) -> Self {
+        // The sockets never block in `axnet`, see `block_on`.
+        match &inner {
+            SocketInner::Udp(udpsocket) => udpsocket.lock().set_nonblocking(true),
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(true),
+        }
+        Self {
+            inner,
+            flags: StatusFlags::new(ctypes::O_RDWR),
+            options: SocketOptions::new(),
+            read_shut: AtomicBool::new(false),
+            write_shut: AtomicBool::new(false),
+            connecting: AtomicBool::new(false),
+        }
+    }
+
     fn add_to_fd_table(self) -> LinuxResult<c_int> {
         super::fd_ops::add_file_like(Arc::new(self))
     }
@@ -29,98 +64,170 @@ impl Socket {
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
+    /// Calls `f` until it does not fail with `EAGAIN`, waiting in between
+    /// unless the socket is nonblocking or `dontwait` is set.
+    ///
+    /// The sockets of `axnet` are kept nonblocking, so that the waits happen
+    /// here, where a signal interrupts them with `EINTR`. Each call of `f`
+    /// either transfers some data or fails, so `EINTR` is only returned when
+    /// nothing has been transferred.
+    fn block_on<T>(&self, dontwait: bool, mut f: impl FnMut() -> LinuxResult<T>) -> LinuxResult<T> {
+        loop {
+            axnet::poll_interfaces();
+            match f() {
+                Err(LinuxError::EAGAIN) if !dontwait && !self.flags.nonblocking() => {
+                    if crate::imp::task::interrupted() {
+                        return Err(LinuxError::EINTR);
+                    }
+                    crate::sys_sched_yield();
+                }
+                res => return res,
+            }
         }
     }
 
-    fn recv(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
-        }
+    fn send(&self, buf: &[u8], dontwait: bool) -> LinuxResult<usize> {
+        self.block_on(dontwait, || match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
+        })
+    }
+
+    fn recv(&self, buf: &mut [u8], dontwait: bool) -> LinuxResult<usize> {
+        self.block_on(dontwait, || match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
+        })
     }
 
-    pub fn poll(&self) -> LinuxResult<PollState> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().poll()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().poll()?),
+    /// Whether the socket is readable or writable, recording the failure of
+    /// a nonblocking connect as the pending error.
+    fn poll_socket(&self) -> LinuxResult<axio::PollState> {
+        let state = match &self.inner {
+            SocketInner::Udp(udpsocket) => udpsocket.lock().poll()?,
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().poll()?,
+        };
+        // A nonblocking connect has failed if the socket is writable but not
+        // connected.
+        if state.writable && self.connecting.load(Ordering::Acquire) && self.peer_addr().is_err() {
+            self.connecting.store(false, Ordering::Release);
+            self.options.set_error(LinuxError::ECONNREFUSED);
         }
+        Ok(state)
     }
 
     fn local_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().local_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?),
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().local_addr()?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?),
         }
     }
 
     fn peer_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().peer_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?),
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().peer_addr()?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?),
         }
     }
 
     fn bind(&self, addr: SocketAddr) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
         }
     }
 
+    /// Connects the socket to `addr`.
+    ///
+    /// A nonblocking TCP socket returns `EINPROGRESS` at once. Another
+    /// `connect` returns `EALREADY` until the connection is made, and then
+    /// succeeds once, as on Linux. A failure is reported by `poll` and
+    /// `SO_ERROR`. A blocking `connect` interrupted by a signal returns
+    /// `EINTR`, and the connection goes on as if it were nonblocking.
     fn connect(&self, addr: SocketAddr) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr)?),
+        if self.connecting.load(Ordering::Acquire) {
+            self.poll_socket()?;
+            if self.connecting.load(Ordering::Acquire) {
+                self.peer_addr().map_err(|_| LinuxError::EALREADY)?;
+                self.connecting.store(false, Ordering::Release);
+                return Ok(());
+            }
+        }
+        let res = match &self.inner {
+            SocketInner::Udp(udpsocket) => udpsocket.lock().connect(addr),
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().connect(addr),
+        };
+        match res.map_err(LinuxError::from) {
+            Err(LinuxError::EAGAIN) if self.flags.nonblocking() => {
+                self.connecting.store(true, Ordering::Release);
+                Err(LinuxError::EINPROGRESS)
+            }
+            Err(LinuxError::EAGAIN) => {
+                self.connecting.store(true, Ordering::Release);
+                self.block_on(false, || match self.poll_socket()?.writable {
+                    true => Ok(()),
+                    false => Err(LinuxError::EAGAIN),
+                })?;
+                // A failed connection has already been marked as not connecting.
+                if self.connecting.swap(false, Ordering::AcqRel) {
+                    return Ok(());
+                }
+                Err(self
+                    .options
+                    .take_error()
+                    .unwrap_or(LinuxError::ECONNREFUSED))
+            }
+            // The TCP socket is already connected.
+            Err(LinuxError::EEXIST) => Err(LinuxError::EISCONN),
+            res => res,
         }
     }
 
     fn sendto(&self, buf: &[u8], addr: SocketAddr) -> LinuxResult<usize> {
-        match self {
+        self.block_on(false, || match &self.inner {
             // diff: must bind before sendto
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
-            Socket::Tcp(_) => Err(LinuxError::EISCONN),
-        }
+            SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
+            SocketInner::Tcp(_) => Err(LinuxError::EISCONN),
+        })
     }
 
     fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SocketAddr>)> {
-        match self {
+        self.block_on(false, || match &self.inner {
             // diff: must bind before recvfrom
-            Socket::Udp(udpsocket) => Ok(udpsocket
+            SocketInner::Udp(udpsocket) => Ok(udpsocket
                 .lock()
                 .recv_from(buf)
                 .map(|res| (res.0, Some(res.1)))?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
-        }
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
+        })
     }
 
     fn listen(&self) -> LinuxResult {
-        match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
+        match &self.inner {
+            SocketInner::Udp(_) => Err(LinuxError::EOPNOTSUPP),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
         }
     }
 
     fn accept(&self) -> LinuxResult<TcpSocket> {
-        match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().accept()?),
+        match &self.inner {
+            SocketInner::Udp(_) => Err(LinuxError::EOPNOTSUPP),
+            SocketInner::Tcp(tcpsocket) => {
+                self.block_on(false, || Ok(tcpsocket.lock().accept()?))
+            }
         }
     }
 
     fn shutdown(&self) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => {
+        match &self.inner {
+            SocketInner::Udp(udpsocket) => {
                 let udpsocket = udpsocket.lock();
                 udpsocket.peer_addr()?;
                 udpsocket.shutdown()?;
                 Ok(())
             }
 
-            Socket::Tcp(tcpsocket) => {
+            SocketInner::Tcp(tcpsocket) => {
                 let tcpsocket = tcpsocket.lock();
                 tcpsocket.peer_addr()?;
                 tcpsocket.shutdown()?;
@@ -130,13 +237,101 @@ impl Socket {
     }
 }
 
+impl SocketOps for Socket {
+    fn socket_type(&self) -> u32 {
+        match &self.inner {
+            SocketInner::Udp(_) => ctypes::SOCK_DGRAM,
+            SocketInner::Tcp(_) => ctypes::SOCK_STREAM,
+        }
+    }
+
+    fn options(&self) -> &SocketOptions {
+        &self.options
+    }
+
+    /// Sends the data. Passing files is only supported by Unix domain sockets.
+    fn send_msg(
+        &self,
+        buf: &[u8],
+        rights: Vec<Arc<dyn FileLike>>,
+        flags: u32,
+    ) -> LinuxResult<usize> {
+        if !rights.is_empty() {
+            return Err(LinuxError::EINVAL);
+        }
+        if self.write_shut.load(Ordering::Acquire) {
+            return Err(LinuxError::EPIPE);
+        }
+        self.send(buf, flags & MSG_DONTWAIT != 0)
+    }
+
+    fn recv_msg(&self, buf: &mut [u8], flags: u32) -> LinuxResult<(usize, Vec<Arc<dyn FileLike>>)> {
+        if self.read_shut.load(Ordering::Acquire) {
+            return Ok((0, Vec::new()));
+        }
+        let dontwait = flags & MSG_DONTWAIT != 0;
+        let len = if flags & MSG_PEEK != 0 {
+            self.block_on(dontwait, || match &self.inner {
+                SocketInner::Udp(udpsocket) => Ok(udpsocket.lock().peek_from(buf)?.0),
+                SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().peek(buf)?),
+            })?
+        } else {
+            self.recv(buf, dontwait)?
+        };
+        Ok((len, Vec::new()))
+    }
+
+    /// Shuts down receiving, sending or both on a connected socket. `SHUT_WR`
+    /// on a TCP socket sends the end of the stream to the peer, which can
+    /// still send data back.
+    fn shutdown_socket(&self, how: c_int) -> LinuxResult {
+        self.peer_addr()?;
+        match how {
+            SHUT_RD => self.read_shut.store(true, Ordering::Release),
+            SHUT_WR => {
+                if let SocketInner::Tcp(tcpsocket) = &self.inner {
+                    tcpsocket.lock().shutdown_write()?;
+                }
+                self.write_shut.store(true, Ordering::Release);
+            }
+            SHUT_RDWR => {
+                self.read_shut.store(true, Ordering::Release);
+                self.write_shut.store(true, Ordering::Release);
+                self.shutdown()?;
+            }
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(())
+    }
+
+    fn set_protocol_option(&self, level: c_int, name: c_int, value: &[u8]) -> LinuxResult {
+        match (&self.inner, level as u32, name) {
+            (SocketInner::Tcp(tcpsocket), ctypes::IPPROTO_TCP, TCP_NODELAY) => {
+                let nodelay = option_value::<c_int>(value)? != 0;
+                tcpsocket.lock().set_nodelay(nodelay);
+                Ok(())
+            }
+            _ => Err(LinuxError::ENOPROTOOPT),
+        }
+    }
+
+    fn get_protocol_option(&self, level: c_int, name: c_int) -> LinuxResult<Vec<u8>> {
+        match (&self.inner, level as u32, name) {
+            (SocketInner::Tcp(tcpsocket), ctypes::IPPROTO_TCP, TCP_NODELAY) => {
+                Ok(option_bytes(&(tcpsocket.lock().nodelay() as c_int)))
+            }
+            _ => Err(LinuxError::ENOPROTOOPT),
+        }
+    }
+}
+
 impl FileLike for Socket {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        self.recv(buf)
+        self.recv(buf, false)
     }
 
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        self.send(buf)
+        self.send(buf, false)
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
@@ -157,17 +352,38 @@ impl FileLike for Socket {
         self
     }
 
-    fn poll(&self) -> LinuxResult<PollState> {
-        self.poll()
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
     }
 
-    fn set_nonblocking(&self, nonblock: bool) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => udpsocket.lock().set_nonblocking(nonblock),
-            Socket::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(nonblock),
-        }
+    fn set_nonblocking(&self, _nonblock: bool) -> LinuxResult {
+        // The flag is checked by `block_on`.
         Ok(())
     }
+
+    fn link_target(&self) -> String {
+        format!("socket:[{}]", self as *const Self as usize)
+    }
+}
+
+impl Pollable for Socket {
+    /// A socket with a pending error is also readable and writable, so that
+    /// waiting for it ends.
+    fn poll(&self) -> PollState {
+        let Ok(state) = self.poll_socket() else {
+            return PollState {
+                error: true,
+                ..Default::default()
+            };
+        };
+        let error = self.options.has_error();
+        PollState {
+            readable: state.readable || error,
+            writable: state.writable || error,
+            hangup: false,
+            error,
+        }
+    }
 }
 
 impl From<SocketAddrV4> for ctypes::sockaddr_in {
@@ -236,11 +452,11 @@ pub fn sys_socket(domain: c_int, socktype: c_int, protocol: c_int) -> c_int {
         match (domain, socktype, protocol) {
             (ctypes::AF_INET, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
             | (ctypes::AF_INET, ctypes::SOCK_STREAM, 0) => {
-                Socket::Tcp(Mutex::new(TcpSocket::new())).add_to_fd_table()
+                Socket::new(SocketInner::Tcp(Mutex::new(TcpSocket::new()))).add_to_fd_table()
             }
             (ctypes::AF_INET, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
             | (ctypes::AF_INET, ctypes::SOCK_DGRAM, 0) => {
-                Socket::Udp(Mutex::new(UdpSocket::new())).add_to_fd_table()
+                Socket::new(SocketInner::Udp(Mutex::new(UdpSocket::new()))).add_to_fd_table()
             }
             _ => Err(LinuxError::EINVAL),
         }
@@ -317,7 +533,7 @@ pub fn sys_send(
     socket_fd: c_int,
     buf_ptr: *const c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
 ) -> ctypes::ssize_t {
     debug!(
         "sys_sendto <= {} {:#x} {} {}",
@@ -328,7 +544,7 @@ pub fn sys_send(
             return Err(LinuxError::EFAULT);
         }
         let buf = unsafe { core::slice::from_raw_parts(buf_ptr as *const u8, len) };
-        Socket::from_fd(socket_fd)?.send(buf)
+        socket_from_fd(socket_fd)?.send_msg(buf, Vec::new(), flag as u32)
     })
 }
 
@@ -371,7 +587,7 @@ pub fn sys_recv(
     socket_fd: c_int,
     buf_ptr: *mut c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
 ) -> ctypes::ssize_t {
     debug!(
         "sys_recv <= {} {:#x} {} {}",
@@ -382,7 +598,7 @@ pub fn sys_recv(
             return Err(LinuxError::EFAULT);
         }
         let buf = unsafe { core::slice::from_raw_parts_mut(buf_ptr as *mut u8, len) };
-        Socket::from_fd(socket_fd)?.recv(buf)
+        Ok(socket_from_fd(socket_fd)?.recv_msg(buf, flag as u32)?.0)
     })
 }
 
@@ -413,34 +629,20 @@ pub unsafe fn sys_accept(
         socket_fd, socket_addr as usize, socket_len as usize
     );
     syscall_body!(sys_accept, {
-        if socket_addr.is_null() || socket_len.is_null() {
-            return Err(LinuxError::EFAULT);
-        }
         let socket = Socket::from_fd(socket_fd)?;
         let new_socket = socket.accept()?;
         let addr = new_socket.peer_addr()?;
-        let new_fd = Socket::add_to_fd_table(Socket::Tcp(Mutex::new(new_socket)))?;
-        unsafe {
-            (*socket_addr, *socket_len) = into_sockaddr(addr);
+        let new_fd = Socket::new(SocketInner::Tcp(Mutex::new(new_socket))).add_to_fd_table()?;
+        // The peer address is not wanted if `socket_addr` is null.
+        if !socket_addr.is_null() && !socket_len.is_null() {
+            unsafe {
+                (*socket_addr, *socket_len) = into_sockaddr(addr);
+            }
         }
         Ok(new_fd)
     })
 }
 
-/// Shut down a full-duplex connection.
-///
-/// Return 0 if success.
-pub fn sys_shutdown(
-    socket_fd: c_int,
-    flag: c_int, // currently not used
-) -> c_int {
-    debug!("sys_shutdown <= {} {}", socket_fd, flag);
-    syscall_body!(sys_shutdown, {
-        Socket::from_fd(socket_fd)?.shutdown()?;
-        Ok(0)
-    })
-}
-
 /// Query addresses for a domain name.
 ///
 /// Only IPv4. Ports are always 0. Ignore servname and hint.
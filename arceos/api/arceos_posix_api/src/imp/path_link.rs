@@ -5,10 +5,14 @@ use core::ops::Deref;
 use spin::RwLock;
 
 use alloc::string::{String, ToString};
-use axerrno::{AxError, AxResult};
-use axfs::{api::canonicalize, CURRENT_DIR_PATH};
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult, LinuxError, LinuxResult};
+use axfs::{api::canonicalize, CURRENT_DIR_PATH, CURRENT_ROOT_PATH};
 
-use crate::FD_TABLE;
+use super::file_modes::FILE_MODES;
+use super::file_times::FILE_TIMES;
+use super::special_file::SPECIAL_FILES;
+use super::symlink::SYMLINKS;
 
 /// 一个规范化的文件路径表示
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
@@ -18,6 +22,13 @@ impl FilePath {
     /// 从路径字符串创建一个新的 `FilePath`，路径将被规范化。
     /// 输入路径可以是绝对路径或相对路径。
     pub fn new<P: AsRef<str>>(path: P) -> AxResult<Self> {
+        let path = Self::new_link(path)?;
+        Ok(Self(HARDLINK_MANAGER.real_path(&path.0)))
+    }
+
+    /// 与 [`FilePath::new`] 相同，但路径是硬链接时不解析为它指向的文件，
+    /// 用于操作链接本身，如 unlink 和 rename。
+    pub fn new_link<P: AsRef<str>>(path: P) -> AxResult<Self> {
         let path = path.as_ref();
         let canonical = canonicalize(path).map_err(|_| AxError::NotFound)?;
         let mut new_path = canonical.trim().to_string();
@@ -32,7 +43,7 @@ impl FilePath {
             "canonical path should start with /"
         );
 
-        Ok(Self(HARDLINK_MANAGER.real_path(&new_path)))
+        Ok(Self(new_path))
     }
 
     /// 返回底层路径的字符串切片
@@ -127,6 +138,22 @@ impl FilePath {
     pub fn link_count(&self) -> usize {
         HARDLINK_MANAGER.link_count(self)
     }
+
+    /// 此路径是符号链接时返回它的目标
+    pub fn symlink_target(&self) -> Option<String> {
+        let path = self.0.trim_end_matches('/');
+        SYMLINKS
+            .get(&HARDLINK_MANAGER.real_path(path))
+            .or_else(|| axfs::fd_link_target(path))
+    }
+
+    /// 检查能否修改此路径，路径在只读挂载的文件系统上时返回 `EROFS`
+    pub fn check_writable(&self) -> LinuxResult {
+        if axfs::is_read_only(&self.0)? {
+            return Err(LinuxError::EROFS);
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for FilePath {
@@ -178,11 +205,17 @@ impl Into<AxError> for LinkError {
 pub static HARDLINK_MANAGER: HardlinkManager = HardlinkManager::new();
 
 /// 硬链接管理器
+///
+/// 文件系统本身不支持硬链接，文件只保存在它的一个名字（实际路径）下，其他名字是指向
+/// 实际路径的链接。删除实际路径时，文件被移动到它的某个链接处，因此文件在还有名字时
+/// 不会被删除。
 pub struct HardlinkManager {
     inner: RwLock<LinkManagerInner>,
 }
 struct LinkManagerInner {
+    /// 链接到它指向的文件的实际路径
     links: BTreeMap<String, String>,
+    /// 有链接的文件的名字数量，包括实际路径本身
     ref_counts: BTreeMap<String, usize>,
 }
 
@@ -197,35 +230,72 @@ impl HardlinkManager {
         }
     }
 
-    /// 创建链接
+    /// 创建指向文件 `dst` 的链接 `src`
     /// 如果目标路径不存在，则返回 `LinkError::NotFound`
     /// 如果目标路径不是文件，则返回 `LinkError::NotFile`
+    /// 如果链接路径已存在，则返回 `LinkError::LinkExists`
     pub fn create_link(&self, src: &FilePath, dst: &FilePath) -> Result<(), LinkError> {
         if !dst.exists() {
             return Err(LinkError::NotFound);
         }
-        if !dst.is_dir() {
+        if dst.is_dir() || axfs::api::metadata(dst.as_str()).map_or(true, |m| m.is_dir()) {
             return Err(LinkError::NotFile);
         }
 
         let mut inner = self.inner.write();
-        self.atomic_link_update(&mut inner, src, dst);
+        if src.exists() || inner.links.contains_key(src.as_str()) {
+            return Err(LinkError::LinkExists);
+        }
+        inner.links.insert(src.to_string(), dst.to_string());
+        *inner.ref_counts.entry(dst.to_string()).or_insert(1) += 1;
+        FILE_TIMES.change(dst);
         Ok(())
     }
 
-    /// 移除链接
-    /// 链接数量为零 或 没有链接时， 删除文件
-    /// 如果路径对应的链接不存在 或 路径对应的文件不存在，则返回 `None`
-    /// 否则返回链接的目标路径
+    /// 移除文件的名字 `src`，它可以是链接或文件的实际路径
+    /// 文件没有其他名字时删除文件
+    /// 如果路径对应的文件不存在，则返回 `None`，否则返回文件原先的实际路径
     pub fn remove_link(&self, src: &FilePath) -> Option<String> {
         let mut inner = self.inner.write();
         self.atomic_link_remove(&mut inner, src).or_else(|| {
-            axfs::api::remove_file(src.as_str())
-                .ok()
-                .map(|_| src.to_string())
+            axfs::api::remove_file(src.as_str()).ok()?;
+            FILE_TIMES.remove(src);
+            FILE_MODES.remove(src);
+            SPECIAL_FILES.remove(src);
+            SYMLINKS.remove(src);
+            Some(src.to_string())
         })
     }
 
+    /// 把文件的名字 `old` 改为 `new`，两者都可以是链接或文件的实际路径
+    ///
+    /// `new` 已存在时先移除它；两者是同一个文件的名字时什么也不做。
+    pub fn rename(&self, old: &FilePath, new: &FilePath) -> AxResult {
+        let (real_old, real_new) = (self.real_path(old), self.real_path(new));
+        if real_old == real_new {
+            return Ok(());
+        }
+        if new.as_str() != real_new || self.link_count(&FilePath(real_new)) > 1 {
+            // `new` 是有链接的文件的名字，直接覆盖会删除其他名字共用的文件
+            self.remove_link(new);
+        }
+
+        let mut inner = self.inner.write();
+        if let Some(dst) = inner.links.remove(old.as_str()) {
+            FILE_TIMES.change(&dst);
+            inner.links.insert(new.to_string(), dst);
+            return Ok(());
+        }
+        axfs::api::rename(old.as_str(), new.as_str())?;
+        self.atomic_rekey(&mut inner, old.as_str(), new.as_str());
+        FILE_TIMES.rename(old, new);
+        FILE_TIMES.change(new);
+        FILE_MODES.rename(old, new);
+        SPECIAL_FILES.rename(old, new);
+        SYMLINKS.rename(old, new);
+        Ok(())
+    }
+
     pub fn real_path(&self, path: &str) -> String {
         self.inner
             .read()
@@ -235,6 +305,7 @@ impl HardlinkManager {
             .unwrap_or_else(|| path.to_string())
     }
 
+    /// 返回文件的名字数量，`path` 应是文件的实际路径
     pub fn link_count(&self, path: &FilePath) -> usize {
         let inner = self.inner.read();
         inner
@@ -246,52 +317,75 @@ impl HardlinkManager {
 
     // 原子操作helpers
 
-    /// 创建或更新链接
-    /// 如果链接已存在，则更新目标路径
-    /// 如果目标路径不存在，则返回 `LinkError::NotFound`
-    fn atomic_link_update(&self, inner: &mut LinkManagerInner, src: &FilePath, dst: &FilePath) {
-        if let Some(old_dst) = inner.links.get(src.as_str()) {
-            if old_dst == dst.as_str() {
-                return;
-            }
-            self.decrease_ref_count(inner, &old_dst.to_string());
-        }
-        inner.links.insert(src.to_string(), dst.to_string());
-        *inner.ref_counts.entry(dst.to_string()).or_insert(0) += 1;
-    }
-
-    /// 移除链接
-    /// 如果链接不存在，则返回 `None`，否则返回链接的目标路径
+    /// 移除文件的名字
+    /// 如果是链接，则只移除链接；如果是还有链接的文件的实际路径，则把文件移动到它的一个
+    /// 链接处。其他情况返回 `None`，否则返回文件原先的实际路径
     fn atomic_link_remove(&self, inner: &mut LinkManagerInner, src: &FilePath) -> Option<String> {
-        inner.links.remove(src.as_str()).map(|dst| {
+        if let Some(dst) = inner.links.remove(src.as_str()) {
             self.decrease_ref_count(inner, &dst);
-            dst
-        })
-    }
-
-    /// 减少引用计数
-    /// 如果引用计数为零，则删除链接，并删除文件，如果删除文件失败，则返回 `None`
-    /// 如果链接不存在，则返回 `None`
-    fn decrease_ref_count(&self, inner: &mut LinkManagerInner, path: &str) -> Option<()> {
+            FILE_TIMES.change(&dst);
+            return Some(dst);
+        }
+        let link = inner
+            .links
+            .iter()
+            .find(|(_, dst)| dst.as_str() == src.as_str())
+            .map(|(link, _)| link.clone())?;
+        if let Err(err) = axfs::api::rename(src.as_str(), &link) {
+            axlog::error!("failed to move {} to its link {}: {:?}", src, link, err);
+            return None;
+        }
+        inner.links.remove(&link);
+        self.atomic_rekey(inner, src.as_str(), &link);
+        FILE_TIMES.rename(src, &link);
+        FILE_MODES.rename(src, &link);
+        SPECIAL_FILES.rename(src, &link);
+        SYMLINKS.rename(src, &link);
+        FILE_TIMES.change(&link);
+        self.decrease_ref_count(inner, &link);
+        Some(src.to_string())
+    }
+
+    /// 文件或目录 `old` 被移动到 `new` 后，更新 `old` 及其下的所有路径
+    fn atomic_rekey(&self, inner: &mut LinkManagerInner, old: &str, new: &str) {
+        let rekey = |path: String| replace_prefix(&path, old, new).unwrap_or(path);
+        inner.links = core::mem::take(&mut inner.links)
+            .into_iter()
+            .map(|(link, dst)| (rekey(link), rekey(dst)))
+            .collect();
+        inner.ref_counts = core::mem::take(&mut inner.ref_counts)
+            .into_iter()
+            .map(|(path, count)| (rekey(path), count))
+            .collect();
+    }
+
+    /// 减少文件的名字数量，只剩实际路径时不再记录
+    fn decrease_ref_count(&self, inner: &mut LinkManagerInner, path: &str) {
         match inner.ref_counts.get_mut(path) {
-            Some(count) => {
-                *count -= 1;
-                if *count == 0 {
-                    inner.ref_counts.remove(path);
-                    axfs::api::remove_file(path).ok()?
-                }
-                Some(())
-            }
-            None => {
-                axlog::error!("link exists but ref count is zero");
-                None
+            Some(count) if *count > 2 => *count -= 1,
+            Some(_) => {
+                inner.ref_counts.remove(path);
             }
+            None => axlog::error!("link exists but ref count is zero"),
         }
     }
 }
 
+/// 把 `path` 开头的路径 `old` 替换为 `new`，`path` 既不是 `old` 也不在 `old` 之下时
+/// 返回 `None`
+pub(super) fn replace_prefix(path: &str, old: &str, new: &str) -> Option<String> {
+    let rest = path.strip_prefix(old.trim_end_matches('/'))?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    Some(format!("{}{}", new.trim_end_matches('/'), rest))
+}
+
 pub const AT_FDCWD: isize = -100;
 
+/// 解析一个路径时最多展开的符号链接数，超过时返回 `ELOOP`
+const MAX_SYMLINK_EXPANSIONS: usize = 40;
+
 /// 处理路径并返回规范化后的 `FilePath`
 ///
 /// * `dir_fd` - 目录的文件描述符，如果是 `AT_FDCWD`，则操作当前工作目录
@@ -300,97 +394,179 @@ pub const AT_FDCWD: isize = -100;
 ///
 /// * `force_dir` - 如果为 `true`，则将路径视为目录
 ///
-/// 该函数会处理链接并规范化路径
+/// 该函数会展开符号链接、处理硬链接并规范化路径
 pub fn handle_file_path(
     dir_fd: isize,
     path_addr: Option<*const u8>,
     force_dir: bool,
-) -> AxResult<FilePath> {
-    // 获取路径字符串
+) -> LinuxResult<FilePath> {
+    Ok(FilePath::new(absolute_path_at(
+        dir_fd, path_addr, force_dir, true,
+    )?)?)
+}
+
+/// 与 [`handle_file_path`] 相同，但路径的最后一个组件是符号链接或硬链接时不解析为它
+/// 指向的文件，用于操作链接本身
+pub fn handle_link_path(dir_fd: isize, path_addr: *const u8) -> LinuxResult<FilePath> {
+    Ok(FilePath::new_link(absolute_path_at(
+        dir_fd,
+        Some(path_addr),
+        false,
+        false,
+    )?)?)
+}
+
+/// 与 [`handle_file_path`] 相同，但路径将被修改（创建、写入、删除等），
+/// 路径在只读挂载的文件系统上时返回 `EROFS`
+pub fn handle_writable_path(
+    dir_fd: isize,
+    path_addr: Option<*const u8>,
+    force_dir: bool,
+) -> LinuxResult<FilePath> {
+    let path = handle_file_path(dir_fd, path_addr, force_dir)?;
+    path.check_writable()?;
+    Ok(path)
+}
+
+/// 与 [`handle_link_path`] 相同，但路径将被修改，路径在只读挂载的文件系统上时返回 `EROFS`
+pub fn handle_writable_link_path(dir_fd: isize, path_addr: *const u8) -> LinuxResult<FilePath> {
+    let path = handle_link_path(dir_fd, path_addr)?;
+    path.check_writable()?;
+    Ok(path)
+}
+
+/// 把相对于 `dir_fd` 的路径转换为规范化的绝对路径并展开其中的符号链接，
+/// `follow_last` 为 `false` 时不展开最后一个组件
+///
+/// 路径由 [`axfs::path::canonicalize`] 按组件规范化。空路径返回 `ENOENT`，
+/// 以 '/' 结尾的路径指向已存在的非目录文件时返回 `ENOTDIR`。
+fn absolute_path_at(
+    dir_fd: isize,
+    path_addr: Option<*const u8>,
+    force_dir: bool,
+    follow_last: bool,
+) -> LinuxResult<String> {
+    // 获取路径字符串，没有路径时操作 `dir_fd` 本身
     let path = match path_addr {
         Some(addr) => {
             if addr.is_null() {
                 axlog::warn!("路径地址为空");
-                return Err(AxError::BadAddress);
+                return Err(LinuxError::EFAULT);
             }
-            crate::utils::char_ptr_to_str(addr as *const i8)
-                .map_err(|_| AxError::NotFound)?
-                .to_string()
+            crate::utils::char_ptr_to_str(addr as *const i8).map_err(|_| LinuxError::ENOENT)?
         }
-        None => String::new(),
+        None => ".",
     };
 
-    // 处理空路径的情况
-    let mut path = if path.is_empty() {
-        handle_empty_path(dir_fd)?
+    // 相对路径以 `dir_fd` 所指向的目录为基准
+    let base = if path.starts_with('/') {
+        String::from("/")
     } else {
-        path
+        base_dir(dir_fd)?
     };
-
-    // 处理相对路径的情况
-    if !path.starts_with('/') {
-        if dir_fd == AT_FDCWD {
-            path = prepend_cwd(&path)?;
-        } else {
-            path = handle_relative_path(dir_fd, &path)?;
+    let path = axfs::path::canonicalize(&base, path)?;
+    let path = resolve_symlinks(&path, follow_last)?;
+
+    // 以 '/' 结尾的路径必须是目录
+    if path.len() > 1 && path.ends_with('/') {
+        let real_path = HARDLINK_MANAGER.real_path(path.trim_end_matches('/'));
+        if axfs::api::metadata(&real_path).is_ok_and(|metadata| !metadata.is_dir()) {
+            return Err(LinuxError::ENOTDIR);
         }
     }
 
-    // 根据 `force_dir` 和路径结尾调整路径
-    path = adjust_path_suffix(path, force_dir);
-
-    // 创建并返回 `FilePath`
-    FilePath::new(&path)
+    // 根据 `force_dir` 调整路径
+    Ok(adjust_path_suffix(path, force_dir))
 }
 
-fn handle_empty_path(dir_fd: isize) -> AxResult<String> {
-    if dir_fd == AT_FDCWD {
-        return Ok(String::from("."));
+/// 展开路径中的符号链接，返回不经过符号链接的绝对路径
+///
+/// `path` 应是由 [`axfs::path::canonicalize`] 规范化的绝对路径。依次检查每个路径组件，
+/// 组件是符号链接时用它的目标替换它后继续解析，目标可以是从进程根目录开始的绝对路径或
+/// 相对于链接所在目录的路径，目标中的 ".." 不会越过进程根目录。`follow_last` 为 `false`
+/// 时不展开最后一个组件。总共展开超过 40 次时返回 `ELOOP`。还没有任何符号链接时原样
+/// 返回 `path`。
+///
+/// `/proc/[pid]/fd` 中的链接展开为文件的实际路径，管道等没有路径的对象不展开。
+pub fn resolve_symlinks(path: &str, follow_last: bool) -> LinuxResult<String> {
+    if SYMLINKS.is_empty() && !path.contains("/fd/") {
+        return Ok(path.to_string());
+    }
+    debug_assert!(path.starts_with('/'), "路径应是绝对路径");
+    let root = CURRENT_ROOT_PATH.lock().trim_end_matches('/').to_string();
+
+    // 待解析的组件，逆序存放
+    let mut pending: Vec<String> = path.split('/').rev().map(String::from).collect();
+    // 已解析的部分，不以 '/' 结尾，根目录为空
+    let mut resolved = String::new();
+    let mut expansions = 0;
+    while let Some(name) = pending.pop() {
+        match name.as_str() {
+            "" | "." => continue,
+            ".." => {
+                if resolved != root {
+                    resolved.truncate(resolved.rfind('/').unwrap_or(0));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let candidate = format!("{}/{}", resolved, name);
+        if follow_last || !pending.is_empty() {
+            // 文件的实际路径不在进程根目录之下
+            let target = match SYMLINKS.get(&HARDLINK_MANAGER.real_path(&candidate)) {
+                Some(target) => Some((target, root.as_str())),
+                None => fd_link_path(&candidate).map(|target| (target, "")),
+            };
+            if let Some((target, target_root)) = target {
+                expansions += 1;
+                if expansions > MAX_SYMLINK_EXPANSIONS {
+                    return Err(LinuxError::ELOOP);
+                }
+                if target.starts_with('/') {
+                    resolved = target_root.to_string();
+                }
+                pending.extend(target.split('/').rev().map(String::from));
+                continue;
+            }
+        }
+        resolved = candidate;
     }
 
-    let fd_table = FD_TABLE.write();
-    if dir_fd >= fd_table.count() as isize || dir_fd < 0 {
-        axlog::warn!("文件描述符索引超出范围");
-        return Err(AxError::InvalidInput);
+    if resolved.is_empty() || path.ends_with('/') {
+        resolved.push('/');
     }
-    super::fs::Directory::from_fd(dir_fd as i32)
-        .map(|dir| dir.path().to_string())
-        .map_err(|_| AxError::NotFound)
+    Ok(resolved)
 }
 
-fn handle_relative_path(dir_fd: isize, path: &str) -> AxResult<String> {
-    let fd_table = FD_TABLE.write();
-    if dir_fd >= fd_table.count() as isize || dir_fd < 0 {
-        axlog::warn!("文件描述符索引超出范围");
-        return Err(AxError::InvalidInput);
-    }
-    match super::fs::Directory::from_fd(dir_fd as i32) {
-        Ok(dir) => {
-            // 假设目录路径以 '/' 结尾，无需手动添加
-            let combined_path = format!("{}{}", dir.path(), path);
-            axlog::info!("处理后的路径: {} (目录: {})", combined_path, dir.path());
-            Ok(combined_path)
-        }
-        Err(_) => {
-            axlog::warn!("文件描述符不存在");
-            Err(AxError::NotFound)
-        }
+/// `path` 是 `/proc/[pid]/fd` 中指向有路径的文件的链接时，返回文件的实际路径
+fn fd_link_path(path: &str) -> Option<String> {
+    let (dir, name) = path.rsplit_once('/')?;
+    if !dir.ends_with("/fd") || !name.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
     }
+    axfs::fd_link_target(path).filter(|target| target.starts_with('/'))
 }
 
-fn prepend_cwd(path: &str) -> AxResult<String> {
-    let cwd = CURRENT_DIR_PATH.lock().clone();
-    debug_assert!(cwd.ends_with('/'), "当前工作目录路径应以 '/' 结尾");
-    Ok(format!("{}{}", cwd, path))
+/// 返回 `dir_fd` 所指向目录的路径，`dir_fd` 为 `AT_FDCWD` 时返回当前工作目录
+pub fn base_dir(dir_fd: isize) -> LinuxResult<String> {
+    if dir_fd == AT_FDCWD {
+        return Ok(CURRENT_DIR_PATH.lock().clone());
+    }
+    let file = super::fd_ops::get_file_like(dir_fd as i32)?;
+    file.into_any()
+        .downcast::<super::fs::Directory>()
+        .map(|dir| dir.path().to_string())
+        .map_err(|_| {
+            axlog::warn!("文件描述符不是目录");
+            LinuxError::ENOTDIR
+        })
 }
 
-/// 根据 `force_dir` 和路径结尾调整路径
+/// 根据 `force_dir` 调整路径，`force_dir` 为 `true` 时路径以 '/' 结尾
 fn adjust_path_suffix(mut path: String, force_dir: bool) -> String {
     if force_dir && !path.ends_with('/') {
         path.push('/');
     }
-    if path.ends_with('.') { // 防止路径以 '.' 结尾
-        path.push('/');
-    }
     path
 }
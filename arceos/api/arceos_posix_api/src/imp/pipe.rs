@@ -1,11 +1,18 @@
-use alloc::sync::Arc;
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::String,
+    sync::{Arc, Weak},
+};
 use core::ffi::c_int;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use axerrno::{LinuxError, LinuxResult};
-use axio::PollState;
 use axsync::Mutex;
 
-use super::fd_ops::{add_file_like, close_file_like, FileLike};
+use super::fd_ops::{add_file_like, close_file_like, set_cloexec, FileLike, StatusFlags};
+use super::pollable::{PollState, PollWaker, PollWakers, Pollable};
+use super::wait_queue::{WaitQueue, WaitResult};
 use crate::ctypes;
 
 #[derive(Copy, Clone, PartialEq)]
@@ -74,94 +81,240 @@ impl PipeRingBuffer {
     }
 }
 
+/// The buffer of a pipe shared by its ends, with the number of open ends.
+struct PipeInner {
+    buffer: Mutex<PipeRingBuffer>,
+    readers: AtomicUsize,
+    writers: AtomicUsize,
+    /// The number of times the pipe has been opened for reading and for
+    /// writing, so that an `open` of a FIFO waiting for the other end notices
+    /// an end that has been opened and closed in the meantime.
+    read_opens: AtomicUsize,
+    write_opens: AtomicUsize,
+    /// Woken when data is written or read, or an end is closed.
+    wakers: PollWakers,
+    /// Readers and writers blocked on the buffer, and `open`s of a FIFO
+    /// blocked for the other end, woken with `wakers`.
+    wait_queue: WaitQueue,
+}
+
+impl PipeInner {
+    const fn new() -> Self {
+        Self {
+            buffer: Mutex::new(PipeRingBuffer::new()),
+            readers: AtomicUsize::new(0),
+            writers: AtomicUsize::new(0),
+            read_opens: AtomicUsize::new(0),
+            write_opens: AtomicUsize::new(0),
+            wakers: PollWakers::new(),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// Wakes up the blocked tasks and the pollers after the buffer or an end
+    /// has changed.
+    fn wake_all(&self) {
+        self.wait_queue.notify_all();
+        self.wakers.wake_all();
+    }
+}
+
+/// The FIFOs that are open, by their real paths. The buffer of a FIFO is
+/// discarded once all its ends are closed.
+static FIFOS: spin::Mutex<BTreeMap<String, Weak<PipeInner>>> = spin::Mutex::new(BTreeMap::new());
+
 pub struct Pipe {
-    readable: bool,
-    buffer: Arc<Mutex<PipeRingBuffer>>,
+    inner: Arc<PipeInner>,
+    flags: StatusFlags,
 }
 
 impl Pipe {
-    pub fn new() -> (Pipe, Pipe) {
-        let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
-        let read_end = Pipe {
-            readable: true,
-            buffer: buffer.clone(),
+    /// Creates the read end and the write end of a pipe, both with the file
+    /// status flags in `flags`, e.g. `O_NONBLOCK`.
+    pub fn new(flags: u32) -> (Pipe, Pipe) {
+        let inner = Arc::new(PipeInner::new());
+        let read_end = Self::open_end(inner.clone(), flags | ctypes::O_RDONLY);
+        let write_end = Self::open_end(inner, flags | ctypes::O_WRONLY);
+        (read_end, write_end)
+    }
+
+    /// Opens the FIFO at `path` with the access mode and status flags in `flags`.
+    ///
+    /// Opening for reading or writing blocks until the other end is opened,
+    /// unless `O_NONBLOCK` is set: then opening for reading succeeds at once,
+    /// and opening for writing fails with `ENXIO` if there is no reader.
+    /// Opening for both never blocks.
+    pub fn open_fifo(path: &str, flags: u32) -> LinuxResult<Pipe> {
+        let inner = {
+            let mut fifos = FIFOS.lock();
+            fifos.retain(|_, inner| inner.strong_count() > 0);
+            match fifos.get(path).and_then(Weak::upgrade) {
+                Some(inner) => inner,
+                None => {
+                    let inner = Arc::new(PipeInner::new());
+                    fifos.insert(path.into(), Arc::downgrade(&inner));
+                    inner
+                }
+            }
         };
-        let write_end = Pipe {
-            readable: false,
-            buffer,
+        let nonblocking = flags & ctypes::O_NONBLOCK != 0;
+        let (others, other_opens) = match flags & 0b11 {
+            ctypes::O_RDONLY => (&inner.writers, &inner.write_opens),
+            ctypes::O_WRONLY => (&inner.readers, &inner.read_opens),
+            _ => return Ok(Self::open_end(inner, flags)),
         };
-        (read_end, write_end)
+        if nonblocking && flags & 0b11 == ctypes::O_WRONLY && others.load(Ordering::Acquire) == 0 {
+            return Err(LinuxError::ENXIO);
+        }
+        let opens = other_opens.load(Ordering::Acquire);
+        let pipe = Self::open_end(inner.clone(), flags);
+        if !nonblocking {
+            let opened = || {
+                others.load(Ordering::Acquire) > 0 || other_opens.load(Ordering::Acquire) != opens
+            };
+            if inner.wait_queue.wait_interruptible(opened) == WaitResult::Interrupted {
+                return Err(LinuxError::EINTR);
+            }
+        }
+        Ok(pipe)
+    }
+
+    /// Opens an end of the pipe, which reads and/or writes as the access mode
+    /// in `flags` allows.
+    fn open_end(inner: Arc<PipeInner>, flags: u32) -> Pipe {
+        let pipe = Pipe {
+            inner,
+            flags: StatusFlags::new(flags),
+        };
+        if pipe.readable() {
+            pipe.inner.readers.fetch_add(1, Ordering::AcqRel);
+            pipe.inner.read_opens.fetch_add(1, Ordering::AcqRel);
+        }
+        if pipe.writable() {
+            pipe.inner.writers.fetch_add(1, Ordering::AcqRel);
+            pipe.inner.write_opens.fetch_add(1, Ordering::AcqRel);
+        }
+        pipe.inner.wait_queue.notify_all();
+        pipe
     }
 
-    pub const fn readable(&self) -> bool {
-        self.readable
+    pub fn readable(&self) -> bool {
+        self.flags.readable()
     }
 
-    pub const fn writable(&self) -> bool {
-        !self.readable
+    pub fn writable(&self) -> bool {
+        self.flags.writable()
     }
 
     pub fn write_end_close(&self) -> bool {
-        Arc::strong_count(&self.buffer) == 1
+        self.inner.writers.load(Ordering::Acquire) == 0
+    }
+
+    fn read_end_close(&self) -> bool {
+        self.inner.readers.load(Ordering::Acquire) == 0
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        if self.readable() {
+            self.inner.readers.fetch_sub(1, Ordering::AcqRel);
+        }
+        if self.writable() {
+            self.inner.writers.fetch_sub(1, Ordering::AcqRel);
+        }
+        self.inner.wake_all();
     }
 }
 
 impl FileLike for Pipe {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        if !self.readable() {
-            return Err(LinuxError::EPERM);
-        }
         let mut read_size = 0usize;
         let max_len = buf.len();
         loop {
-            let mut ring_buffer = self.buffer.lock();
+            let mut ring_buffer = self.inner.buffer.lock();
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
-                if self.write_end_close() {
+                // Return what has been read instead of waiting for the buffer to be filled.
+                if read_size > 0 || self.write_end_close() {
                     return Ok(read_size);
                 }
+                if self.flags.nonblocking() {
+                    return Err(LinuxError::EAGAIN);
+                }
                 drop(ring_buffer);
                 // Data not ready, wait for write end
-                crate::sys_sched_yield(); // TODO: use synconize primitive
+                let ready =
+                    || self.inner.buffer.lock().available_read() > 0 || self.write_end_close();
+                if self.inner.wait_queue.wait_interruptible(ready) == WaitResult::Interrupted {
+                    return Err(LinuxError::EINTR);
+                }
                 continue;
             }
-            for _ in 0..loop_read {
-                if read_size == max_len {
-                    return Ok(read_size);
-                }
+            for _ in 0..loop_read.min(max_len - read_size) {
                 buf[read_size] = ring_buffer.read_byte();
                 read_size += 1;
             }
+            drop(ring_buffer);
+            self.inner.wake_all();
+            if read_size == max_len {
+                return Ok(read_size);
+            }
         }
     }
 
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        if !self.writable() {
-            return Err(LinuxError::EPERM);
-        }
         let mut write_size = 0usize;
         let max_len = buf.len();
         loop {
-            let mut ring_buffer = self.buffer.lock();
+            let mut ring_buffer = self.inner.buffer.lock();
+            if self.read_end_close() {
+                return match write_size {
+                    0 => Err(LinuxError::EPIPE),
+                    _ => Ok(write_size),
+                };
+            }
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
+                if self.flags.nonblocking() {
+                    return match write_size {
+                        0 => Err(LinuxError::EAGAIN),
+                        _ => Ok(write_size),
+                    };
+                }
                 drop(ring_buffer);
                 // Buffer is full, wait for read end to consume
-                crate::sys_sched_yield(); // TODO: use synconize primitive
+                let ready =
+                    || self.inner.buffer.lock().available_write() > 0 || self.read_end_close();
+                if self.inner.wait_queue.wait_interruptible(ready) == WaitResult::Interrupted {
+                    return match write_size {
+                        0 => Err(LinuxError::EINTR),
+                        _ => Ok(write_size),
+                    };
+                }
                 continue;
             }
-            for _ in 0..loop_write {
-                if write_size == max_len {
-                    return Ok(write_size);
-                }
+            for _ in 0..loop_write.min(max_len - write_size) {
                 ring_buffer.write_byte(buf[write_size]);
                 write_size += 1;
             }
+            drop(ring_buffer);
+            self.inner.wake_all();
+            if write_size == max_len {
+                return Ok(write_size);
+            }
         }
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let st_mode = 0o10000 | 0o600u32; // S_IFIFO | rw-------
+        // S_IFIFO, readable by the owner for the read end and writable for the write end
+        let mut st_mode = 0o10000;
+        if self.readable() {
+            st_mode |= 0o400;
+        }
+        if self.writable() {
+            st_mode |= 0o200;
+        }
         Ok(ctypes::stat {
             st_ino: 1,
             st_nlink: 1,
@@ -177,34 +330,77 @@ impl FileLike for Pipe {
         self
     }
 
-    fn poll(&self) -> LinuxResult<PollState> {
-        let buf = self.buffer.lock();
-        Ok(PollState {
-            readable: self.readable() && buf.available_read() > 0,
-            writable: self.writable() && buf.available_write() > 0,
-        })
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
     }
 
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn link_target(&self) -> String {
+        format!("pipe:[{}]", Arc::as_ptr(&self.inner) as usize)
+    }
+
+    /// Opens another end of the pipe, like opening a FIFO.
+    fn reopen(self: Arc<Self>, flags: c_int) -> LinuxResult<Arc<dyn FileLike>> {
+        Ok(Arc::new(Self::open_end(self.inner.clone(), flags as u32)))
+    }
+}
+
+impl Pollable for Pipe {
+    /// The read end hangs up once all write ends are closed, while data may
+    /// still be buffered, and the write end has an error once all read ends
+    /// are closed. An end open for both never sees either.
+    fn poll(&self) -> PollState {
+        let buf = self.inner.buffer.lock();
+        PollState {
+            readable: self.readable() && buf.available_read() > 0,
+            writable: self.writable() && buf.available_write() > 0,
+            // A FIFO opened for reading without blocking has no hangup until
+            // a writer has come.
+            hangup: self.readable()
+                && self.write_end_close()
+                && self.inner.write_opens.load(Ordering::Acquire) > 0,
+            error: self.writable() && self.read_end_close(),
+        }
+    }
+
+    fn register_waker(&self, waker: &Arc<dyn PollWaker>) {
+        self.inner.wakers.register(waker);
+    }
 }
 
 /// Create a pipe
 ///
 /// Return 0 if succeed
 pub fn sys_pipe(fds: &mut [c_int]) -> c_int {
-    debug!("sys_pipe <= {:#x}", fds.as_ptr() as usize);
-    syscall_body!(sys_pipe, {
+    sys_pipe2(fds, 0)
+}
+
+/// Create a pipe with `O_NONBLOCK` and `O_CLOEXEC` in `flags` applied to both ends
+///
+/// Return 0 if succeed
+pub fn sys_pipe2(fds: &mut [c_int], flags: c_int) -> c_int {
+    debug!("sys_pipe2 <= {:#x} {:#o}", fds.as_ptr() as usize, flags);
+    syscall_body!(sys_pipe2, {
         if fds.len() != 2 {
             return Err(LinuxError::EFAULT);
         }
+        let flags = flags as u32;
+        if flags & !(ctypes::O_NONBLOCK | ctypes::O_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
 
-        let (read_end, write_end) = Pipe::new();
+        let (read_end, write_end) = Pipe::new(flags & ctypes::O_NONBLOCK);
         let read_fd = add_file_like(Arc::new(read_end))?;
         let write_fd = add_file_like(Arc::new(write_end)).inspect_err(|_| {
             close_file_like(read_fd).ok();
         })?;
+        if flags & ctypes::O_CLOEXEC != 0 {
+            set_cloexec(read_fd, true);
+            set_cloexec(write_fd, true);
+        }
 
         fds[0] = read_fd as c_int;
         fds[1] = write_fd as c_int;
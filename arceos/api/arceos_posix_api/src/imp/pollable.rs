@@ -0,0 +1,98 @@
+//! Readiness of file-like objects, the only thing `select`, `poll` and
+//! `epoll` know about the files they wait on.
+
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+use crate::ctypes;
+
+/// The readiness of an object, as reported by [`Pollable::poll`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PollState {
+    /// Reading would not block.
+    pub readable: bool,
+    /// Writing would not block.
+    pub writable: bool,
+    /// The peer has gone away, e.g. all write ends of a pipe are closed. Data
+    /// may still be buffered, so this can come with `readable`.
+    pub hangup: bool,
+    /// An error is pending, e.g. all read ends of a pipe are closed.
+    pub error: bool,
+}
+
+impl PollState {
+    /// The state of an object that never blocks, such as a regular file.
+    pub const ALWAYS_READY: Self = Self {
+        readable: true,
+        writable: true,
+        hangup: false,
+        error: false,
+    };
+
+    /// Returns the `POLL*` events in the state: `POLLIN` and `POLLOUT` if
+    /// they are in `requested`, and `POLLHUP` and `POLLERR` always, as they
+    /// cannot be masked. The `EPOLL*` events have the same values.
+    pub fn events(&self, requested: u32) -> u32 {
+        let mut events = 0;
+        if self.readable {
+            events |= ctypes::POLLIN;
+        }
+        if self.writable {
+            events |= ctypes::POLLOUT;
+        }
+        events &= requested;
+        if self.hangup {
+            events |= ctypes::POLLHUP;
+        }
+        if self.error {
+            events |= ctypes::POLLERR;
+        }
+        events
+    }
+}
+
+/// Something to notify when a polled object may have become ready.
+pub trait PollWaker: Send + Sync {
+    /// Called by the object after its readiness may have changed.
+    fn wake(&self);
+}
+
+/// An object whose readiness can be waited for.
+pub trait Pollable {
+    /// Returns the current readiness of the object.
+    fn poll(&self) -> PollState;
+
+    /// Registers `waker` to be woken once the readiness may have changed.
+    ///
+    /// A waiter registers before polling, so that a change after the poll is
+    /// not missed. Objects keep only a weak reference and wake each waker at
+    /// most once. Objects that cannot notify ignore it, and waiters poll them
+    /// again from time to time.
+    fn register_waker(&self, _waker: &Arc<dyn PollWaker>) {}
+}
+
+/// The wakers registered on an object by [`Pollable::register_waker`].
+pub struct PollWakers(spin::Mutex<Vec<Weak<dyn PollWaker>>>);
+
+impl PollWakers {
+    pub const fn new() -> Self {
+        Self(spin::Mutex::new(Vec::new()))
+    }
+
+    /// Adds `waker`, dropping the wakers of waiters that have gone.
+    pub fn register(&self, waker: &Arc<dyn PollWaker>) {
+        let waker = Arc::downgrade(waker);
+        let mut wakers = self.0.lock();
+        wakers.retain(|w| w.strong_count() > 0 && !Weak::ptr_eq(w, &waker));
+        wakers.push(waker);
+    }
+
+    /// Wakes and removes all the wakers. It must not be called with a lock
+    /// held that the waiters take to poll the object.
+    pub fn wake_all(&self) {
+        let wakers = core::mem::take(&mut *self.0.lock());
+        for waker in wakers.iter().filter_map(Weak::upgrade) {
+            waker.wake();
+        }
+    }
+}
@@ -24,7 +24,7 @@ pub unsafe fn sys_getrlimit(resource: c_int, rlimits: *mut ctypes::rlimit) -> c_
             },
             #[cfg(feature = "fd")]
             ctypes::RLIMIT_NOFILE => unsafe {
-                (*rlimits).rlim_cur = super::fd_ops::AX_FILE_LIMIT as _;
+                (*rlimits).rlim_cur = super::fd_ops::fd_limit() as _;
                 (*rlimits).rlim_max = super::fd_ops::AX_FILE_LIMIT as _;
             },
             _ => {}
@@ -0,0 +1,234 @@
+//! Operations shared by all kinds of sockets: socket options, `shutdown`, and
+//! sending and receiving with `MSG_*` flags and passed file descriptors.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::ffi::c_int;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+
+use super::fd_ops::{get_file_like, FileLike};
+use super::unix::UnixSocket;
+
+/// The level of the options of the socket itself.
+pub const SOL_SOCKET: c_int = 1;
+pub const SO_TYPE: c_int = 3;
+pub const SO_ERROR: c_int = 4;
+pub const SO_SNDBUF: c_int = 7;
+pub const SO_RCVBUF: c_int = 8;
+pub const SO_LINGER: c_int = 13;
+/// The `IPPROTO_TCP` option disabling Nagle's algorithm.
+pub const TCP_NODELAY: c_int = 1;
+/// The type of the control message passing file descriptors.
+pub const SCM_RIGHTS: c_int = 1;
+
+/// Receive the data without removing it from the socket.
+pub const MSG_PEEK: u32 = 0x2;
+/// Some control data was discarded for lack of room, set by `recvmsg`.
+pub const MSG_CTRUNC: u32 = 0x8;
+/// Do not block, as if the socket were in nonblocking mode.
+pub const MSG_DONTWAIT: u32 = 0x40;
+/// Do not raise `SIGPIPE` when the peer can no longer receive.
+pub const MSG_NOSIGNAL: u32 = 0x4000;
+/// Set close-on-exec on the file descriptors received with `SCM_RIGHTS`.
+pub const MSG_CMSG_CLOEXEC: u32 = 0x4000_0000;
+
+pub const SHUT_RD: c_int = 0;
+pub const SHUT_WR: c_int = 1;
+pub const SHUT_RDWR: c_int = 2;
+
+/// The default size of the send and receive buffers, as on Linux.
+const DEFAULT_BUF_SIZE: usize = 212992;
+/// The largest buffer size that `setsockopt` accepts before doubling it.
+const MAX_BUF_SIZE: usize = 212992;
+const MIN_SNDBUF: usize = 4608;
+const MIN_RCVBUF: usize = 2304;
+
+/// The value of `SO_LINGER`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Linger {
+    pub l_onoff: c_int,
+    pub l_linger: c_int,
+}
+
+/// The options of a socket kept for `getsockopt`.
+pub struct SocketOptions {
+    sndbuf: AtomicUsize,
+    rcvbuf: AtomicUsize,
+    linger: Mutex<Linger>,
+    /// The pending error, reported and cleared by `SO_ERROR`.
+    error: AtomicI32,
+}
+
+impl SocketOptions {
+    pub fn new() -> Self {
+        Self {
+            sndbuf: AtomicUsize::new(DEFAULT_BUF_SIZE),
+            rcvbuf: AtomicUsize::new(DEFAULT_BUF_SIZE),
+            linger: Mutex::new(Linger::default()),
+            error: AtomicI32::new(0),
+        }
+    }
+
+    /// Returns the size of the send buffer in bytes.
+    pub fn sndbuf(&self) -> usize {
+        self.sndbuf.load(Ordering::Relaxed)
+    }
+
+    /// Records `err` as the pending error of the socket.
+    pub fn set_error(&self, err: LinuxError) {
+        self.error.store(err.code(), Ordering::Relaxed);
+    }
+
+    /// Whether the socket has a pending error.
+    pub fn has_error(&self) -> bool {
+        self.error.load(Ordering::Relaxed) != 0
+    }
+
+    /// Takes the pending error, as reading `SO_ERROR` does.
+    pub fn take_error(&self) -> Option<LinuxError> {
+        LinuxError::try_from(self.error.swap(0, Ordering::Relaxed)).ok()
+    }
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A socket, which the socket system calls operate on.
+pub trait SocketOps: FileLike {
+    /// Returns the type of the socket, e.g. `SOCK_STREAM`.
+    fn socket_type(&self) -> u32;
+
+    /// Returns the options of the socket.
+    fn options(&self) -> &SocketOptions;
+
+    /// Sends the data in `buf` with `MSG_*` `flags`, passing the files in
+    /// `rights` along with it. Returns the number of bytes sent.
+    fn send_msg(
+        &self,
+        buf: &[u8],
+        rights: Vec<Arc<dyn FileLike>>,
+        flags: u32,
+    ) -> LinuxResult<usize>;
+
+    /// Receives data into `buf` with `MSG_*` `flags`. Returns the number of
+    /// bytes received and the files passed along with them.
+    fn recv_msg(&self, buf: &mut [u8], flags: u32) -> LinuxResult<(usize, Vec<Arc<dyn FileLike>>)>;
+
+    /// Shuts down receiving, sending or both, as `how` of `shutdown` says.
+    fn shutdown_socket(&self, how: c_int) -> LinuxResult;
+
+    /// Sets the option `name` at the protocol `level`, which is not
+    /// `SOL_SOCKET`.
+    fn set_protocol_option(&self, _level: c_int, _name: c_int, _value: &[u8]) -> LinuxResult {
+        Err(LinuxError::ENOPROTOOPT)
+    }
+
+    /// Gets the option `name` at the protocol `level`, which is not
+    /// `SOL_SOCKET`.
+    fn get_protocol_option(&self, _level: c_int, _name: c_int) -> LinuxResult<Vec<u8>> {
+        Err(LinuxError::ENOPROTOOPT)
+    }
+}
+
+/// Returns the socket that `fd` refers to, or `ENOTSOCK` if it is another
+/// kind of file.
+pub fn socket_from_fd(fd: c_int) -> LinuxResult<Arc<dyn SocketOps>> {
+    let file = get_file_like(fd)?.into_any();
+    #[cfg(feature = "net")]
+    let file = match file.downcast::<super::net::Socket>() {
+        Ok(socket) => return Ok(socket),
+        Err(file) => file,
+    };
+    match file.downcast::<UnixSocket>() {
+        Ok(socket) => Ok(socket),
+        Err(_) => Err(LinuxError::ENOTSOCK),
+    }
+}
+
+/// Reads an option value of type `T` from `value`, which may be longer.
+pub fn option_value<T: Copy>(value: &[u8]) -> LinuxResult<T> {
+    if value.len() < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
+    }
+    // SAFETY: `value` holds at least `size_of::<T>()` bytes, and the option
+    // types are plain integers and structs of them.
+    Ok(unsafe { value.as_ptr().cast::<T>().read_unaligned() })
+}
+
+/// Returns the bytes of the option value `value`.
+pub fn option_bytes<T: Copy>(value: &T) -> Vec<u8> {
+    // SAFETY: the option types are plain integers and structs of them without
+    // padding.
+    unsafe { core::slice::from_raw_parts((value as *const T).cast::<u8>(), size_of::<T>()) }
+        .to_vec()
+}
+
+/// Sets the option `name` at `level` of the socket `fd` to `value`.
+///
+/// `SO_SNDBUF` and `SO_RCVBUF` are doubled and clamped as on Linux, so
+/// `getsockopt` reports a different size than the one set.
+pub fn setsockopt(fd: c_int, level: c_int, name: c_int, value: &[u8]) -> LinuxResult {
+    let socket = socket_from_fd(fd)?;
+    if level != SOL_SOCKET {
+        return socket.set_protocol_option(level, name, value);
+    }
+    let options = socket.options();
+    match name {
+        SO_SNDBUF | SO_RCVBUF => {
+            let size = (option_value::<c_int>(value)?.max(0) as usize).min(MAX_BUF_SIZE) * 2;
+            if name == SO_SNDBUF {
+                options
+                    .sndbuf
+                    .store(size.max(MIN_SNDBUF), Ordering::Relaxed);
+            } else {
+                options
+                    .rcvbuf
+                    .store(size.max(MIN_RCVBUF), Ordering::Relaxed);
+            }
+        }
+        SO_LINGER => *options.linger.lock() = option_value(value)?,
+        _ => return Err(LinuxError::ENOPROTOOPT),
+    }
+    Ok(())
+}
+
+/// Gets the option `name` at `level` of the socket `fd`. The caller copies as
+/// much of the value as the user buffer holds.
+pub fn getsockopt(fd: c_int, level: c_int, name: c_int) -> LinuxResult<Vec<u8>> {
+    let socket = socket_from_fd(fd)?;
+    if level != SOL_SOCKET {
+        return socket.get_protocol_option(level, name);
+    }
+    let options = socket.options();
+    let value = match name {
+        SO_TYPE => option_bytes(&(socket.socket_type() as c_int)),
+        SO_ERROR => {
+            // Polling records the result of a nonblocking connect.
+            socket.poll();
+            option_bytes(&options.error.swap(0, Ordering::Relaxed))
+        }
+        SO_SNDBUF => option_bytes(&(options.sndbuf() as c_int)),
+        SO_RCVBUF => option_bytes(&(options.rcvbuf.load(Ordering::Relaxed) as c_int)),
+        SO_LINGER => option_bytes(&*options.linger.lock()),
+        _ => return Err(LinuxError::ENOPROTOOPT),
+    };
+    Ok(value)
+}
+
+/// Shut down receiving, sending or both on a socket, as `how` says.
+///
+/// Return 0 if success.
+pub fn sys_shutdown(socket_fd: c_int, how: c_int) -> c_int {
+    debug!("sys_shutdown <= {} {}", socket_fd, how);
+    syscall_body!(sys_shutdown, {
+        socket_from_fd(socket_fd)?.shutdown_socket(how)?;
+        Ok(0)
+    })
+}
@@ -0,0 +1,161 @@
+use alloc::{collections::BTreeMap, format, string::String};
+use spin::RwLock;
+
+use super::path_link::replace_prefix;
+
+/// `st_mode` 中文件类型的掩码
+pub const S_IFMT: u32 = 0o170000;
+/// 命名管道
+pub const S_IFIFO: u32 = 0o010000;
+/// 字符设备
+pub const S_IFCHR: u32 = 0o020000;
+/// 块设备
+pub const S_IFBLK: u32 = 0o060000;
+
+/// 内置的字符设备：主设备号、次设备号和 `/dev` 下对应的设备文件
+///
+/// 驱动程序注册的字符设备（见 [`axdriver::chardev`]）的设备文件也在 `/dev` 下，以设备名命名。
+const CHAR_DEVICES: &[(u32, u32, &str)] = &[(1, 3, "/dev/null"), (1, 5, "/dev/zero")];
+/// virtio 块设备的主设备号，每个磁盘依次占用 16 个次设备号：整个磁盘和它的分区
+const VIRTIO_BLK_MAJOR: u32 = 254;
+const VIRTIO_BLK_MINORS: u32 = 16;
+
+/// 由 `mknod` 创建的特殊文件
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecialFile {
+    /// 命名管道
+    Fifo,
+    /// 字符设备，包含主设备号和次设备号
+    CharDevice(u32, u32),
+    /// 块设备，包含主设备号和次设备号
+    BlockDevice(u32, u32),
+}
+
+impl SpecialFile {
+    /// 返回 `st_mode` 中的文件类型
+    pub fn file_type(&self) -> u32 {
+        match self {
+            SpecialFile::Fifo => S_IFIFO,
+            SpecialFile::CharDevice(..) => S_IFCHR,
+            SpecialFile::BlockDevice(..) => S_IFBLK,
+        }
+    }
+
+    /// 返回 `st_rdev`，即按 Linux 的格式编码的设备号，命名管道为 0
+    pub fn rdev(&self) -> u64 {
+        match *self {
+            SpecialFile::Fifo => 0,
+            SpecialFile::CharDevice(major, minor) | SpecialFile::BlockDevice(major, minor) => {
+                let (major, minor) = (major as u64, minor as u64);
+                (major & 0xfff) << 8
+                    | (major & !0xfff) << 32
+                    | (minor & 0xff)
+                    | (minor & !0xff) << 12
+            }
+        }
+    }
+
+    /// 从 `st_rdev` 格式的设备号中解析出主设备号和次设备号
+    pub fn split_dev(dev: u64) -> (u32, u32) {
+        let major = (dev >> 8) & 0xfff | (dev >> 32) & !0xfff;
+        let minor = dev & 0xff | (dev >> 12) & !0xff;
+        (major as u32, minor as u32)
+    }
+
+    /// 返回设备在 `/dev` 下对应的设备文件，设备未注册或不是设备时返回 `None`
+    pub fn device_path(&self) -> Option<String> {
+        match *self {
+            SpecialFile::Fifo => None,
+            SpecialFile::CharDevice(major, minor) => CHAR_DEVICES
+                .iter()
+                .find(|dev| dev.0 == major && dev.1 == minor)
+                .map(|dev| dev.2.into())
+                .or_else(|| {
+                    axdriver::chardev::find_char_device(major, minor)
+                        .map(|dev| format!("/dev/{}", dev.name()))
+                }),
+            SpecialFile::BlockDevice(VIRTIO_BLK_MAJOR, minor) => {
+                let disk = minor / VIRTIO_BLK_MINORS;
+                let disk = char::from_u32('a' as u32 + disk).filter(char::is_ascii_lowercase)?;
+                Some(match minor % VIRTIO_BLK_MINORS {
+                    0 => format!("/dev/vd{}", disk),
+                    part => format!("/dev/vd{}{}", disk, part),
+                })
+            }
+            SpecialFile::BlockDevice(..) => None,
+        }
+    }
+
+    /// 返回 `/dev` 下的设备文件 `path` 对应的设备，与 [`device_path`](Self::device_path) 相反
+    pub fn from_device_path(path: &str) -> Option<Self> {
+        if let Some(dev) = CHAR_DEVICES.iter().find(|dev| dev.2 == path) {
+            return Some(SpecialFile::CharDevice(dev.0, dev.1));
+        }
+        if let Some(dev) = path
+            .strip_prefix("/dev/")
+            .and_then(axdriver::chardev::find_char_device_by_name)
+        {
+            return Some(SpecialFile::CharDevice(dev.major, dev.minor));
+        }
+        let name = path.strip_prefix("/dev/vd")?;
+        let disk = name.chars().next().filter(char::is_ascii_lowercase)?;
+        let part = match &name[1..] {
+            "" => 0,
+            part => part
+                .parse()
+                .ok()
+                .filter(|part| (1..VIRTIO_BLK_MINORS).contains(part))?,
+        };
+        let disk = disk as u32 - 'a' as u32;
+        Some(SpecialFile::BlockDevice(
+            VIRTIO_BLK_MAJOR,
+            disk * VIRTIO_BLK_MINORS + part,
+        ))
+    }
+}
+
+pub static SPECIAL_FILES: SpecialFileManager = SpecialFileManager::new();
+
+/// 特殊文件管理器
+///
+/// 文件系统本身不支持特殊文件，`mknod` 在文件系统中创建一个空的普通文件占据名字，
+/// 它的类型和设备号由这里按文件的实际路径记录。打开命名管道时得到与匿名管道相同的
+/// 管道端口，打开设备时得到 `/dev` 下对应的设备文件。
+pub struct SpecialFileManager {
+    files: RwLock<BTreeMap<String, SpecialFile>>,
+}
+
+impl SpecialFileManager {
+    pub const fn new() -> Self {
+        Self {
+            files: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// 在 `path` 创建特殊文件 `file`，`path` 已存在时返回 `AlreadyExists`
+    pub fn create(&self, path: &str, file: SpecialFile) -> axerrno::AxResult {
+        axfs::api::File::create_new(path)?;
+        self.files.write().insert(path.into(), file);
+        Ok(())
+    }
+
+    /// 返回 `path` 处的特殊文件，`path` 应是文件的实际路径
+    pub fn get(&self, path: &str) -> Option<SpecialFile> {
+        self.files.read().get(path).copied()
+    }
+
+    /// 文件或目录 `old` 被移动到 `new` 后，更新 `old` 及其下的所有特殊文件
+    pub fn rename(&self, old: &str, new: &str) {
+        let mut files = self.files.write();
+        *files = core::mem::take(&mut *files)
+            .into_iter()
+            .filter(|(path, _)| path != new)
+            .map(|(path, file)| (replace_prefix(&path, old, new).unwrap_or(path), file))
+            .collect();
+    }
+
+    /// 文件被删除后不再记录它
+    pub fn remove(&self, path: &str) {
+        self.files.write().remove(path);
+    }
+}
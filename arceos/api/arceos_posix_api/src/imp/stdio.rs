@@ -3,7 +3,15 @@ use axio::{prelude::*, BufReader};
 use axsync::Mutex;
 
 #[cfg(feature = "fd")]
-use {alloc::sync::Arc, axerrno::LinuxError, axerrno::LinuxResult, axio::PollState};
+use {
+    super::fd_ops::{FileLike, StatusFlags},
+    super::pollable::PollState,
+    crate::ctypes,
+    alloc::{string::String, sync::Arc},
+    axerrno::LinuxError,
+    axerrno::LinuxResult,
+    core::ffi::c_int,
+};
 
 fn console_read_bytes() -> Option<u8> {
     axhal::console::getchar().map(|c| if c == b'\r' { b'\n' } else { c })
@@ -45,6 +53,8 @@ impl Write for StdoutRaw {
 
 pub struct Stdin {
     inner: &'static Mutex<BufReader<StdinRaw>>,
+    #[cfg(feature = "fd")]
+    flags: StatusFlags,
 }
 
 impl Stdin {
@@ -73,6 +83,8 @@ impl Read for Stdin {
 
 pub struct Stdout {
     inner: &'static Mutex<StdoutRaw>,
+    #[cfg(feature = "fd")]
+    flags: StatusFlags,
 }
 
 impl Write for Stdout {
@@ -88,28 +100,42 @@ impl Write for Stdout {
 /// Constructs a new handle to the standard input of the current process.
 pub fn stdin() -> Stdin {
     static INSTANCE: Mutex<BufReader<StdinRaw>> = Mutex::new(BufReader::new(StdinRaw));
-    Stdin { inner: &INSTANCE }
+    Stdin {
+        inner: &INSTANCE,
+        #[cfg(feature = "fd")]
+        flags: StatusFlags::new(ctypes::O_RDONLY),
+    }
 }
 
 /// Constructs a new handle to the standard output of the current process.
 pub fn stdout() -> Stdout {
     static INSTANCE: Mutex<StdoutRaw> = Mutex::new(StdoutRaw);
-    Stdout { inner: &INSTANCE }
+    Stdout {
+        inner: &INSTANCE,
+        #[cfg(feature = "fd")]
+        flags: StatusFlags::new(ctypes::O_WRONLY),
+    }
 }
 
 #[cfg(feature = "fd")]
 impl super::fd_ops::FileLike for Stdin {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        Ok(self.read_blocked(buf)?)
+        if !self.flags.nonblocking() {
+            return Ok(self.read_blocked(buf)?);
+        }
+        match self.inner.lock().read(buf)? {
+            0 if !buf.is_empty() => Err(LinuxError::EAGAIN),
+            read_len => Ok(read_len),
+        }
     }
 
     fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
         Err(LinuxError::EPERM)
     }
 
-    fn stat(&self) -> LinuxResult<crate::ctypes::stat> {
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
         let st_mode = 0o20000 | 0o440u32; // S_IFCHR | r--r-----
-        Ok(crate::ctypes::stat {
+        Ok(ctypes::stat {
             st_ino: 1,
             st_nlink: 1,
             st_mode,
@@ -121,16 +147,29 @@ impl super::fd_ops::FileLike for Stdin {
         self
     }
 
-    fn poll(&self) -> LinuxResult<PollState> {
-        Ok(PollState {
-            readable: true,
-            writable: true,
-        })
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
     }
 
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn link_target(&self) -> String {
+        "/dev/console".into()
+    }
+
+    /// The console has no state of its own in an open file description.
+    fn reopen(self: Arc<Self>, _flags: c_int) -> LinuxResult<Arc<dyn FileLike>> {
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "fd")]
+impl super::pollable::Pollable for Stdin {
+    fn poll(&self) -> PollState {
+        PollState::ALWAYS_READY
+    }
 }
 
 #[cfg(feature = "fd")]
@@ -143,9 +182,9 @@ impl super::fd_ops::FileLike for Stdout {
         Ok(self.inner.lock().write(buf)?)
     }
 
-    fn stat(&self) -> LinuxResult<crate::ctypes::stat> {
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
         let st_mode = 0o20000 | 0o220u32; // S_IFCHR | -w--w----
-        Ok(crate::ctypes::stat {
+        Ok(ctypes::stat {
             st_ino: 1,
             st_nlink: 1,
             st_mode,
@@ -157,14 +196,27 @@ impl super::fd_ops::FileLike for Stdout {
         self
     }
 
-    fn poll(&self) -> LinuxResult<PollState> {
-        Ok(PollState {
-            readable: true,
-            writable: true,
-        })
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
     }
 
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn link_target(&self) -> String {
+        "/dev/console".into()
+    }
+
+    /// The console has no state of its own in an open file description.
+    fn reopen(self: Arc<Self>, _flags: c_int) -> LinuxResult<Arc<dyn FileLike>> {
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "fd")]
+impl super::pollable::Pollable for Stdout {
+    fn poll(&self) -> PollState {
+        PollState::ALWAYS_READY
+    }
 }
@@ -0,0 +1,58 @@
+use alloc::{collections::BTreeMap, string::String};
+use spin::RwLock;
+
+use super::path_link::replace_prefix;
+
+/// 符号链接
+pub const S_IFLNK: u32 = 0o120000;
+
+pub static SYMLINKS: SymlinkManager = SymlinkManager::new();
+
+/// 符号链接管理器
+///
+/// 文件系统本身不支持符号链接，`symlink` 在文件系统中创建一个空的普通文件占据名字，
+/// 链接的目标由这里按链接的实际路径记录。路径解析时由
+/// [`resolve_symlinks`](super::path_link::resolve_symlinks) 展开。
+pub struct SymlinkManager {
+    links: RwLock<BTreeMap<String, String>>,
+}
+
+impl SymlinkManager {
+    pub const fn new() -> Self {
+        Self {
+            links: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// 在 `path` 创建指向 `target` 的符号链接，`path` 已存在时返回 `AlreadyExists`
+    pub fn create(&self, path: &str, target: &str) -> axerrno::AxResult {
+        axfs::api::File::create_new(path)?;
+        self.links.write().insert(path.into(), target.into());
+        Ok(())
+    }
+
+    /// 返回符号链接 `path` 的目标，`path` 应是链接的实际路径，不是符号链接时返回 `None`
+    pub fn get(&self, path: &str) -> Option<String> {
+        self.links.read().get(path).cloned()
+    }
+
+    /// 是否还没有任何符号链接，此时路径解析不需要逐个检查路径组件
+    pub fn is_empty(&self) -> bool {
+        self.links.read().is_empty()
+    }
+
+    /// 文件或目录 `old` 被移动到 `new` 后，更新 `old` 及其下的所有符号链接
+    pub fn rename(&self, old: &str, new: &str) {
+        let mut links = self.links.write();
+        *links = core::mem::take(&mut *links)
+            .into_iter()
+            .filter(|(path, _)| path != new)
+            .map(|(path, target)| (replace_prefix(&path, old, new).unwrap_or(path), target))
+            .collect();
+    }
+
+    /// 符号链接被删除后不再记录它
+    pub fn remove(&self, path: &str) {
+        self.links.write().remove(path);
+    }
+}
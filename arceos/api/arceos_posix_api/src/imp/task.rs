@@ -16,6 +16,23 @@ pub fn sys_sched_yield() -> c_int {
     0
 }
 
+/// Signal handling of the kernel built on these APIs.
+#[crate_interface::def_interface]
+pub trait SignalIf {
+    /// Whether a blocking operation of the current task should be interrupted
+    /// by a pending signal.
+    fn interrupted() -> bool;
+}
+
+/// Checks whether a blocking operation should give up with `EINTR` because a
+/// signal is pending. It is never interrupted without the `uspace` feature.
+pub fn interrupted() -> bool {
+    #[cfg(feature = "uspace")]
+    return crate_interface::call_interface!(SignalIf::interrupted);
+    #[cfg(not(feature = "uspace"))]
+    false
+}
+
 /// Get current thread ID.
 pub fn sys_getpid() -> c_int {
     syscall_body!(sys_getpid,
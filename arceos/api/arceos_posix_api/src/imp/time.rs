@@ -1,5 +1,7 @@
 use axerrno::LinuxError;
+use axhal::time::{TimeValue, NANOS_PER_SEC};
 use core::ffi::{c_int, c_long};
+use core::sync::atomic::{AtomicI64, Ordering};
 use core::time::Duration;
 
 use crate::ctypes;
@@ -35,6 +37,39 @@ impl From<Duration> for ctypes::timeval {
     }
 }
 
+/// Nanoseconds added to [`axhal::time::wall_time`] to obtain `CLOCK_REALTIME`.
+///
+/// `clock_settime` only changes this adjustment, so kernel deadlines computed
+/// from [`axhal::time::wall_time`] are not affected by jumps of the user-visible clock.
+static REALTIME_ADJUST_NANOS: AtomicI64 = AtomicI64::new(0);
+
+/// Returns the current `CLOCK_REALTIME`, i.e. the time elapsed since epoch
+/// including the adjustments made by [`sys_clock_settime`].
+pub fn realtime() -> TimeValue {
+    let nanos =
+        axhal::time::wall_time_nanos() as i64 + REALTIME_ADJUST_NANOS.load(Ordering::Relaxed);
+    TimeValue::from_nanos(nanos.max(0) as u64)
+}
+
+/// Lets the filesystems stamp files with `CLOCK_REALTIME`, so that the stamps
+/// follow the clock set by [`sys_clock_settime`].
+#[cfg(feature = "fs")]
+#[ctor_bare::register_ctor]
+fn register_fs_clock() {
+    axfs::register_clock(realtime);
+}
+
+/// Sets `CLOCK_REALTIME` to `now`.
+fn set_realtime(now: TimeValue) {
+    let adjust = now.as_nanos() as i64 - axhal::time::wall_time_nanos() as i64;
+    REALTIME_ADJUST_NANOS.store(adjust, Ordering::Relaxed);
+}
+
+/// Shifts `CLOCK_REALTIME` by `delta_nanos` nanoseconds.
+pub fn adjust_realtime(delta_nanos: i64) {
+    REALTIME_ADJUST_NANOS.fetch_add(delta_nanos, Ordering::Relaxed);
+}
+
 /// Get clock time since booting
 pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
     syscall_body!(sys_clock_gettime, {
@@ -42,7 +77,7 @@ pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespe
             return Err(LinuxError::EFAULT);
         }
         let now = match clk as u32 {
-            CLOCK_REALTIME => axhal::time::wall_time().into(),
+            CLOCK_REALTIME => realtime().into(),
             CLOCK_MONOTONIC => axhal::time::monotonic_time().into(),
             _ => {
                 warn!("Called sys_clock_gettime for unsupported clock {}", clk);
@@ -55,6 +90,31 @@ pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespe
     })
 }
 
+/// Set the time of the specified clock
+///
+/// Only `CLOCK_REALTIME` can be set; the monotonic clock always counts from boot.
+/// Permission checks are left to the caller.
+pub unsafe fn sys_clock_settime(clk: ctypes::clockid_t, ts: *const ctypes::timespec) -> c_int {
+    syscall_body!(sys_clock_settime, {
+        if ts.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let ts = unsafe { *ts };
+        if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec >= NANOS_PER_SEC as c_long {
+            return Err(LinuxError::EINVAL);
+        }
+        match clk as u32 {
+            CLOCK_REALTIME => set_realtime(ts.into()),
+            _ => {
+                warn!("Called sys_clock_settime for unsupported clock {}", clk);
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        debug!("sys_clock_settime: {}.{:09}s", ts.tv_sec, ts.tv_nsec);
+        Ok(0)
+    })
+}
+
 /// Sleep some nanoseconds
 ///
 /// TODO: should be woken by signals, and set errno
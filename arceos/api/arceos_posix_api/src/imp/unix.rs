@@ -0,0 +1,355 @@
+//! Unix domain stream sockets connected in pairs by `socketpair`.
+//!
+//! Each direction of a pair is a [`Channel`] of segments, one for the bytes
+//! of every `send`. Files passed with `SCM_RIGHTS` travel in the segment of
+//! the bytes they were sent with, and a receive never returns bytes of two
+//! segments with files, so the receiver learns which bytes they came with.
+//!
+//! A socket that is passed over its own pair keeps the pair alive until the
+//! files in flight are received, as there is no garbage collection of them.
+
+use alloc::{collections::VecDeque, format, string::String, sync::Arc, vec::Vec};
+use core::ffi::c_int;
+use core::mem;
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+
+use super::fd_ops::{add_file_like, close_file_like, set_cloexec, FileLike, StatusFlags};
+use super::pollable::{PollState, Pollable};
+use super::socket::{
+    SocketOps, SocketOptions, MSG_DONTWAIT, MSG_PEEK, SHUT_RD, SHUT_RDWR, SHUT_WR,
+};
+use crate::ctypes;
+
+/// The bytes sent by one `send`, or the part of them that fitted in the
+/// buffer, with the files passed along with them.
+struct Segment {
+    data: Vec<u8>,
+    /// The number of bytes already received.
+    read: usize,
+    rights: Vec<Arc<dyn FileLike>>,
+}
+
+/// One direction of a connected pair.
+#[derive(Default)]
+struct Channel {
+    segments: VecDeque<Segment>,
+    /// The number of bytes in `segments` not received yet.
+    len: usize,
+    /// The sending end shut down sending or was closed. The receiving end
+    /// reads the end of the stream once the data is received.
+    write_shut: bool,
+    /// The receiving end shut down receiving or was closed. Sending fails
+    /// with `EPIPE`.
+    read_shut: bool,
+}
+
+impl Channel {
+    /// Copies data into `buf`, removing it from the channel unless `peek` is
+    /// set. Returns the number of bytes copied and the files passed with them,
+    /// which are left in the channel on `peek`.
+    fn read(&mut self, buf: &mut [u8], peek: bool) -> (usize, Vec<Arc<dyn FileLike>>) {
+        let mut copied = 0;
+        let mut rights = Vec::new();
+        for segment in self.segments.iter_mut() {
+            let has_rights = !segment.rights.is_empty();
+            if copied == buf.len() || (has_rights && copied > 0) {
+                break;
+            }
+            let data = &segment.data[segment.read..];
+            let n = data.len().min(buf.len() - copied);
+            buf[copied..copied + n].copy_from_slice(&data[..n]);
+            copied += n;
+            if !peek {
+                segment.read += n;
+                if has_rights {
+                    rights = mem::take(&mut segment.rights);
+                }
+            }
+            // The bytes after the ones with files are received by the next call.
+            if has_rights {
+                break;
+            }
+        }
+        if !peek {
+            self.segments
+                .retain(|segment| segment.read < segment.data.len());
+            self.len -= copied;
+        }
+        (copied, rights)
+    }
+}
+
+/// An end of a connected pair of Unix domain stream sockets.
+pub struct UnixSocket {
+    /// The channel to the peer.
+    tx: Arc<Mutex<Channel>>,
+    /// The channel from the peer.
+    rx: Arc<Mutex<Channel>>,
+    flags: StatusFlags,
+    options: SocketOptions,
+}
+
+impl UnixSocket {
+    /// Creates a connected pair of sockets, both with the file status flags in
+    /// `flags`, e.g. `O_NONBLOCK`.
+    pub fn new_pair(flags: u32) -> (UnixSocket, UnixSocket) {
+        let a = Arc::new(Mutex::new(Channel::default()));
+        let b = Arc::new(Mutex::new(Channel::default()));
+        let first = Self {
+            tx: a.clone(),
+            rx: b.clone(),
+            flags: StatusFlags::new(flags | ctypes::O_RDWR),
+            options: SocketOptions::new(),
+        };
+        let second = Self {
+            tx: b,
+            rx: a,
+            flags: StatusFlags::new(flags | ctypes::O_RDWR),
+            options: SocketOptions::new(),
+        };
+        (first, second)
+    }
+
+    fn nonblocking(&self, flags: u32) -> bool {
+        self.flags.nonblocking() || flags & MSG_DONTWAIT != 0
+    }
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        self.tx.lock().write_shut = true;
+        let mut rx = self.rx.lock();
+        rx.read_shut = true;
+        rx.len = 0;
+        let segments = mem::take(&mut rx.segments);
+        drop(rx);
+        // The files in flight may include the peer, whose drop locks `rx`.
+        drop(segments);
+    }
+}
+
+impl SocketOps for UnixSocket {
+    fn socket_type(&self) -> u32 {
+        ctypes::SOCK_STREAM
+    }
+
+    fn options(&self) -> &SocketOptions {
+        &self.options
+    }
+
+    /// Sends the data, blocking while the peer has `SO_SNDBUF` bytes of this
+    /// socket not received yet. The files go with the first bytes sent, and
+    /// are dropped if there are no bytes to send.
+    fn send_msg(
+        &self,
+        buf: &[u8],
+        rights: Vec<Arc<dyn FileLike>>,
+        flags: u32,
+    ) -> LinuxResult<usize> {
+        let mut rights = Some(rights);
+        let mut sent = 0;
+        loop {
+            let mut tx = self.tx.lock();
+            if tx.read_shut || tx.write_shut {
+                return match sent {
+                    0 => Err(LinuxError::EPIPE),
+                    _ => Ok(sent),
+                };
+            }
+            if sent == buf.len() {
+                return Ok(sent);
+            }
+            let space = self.options.sndbuf().saturating_sub(tx.len);
+            if space > 0 {
+                let n = space.min(buf.len() - sent);
+                tx.segments.push_back(Segment {
+                    data: buf[sent..sent + n].to_vec(),
+                    read: 0,
+                    rights: rights.take().unwrap_or_default(),
+                });
+                tx.len += n;
+                sent += n;
+                continue;
+            }
+            let err = if self.nonblocking(flags) {
+                LinuxError::EAGAIN
+            } else if crate::imp::task::interrupted() {
+                LinuxError::EINTR
+            } else {
+                drop(tx);
+                // Buffer is full, wait for the peer to receive
+                crate::sys_sched_yield(); // TODO: use synconize primitive
+                continue;
+            };
+            return match sent {
+                0 => Err(err),
+                _ => Ok(sent),
+            };
+        }
+    }
+
+    /// Receives the data, blocking until some is sent or the stream ends.
+    /// With `MSG_PEEK` the files are left in the socket and not returned.
+    fn recv_msg(&self, buf: &mut [u8], flags: u32) -> LinuxResult<(usize, Vec<Arc<dyn FileLike>>)> {
+        loop {
+            let mut rx = self.rx.lock();
+            if rx.len > 0 {
+                return Ok(rx.read(buf, flags & MSG_PEEK != 0));
+            }
+            if rx.write_shut || rx.read_shut || buf.is_empty() {
+                return Ok((0, Vec::new()));
+            }
+            if self.nonblocking(flags) {
+                return Err(LinuxError::EAGAIN);
+            }
+            if crate::imp::task::interrupted() {
+                return Err(LinuxError::EINTR);
+            }
+            drop(rx);
+            // Data not ready, wait for the peer to send
+            crate::sys_sched_yield(); // TODO: use synconize primitive
+        }
+    }
+
+    /// Shuts down receiving, sending or both. After `SHUT_WR` the peer reads
+    /// the end of the stream once it has received the data already sent, and
+    /// after `SHUT_RD` the peer can no longer send.
+    fn shutdown_socket(&self, how: c_int) -> LinuxResult {
+        let (read, write) = match how {
+            SHUT_RD => (true, false),
+            SHUT_WR => (false, true),
+            SHUT_RDWR => (true, true),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if read {
+            self.rx.lock().read_shut = true;
+        }
+        if write {
+            self.tx.lock().write_shut = true;
+        }
+        Ok(())
+    }
+
+    /// Unix domain sockets have no protocol options, e.g. `TCP_NODELAY`.
+    fn set_protocol_option(&self, _level: c_int, _name: c_int, _value: &[u8]) -> LinuxResult {
+        Err(LinuxError::EOPNOTSUPP)
+    }
+
+    fn get_protocol_option(&self, _level: c_int, _name: c_int) -> LinuxResult<Vec<u8>> {
+        Err(LinuxError::EOPNOTSUPP)
+    }
+}
+
+impl FileLike for UnixSocket {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        // Files passed with the data are dropped, as by `recvmsg` without
+        // room for the control message.
+        Ok(self.recv_msg(buf, 0)?.0)
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.send_msg(buf, Vec::new(), 0)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o140000 | 0o777u32; // S_IFSOCK | rwxrwxrwx
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            st_uid: 1000,
+            st_gid: 1000,
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn link_target(&self) -> String {
+        format!("socket:[{}]", self as *const Self as usize)
+    }
+}
+
+impl Pollable for UnixSocket {
+    /// The socket hangs up once both directions are shut down, e.g. when the
+    /// peer is closed.
+    fn poll(&self) -> PollState {
+        // The channels are locked one at a time, as the peer locks them in
+        // the other order.
+        let (readable, rx_shut) = {
+            let rx = self.rx.lock();
+            let shut = rx.write_shut || rx.read_shut;
+            (rx.len > 0 || shut, shut)
+        };
+        let tx = self.tx.lock();
+        let tx_shut = tx.read_shut || tx.write_shut;
+        PollState {
+            readable,
+            writable: tx_shut || tx.len < self.options.sndbuf(),
+            hangup: rx_shut && tx_shut,
+            error: false,
+        }
+    }
+}
+
+/// Create a pair of connected sockets, with `SOCK_NONBLOCK` and
+/// `SOCK_CLOEXEC` in `socktype` applied to both
+///
+/// Only Unix domain stream sockets are supported.
+///
+/// Return 0 if succeed
+pub fn sys_socketpair(domain: c_int, socktype: c_int, protocol: c_int, fds: &mut [c_int]) -> c_int {
+    debug!(
+        "sys_socketpair <= {} {:#x} {} {:#x}",
+        domain,
+        socktype,
+        protocol,
+        fds.as_ptr() as usize
+    );
+    syscall_body!(sys_socketpair, {
+        if fds.len() != 2 {
+            return Err(LinuxError::EFAULT);
+        }
+        let (domain, socktype) = (domain as u32, socktype as u32);
+        let flags = socktype & (ctypes::SOCK_NONBLOCK | ctypes::SOCK_CLOEXEC);
+        match domain {
+            ctypes::AF_UNIX => {}
+            ctypes::AF_INET | ctypes::AF_INET6 => return Err(LinuxError::EOPNOTSUPP),
+            _ => return Err(LinuxError::EAFNOSUPPORT),
+        }
+        if socktype & !flags != ctypes::SOCK_STREAM {
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+        if protocol != 0 {
+            return Err(LinuxError::EPROTONOSUPPORT);
+        }
+
+        let (first, second) = UnixSocket::new_pair(flags & ctypes::O_NONBLOCK);
+        let first_fd = add_file_like(Arc::new(first))?;
+        let second_fd = add_file_like(Arc::new(second)).inspect_err(|_| {
+            close_file_like(first_fd).ok();
+        })?;
+        if flags & ctypes::SOCK_CLOEXEC != 0 {
+            set_cloexec(first_fd, true);
+            set_cloexec(second_fd, true);
+        }
+
+        fds[0] = first_fd;
+        fds[1] = second_fd;
+
+        Ok(0)
+    })
+}
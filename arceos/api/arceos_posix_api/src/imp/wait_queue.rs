@@ -0,0 +1,232 @@
+//! A wait queue for blocking calls: sleeping until a condition holds, a
+//! deadline passes, or a signal arrives.
+//!
+//! A waiter is queued before it checks the condition for the last time and
+//! goes to sleep, and is woken up by [`WaitQueue::notify_one`] or
+//! [`WaitQueue::notify_all`], so a notification after the condition becomes
+//! true is never missed. Each task sleeps on its own, as the kernel provides
+//! through [`WaitIf`]: there it is also woken up by signals, so an
+//! interruptible wait can return [`WaitResult::Interrupted`].
+//!
+//! # Lock ordering
+//!
+//! - The condition is checked with no lock of the queue held. It may take any
+//!   lock, including sleeping ones, but not one held by the notifier while it
+//!   calls `notify_*`, unless the notifier is careful to drop it before.
+//! - A notifier updates the state behind the condition first, then calls
+//!   `notify_*`, with or without its own locks held. Notifying only takes the
+//!   spin lock of the queue and those the kernel uses to wake a task up, all
+//!   with IRQs disabled, so it never sleeps and may be done in IRQ context.
+//! - A waiter must not hold a spin lock when it waits, nor a sleeping lock
+//!   that the notifier needs before it can notify.
+
+use alloc::{collections::VecDeque, sync::Arc};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axsync::spin::SpinNoIrq;
+use axtask::{current, AxTaskRef};
+
+/// Why a wait has finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitResult {
+    /// The condition holds.
+    Ok,
+    /// The deadline has passed before the condition holds.
+    TimedOut,
+    /// A signal should be handled before the condition holds.
+    Interrupted,
+}
+
+/// Sleeping and waking of the tasks of the kernel built on these APIs.
+#[crate_interface::def_interface]
+pub trait WaitIf {
+    /// Blocks the current task until `woken` returns true, or `timeout`
+    /// elapses if it is not `None`. If `interruptible`, the task also wakes up
+    /// when a signal should interrupt it.
+    ///
+    /// `woken` must be checked in a way that a [`WaitIf::wake`] after it
+    /// becomes true is not missed.
+    fn wait(woken: &dyn Fn() -> bool, timeout: Option<Duration>, interruptible: bool)
+        -> WaitResult;
+
+    /// Wakes up `task` from [`WaitIf::wait`] to check its condition again.
+    fn wake(task: &AxTaskRef);
+}
+
+/// Where tasks sleep without the `uspace` feature, when there are no signals.
+/// All of them are woken up to check their conditions.
+#[cfg(not(feature = "uspace"))]
+static PARKED: axtask::WaitQueue = axtask::WaitQueue::new();
+
+fn park(woken: &dyn Fn() -> bool, timeout: Option<Duration>, interruptible: bool) -> WaitResult {
+    #[cfg(feature = "uspace")]
+    return crate_interface::call_interface!(WaitIf::wait, woken, timeout, interruptible);
+    #[cfg(not(feature = "uspace"))]
+    {
+        let _ = interruptible;
+        match timeout {
+            #[cfg(feature = "irq")]
+            Some(dur) if PARKED.wait_timeout_until(dur, woken) => WaitResult::TimedOut,
+            _ => {
+                PARKED.wait_until(woken);
+                WaitResult::Ok
+            }
+        }
+    }
+}
+
+fn unpark(task: &AxTaskRef) {
+    #[cfg(feature = "uspace")]
+    crate_interface::call_interface!(WaitIf::wake, task);
+    #[cfg(not(feature = "uspace"))]
+    {
+        let _ = task;
+        PARKED.notify_all(false);
+    }
+}
+
+/// A task waiting in a [`WaitQueue`].
+struct Waiter {
+    task: AxTaskRef,
+    /// Set when the waiter is removed from the queue to be woken up.
+    woken: AtomicBool,
+}
+
+/// A queue of tasks waiting for a condition. See the [module-level
+/// documentation](self) for how it is used.
+pub struct WaitQueue {
+    waiters: SpinNoIrq<VecDeque<Arc<Waiter>>>,
+}
+
+impl WaitQueue {
+    /// Creates an empty wait queue.
+    pub const fn new() -> Self {
+        Self {
+            waiters: SpinNoIrq::new(VecDeque::new()),
+        }
+    }
+
+    /// Blocks the current task until `condition` returns true. Signals do not
+    /// wake it up.
+    pub fn wait_until<F: FnMut() -> bool>(&self, condition: F) {
+        self.wait(condition, None, false);
+    }
+
+    /// Blocks the current task until `condition` returns true, or `deadline`
+    /// in monotonic time passes. Signals do not wake it up.
+    #[cfg(feature = "irq")]
+    pub fn wait_timeout<F: FnMut() -> bool>(&self, condition: F, deadline: Duration) -> WaitResult {
+        self.wait(condition, Some(deadline), false)
+    }
+
+    /// Blocks the current task until `condition` returns true, or a signal
+    /// should interrupt it, when the caller should fail with `EINTR`.
+    pub fn wait_interruptible<F: FnMut() -> bool>(&self, condition: F) -> WaitResult {
+        self.wait(condition, None, true)
+    }
+
+    /// Blocks the current task until `condition` returns true, `deadline` in
+    /// monotonic time passes, or a signal should interrupt it.
+    #[cfg(feature = "irq")]
+    pub fn wait_interruptible_timeout<F: FnMut() -> bool>(
+        &self,
+        condition: F,
+        deadline: Duration,
+    ) -> WaitResult {
+        self.wait(condition, Some(deadline), true)
+    }
+
+    /// Wakes up the task that has waited the longest. Returns whether there
+    /// was one.
+    pub fn notify_one(&self) -> bool {
+        let waiter = self.waiters.lock().pop_front();
+        match waiter {
+            Some(waiter) => {
+                waiter.woken.store(true, Ordering::Release);
+                unpark(&waiter.task);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wakes up all the waiting tasks.
+    pub fn notify_all(&self) {
+        let waiters = core::mem::take(&mut *self.waiters.lock());
+        for waiter in waiters {
+            waiter.woken.store(true, Ordering::Release);
+            unpark(&waiter.task);
+        }
+    }
+
+    fn wait<F: FnMut() -> bool>(
+        &self,
+        mut condition: F,
+        deadline: Option<Duration>,
+        interruptible: bool,
+    ) -> WaitResult {
+        loop {
+            if condition() {
+                return WaitResult::Ok;
+            }
+            let waiter = Arc::new(Waiter {
+                task: current().as_task_ref().clone(),
+                woken: AtomicBool::new(false),
+            });
+            self.waiters.lock().push_back(waiter.clone());
+            // Once queued, a change of the condition is followed by a wakeup.
+            if condition() {
+                self.remove(&waiter);
+                return WaitResult::Ok;
+            }
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = axhal::time::monotonic_time();
+                    if now >= deadline {
+                        self.leave(&waiter);
+                        return WaitResult::TimedOut;
+                    }
+                    Some(deadline - now)
+                }
+                None => None,
+            };
+            let woken = || waiter.woken.load(Ordering::Acquire);
+            match park(&woken, timeout, interruptible) {
+                WaitResult::Ok => {}
+                WaitResult::TimedOut => self.leave(&waiter),
+                WaitResult::Interrupted => {
+                    self.leave(&waiter);
+                    return WaitResult::Interrupted;
+                }
+            }
+        }
+    }
+
+    /// Removes `waiter` from the queue if it is still there, returning whether
+    /// it was.
+    fn remove(&self, waiter: &Arc<Waiter>) -> bool {
+        let mut waiters = self.waiters.lock();
+        match waiters.iter().position(|w| Arc::ptr_eq(w, waiter)) {
+            Some(index) => {
+                waiters.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes `waiter` that gives up waiting. If it has been woken up by
+    /// [`WaitQueue::notify_one`], the wakeup is passed on to the next waiter.
+    fn leave(&self, waiter: &Arc<Waiter>) {
+        if !self.remove(waiter) && waiter.woken.load(Ordering::Acquire) {
+            self.notify_one();
+        }
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -32,32 +32,50 @@ pub mod config {
 #[allow(dead_code, non_snake_case, non_camel_case_types, non_upper_case_globals, clippy::upper_case_acronyms, missing_docs)]
 pub mod ctypes;
 
-pub use imp::io::{sys_read, sys_write, sys_writev};
+pub use imp::io::{sys_read, sys_readv, sys_write, sys_writev};
 pub use imp::resources::{sys_getrlimit, sys_setrlimit};
 pub use imp::sys::sys_sysconf;
-pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
-pub use imp::time::{sys_clock_gettime, sys_nanosleep};
-pub use imp::path_link::{HARDLINK_MANAGER, FilePath, handle_file_path, AT_FDCWD};
+pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield, SignalIf};
+pub use imp::time::{adjust_realtime, realtime, sys_clock_gettime, sys_clock_settime, sys_nanosleep};
+pub use imp::path_link::{HARDLINK_MANAGER, FilePath, handle_file_path, handle_link_path, handle_writable_path, handle_writable_link_path, resolve_symlinks, base_dir, AT_FDCWD};
+pub use imp::file_modes::FILE_MODES;
+pub use imp::file_times::{FileTimes, FILE_TIMES};
+pub use imp::special_file::{SpecialFile, SPECIAL_FILES, S_IFBLK, S_IFCHR, S_IFIFO, S_IFMT};
+pub use imp::symlink::{SYMLINKS, S_IFLNK};
 
 #[cfg(feature = "fd")]
-pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl, FD_TABLE, get_file_like, add_file_like};
+pub use imp::fd_ops::{sys_close, sys_close_range, sys_dup, sys_dup2, sys_fcntl, FD_TABLE, FD_CLOEXEC, FD_LIMIT, FILE_MAX, AX_FILE_LIMIT, FileLike, FdEntry, get_file_like, access_file_like, add_file_like, set_cloexec, close_on_exec, close_all, fd_limit, StatusFlags};
+#[cfg(feature = "fd")]
+pub use imp::pollable::{PollState, PollWaker, PollWakers, Pollable};
+#[cfg(feature = "fd")]
+pub use cap_access::Cap;
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat, sys_openat, Directory, File};
+pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat, sys_openat, sys_sync, stat_path, add_open_file, is_unnamed, open_path, Directory, File};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
+#[cfg(feature = "poll")]
+pub use imp::io_mpx::{poll_fds, sys_poll};
 #[cfg(feature = "epoll")]
 pub use imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
-    sys_getsockname, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto, sys_shutdown,
-    sys_socket,
+    sys_getsockname, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto, sys_socket,
 };
 #[cfg(feature = "pipe")]
-pub use imp::pipe::sys_pipe;
+pub use imp::pipe::{sys_pipe, sys_pipe2};
+#[cfg(feature = "fd")]
+pub use imp::socket::{
+    getsockopt, setsockopt, socket_from_fd, sys_shutdown, SocketOps, MSG_CMSG_CLOEXEC, MSG_CTRUNC,
+    MSG_NOSIGNAL, SCM_RIGHTS, SOL_SOCKET,
+};
+#[cfg(feature = "fd")]
+pub use imp::unix::{sys_socketpair, UnixSocket};
 #[cfg(feature = "multitask")]
 pub use imp::pthread::mutex::{
     sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_unlock,
 };
 #[cfg(feature = "multitask")]
 pub use imp::pthread::{sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_self};
+#[cfg(feature = "multitask")]
+pub use imp::wait_queue::{WaitIf, WaitQueue, WaitResult};
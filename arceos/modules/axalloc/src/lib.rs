@@ -16,6 +16,7 @@ mod page;
 use allocator::{AllocResult, BaseAllocator, BitmapPageAllocator, ByteAllocator, PageAllocator};
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use kspin::SpinNoIrq;
 
 const PAGE_SIZE: usize = 0x1000;
@@ -50,6 +51,10 @@ cfg_if::cfg_if! {
 pub struct GlobalAllocator {
     balloc: SpinNoIrq<DefaultByteAllocator>,
     palloc: SpinNoIrq<BitmapPageAllocator<PAGE_SIZE>>,
+    /// The largest number of pages allocated at the same time.
+    peak_pages: AtomicUsize,
+    /// The number of free pages kept for the kernel itself.
+    min_free_pages: AtomicUsize,
 }
 
 impl GlobalAllocator {
@@ -58,6 +63,8 @@ impl GlobalAllocator {
         Self {
             balloc: SpinNoIrq::new(DefaultByteAllocator::new()),
             palloc: SpinNoIrq::new(BitmapPageAllocator::new()),
+            peak_pages: AtomicUsize::new(0),
+            min_free_pages: AtomicUsize::new(0),
         }
     }
 
@@ -101,7 +108,8 @@ impl GlobalAllocator {
     ///
     /// It firstly tries to allocate from the byte allocator. If there is no
     /// memory, it asks the page allocator for more memory and adds it to the
-    /// byte allocator.
+    /// byte allocator, doubling the heap, or growing it by only what the
+    /// allocation needs if there are not enough free pages to double it.
     pub fn alloc(&self, layout: Layout) -> AllocResult<NonNull<u8>> {
         // simple two-level allocator: if no heap memory, allocate from the page allocator.
         let mut balloc = self.balloc.lock();
@@ -110,11 +118,20 @@ impl GlobalAllocator {
                 return Ok(ptr);
             } else {
                 let old_size = balloc.total_bytes();
-                let expand_size = old_size
+                let mut expand_size = old_size
                     .max(layout.size())
                     .next_power_of_two()
                     .max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+                let heap_ptr = match self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE) {
+                    Ok(ptr) => ptr,
+                    Err(_) => {
+                        // Low on memory: expand by just enough for this allocation.
+                        expand_size = (layout.size() + layout.align())
+                            .next_power_of_two()
+                            .max(PAGE_SIZE);
+                        self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?
+                    }
+                };
                 debug!(
                     "expand heap memory: [{:#x}, {:#x})",
                     heap_ptr,
@@ -143,7 +160,11 @@ impl GlobalAllocator {
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     /// aligned to it.
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        self.palloc.lock().alloc_pages(num_pages, align_pow2)
+        let mut palloc = self.palloc.lock();
+        let pos = palloc.alloc_pages(num_pages, align_pow2)?;
+        self.peak_pages
+            .fetch_max(palloc.used_pages(), Ordering::Relaxed);
+        Ok(pos)
     }
 
     /// Gives back the allocated pages starts from `pos` to the page allocator.
@@ -176,6 +197,34 @@ impl GlobalAllocator {
     pub fn available_pages(&self) -> usize {
         self.palloc.lock().available_pages()
     }
+
+    /// Returns the number of pages managed by the page allocator.
+    pub fn total_pages(&self) -> usize {
+        self.palloc.lock().total_pages()
+    }
+
+    /// Returns the largest number of pages allocated at the same time.
+    pub fn peak_used_pages(&self) -> usize {
+        self.peak_pages.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of free pages kept for the kernel itself.
+    pub fn min_free_pages(&self) -> usize {
+        self.min_free_pages.load(Ordering::Relaxed)
+    }
+
+    /// Sets the number of free pages kept for the kernel itself. Allocations
+    /// on behalf of user programs should fail before they use these pages, so
+    /// that the kernel does not run out of memory.
+    pub fn set_min_free_pages(&self, num_pages: usize) {
+        self.min_free_pages.store(num_pages, Ordering::Relaxed);
+    }
+
+    /// Returns the number of available pages beyond those kept for the
+    /// kernel, which user programs may use.
+    pub fn pages_above_watermark(&self) -> usize {
+        self.available_pages().saturating_sub(self.min_free_pages())
+    }
 }
 
 unsafe impl GlobalAlloc for GlobalAllocator {
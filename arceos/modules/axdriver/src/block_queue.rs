@@ -0,0 +1,64 @@
+//! Queued requests of block storage devices.
+//!
+//! [`BlockQueueOps`] extends [`BlockDriverOps`] with a submit and poll model: a
+//! request is submitted together with the buffer it reads into or writes from,
+//! and the device holds the buffer until the request completes, so several
+//! requests can be outstanding at once. Devices that finish every request
+//! before returning keep the default methods, which report
+//! [`DevError::Unsupported`], and are only used through [`BlockDriverOps`].
+
+use alloc::vec::Vec;
+
+use axdriver_base::{DevError, DevResult};
+use axdriver_block::BlockDriverOps;
+
+/// Identifies a submitted request until its completion is polled.
+pub type RequestId = u64;
+
+/// Operations of block storage devices that can queue requests.
+pub trait BlockQueueOps: BlockDriverOps {
+    /// The maximum number of outstanding requests, or 0 if the device cannot
+    /// queue requests.
+    fn queue_depth(&self) -> usize {
+        0
+    }
+
+    /// Submits a request to read `buf.len()` bytes from `block_id` into `buf`.
+    ///
+    /// Returns [`DevError::Again`] if the queue is full.
+    fn submit_read(&mut self, _block_id: u64, _buf: Vec<u8>) -> DevResult<RequestId> {
+        Err(DevError::Unsupported)
+    }
+
+    /// Submits a request to write `buf` to the blocks from `block_id`.
+    ///
+    /// Returns [`DevError::Again`] if the queue is full.
+    fn submit_write(&mut self, _block_id: u64, _buf: Vec<u8>) -> DevResult<RequestId> {
+        Err(DevError::Unsupported)
+    }
+
+    /// Returns the buffer of request `id` once it completed, or `None` while it
+    /// is still in flight.
+    ///
+    /// A completed request is forgotten after it is polled, so polling it again
+    /// returns [`DevError::InvalidParam`], as does an unknown `id`.
+    fn poll(&mut self, _id: RequestId) -> DevResult<Option<Vec<u8>>> {
+        Err(DevError::Unsupported)
+    }
+
+    /// Waits for request `id` to complete and returns its buffer.
+    fn wait(&mut self, id: RequestId) -> DevResult<Vec<u8>> {
+        loop {
+            if let Some(buf) = self.poll(id)? {
+                return Ok(buf);
+            }
+            core::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(feature = "ramdisk")]
+impl BlockQueueOps for axdriver_block::ramdisk::RamDisk {}
+
+#[cfg(feature = "bcm2835-sdhci")]
+impl BlockQueueOps for axdriver_block::bcm2835sdhci::SDHCIDriver {}
@@ -4,8 +4,10 @@ use crate::{prelude::*, AllDevices};
 impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
         // TODO: parse device tree
+        // QEMU assigns the virtio-mmio slots from the highest address down, so
+        // probe in reverse to find the devices in command-line order.
         #[cfg(feature = "virtio")]
-        for reg in axconfig::VIRTIO_MMIO_REGIONS {
+        for reg in axconfig::VIRTIO_MMIO_REGIONS.iter().rev() {
             for_each_drivers!(type Driver, {
                 if let Some(dev) = Driver::probe_mmio(reg.0, reg.1) {
                     info!(
@@ -0,0 +1,135 @@
+//! Character devices and the registry through which they are exposed as
+//! device files.
+//!
+//! A character device driver implements [`CharDriverOps`] and registers
+//! itself with [`register_char_device`] when it is probed, under the name of
+//! its device file and a major and minor number. The filesystem enumerates
+//! the registry with [`char_devices`] to create the nodes in `/dev`, and
+//! dispatches the reads, writes and `ioctl`s of an opened node to the driver.
+//!
+//! The drivers built in are registered by [`init_drivers`](crate::init_drivers):
+//!
+//! | Device file | Major | Minor | Driver |
+//! |-|-|-|-|
+//! | `ttyS0` | 4 | 64 | [`ConsoleUart`], the UART of the console |
+//! | `hwrng` | 10 | 183 | [`TimerRng`], random bytes from the timer |
+
+mod rng;
+mod uart;
+
+use alloc::{sync::Arc, vec::Vec};
+
+use axdriver_base::{BaseDriverOps, DevError, DevResult, DeviceType};
+use kspin::SpinNoIrq;
+
+pub use self::rng::TimerRng;
+pub use self::uart::ConsoleUart;
+
+/// Operations of character devices, which are read and written as streams of
+/// bytes without positions.
+pub trait CharDriverOps: BaseDriverOps {
+    /// Reads the bytes available into `buf`, returning how many were read.
+    fn read(&self, buf: &mut [u8]) -> DevResult<usize>;
+
+    /// Writes the bytes of `buf`, returning how many were written.
+    fn write(&self, buf: &[u8]) -> DevResult<usize>;
+
+    /// Performs the device-specific request `cmd` with the argument `arg`,
+    /// returning a non-negative result.
+    ///
+    /// Devices without requests keep the default, which reports
+    /// [`DevError::Unsupported`].
+    fn ioctl(&self, _cmd: u32, _arg: usize) -> DevResult<usize> {
+        Err(DevError::Unsupported)
+    }
+}
+
+/// A registered character device.
+#[derive(Clone)]
+pub struct CharDevice {
+    /// The major number of the device.
+    pub major: u32,
+    /// The minor number of the device.
+    pub minor: u32,
+    /// The driver of the device.
+    pub driver: Arc<dyn CharDriverOps>,
+}
+
+impl CharDevice {
+    /// The name of the device file, which is the name of the device.
+    pub fn name(&self) -> &str {
+        self.driver.device_name()
+    }
+}
+
+static CHAR_DEVICES: SpinNoIrq<Vec<CharDevice>> = SpinNoIrq::new(Vec::new());
+
+/// Registers `driver` as the character device `major:minor`.
+///
+/// Returns [`DevError::InvalidParam`] if the driver is not of a character
+/// device, and [`DevError::AlreadyExists`] if the numbers or the name are
+/// taken.
+pub fn register_char_device(major: u32, minor: u32, driver: Arc<dyn CharDriverOps>) -> DevResult {
+    if driver.device_type() != DeviceType::Char {
+        return Err(DevError::InvalidParam);
+    }
+    let mut devices = CHAR_DEVICES.lock();
+    if devices
+        .iter()
+        .any(|dev| (dev.major, dev.minor) == (major, minor) || dev.name() == driver.device_name())
+    {
+        return Err(DevError::AlreadyExists);
+    }
+    info!(
+        "registered character device {}:{} {:?}",
+        major,
+        minor,
+        driver.device_name()
+    );
+    devices.push(CharDevice {
+        major,
+        minor,
+        driver,
+    });
+    Ok(())
+}
+
+/// Returns all the registered character devices, in the order they were
+/// registered.
+pub fn char_devices() -> Vec<CharDevice> {
+    CHAR_DEVICES.lock().clone()
+}
+
+/// Returns the character device `major:minor` if it is registered.
+pub fn find_char_device(major: u32, minor: u32) -> Option<CharDevice> {
+    CHAR_DEVICES
+        .lock()
+        .iter()
+        .find(|dev| (dev.major, dev.minor) == (major, minor))
+        .cloned()
+}
+
+/// Returns the character device whose device file is named `name`.
+pub fn find_char_device_by_name(name: &str) -> Option<CharDevice> {
+    CHAR_DEVICES
+        .lock()
+        .iter()
+        .find(|dev| dev.name() == name)
+        .cloned()
+}
+
+/// Registers the character devices built in.
+pub(crate) fn probe() {
+    let devices: [(u32, u32, Arc<dyn CharDriverOps>); 2] = [
+        (4, 64, Arc::new(ConsoleUart)),
+        (10, 183, Arc::new(TimerRng::new())),
+    ];
+    for (major, minor, driver) in devices {
+        if let Err(err) = register_char_device(major, minor, driver) {
+            warn!(
+                "failed to register character device {}:{}: {:?}",
+                major, minor, err
+            );
+        }
+    }
+}
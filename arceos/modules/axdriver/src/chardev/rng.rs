@@ -0,0 +1,83 @@
+//! A random number generator as a character device.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axdriver_base::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+use super::CharDriverOps;
+
+/// A random number generator fed by the timer.
+///
+/// None of the supported platforms provides a hardware generator to the
+/// kernel, so the state of a xorshift generator is stirred with the timer
+/// counter at each read. The output is unpredictable enough for seeding
+/// userspace generators, but it is not suitable for cryptography.
+pub struct TimerRng {
+    state: AtomicU64,
+}
+
+impl TimerRng {
+    /// Creates a generator seeded with the current time.
+    pub fn new() -> Self {
+        Self {
+            state: AtomicU64::new(mix(axhal::time::current_ticks() | 1)),
+        }
+    }
+
+    fn next(&self) -> u64 {
+        let ticks = axhal::time::current_ticks();
+        let mut next = 0;
+        let _ = self
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |state| {
+                let mut x = state ^ mix(ticks);
+                if x == 0 {
+                    x = 1;
+                }
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                next = x;
+                Some(x)
+            });
+        mix(next)
+    }
+}
+
+impl Default for TimerRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The finalizer of SplitMix64, which spreads every bit of `x` over all the
+/// bits of the result.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+impl BaseDriverOps for TimerRng {
+    fn device_name(&self) -> &str {
+        "hwrng"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
+
+impl CharDriverOps for TimerRng {
+    fn read(&self, buf: &mut [u8]) -> DevResult<usize> {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next().to_ne_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        Ok(buf.len())
+    }
+
+    fn write(&self, _buf: &[u8]) -> DevResult<usize> {
+        Err(DevError::Unsupported)
+    }
+}
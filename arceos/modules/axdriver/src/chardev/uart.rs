@@ -0,0 +1,44 @@
+//! The UART of the console as a character device.
+
+use axdriver_base::{BaseDriverOps, DevResult, DeviceType};
+
+use super::CharDriverOps;
+
+/// The UART that the console of the platform writes to and reads from, which
+/// is also used by standard input and output.
+///
+/// Like a terminal in non-canonical mode with `VMIN` and `VTIME` both 0, a
+/// read does not wait for input: it returns the bytes received so far, which
+/// may be none.
+pub struct ConsoleUart;
+
+impl BaseDriverOps for ConsoleUart {
+    fn device_name(&self) -> &str {
+        "ttyS0"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
+
+impl CharDriverOps for ConsoleUart {
+    fn read(&self, buf: &mut [u8]) -> DevResult<usize> {
+        let mut read_len = 0;
+        while read_len < buf.len() {
+            match axhal::console::getchar() {
+                Some(c) => {
+                    buf[read_len] = c;
+                    read_len += 1;
+                }
+                None => break,
+            }
+        }
+        Ok(read_len)
+    }
+
+    fn write(&self, buf: &[u8]) -> DevResult<usize> {
+        axhal::console::write_bytes(buf);
+        Ok(buf.len())
+    }
+}
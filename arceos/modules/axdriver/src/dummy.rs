@@ -66,6 +66,8 @@ cfg_if! {
                 Err(DevError::Unsupported)
             }
         }
+
+        impl BlockQueueOps for DummyBlockDev {}
     }
 }
 
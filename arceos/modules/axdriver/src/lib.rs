@@ -34,6 +34,7 @@
 //! | Block | `virtio-blk` | VirtIO block device |
 //! | Network | `virtio-net` | VirtIO network device |
 //! | Display | `virtio-gpu` | VirtIO graphics device |
+//! | Char | `char` | The console UART and a random number generator |
 //!
 //! # Other Cargo Features
 //!
@@ -48,6 +49,8 @@
 //!    features, a dummy struct is used for [`AxNetDevice`].
 //! - `block`: use block storage devices. Similar to the `net` feature.
 //! - `display`: use graphics display devices. Similar to the `net` feature.
+//! - `char`: register the character devices in the registry of [`chardev`],
+//!    from which the filesystem creates their device files.
 //!
 //! [`VirtioNetDev`]: axdriver_virtio::VirtIoNetDev
 //! [`Box<dyn NetDriverOps>`]: axdriver_net::NetDriverOps
@@ -61,13 +64,17 @@
 #[macro_use]
 extern crate log;
 
-#[cfg(feature = "dyn")]
+#[cfg(any(feature = "dyn", feature = "block", feature = "char"))]
 extern crate alloc;
 
 #[macro_use]
 mod macros;
 
+#[cfg(feature = "block")]
+mod block_queue;
 mod bus;
+#[cfg(feature = "char")]
+pub mod chardev;
 mod drivers;
 mod dummy;
 mod structs;
@@ -75,6 +82,9 @@ mod structs;
 #[cfg(feature = "virtio")]
 mod virtio;
 
+#[cfg(block_dev = "virtio-blk")]
+mod virtio_blk;
+
 #[cfg(feature = "ixgbe")]
 mod ixgbe;
 
@@ -131,6 +141,9 @@ impl AllDevices {
         });
 
         self.probe_bus_devices();
+
+        #[cfg(feature = "char")]
+        chardev::probe();
     }
 
     /// Adds one device into the corresponding container, according to its device category.
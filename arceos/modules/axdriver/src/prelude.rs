@@ -2,8 +2,14 @@
 
 pub use axdriver_base::{BaseDriverOps, DevError, DevResult, DeviceType};
 
+#[cfg(feature = "char")]
+pub use crate::chardev::CharDriverOps;
 #[cfg(feature = "block")]
-pub use {crate::structs::AxBlockDevice, axdriver_block::BlockDriverOps};
+pub use {
+    crate::block_queue::{BlockQueueOps, RequestId},
+    crate::structs::{block_queue, AxBlockDevice},
+    axdriver_block::BlockDriverOps,
+};
 #[cfg(feature = "display")]
 pub use {crate::structs::AxDisplayDevice, axdriver_display::DisplayDriverOps};
 #[cfg(feature = "net")]
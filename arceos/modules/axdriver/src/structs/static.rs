@@ -1,4 +1,6 @@
 #[cfg(feature = "block")]
+use crate::block_queue::BlockQueueOps;
+#[cfg(feature = "block")]
 pub use crate::drivers::AxBlockDevice;
 #[cfg(feature = "display")]
 pub use crate::drivers::AxDisplayDevice;
@@ -25,6 +27,12 @@ impl super::AxDeviceEnum {
     }
 }
 
+/// Returns the queue operations of a block device.
+#[cfg(feature = "block")]
+pub fn block_queue(dev: &mut AxBlockDevice) -> &mut dyn BlockQueueOps {
+    dev
+}
+
 /// A structure that contains all device drivers of a certain category.
 ///
 /// If the feature `dyn` is enabled, the inner type is [`Vec<D>`]. Otherwise,
@@ -0,0 +1,219 @@
+//! VirtIO block devices with queued requests.
+//!
+//! Requests are added to the virtqueue without waiting, so several of them can
+//! be outstanding, and finished requests are taken from the used ring when a
+//! request is polled. The synchronous [`BlockDriverOps`] methods submit a
+//! request and wait for it.
+//!
+//! Completions are collected by polling, which also acknowledges the interrupt
+//! of the device: the platforms do not route device interrupts to drivers yet.
+
+use alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+
+use axdriver_base::{BaseDriverOps, DevError, DevResult, DeviceType};
+use axdriver_block::BlockDriverOps;
+use virtio_drivers::device::blk::{BlkReq, BlkResp, VirtIOBlk, SECTOR_SIZE};
+use virtio_drivers::{transport::Transport, Hal};
+
+use crate::block_queue::{BlockQueueOps, RequestId};
+
+/// The number of descriptors used by a request without indirect descriptors:
+/// the request header, the data and the response status.
+const DESCS_PER_REQUEST: usize = 3;
+
+/// A request on the virtqueue. The boxed header and status and the buffer stay
+/// in place until the device is done with them.
+struct Pending {
+    id: RequestId,
+    write: bool,
+    req: Box<BlkReq>,
+    resp: Box<BlkResp>,
+    buf: Vec<u8>,
+}
+
+/// The VirtIO block device driver.
+pub struct VirtIoBlkDev<H: Hal, T: Transport> {
+    inner: VirtIOBlk<H, T>,
+    /// Requests on the virtqueue, keyed by their token.
+    pending: BTreeMap<u16, Pending>,
+    /// Completed requests that have not been polled.
+    done: BTreeMap<RequestId, DevResult<Vec<u8>>>,
+    next_id: RequestId,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoBlkDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoBlkDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIoBlkDev<H, T> {
+    /// Creates a new driver instance and initializes the device, or returns
+    /// an error if any step fails.
+    pub fn try_new(transport: T) -> DevResult<Self> {
+        Ok(Self {
+            inner: VirtIOBlk::new(transport).map_err(as_dev_err)?,
+            pending: BTreeMap::new(),
+            done: BTreeMap::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Adds a request to the virtqueue, or gives the buffer back on failure.
+    fn submit(
+        &mut self,
+        block_id: u64,
+        buf: Vec<u8>,
+        write: bool,
+    ) -> Result<RequestId, (DevError, Vec<u8>)> {
+        if buf.is_empty() || buf.len() % SECTOR_SIZE != 0 {
+            return Err((DevError::InvalidParam, buf));
+        }
+        let id = self.next_id;
+        let mut pending = Pending {
+            id,
+            write,
+            req: Box::default(),
+            resp: Box::default(),
+            buf,
+        };
+        // SAFETY: the header, status and buffer are heap allocations owned by
+        // `pending`, which is kept until the request is completed.
+        let res = unsafe {
+            if write {
+                self.inner.write_blocks_nb(
+                    block_id as _,
+                    &mut pending.req,
+                    &pending.buf,
+                    &mut pending.resp,
+                )
+            } else {
+                self.inner.read_blocks_nb(
+                    block_id as _,
+                    &mut pending.req,
+                    &mut pending.buf,
+                    &mut pending.resp,
+                )
+            }
+        };
+        match res {
+            Ok(token) => {
+                self.next_id += 1;
+                self.pending.insert(token, pending);
+                Ok(id)
+            }
+            Err(e) => Err((as_dev_err(e), pending.buf)),
+        }
+    }
+
+    /// Moves the requests the device has finished from the used ring to `done`.
+    fn collect(&mut self) {
+        self.inner.ack_interrupt();
+        while let Some(token) = self.inner.peek_used() {
+            let Some(mut p) = self.pending.remove(&token) else {
+                warn!("virtio-blk: completion of an unknown request {}", token);
+                break;
+            };
+            // SAFETY: the same buffers are passed as when the request was added.
+            let res = unsafe {
+                if p.write {
+                    self.inner
+                        .complete_write_blocks(token, &p.req, &p.buf, &mut p.resp)
+                } else {
+                    self.inner
+                        .complete_read_blocks(token, &p.req, &mut p.buf, &mut p.resp)
+                }
+            };
+            self.done
+                .insert(p.id, res.map(|_| p.buf).map_err(as_dev_err));
+        }
+    }
+
+    /// Submits a request and waits for it, first waiting for a free slot if
+    /// the queue is full.
+    fn submit_wait(&mut self, block_id: u64, mut buf: Vec<u8>, write: bool) -> DevResult<Vec<u8>> {
+        let id = loop {
+            match self.submit(block_id, buf, write) {
+                Ok(id) => break id,
+                Err((DevError::Again, back)) => {
+                    buf = back;
+                    self.collect();
+                    core::hint::spin_loop();
+                }
+                Err((e, _)) => return Err(e),
+            }
+        };
+        self.wait(id)
+    }
+}
+
+impl<H: Hal, T: Transport> BaseDriverOps for VirtIoBlkDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-blk"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+}
+
+impl<H: Hal, T: Transport> BlockDriverOps for VirtIoBlkDev<H, T> {
+    #[inline]
+    fn num_blocks(&self) -> u64 {
+        self.inner.capacity()
+    }
+
+    #[inline]
+    fn block_size(&self) -> usize {
+        SECTOR_SIZE
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        let data = self.submit_wait(block_id, vec![0; buf.len()], false)?;
+        buf.copy_from_slice(&data);
+        Ok(())
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        self.submit_wait(block_id, buf.to_vec(), true).map(|_| ())
+    }
+
+    fn flush(&mut self) -> DevResult {
+        Ok(())
+    }
+}
+
+impl<H: Hal, T: Transport> BlockQueueOps for VirtIoBlkDev<H, T> {
+    fn queue_depth(&self) -> usize {
+        self.inner.virt_queue_size() as usize / DESCS_PER_REQUEST
+    }
+
+    fn submit_read(&mut self, block_id: u64, buf: Vec<u8>) -> DevResult<RequestId> {
+        self.submit(block_id, buf, false).map_err(|(e, _)| e)
+    }
+
+    fn submit_write(&mut self, block_id: u64, buf: Vec<u8>) -> DevResult<RequestId> {
+        self.submit(block_id, buf, true).map_err(|(e, _)| e)
+    }
+
+    fn poll(&mut self, id: RequestId) -> DevResult<Option<Vec<u8>>> {
+        self.collect();
+        match self.done.remove(&id) {
+            Some(res) => res.map(Some),
+            None if self.pending.values().any(|p| p.id == id) => Ok(None),
+            None => Err(DevError::InvalidParam),
+        }
+    }
+}
+
+const fn as_dev_err(e: virtio_drivers::Error) -> DevError {
+    use virtio_drivers::Error::*;
+    match e {
+        QueueFull => DevError::Again,
+        NotReady => DevError::Again,
+        WrongToken => DevError::BadState,
+        AlreadyUsed => DevError::AlreadyExists,
+        InvalidParam => DevError::InvalidParam,
+        DmaError => DevError::NoMemory,
+        IoError => DevError::Io,
+        Unsupported => DevError::Unsupported,
+        _ => DevError::BadState,
+    }
+}
@@ -30,6 +30,11 @@ pub fn set_current_dir(path: &str) -> io::Result<()> {
     crate::root::set_current_dir(path)
 }
 
+/// Changes the root directory of the current process to the specified path.
+pub fn set_root_dir(path: &str) -> io::Result<()> {
+    crate::root::set_root_dir(path)
+}
+
 /// Read the entire contents of a file into a bytes vector.
 pub fn read(path: &str) -> io::Result<Vec<u8>> {
     let mut file = File::open(path)?;
@@ -0,0 +1,849 @@
+//! A cache of the pages of block devices.
+//!
+//! Block devices are read and written in pages of [`PAGE_SIZE`] bytes kept in
+//! one cache shared by all devices, keyed by the device and the page number.
+//! Once the cache is full, the least recently used pages are evicted.
+//!
+//! Writes go to the device immediately and update the cached pages, so the
+//! device never holds stale data and nothing has to be written back on `sync`
+//! or unmount. All the ranges of a device (the whole disk and its partitions)
+//! go through the same [`CachedDevice`], so they see the same data.
+//!
+//! A device is used by one request at a time, so only the pages are shared
+//! between devices. The cache is locked to look up and insert pages but never
+//! during device I/O, so a slow device does not hold up the others.
+//!
+//! A read that misses the page right after the pages read by the previous miss
+//! is taken as sequential, and the following pages up to the read-ahead window
+//! are read along with it. On a device that queues requests, only the missed
+//! page is waited for and the window is read by a request in the background.
+//! Using the first page of that window submits the request for the next one,
+//! so the device keeps reading ahead of a sequential reader. Other devices
+//! read the window in the same request as the missed page.
+//!
+//! A file can change this for its own reads with [`ReadAdvice`], chosen by
+//! `posix_fadvise`. The choice is in effect for the whole cache while the
+//! file is being read, so a concurrent read of another file may use it too.
+//! That only changes how much is read ahead, never the data read.
+
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use axdriver::prelude::*;
+use axdriver_block::{BaseDriverOps, DevError, DevResult, DeviceType};
+use axsync::Mutex;
+
+/// The size of a cached page.
+pub const PAGE_SIZE: usize = 4096;
+/// The default capacity of the cache in bytes.
+pub const DEFAULT_CAPACITY: usize = 4 * 1024 * 1024;
+/// The default read-ahead window in bytes.
+pub const DEFAULT_READ_AHEAD: usize = 64 * 1024;
+
+const BLOCK_SIZE: usize = 512;
+const BLOCKS_PER_PAGE: u64 = (PAGE_SIZE / BLOCK_SIZE) as u64;
+
+static BLOCK_CACHE: Mutex<BlockCache> = Mutex::new(BlockCache::new(
+    DEFAULT_CAPACITY / PAGE_SIZE,
+    DEFAULT_READ_AHEAD / PAGE_SIZE,
+));
+
+/// Sets the capacity of the cache and the read-ahead window, both in bytes.
+///
+/// A capacity of 0 disables the cache, and a window of 0 disables read-ahead.
+pub fn set_block_cache(capacity: usize, read_ahead: usize) {
+    let mut cache = BLOCK_CACHE.lock();
+    cache.capacity = capacity / PAGE_SIZE;
+    cache.read_ahead = read_ahead / PAGE_SIZE;
+    cache.shrink();
+}
+
+/// Counters of the cache since boot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCacheStats {
+    /// Page lookups served from the cache.
+    pub hits: u64,
+    /// Page lookups that read the device.
+    pub misses: u64,
+    /// Pages read ahead of the missed ones.
+    pub read_ahead: u64,
+}
+
+/// Returns the counters of the cache.
+pub fn block_cache_stats() -> BlockCacheStats {
+    BLOCK_CACHE.lock().stats
+}
+
+/// Renders the state of the cache in the format of `/proc/meminfo`.
+#[cfg_attr(not(feature = "procfs"), allow(dead_code))]
+pub(crate) fn render_stats() -> String {
+    let cache = BLOCK_CACHE.lock();
+    let kb = |pages: usize| pages * PAGE_SIZE / 1024;
+    format!(
+        "Capacity:      {:>8} kB\nCached:        {:>8} kB\nReadAhead:     {:>8} kB\n\
+         Hits:          {:>8}\nMisses:        {:>8}\nReadAheadPages:{:>8}\n",
+        kb(cache.capacity),
+        kb(cache.pages.len()),
+        kb(cache.read_ahead),
+        cache.stats.hits,
+        cache.stats.misses,
+        cache.stats.read_ahead,
+    )
+}
+
+/// How reads of a file use read-ahead.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReadAdvice {
+    /// Read ahead by the window once reads are sequential.
+    #[default]
+    Normal,
+    /// Read ahead by twice the window.
+    Sequential,
+    /// Do not read ahead.
+    Random,
+}
+
+/// Runs `f`, which reads a file, with the read-ahead chosen for that file.
+pub(crate) fn with_read_ahead<T>(advice: ReadAdvice, f: impl FnOnce() -> T) -> T {
+    if advice == ReadAdvice::Normal {
+        return f();
+    }
+    let old = core::mem::replace(&mut BLOCK_CACHE.lock().hint, advice);
+    let ret = f();
+    BLOCK_CACHE.lock().hint = old;
+    ret
+}
+
+/// Runs `f`, which reads a file, dropping the pages it reads from the cache.
+/// Missed pages are read from the device without being cached.
+pub(crate) fn evicting<T>(f: impl FnOnce() -> T) -> T {
+    let old = core::mem::replace(&mut BLOCK_CACHE.lock().evict, true);
+    let ret = f();
+    BLOCK_CACHE.lock().evict = old;
+    ret
+}
+
+/// A cached page and when it was last used.
+struct Page {
+    /// The blocks of the page, fewer than a page at the end of the device.
+    data: Vec<u8>,
+    last_used: u64,
+}
+
+/// Pages of all devices, keyed by the device ID and the page number.
+struct BlockCache {
+    pages: BTreeMap<(usize, u64), Page>,
+    /// The pages ordered by when they were last used.
+    lru: BTreeMap<u64, (usize, u64)>,
+    clock: u64,
+    /// The maximum number of pages.
+    capacity: usize,
+    /// The number of pages in the read-ahead window.
+    read_ahead: usize,
+    /// The read-ahead of the file being read.
+    hint: ReadAdvice,
+    /// Whether the pages being read are dropped.
+    evict: bool,
+    stats: BlockCacheStats,
+}
+
+impl BlockCache {
+    const fn new(capacity: usize, read_ahead: usize) -> Self {
+        Self {
+            pages: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            capacity,
+            read_ahead,
+            hint: ReadAdvice::Normal,
+            evict: false,
+            stats: BlockCacheStats {
+                hits: 0,
+                misses: 0,
+                read_ahead: 0,
+            },
+        }
+    }
+
+    fn contains(&self, key: (usize, u64)) -> bool {
+        self.pages.contains_key(&key)
+    }
+
+    /// Returns the data of a cached page and marks it as the most recently used.
+    fn get(&mut self, key: (usize, u64)) -> Option<&[u8]> {
+        let page = self.pages.get_mut(&key)?;
+        self.lru.remove(&page.last_used);
+        self.clock += 1;
+        page.last_used = self.clock;
+        self.lru.insert(self.clock, key);
+        Some(&page.data)
+    }
+
+    /// The number of pages to read ahead of a sequential miss.
+    fn window(&self) -> usize {
+        match self.hint {
+            ReadAdvice::Normal => self.read_ahead,
+            ReadAdvice::Sequential => self.read_ahead * 2,
+            ReadAdvice::Random => 0,
+        }
+    }
+
+    fn remove(&mut self, key: (usize, u64)) -> Option<Vec<u8>> {
+        let page = self.pages.remove(&key)?;
+        self.lru.remove(&page.last_used);
+        Some(page.data)
+    }
+
+    /// Returns the data of a cached page for updating it, without using it.
+    fn get_mut(&mut self, key: (usize, u64)) -> Option<&mut [u8]> {
+        self.pages.get_mut(&key).map(|page| &mut page.data[..])
+    }
+
+    fn insert(&mut self, key: (usize, u64), data: Vec<u8>) {
+        self.clock += 1;
+        let page = Page {
+            data,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.pages.insert(key, page) {
+            self.lru.remove(&old.last_used);
+        }
+        self.lru.insert(self.clock, key);
+        self.shrink();
+    }
+
+    /// Evicts the least recently used pages until the cache fits its capacity.
+    fn shrink(&mut self) {
+        while self.pages.len() > self.capacity {
+            let Some((_, key)) = self.lru.pop_first() else {
+                break;
+            };
+            self.pages.remove(&key);
+        }
+    }
+}
+
+/// A block device whose blocks are read through the cache.
+pub(crate) struct CachedDevice {
+    dev: AxBlockDevice,
+    pages: DevicePages,
+}
+
+impl CachedDevice {
+    pub fn new(dev: AxBlockDevice) -> Self {
+        assert_eq!(dev.block_size(), BLOCK_SIZE);
+        let pages = DevicePages::new(dev.num_blocks());
+        Self { dev, pages }
+    }
+}
+
+impl BaseDriverOps for CachedDevice {
+    fn device_name(&self) -> &str {
+        self.dev.device_name()
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+}
+
+impl BlockDriverOps for CachedDevice {
+    fn num_blocks(&self) -> u64 {
+        self.dev.num_blocks()
+    }
+
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        let dev = block_queue(&mut self.dev);
+        self.pages.read(&BLOCK_CACHE, block_id, buf, dev)
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        let dev = block_queue(&mut self.dev);
+        self.pages.write(&BLOCK_CACHE, block_id, buf, dev)
+    }
+
+    fn flush(&mut self) -> DevResult {
+        self.dev.flush()
+    }
+}
+
+/// A read-ahead request that has not been completed.
+struct ReadAhead {
+    id: RequestId,
+    /// The first page read.
+    page: u64,
+    count: u64,
+}
+
+/// The pages of one device in the cache.
+struct DevicePages {
+    /// The key of the device in the cache.
+    id: usize,
+    num_blocks: u64,
+    /// The page after those read by the last miss or read-ahead.
+    next_page: u64,
+    /// Read-ahead requests on the device.
+    in_flight: Vec<ReadAhead>,
+    /// The first page of the last read-ahead request, whose use submits the
+    /// next one.
+    marker: u64,
+}
+
+impl DevicePages {
+    fn new(num_blocks: u64) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            num_blocks,
+            next_page: u64::MAX,
+            in_flight: Vec::new(),
+            marker: u64::MAX,
+        }
+    }
+
+    /// Calls `f` with each page touched by the `len` bytes from `block_id`,
+    /// the range of bytes within the page and the range within the buffer.
+    fn for_each_page(
+        block_id: u64,
+        len: usize,
+        mut f: impl FnMut(u64, Range<usize>, Range<usize>) -> DevResult,
+    ) -> DevResult {
+        let mut done = 0;
+        while done < len {
+            let block = block_id + (done / BLOCK_SIZE) as u64;
+            let start = (block % BLOCKS_PER_PAGE) as usize * BLOCK_SIZE;
+            let count = (PAGE_SIZE - start).min(len - done);
+            f(
+                block / BLOCKS_PER_PAGE,
+                start..start + count,
+                done..done + count,
+            )?;
+            done += count;
+        }
+        Ok(())
+    }
+
+    /// Reads blocks through the cache, reading missed pages from `dev`.
+    fn read(
+        &mut self,
+        cache: &Mutex<BlockCache>,
+        block_id: u64,
+        buf: &mut [u8],
+        dev: &mut dyn BlockQueueOps,
+    ) -> DevResult {
+        let (capacity, evict) = {
+            let cache = cache.lock();
+            (cache.capacity, cache.evict)
+        };
+        if capacity == 0 || buf.len() % BLOCK_SIZE != 0 {
+            self.finish_all(cache, dev);
+            return dev.read_block(block_id, buf);
+        }
+        if block_id.saturating_add((buf.len() / BLOCK_SIZE) as u64) > self.num_blocks {
+            return Err(DevError::InvalidParam);
+        }
+        if evict {
+            return self.read_evicting(cache, block_id, buf, dev);
+        }
+        Self::for_each_page(block_id, buf.len(), |page, in_page, in_buf| {
+            if let Some(i) = self.in_flight_index(page) {
+                self.finish(cache, i, dev);
+            }
+            let hit = {
+                let mut cache = cache.lock();
+                match cache.get((self.id, page)) {
+                    Some(data) => {
+                        buf[in_buf.clone()].copy_from_slice(&data[in_page.clone()]);
+                        cache.stats.hits += 1;
+                        true
+                    }
+                    None => {
+                        cache.stats.misses += 1;
+                        false
+                    }
+                }
+            };
+            if !hit {
+                // Other devices may evict the page once the cache is unlocked,
+                // so it is copied from the data read.
+                let data = self.fill(cache, page, dev)?;
+                buf[in_buf].copy_from_slice(&data[in_page]);
+            }
+            if page == self.marker {
+                self.marker = u64::MAX;
+                self.submit_read_ahead(cache, dev);
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads blocks, dropping the cached pages and reading the others from
+    /// `dev` without caching them.
+    fn read_evicting(
+        &mut self,
+        cache: &Mutex<BlockCache>,
+        block_id: u64,
+        buf: &mut [u8],
+        dev: &mut dyn BlockQueueOps,
+    ) -> DevResult {
+        // A read-ahead completing later would put the pages back.
+        self.finish_all(cache, dev);
+        Self::for_each_page(block_id, buf.len(), |page, in_page, in_buf| {
+            let cached = cache.lock().remove((self.id, page));
+            match cached {
+                Some(data) => buf[in_buf].copy_from_slice(&data[in_page]),
+                None => {
+                    let block = page * BLOCKS_PER_PAGE + (in_page.start / BLOCK_SIZE) as u64;
+                    dev.read_block(block, &mut buf[in_buf])?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Writes blocks to `dev` and updates the cached pages.
+    fn write(
+        &mut self,
+        cache: &Mutex<BlockCache>,
+        block_id: u64,
+        buf: &[u8],
+        dev: &mut dyn BlockQueueOps,
+    ) -> DevResult {
+        // Requests may complete in any order, so a read-ahead of the written
+        // pages must not complete after the write with the old data.
+        let first = block_id / BLOCKS_PER_PAGE;
+        let end = (block_id + buf.len().div_ceil(BLOCK_SIZE) as u64).div_ceil(BLOCKS_PER_PAGE);
+        while let Some(i) = self
+            .in_flight
+            .iter()
+            .position(|ra| ra.page < end && first < ra.page + ra.count)
+        {
+            self.finish(cache, i, dev);
+        }
+        dev.write_block(block_id, buf)?;
+        self.update(&mut cache.lock(), block_id, buf);
+        Ok(())
+    }
+
+    /// Reads the missed `page` into the cache, along with the read-ahead window
+    /// if the access is sequential, and returns the data read from `page`.
+    fn fill(
+        &mut self,
+        cache: &Mutex<BlockCache>,
+        page: u64,
+        dev: &mut dyn BlockQueueOps,
+    ) -> DevResult<Vec<u8>> {
+        let count = {
+            let cache = cache.lock();
+            let window = if page == self.next_page {
+                cache.window().max(1)
+            } else {
+                1
+            };
+            // The capacity may have been set to 0 since the miss.
+            self.uncached_run(&cache, page, window).max(1)
+        };
+        if count > 1 && dev.queue_depth() > 0 {
+            let data = self.read_pages(cache, page, 1, dev)?;
+            self.next_page = page + 1;
+            self.submit_read_ahead(cache, dev);
+            Ok(data)
+        } else {
+            let data = self.read_pages(cache, page, count, dev)?;
+            cache.lock().stats.read_ahead += count - 1;
+            self.next_page = page + count;
+            Ok(data)
+        }
+    }
+
+    /// Returns the number of pages from `page`, up to `window`, that are
+    /// neither cached nor being read. A cached page is not older than the
+    /// device, so it must not be read again.
+    fn uncached_run(&self, cache: &BlockCache, page: u64, window: usize) -> u64 {
+        let num_pages = self.num_blocks.div_ceil(BLOCKS_PER_PAGE);
+        let window = (window.min(cache.capacity) as u64).min(num_pages.saturating_sub(page));
+        (0..window)
+            .find(|i| {
+                cache.contains((self.id, page + i)) || self.in_flight_index(page + i).is_some()
+            })
+            .unwrap_or(window)
+    }
+
+    /// Returns the range of blocks of `count` pages from `page`.
+    fn blocks(&self, page: u64, count: u64) -> Range<u64> {
+        page * BLOCKS_PER_PAGE..((page + count) * BLOCKS_PER_PAGE).min(self.num_blocks)
+    }
+
+    /// Reads `count` pages from `page` into the cache, waits for them and
+    /// returns their data.
+    fn read_pages(
+        &self,
+        cache: &Mutex<BlockCache>,
+        page: u64,
+        count: u64,
+        dev: &mut dyn BlockQueueOps,
+    ) -> DevResult<Vec<u8>> {
+        let blocks = self.blocks(page, count);
+        let mut data = vec![0u8; (blocks.end - blocks.start) as usize * BLOCK_SIZE];
+        dev.read_block(blocks.start, &mut data)?;
+        self.insert_pages(&mut cache.lock(), page, &data);
+        Ok(data)
+    }
+
+    /// Inserts the pages in `data` from `page`, the first one last so that it
+    /// is the most recently used.
+    fn insert_pages(&self, cache: &mut BlockCache, page: u64, data: &[u8]) {
+        for (i, chunk) in data.chunks(PAGE_SIZE).enumerate().rev() {
+            cache.insert((self.id, page + i as u64), chunk.to_vec());
+        }
+    }
+
+    /// Submits a request reading the read-ahead window from `next_page`,
+    /// without waiting for it.
+    fn submit_read_ahead(&mut self, cache: &Mutex<BlockCache>, dev: &mut dyn BlockQueueOps) {
+        let page = self.next_page;
+        let count = {
+            let cache = cache.lock();
+            self.uncached_run(&cache, page, cache.window())
+        };
+        if count == 0 || self.in_flight.len() >= dev.queue_depth() {
+            return;
+        }
+        let blocks = self.blocks(page, count);
+        let buf = vec![0u8; (blocks.end - blocks.start) as usize * BLOCK_SIZE];
+        // If the queue is full, the pages are read when they are missed.
+        if let Ok(id) = dev.submit_read(blocks.start, buf) {
+            self.in_flight.push(ReadAhead { id, page, count });
+            cache.lock().stats.read_ahead += count;
+            self.next_page = page + count;
+            self.marker = page;
+        }
+    }
+
+    fn in_flight_index(&self, page: u64) -> Option<usize> {
+        self.in_flight
+            .iter()
+            .position(|ra| (ra.page..ra.page + ra.count).contains(&page))
+    }
+
+    /// Waits for the `i`-th read-ahead request and inserts its pages.
+    fn finish(&mut self, cache: &Mutex<BlockCache>, i: usize, dev: &mut dyn BlockQueueOps) {
+        let ra = self.in_flight.swap_remove(i);
+        match dev.wait(ra.id) {
+            Ok(data) => self.insert_pages(&mut cache.lock(), ra.page, &data),
+            // The pages are read again when they are missed.
+            Err(e) => warn!("read-ahead of page {} failed: {:?}", ra.page, e),
+        }
+    }
+
+    fn finish_all(&mut self, cache: &Mutex<BlockCache>, dev: &mut dyn BlockQueueOps) {
+        while !self.in_flight.is_empty() {
+            self.finish(cache, 0, dev);
+        }
+    }
+
+    /// Updates the cached pages after `buf` is written to the device.
+    fn update(&self, cache: &mut BlockCache, block_id: u64, buf: &[u8]) {
+        if cache.pages.is_empty() || buf.len() % BLOCK_SIZE != 0 {
+            return;
+        }
+        let _ = Self::for_each_page(block_id, buf.len(), |page, in_page, in_buf| {
+            if let Some(data) = cache.get_mut((self.id, page)) {
+                data[in_page].copy_from_slice(&buf[in_buf]);
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axdriver_block::ramdisk::RamDisk;
+
+    const DISK_SIZE: usize = 64 * PAGE_SIZE + 3 * BLOCK_SIZE;
+
+    fn ram_disk() -> RamDisk {
+        let mut data = vec![0u8; DISK_SIZE];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = (i * 7 + i / BLOCK_SIZE) as u8;
+        }
+        RamDisk::from(&data[..])
+    }
+
+    /// A RAM disk that queues requests. A read gets the data of when it was
+    /// submitted and completes when it is polled.
+    struct QueuedDisk {
+        disk: RamDisk,
+        done: BTreeMap<RequestId, Vec<u8>>,
+        submitted: u64,
+        /// The cache that must not be locked during I/O, if checked.
+        cache: Option<&'static Mutex<BlockCache>>,
+    }
+
+    impl QueuedDisk {
+        fn check_unlocked(&self) {
+            if let Some(cache) = self.cache {
+                assert!(!cache.is_locked(), "cache locked during I/O");
+            }
+        }
+    }
+
+    impl BaseDriverOps for QueuedDisk {
+        fn device_name(&self) -> &str {
+            "queued"
+        }
+
+        fn device_type(&self) -> DeviceType {
+            DeviceType::Block
+        }
+    }
+
+    impl BlockDriverOps for QueuedDisk {
+        fn num_blocks(&self) -> u64 {
+            self.disk.num_blocks()
+        }
+
+        fn block_size(&self) -> usize {
+            BLOCK_SIZE
+        }
+
+        fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+            self.check_unlocked();
+            self.disk.read_block(block_id, buf)
+        }
+
+        fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+            self.check_unlocked();
+            self.disk.write_block(block_id, buf)
+        }
+
+        fn flush(&mut self) -> DevResult {
+            Ok(())
+        }
+    }
+
+    impl BlockQueueOps for QueuedDisk {
+        fn queue_depth(&self) -> usize {
+            4
+        }
+
+        fn submit_read(&mut self, block_id: u64, mut buf: Vec<u8>) -> DevResult<RequestId> {
+            self.check_unlocked();
+            if self.done.len() >= self.queue_depth() {
+                return Err(DevError::Again);
+            }
+            self.disk.read_block(block_id, &mut buf)?;
+            self.submitted += 1;
+            self.done.insert(self.submitted, buf);
+            Ok(self.submitted)
+        }
+
+        fn poll(&mut self, id: RequestId) -> DevResult<Option<Vec<u8>>> {
+            self.check_unlocked();
+            self.done
+                .remove(&id)
+                .map(Some)
+                .ok_or(DevError::InvalidParam)
+        }
+    }
+
+    struct TestDisk<D> {
+        dev: D,
+        pages: DevicePages,
+    }
+
+    impl TestDisk<RamDisk> {
+        fn new() -> Self {
+            Self::with(ram_disk())
+        }
+    }
+
+    impl TestDisk<QueuedDisk> {
+        fn queued() -> Self {
+            Self::with(QueuedDisk {
+                disk: ram_disk(),
+                done: BTreeMap::new(),
+                submitted: 0,
+                cache: None,
+            })
+        }
+    }
+
+    impl<D: BlockQueueOps> TestDisk<D> {
+        fn with(dev: D) -> Self {
+            let pages = DevicePages::new(dev.num_blocks());
+            Self { dev, pages }
+        }
+
+        fn try_read(&mut self, cache: &Mutex<BlockCache>, block: u64, buf: &mut [u8]) -> DevResult {
+            self.pages.read(cache, block, buf, &mut self.dev)
+        }
+
+        fn read(&mut self, cache: &Mutex<BlockCache>, block: u64, len: usize) -> Vec<u8> {
+            let mut buf = vec![0u8; len];
+            self.try_read(cache, block, &mut buf).unwrap();
+            buf
+        }
+
+        fn write(&mut self, cache: &Mutex<BlockCache>, block: u64, buf: &[u8]) {
+            self.pages.write(cache, block, buf, &mut self.dev).unwrap();
+        }
+    }
+
+    #[test]
+    fn same_data_with_and_without_cache() {
+        let (mut cached, mut plain) = (TestDisk::new(), TestDisk::new());
+        let cache = Mutex::new(BlockCache::new(16, 4));
+        let off = Mutex::new(BlockCache::new(0, 0));
+        let writes: [(u64, usize); 4] = [(3, 1), (7, 2), (60, 20), (510, 3)];
+        for (i, &(block, count)) in writes.iter().enumerate() {
+            let data = vec![i as u8 + 1; count * BLOCK_SIZE];
+            // Read first so that the written pages are cached.
+            cached.read(&cache, block, count * BLOCK_SIZE);
+            cached.write(&cache, block, &data);
+            plain.write(&off, block, &data);
+        }
+        let num_blocks = (DISK_SIZE / BLOCK_SIZE) as u64;
+        for block in (0..num_blocks).step_by(5) {
+            let len = (num_blocks - block).min(11) as usize * BLOCK_SIZE;
+            assert_eq!(
+                cached.read(&cache, block, len),
+                plain.read(&off, block, len),
+                "block {block}"
+            );
+        }
+        assert!(cache.lock().pages.len() <= 16);
+        assert_eq!(off.lock().stats, BlockCacheStats::default());
+    }
+
+    #[test]
+    fn sequential_reads_read_ahead() {
+        let mut disk = TestDisk::new();
+        let cache = Mutex::new(BlockCache::new(32, 4));
+        // A sector at a time, like the FAT driver.
+        for block in 0..(16 * BLOCKS_PER_PAGE) {
+            disk.read(&cache, block, BLOCK_SIZE);
+        }
+        // The miss of page 0 is not sequential, then the misses of pages 1,
+        // 5, 9 and 13 read 4 pages each.
+        let stats = cache.lock().stats;
+        assert_eq!(stats.misses, 5);
+        assert_eq!(stats.read_ahead, 12);
+        assert_eq!(stats.hits + stats.misses, 16 * BLOCKS_PER_PAGE);
+    }
+
+    #[test]
+    fn read_ahead_hints() {
+        // Sequential: the misses of pages 0, 1 and 9, the last two reading 8
+        // pages each. Random: every page is missed.
+        for (hint, misses, read_ahead) in
+            [(ReadAdvice::Sequential, 3, 14), (ReadAdvice::Random, 16, 0)]
+        {
+            let mut disk = TestDisk::new();
+            let cache = Mutex::new(BlockCache::new(64, 4));
+            cache.lock().hint = hint;
+            for block in 0..(16 * BLOCKS_PER_PAGE) {
+                disk.read(&cache, block, BLOCK_SIZE);
+            }
+            let stats = cache.lock().stats;
+            assert_eq!(stats.read_ahead, read_ahead, "{hint:?}");
+            assert_eq!(stats.misses, misses, "{hint:?}");
+        }
+    }
+
+    #[test]
+    fn evicting_read_drops_pages() {
+        let mut disk = TestDisk::queued();
+        let cache = Mutex::new(BlockCache::new(32, 4));
+        disk.read(&cache, 0, BLOCK_SIZE);
+        disk.read(&cache, BLOCKS_PER_PAGE, BLOCK_SIZE);
+        let expected = TestDisk::new().read(&Mutex::new(BlockCache::new(0, 0)), 0, 8 * PAGE_SIZE);
+        cache.lock().evict = true;
+        assert_eq!(disk.read(&cache, 0, 8 * PAGE_SIZE), expected);
+        // Pages 2 to 5 were being read ahead, and are dropped too.
+        assert!(disk.pages.in_flight.is_empty());
+        let cache = cache.lock();
+        assert!(cache.pages.is_empty() && cache.lru.is_empty());
+    }
+
+    #[test]
+    fn least_recently_used_evicted() {
+        let mut disk = TestDisk::new();
+        let cache = Mutex::new(BlockCache::new(2, 0));
+        for page in [0, 2, 0, 4] {
+            disk.read(&cache, page * BLOCKS_PER_PAGE, BLOCK_SIZE);
+        }
+        let id = disk.pages.id;
+        let cache = cache.lock();
+        assert!(cache.contains((id, 0)));
+        assert!(!cache.contains((id, 2)));
+        assert!(cache.contains((id, 4)));
+    }
+
+    #[test]
+    fn partial_last_page() {
+        let mut disk = TestDisk::new();
+        let cache = Mutex::new(BlockCache::new(8, 4));
+        let last = (DISK_SIZE / BLOCK_SIZE) as u64 - 3;
+        let expected =
+            TestDisk::new().read(&Mutex::new(BlockCache::new(0, 0)), last, 3 * BLOCK_SIZE);
+        assert_eq!(disk.read(&cache, last, 3 * BLOCK_SIZE), expected);
+        let mut buf = vec![0u8; 4 * BLOCK_SIZE];
+        assert!(disk.try_read(&cache, last, &mut buf).is_err());
+    }
+
+    #[test]
+    fn queued_read_ahead_in_background() {
+        let mut disk = TestDisk::queued();
+        let cache = Mutex::new(BlockCache::new(32, 4));
+        for block in 0..(16 * BLOCKS_PER_PAGE) {
+            let expected =
+                TestDisk::new().read(&Mutex::new(BlockCache::new(0, 0)), block, BLOCK_SIZE);
+            assert_eq!(disk.read(&cache, block, BLOCK_SIZE), expected);
+        }
+        // Only pages 0 and 1 are waited for. Pages 2, 6, 10 and 14 each submit
+        // the next 4 pages while the previous ones are used.
+        let stats = cache.lock().stats;
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.read_ahead, 20);
+        assert_eq!(disk.dev.submitted, 5);
+        assert_eq!(disk.pages.in_flight.len(), 1);
+    }
+
+    #[test]
+    fn write_completes_read_ahead_first() {
+        let mut disk = TestDisk::queued();
+        let cache = Mutex::new(BlockCache::new(32, 4));
+        disk.read(&cache, 0, BLOCK_SIZE);
+        // Sequential, so pages 2 to 5 are read in the background.
+        disk.read(&cache, BLOCKS_PER_PAGE, BLOCK_SIZE);
+        assert_eq!(disk.pages.in_flight.len(), 1);
+        let data = vec![0xa5; BLOCK_SIZE];
+        let block = 3 * BLOCKS_PER_PAGE + 1;
+        disk.write(&cache, block, &data);
+        assert!(disk.pages.in_flight.is_empty());
+        assert_eq!(disk.read(&cache, block, BLOCK_SIZE), data);
+    }
+
+    #[test]
+    fn unlocked_during_io() {
+        static CACHE: Mutex<BlockCache> = Mutex::new(BlockCache::new(32, 4));
+        let mut disk = TestDisk::queued();
+        disk.dev.cache = Some(&CACHE);
+        // Misses, read-ahead in the background and waiting for it.
+        for block in 0..(16 * BLOCKS_PER_PAGE) {
+            disk.read(&CACHE, block, BLOCK_SIZE);
+        }
+        disk.write(&CACHE, 5, &[0xa5; BLOCK_SIZE]);
+        CACHE.lock().evict = true;
+        disk.read(&CACHE, 0, 32 * PAGE_SIZE);
+    }
+}
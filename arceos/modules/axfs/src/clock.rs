@@ -0,0 +1,24 @@
+//! The clock used to stamp files.
+//!
+//! Files are stamped with the wall-clock time of [`axhal::time`] by default. A
+//! kernel that lets users set the time registers the clock they see with
+//! [`register_clock`], so that the stamps agree with it.
+
+use axhal::time::TimeValue;
+use spin::Once;
+
+static CLOCK: Once<fn() -> TimeValue> = Once::new();
+
+/// Registers the clock used to stamp files, which returns the time elapsed
+/// since the Unix epoch.
+pub fn register_clock(clock: fn() -> TimeValue) {
+    CLOCK.call_once(|| clock);
+}
+
+/// Returns the current time to stamp files with.
+#[cfg_attr(not(feature = "fatfs"), allow(dead_code))]
+pub(crate) fn now() -> TimeValue {
+    CLOCK
+        .get()
+        .map_or_else(axhal::time::wall_time, |clock| clock())
+}
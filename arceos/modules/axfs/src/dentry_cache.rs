@@ -0,0 +1,315 @@
+//! A cache of the results of path lookups.
+//!
+//! Looking up a path on FAT reads every directory on the way from the root.
+//! The cache keeps what lookups found on such filesystems, keyed by the mount
+//! point and the canonical path in it: the nodes of directories, the nodes of
+//! files while they are in use, and names known to be missing. A lookup whose
+//! path is not cached starts from its cached parent directory, so only the
+//! last component is searched on the disk.
+//!
+//! Entries change only through [`crate::root`], which invalidates the cached
+//! path and everything below it whenever an entry is created, removed or
+//! renamed, and the whole cache when a filesystem is mounted or unmounted.
+//! Once the cache is full, the least recently used entries are evicted.
+
+use alloc::{collections::BTreeMap, format, string::String, sync::Weak, vec::Vec};
+
+use axerrno::{AxError, AxResult};
+use axfs_vfs::{VfsNodeOps, VfsNodeRef};
+use axsync::Mutex;
+
+/// The default maximum number of entries.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// A cached path: the path of the mount point, and the path in the mounted
+/// filesystem without leading `/`, in lowercase as FAT names are not case
+/// sensitive.
+pub(crate) type DentryKey = (&'static str, String);
+
+static DENTRY_CACHE: Mutex<DentryCache> = Mutex::new(DentryCache::new(DEFAULT_CAPACITY));
+
+/// Counters of the cache since boot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DentryCacheStats {
+    /// Lookups served from the cache, including those of missing names.
+    pub hits: u64,
+    /// Lookups of missing names served from the cache.
+    pub negative_hits: u64,
+    /// Lookups that searched the filesystem.
+    pub misses: u64,
+}
+
+/// Returns the counters of the cache.
+pub fn dentry_cache_stats() -> DentryCacheStats {
+    DENTRY_CACHE.lock().stats
+}
+
+/// Renders the state of the cache in the format of `/proc/meminfo`.
+#[cfg_attr(not(feature = "procfs"), allow(dead_code))]
+pub(crate) fn render_stats() -> String {
+    let cache = DENTRY_CACHE.lock();
+    let negative = cache
+        .entries
+        .values()
+        .filter(|entry| matches!(entry.dentry, Dentry::Missing))
+        .count();
+    format!(
+        "Capacity:      {:>8}\nEntries:       {:>8}\nNegative:      {:>8}\n\
+         Hits:          {:>8}\nNegativeHits:  {:>8}\nMisses:        {:>8}\n",
+        cache.capacity,
+        cache.entries.len(),
+        negative,
+        cache.stats.hits,
+        cache.stats.negative_hits,
+        cache.stats.misses,
+    )
+}
+
+/// What a lookup found.
+pub(crate) enum Dentry {
+    /// A directory, whose node is kept.
+    Dir(VfsNodeRef),
+    /// A file, whose node is not kept, or the file would be taken as still in
+    /// use when it is removed.
+    File(Weak<dyn VfsNodeOps>),
+    /// Nothing.
+    Missing,
+}
+
+impl Dentry {
+    /// Returns the entry for the result of a lookup, or `None` if the result
+    /// is an error other than a missing name, which is not cached.
+    pub fn new(result: &AxResult<VfsNodeRef>) -> Option<Self> {
+        match result {
+            Ok(node) if node.get_attr().ok()?.is_dir() => Some(Self::Dir(node.clone())),
+            Ok(node) => Some(Self::File(alloc::sync::Arc::downgrade(node))),
+            Err(AxError::NotFound) => Some(Self::Missing),
+            Err(_) => None,
+        }
+    }
+}
+
+/// Returns the cached result of looking up `key`, or `None` if it is not
+/// cached.
+pub(crate) fn get(key: &DentryKey) -> Option<AxResult<VfsNodeRef>> {
+    DENTRY_CACHE.lock().get(key)
+}
+
+/// Returns the generation of the cache, to be passed to [`insert`].
+pub(crate) fn generation() -> u64 {
+    DENTRY_CACHE.lock().generation
+}
+
+/// Caches `dentry` for `key` unless the cache has been invalidated since
+/// `generation`, when the lookup may have seen entries that have changed.
+pub(crate) fn insert(key: DentryKey, generation: u64, dentry: Dentry) {
+    let mut cache = DENTRY_CACHE.lock();
+    if cache.generation == generation {
+        cache.insert(key, dentry);
+    }
+}
+
+/// Drops the entries of the path `path` in the filesystem mounted at `mount`
+/// and of everything below it.
+pub(crate) fn invalidate(mount: &'static str, path: &str) {
+    DENTRY_CACHE.lock().invalidate(mount, path);
+}
+
+/// Drops all the entries.
+pub(crate) fn clear() {
+    let mut cache = DENTRY_CACHE.lock();
+    cache.generation += 1;
+    cache.entries.clear();
+    cache.lru.clear();
+}
+
+/// A cached entry and when it was last used.
+struct Entry {
+    dentry: Dentry,
+    last_used: u64,
+}
+
+struct DentryCache {
+    entries: BTreeMap<DentryKey, Entry>,
+    /// The entries ordered by when they were last used.
+    lru: BTreeMap<u64, DentryKey>,
+    clock: u64,
+    /// Changes whenever entries are invalidated.
+    generation: u64,
+    /// The maximum number of entries.
+    capacity: usize,
+    stats: DentryCacheStats,
+}
+
+impl DentryCache {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            generation: 0,
+            capacity,
+            stats: DentryCacheStats {
+                hits: 0,
+                negative_hits: 0,
+                misses: 0,
+            },
+        }
+    }
+
+    /// Returns the cached result for `key` and marks it as the most recently
+    /// used. A file no longer in use is not cached any more.
+    fn get(&mut self, key: &DentryKey) -> Option<AxResult<VfsNodeRef>> {
+        let Some(entry) = self.entries.get_mut(key) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        let result = match &entry.dentry {
+            Dentry::Dir(node) => Ok(node.clone()),
+            Dentry::File(node) => match node.upgrade() {
+                Some(node) => Ok(node),
+                None => {
+                    self.remove(key);
+                    self.stats.misses += 1;
+                    return None;
+                }
+            },
+            Dentry::Missing => {
+                self.stats.negative_hits += 1;
+                Err(AxError::NotFound)
+            }
+        };
+        self.stats.hits += 1;
+        self.lru.remove(&entry.last_used);
+        self.clock += 1;
+        entry.last_used = self.clock;
+        self.lru.insert(self.clock, key.clone());
+        Some(result)
+    }
+
+    fn insert(&mut self, key: DentryKey, dentry: Dentry) {
+        self.clock += 1;
+        let entry = Entry {
+            dentry,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.entries.insert(key.clone(), entry) {
+            self.lru.remove(&old.last_used);
+        }
+        self.lru.insert(self.clock, key);
+        while self.entries.len() > self.capacity {
+            let Some((_, key)) = self.lru.pop_first() else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+
+    fn remove(&mut self, key: &DentryKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+        }
+    }
+
+    fn invalidate(&mut self, mount: &'static str, path: &str) {
+        self.generation += 1;
+        // The paths starting with `path` are adjacent, and those below it
+        // continue with `/`.
+        let keys: Vec<DentryKey> = self
+            .entries
+            .range((mount, String::from(path))..)
+            .map(|(key, _)| key)
+            .take_while(|(m, p)| *m == mount && p.starts_with(path))
+            .filter(|(_, p)| {
+                path.is_empty() || p.len() == path.len() || p.as_bytes()[path.len()] == b'/'
+            })
+            .cloned()
+            .collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use axfs_vfs::{VfsNodeAttr, VfsNodePerm, VfsNodeType, VfsResult};
+
+    struct Node(VfsNodeType);
+
+    impl VfsNodeOps for Node {
+        fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+            Ok(VfsNodeAttr::new(VfsNodePerm::empty(), self.0, 0, 0))
+        }
+    }
+
+    fn key(path: &str) -> DentryKey {
+        ("/", path.into())
+    }
+
+    fn dir() -> Dentry {
+        let node: VfsNodeRef = Arc::new(Node(VfsNodeType::Dir));
+        Dentry::new(&Ok(node)).unwrap()
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let mut cache = DentryCache::new(8);
+        assert!(cache.get(&key("a")).is_none());
+        cache.insert(key("a"), dir());
+        cache.insert(key("b"), Dentry::Missing);
+        assert!(cache.get(&key("a")).unwrap().is_ok());
+        assert_eq!(cache.get(&key("b")).unwrap().err(), Some(AxError::NotFound));
+        assert_eq!(
+            cache.stats,
+            DentryCacheStats {
+                hits: 2,
+                negative_hits: 1,
+                misses: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn files_not_kept() {
+        let mut cache = DentryCache::new(8);
+        let node: VfsNodeRef = Arc::new(Node(VfsNodeType::File));
+        cache.insert(key("f"), Dentry::new(&Ok(node.clone())).unwrap());
+        assert!(cache.get(&key("f")).unwrap().is_ok());
+        drop(node);
+        assert!(cache.get(&key("f")).is_none());
+        assert!(cache.entries.is_empty() && cache.lru.is_empty());
+    }
+
+    #[test]
+    fn invalidate_subtree() {
+        let mut cache = DentryCache::new(8);
+        for path in ["a", "a/b", "a/b/c", "a b", "ab", "b"] {
+            cache.insert(key(path), dir());
+        }
+        cache.insert(("/mnt", "a".into()), dir());
+        cache.invalidate("/", "a");
+        let left: Vec<_> = cache.entries.keys().cloned().collect();
+        assert_eq!(
+            left,
+            [key("a b"), key("ab"), key("b"), ("/mnt", "a".into())]
+        );
+        cache.invalidate("/mnt", "");
+        assert_eq!(cache.entries.len(), 3);
+        assert_eq!(cache.lru.len(), 3);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = DentryCache::new(2);
+        cache.insert(key("a"), dir());
+        cache.insert(key("b"), dir());
+        cache.get(&key("a"));
+        cache.insert(key("c"), dir());
+        assert!(cache.entries.contains_key(&key("a")));
+        assert!(!cache.entries.contains_key(&key("b")));
+        assert_eq!(cache.lru.len(), 2);
+    }
+}
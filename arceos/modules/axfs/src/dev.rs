@@ -1,17 +1,23 @@
 use axdriver::prelude::*;
 
+use crate::partition::BlockRange;
+#[cfg(feature = "devfs")]
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+#[cfg(feature = "devfs")]
+use axsync::Mutex;
+
 const BLOCK_SIZE: usize = 512;
 
 /// A disk device with a cursor.
 pub struct Disk {
     block_id: u64,
     offset: usize,
-    dev: AxBlockDevice,
+    dev: BlockRange,
 }
 
 impl Disk {
-    /// Create a new disk.
-    pub fn new(dev: AxBlockDevice) -> Self {
+    /// Create a new disk on a whole device or a partition.
+    pub fn new(dev: BlockRange) -> Self {
         assert_eq!(BLOCK_SIZE, dev.block_size());
         Self {
             block_id: 0,
@@ -20,6 +26,11 @@ impl Disk {
         }
     }
 
+    /// Get the device file name of the disk, e.g. `vda1`.
+    pub fn name(&self) -> &'static str {
+        self.dev.name()
+    }
+
     /// Get the size of the disk.
     pub fn size(&self) -> u64 {
         self.dev.num_blocks() * BLOCK_SIZE as u64
@@ -90,3 +101,113 @@ impl Disk {
         Ok(write_size)
     }
 }
+
+/// A block device exposed as a file, e.g. `/dev/vda2`.
+#[cfg(feature = "devfs")]
+pub struct BlockDevFile(Mutex<Disk>);
+
+#[cfg(feature = "devfs")]
+impl BlockDevFile {
+    /// Create a file node for the disk.
+    pub fn new(disk: Disk) -> Self {
+        Self(Mutex::new(disk))
+    }
+}
+
+#[cfg(feature = "devfs")]
+impl VfsNodeOps for BlockDevFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.0.lock().size();
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::BlockDevice,
+            size,
+            size / BLOCK_SIZE as u64,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, mut buf: &mut [u8]) -> VfsResult<usize> {
+        let mut disk = self.0.lock();
+        let end = disk.size().min(offset.saturating_add(buf.len() as u64));
+        buf = &mut buf[..end.saturating_sub(offset) as usize];
+        disk.set_position(offset);
+        let mut read_len = 0;
+        while !buf.is_empty() {
+            let n = disk.read_one(buf).map_err(|_| VfsError::Io)?;
+            buf = &mut buf[n..];
+            read_len += n;
+        }
+        Ok(read_len)
+    }
+
+    fn write_at(&self, offset: u64, mut buf: &[u8]) -> VfsResult<usize> {
+        let mut disk = self.0.lock();
+        let end = disk.size().min(offset.saturating_add(buf.len() as u64));
+        buf = &buf[..end.saturating_sub(offset) as usize];
+        disk.set_position(offset);
+        let mut write_len = 0;
+        while !buf.is_empty() {
+            let n = disk.write_one(buf).map_err(|_| VfsError::Io)?;
+            buf = &buf[n..];
+            write_len += n;
+        }
+        Ok(write_len)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// A registered character device exposed as a file, e.g. `/dev/ttyS0`.
+///
+/// Reads and writes go to the driver, ignoring the offset.
+#[cfg(feature = "devfs")]
+pub struct CharDevFile(axdriver::chardev::CharDevice);
+
+#[cfg(feature = "devfs")]
+impl CharDevFile {
+    /// Create a file node for the character device.
+    pub fn new(dev: axdriver::chardev::CharDevice) -> Self {
+        Self(dev)
+    }
+}
+
+#[cfg(feature = "devfs")]
+fn char_dev_err(err: DevError) -> VfsError {
+    match err {
+        DevError::Again => VfsError::WouldBlock,
+        DevError::InvalidParam | DevError::Unsupported => VfsError::InvalidInput,
+        DevError::NoMemory => VfsError::NoMemory,
+        DevError::ResourceBusy => VfsError::ResourceBusy,
+        _ => VfsError::Io,
+    }
+}
+
+#[cfg(feature = "devfs")]
+impl VfsNodeOps for CharDevFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.0.driver.read(buf).map_err(char_dev_err)
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.0.driver.write(buf).map_err(char_dev_err)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
@@ -1,11 +1,16 @@
 //! Low-level filesystem operations.
 
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
+use axsync::Mutex;
 use cap_access::{Cap, WithCap};
 use core::fmt;
 
+use crate::block_cache::{self, ReadAdvice};
+use crate::mount_stats::MountStats;
+
 #[cfg(feature = "myfs")]
 pub use crate::dev::Disk;
 #[cfg(feature = "myfs")]
@@ -25,6 +30,36 @@ pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
     offset: u64,
+    read_advice: ReadAdvice,
+    /// The counters of the filesystem the file is on.
+    stats: Option<Arc<MountStats>>,
+}
+
+/// The nodes of the files opened for writing on any filesystem, keyed by their
+/// addresses, with the number of [`File`]s opened for writing on each. They are
+/// flushed by [`sync_all`].
+static WRITABLE_NODES: Mutex<BTreeMap<usize, (VfsNodeRef, usize)>> = Mutex::new(BTreeMap::new());
+
+/// Returns the key of `node` in [`WRITABLE_NODES`].
+fn node_key(node: &VfsNodeRef) -> usize {
+    Arc::as_ptr(node) as *const () as usize
+}
+
+/// Flushes all files opened for writing on all filesystems, like `sync`.
+///
+/// The filesystems write data through to the block devices, so only the
+/// metadata of the open files (e.g. the size in the directory entry) needs to
+/// be written back. Closed files were already written back when closed.
+pub fn sync_all() {
+    // Flush without holding the lock, since files may be closed meanwhile.
+    let nodes: Vec<VfsNodeRef> = WRITABLE_NODES
+        .lock()
+        .values()
+        .map(|(node, _)| node.clone())
+        .collect();
+    for node in nodes {
+        node.fsync().ok();
+    }
 }
 
 /// An opened directory object, with open permissions and a cursor for
@@ -46,6 +81,7 @@ pub struct OpenOptions {
     create: bool,
     create_new: bool,
     directory: bool,
+    path: bool,
     // system-specific
     _custom_flags: i32,
     _mode: u32,
@@ -64,6 +100,7 @@ impl OpenOptions {
             create: false,
             create_new: false,
             directory: false,
+            path: false,
             // system-specific
             _custom_flags: 0,
             _mode: 0o666,
@@ -105,6 +142,12 @@ impl OpenOptions {
     pub fn has_directory(&self) -> bool {
         self.directory
     }
+    /// Sets the option to open only a location in the filesystem tree, like
+    /// `O_PATH`. The opened object can neither be read nor written, but its
+    /// attributes can be queried. Other options except `directory` are ignored.
+    pub fn path(&mut self, path: bool) {
+        self.path = path;
+    }
 
     pub fn set_crate(mut self, create: bool, create_new: bool) -> Self {
         self.create = create;
@@ -123,6 +166,9 @@ impl OpenOptions {
     }
 
     const fn is_valid(&self) -> bool {
+        if self.path {
+            return true;
+        }
         if !self.read && !self.write && !self.append && !self.directory {
             return false;
         }
@@ -155,7 +201,7 @@ impl File {
         }
 
         let node_option = crate::root::lookup(dir, path);
-        let node = if opts.create || opts.create_new {
+        let node = if !opts.path && (opts.create || opts.create_new) {
             match node_option {
                 Ok(node) => {
                     // already exists
@@ -172,11 +218,19 @@ impl File {
             // just open the existing
             node_option?
         };
+        Self::open_node(node, opts, crate::root::mount_stats(dir, path))
+    }
 
+    /// Opens `node` of the filesystem with the counters `stats`.
+    fn open_node(
+        node: VfsNodeRef,
+        opts: &OpenOptions,
+        stats: Option<Arc<MountStats>>,
+    ) -> AxResult<Self> {
         let attr = node.get_attr()?;
         debug!("attr: {:?}", attr);
         if attr.is_dir()
-            && (opts.create || opts.create_new || opts.write || opts.append || opts.truncate || opts.execute || opts.read)
+            && (opts.create || opts.create_new || opts.write || opts.append || opts.truncate || opts.execute || opts.read || opts.path)
         {
             return Err(AxError::IsADirectory);
         }
@@ -187,13 +241,25 @@ impl File {
         }
 
         node.open()?;
-        if opts.truncate {
+        if opts.truncate && !opts.path {
             node.truncate(0)?;
         }
+        if let Some(stats) = &stats {
+            stats.file_opened();
+        }
+        if access_cap.contains(Cap::WRITE) {
+            WRITABLE_NODES
+                .lock()
+                .entry(node_key(&node))
+                .or_insert_with(|| (node.clone(), 0))
+                .1 += 1;
+        }
         Ok(Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
             offset: 0,
+            read_advice: ReadAdvice::Normal,
+            stats,
         })
     }
 
@@ -203,6 +269,55 @@ impl File {
         Self::_open_at(None, path, opts)
     }
 
+    /// Opens the file again with `opts`, as a new opened file with its own
+    /// cursor. It works even if the file no longer has a name, e.g. after it
+    /// was removed while open. The options to create the file are ignored.
+    pub fn reopen(&self, opts: &OpenOptions) -> AxResult<Self> {
+        if !opts.is_valid() {
+            return ax_err!(InvalidInput);
+        }
+        let node = unsafe { self.node.access_unchecked() }.clone();
+        Self::open_node(node, opts, self.stats.clone())
+    }
+
+    /// Sets whether writes always go to the end of the file, as if it was
+    /// opened with [`OpenOptions::append`].
+    pub fn set_append(&mut self, append: bool) {
+        self.is_append = append;
+    }
+
+    /// Sets how reads of the file read ahead.
+    pub fn set_read_advice(&mut self, advice: ReadAdvice) {
+        self.read_advice = advice;
+    }
+
+    /// Reads `len` bytes from `offset`, or up to the end of the file, so that
+    /// they are cached.
+    pub fn prefetch(&self, offset: u64, len: u64) -> AxResult {
+        self.read_range(offset, len)
+    }
+
+    /// Drops the cached data of `len` bytes from `offset`, or up to the end of
+    /// the file. Data that is not cached is read without being cached.
+    pub fn drop_cache(&self, offset: u64, len: u64) -> AxResult {
+        block_cache::evicting(|| self.read_range(offset, len))
+    }
+
+    /// Reads `len` bytes from `offset` and discards them.
+    fn read_range(&self, offset: u64, len: u64) -> AxResult {
+        let mut buf = alloc::vec![0u8; block_cache::PAGE_SIZE];
+        let end = offset.saturating_add(len);
+        let mut pos = offset;
+        while pos < end {
+            let chunk = (end - pos).min(buf.len() as u64) as usize;
+            match self.read_at(pos, &mut buf[..chunk])? {
+                0 => break,
+                n => pos += n as u64,
+            }
+        }
+        Ok(())
+    }
+
     /// Truncates the file to the specified size.
     pub fn truncate(&self, size: u64) -> AxResult {
         self.access_node(Cap::WRITE)?.truncate(size)?;
@@ -215,8 +330,10 @@ impl File {
     /// After the read, the cursor will be advanced by the number of bytes read.
     pub fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
         let node = self.access_node(Cap::READ)?;
-        let read_len = node.read_at(self.offset, buf)?;
+        let read_len =
+            block_cache::with_read_ahead(self.read_advice, || node.read_at(self.offset, buf))?;
         self.offset += read_len as u64;
+        self.count_read(read_len);
         Ok(read_len)
     }
 
@@ -225,7 +342,9 @@ impl File {
     /// It does not update the file cursor.
     pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
         let node = self.access_node(Cap::READ)?;
-        let read_len = node.read_at(offset, buf)?;
+        let read_len =
+            block_cache::with_read_ahead(self.read_advice, || node.read_at(offset, buf))?;
+        self.count_read(read_len);
         Ok(read_len)
     }
 
@@ -243,6 +362,7 @@ impl File {
         let node = self.access_node(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
         self.offset = offset + write_len as u64;
+        self.count_written(write_len);
         Ok(write_len)
     }
 
@@ -253,9 +373,24 @@ impl File {
     pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
         let node = self.access_node(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
+        self.count_written(write_len);
         Ok(write_len)
     }
 
+    /// Counts `n` bytes read in the statistics of the filesystem.
+    fn count_read(&self, n: usize) {
+        if let Some(stats) = &self.stats {
+            stats.add_read(n);
+        }
+    }
+
+    /// Counts `n` bytes written in the statistics of the filesystem.
+    fn count_written(&self, n: usize) {
+        if let Some(stats) = &self.stats {
+            stats.add_written(n);
+        }
+    }
+
     /// Flushes the file, writes all buffered data to the underlying device.
     pub fn flush(&self) -> AxResult {
         self.access_node(Cap::WRITE)?.fsync()?;
@@ -290,11 +425,14 @@ impl Directory {
 
     fn _open_dir_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
         debug!("open dir: {}", path);
-        if !opts.read {
-            return ax_err!(InvalidInput);
-        }
-        if opts.create || opts.create_new || opts.write || opts.append || opts.truncate {
-            return ax_err!(InvalidInput);
+        // Opening only the location needs no other options.
+        if !opts.path {
+            if !opts.read {
+                return ax_err!(InvalidInput);
+            }
+            if opts.create || opts.create_new || opts.write || opts.append || opts.truncate {
+                return ax_err!(InvalidInput);
+            }
         }
 
         let node = crate::root::lookup(dir, path)?;
@@ -360,6 +498,11 @@ impl Directory {
         crate::root::remove_dir(self.access_at(path)?, path)
     }
 
+    /// Gets the directory attributes.
+    pub fn get_attr(&self) -> AxResult<FileAttr> {
+        self.access_node(Cap::empty())?.get_attr()
+    }
+
     /// Reads directory entries starts from the current position into the
     /// given buffer. Returns the number of entries read.
     ///
@@ -373,6 +516,13 @@ impl Directory {
         Ok(n)
     }
 
+    /// Reads directory entries starting from the `start_idx`-th entry into the
+    /// given buffer, without moving the cursor. Returns the number of entries
+    /// read.
+    pub fn read_dir_at(&self, start_idx: usize, dirents: &mut [DirEntry]) -> AxResult<usize> {
+        self.access_node(Cap::READ)?.read_dir(start_idx, dirents)
+    }
+
     /// Rename a file or directory to a new name.
     /// Delete the original file if `old` already exists.
     ///
@@ -384,7 +534,21 @@ impl Directory {
 
 impl Drop for File {
     fn drop(&mut self) {
-        unsafe { self.node.access_unchecked().release().ok() };
+        let node = unsafe { self.node.access_unchecked() };
+        node.release().ok();
+        if self.node.can_access(Cap::WRITE) {
+            let mut nodes = WRITABLE_NODES.lock();
+            let key = node_key(node);
+            if let Some((_, count)) = nodes.get_mut(&key) {
+                *count -= 1;
+                if *count == 0 {
+                    nodes.remove(&key);
+                }
+            }
+        }
+        if let Some(stats) = &self.stats {
+            stats.file_closed();
+        }
     }
 }
 
@@ -415,6 +579,7 @@ impl fmt::Debug for OpenOptions {
         fmt_opt!(truncate, "TRUNC");
         fmt_opt!(create, "CREATE");
         fmt_opt!(create_new, "CREATE_NEW");
+        fmt_opt!(path, "PATH");
         Ok(())
     }
 }
@@ -422,6 +587,9 @@ impl fmt::Debug for OpenOptions {
 impl From<&OpenOptions> for Cap {
     fn from(opts: &OpenOptions) -> Cap {
         let mut cap = Cap::empty();
+        if opts.path {
+            return cap;
+        }
         if opts.read {
             cap |= Cap::READ;
         }
@@ -1,22 +1,121 @@
-use alloc::sync::Arc;
-use core::cell::UnsafeCell;
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use fatfs::{
+    Date, DateTime, Dir, File, FileAttributes, LossyOemCpConverter, Read, Seek, SeekFrom, Time,
+    Write,
+};
 
 use crate::dev::Disk;
+use crate::mount_stats::{FsSpace, SpaceInfo};
 
 const BLOCK_SIZE: usize = 512;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The directory in the root where files removed while in use are kept until
+/// they are no longer used. It is hidden from the listing of the root, and the
+/// files left in it are removed when the filesystem is initialized.
+const ORPHAN_DIR: &str = ".orphans";
+
+/// Stamps the directory entries with the time of the registered clock (see
+/// [`register_clock`](crate::register_clock)) when files are created, written
+/// or read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WallTimeProvider;
+
+impl fatfs::TimeProvider for WallTimeProvider {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        fat_date_time(crate::clock::now().as_secs())
+    }
+}
+
+/// Converts seconds since the Unix epoch to a FAT date and time, clamped to
+/// the years FAT can represent (1980 to 2107).
+fn fat_date_time(secs: u64) -> DateTime {
+    // Civil date from the number of days, counting eras of 400 years from
+    // 0000-03-01 so that leap days are at the end of a year.
+    let days = secs / SECS_PER_DAY + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = era * 400 + year_of_era + (month <= 2) as u64;
+
+    if year < 1980 {
+        return DateTime::new(Date::new(1980, 1, 1), Time::new(0, 0, 0, 0));
+    } else if year > 2107 {
+        return DateTime::new(Date::new(2107, 12, 31), Time::new(23, 59, 59, 0));
+    }
+    let secs_of_day = secs % SECS_PER_DAY;
+    let time = Time::new(
+        (secs_of_day / 3600) as u16,
+        (secs_of_day / 60 % 60) as u16,
+        (secs_of_day % 60) as u16,
+        0,
+    );
+    DateTime::new(Date::new(year as u16, month as u16, day as u16), time)
+}
+
+fn fs_options() -> fatfs::FsOptions<WallTimeProvider, LossyOemCpConverter> {
+    fatfs::FsOptions::new()
+        .update_accessed_date(true)
+        .time_provider(WallTimeProvider)
+}
 
 pub struct FatFileSystem {
-    inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
+    inner: fatfs::FileSystem<Disk, WallTimeProvider, LossyOemCpConverter>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
 }
 
-pub struct FileWrapper<'a, IO: IoTrait>(Mutex<File<'a, IO, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a, IO: IoTrait>(Dir<'a, IO, NullTimeProvider, LossyOemCpConverter>);
+type FatDir<'a, IO> = Dir<'a, IO, WallTimeProvider, LossyOemCpConverter>;
+type FatFile<'a, IO> = File<'a, IO, WallTimeProvider, LossyOemCpConverter>;
+
+/// The state shared by all the nodes of a FAT filesystem.
+struct FatShared<'a, IO: IoTrait> {
+    root: FatDir<'a, IO>,
+    /// The nodes of the files in use, keyed by their path from the root in
+    /// lowercase, so that all the users of a file share one node.
+    open_files: Mutex<BTreeMap<String, Weak<FileWrapper<'a, IO>>>>,
+    next_orphan: AtomicU64,
+}
+
+/// A FAT file. All the users of the file share this node.
+///
+/// A file removed while its node is in use is moved to [`ORPHAN_DIR`] instead,
+/// and removed from there when the node is dropped.
+pub struct FileWrapper<'a, IO: IoTrait> {
+    file: Mutex<FatFile<'a, IO>>,
+    read_only: bool,
+    /// The name of the file in [`ORPHAN_DIR`] once it has been removed.
+    orphan: Mutex<Option<String>>,
+    shared: Arc<FatShared<'a, IO>>,
+}
+
+pub struct DirWrapper<'a, IO: IoTrait> {
+    dir: FatDir<'a, IO>,
+    /// The path of the directory from the root, empty for the root.
+    path: String,
+    shared: Arc<FatShared<'a, IO>>,
+}
 
 pub trait IoTrait: Read + Write + Seek {}
 
@@ -32,7 +131,7 @@ impl FatFileSystem {
     pub fn new(mut disk: Disk) -> Self {
         let opts = fatfs::FormatVolumeOptions::new();
         fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
+        let inner = fatfs::FileSystem::new(disk, fs_options())
             .expect("failed to initialize FAT filesystem");
         Self {
             inner,
@@ -42,7 +141,7 @@ impl FatFileSystem {
 
     #[cfg(not(feature = "use-ramdisk"))]
     pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
+        let inner = fatfs::FileSystem::new(disk, fs_options())
             .expect("failed to initialize FAT filesystem");
         Self {
             inner,
@@ -52,52 +151,189 @@ impl FatFileSystem {
 
     pub fn init(&'static self) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        let root = self.inner.root_dir();
+        remove_orphans(&root);
+        unsafe { *self.root_dir.get() = Some(Self::new_root(root)) }
     }
 
-    fn new_file<IO: IoTrait>(
-        file: File<'_, IO, NullTimeProvider, LossyOemCpConverter>,
-    ) -> Arc<FileWrapper<IO>> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+    fn new_root<IO: IoTrait>(root: FatDir<'static, IO>) -> Arc<DirWrapper<'static, IO>> {
+        let shared = Arc::new(FatShared {
+            root: root.clone(),
+            open_files: Mutex::new(BTreeMap::new()),
+            next_orphan: AtomicU64::new(0),
+        });
+        Arc::new(DirWrapper {
+            dir: root,
+            path: String::new(),
+            shared,
+        })
     }
+}
 
-    fn new_dir<IO: IoTrait>(
-        dir: Dir<'_, IO, NullTimeProvider, LossyOemCpConverter>,
-    ) -> Arc<DirWrapper<IO>> {
-        Arc::new(DirWrapper(dir))
+/// Removes the files left in [`ORPHAN_DIR`] when the filesystem was last used
+/// without closing them, such as on a crash.
+fn remove_orphans<IO: IoTrait>(root: &FatDir<'_, IO>) {
+    let Ok(orphans) = root.open_dir(ORPHAN_DIR) else {
+        return;
+    };
+    let names: Vec<_> = orphans
+        .iter()
+        .flatten()
+        .filter(|entry| entry.is_file())
+        .map(|entry| entry.file_name())
+        .collect();
+    for name in names {
+        if orphans.remove(&name).is_ok() {
+            info!("  removed orphan file {}", name);
+        }
+    }
+    if let Err(err) = root.remove(ORPHAN_DIR) {
+        warn!("  failed to remove {}: {:?}", ORPHAN_DIR, err);
     }
 }
 
+/// Joins `path` to the directory `dir` and resolves `.` and `..`, giving the
+/// key of the file in [`FatShared::open_files`].
+fn file_key(dir: &str, path: &str) -> String {
+    let mut parts = Vec::new();
+    for part in dir.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/").to_lowercase()
+}
+
 impl<IO: IoTrait> VfsNodeOps for FileWrapper<'static, IO> {
     axfs_vfs::impl_vfs_non_dir_default! {}
 
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        let size = self.0.lock().seek(SeekFrom::End(0)).map_err(as_vfs_err)?;
+        let size = self
+            .file
+            .lock()
+            .seek(SeekFrom::End(0))
+            .map_err(as_vfs_err)?;
         let blocks = (size + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64;
-        // FAT fs doesn't support permissions, we just set everything to 755
-        let perm = VfsNodePerm::from_bits_truncate(0o755);
+        // FAT fs doesn't support permissions, we just set everything to 755,
+        // or 555 for files with the read-only attribute
+        let perm = VfsNodePerm::from_bits_truncate(if self.read_only { 0o555 } else { 0o755 });
         Ok(VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks))
     }
 
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
         file.read(buf).map_err(as_vfs_err)
     }
 
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
         file.write(buf).map_err(as_vfs_err)
     }
 
+    fn fsync(&self) -> VfsResult {
+        // Writes the directory entry (e.g. the file size) back to the disk.
+        self.file.lock().flush().map_err(as_vfs_err)
+    }
+
     fn truncate(&self, size: u64) -> VfsResult {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
         file.truncate().map_err(as_vfs_err)
     }
 }
 
+impl<'a, IO: IoTrait> FileWrapper<'a, IO> {
+    /// Moves the file from `path` in `dir` to `dst_path` in `dst_dir`, and
+    /// reopens it there.
+    ///
+    /// The directory entry is written back first so that the new entry gets
+    /// the current size, and the file is reopened because the old entry would
+    /// be written again when the file changes.
+    fn move_to(
+        &self,
+        dir: &FatDir<'a, IO>,
+        path: &str,
+        dst_dir: &FatDir<'a, IO>,
+        dst_path: &str,
+    ) -> VfsResult {
+        let mut file = self.file.lock();
+        file.flush().map_err(as_vfs_err)?;
+        dir.rename(path, dst_dir, dst_path).map_err(as_vfs_err)?;
+        *file = dst_dir.open_file(dst_path).map_err(as_vfs_err)?;
+        Ok(())
+    }
+}
+
+impl<IO: IoTrait> Drop for FileWrapper<'_, IO> {
+    fn drop(&mut self) {
+        let Some(name) = self.orphan.lock().take() else {
+            return;
+        };
+        // Otherwise dropping the file writes its entry back after it is removed.
+        let _ = self.file.lock().flush();
+        let removed = self
+            .shared
+            .root
+            .open_dir(ORPHAN_DIR)
+            .and_then(|orphans| orphans.remove(&name));
+        if let Err(err) = removed {
+            warn!("failed to remove orphan file {}: {:?}", name, err);
+        }
+        // Fails while other orphan files are still in use.
+        let _ = self.shared.root.remove(ORPHAN_DIR);
+    }
+}
+
+impl<IO: IoTrait> DirWrapper<'static, IO> {
+    /// Whether the entry at `path` has the read-only attribute.
+    fn is_read_only(&self, path: &str) -> bool {
+        let (dir, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => (self.dir.open_dir(parent).ok(), name),
+            None => (Some(self.dir.clone()), path),
+        };
+        dir.and_then(|dir| {
+            dir.iter()
+                .flatten()
+                .find(|entry| entry.file_name().eq_ignore_ascii_case(name))
+        })
+        .is_some_and(|entry| entry.attributes().contains(FileAttributes::READ_ONLY))
+    }
+
+    fn new_dir(&self, dir: FatDir<'static, IO>, path: &str) -> Arc<Self> {
+        Arc::new(Self {
+            dir,
+            path: file_key(&self.path, path),
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Returns the node of the file at `path`, which is shared with the other
+    /// users of the file.
+    fn open_file(&self, path: &str) -> VfsResult<Arc<FileWrapper<'static, IO>>> {
+        let key = file_key(&self.path, path);
+        let mut open_files = self.shared.open_files.lock();
+        if let Some(node) = open_files.get(&key).and_then(Weak::upgrade) {
+            return Ok(node);
+        }
+        let file = self.dir.open_file(path).map_err(as_vfs_err)?;
+        let node = Arc::new(FileWrapper {
+            file: Mutex::new(file),
+            read_only: self.is_read_only(path),
+            orphan: Mutex::new(None),
+            shared: self.shared.clone(),
+        });
+        open_files.retain(|_, node| node.strong_count() > 0);
+        open_files.insert(key, Arc::downgrade(&node));
+        Ok(node)
+    }
+}
+
 impl<IO: IoTrait> VfsNodeOps for DirWrapper<'static, IO> {
     axfs_vfs::impl_vfs_dir_default! {}
 
@@ -112,9 +348,9 @@ impl<IO: IoTrait> VfsNodeOps for DirWrapper<'static, IO> {
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
-        self.0
+        self.dir
             .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+            .map_or(None, |dir| Some(self.new_dir(dir, "..")))
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -126,12 +362,22 @@ impl<IO: IoTrait> VfsNodeOps for DirWrapper<'static, IO> {
         if let Some(rest) = path.strip_prefix("./") {
             return self.lookup(rest);
         }
+        // Hidden like in the listing, and created and removed without going
+        // through the dentry cache, which must not keep it.
+        if self.path.is_empty()
+            && path
+                .split('/')
+                .next()
+                .is_some_and(|name| name.eq_ignore_ascii_case(ORPHAN_DIR))
+        {
+            return Err(VfsError::NotFound);
+        }
 
         // TODO: use `fatfs::Dir::find_entry`, but it's not public.
-        if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
-        } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+        if let Ok(file) = self.open_file(path) {
+            Ok(file)
+        } else if let Ok(dir) = self.dir.open_dir(path) {
+            Ok(self.new_dir(dir, path))
         } else {
             Err(VfsError::NotFound)
         }
@@ -149,11 +395,11 @@ impl<IO: IoTrait> VfsNodeOps for DirWrapper<'static, IO> {
 
         match ty {
             VfsNodeType::File => {
-                self.0.create_file(path).map_err(as_vfs_err)?;
+                self.dir.create_file(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             VfsNodeType::Dir => {
-                self.0.create_dir(path).map_err(as_vfs_err)?;
+                self.dir.create_dir(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             _ => Err(VfsError::Unsupported),
@@ -167,11 +413,39 @@ impl<IO: IoTrait> VfsNodeOps for DirWrapper<'static, IO> {
         if let Some(rest) = path.strip_prefix("./") {
             return self.remove(rest);
         }
-        self.0.remove(path).map_err(as_vfs_err)
+        let mut open_files = self.shared.open_files.lock();
+        let key = file_key(&self.path, path);
+        let Some(node) = open_files.get(&key).and_then(Weak::upgrade) else {
+            return self.dir.remove(path).map_err(as_vfs_err);
+        };
+        // The file is still in use, so its data is kept until the node is dropped.
+        let orphans = self
+            .shared
+            .root
+            .create_dir(ORPHAN_DIR)
+            .map_err(as_vfs_err)?;
+        let name = self
+            .shared
+            .next_orphan
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string();
+        node.move_to(&self.dir, path, &orphans, &name)?;
+        *node.orphan.lock() = Some(name);
+        open_files.remove(&key);
+        Ok(())
     }
 
     fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
-        let mut iter = self.0.iter().skip(start_idx);
+        let mut iter = self
+            .dir
+            .iter()
+            .filter(|entry| {
+                !(self.path.is_empty()
+                    && entry
+                        .as_ref()
+                        .is_ok_and(|entry| entry.file_name() == ORPHAN_DIR))
+            })
+            .skip(start_idx);
         for (i, out_entry) in dirents.iter_mut().enumerate() {
             let x = iter.next();
             match x {
@@ -198,9 +472,33 @@ impl<IO: IoTrait> VfsNodeOps for DirWrapper<'static, IO> {
             src_path, dst_path
         );
 
-        self.0
-            .rename(src_path, &self.0, dst_path)
-            .map_err(as_vfs_err)
+        let mut open_files = self.shared.open_files.lock();
+        let (src_key, dst_key) = (
+            file_key(&self.path, src_path),
+            file_key(&self.path, dst_path),
+        );
+        if let Some(node) = open_files.get(&src_key).and_then(Weak::upgrade) {
+            node.move_to(&self.dir, src_path, &self.dir, dst_path)?;
+            open_files.remove(&src_key);
+            open_files.insert(dst_key, Arc::downgrade(&node));
+            return Ok(());
+        }
+        self.dir
+            .rename(src_path, &self.dir, dst_path)
+            .map_err(as_vfs_err)?;
+        // The entries of the files in a moved directory stay where they are,
+        // only their paths change.
+        let prefix = src_key + "/";
+        let moved: Vec<_> = open_files
+            .keys()
+            .filter(|key| key.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for key in moved {
+            let node = open_files.remove(&key).unwrap();
+            open_files.insert(format!("{}/{}", dst_key, &key[prefix.len()..]), node);
+        }
+        Ok(())
     }
 }
 
@@ -211,6 +509,24 @@ impl VfsOps for FatFileSystem {
     }
 }
 
+impl SpaceInfo for FatFileSystem {
+    fn space(&self) -> Option<FsSpace> {
+        fat_space(&self.inner)
+    }
+}
+
+/// Returns the space of a FAT filesystem, counted in clusters.
+fn fat_space<IO: IoTrait>(
+    fs: &fatfs::FileSystem<IO, WallTimeProvider, LossyOemCpConverter>,
+) -> Option<FsSpace> {
+    let stats = fs.stats().ok()?;
+    Some(FsSpace {
+        block_size: stats.cluster_size() as u64,
+        blocks: stats.total_clusters() as u64,
+        free_blocks: stats.free_clusters() as u64,
+    })
+}
+
 impl fatfs::IoBase for Disk {
     type Error = ();
 }
@@ -290,16 +606,10 @@ fn as_vfs_err<E>(err: fatfs::Error<E>) -> VfsError {
     }
 }
 
-impl Clone for FileWrapper<'static, Disk> {
-    fn clone(&self) -> Self {
-        let file = self.0.lock();
-        let cloned_file = file.clone();
-        Self(Mutex::new(cloned_file))
-    }
-}
-
+/// A FAT filesystem stored in a VFS node, such as a block device in `/dev`
+/// or an image file.
 pub struct FatFileSystemFromFile {
-    inner: fatfs::FileSystem<FileWrapper<'static, Disk>, NullTimeProvider, LossyOemCpConverter>,
+    inner: fatfs::FileSystem<NodeIo, WallTimeProvider, LossyOemCpConverter>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
 }
 
@@ -307,18 +617,26 @@ unsafe impl Sync for FatFileSystemFromFile {}
 unsafe impl Send for FatFileSystemFromFile {}
 
 impl FatFileSystemFromFile {
-    pub fn new(file: FileWrapper<'static, Disk>) -> Self {
-        let inner = fatfs::FileSystem::new(file, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+    /// Opens the FAT filesystem stored in `node`.
+    ///
+    /// Returns [`VfsError::InvalidData`] if `node` does not contain a valid
+    /// FAT filesystem.
+    pub fn new(node: VfsNodeRef) -> VfsResult<Self> {
+        let inner = fatfs::FileSystem::new(NodeIo { node, pos: 0 }, fs_options()).map_err(
+            |err| match err {
+                fatfs::Error::Io(()) => VfsError::Io,
+                _ => VfsError::InvalidData,
+            },
+        )?;
+        Ok(Self {
             inner,
             root_dir: UnsafeCell::new(None),
-        }
+        })
     }
 
     pub fn init(&'static self) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(FatFileSystem::new_dir(self.inner.root_dir())) }
+        unsafe { *self.root_dir.get() = Some(FatFileSystem::new_root(self.inner.root_dir())) }
     }
 }
 
@@ -329,42 +647,64 @@ impl VfsOps for FatFileSystemFromFile {
     }
 }
 
-impl<'a> fatfs::IoBase for FileWrapper<'a, Disk> {
+impl SpaceInfo for FatFileSystemFromFile {
+    fn space(&self) -> Option<FsSpace> {
+        fat_space(&self.inner)
+    }
+}
+
+/// Accesses a VFS node through a cursor, as `fatfs` requires.
+pub struct NodeIo {
+    node: VfsNodeRef,
+    pos: u64,
+}
+
+impl fatfs::IoBase for NodeIo {
     type Error = ();
 }
 
-impl<'a> IoTrait for FileWrapper<'a, Disk> {}
+impl IoTrait for NodeIo {}
 
-impl<'a> fatfs::Read for FileWrapper<'a, Disk> {
+impl fatfs::Read for NodeIo {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-        let mut file = self.0.lock();
-        file.read(buf)
+        let n = self
+            .node
+            .read_at(self.pos, buf)
             .inspect_err(|e| error!("read error: {e:?}"))
-            .map_err(|_| ())
+            .map_err(|_| ())?;
+        self.pos += n as u64;
+        Ok(n)
     }
 }
 
-impl<'a> fatfs::Write for FileWrapper<'a, Disk> {
+impl fatfs::Write for NodeIo {
     fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        let mut file = self.0.lock();
-        file.write(buf)
+        let n = self
+            .node
+            .write_at(self.pos, buf)
             .inspect_err(|e| error!("write error: {e:?}"))
-            .map_err(|_| ())
+            .map_err(|_| ())?;
+        self.pos += n as u64;
+        Ok(n)
     }
 
     fn flush(&mut self) -> Result<(), Self::Error> {
-        let mut file = self.0.lock();
-        file.flush()
+        self.node
+            .fsync()
             .inspect_err(|e| error!("flush error: {e:?}"))
             .map_err(|_| ())
     }
 }
 
-impl<'a> fatfs::Seek for FileWrapper<'a, Disk> {
-    fn seek(&mut self, pos: fatfs::SeekFrom) -> Result<u64, Self::Error> {
-        let mut file = self.0.lock();
-        file.seek(pos)
-            .inspect_err(|e| error!("seek error: {e:?}"))
-            .map_err(|_| ())
+impl fatfs::Seek for NodeIo {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let size = self.node.get_attr().map_err(|_| ())?.size();
+        self.pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(off) => self.pos.checked_add_signed(off),
+            SeekFrom::End(off) => size.checked_add_signed(off),
+        }
+        .ok_or(())?;
+        Ok(self.pos)
     }
 }
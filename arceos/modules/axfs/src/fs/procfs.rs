@@ -0,0 +1,673 @@
+//! The process information filesystem mounted on `/proc`.
+//!
+//! Static entries are kept in a RAM filesystem, and `/proc/sys` lists the
+//! tunables registered with [`register_sysctl`](crate::register_sysctl). The
+//! per-process directories `/proc/[pid]` and `/proc/self` are generated from
+//! the [`ProcessInfoProvider`] registered by the kernel, and their files are
+//! rendered each time they are read. `/proc/[pid]/fd` holds a link for each
+//! open file descriptor of the process. The files of the filesystem module
+//! itself such as `/proc/blockcache`, of the memory statistics in
+//! `/proc/meminfo`, and the files registered by the kernel with
+//! [`register_proc_file`] are rendered on every read too.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axfs_ramfs::{DirNode, RamFileSystem};
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsNodePerm, VfsOps, VfsResult};
+use spin::{Once, RwLock};
+
+/// Per-process information supplied by the kernel.
+pub trait ProcessInfoProvider: Send + Sync {
+    /// Returns the PIDs of all processes that have a directory in `/proc`.
+    fn pids(&self) -> Vec<u64>;
+
+    /// Returns the PID of the calling process, which `/proc/self` refers to.
+    fn current_pid(&self) -> u64;
+
+    /// Returns the owner `(uid, gid)` of the process `pid`, or `None` if the
+    /// process no longer exists.
+    fn owner(&self, pid: u64) -> Option<(u32, u32)>;
+
+    /// Returns the names of the files in each process directory.
+    fn files(&self) -> &'static [&'static str];
+
+    /// Renders the file `name` of the process `pid`, or returns `None` if the
+    /// process no longer exists.
+    fn render(&self, pid: u64, name: &str) -> Option<Vec<u8>>;
+
+    /// Returns the open file descriptors of the process `pid` with the targets
+    /// of their links in `/proc/[pid]/fd`, or `None` if the process no longer
+    /// exists.
+    fn fds(&self, pid: u64) -> Option<Vec<(u32, String)>>;
+}
+
+static PROVIDER: Once<&'static dyn ProcessInfoProvider> = Once::new();
+
+/// Handles a write to a file registered with [`register_proc_file`].
+pub type ProcFileWrite = fn(&[u8]) -> VfsResult;
+
+/// A generated file in `/proc`: its name, the function rendering its content
+/// and the handler of writes, if it is writable.
+type ProcFileEntry = (&'static str, fn() -> String, Option<ProcFileWrite>);
+
+/// Files in `/proc` rendered by the filesystem module.
+const GENERATED_FILES: &[ProcFileEntry] = &[
+    ("blockcache", crate::block_cache::render_stats, None),
+    ("dentrycache", crate::dentry_cache::render_stats, None),
+    ("meminfo", render_meminfo, None),
+    ("mounts", crate::mount_stats::render_mounts, None),
+];
+
+/// Files in `/proc` registered by the kernel.
+static KERNEL_FILES: RwLock<Vec<ProcFileEntry>> = RwLock::new(Vec::new());
+
+/// Adds the file `/proc/<name>`, whose content is rendered by `render` each
+/// time it is read.
+///
+/// Without `write` the file is read-only. Otherwise every write passes the
+/// written bytes to `write` regardless of the offset, like the files in
+/// `/proc/sys` on Linux.
+pub fn register_proc_file(
+    name: &'static str,
+    render: fn() -> String,
+    write: Option<ProcFileWrite>,
+) {
+    KERNEL_FILES.write().push((name, render, write));
+}
+
+/// Looks up a file rendered by the filesystem module or registered by the
+/// kernel.
+fn generated_file(name: &str) -> Option<GeneratedFile> {
+    GENERATED_FILES
+        .iter()
+        .copied()
+        .chain(KERNEL_FILES.read().iter().copied())
+        .find(|file| file.0 == name)
+        .map(|(_, render, write)| GeneratedFile { render, write })
+}
+
+/// Registers the provider of the per-process directories.
+///
+/// Until it is called, `/proc` only contains the static entries.
+pub fn register_process_info(provider: &'static dyn ProcessInfoProvider) {
+    PROVIDER.call_once(|| provider);
+}
+
+/// Returns the owner `(uid, gid)` of the file at `path` in the filesystem,
+/// which is the process for the files in `/proc/[pid]`, and root for those in
+/// `/proc/sys`.
+pub(crate) fn owner(path: &str) -> Option<(u32, u32)> {
+    let provider = PROVIDER.get()?;
+    let pid = match split_path(path).0 {
+        "sys" => return Some((0, 0)),
+        "self" => provider.current_pid(),
+        name => name.parse().ok()?,
+    };
+    provider.owner(pid)
+}
+
+/// Returns the PID and the file descriptor of the link at `path` in the
+/// filesystem, if it is a link in `/proc/[pid]/fd`.
+pub(crate) fn fd_link(path: &str) -> Option<(u64, u32)> {
+    let provider = PROVIDER.get()?;
+    let (name, rest) = split_path(path);
+    let pid = match name {
+        "self" => provider.current_pid(),
+        name => name.parse().ok()?,
+    };
+    let (dir, fd) = split_path(rest?);
+    if dir != "fd" {
+        return None;
+    }
+    Some((pid, fd?.parse().ok()?))
+}
+
+/// Returns the target of the link to the file descriptor `fd` of the process
+/// `pid`, or `None` if the descriptor is not open.
+pub(crate) fn fd_link_target(pid: u64, fd: u32) -> Option<String> {
+    let fds = PROVIDER.get()?.fds(pid)?;
+    fds.into_iter()
+        .find(|(open_fd, _)| *open_fd == fd)
+        .map(|(_, target)| target)
+}
+
+/// Returns `Ok` if the process `pid` still exists. Once it has been reaped,
+/// the entries of its directory vanish, even for those holding them open.
+fn check_process(pid: u64) -> VfsResult {
+    match PROVIDER.get().and_then(|provider| provider.owner(pid)) {
+        Some(_) => Ok(()),
+        None => Err(VfsError::NotFound),
+    }
+}
+
+/// The process information filesystem.
+pub struct ProcFileSystem {
+    ram: RamFileSystem,
+    root: Arc<ProcRootDir>,
+}
+
+impl ProcFileSystem {
+    /// Creates the filesystem whose static entries are in `ram`.
+    pub fn new(ram: RamFileSystem) -> Self {
+        let root = Arc::new_cyclic(|this| ProcRootDir {
+            this: this.clone(),
+            ram: ram.root_dir_node(),
+        });
+        Self { ram, root }
+    }
+}
+
+impl VfsOps for ProcFileSystem {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.ram.mount(path, mount_point)
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// The root directory, which adds the process directories to the static ones.
+struct ProcRootDir {
+    this: Weak<ProcRootDir>,
+    ram: Arc<DirNode>,
+}
+
+impl ProcRootDir {
+    /// Resolves `self` or a PID to a process directory.
+    fn process_dir(&self, name: &str) -> Option<VfsNodeRef> {
+        let provider = PROVIDER.get()?;
+        let pid = match name {
+            "self" => provider.current_pid(),
+            _ => name
+                .parse()
+                .ok()
+                .filter(|pid| provider.pids().contains(pid))?,
+        };
+        Some(Arc::new(ProcessDir {
+            pid,
+            parent: self.this.clone(),
+        }))
+    }
+
+    fn entries(&self) -> Vec<(String, VfsNodeType)> {
+        let mut entries = Vec::new();
+        for name in self.ram.get_entries() {
+            if let Ok(node) = self.ram.clone().lookup(&name) {
+                let ty = node
+                    .get_attr()
+                    .map_or(VfsNodeType::File, |attr| attr.file_type());
+                entries.push((name, ty));
+            }
+        }
+        entries.push(("sys".into(), VfsNodeType::Dir));
+        for (name, ..) in GENERATED_FILES {
+            entries.push((name.to_string(), VfsNodeType::File));
+        }
+        for (name, ..) in KERNEL_FILES.read().iter() {
+            entries.push((name.to_string(), VfsNodeType::File));
+        }
+        if let Some(provider) = PROVIDER.get() {
+            entries.push(("self".into(), VfsNodeType::Dir));
+            for pid in provider.pids() {
+                entries.push((pid.to_string(), VfsNodeType::Dir));
+            }
+        }
+        entries
+    }
+}
+
+impl VfsNodeOps for ProcRootDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.ram.get_attr()
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.ram.parent()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        match self.process_dir(name) {
+            Some(dir) => match rest {
+                Some(rest) => dir.lookup(rest),
+                None => Ok(dir),
+            },
+            None if name.is_empty() || name == "." => match rest {
+                Some(rest) => self.lookup(rest),
+                None => Ok(self),
+            },
+            None if name == "sys" => {
+                let dir = Arc::new(SysctlDir {
+                    dir: String::new(),
+                    root: self.this.clone(),
+                });
+                match rest {
+                    Some(rest) => dir.lookup(rest),
+                    None => Ok(dir),
+                }
+            }
+            None => match generated_file(name) {
+                Some(file) if rest.is_none() => Ok(Arc::new(file)),
+                _ => self.ram.clone().lookup(path),
+            },
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        fill_dirents(&self.entries(), start_idx, dirents)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        self.ram.create(path, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        self.ram.remove(path)
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// The directory `/proc/[pid]`.
+struct ProcessDir {
+    pid: u64,
+    parent: Weak<ProcRootDir>,
+}
+
+impl VfsNodeOps for ProcessDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        check_process(self.pid)?;
+        Ok(VfsNodeAttr::new_dir(0, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.upgrade().map(|parent| parent as VfsNodeRef)
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node: VfsNodeRef = match name {
+            "" | "." => self.clone(),
+            ".." => self.parent().ok_or(VfsError::NotFound)?,
+            "fd" => {
+                check_process(self.pid)?;
+                Arc::new(FdDir {
+                    pid: self.pid,
+                    root: self.parent.clone(),
+                })
+            }
+            _ => {
+                check_process(self.pid)?;
+                let provider = PROVIDER.get().ok_or(VfsError::NotFound)?;
+                let name = provider
+                    .files()
+                    .iter()
+                    .find(|file| **file == name)
+                    .ok_or(VfsError::NotFound)?;
+                Arc::new(ProcessFile {
+                    pid: self.pid,
+                    name,
+                })
+            }
+        };
+        match rest {
+            Some(rest) => node.lookup(rest),
+            None => Ok(node),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        check_process(self.pid)?;
+        let mut entries: Vec<_> = PROVIDER
+            .get()
+            .map_or(&[][..], |provider| provider.files())
+            .iter()
+            .map(|name| (name.to_string(), VfsNodeType::File))
+            .collect();
+        entries.push(("fd".into(), VfsNodeType::Dir));
+        fill_dirents(&entries, start_idx, dirents)
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// The directory `/proc/[pid]/fd`.
+struct FdDir {
+    pid: u64,
+    root: Weak<ProcRootDir>,
+}
+
+impl VfsNodeOps for FdDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        check_process(self.pid)?;
+        // Like Linux, only the process may list its descriptors.
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o500),
+            VfsNodeType::Dir,
+            0,
+            0,
+        ))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        Some(Arc::new(ProcessDir {
+            pid: self.pid,
+            parent: self.root.clone(),
+        }))
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        match name {
+            "" | "." => match rest {
+                Some(rest) => self.lookup(rest),
+                None => Ok(self),
+            },
+            ".." => {
+                let parent = self.parent().ok_or(VfsError::NotFound)?;
+                match rest {
+                    Some(rest) => parent.lookup(rest),
+                    None => Ok(parent),
+                }
+            }
+            // The links are not followed here, see `crate::fd_link`.
+            _ if rest.is_some() => Err(VfsError::NotADirectory),
+            _ => {
+                let fd = name.parse().map_err(|_| VfsError::NotFound)?;
+                fd_link_target(self.pid, fd).ok_or(VfsError::NotFound)?;
+                Ok(Arc::new(FdLink { pid: self.pid, fd }))
+            }
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let fds = PROVIDER
+            .get()
+            .and_then(|provider| provider.fds(self.pid))
+            .ok_or(VfsError::NotFound)?;
+        let entries: Vec<_> = fds
+            .into_iter()
+            .map(|(fd, _)| (fd.to_string(), VfsNodeType::SymLink))
+            .collect();
+        fill_dirents(&entries, start_idx, dirents)
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// A link `/proc/[pid]/fd/N` to an open file of a process. Its target is
+/// given by [`fd_link_target`], and opening it opens the file itself, which is
+/// done by the kernel.
+struct FdLink {
+    pid: u64,
+    fd: u32,
+}
+
+impl VfsNodeOps for FdLink {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let target = fd_link_target(self.pid, self.fd).ok_or(VfsError::NotFound)?;
+        // Linux gives the link the permissions of the access mode of the file.
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o700),
+            VfsNodeType::SymLink,
+            target.len() as u64,
+            0,
+        ))
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// A file in `/proc/[pid]`, rendered on every read.
+struct ProcessFile {
+    pid: u64,
+    name: &'static str,
+}
+
+impl VfsNodeOps for ProcessFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        check_process(self.pid)?;
+        // Like Linux, the size is unknown until the file is read.
+        Ok(VfsNodeAttr::new_file(0, 0))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = PROVIDER
+            .get()
+            .and_then(|provider| provider.render(self.pid, self.name))
+            .ok_or(VfsError::NotFound)?;
+        Ok(read_content(&content, offset, buf))
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// A directory in `/proc/sys`, `dir` being its path in `/proc/sys`.
+struct SysctlDir {
+    dir: String,
+    root: Weak<ProcRootDir>,
+}
+
+impl SysctlDir {
+    /// Returns the path in `/proc/sys` of the entry `name` in the directory.
+    fn child(&self, name: &str) -> String {
+        match self.dir.as_str() {
+            "" => name.into(),
+            dir => format!("{dir}/{name}"),
+        }
+    }
+}
+
+impl VfsNodeOps for SysctlDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o555),
+            VfsNodeType::Dir,
+            0,
+            0,
+        ))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        match self.dir.rsplit_once('/') {
+            Some((parent, _)) => Some(Arc::new(SysctlDir {
+                dir: parent.into(),
+                root: self.root.clone(),
+            })),
+            None if self.dir.is_empty() => self.root.upgrade().map(|root| root as VfsNodeRef),
+            None => Some(Arc::new(SysctlDir {
+                dir: String::new(),
+                root: self.root.clone(),
+            })),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node: VfsNodeRef = match name {
+            "" | "." => self.clone(),
+            ".." => self.parent().ok_or(VfsError::NotFound)?,
+            _ => {
+                let path = self.child(name);
+                if crate::sysctl::writable(&path).is_some() {
+                    if rest.is_some_and(|rest| !rest.is_empty()) {
+                        return Err(VfsError::NotADirectory);
+                    }
+                    Arc::new(SysctlFile { path })
+                } else if crate::sysctl::entries(&path).is_some() {
+                    Arc::new(SysctlDir {
+                        dir: path,
+                        root: self.root.clone(),
+                    })
+                } else {
+                    return Err(VfsError::NotFound);
+                }
+            }
+        };
+        match rest {
+            Some(rest) => node.lookup(rest),
+            None => Ok(node),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let entries: Vec<_> = crate::sysctl::entries(&self.dir)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, is_dir)| match is_dir {
+                true => (name, VfsNodeType::Dir),
+                false => (name, VfsNodeType::File),
+            })
+            .collect();
+        fill_dirents(&entries, start_idx, dirents)
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// A tunable in `/proc/sys`, `path` being its path in `/proc/sys`.
+struct SysctlFile {
+    path: String,
+}
+
+impl VfsNodeOps for SysctlFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = match crate::sysctl::writable(&self.path).ok_or(VfsError::NotFound)? {
+            true => 0o644,
+            false => 0o444,
+        };
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(perm),
+            VfsNodeType::File,
+            0,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = crate::sysctl::read(&self.path).ok_or(VfsError::NotFound)?;
+        Ok(read_content(content.as_bytes(), offset, buf))
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        crate::sysctl::write(&self.path, buf)?;
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        // Like the files registered by the kernel, `O_TRUNC` has no effect.
+        match crate::sysctl::writable(&self.path) {
+            Some(true) => Ok(()),
+            _ => Err(VfsError::PermissionDenied),
+        }
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// A file in `/proc` rendered on every read by the filesystem module or the
+/// kernel.
+struct GeneratedFile {
+    render: fn() -> String,
+    write: Option<ProcFileWrite>,
+}
+
+impl VfsNodeOps for GeneratedFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_file(0, 0))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        Ok(read_content((self.render)().as_bytes(), offset, buf))
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let write = self.write.ok_or(VfsError::PermissionDenied)?;
+        write(buf)?;
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        // Opening with `O_TRUNC` before writing is common, and has no effect.
+        match self.write {
+            Some(_) => Ok(()),
+            None => Err(VfsError::PermissionDenied),
+        }
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// Copies the part of `content` from `offset` to `buf`, returns the number of
+/// bytes copied.
+/// Renders `/proc/meminfo` from the frame allocator in the format of Linux.
+///
+/// `MemAvailable` leaves out the free frames reserved for the kernel, and
+/// `MemPeak` is the most memory ever in use.
+fn render_meminfo() -> String {
+    let alloc = axalloc::global_allocator();
+    let kb = |pages: usize| pages * axhal::mem::PAGE_SIZE_4K / 1024;
+    let free = alloc.available_pages();
+    let mut out = String::new();
+    for (name, pages) in [
+        ("MemTotal", alloc.total_pages()),
+        ("MemFree", free),
+        ("MemAvailable", free.saturating_sub(alloc.min_free_pages())),
+        ("MemPeak", alloc.peak_used_pages()),
+        ("Buffers", 0),
+        ("Cached", 0),
+        ("SwapTotal", 0),
+        ("SwapFree", 0),
+    ] {
+        out += &format!("{:<16}{:>8} kB\n", format!("{name}:"), kb(pages));
+    }
+    out
+}
+
+fn read_content(content: &[u8], offset: u64, buf: &mut [u8]) -> usize {
+    let start = content.len().min(offset as usize);
+    let end = content.len().min(start + buf.len());
+    buf[..end - start].copy_from_slice(&content[start..end]);
+    end - start
+}
+
+/// Fills `dirents` with `.`, `..` and `entries`, starting from `start_idx`.
+fn fill_dirents(
+    entries: &[(String, VfsNodeType)],
+    start_idx: usize,
+    dirents: &mut [VfsDirEntry],
+) -> VfsResult<usize> {
+    let mut entries = entries.iter().skip(start_idx.max(2) - 2);
+    for (i, ent) in dirents.iter_mut().enumerate() {
+        match i + start_idx {
+            0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+            1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+            _ => match entries.next() {
+                Some((name, ty)) => *ent = VfsDirEntry::new(name, *ty),
+                None => return Ok(i),
+            },
+        }
+    }
+    Ok(dirents.len())
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
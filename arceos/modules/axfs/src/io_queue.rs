@@ -0,0 +1,148 @@
+//! Ordering of the requests to a block device by I/O priority.
+//!
+//! All the ranges of a device share one [`RequestQueue`], which runs one
+//! request on the device at a time. When the device is busy, the requests that
+//! arrive wait in the queue, and the next one to run is taken by the
+//! [`IoClass`] of the task that submitted it: real-time requests go before
+//! best-effort ones, which go before idle ones. Requests of the same class run
+//! in the order they were submitted.
+//!
+//! The class of the current task is given by the [`IoPriorityProvider`]
+//! registered by the kernel. Until it is called, every request is best-effort
+//! and the queue is first come, first served.
+
+use alloc::collections::BTreeSet;
+
+use axsync::Mutex;
+use spin::Once;
+
+/// The scheduling class of I/O requests, from the highest priority to the
+/// lowest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IoClass {
+    /// Served before any other class.
+    RealTime,
+    /// The default class.
+    BestEffort,
+    /// Served only when no request of another class is waiting.
+    Idle,
+}
+
+/// Gives the I/O class of the current task.
+pub trait IoPriorityProvider: Send + Sync {
+    /// Returns the class of the requests submitted by the current task.
+    fn current_class(&self) -> IoClass;
+}
+
+static PROVIDER: Once<&'static dyn IoPriorityProvider> = Once::new();
+
+/// Registers the provider of the I/O class of tasks.
+pub fn register_io_priority(provider: &'static dyn IoPriorityProvider) {
+    PROVIDER.call_once(|| provider);
+}
+
+fn current_class() -> IoClass {
+    PROVIDER
+        .get()
+        .map_or(IoClass::BestEffort, |provider| provider.current_class())
+}
+
+/// A request waiting for the device, which is served in the order of the
+/// class and then the submission number.
+type Ticket = (IoClass, u64);
+
+/// The requests waiting for a device.
+#[derive(Default)]
+struct Pending {
+    waiting: BTreeSet<Ticket>,
+    next_seq: u64,
+    busy: bool,
+}
+
+impl Pending {
+    /// Adds a request of `class` to the queue.
+    fn push(&mut self, class: IoClass) -> Ticket {
+        let ticket = (class, self.next_seq);
+        self.next_seq += 1;
+        self.waiting.insert(ticket);
+        ticket
+    }
+
+    /// Takes the device for `ticket` if it is free and no request goes before
+    /// it.
+    fn try_start(&mut self, ticket: Ticket) -> bool {
+        if self.busy || self.waiting.first() != Some(&ticket) {
+            return false;
+        }
+        self.waiting.remove(&ticket);
+        self.busy = true;
+        true
+    }
+
+    /// Frees the device after a request completes.
+    fn finish(&mut self) {
+        self.busy = false;
+    }
+}
+
+/// A device whose requests are served in the order of their I/O priority.
+pub(crate) struct RequestQueue<T> {
+    pending: Mutex<Pending>,
+    dev: Mutex<T>,
+}
+
+impl<T> RequestQueue<T> {
+    pub fn new(dev: T) -> Self {
+        Self {
+            pending: Mutex::new(Pending::default()),
+            dev: Mutex::new(dev),
+        }
+    }
+
+    /// Runs the request `f` on the device once all the requests that go
+    /// before it have completed.
+    pub fn submit<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let ticket = self.pending.lock().push(current_class());
+        while !self.pending.lock().try_start(ticket) {
+            axtask::yield_now();
+        }
+        let ret = f(&mut self.dev.lock());
+        self.pending.lock().finish();
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn real_time_goes_before_idle_backlog() {
+        let mut pending = Pending::default();
+        let running = pending.push(IoClass::Idle);
+        assert!(pending.try_start(running));
+        let mut submitted: Vec<_> = (0..4).map(|_| pending.push(IoClass::Idle)).collect();
+        submitted.push(pending.push(IoClass::BestEffort));
+        submitted.push(pending.push(IoClass::RealTime));
+
+        // Nothing starts while the device is busy.
+        assert!(submitted.iter().all(|&ticket| !pending.try_start(ticket)));
+        pending.finish();
+
+        // Every waiting request tries to start, and only one of them can.
+        let mut order = Vec::new();
+        for _ in 0..submitted.len() {
+            let started: Vec<_> = submitted
+                .iter()
+                .copied()
+                .filter(|&ticket| pending.try_start(ticket))
+                .collect();
+            assert_eq!(started.len(), 1);
+            order.push(started[0]);
+            pending.finish();
+        }
+        let mut expected = vec![submitted[5], submitted[4]];
+        expected.extend_from_slice(&submitted[..4]);
+        assert_eq!(order, expected);
+    }
+}
@@ -6,10 +6,14 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!    is **enabled** by default.
-//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
-//!    **enabled** by default.
+//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`, with a file for
+//!    each block device and each character device registered in
+//!    [`axdriver::chardev`]. This feature is **enabled** by default.
 //! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
 //!    **enabled** by default.
+//! - `procfs`: Mount the process information filesystem on `/proc`. The kernel
+//!    provides the per-process directories via [`register_process_info`]. This
+//!    feature is **enabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!    default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!    to create and initialize other filesystems. This feature is **disabled** by
@@ -26,22 +30,91 @@
 extern crate log;
 extern crate alloc;
 
+mod block_cache;
+mod clock;
+mod dentry_cache;
 mod dev;
 mod fs;
+mod io_queue;
+mod mount_stats;
 mod mounts;
+mod notify;
+mod partition;
 mod root;
+mod sysctl;
 
 pub mod api;
 pub mod fops;
-pub use root::{mount, umount, CURRENT_DIR, CURRENT_DIR_PATH};
+pub mod path;
+pub use block_cache::{block_cache_stats, set_block_cache, BlockCacheStats, ReadAdvice};
+pub use clock::register_clock;
+pub use dentry_cache::{dentry_cache_stats, DentryCacheStats};
+pub use fops::sync_all;
+pub use io_queue::{register_io_priority, IoClass, IoPriorityProvider};
+pub use mount_stats::{mounts, FsSpace, MountFlags, MountInfo};
+pub use notify::{notify, register_fs_listener, FsEvent, FsListener};
+pub use root::{
+    dir_generation, fd_link, fd_link_target, is_read_only, mount, mount_info, owner, remount,
+    umount, CURRENT_DIR, CURRENT_DIR_PATH, CURRENT_ROOT_PATH,
+};
+pub use sysctl::{register_sysctl, FnTunable, IntTunable, Tunable};
+
+#[cfg(feature = "procfs")]
+pub use fs::procfs::{
+    register_proc_file, register_process_info, ProcFileWrite, ProcessInfoProvider,
+};
 
+use alloc::{format, sync::Arc, vec::Vec};
 use axdriver::{prelude::*, AxDeviceContainer};
 
+use self::{block_cache::CachedDevice, dev::Disk, io_queue::RequestQueue, partition::BlockRange};
+
 /// Initializes filesystems by block devices.
+///
+/// Block devices are named `vda`, `vdb`, ... in probe order, and their
+/// partitions `vda1`, `vda2`, ... The root filesystem is on the first partition
+/// of the first device, or on the whole device if it has no partition table.
+/// With the `devfs` feature, all devices and partitions are exposed in `/dev`
+/// so that they can be mounted later.
 pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
     info!("Initialize filesystems...");
 
-    let dev = blk_devs.take_one().expect("No block device found!");
-    info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    let mut disks = Vec::new();
+    let mut root_idx = None;
+    let mut dev_idx = 0;
+    while let Some(dev) = blk_devs.take_one() {
+        let name = format!("vd{}", (b'a' + dev_idx as u8) as char).leak();
+        info!("  found block device {}: {:?}", name, dev.device_name());
+        let dev = Arc::new(RequestQueue::new(CachedDevice::new(dev)));
+        let mut whole = BlockRange::whole(name, dev.clone());
+        let parts = partition::scan(&mut whole).unwrap_or_else(|err| {
+            warn!(
+                "  failed to read the partition table of {}: {:?}",
+                name, err
+            );
+            Vec::new()
+        });
+        if dev_idx == 0 {
+            root_idx = Some(if parts.is_empty() { 0 } else { 1 });
+        }
+        disks.push(Disk::new(whole));
+        for part in &parts {
+            let part_name = format!("{}{}", name, part.number).leak();
+            info!(
+                "    partition {}: {} blocks from block {}",
+                part_name, part.num_blocks, part.start
+            );
+            disks.push(Disk::new(BlockRange::partition(
+                part_name,
+                dev.clone(),
+                part,
+            )));
+        }
+        dev_idx += 1;
+    }
+
+    let root_idx = root_idx.expect("No block device found!");
+    let root = disks.remove(root_idx);
+    info!("  use {} as the root filesystem", root.name());
+    self::root::init_rootfs(root, disks);
 }
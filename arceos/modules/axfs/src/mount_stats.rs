@@ -0,0 +1,182 @@
+//! Statistics of the mounted filesystems.
+//!
+//! Each mount point counts the files open on it and the bytes read from and
+//! written to them since it was mounted. The counters are relaxed atomics
+//! updated on every read and write, and are reported by [`mounts`] and
+//! `/proc/mounts` together with the mount flags and the space of the
+//! filesystem.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// The space of a filesystem, counted in blocks of `block_size` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsSpace {
+    /// The size of a block in bytes.
+    pub block_size: u64,
+    /// The total number of blocks.
+    pub blocks: u64,
+    /// The number of free blocks.
+    pub free_blocks: u64,
+}
+
+/// A filesystem that knows how much space it has.
+pub(crate) trait SpaceInfo: Send + Sync {
+    /// Returns the current space of the filesystem, or `None` if it cannot be
+    /// read.
+    fn space(&self) -> Option<FsSpace>;
+}
+
+/// The counters of a mount point.
+#[derive(Debug)]
+pub(crate) struct MountStats {
+    open_files: AtomicUsize,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    /// The fewest free blocks seen, `u64::MAX` until the space is first read.
+    min_free_blocks: AtomicU64,
+}
+
+impl MountStats {
+    pub const fn new() -> Self {
+        Self {
+            open_files: AtomicUsize::new(0),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            min_free_blocks: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// Counts a file opened on the filesystem.
+    pub fn file_opened(&self) {
+        self.open_files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a file closed on the filesystem.
+    pub fn file_closed(&self) {
+        self.open_files.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Counts `n` bytes read from a file on the filesystem.
+    pub fn add_read(&self, n: usize) {
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// Counts `n` bytes written to a file on the filesystem.
+    pub fn add_written(&self, n: usize) {
+        self.bytes_written.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// Reads `space` of the filesystem, lowering the low-water mark of the free
+    /// blocks if needed.
+    pub fn observe_space(&self, space: Option<FsSpace>) -> Option<FsSpace> {
+        if let Some(space) = space {
+            self.min_free_blocks
+                .fetch_min(space.free_blocks, Ordering::Relaxed);
+        }
+        space
+    }
+}
+
+/// Information about a mounted filesystem.
+#[derive(Debug, Clone)]
+pub struct MountInfo {
+    /// The path of the mount point in the whole filesystem tree.
+    pub path: String,
+    /// The mounted device or image file, or the type of a virtual filesystem.
+    pub source: String,
+    /// The type of the filesystem, as in `/proc/mounts`.
+    pub fs_type: &'static str,
+    /// Whether the filesystem is mounted read-only.
+    pub read_only: bool,
+    /// Whether the filesystem is mounted with `nosuid`.
+    pub nosuid: bool,
+    /// The current space of the filesystem, if it has any.
+    pub space: Option<FsSpace>,
+    /// The number of files open on the filesystem.
+    pub open_files: usize,
+    /// The bytes read from files on the filesystem since it was mounted.
+    pub bytes_read: u64,
+    /// The bytes written to files on the filesystem since it was mounted.
+    pub bytes_written: u64,
+    /// The fewest free blocks seen since the filesystem was mounted. The space
+    /// is read whenever the information is collected, so this is the
+    /// low-water mark over those readings.
+    pub min_free_blocks: Option<u64>,
+}
+
+impl MountInfo {
+    pub(crate) fn new(
+        path: &str,
+        source: &str,
+        fs_type: &'static str,
+        flags: MountFlags,
+        space: Option<FsSpace>,
+        stats: &MountStats,
+    ) -> Self {
+        let space = stats.observe_space(space);
+        let min_free_blocks = stats.min_free_blocks.load(Ordering::Relaxed);
+        Self {
+            path: path.to_string(),
+            source: source.to_string(),
+            fs_type,
+            read_only: flags.read_only,
+            nosuid: flags.nosuid,
+            space,
+            open_files: stats.open_files.load(Ordering::Relaxed),
+            bytes_read: stats.bytes_read.load(Ordering::Relaxed),
+            bytes_written: stats.bytes_written.load(Ordering::Relaxed),
+            min_free_blocks: (min_free_blocks != u64::MAX).then_some(min_free_blocks),
+        }
+    }
+}
+
+/// Flags of a mount.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MountFlags {
+    /// Files on the filesystem cannot be modified.
+    pub read_only: bool,
+    /// Set-user-ID and set-group-ID bits are ignored on the filesystem.
+    pub nosuid: bool,
+}
+
+/// Returns the information of all the mounted filesystems, starting with the
+/// root.
+pub fn mounts() -> Vec<MountInfo> {
+    crate::root::mount_infos()
+}
+
+/// Renders `/proc/mounts`.
+///
+/// Each line has the six columns of Linux, followed by the number of open
+/// files, the bytes read and written since the filesystem was mounted and the
+/// low-water mark of its free blocks, or `-` if its space is unknown.
+pub(crate) fn render_mounts() -> String {
+    let mut out = String::new();
+    for info in mounts() {
+        let mut options = String::from(if info.read_only { "ro" } else { "rw" });
+        if info.nosuid {
+            options.push_str(",nosuid");
+        }
+        let min_free = info
+            .min_free_blocks
+            .map_or_else(|| "-".to_string(), |blocks| blocks.to_string());
+        let _ = writeln!(
+            out,
+            "{} {} {} {} 0 0 {} {} {} {}",
+            info.source,
+            info.path,
+            info.fs_type,
+            options,
+            info.open_files,
+            info.bytes_read,
+            info.bytes_written,
+            min_free
+        );
+    }
+    out
+}
@@ -4,7 +4,7 @@ use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
 use crate::fs;
 
 #[cfg(feature = "devfs")]
-pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
+pub(crate) fn devfs(disks: alloc::vec::Vec<crate::dev::Disk>) -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
     let zero = fs::devfs::ZeroDev;
     let bar = fs::devfs::ZeroDev;
@@ -13,6 +13,13 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     devfs.add("null", Arc::new(null));
     devfs.add("zero", Arc::new(zero));
     foo_dir.add("bar", Arc::new(bar));
+    for disk in disks {
+        devfs.add(disk.name(), Arc::new(crate::dev::BlockDevFile::new(disk)));
+    }
+    for dev in axdriver::chardev::char_devices() {
+        let name = alloc::string::String::from(dev.name()).leak();
+        devfs.add(name, Arc::new(crate::dev::CharDevFile::new(dev)));
+    }
     Arc::new(devfs)
 }
 
@@ -22,29 +29,19 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
 }
 
 #[cfg(feature = "procfs")]
-pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
+pub(crate) fn procfs() -> VfsResult<Arc<fs::procfs::ProcFileSystem>> {
     let procfs = fs::ramfs::RamFileSystem::new();
-    let proc_root = procfs.root_dir();
 
-    // Create /proc/sys/net/core/somaxconn
-    proc_root.create("sys", VfsNodeType::Dir)?;
-    proc_root.create("sys/net", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core/somaxconn", VfsNodeType::File)?;
-    let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
-    file_somaxconn.write_at(0, b"4096\n")?;
+    // /proc/sys/net/core/somaxconn, which nothing consults yet
+    static SOMAXCONN: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(4096);
+    crate::register_sysctl(
+        "net/core/somaxconn",
+        crate::IntTunable::new(&SOMAXCONN, 0..=i32::MAX as usize),
+    );
 
-    // Create /proc/sys/vm/overcommit_memory
-    proc_root.create("sys/vm", VfsNodeType::Dir)?;
-    proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
-    let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
-    file_over.write_at(0, b"0\n")?;
-
-    // Create /proc/self/stat
-    proc_root.create("self", VfsNodeType::Dir)?;
-    proc_root.create("self/stat", VfsNodeType::File)?;
-
-    Ok(Arc::new(procfs))
+    // /proc/sys lists the registered tunables, and /proc/self and /proc/[pid]
+    // are provided by the kernel
+    Ok(Arc::new(fs::procfs::ProcFileSystem::new(procfs)))
 }
 
 #[cfg(feature = "sysfs")]
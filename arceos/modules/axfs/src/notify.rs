@@ -0,0 +1,51 @@
+//! Notification of changes in the filesystem tree.
+//!
+//! The kernel registers an [`FsListener`] to learn about the changes made
+//! through the filesystem API, e.g. to implement `inotify`. Entries created,
+//! removed or renamed are reported by this module itself; the layers above
+//! report the changes to the contents and attributes of files with [`notify`].
+//!
+//! Paths in the events are absolute paths in the whole filesystem tree.
+
+use spin::Once;
+
+/// A change in the filesystem tree.
+#[derive(Debug, Clone, Copy)]
+pub enum FsEvent<'a> {
+    /// A file or directory was created at `path`.
+    Created { path: &'a str, is_dir: bool },
+    /// The file or directory at `path` was removed.
+    Removed { path: &'a str, is_dir: bool },
+    /// The file or directory at `old` was moved to `new`.
+    Renamed {
+        old: &'a str,
+        new: &'a str,
+        is_dir: bool,
+    },
+    /// The contents of the file at `path` were written or truncated.
+    Modified { path: &'a str },
+    /// The attributes of the file at `path`, such as its times, were changed.
+    AttribChanged { path: &'a str },
+    /// A file opened for writing at `path` was closed.
+    ClosedWrite { path: &'a str },
+}
+
+/// Receives the changes in the filesystem tree.
+pub trait FsListener: Send + Sync {
+    /// Called after `event` has happened.
+    fn notify(&self, event: &FsEvent);
+}
+
+static LISTENER: Once<&'static dyn FsListener> = Once::new();
+
+/// Registers the listener of the changes in the filesystem tree.
+pub fn register_fs_listener(listener: &'static dyn FsListener) {
+    LISTENER.call_once(|| listener);
+}
+
+/// Reports `event` to the registered listener.
+pub fn notify(event: FsEvent) {
+    if let Some(listener) = LISTENER.get() {
+        listener.notify(&event);
+    }
+}
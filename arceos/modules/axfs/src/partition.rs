@@ -0,0 +1,317 @@
+//! Partition table scanning.
+//!
+//! Each probed block device is scanned for an MBR partition table, and for a
+//! GPT if the MBR is a protective one. Every partition found becomes a
+//! [`BlockRange`] of the device, which is used the same way as a whole disk.
+//!
+//! Checksums of the GPT are not verified, and extended MBR partitions are not
+//! followed.
+
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use axdriver::prelude::*;
+use axdriver_block::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+use crate::{block_cache::CachedDevice, io_queue::RequestQueue};
+
+const BLOCK_SIZE: usize = 512;
+
+/// The MBR partition type of a protective MBR in front of a GPT.
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xee;
+/// The maximum number of GPT entries that are read.
+const GPT_MAX_ENTRIES: usize = 128;
+
+/// A partition found in a partition table, in units of blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partition {
+    /// The partition number, starting from 1. For MBR it is the slot of the
+    /// entry in the table, so there may be gaps.
+    pub number: usize,
+    /// The first block of the partition.
+    pub start: u64,
+    /// The number of blocks of the partition.
+    pub num_blocks: u64,
+}
+
+/// Reads the partition table of `dev`.
+///
+/// Returns an empty list if the device has no partition table, e.g. it holds
+/// a filesystem directly.
+pub fn scan<D: BlockDriverOps + ?Sized>(dev: &mut D) -> DevResult<Vec<Partition>> {
+    if dev.block_size() != BLOCK_SIZE {
+        return Err(DevError::Unsupported);
+    }
+    let mut mbr = [0u8; BLOCK_SIZE];
+    dev.read_block(0, &mut mbr)?;
+    if mbr[510..512] != [0x55, 0xaa] || is_fat_boot_sector(&mbr) {
+        return Ok(Vec::new());
+    }
+
+    let total = dev.num_blocks();
+    let mut parts = Vec::new();
+    for slot in 0..4 {
+        let entry = &mbr[446 + slot * 16..446 + (slot + 1) * 16];
+        let (boot_flag, ty) = (entry[0], entry[4]);
+        if boot_flag != 0 && boot_flag != 0x80 {
+            // Not a partition table, but maybe the boot code of a filesystem.
+            return Ok(Vec::new());
+        }
+        if ty == MBR_TYPE_GPT_PROTECTIVE {
+            return scan_gpt(dev);
+        }
+        let start = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as u64;
+        let num_blocks = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as u64;
+        if ty == 0 || num_blocks == 0 {
+            continue;
+        }
+        if start == 0 || start + num_blocks > total {
+            return Ok(Vec::new());
+        }
+        parts.push(Partition {
+            number: slot + 1,
+            start,
+            num_blocks,
+        });
+    }
+    Ok(parts)
+}
+
+/// Reads the GPT whose header is at block 1.
+fn scan_gpt<D: BlockDriverOps + ?Sized>(dev: &mut D) -> DevResult<Vec<Partition>> {
+    let mut header = [0u8; BLOCK_SIZE];
+    dev.read_block(1, &mut header)?;
+    if &header[0..8] != b"EFI PART" {
+        warn!("Protective MBR without a GPT header");
+        return Ok(Vec::new());
+    }
+    let read_u32 = |off: usize| u32::from_le_bytes(header[off..off + 4].try_into().unwrap());
+    let entries_lba = u64::from_le_bytes(header[72..80].try_into().unwrap());
+    let num_entries = (read_u32(80) as usize).min(GPT_MAX_ENTRIES);
+    let entry_size = read_u32(84) as usize;
+    if entry_size < 128 || entry_size % 8 != 0 {
+        return Err(DevError::InvalidParam);
+    }
+
+    let total = dev.num_blocks();
+    let len = (num_entries * entry_size).div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+    if entries_lba + (len / BLOCK_SIZE) as u64 > total {
+        return Err(DevError::InvalidParam);
+    }
+    let mut entries = vec![0u8; len];
+    dev.read_block(entries_lba, &mut entries)?;
+
+    let mut parts = Vec::new();
+    for (i, entry) in entries
+        .chunks_exact(entry_size)
+        .take(num_entries)
+        .enumerate()
+    {
+        // An all-zero type GUID marks an unused entry.
+        if entry[0..16].iter().all(|&b| b == 0) {
+            continue;
+        }
+        let first = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+        let last = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+        if first == 0 || last < first || last >= total {
+            warn!("Ignoring invalid GPT entry {}", i);
+            continue;
+        }
+        parts.push(Partition {
+            number: i + 1,
+            start: first,
+            num_blocks: last - first + 1,
+        });
+    }
+    Ok(parts)
+}
+
+/// Whether `sector` is the boot sector of a FAT filesystem, which also ends
+/// with the `55 aa` signature.
+fn is_fat_boot_sector(sector: &[u8; BLOCK_SIZE]) -> bool {
+    let bytes_per_sector = u16::from_le_bytes([sector[11], sector[12]]);
+    let reserved = u16::from_le_bytes([sector[14], sector[15]]);
+    let (fats, media) = (sector[16], sector[21]);
+    matches!(sector[0], 0xeb | 0xe9)
+        && bytes_per_sector.is_power_of_two()
+        && (512..=4096).contains(&bytes_per_sector)
+        && reserved != 0
+        && (1..=2).contains(&fats)
+        && (media == 0xf0 || media >= 0xf8)
+}
+
+/// A range of blocks of a block device shared with other ranges, such as the
+/// whole disk or one of its partitions.
+pub struct BlockRange {
+    name: &'static str,
+    dev: Arc<RequestQueue<CachedDevice>>,
+    start: u64,
+    num_blocks: u64,
+}
+
+impl BlockRange {
+    /// Creates the range of the whole device.
+    pub fn whole(name: &'static str, dev: Arc<RequestQueue<CachedDevice>>) -> Self {
+        let num_blocks = dev.submit(|dev| dev.num_blocks());
+        Self {
+            name,
+            dev,
+            start: 0,
+            num_blocks,
+        }
+    }
+
+    /// Creates the range of a partition of the device.
+    pub fn partition(
+        name: &'static str,
+        dev: Arc<RequestQueue<CachedDevice>>,
+        part: &Partition,
+    ) -> Self {
+        Self {
+            name,
+            dev,
+            start: part.start,
+            num_blocks: part.num_blocks,
+        }
+    }
+
+    /// The device file name of the range, e.g. `vda1`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Translates `block_id` to the underlying device, checking that the
+    /// `len` bytes starting from it are within the range.
+    fn translate(&self, block_id: u64, len: usize) -> DevResult<u64> {
+        let end = block_id.checked_add(len.div_ceil(BLOCK_SIZE) as u64);
+        match end {
+            Some(end) if end <= self.num_blocks => Ok(self.start + block_id),
+            _ => Err(DevError::InvalidParam),
+        }
+    }
+}
+
+impl BaseDriverOps for BlockRange {
+    fn device_name(&self) -> &str {
+        self.name
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+}
+
+impl BlockDriverOps for BlockRange {
+    fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        let block_id = self.translate(block_id, buf.len())?;
+        self.dev.submit(|dev| dev.read_block(block_id, buf))
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        let block_id = self.translate(block_id, buf.len())?;
+        self.dev.submit(|dev| dev.write_block(block_id, buf))
+    }
+
+    fn flush(&mut self) -> DevResult {
+        self.dev.submit(|dev| dev.flush())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axdriver_block::ramdisk::RamDisk;
+
+    const DISK_BLOCKS: usize = 2048;
+
+    fn mbr_entry(disk: &mut [u8], slot: usize, ty: u8, start: u32, num_blocks: u32) {
+        let entry = &mut disk[446 + slot * 16..446 + (slot + 1) * 16];
+        entry[4] = ty;
+        entry[8..12].copy_from_slice(&start.to_le_bytes());
+        entry[12..16].copy_from_slice(&num_blocks.to_le_bytes());
+    }
+
+    fn new_image() -> Vec<u8> {
+        let mut disk = vec![0u8; DISK_BLOCKS * BLOCK_SIZE];
+        disk[510] = 0x55;
+        disk[511] = 0xaa;
+        disk
+    }
+
+    #[test]
+    fn mbr_two_partitions() {
+        let mut disk = new_image();
+        mbr_entry(&mut disk, 0, 0x0c, 48, 1000);
+        mbr_entry(&mut disk, 2, 0x83, 1048, 1000);
+        let parts = scan(&mut RamDisk::from(&disk)).unwrap();
+        assert_eq!(
+            parts,
+            [
+                Partition {
+                    number: 1,
+                    start: 48,
+                    num_blocks: 1000,
+                },
+                Partition {
+                    number: 3,
+                    start: 1048,
+                    num_blocks: 1000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gpt() {
+        let mut disk = new_image();
+        mbr_entry(
+            &mut disk,
+            0,
+            MBR_TYPE_GPT_PROTECTIVE,
+            1,
+            DISK_BLOCKS as u32 - 1,
+        );
+        let header = &mut disk[BLOCK_SIZE..2 * BLOCK_SIZE];
+        header[0..8].copy_from_slice(b"EFI PART");
+        header[72..80].copy_from_slice(&2u64.to_le_bytes());
+        header[80..84].copy_from_slice(&128u32.to_le_bytes());
+        header[84..88].copy_from_slice(&128u32.to_le_bytes());
+        for (i, (first, last)) in [(34u64, 1033u64), (1034, 2014)].into_iter().enumerate() {
+            let entry = &mut disk[2 * BLOCK_SIZE + i * 128..2 * BLOCK_SIZE + (i + 1) * 128];
+            entry[0..16].fill(0xaa);
+            entry[32..40].copy_from_slice(&first.to_le_bytes());
+            entry[40..48].copy_from_slice(&last.to_le_bytes());
+        }
+        let parts = scan(&mut RamDisk::from(&disk)).unwrap();
+        assert_eq!(
+            parts,
+            [
+                Partition {
+                    number: 1,
+                    start: 34,
+                    num_blocks: 1000,
+                },
+                Partition {
+                    number: 2,
+                    start: 1034,
+                    num_blocks: 981,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn no_partition_table() {
+        let disk = std::fs::read("resources/fat16.img").unwrap();
+        assert!(scan(&mut RamDisk::from(&disk)).unwrap().is_empty());
+        let disk = vec![0u8; DISK_BLOCKS * BLOCK_SIZE];
+        assert!(scan(&mut RamDisk::from(&disk)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Lexical path canonicalization shared by all path-based operations.
+
+use alloc::{string::String, vec::Vec};
+use axerrno::{ax_err, AxResult};
+
+/// Canonicalizes `path` relative to the absolute directory `base` for the
+/// current process.
+///
+/// Components are processed lexically: empty components and `.` are dropped,
+/// and `..` removes the previous component but stays at the root. Mount points
+/// are ordinary directories of the tree, so `..` at the root of a mounted
+/// filesystem leads to the directory containing the mount point.
+///
+/// Absolute paths start at the root directory of the process
+/// ([`CURRENT_ROOT_PATH`](crate::CURRENT_ROOT_PATH)), and `..` never leads
+/// above it. `base` and the result are paths in the whole filesystem tree.
+///
+/// The result ends with `/` if it is the root, or if `path` ends with `/`, `.`
+/// or `..` and thus must name a directory, which the lookup then checks.
+/// Returns `NotFound` if `path` is empty.
+pub fn canonicalize(base: &str, path: &str) -> AxResult<String> {
+    let root = crate::CURRENT_ROOT_PATH.lock().clone();
+    canonicalize_in(&root, base, path)
+}
+
+/// Same as [`canonicalize`], but with `root` as the root directory.
+pub fn canonicalize_in(root: &str, base: &str, path: &str) -> AxResult<String> {
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    fn components(path: &str) -> impl Iterator<Item = &str> {
+        path.split('/').filter(|part| !matches!(*part, "" | "."))
+    }
+    let root: Vec<_> = components(root).collect();
+    let mut parts: Vec<_> = if path.starts_with('/') {
+        root.clone()
+    } else {
+        components(base).collect()
+    };
+    // `..` stops at the root directory, unless it starts outside of it
+    let floor = if parts.starts_with(&root) {
+        root.len()
+    } else {
+        0
+    };
+    for part in components(path) {
+        match part {
+            ".." => {
+                if parts.len() > floor {
+                    parts.pop();
+                }
+            }
+            _ => parts.push(part),
+        }
+    }
+
+    let mut canonical = String::new();
+    for part in parts {
+        canonical.push('/');
+        canonical.push_str(part);
+    }
+    if canonical.is_empty() || matches!(path.rsplit('/').next(), Some("" | "." | "..")) {
+        canonical.push('/');
+    }
+    Ok(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_forms() {
+        let cases = [
+            ("/", "/", "/"),
+            ("/", ".", "/"),
+            ("/", "..", "/"),
+            ("/", "/..", "/"),
+            ("/", "../../..", "/"),
+            ("/", "a", "/a"),
+            ("/", "a/", "/a/"),
+            ("/", "a//b", "/a/b"),
+            ("/", "///a///b///", "/a/b/"),
+            ("/", "./a", "/a"),
+            ("/", "a/.", "/a/"),
+            ("/", "a/..", "/"),
+            ("/", "a/../b", "/b"),
+            ("/", "a/./b/./c", "/a/b/c"),
+            ("/home/", "x", "/home/x"),
+            ("/home", "x", "/home/x"),
+            ("/home/user/", "../x", "/home/x"),
+            ("/home/user/", "../../../x", "/x"),
+            ("/home/user/", "/etc/passwd", "/etc/passwd"),
+            ("/home/user/", "subdir/../other//", "/home/user/other/"),
+            ("/home/user/", "./x", "/home/user/x"),
+            ("/home/user/", "..", "/home/"),
+            ("/tmp/", "../tmp/../dev/./null", "/dev/null"),
+            ("/dev/", "..//..//tmp", "/tmp"),
+        ];
+        for (base, path, expected) in cases {
+            assert_eq!(
+                canonicalize_in("/", base, path).as_deref(),
+                Ok(expected),
+                "canonicalize({base:?}, {path:?})"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_path() {
+        assert!(canonicalize_in("/", "/", "").is_err());
+        assert!(canonicalize_in("/", "/home/", "").is_err());
+    }
+
+    #[test]
+    fn chroot() {
+        let cases = [
+            ("/", "/", "/jail/"),
+            ("/", "/etc", "/jail/etc"),
+            ("/", "/..", "/jail/"),
+            ("/jail/", "../../etc", "/jail/etc"),
+            ("/jail/sub/", "..", "/jail/"),
+            ("/jail/sub/", "../../..", "/jail/"),
+            ("/jail/sub/", "x/../../../y", "/jail/y"),
+            // a current directory outside the root is not bounded by it
+            ("/home/", "../etc", "/etc"),
+        ];
+        for (base, path, expected) in cases {
+            assert_eq!(
+                canonicalize_in("/jail/", base, path).as_deref(),
+                Ok(expected),
+                "canonicalize({base:?}, {path:?}) in /jail"
+            );
+        }
+    }
+}
@@ -2,55 +2,130 @@
 //!
 //! TODO: it doesn't work very well if the mount points have containment relationships.
 
-use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
 use axerrno::{ax_err, AxError, AxResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
 use axns::{def_resource, AxResource};
 use axsync::Mutex;
+use core::sync::atomic::{AtomicBool, Ordering};
 use lazyinit::LazyInit;
 use spin::RwLock;
 
 use crate::{
     api::FileType,
-    dev::Disk,
-    fs::{self, fatfs::FileWrapper},
+    dentry_cache::{self, Dentry, DentryKey},
+    fs,
+    mount_stats::{MountFlags, MountInfo, MountStats, SpaceInfo},
     mounts,
+    notify::{notify, FsEvent},
 };
 
 def_resource! {
     #[allow(non_camel_case_types)]
-    pub static CURRENT_DIR_PATH: AxResource<Mutex<String>> = AxResource::new();
+    pub static CURRENT_DIR_PATH: AxResource<Arc<Mutex<String>>> = AxResource::new();
     #[allow(non_camel_case_types)]
-    pub static CURRENT_DIR: AxResource<Mutex<VfsNodeRef>> = AxResource::new();
+    pub static CURRENT_DIR: AxResource<Arc<Mutex<VfsNodeRef>>> = AxResource::new();
+    /// The root directory of the current process set by `chroot`, which ends
+    /// with `/`. Absolute paths from the process start here.
+    #[allow(non_camel_case_types)]
+    pub static CURRENT_ROOT_PATH: AxResource<Arc<Mutex<String>>> = AxResource::new();
 }
 
 impl CURRENT_DIR_PATH {
-    pub fn copy_inner(&self) -> Mutex<String> {
-        Mutex::new(self.lock().clone())
+    pub fn copy_inner(&self) -> Arc<Mutex<String>> {
+        Arc::new(Mutex::new(self.lock().clone()))
+    }
+
+    /// Shares the current directory with another process, as `CLONE_FS` does.
+    pub fn share_inner(&self) -> Arc<Mutex<String>> {
+        Arc::clone(self)
     }
 }
 
 impl CURRENT_DIR {
-    pub fn copy_inner(&self) -> Mutex<VfsNodeRef> {
-        Mutex::new(self.lock().clone())
+    pub fn copy_inner(&self) -> Arc<Mutex<VfsNodeRef>> {
+        Arc::new(Mutex::new(self.lock().clone()))
+    }
+
+    /// Shares the current directory with another process, as `CLONE_FS` does.
+    pub fn share_inner(&self) -> Arc<Mutex<VfsNodeRef>> {
+        Arc::clone(self)
     }
 }
 
-struct MountPoint {
+impl CURRENT_ROOT_PATH {
+    pub fn copy_inner(&self) -> Arc<Mutex<String>> {
+        Arc::new(Mutex::new(self.lock().clone()))
+    }
+
+    /// Shares the root directory with another process, as `CLONE_FS` does.
+    pub fn share_inner(&self) -> Arc<Mutex<String>> {
+        Arc::clone(self)
+    }
+}
+
+pub(crate) struct MountPoint {
     path: &'static str,
     fs: Arc<dyn VfsOps>,
+    /// The mounted device or image file, or the type of a virtual filesystem.
+    source: String,
+    fs_type: &'static str,
+    read_only: AtomicBool,
+    nosuid: bool,
+    /// Reads the space of the filesystem, if it has any.
+    space: Option<Arc<dyn SpaceInfo>>,
+    stats: Arc<MountStats>,
 }
 
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
+    /// The main filesystem mounted at `/`.
+    root: MountPoint,
     mounts: RwLock<Vec<MountPoint>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(
+        path: &'static str,
+        fs: Arc<dyn VfsOps>,
+        source: &str,
+        fs_type: &'static str,
+        flags: MountFlags,
+    ) -> Self {
+        Self {
+            path,
+            fs,
+            source: source.into(),
+            fs_type,
+            read_only: AtomicBool::new(flags.read_only),
+            nosuid: flags.nosuid,
+            space: None,
+            stats: Arc::new(MountStats::new()),
+        }
+    }
+
+    /// Sets how to read the space of the filesystem.
+    pub fn with_space(mut self, space: Arc<dyn SpaceInfo>) -> Self {
+        self.space = Some(space);
+        self
+    }
+
+    fn info(&self) -> MountInfo {
+        let flags = MountFlags {
+            read_only: self.read_only.load(Ordering::Acquire),
+            nosuid: self.nosuid,
+        };
+        let space = self.space.as_ref().and_then(|space| space.space());
+        MountInfo::new(
+            self.path,
+            &self.source,
+            self.fs_type,
+            flags,
+            space,
+            &self.stats,
+        )
     }
 }
 
@@ -61,14 +136,16 @@ impl Drop for MountPoint {
 }
 
 impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
+    pub fn new(root: MountPoint) -> Self {
         Self {
-            main_fs,
+            main_fs: root.fs.clone(),
+            root,
             mounts: RwLock::new(Vec::new()),
         }
     }
 
-    pub fn mount(&self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    pub fn mount(&self, mp: MountPoint) -> AxResult {
+        let (path, fs) = (mp.path, &mp.fs);
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
@@ -81,12 +158,45 @@ impl RootDirectory {
         // create the mount point in the main filesystem if it does not exist
         self.main_fs.root_dir().create(path, FileType::Dir)?;
         fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.write().push(MountPoint::new(path, fs));
+        self.mounts.write().push(mp);
+        dentry_cache::clear();
         Ok(())
     }
 
+    /// Changes whether the filesystem mounted at `path` is read-only.
+    pub fn remount(&self, path: &str, read_only: bool) -> AxResult {
+        let mounts = self.mounts.read();
+        let mp = mounts
+            .iter()
+            .find(|mp| mp.path.trim_end_matches('/') == path.trim_end_matches('/'))
+            .ok_or(AxError::InvalidInput)?;
+        mp.read_only.store(read_only, Ordering::Release);
+        Ok(())
+    }
+
+    /// Calls `f` with the mount point of the filesystem that the absolute
+    /// `path` is on.
+    fn with_mount_point<T>(&self, path: &str, f: impl FnOnce(&MountPoint) -> T) -> T {
+        let path = path.trim_end_matches('/');
+        let mounts = self.mounts.read();
+        let mp = mounts
+            .iter()
+            .filter(|mp| {
+                path.strip_prefix(mp.path.trim_end_matches('/'))
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|mp| mp.path.len());
+        f(mp.unwrap_or(&self.root))
+    }
+
+    /// Whether the absolute `path` is on a filesystem mounted read-only.
+    pub fn is_read_only(&self, path: &str) -> bool {
+        self.with_mount_point(path, |mp| mp.read_only.load(Ordering::Acquire))
+    }
+
     pub fn _umount(&self, path: &str) {
         self.mounts.write().retain(|mp| mp.path != path);
+        dentry_cache::clear();
     }
 
     pub fn contains(&self, path: &str) -> bool {
@@ -166,45 +276,81 @@ impl VfsNodeOps for RootDirectory {
     }
 }
 
-pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
+pub(crate) fn init_rootfs(disk: crate::dev::Disk, other_disks: Vec<crate::dev::Disk>) {
+    let source = format!("/dev/{}", disk.name());
+    let flags = MountFlags::default();
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
-            let main_fs = fs::myfs::new_myfs(disk);
+            let root = MountPoint::new("/", fs::myfs::new_myfs(disk), &source, "myfs", flags);
         } else if #[cfg(feature = "fatfs")] {
             static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
             FAT_FS.init_once(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
             FAT_FS.init();
-            let main_fs = FAT_FS.clone();
+            let root = MountPoint::new("/", FAT_FS.clone(), &source, "vfat", flags)
+                .with_space(FAT_FS.clone());
         }
     }
 
-    let root_dir = RootDirectory::new(main_fs);
+    let root_dir = RootDirectory::new(root);
+
+    #[cfg(not(feature = "devfs"))]
+    if !other_disks.is_empty() {
+        warn!(
+            "devfs is disabled, ignoring {} other disks",
+            other_disks.len()
+        );
+    }
 
     #[cfg(feature = "devfs")]
     root_dir
-        .mount("/dev", mounts::devfs())
+        .mount(MountPoint::new(
+            "/dev",
+            mounts::devfs(other_disks),
+            "devtmpfs",
+            "devtmpfs",
+            flags,
+        ))
         .expect("failed to mount devfs at /dev");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/tmp", mounts::ramfs())
+        .mount(MountPoint::new(
+            "/tmp",
+            mounts::ramfs(),
+            "tmpfs",
+            "tmpfs",
+            flags,
+        ))
         .expect("failed to mount ramfs at /tmp");
 
     // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
     root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
+        .mount(MountPoint::new(
+            "/proc",
+            mounts::procfs().unwrap(),
+            "proc",
+            "proc",
+            flags,
+        ))
         .expect("fail to mount procfs at /proc");
 
     // Mount another ramfs as sysfs
     #[cfg(feature = "sysfs")]
     root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
+        .mount(MountPoint::new(
+            "/sys",
+            mounts::sysfs().unwrap(),
+            "sysfs",
+            "sysfs",
+            flags,
+        ))
         .expect("fail to mount sysfs at /sys");
 
     ROOT_DIR.init_once(Arc::new(root_dir));
-    CURRENT_DIR.init_new(Mutex::new(ROOT_DIR.clone()));
-    CURRENT_DIR_PATH.init_new(Mutex::new("/".into()));
+    CURRENT_DIR.init_new(Arc::new(Mutex::new(ROOT_DIR.clone())));
+    CURRENT_DIR_PATH.init_new(Arc::new(Mutex::new("/".into())));
+    CURRENT_ROOT_PATH.init_new(Arc::new(Mutex::new("/".into())));
 }
 
 fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
@@ -215,20 +361,131 @@ fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
     }
 }
 
+/// Returns the canonical absolute form of `path` without a trailing `/`,
+/// except for the root.
+///
+/// Paths here are in the whole filesystem tree, not in the root directory of
+/// the process; see [`crate::path::canonicalize`] for the latter.
 pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
-    if path.starts_with('/') {
-        Ok(axfs_vfs::path::canonicalize(path))
+    let path = crate::path::canonicalize_in("/", &CURRENT_DIR_PATH.lock(), path)?;
+    Ok(trim_trailing_slash(path))
+}
+
+/// Generations of the directories, which change whenever entries are created,
+/// removed or renamed in them through the filesystem API.
+struct DirGenerations {
+    /// The most recently assigned generation.
+    last: u64,
+    /// The generation of every directory, assigned when an entry changed in a
+    /// directory whose path is not known.
+    all: u64,
+    /// Generations of single directories, keyed by their absolute path.
+    dirs: BTreeMap<String, u64>,
+}
+
+static DIR_GENERATIONS: Mutex<DirGenerations> = Mutex::new(DirGenerations {
+    last: 0,
+    all: 0,
+    dirs: BTreeMap::new(),
+});
+
+/// Returns the absolute path of `path` looked up in `dir`, or `None` if it is
+/// relative to a directory whose path is not known.
+fn known_path(dir: Option<&VfsNodeRef>, path: &str) -> Option<String> {
+    if path.starts_with('/') || dir.is_none() {
+        absolute_path(path).ok()
     } else {
-        let path = CURRENT_DIR_PATH.lock().clone() + path;
-        Ok(axfs_vfs::path::canonicalize(&path))
+        None
+    }
+}
+
+/// Moves the directory containing `path` to a new generation, and drops the
+/// cached lookups of `path` and below.
+fn entries_changed(dir: Option<&VfsNodeRef>, path: &str) {
+    let path = known_path(dir, path);
+    match path.as_deref().map(dentry_key) {
+        Some(Some((mount, path))) => dentry_cache::invalidate(mount, &path),
+        Some(None) => {}
+        None => dentry_cache::clear(),
+    }
+    let parent = path.map(|path| match path.rfind('/') {
+        Some(0) | None => String::from("/"),
+        Some(idx) => String::from(&path[..idx]),
+    });
+    let mut generations = DIR_GENERATIONS.lock();
+    generations.last += 1;
+    let generation = generations.last;
+    match parent {
+        Some(parent) => {
+            generations.dirs.insert(parent, generation);
+        }
+        None => generations.all = generation,
+    }
+}
+
+/// Returns the generation of the directory at `path`. It differs from any
+/// earlier generation of the directory if entries in it have changed since.
+pub fn dir_generation(path: &str) -> u64 {
+    let path = absolute_path(path).unwrap_or_default();
+    let generations = DIR_GENERATIONS.lock();
+    let own = generations.dirs.get(&path).copied().unwrap_or(0);
+    own.max(generations.all)
+}
+
+fn trim_trailing_slash(mut path: String) -> String {
+    while path.len() > 1 && path.ends_with('/') {
+        path.pop();
+    }
+    path
+}
+
+/// Returns the key of the canonical absolute `path` in the dentry cache, or
+/// `None` if it is on a filesystem whose lookups are not cached. Only FAT is,
+/// as the entries of the others are in memory or change on their own.
+fn dentry_key(path: &str) -> Option<DentryKey> {
+    ROOT_DIR.with_mount_point(path, |mp| {
+        let rest = path.strip_prefix(mp.path.trim_end_matches('/'))?;
+        (mp.fs_type == "vfat").then(|| (mp.path, rest.trim_start_matches('/').to_lowercase()))
+    })
+}
+
+/// Looks up the canonical absolute `path` through the dentry cache. A path
+/// not cached is looked up in its parent directory, itself looked up the same
+/// way.
+fn lookup_cached(path: &str) -> AxResult<VfsNodeRef> {
+    let Some(key) = dentry_key(path) else {
+        return ROOT_DIR.clone().lookup(path);
+    };
+    if let Some(result) = dentry_cache::get(&key) {
+        return result;
+    }
+    let generation = dentry_cache::generation();
+    let result = match path.rsplit_once('/') {
+        // The root of a filesystem is found through its mount point.
+        Some((parent, name)) if !key.1.is_empty() => {
+            let parent = lookup_cached(if parent.is_empty() { "/" } else { parent })?;
+            if parent.get_attr()?.is_dir() {
+                parent.lookup(name)
+            } else {
+                ROOT_DIR.clone().lookup(path)
+            }
+        }
+        _ => ROOT_DIR.clone().lookup(path),
+    };
+    if let Some(dentry) = Dentry::new(&result) {
+        dentry_cache::insert(key, generation, dentry);
     }
+    result
 }
 
 pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
+    let node = match absolute_path(path) {
+        Ok(abs_path) if path.starts_with('/') => lookup_cached(&abs_path)?,
+        _ => parent_node_of(dir, path).lookup(path)?,
+    };
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
     } else {
@@ -244,26 +501,51 @@ pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsN
     }
     let parent = parent_node_of(dir, path);
     parent.create(path, VfsNodeType::File)?;
+    entries_changed(dir, path);
+    if let Some(path) = known_path(dir, path) {
+        notify(FsEvent::Created {
+            path: &path,
+            is_dir: false,
+        });
+    }
     parent.lookup(path)
 }
 
 pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     match lookup(dir, path) {
         Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
+        Err(AxError::NotFound) => {
+            parent_node_of(dir, path).create(path, VfsNodeType::Dir)?;
+            entries_changed(dir, path);
+            if let Some(path) = known_path(dir, path) {
+                notify(FsEvent::Created {
+                    path: &path,
+                    is_dir: true,
+                });
+            }
+            Ok(())
+        }
         Err(e) => Err(e),
     }
 }
 
 pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
+    // The node is not kept, or the file would be taken as still in use.
+    let attr = lookup(dir, path)?.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, path).remove(path)?;
+        entries_changed(dir, path);
+        if let Some(path) = known_path(dir, path) {
+            notify(FsEvent::Removed {
+                path: &path,
+                is_dir: false,
+            });
+        }
+        Ok(())
     }
 }
 
@@ -292,12 +574,43 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, path).remove(path)?;
+        entries_changed(dir, path);
+        if let Some(path) = known_path(dir, path) {
+            notify(FsEvent::Removed {
+                path: &path,
+                is_dir: true,
+            });
+        }
+        Ok(())
     }
 }
 
+/// Returns the canonical path of the current directory as seen from the root
+/// directory of the process. Unlike [`CURRENT_DIR_PATH`], it does not end with
+/// `/` unless it is the root.
 pub(crate) fn current_dir() -> AxResult<String> {
-    Ok(CURRENT_DIR_PATH.lock().clone())
+    let cwd = CURRENT_DIR_PATH.lock().clone();
+    let root = CURRENT_ROOT_PATH.lock().clone();
+    // A directory outside the root, e.g. the one left by `chroot`, is shown in full.
+    let cwd = match cwd.strip_prefix(root.trim_end_matches('/')) {
+        Some(rest) if rest.starts_with('/') => rest.into(),
+        _ => cwd,
+    };
+    Ok(trim_trailing_slash(cwd))
+}
+
+/// Changes the root directory of the process to the directory `path`.
+pub(crate) fn set_root_dir(path: &str) -> AxResult {
+    let mut abs_path = absolute_path(path)?;
+    if !lookup(None, &abs_path)?.get_attr()?.is_dir() {
+        return ax_err!(NotADirectory);
+    }
+    if !abs_path.ends_with('/') {
+        abs_path += "/";
+    }
+    *CURRENT_ROOT_PATH.lock() = abs_path;
+    Ok(())
 }
 
 pub(crate) fn set_current_dir(path: &str) -> AxResult {
@@ -329,27 +642,105 @@ pub(crate) fn rename(old: &str, new: &str) -> AxResult {
         warn!("dst file already exist, now remove it");
         remove_file(None, new)?;
     }
-    parent_node_of(None, old).rename(old, new)
+    parent_node_of(None, old).rename(old, new)?;
+    entries_changed(None, old);
+    entries_changed(None, new);
+    if let (Ok(old), Ok(new)) = (absolute_path(old), absolute_path(new)) {
+        let is_dir = lookup(None, &new)
+            .and_then(|node| node.get_attr())
+            .is_ok_and(|attr| attr.is_dir());
+        notify(FsEvent::Renamed {
+            old: &old,
+            new: &new,
+            is_dir,
+        });
+    }
+    Ok(())
 }
 
-pub fn mount(src: &str, mount_target: &'static str) -> AxResult {
-    let fs = lookup(None, src).inspect_err(|e| log::error!("{e}"))?;
-    let fs = fs
-        .as_any()
-        .downcast_ref::<FileWrapper<Disk>>()
-        .ok_or(AxError::InvalidInput)?;
-    let fs = crate::fs::fatfs::FatFileSystemFromFile::new(fs.clone());
+/// Mounts the FAT filesystem in `src` at `mount_target` with `flags`.
+pub fn mount(src: &str, mount_target: &'static str, flags: MountFlags) -> AxResult {
+    let node = lookup(None, src).inspect_err(|e| log::error!("{e}"))?;
+    let fs = Arc::new(crate::fs::fatfs::FatFileSystemFromFile::new(node)?);
+    // SAFETY: 文件系统由 Arc 持有，根目录引用它的期间不会被移动或释放
+    unsafe { &*Arc::as_ptr(&fs) }.init();
+    let source = absolute_path(src)?;
+    ROOT_DIR.mount(MountPoint::new(mount_target, fs.clone(), &source, "vfat", flags).with_space(fs))
+}
 
-    // 使用 Box 将 fs 装箱
-    let fs_box = Box::new(fs);
-    let fs_ptr = Box::into_raw(fs_box);
-    unsafe {
-        (*fs_ptr).init();
-        // 重新获取所有权
-        let fs = Box::from_raw(fs_ptr);
-        ROOT_DIR.mount(mount_target, Arc::new(*fs))?;
+/// Returns the information of the filesystem that `path` is on.
+pub fn mount_info(path: &str) -> AxResult<MountInfo> {
+    Ok(ROOT_DIR.with_mount_point(&absolute_path(path)?, MountPoint::info))
+}
+
+/// Returns the information of all the mounted filesystems, starting with the
+/// root.
+pub(crate) fn mount_infos() -> Vec<MountInfo> {
+    let mounts = ROOT_DIR.mounts.read();
+    core::iter::once(&ROOT_DIR.root)
+        .chain(mounts.iter())
+        .map(MountPoint::info)
+        .collect()
+}
+
+/// Returns the counters of the filesystem that the file at `path`, relative to
+/// `dir`, is on. Returns `None` if the path is relative to a directory other
+/// than the current one, whose path is not known.
+pub(crate) fn mount_stats(dir: Option<&VfsNodeRef>, path: &str) -> Option<Arc<MountStats>> {
+    if dir.is_some() && !path.starts_with('/') {
+        return None;
     }
-    Ok(())
+    let path = absolute_path(path).ok()?;
+    Some(ROOT_DIR.with_mount_point(&path, |mp| mp.stats.clone()))
+}
+
+/// Changes whether the filesystem mounted at `path` is read-only.
+pub fn remount(path: &str, read_only: bool) -> AxResult {
+    ROOT_DIR.remount(path, read_only)
+}
+
+/// Returns the owner `(uid, gid)` of the file at `path` if the filesystem it is
+/// on knows one, like the process directories in `/proc`. Other filesystems do
+/// not record owners.
+pub fn owner(path: &str) -> Option<(u32, u32)> {
+    let path = absolute_path(path).ok()?;
+    ROOT_DIR.with_mount_point(&path, |mp| match mp.fs_type {
+        #[cfg(feature = "procfs")]
+        "proc" => fs::procfs::owner(&path[mp.path.len()..]),
+        _ => None,
+    })
+}
+
+/// Returns the PID and the file descriptor of the link at `path` if it is in a
+/// `/proc/[pid]/fd` directory, like `/proc/self/fd/3`. Such a link refers to the
+/// file open under the descriptor, even if the file has no path.
+pub fn fd_link(path: &str) -> Option<(u64, u32)> {
+    let path = absolute_path(path).ok()?;
+    ROOT_DIR.with_mount_point(&path, |mp| match mp.fs_type {
+        #[cfg(feature = "procfs")]
+        "proc" => fs::procfs::fd_link(&path[mp.path.len()..]),
+        _ => None,
+    })
+}
+
+/// Returns the target of the link at `path` in a `/proc/[pid]/fd` directory,
+/// see [`fd_link`]. The target is the path of the open file, or a name like
+/// `pipe:[id]` for other objects.
+pub fn fd_link_target(path: &str) -> Option<String> {
+    let path = absolute_path(path).ok()?;
+    ROOT_DIR.with_mount_point(&path, |mp| match mp.fs_type {
+        #[cfg(feature = "procfs")]
+        "proc" => {
+            let (pid, fd) = fs::procfs::fd_link(&path[mp.path.len()..])?;
+            fs::procfs::fd_link_target(pid, fd)
+        }
+        _ => None,
+    })
+}
+
+/// Whether `path` is on a filesystem mounted read-only.
+pub fn is_read_only(path: &str) -> AxResult<bool> {
+    Ok(ROOT_DIR.is_read_only(&absolute_path(path)?))
 }
 
 pub fn umount(path: &str) -> AxResult {
@@ -0,0 +1,153 @@
+//! Named tunables of the kernel, like the `sysctl` parameters of Linux.
+//!
+//! A subsystem keeps the value of a tunable itself, and registers it with
+//! [`register_sysctl`] under a path such as `kernel/pid_max`. With the `procfs`
+//! feature, every tunable is a file in `/proc/sys`: reading it renders the
+//! current value with a trailing newline, and writing it validates the value
+//! and applies it at once.
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+};
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axfs_vfs::{VfsError, VfsResult};
+use spin::RwLock;
+
+/// A value that can be read and written as text.
+pub trait Tunable: Send + Sync {
+    /// Renders the current value, without the trailing newline.
+    fn read(&self) -> String;
+
+    /// Parses and applies `value`, from which the trailing newline has been
+    /// removed. Returns [`VfsError::InvalidInput`] if the value is not valid.
+    fn write(&self, value: &str) -> VfsResult;
+
+    /// Returns whether the value can be written.
+    fn writable(&self) -> bool {
+        true
+    }
+}
+
+/// An integer kept in an atomic, whose written values must be in a range.
+pub struct IntTunable {
+    value: &'static AtomicUsize,
+    range: RangeInclusive<usize>,
+}
+
+impl IntTunable {
+    /// Creates a tunable for `value`, which is written only with values in
+    /// `range`.
+    pub const fn new(value: &'static AtomicUsize, range: RangeInclusive<usize>) -> Self {
+        Self { value, range }
+    }
+}
+
+impl Tunable for IntTunable {
+    fn read(&self) -> String {
+        self.value.load(Ordering::Relaxed).to_string()
+    }
+
+    fn write(&self, value: &str) -> VfsResult {
+        let value = value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|value| self.range.contains(value))
+            .ok_or(VfsError::InvalidInput)?;
+        self.value.store(value, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// A tunable whose value is read and written by handlers, for values that are
+/// also changed by other means, such as the host name by `sethostname`.
+pub struct FnTunable {
+    read: fn() -> String,
+    write: Option<fn(&str) -> VfsResult>,
+}
+
+impl FnTunable {
+    /// Creates a tunable rendered by `read`, and without `write` read-only.
+    pub const fn new(read: fn() -> String, write: Option<fn(&str) -> VfsResult>) -> Self {
+        Self { read, write }
+    }
+}
+
+impl Tunable for FnTunable {
+    fn read(&self) -> String {
+        (self.read)()
+    }
+
+    fn write(&self, value: &str) -> VfsResult {
+        (self.write.ok_or(VfsError::PermissionDenied)?)(value)
+    }
+
+    fn writable(&self) -> bool {
+        self.write.is_some()
+    }
+}
+
+/// The registered tunables by path.
+static TUNABLES: RwLock<BTreeMap<&'static str, Box<dyn Tunable>>> = RwLock::new(BTreeMap::new());
+
+/// Registers `tunable` under `path`, the components of which are separated by
+/// `/`. A tunable registered earlier under the same path is replaced.
+pub fn register_sysctl(path: &'static str, tunable: impl Tunable + 'static) {
+    TUNABLES.write().insert(path, Box::new(tunable));
+}
+
+/// Renders the tunable at `path` with a trailing newline.
+#[cfg(feature = "procfs")]
+pub(crate) fn read(path: &str) -> Option<String> {
+    TUNABLES
+        .read()
+        .get(path)
+        .map(|tunable| tunable.read() + "\n")
+}
+
+/// Writes `buf` to the tunable at `path`.
+#[cfg(feature = "procfs")]
+pub(crate) fn write(path: &str, buf: &[u8]) -> VfsResult {
+    let value = core::str::from_utf8(buf).map_err(|_| VfsError::InvalidInput)?;
+    let tunables = TUNABLES.read();
+    let tunable = tunables.get(path).ok_or(VfsError::NotFound)?;
+    tunable.write(value.strip_suffix('\n').unwrap_or(value))
+}
+
+/// Returns whether the tunable at `path` can be written, or `None` if there
+/// is no tunable at `path`.
+#[cfg(feature = "procfs")]
+pub(crate) fn writable(path: &str) -> Option<bool> {
+    TUNABLES.read().get(path).map(|tunable| tunable.writable())
+}
+
+/// Returns the names of the entries in the directory `dir` of tunables, and
+/// whether each is a directory. `dir` is empty for the top directory.
+///
+/// Returns `None` if no tunable is in `dir`.
+#[cfg(feature = "procfs")]
+pub(crate) fn entries(dir: &str) -> Option<alloc::vec::Vec<(String, bool)>> {
+    let mut entries: alloc::vec::Vec<(String, bool)> = alloc::vec::Vec::new();
+    for path in TUNABLES.read().keys() {
+        let rest = match dir {
+            "" => *path,
+            _ => match path.strip_prefix(dir).and_then(|p| p.strip_prefix('/')) {
+                Some(rest) => rest,
+                None => continue,
+            },
+        };
+        let (name, is_dir) = match rest.split_once('/') {
+            Some((name, _)) => (name, true),
+            None => (rest, false),
+        };
+        // The paths are sorted, so those in the same directory are adjacent.
+        if entries.last().map_or(true, |last| last.0 != name) {
+            entries.push((name.into(), is_dir));
+        }
+    }
+    (!entries.is_empty() || dir.is_empty()).then_some(entries)
+}
@@ -164,6 +164,34 @@ fn test_remove_file_dir() -> Result<()> {
     Ok(())
 }
 
+fn test_remove_open_file() -> Result<()> {
+    let fname = "/open_removed.txt";
+    println!("test remove open file {:?}:", fname);
+    let mut file = File::create(fname)?;
+    assert_eq!(file.write(b"before\n")?, 7);
+    let mut other = File::open(fname)?;
+    fs::remove_file(fname)?;
+    assert_err!(File::open(fname), NotFound);
+    assert!(fs::read_dir("/")?.all(|entry| entry.unwrap().file_name() != "open_removed.txt"));
+
+    // The data is still there for the files opened before.
+    assert_eq!(file.write(b"after\n")?, 6);
+    let mut contents = String::new();
+    other.read_to_string(&mut contents)?;
+    assert_eq!(contents, "before\nafter\n");
+
+    // A new file of the same name is another file.
+    fs::write(fname, "new\n")?;
+    assert_eq!(file.metadata()?.len(), 13);
+    drop(file);
+    drop(other);
+    assert_eq!(fs::read_to_string(fname)?, "new\n");
+    fs::remove_file(fname)?;
+
+    println!("test_remove_open_file() OK!");
+    Ok(())
+}
+
 fn test_devfs_ramfs() -> Result<()> {
     const N: usize = 32;
     let mut buf = [1; N];
@@ -258,5 +286,6 @@ pub fn test_all() {
     test_file_permission().expect("test_file_permission() failed");
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
+    test_remove_open_file().expect("test_remove_open_file() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
 }
@@ -47,6 +47,39 @@ impl TrapFrame {
     pub const fn arg5(&self) -> usize {
         self.r[5] as _
     }
+
+    /// Gets the instruction pointer.
+    pub const fn get_ip(&self) -> usize {
+        self.elr as _
+    }
+
+    /// Sets the instruction pointer.
+    pub const fn set_ip(&mut self, ip: usize) {
+        self.elr = ip as _;
+    }
+
+    /// Gets the stack pointer.
+    pub const fn get_sp(&self) -> usize {
+        self.usp as _
+    }
+
+    /// Sets the stack pointer.
+    pub const fn set_sp(&mut self, sp: usize) {
+        self.usp = sp as _;
+    }
+
+    /// Sets the first three function arguments, used when calling a user
+    /// function such as a signal handler.
+    pub const fn set_args(&mut self, arg0: usize, arg1: usize, arg2: usize) {
+        self.r[0] = arg0 as _;
+        self.r[1] = arg1 as _;
+        self.r[2] = arg2 as _;
+    }
+
+    /// Sets the return address register.
+    pub const fn set_ra(&mut self, ra: usize) {
+        self.r[30] = ra as _;
+    }
 }
 
 /// Context to enter user space.
@@ -84,6 +117,11 @@ impl UspaceContext {
         Self(*trap_frame)
     }
 
+    /// Gets the saved registers.
+    pub const fn trap_frame(&self) -> &TrapFrame {
+        &self.0
+    }
+
     /// Gets the instruction pointer.
     pub const fn get_ip(&self) -> usize {
         self.0.elr as _
@@ -160,7 +198,7 @@ impl UspaceContext {
 
 /// FP & SIMD registers.
 #[repr(C, align(16))]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct FpState {
     /// 128-bit SIMD & FP registers (V0..V31)
     pub regs: [u128; 32],
@@ -172,8 +210,19 @@ pub struct FpState {
 
 #[cfg(feature = "fp_simd")]
 impl FpState {
+    /// Saves the FP/SIMD registers of the current CPU to this place.
+    pub fn save(&mut self) {
+        unsafe { fpstate_save(self) }
+    }
+
+    /// Restores the FP/SIMD registers of the current CPU from this place.
+    pub fn restore(&self) {
+        unsafe { fpstate_restore(self) }
+    }
+
     fn switch_to(&mut self, next_fpstate: &FpState) {
-        unsafe { fpstate_switch(self, next_fpstate) }
+        self.save();
+        next_fpstate.restore();
     }
 }
 
@@ -242,6 +291,15 @@ impl TaskContext {
         self.ttbr0_el1 = ttbr0_el1;
     }
 
+    /// Copies the FP/SIMD registers of the current CPU into this context.
+    ///
+    /// It initializes the context of a task that duplicates the current one,
+    /// such as a forked child.
+    #[cfg(feature = "fp_simd")]
+    pub fn copy_fp_state(&mut self) {
+        self.fp_state.save();
+    }
+
     /// Switches to another task.
     ///
     /// It first saves the current task's context from CPU to this place, and then
@@ -292,7 +350,7 @@ unsafe extern "C" fn context_switch(_current_task: &mut TaskContext, _next_task:
 
 #[naked]
 #[cfg(feature = "fp_simd")]
-unsafe extern "C" fn fpstate_switch(_current_fpstate: &mut FpState, _next_fpstate: &FpState) {
+unsafe extern "C" fn fpstate_save(_fpstate: &mut FpState) {
     asm!(
         "
         // save fp/neon context
@@ -314,28 +372,37 @@ unsafe extern "C" fn fpstate_switch(_current_fpstate: &mut FpState, _next_fpstat
         stp     q26, q27, [x0, 26 * 16]
         stp     q28, q29, [x0, 28 * 16]
         stp     q30, q31, [x0, 30 * 16]
-        str     x9, [x0, 64 *  8]
-        str     x10, [x0, 65 * 8]
+        str     w9, [x0, 64 * 8]
+        str     w10, [x0, 64 * 8 + 4]
+        ret",
+        options(noreturn),
+    )
+}
 
+#[naked]
+#[cfg(feature = "fp_simd")]
+unsafe extern "C" fn fpstate_restore(_fpstate: &FpState) {
+    asm!(
+        "
         // restore fp/neon context
-        ldp     q0, q1, [x1, 0 * 16]
-        ldp     q2, q3, [x1, 2 * 16]
-        ldp     q4, q5, [x1, 4 * 16]
-        ldp     q6, q7, [x1, 6 * 16]
-        ldp     q8, q9, [x1, 8 * 16]
-        ldp     q10, q11, [x1, 10 * 16]
-        ldp     q12, q13, [x1, 12 * 16]
-        ldp     q14, q15, [x1, 14 * 16]
-        ldp     q16, q17, [x1, 16 * 16]
-        ldp     q18, q19, [x1, 18 * 16]
-        ldp     q20, q21, [x1, 20 * 16]
-        ldp     q22, q23, [x1, 22 * 16]
-        ldp     q24, q25, [x1, 24 * 16]
-        ldp     q26, q27, [x1, 26 * 16]
-        ldp     q28, q29, [x1, 28 * 16]
-        ldp     q30, q31, [x1, 30 * 16]
-        ldr     x9, [x1, 64 * 8]
-        ldr     x10, [x1, 65 * 8]
+        ldp     q0, q1, [x0, 0 * 16]
+        ldp     q2, q3, [x0, 2 * 16]
+        ldp     q4, q5, [x0, 4 * 16]
+        ldp     q6, q7, [x0, 6 * 16]
+        ldp     q8, q9, [x0, 8 * 16]
+        ldp     q10, q11, [x0, 10 * 16]
+        ldp     q12, q13, [x0, 12 * 16]
+        ldp     q14, q15, [x0, 14 * 16]
+        ldp     q16, q17, [x0, 16 * 16]
+        ldp     q18, q19, [x0, 18 * 16]
+        ldp     q20, q21, [x0, 20 * 16]
+        ldp     q22, q23, [x0, 22 * 16]
+        ldp     q24, q25, [x0, 24 * 16]
+        ldp     q26, q27, [x0, 26 * 16]
+        ldp     q28, q29, [x0, 28 * 16]
+        ldp     q30, q31, [x0, 30 * 16]
+        ldr     w9, [x0, 64 * 8]
+        ldr     w10, [x0, 64 * 8 + 4]
         msr     fpcr, x9
         msr     fpsr, x10
 
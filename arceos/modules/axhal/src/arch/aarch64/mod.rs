@@ -118,6 +118,16 @@ pub fn flush_dcache_line(vaddr: VirtAddr) {
     unsafe { asm!("dc ivac, {0:x}; dsb sy; isb", in(reg) vaddr.as_usize()) };
 }
 
+/// Reads the frame pointer of the current function.
+///
+/// It is used to walk the stack for backtraces.
+#[inline(always)]
+pub fn read_frame_pointer() -> usize {
+    let fp;
+    unsafe { core::arch::asm!("mov {}, x29", out(reg) fp) };
+    fp
+}
+
 /// Reads the thread pointer of the current CPU.
 ///
 /// It is used to implement TLS (Thread Local Storage).
@@ -37,8 +37,14 @@ fn invalid_exception(tf: &TrapFrame, kind: TrapKind, source: TrapSource) {
 }
 
 #[no_mangle]
-fn handle_irq_exception(_tf: &TrapFrame) {
+fn handle_irq_exception(tf: &mut TrapFrame) {
     handle_trap!(IRQ, 0);
+    #[cfg(feature = "uspace")]
+    if tf.spsr & 0b1111 == 0 {
+        crate::trap::handle_return_to_user(tf);
+    }
+    #[cfg(not(feature = "uspace"))]
+    let _ = tf;
 }
 
 fn handle_instruction_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
@@ -120,4 +126,9 @@ fn handle_sync_exception(tf: &mut TrapFrame) {
             );
         }
     }
+    // Exceptions from EL0 (SPSR.M == EL0t) return to user space.
+    #[cfg(feature = "uspace")]
+    if tf.spsr & 0b1111 == 0 {
+        crate::trap::handle_return_to_user(tf);
+    }
 }
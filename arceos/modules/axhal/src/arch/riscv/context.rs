@@ -2,6 +2,7 @@
 
 use core::arch::asm;
 use memory_addr::{PhysAddr, VirtAddr};
+use riscv::register::sstatus::{self, FS};
 
 include_asm_marcos!();
 
@@ -85,6 +86,39 @@ impl TrapFrame {
     pub const fn arg5(&self) -> usize {
         self.regs.a5
     }
+
+    /// Gets the instruction pointer.
+    pub const fn get_ip(&self) -> usize {
+        self.sepc
+    }
+
+    /// Sets the instruction pointer.
+    pub const fn set_ip(&mut self, ip: usize) {
+        self.sepc = ip;
+    }
+
+    /// Gets the stack pointer.
+    pub const fn get_sp(&self) -> usize {
+        self.regs.sp
+    }
+
+    /// Sets the stack pointer.
+    pub const fn set_sp(&mut self, sp: usize) {
+        self.regs.sp = sp;
+    }
+
+    /// Sets the first three function arguments, used when calling a user
+    /// function such as a signal handler.
+    pub const fn set_args(&mut self, arg0: usize, arg1: usize, arg2: usize) {
+        self.regs.a0 = arg0;
+        self.regs.a1 = arg1;
+        self.regs.a2 = arg2;
+    }
+
+    /// Sets the return address register.
+    pub const fn set_ra(&mut self, ra: usize) {
+        self.regs.ra = ra;
+    }
 }
 
 /// Context to enter user space.
@@ -103,6 +137,13 @@ impl UspaceContext {
     pub fn new(entry: usize, ustack_top: VirtAddr, arg0: usize) -> Self {
         const SPIE: usize = 1 << 5;
         const SUM: usize = 1 << 18;
+        // Allow user space to use the FP registers, which are saved by
+        // `TaskContext` on context switch.
+        const FS_INITIAL: usize = if cfg!(feature = "fp_simd") {
+            (FS::Initial as usize) << 13
+        } else {
+            0
+        };
         Self(TrapFrame {
             regs: GeneralRegisters {
                 a0: arg0,
@@ -110,7 +151,7 @@ impl UspaceContext {
                 ..Default::default()
             },
             sepc: entry,
-            sstatus: SPIE | SUM,
+            sstatus: SPIE | SUM | FS_INITIAL,
         })
     }
 
@@ -119,6 +160,11 @@ impl UspaceContext {
         Self(*trap_frame)
     }
 
+    /// Gets the saved registers.
+    pub const fn trap_frame(&self) -> &TrapFrame {
+        &self.0
+    }
+
     /// Gets the instruction pointer.
     pub const fn get_ip(&self) -> usize {
         self.0.sepc
@@ -185,6 +231,41 @@ impl UspaceContext {
     }
 }
 
+/// Floating-point registers of the F and D extensions.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FpState {
+    /// The `f0`-`f31` registers.
+    pub fp: [u64; 32],
+    /// The floating-point control and status register.
+    pub fcsr: usize,
+}
+
+#[cfg(feature = "fp_simd")]
+impl FpState {
+    /// Saves the FP registers of the current CPU to this place.
+    pub fn save(&mut self) {
+        if sstatus::read().fs() == FS::Off {
+            // The registers are not in use.
+            *self = Self::default();
+        } else {
+            unsafe { save_fp_registers(self) };
+        }
+    }
+
+    /// Restores the FP registers of the current CPU from this place.
+    ///
+    /// The registers are then regarded as modified (the `FS` field of
+    /// `sstatus` is `Dirty`), so that they are saved on the next context
+    /// switch.
+    pub fn restore(&self) {
+        unsafe {
+            sstatus::set_fs(FS::Dirty);
+            restore_fp_registers(self);
+        }
+    }
+}
+
 /// Saved hardware states of a task.
 ///
 /// The context usually includes:
@@ -198,7 +279,7 @@ impl UspaceContext {
 /// and the next task restores its context from memory to CPU.
 #[allow(missing_docs)]
 #[repr(C)]
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct TaskContext {
     pub ra: usize, // return address (x1)
     pub sp: usize, // stack pointer (x2)
@@ -221,7 +302,17 @@ pub struct TaskContext {
     /// The `satp` register value, i.e., the page table root.
     #[cfg(feature = "uspace")]
     pub satp: PhysAddr,
-    // TODO: FP states
+    /// The FP registers.
+    #[cfg(feature = "fp_simd")]
+    pub fp_state: FpState,
+    /// Status of the FP registers as in the `FS` field of `sstatus`.
+    ///
+    /// It is `Clean` if the registers are saved in [`fp_state`], `Initial` if
+    /// they are all zero, and `Off` if they are not used at all.
+    ///
+    /// [`fp_state`]: TaskContext::fp_state
+    #[cfg(feature = "fp_simd")]
+    pub fp_status: FS,
 }
 
 impl TaskContext {
@@ -236,7 +327,25 @@ impl TaskContext {
         Self {
             #[cfg(feature = "uspace")]
             satp: crate::paging::kernel_page_table_root(),
-            ..Default::default()
+            ra: 0,
+            sp: 0,
+            s0: 0,
+            s1: 0,
+            s2: 0,
+            s3: 0,
+            s4: 0,
+            s5: 0,
+            s6: 0,
+            s7: 0,
+            s8: 0,
+            s9: 0,
+            s10: 0,
+            s11: 0,
+            tp: 0,
+            #[cfg(feature = "fp_simd")]
+            fp_state: FpState::default(),
+            #[cfg(feature = "fp_simd")]
+            fp_status: FS::Off,
         }
     }
 
@@ -259,6 +368,16 @@ impl TaskContext {
         self.satp = satp;
     }
 
+    /// Copies the FP registers of the current CPU into this context.
+    ///
+    /// It initializes the context of a task that duplicates the current one,
+    /// such as a forked child.
+    #[cfg(feature = "fp_simd")]
+    pub fn copy_fp_state(&mut self) {
+        self.fp_state.save();
+        self.fp_status = FS::Clean;
+    }
+
     /// Switches to another task.
     ///
     /// It first saves the current task's context from CPU to this place, and then
@@ -275,9 +394,30 @@ impl TaskContext {
                 super::write_page_table_root(next_ctx.satp);
             }
         }
+        #[cfg(feature = "fp_simd")]
+        self.switch_fp_state(next_ctx);
+        unsafe { context_switch(self, next_ctx) }
+    }
+
+    /// Saves the FP registers only if they were modified since restored, and
+    /// restores those of the next task.
+    #[cfg(feature = "fp_simd")]
+    fn switch_fp_state(&mut self, next_ctx: &Self) {
+        self.fp_status = match sstatus::read().fs() {
+            FS::Dirty => {
+                unsafe { save_fp_registers(&mut self.fp_state) };
+                FS::Clean
+            }
+            fs => fs,
+        };
         unsafe {
-            // TODO: switch FP states
-            context_switch(self, next_ctx)
+            sstatus::set_fs(FS::Initial);
+            match next_ctx.fp_status {
+                FS::Clean | FS::Dirty => restore_fp_registers(&next_ctx.fp_state),
+                FS::Initial => clear_fp_registers(),
+                FS::Off => {}
+            }
+            sstatus::set_fs(next_ctx.fp_status);
         }
     }
 }
@@ -322,3 +462,134 @@ unsafe extern "C" fn context_switch(_current_task: &mut TaskContext, _next_task:
         options(noreturn),
     )
 }
+
+#[naked]
+#[cfg(feature = "fp_simd")]
+unsafe extern "C" fn save_fp_registers(_fpstate: &mut FpState) {
+    asm!(
+        "
+        fsd     f0, 0*8(a0)
+        fsd     f1, 1*8(a0)
+        fsd     f2, 2*8(a0)
+        fsd     f3, 3*8(a0)
+        fsd     f4, 4*8(a0)
+        fsd     f5, 5*8(a0)
+        fsd     f6, 6*8(a0)
+        fsd     f7, 7*8(a0)
+        fsd     f8, 8*8(a0)
+        fsd     f9, 9*8(a0)
+        fsd     f10, 10*8(a0)
+        fsd     f11, 11*8(a0)
+        fsd     f12, 12*8(a0)
+        fsd     f13, 13*8(a0)
+        fsd     f14, 14*8(a0)
+        fsd     f15, 15*8(a0)
+        fsd     f16, 16*8(a0)
+        fsd     f17, 17*8(a0)
+        fsd     f18, 18*8(a0)
+        fsd     f19, 19*8(a0)
+        fsd     f20, 20*8(a0)
+        fsd     f21, 21*8(a0)
+        fsd     f22, 22*8(a0)
+        fsd     f23, 23*8(a0)
+        fsd     f24, 24*8(a0)
+        fsd     f25, 25*8(a0)
+        fsd     f26, 26*8(a0)
+        fsd     f27, 27*8(a0)
+        fsd     f28, 28*8(a0)
+        fsd     f29, 29*8(a0)
+        fsd     f30, 30*8(a0)
+        fsd     f31, 31*8(a0)
+        frcsr   t0
+        sd      t0, 32*8(a0)
+        ret",
+        options(noreturn),
+    )
+}
+
+#[naked]
+#[cfg(feature = "fp_simd")]
+unsafe extern "C" fn restore_fp_registers(_fpstate: &FpState) {
+    asm!(
+        "
+        fld     f0, 0*8(a0)
+        fld     f1, 1*8(a0)
+        fld     f2, 2*8(a0)
+        fld     f3, 3*8(a0)
+        fld     f4, 4*8(a0)
+        fld     f5, 5*8(a0)
+        fld     f6, 6*8(a0)
+        fld     f7, 7*8(a0)
+        fld     f8, 8*8(a0)
+        fld     f9, 9*8(a0)
+        fld     f10, 10*8(a0)
+        fld     f11, 11*8(a0)
+        fld     f12, 12*8(a0)
+        fld     f13, 13*8(a0)
+        fld     f14, 14*8(a0)
+        fld     f15, 15*8(a0)
+        fld     f16, 16*8(a0)
+        fld     f17, 17*8(a0)
+        fld     f18, 18*8(a0)
+        fld     f19, 19*8(a0)
+        fld     f20, 20*8(a0)
+        fld     f21, 21*8(a0)
+        fld     f22, 22*8(a0)
+        fld     f23, 23*8(a0)
+        fld     f24, 24*8(a0)
+        fld     f25, 25*8(a0)
+        fld     f26, 26*8(a0)
+        fld     f27, 27*8(a0)
+        fld     f28, 28*8(a0)
+        fld     f29, 29*8(a0)
+        fld     f30, 30*8(a0)
+        fld     f31, 31*8(a0)
+        ld      t0, 32*8(a0)
+        fscsr   t0
+        ret",
+        options(noreturn),
+    )
+}
+
+#[naked]
+#[cfg(feature = "fp_simd")]
+unsafe extern "C" fn clear_fp_registers() {
+    asm!(
+        "
+        fmv.d.x f0, zero
+        fmv.d.x f1, zero
+        fmv.d.x f2, zero
+        fmv.d.x f3, zero
+        fmv.d.x f4, zero
+        fmv.d.x f5, zero
+        fmv.d.x f6, zero
+        fmv.d.x f7, zero
+        fmv.d.x f8, zero
+        fmv.d.x f9, zero
+        fmv.d.x f10, zero
+        fmv.d.x f11, zero
+        fmv.d.x f12, zero
+        fmv.d.x f13, zero
+        fmv.d.x f14, zero
+        fmv.d.x f15, zero
+        fmv.d.x f16, zero
+        fmv.d.x f17, zero
+        fmv.d.x f18, zero
+        fmv.d.x f19, zero
+        fmv.d.x f20, zero
+        fmv.d.x f21, zero
+        fmv.d.x f22, zero
+        fmv.d.x f23, zero
+        fmv.d.x f24, zero
+        fmv.d.x f25, zero
+        fmv.d.x f26, zero
+        fmv.d.x f27, zero
+        fmv.d.x f28, zero
+        fmv.d.x f29, zero
+        fmv.d.x f30, zero
+        fmv.d.x f31, zero
+        fscsr   zero
+        ret",
+        options(noreturn),
+    )
+}
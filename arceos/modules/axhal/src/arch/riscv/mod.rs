@@ -10,7 +10,7 @@ use riscv::register::{satp, sstatus, stvec};
 
 #[cfg(feature = "uspace")]
 pub use self::context::UspaceContext;
-pub use self::context::{GeneralRegisters, TaskContext, TrapFrame};
+pub use self::context::{FpState, GeneralRegisters, TaskContext, TrapFrame};
 
 /// Allows the current CPU to respond to interrupts.
 #[inline]
@@ -88,6 +88,16 @@ pub fn set_trap_vector_base(stvec: usize) {
     unsafe { stvec::write(stvec, stvec::TrapMode::Direct) }
 }
 
+/// Reads the frame pointer of the current function.
+///
+/// It is used to walk the stack for backtraces.
+#[inline(always)]
+pub fn read_frame_pointer() -> usize {
+    let fp;
+    unsafe { core::arch::asm!("mv {}, s0", out(reg) fp) };
+    fp
+}
+
 /// Reads the thread pointer of the current CPU.
 ///
 /// It is used to implement TLS (Thread Local Storage).
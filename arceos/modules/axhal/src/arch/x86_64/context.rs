@@ -74,6 +74,34 @@ impl TrapFrame {
     pub const fn is_user(&self) -> bool {
         self.cs & 0b11 == 3
     }
+
+    /// Gets the instruction pointer.
+    pub const fn get_ip(&self) -> usize {
+        self.rip as _
+    }
+
+    /// Sets the instruction pointer.
+    pub const fn set_ip(&mut self, ip: usize) {
+        self.rip = ip as _;
+    }
+
+    /// Gets the stack pointer.
+    pub const fn get_sp(&self) -> usize {
+        self.rsp as _
+    }
+
+    /// Sets the stack pointer.
+    pub const fn set_sp(&mut self, sp: usize) {
+        self.rsp = sp as _;
+    }
+
+    /// Sets the first three function arguments, used when calling a user
+    /// function such as a signal handler.
+    pub const fn set_args(&mut self, arg0: usize, arg1: usize, arg2: usize) {
+        self.rdi = arg0 as _;
+        self.rsi = arg1 as _;
+        self.rdx = arg2 as _;
+    }
 }
 
 /// Context to enter user space.
@@ -113,6 +141,11 @@ impl UspaceContext {
         Self(tf)
     }
 
+    /// Gets the saved registers.
+    pub const fn trap_frame(&self) -> &TrapFrame {
+        &self.0
+    }
+
     /// Gets the instruction pointer.
     pub const fn get_ip(&self) -> usize {
         self.0.rip as _
@@ -195,7 +228,7 @@ struct ContextSwitchFrame {
 /// See <https://www.felixcloutier.com/x86/fxsave> for more details.
 #[allow(missing_docs)]
 #[repr(C, align(16))]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct FxsaveArea {
     pub fcw: u16,
     pub fsw: u16,
@@ -213,6 +246,7 @@ pub struct FxsaveArea {
 static_assertions::const_assert_eq!(core::mem::size_of::<FxsaveArea>(), 512);
 
 /// Extended state of a task, such as FP/SIMD states.
+#[derive(Clone, Copy)]
 pub struct ExtendedState {
     /// Memory region for the FXSAVE/FXRSTOR instruction.
     pub fxsave_area: FxsaveArea,
@@ -220,17 +254,19 @@ pub struct ExtendedState {
 
 #[cfg(feature = "fp_simd")]
 impl ExtendedState {
+    /// Saves the extended states of the current CPU to this place.
     #[inline]
-    fn save(&mut self) {
+    pub fn save(&mut self) {
         unsafe { core::arch::x86_64::_fxsave64(&mut self.fxsave_area as *mut _ as *mut u8) }
     }
 
+    /// Restores the extended states of the current CPU from this place.
     #[inline]
-    fn restore(&self) {
+    pub fn restore(&self) {
         unsafe { core::arch::x86_64::_fxrstor64(&self.fxsave_area as *const _ as *const u8) }
     }
 
-    const fn default() -> Self {
+    const fn new() -> Self {
         let mut area: FxsaveArea = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
         area.fcw = 0x37f;
         area.ftw = 0xffff;
@@ -239,6 +275,16 @@ impl ExtendedState {
     }
 }
 
+#[cfg(feature = "fp_simd")]
+impl Default for ExtendedState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// FP/SIMD states of a task, saved in the [`ExtendedState`].
+pub type FpState = ExtendedState;
+
 impl fmt::Debug for ExtendedState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("ExtendedState")
@@ -301,7 +347,7 @@ impl TaskContext {
             #[cfg(feature = "uspace")]
             cr3: crate::paging::kernel_page_table_root(),
             #[cfg(feature = "fp_simd")]
-            ext_state: ExtendedState::default(),
+            ext_state: ExtendedState::new(),
             #[cfg(feature = "uspace")]
             gs_base: 0,
         }
@@ -340,6 +386,15 @@ impl TaskContext {
         self.cr3 = cr3;
     }
 
+    /// Copies the extended states of the current CPU into this context.
+    ///
+    /// It initializes the context of a task that duplicates the current one,
+    /// such as a forked child.
+    #[cfg(feature = "fp_simd")]
+    pub fn copy_fp_state(&mut self) {
+        self.ext_state.save();
+    }
+
     /// Switches to another task.
     ///
     /// It first saves the current task's context from CPU to this place, and then
@@ -14,7 +14,7 @@ use memory_addr::{MemoryAddr, PhysAddr, VirtAddr};
 use x86::{controlregs, msr, tlb};
 use x86_64::instructions::interrupts;
 
-pub use self::context::{ExtendedState, FxsaveArea, TaskContext, TrapFrame};
+pub use self::context::{ExtendedState, FpState, FxsaveArea, TaskContext, TrapFrame};
 pub use self::gdt::{init_gdt, tss_get_rsp0, tss_set_rsp0, GdtStruct};
 pub use self::idt::{init_idt, IdtStruct};
 
@@ -94,6 +94,16 @@ pub fn flush_tlb(vaddr: Option<VirtAddr>) {
     }
 }
 
+/// Reads the frame pointer of the current function.
+///
+/// It is used to walk the stack for backtraces.
+#[inline(always)]
+pub fn read_frame_pointer() -> usize {
+    let fp;
+    unsafe { core::arch::asm!("mov {}, rbp", out(reg) fp) };
+    fp
+}
+
 /// Reads the thread pointer of the current CPU.
 ///
 /// It is used to implement TLS (Thread Local Storage).
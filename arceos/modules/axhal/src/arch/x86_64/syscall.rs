@@ -17,6 +17,7 @@ core::arch::global_asm!(
 #[no_mangle]
 pub(super) fn x86_syscall_handler(tf: &mut TrapFrame) {
     tf.rax = crate::trap::handle_syscall(tf, tf.rax as usize) as u64;
+    crate::trap::handle_return_to_user(tf);
 }
 
 /// Initializes syscall support and setups the syscall handler.
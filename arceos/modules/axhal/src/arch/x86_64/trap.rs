@@ -56,6 +56,10 @@ fn x86_trap_handler(tf: &mut TrapFrame) {
             );
         }
     }
+    #[cfg(feature = "uspace")]
+    if tf.is_user() {
+        crate::trap::handle_return_to_user(tf);
+    }
 }
 
 fn vec_to_str(vec: u64) -> &'static str {
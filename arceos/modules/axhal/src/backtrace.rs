@@ -0,0 +1,55 @@
+//! Stack backtraces by walking the frame pointers.
+//!
+//! The result is only complete if the kernel is built with frame pointers
+//! (`-C force-frame-pointers=yes`). Functions without them, such as those in
+//! the precompiled `core`, are skipped, and the walk may stop early. It never
+//! reads outside the given stack range.
+
+use core::ops::Range;
+
+/// The maximum number of frames to walk.
+const MAX_DEPTH: usize = 32;
+
+const WORD: usize = core::mem::size_of::<usize>();
+
+/// Returns the addresses of the saved frame pointer and return address of the
+/// frame whose frame pointer is `fp`.
+fn frame_record(fp: usize) -> (usize, usize) {
+    if cfg!(target_arch = "riscv64") {
+        // The frame pointer points to the top of the frame.
+        (fp.wrapping_sub(2 * WORD), fp.wrapping_sub(WORD))
+    } else {
+        (fp, fp.wrapping_add(WORD))
+    }
+}
+
+/// Calls `f` with the return address of each frame on the current stack,
+/// from the innermost caller outwards.
+///
+/// `stack` is the address range of the current stack.
+#[inline(never)]
+pub fn backtrace(stack: Range<usize>, mut f: impl FnMut(usize)) {
+    let mut fp = crate::arch::read_frame_pointer();
+    for _ in 0..MAX_DEPTH {
+        let (prev_fp_addr, ra_addr) = frame_record(fp);
+        let in_stack = prev_fp_addr >= stack.start && ra_addr.saturating_add(WORD) <= stack.end;
+        if fp % WORD != 0 || !in_stack {
+            break;
+        }
+        let (prev_fp, ra) = unsafe {
+            (
+                (prev_fp_addr as *const usize).read(),
+                (ra_addr as *const usize).read(),
+            )
+        };
+        if ra == 0 {
+            break;
+        }
+        f(ra);
+        // The stack grows downwards, so callers have higher frame pointers.
+        if prev_fp <= fp {
+            break;
+        }
+        fp = prev_fp;
+    }
+}
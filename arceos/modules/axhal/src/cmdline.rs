@@ -0,0 +1,139 @@
+//! The kernel command line passed by the bootloader.
+//!
+//! On x86 it is read from the multiboot information, on RISC-V and AArch64
+//! from the `bootargs` property of the `/chosen` node of the device tree. It
+//! is copied during early boot, before the memory holding the boot information
+//! can be reused.
+
+use lazyinit::LazyInit;
+
+use crate::mem::phys_to_virt;
+
+/// The maximum length of the command line, longer ones are truncated.
+const MAX_CMDLINE_LEN: usize = 1024;
+
+struct CmdLine {
+    buf: [u8; MAX_CMDLINE_LEN],
+    len: usize,
+}
+
+static CMDLINE: LazyInit<CmdLine> = LazyInit::new();
+
+/// Returns the kernel command line, or an empty string if the bootloader did
+/// not provide one.
+pub fn cmdline() -> &'static str {
+    CMDLINE.get().map_or("", |cmdline| {
+        let bytes = &cmdline.buf[..cmdline.len];
+        match core::str::from_utf8(bytes) {
+            Ok(s) => s,
+            // The command line may be truncated in the middle of a character.
+            Err(err) => core::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or(""),
+        }
+    })
+}
+
+/// Copies the null-terminated string at `ptr` as the command line.
+unsafe fn save(ptr: *const u8) {
+    let mut cmdline = CmdLine {
+        buf: [0; MAX_CMDLINE_LEN],
+        len: 0,
+    };
+    while cmdline.len < MAX_CMDLINE_LEN {
+        let c = ptr.add(cmdline.len).read();
+        if c == 0 {
+            break;
+        }
+        cmdline.buf[cmdline.len] = c;
+        cmdline.len += 1;
+    }
+    CMDLINE.init_once(cmdline);
+}
+
+/// Reads the command line from the multiboot information at the physical
+/// address `mbi`.
+#[allow(dead_code)]
+pub(crate) unsafe fn init_from_multiboot(mbi: usize) {
+    /// The `cmdline` field of the multiboot information is valid.
+    const MULTIBOOT_INFO_CMDLINE: u32 = 1 << 2;
+
+    let info = phys_to_virt(mbi.into()).as_ptr() as *const u32;
+    if mbi == 0 || info.read() & MULTIBOOT_INFO_CMDLINE == 0 {
+        return;
+    }
+    let cmdline = info.add(4).read() as usize;
+    save(phys_to_virt(cmdline.into()).as_ptr());
+}
+
+/// Reads the command line from the flattened device tree at the physical
+/// address `dtb`.
+///
+/// Nothing is read if `dtb` does not point to a valid device tree.
+#[allow(dead_code)]
+pub(crate) unsafe fn init_from_dtb(dtb: usize) {
+    const FDT_MAGIC: u32 = 0xd00d_feed;
+    const FDT_BEGIN_NODE: u32 = 1;
+    const FDT_END_NODE: u32 = 2;
+    const FDT_PROP: u32 = 3;
+    const FDT_NOP: u32 = 4;
+
+    if dtb == 0 {
+        return;
+    }
+    let base = phys_to_virt(dtb.into()).as_ptr();
+    // All fields of the device tree are big-endian.
+    let read_u32 = |offset: usize| u32::from_be((base.add(offset) as *const u32).read_unaligned());
+    if read_u32(0) != FDT_MAGIC {
+        return;
+    }
+    let struct_offset = read_u32(8) as usize;
+    let strings_offset = read_u32(12) as usize;
+    let struct_size = read_u32(36) as usize;
+
+    let mut offset = struct_offset;
+    let mut depth = 0;
+    let mut in_chosen = false;
+    while offset < struct_offset + struct_size {
+        let token = read_u32(offset);
+        offset += 4;
+        match token {
+            FDT_BEGIN_NODE => {
+                let name = base.add(offset);
+                depth += 1;
+                in_chosen = depth == 2 && cstr_eq(name, b"chosen");
+                let mut len = 0;
+                while name.add(len).read() != 0 {
+                    len += 1;
+                }
+                offset = (offset + len + 1).next_multiple_of(4);
+            }
+            FDT_END_NODE => {
+                depth -= 1;
+                in_chosen = false;
+            }
+            FDT_PROP => {
+                let len = read_u32(offset) as usize;
+                let name = base.add(strings_offset + read_u32(offset + 4) as usize);
+                let value = base.add(offset + 8);
+                if in_chosen && cstr_eq(name, b"bootargs") {
+                    if len > 0 {
+                        save(value);
+                    }
+                    return;
+                }
+                offset = (offset + 8 + len).next_multiple_of(4);
+            }
+            FDT_NOP => {}
+            _ => return,
+        }
+    }
+}
+
+/// Whether the null-terminated string at `ptr` equals `s`.
+unsafe fn cstr_eq(ptr: *const u8, s: &[u8]) -> bool {
+    for (i, &c) in s.iter().enumerate() {
+        if ptr.add(i).read() != c {
+            return false;
+        }
+    }
+    ptr.add(s.len()).read() == 0
+}
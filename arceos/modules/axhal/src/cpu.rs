@@ -94,6 +94,8 @@ pub(crate) fn init_primary(cpu_id: usize) {
         IS_BSP.write_current_raw(true);
     }
     crate::arch::cpu_init();
+    #[cfg(feature = "uspace")]
+    crate::trap::check_syscall_handler();
 }
 
 #[allow(dead_code)]
@@ -44,7 +44,10 @@ mod platform;
 #[macro_use]
 pub mod trap;
 
+mod cmdline;
+
 pub mod arch;
+pub mod backtrace;
 pub mod cpu;
 pub mod mem;
 pub mod time;
@@ -81,6 +84,7 @@ pub mod mp {
     pub use super::platform::mp::*;
 }
 
+pub use self::cmdline::cmdline;
 pub use self::platform::platform_init;
 
 #[cfg(feature = "smp")]
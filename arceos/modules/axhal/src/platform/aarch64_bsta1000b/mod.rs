@@ -28,6 +28,7 @@ extern "C" {
 pub(crate) unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     crate::mem::clear_bss();
     crate::cpu::init_primary(cpu_id);
+    crate::cmdline::init_from_dtb(dtb);
     dw_apb_uart::init_early();
     super::aarch64_common::generic_timer::init_early();
     rust_main(cpu_id, dtb);
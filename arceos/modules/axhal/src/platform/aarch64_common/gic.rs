@@ -9,12 +9,20 @@ pub const MAX_IRQ_COUNT: usize = 1024;
 /// The timer IRQ number.
 pub const TIMER_IRQ_NUM: usize = translate_irq(14, InterruptType::PPI).unwrap();
 
+/// The IRQ number of inter-processor interrupts, an SGI.
+#[cfg(feature = "smp")]
+pub const IPI_IRQ_NUM: usize = translate_irq(1, InterruptType::SGI).unwrap();
+
 /// The UART IRQ number.
 pub const UART_IRQ_NUM: usize = translate_irq(axconfig::UART_IRQ, InterruptType::SPI).unwrap();
 
 const GICD_BASE: PhysAddr = pa!(axconfig::GICD_PADDR);
 const GICC_BASE: PhysAddr = pa!(axconfig::GICC_PADDR);
 
+/// The offset of the software generated interrupt register in the GICD.
+#[cfg(feature = "smp")]
+const GICD_SGIR: usize = 0xf00;
+
 static GICD: SpinNoIrq<GicDistributor> =
     SpinNoIrq::new(GicDistributor::new(phys_to_virt(GICD_BASE).as_mut_ptr()));
 
@@ -45,6 +53,19 @@ pub fn dispatch_irq(_unused: usize) {
     GICC.handle_irq(|irq_num| crate::irq::dispatch_irq_common(irq_num as _));
 }
 
+/// Sends an inter-processor interrupt to the given CPU.
+#[cfg(feature = "smp")]
+pub fn send_ipi(cpu_id: usize) {
+    // The target list filter 0b00 sends it to the CPUs in the target list.
+    let sgir = (1 << (16 + cpu_id)) | IPI_IRQ_NUM as u32;
+    // Hold the GICD lock to order it with other accesses to the GICD.
+    let _gicd = GICD.lock();
+    unsafe {
+        let ptr = phys_to_virt(GICD_BASE).as_mut_ptr().add(GICD_SGIR) as *mut u32;
+        ptr.write_volatile(sgir);
+    }
+}
+
 /// Initializes GICD, GICC on the primary CPU.
 pub(crate) fn init_primary() {
     info!("Initialize GICv2...");
@@ -56,4 +77,6 @@ pub(crate) fn init_primary() {
 #[cfg(feature = "smp")]
 pub(crate) fn init_secondary() {
     GICC.init();
+    // The enable bits of SGIs and PPIs are banked for each CPU.
+    GICD.lock().set_enable(IPI_IRQ_NUM, true);
 }
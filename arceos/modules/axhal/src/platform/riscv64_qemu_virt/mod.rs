@@ -20,6 +20,7 @@ extern "C" {
 unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     crate::mem::clear_bss();
     crate::cpu::init_primary(cpu_id);
+    crate::cmdline::init_from_dtb(dtb);
     self::time::init_early();
     rust_main(cpu_id, dtb);
 }
@@ -1,4 +1,12 @@
 //! Trap handling.
+//!
+//! Handlers are registered in the slices below with [`register_trap_handler`].
+//! All handlers of a trap are called in ascending order of their priority (see
+//! [`HANDLER_PRIORITY`]), except for [`SYSCALL`], which takes exactly one.
+//! For [`IRQ`] and [`PAGE_FAULT`], the first handler that returns `true`
+//! handles the trap and the rest are skipped.
+
+use core::panic::PanicInfo;
 
 use linkme::distributed_slice as def_trap_handler;
 use memory_addr::VirtAddr;
@@ -18,51 +26,238 @@ pub static IRQ: [fn(usize) -> bool];
 pub static PAGE_FAULT: [fn(VirtAddr, MappingFlags, bool) -> bool];
 
 /// A slice of syscall handler functions.
+///
+/// Exactly one handler must be registered, which is checked at boot.
 #[cfg(feature = "uspace")]
 #[def_trap_handler]
 pub static SYSCALL: [fn(&TrapFrame, usize) -> isize];
 
 // 先将 uspace feature 当做 monolithic feature 使用
+/// A slice of functions called on entry of every trap (including syscalls),
+/// before it is handled.
 #[cfg(feature = "uspace")]
 #[def_trap_handler]
 pub static BEFORE_ALL_TRAPS: [fn()];
 
+/// A slice of functions called on exit of every trap (including syscalls),
+/// right after it is handled.
+///
+/// For a trap from user space, they run before [`RETURN_TO_USER`], so the
+/// work done by the latter, such as delivering signals, happens after them.
 #[cfg(feature = "uspace")]
 #[def_trap_handler]
 pub static AFTER_ALL_TRAPS: [fn()];
 
+/// A slice of functions called right before returning to user space.
+///
+/// They are called for every trap from user space, i.e. syscalls, interrupts
+/// and exceptions, after [`AFTER_ALL_TRAPS`]. The trap (including the syscall
+/// return value) has been fully handled, so they may freely modify the user
+/// context, e.g. to deliver signals or restart the syscall. They may also
+/// block or reschedule the current task.
+#[cfg(feature = "uspace")]
+#[def_trap_handler]
+pub static RETURN_TO_USER: [fn(&mut TrapFrame)];
+
+/// A slice of functions called when the kernel panics, before it halts.
+///
+/// A handler may choose not to return, e.g. to terminate only the current
+/// task instead of halting the whole kernel.
+#[def_trap_handler]
+pub static PANIC: [fn(&PanicInfo)];
+
+/// The priority of a trap handler, registered in [`HANDLER_PRIORITY`].
+pub struct HandlerPriority {
+    handler: *const (),
+    priority: i32,
+}
+
+// The handler pointer is only compared, never called through.
+unsafe impl Sync for HandlerPriority {}
+
+impl HandlerPriority {
+    /// Gives `handler` (cast to `*const ()`) the priority `priority`.
+    pub const fn new(handler: *const (), priority: i32) -> Self {
+        Self { handler, priority }
+    }
+}
+
+/// Priorities of trap handlers.
+///
+/// Handlers of the same trap are called in ascending order of priority.
+/// Handlers not listed here have priority 0, and handlers with the same
+/// priority are called in link order. For example:
+///
+/// ```ignore
+/// #[register_trap_handler(PAGE_FAULT)]
+/// fn cow_fault(vaddr: VirtAddr, flags: MappingFlags, is_user: bool) -> bool {
+///     // ...
+/// }
+///
+/// #[register_trap_handler(HANDLER_PRIORITY)]
+/// static COW_FAULT_PRIORITY: HandlerPriority = HandlerPriority::new(cow_fault as _, -10);
+/// ```
+#[def_trap_handler]
+pub static HANDLER_PRIORITY: [HandlerPriority];
+
+/// Calls `f` on the handlers in `handlers` in order of priority, until it
+/// returns `true`. Returns whether any call returned `true`.
+pub fn for_each_handler<F: Copy>(handlers: &[F], f: impl FnMut(F) -> bool) -> bool {
+    for_each_handler_with(handlers, &HANDLER_PRIORITY, f)
+}
+
+fn for_each_handler_with<F: Copy>(
+    handlers: &[F],
+    priorities: &[HandlerPriority],
+    mut f: impl FnMut(F) -> bool,
+) -> bool {
+    assert_eq!(core::mem::size_of::<F>(), core::mem::size_of::<*const ()>());
+    let priority_of = |handler: &F| {
+        // SAFETY: handlers are function pointers, as checked above.
+        let ptr: *const () = unsafe { core::mem::transmute_copy(handler) };
+        priorities
+            .iter()
+            .find(|p| p.handler == ptr)
+            .map_or(0, |p| p.priority)
+    };
+    // Selects the handlers one by one by (priority, index), so that nothing
+    // needs to be allocated in the trap path. There are only a few handlers.
+    let mut last = None;
+    while let Some((_, index)) = handlers
+        .iter()
+        .enumerate()
+        .map(|(i, handler)| (priority_of(handler), i))
+        .filter(|key| last < Some(*key))
+        .min()
+    {
+        if f(handlers[index]) {
+            return true;
+        }
+        last = Some((priority_of(&handlers[index]), index));
+    }
+    false
+}
+
 #[allow(unused_macros)]
 macro_rules! handle_trap {
     ($trap:ident, $($args:tt)*) => {{
-        // 目前主要用于统计时间
+        // 目前用于统计时间和处理跨核同步请求，所有注册的钩子都会被调用
         #[cfg(feature = "uspace")]
-        if let Some(func) = $crate::trap::BEFORE_ALL_TRAPS.iter().next() {
+        $crate::trap::for_each_handler(&$crate::trap::BEFORE_ALL_TRAPS, |func| {
             func();
-        }
+            false
+        });
 
-        let mut iter = $crate::trap::$trap.iter();
-        let ret = if let Some(func) = iter.next() {
-            if iter.next().is_some() {
-                warn!("Multiple handlers for trap {} are not currently supported", stringify!($trap));
-            }
-            func($($args)*)
-        } else {
+        if $crate::trap::$trap.is_empty() {
             warn!("No registered handler for trap {}", stringify!($trap));
-            false
-        };
+        }
+        let ret = $crate::trap::for_each_handler(&$crate::trap::$trap, |func| func($($args)*));
 
         // 目前主要用于统计时间
         #[cfg(feature = "uspace")]
-        if let Some(func) = $crate::trap::AFTER_ALL_TRAPS.iter().next() {
+        $crate::trap::for_each_handler(&$crate::trap::AFTER_ALL_TRAPS, |func| {
             func();
-        }
+            false
+        });
 
         ret
     }}
 }
 
+/// Checks that exactly one syscall handler is registered.
+#[cfg(feature = "uspace")]
+pub(crate) fn check_syscall_handler() {
+    assert_eq!(
+        SYSCALL.len(),
+        1,
+        "exactly one handler must be registered for trap SYSCALL"
+    );
+}
+
 /// Call the external syscall handler.
 #[cfg(feature = "uspace")]
 pub(crate) fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
-    SYSCALL[0](tf, syscall_num)
+    // 与其他陷入一样，系统调用也需要统计时间
+    for_each_handler(&BEFORE_ALL_TRAPS, |func| {
+        func();
+        false
+    });
+    let ret = SYSCALL[0](tf, syscall_num);
+    for_each_handler(&AFTER_ALL_TRAPS, |func| {
+        func();
+        false
+    });
+    ret
+}
+
+/// Call the external handlers before returning to user space.
+#[cfg(feature = "uspace")]
+pub(crate) fn handle_return_to_user(tf: &mut TrapFrame) {
+    for_each_handler(&RETURN_TO_USER, |func| {
+        func(tf);
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    type PageFaultHandler = fn(VirtAddr, MappingFlags, bool) -> bool;
+
+    /// Handler calls, one decimal digit for each call.
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn record(id: usize) {
+        CALLS.store(CALLS.load(Ordering::Relaxed) * 10 + id, Ordering::Relaxed);
+    }
+
+    fn cow_fault(vaddr: VirtAddr, _flags: MappingFlags, _is_user: bool) -> bool {
+        record(1);
+        vaddr.as_usize() == 0x1000
+    }
+
+    fn stack_growth(vaddr: VirtAddr, _flags: MappingFlags, _is_user: bool) -> bool {
+        record(2);
+        vaddr.as_usize() == 0x2000
+    }
+
+    fn dispatch(
+        handlers: &[PageFaultHandler],
+        priorities: &[HandlerPriority],
+        vaddr: usize,
+    ) -> (bool, usize) {
+        CALLS.store(0, Ordering::Relaxed);
+        let handled = for_each_handler_with(handlers, priorities, |handler| {
+            handler(VirtAddr::from(vaddr), MappingFlags::READ, true)
+        });
+        (handled, CALLS.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn page_fault_handlers() {
+        let handlers: [PageFaultHandler; 2] = [cow_fault, stack_growth];
+
+        // Without priorities, handlers are called in registration order.
+        assert_eq!(dispatch(&handlers, &[], 0x1000), (true, 1));
+        assert_eq!(dispatch(&handlers, &[], 0x2000), (true, 12));
+        assert_eq!(dispatch(&handlers, &[], 0x3000), (false, 12));
+
+        // A lower priority runs first, and the first handler returning `true`
+        // stops the dispatch.
+        let priorities = [HandlerPriority::new(stack_growth as _, -1)];
+        assert_eq!(dispatch(&handlers, &priorities, 0x1000), (true, 21));
+        assert_eq!(dispatch(&handlers, &priorities, 0x2000), (true, 2));
+        assert_eq!(dispatch(&handlers, &priorities, 0x3000), (false, 21));
+
+        let priorities = [
+            HandlerPriority::new(cow_fault as _, 5),
+            HandlerPriority::new(stack_growth as _, 5),
+        ];
+        assert_eq!(dispatch(&handlers, &priorities, 0x3000), (false, 12));
+
+        assert_eq!(dispatch(&[], &priorities, 0x1000), (false, 0));
+    }
 }
@@ -60,6 +60,9 @@ use crate_interface::call_interface;
 
 pub use log::{debug, error, info, trace, warn};
 
+#[cfg(not(feature = "std"))]
+pub mod ring;
+
 /// Prints to the console.
 ///
 /// Equivalent to the [`ax_println!`] macro except that a newline is not printed at
@@ -178,6 +181,15 @@ impl Log for Logger {
                 let cpu_id = call_interface!(LogIf::current_cpu_id);
                 let tid = call_interface!(LogIf::current_task_id);
                 let now = call_interface!(LogIf::current_time);
+                ring::record(format_args!(
+                    "[{:>3}.{:06} {level:<5} {path}:{line}] {args}\n",
+                    now.as_secs(),
+                    now.subsec_micros(),
+                    level = level,
+                    path = path,
+                    line = line,
+                    args = record.args(),
+                ));
                 if let Some(cpu_id) = cpu_id {
                     if let Some(tid) = tid {
                         // show CPU ID and task ID
@@ -0,0 +1,128 @@
+//! A ring buffer that keeps the most recent kernel log records.
+//!
+//! Every record written by the kernel logger is also appended here as a whole
+//! line (without color codes), so that it can be read back later, e.g., by the
+//! `syslog` system call. When the buffer is full, the oldest lines are dropped.
+
+use core::fmt::{self, Write};
+
+use kspin::SpinNoIrq;
+
+/// The capacity of the log ring buffer in bytes.
+pub const LOG_BUF_SIZE: usize = axconfig::LOG_BUF_SIZE;
+
+static LOG_RING: SpinNoIrq<LogRing> = SpinNoIrq::new(LogRing::new());
+
+/// Positions are sequence numbers in the stream of all bytes ever written,
+/// the byte with sequence `s` is stored at `buf[s % LOG_BUF_SIZE]`.
+struct LogRing {
+    buf: [u8; LOG_BUF_SIZE],
+    /// Sequence of the next byte to be written.
+    head: usize,
+    /// Sequence of the oldest byte kept in the buffer, always at a line start.
+    tail: usize,
+    /// Sequence of the next byte to be consumed by [`read`].
+    read_seq: usize,
+    /// Bytes before this sequence are hidden from [`read_all`] by [`clear`].
+    clear_seq: usize,
+}
+
+impl LogRing {
+    const fn new() -> Self {
+        Self {
+            buf: [0; LOG_BUF_SIZE],
+            head: 0,
+            tail: 0,
+            read_seq: 0,
+            clear_seq: 0,
+        }
+    }
+
+    fn byte_at(&self, seq: usize) -> u8 {
+        self.buf[seq % LOG_BUF_SIZE]
+    }
+
+    /// Returns the sequence just after the first `\n` in `[from, self.head)`,
+    /// or `self.head` if there is none.
+    fn next_line(&self, from: usize) -> usize {
+        (from..self.head)
+            .find(|&seq| self.byte_at(seq) == b'\n')
+            .map_or(self.head, |seq| seq + 1)
+    }
+
+    /// Returns the sequence just after the last `\n` in `[from, to)`, or `from`
+    /// if there is none.
+    fn last_line_end(&self, from: usize, to: usize) -> usize {
+        (from..to)
+            .rev()
+            .find(|&seq| self.byte_at(seq) == b'\n')
+            .map_or(from, |seq| seq + 1)
+    }
+
+    fn copy_out(&self, from: usize, to: usize, dst: &mut [u8]) -> usize {
+        for (i, seq) in (from..to).enumerate() {
+            dst[i] = self.byte_at(seq);
+        }
+        to - from
+    }
+}
+
+impl Write for LogRing {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            self.buf[self.head % LOG_BUF_SIZE] = b;
+            self.head += 1;
+        }
+        if self.head - self.tail > LOG_BUF_SIZE {
+            // Drop the partially overwritten oldest line as well.
+            self.tail = self.next_line(self.head - LOG_BUF_SIZE);
+            self.read_seq = self.read_seq.max(self.tail);
+            self.clear_seq = self.clear_seq.max(self.tail);
+        }
+        Ok(())
+    }
+}
+
+/// Appends a whole record to the ring buffer.
+///
+/// The buffer is locked during the whole formatting, so records written
+/// concurrently never interleave.
+pub(crate) fn record(args: fmt::Arguments) {
+    let _ = LOG_RING.lock().write_fmt(args);
+}
+
+/// Copies the most recent lines that fit into `dst`, without consuming them.
+///
+/// Returns the number of bytes copied. Lines removed by [`clear`] are skipped.
+pub fn read_all(dst: &mut [u8]) -> usize {
+    let ring = LOG_RING.lock();
+    let mut from = ring.clear_seq;
+    if ring.head - from > dst.len() {
+        from = ring.next_line(ring.head - dst.len());
+    }
+    ring.copy_out(from, ring.head, dst)
+}
+
+/// Consumes the oldest unread lines that fit into `dst`.
+///
+/// Returns the number of bytes copied, which is 0 if there are no unread
+/// lines or the first one is longer than `dst`.
+pub fn read(dst: &mut [u8]) -> usize {
+    let mut ring = LOG_RING.lock();
+    let from = ring.read_seq;
+    let to = ring.last_line_end(from, ring.head.min(from + dst.len()));
+    ring.read_seq = to;
+    ring.copy_out(from, to, dst)
+}
+
+/// Returns the number of bytes that have not been consumed by [`read`].
+pub fn unread_len() -> usize {
+    let ring = LOG_RING.lock();
+    ring.head - ring.read_seq
+}
+
+/// Discards all lines currently in the buffer for [`read_all`].
+pub fn clear() {
+    let mut ring = LOG_RING.lock();
+    ring.clear_seq = ring.head;
+}
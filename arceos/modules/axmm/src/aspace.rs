@@ -1,15 +1,16 @@
 use core::fmt;
 
-use alloc::vec;
+use alloc::{sync::Arc, vec::Vec};
+use axalloc::global_allocator;
 use axerrno::{ax_err, AxError, AxResult};
 use axhal::mem::phys_to_virt;
 use axhal::paging::{MappingFlags, PageTable};
 use memory_addr::{
     is_aligned_4k, MemoryAddr, PageIter4K, PhysAddr, VirtAddr, VirtAddrRange, PAGE_SIZE_4K,
 };
-use memory_set::{MemoryArea, MemorySet};
+use memory_set::{MappingError, MemoryArea, MemorySet};
 
-use crate::backend::Backend;
+use crate::backend::{alloc_frame, query_frame, zero_frame, Backend, FileSource, SharedFrame};
 use crate::{mapping_err_to_ax_err, KERNEL_ASPACE};
 
 /// The virtual memory address space.
@@ -35,6 +36,37 @@ impl AddrSpace {
         self.va_range.size()
     }
 
+    /// 返回地址空间中已映射区域的总大小（字节）。
+    pub fn mapped_size(&self) -> usize {
+        self.areas.iter().map(|area| area.size()).sum()
+    }
+
+    /// Returns the number of pages in the memory areas that are backed by
+    /// physical frames of their own. Pages mapping the shared zero frame are
+    /// not counted.
+    pub fn resident_pages(&self) -> usize {
+        self.areas
+            .iter()
+            .filter_map(|area| PageIter4K::new(area.start(), area.end()))
+            .flatten()
+            .filter(|&page| {
+                query_frame(&self.pt, page).is_some_and(|(frame, _)| frame != zero_frame())
+            })
+            .count()
+    }
+
+    /// Returns whether a physical frame is mapped at `vaddr`, which may be
+    /// the shared zero frame.
+    pub fn is_resident(&self, vaddr: VirtAddr) -> bool {
+        query_frame(&self.pt, vaddr).is_some()
+    }
+
+    /// Returns whether the page at `vaddr` maps the shared zero frame, so a
+    /// write to it needs a private frame.
+    pub fn maps_zero_frame(&self, vaddr: VirtAddr) -> bool {
+        query_frame(&self.pt, vaddr).is_some_and(|(frame, _)| frame == zero_frame())
+    }
+
     /// Returns the reference to the inner page table.
     pub const fn page_table(&self) -> &PageTable {
         &self.pt
@@ -126,13 +158,40 @@ impl AddrSpace {
     /// The `flags` parameter indicates the mapping permissions and attributes.
     ///
     /// Returns an error if the address range is out of the address space or not
-    /// aligned.
+    /// aligned, and `NoMemory` if the mapping does not fit in memory: a
+    /// populated mapping must fit in the free pages beyond those kept for the
+    /// kernel, and a lazy writable one in the whole memory beyond them, much
+    /// like the heuristic overcommit of Linux.
     pub fn map_alloc(
         &mut self,
         start: VirtAddr,
         size: usize,
         flags: MappingFlags,
         populate: bool,
+    ) -> AxResult {
+        self.map_alloc_area(start, size, flags, populate, None)
+    }
+
+    /// Like [`Self::map_alloc`], and records that `file` is copied into the
+    /// mapping, which the caller does.
+    pub fn map_file_alloc(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        populate: bool,
+        file: Arc<FileSource>,
+    ) -> AxResult {
+        self.map_alloc_area(start, size, flags, populate, Some(file))
+    }
+
+    fn map_alloc_area(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        populate: bool,
+        file: Option<Arc<FileSource>>,
     ) -> AxResult {
         if !self.contains_range(start, size) {
             return ax_err!(InvalidInput, "address out of range");
@@ -140,8 +199,85 @@ impl AddrSpace {
         if !start.is_aligned_4k() || !is_aligned_4k(size) {
             return ax_err!(InvalidInput, "address not aligned");
         }
+        let allocator = global_allocator();
+        let limit = if populate {
+            allocator.pages_above_watermark()
+        } else if flags.contains(MappingFlags::WRITE) {
+            allocator
+                .total_pages()
+                .saturating_sub(allocator.min_free_pages())
+        } else {
+            // Pages that are never written take no memory of their own.
+            usize::MAX
+        };
+        if size / PAGE_SIZE_4K > limit {
+            return ax_err!(NoMemory, "mapping does not fit in memory");
+        }
 
-        let area = MemoryArea::new(start, size, flags, Backend::new_alloc(populate));
+        let backend = Backend::Alloc { populate, file };
+        let area = MemoryArea::new(start, size, flags, backend);
+        self.areas
+            .map(area, &mut self.pt, false)
+            .map_err(|err| match err {
+                // The backend fails to map only when out of memory.
+                MappingError::BadState => AxError::NoMemory,
+                err => mapping_err_to_ax_err(err),
+            })?;
+        Ok(())
+    }
+
+    /// Add a new shared mapping, the `i`-th frame is mapped at
+    /// `start + i * PAGE_SIZE_4K`.
+    ///
+    /// Pages beyond the given frames are left unmapped. The frames are shared
+    /// with the address spaces cloned from this one.
+    ///
+    /// Returns an error if the address range is out of the address space or not
+    /// aligned.
+    pub fn map_shared(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        frames: Arc<[Arc<SharedFrame>]>,
+        flags: MappingFlags,
+    ) -> AxResult {
+        self.map_shared_area(start, size, frames, flags, None)
+    }
+
+    /// Like [`Self::map_shared`], and records that the frames are the pages of
+    /// `file`.
+    pub fn map_file_shared(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        frames: Arc<[Arc<SharedFrame>]>,
+        flags: MappingFlags,
+        file: Arc<FileSource>,
+    ) -> AxResult {
+        self.map_shared_area(start, size, frames, flags, Some(file))
+    }
+
+    fn map_shared_area(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        frames: Arc<[Arc<SharedFrame>]>,
+        flags: MappingFlags,
+        file: Option<Arc<FileSource>>,
+    ) -> AxResult {
+        if !self.contains_range(start, size) {
+            return ax_err!(InvalidInput, "address out of range");
+        }
+        if !start.is_aligned_4k() || !is_aligned_4k(size) {
+            return ax_err!(InvalidInput, "address not aligned");
+        }
+
+        let backend = Backend::Shared {
+            start,
+            frames,
+            file,
+        };
+        let area = MemoryArea::new(start, size, flags, backend);
         self.areas
             .map(area, &mut self.pt, false)
             .map_err(mapping_err_to_ax_err)?;
@@ -165,12 +301,32 @@ impl AddrSpace {
         while let Some(area) = self.areas.find(start) {
             let area_backend = area.backend();
             match area_backend{
-                Backend::Alloc { populate } => {
+                Backend::Alloc { populate, .. } => {
                     if !*populate {
                         let count = (area.end().min(end) - start).align_up_4k() / PAGE_SIZE_4K;
                         for i in 0..count {
                             let addr = start + i * PAGE_SIZE_4K;
-                            area_backend.handle_page_fault_alloc(addr, area.flags(), &mut self.pt, *populate);
+                            // 已经分配过的页不能重新分配，否则其中的数据会丢失；
+                            // 可写区域中映射零页的页要换成私有的页，以便内核直接写入
+                            let writable = area.flags().contains(MappingFlags::WRITE);
+                            if query_frame(&self.pt, addr)
+                                .is_some_and(|(frame, _)| frame != zero_frame() || !writable)
+                            {
+                                continue;
+                            }
+                            // 不能用掉为内核保留的页
+                            if global_allocator().pages_above_watermark() == 0 {
+                                return ax_err!(NoMemory);
+                            }
+                            if !area_backend.handle_page_fault_alloc(
+                                addr,
+                                area.flags(),
+                                area.flags(),
+                                &mut self.pt,
+                                *populate,
+                            ) {
+                                return ax_err!(NoMemory);
+                            }
                         }
                     }
                 }
@@ -187,6 +343,49 @@ impl AddrSpace {
         Ok(())
     }
 
+    /// Returns the flags and the backend of the memory area containing
+    /// `vaddr`, or `None` if no area contains it.
+    pub fn find_area(&self, vaddr: VirtAddr) -> Option<(MappingFlags, &Backend)> {
+        self.areas
+            .find(vaddr)
+            .map(|area| (area.flags(), area.backend()))
+    }
+
+    /// Returns the start address, the size, the flags and the backend of each
+    /// memory area, in ascending order of address.
+    pub fn areas(&self) -> impl Iterator<Item = (VirtAddr, usize, MappingFlags, &Backend)> {
+        self.areas
+            .iter()
+            .map(|area| (area.start(), area.size(), area.flags(), area.backend()))
+    }
+
+    /// Returns whether every page in the given range belongs to some memory
+    /// area of this address space.
+    pub fn is_mapped(&self, start: VirtAddr, size: usize) -> bool {
+        let end = (start + size).align_up_4k();
+        let mut start = start.align_down_4k();
+        while start < end {
+            match self.areas.find(start) {
+                Some(area) => start = area.end(),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Allocates physical frames for all lazily allocated areas.
+    pub fn populate_all(&mut self) -> AxResult {
+        let ranges: Vec<_> = self
+            .areas
+            .iter()
+            .map(|area| (area.start(), area.size()))
+            .collect();
+        for (start, size) in ranges {
+            self.alloc_for_lazy(start, size)?;
+        }
+        Ok(())
+    }
+
     /// Removes mappings within the specified virtual address range.
     ///
     /// Returns an error if the address range is out of the address space or not
@@ -318,9 +517,30 @@ impl AddrSpace {
             .protect_region(start, size, flags, true)
             .map_err(|_| AxError::BadState)?
             .ignore();
+        crate::backend::protect_zero_frames(start, size, flags, &mut self.pt);
         Ok(())
     }
 
+    /// Changes the flags of the memory areas within the specified virtual
+    /// address range to `flags`, splitting the areas at the ends of the range.
+    ///
+    /// Unlike [`Self::protect`], the pages allocated later in the range are
+    /// mapped with the new flags too.
+    ///
+    /// Returns an error if the address range is not aligned or some page in it
+    /// is not in any memory area.
+    pub fn protect_areas(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) -> AxResult {
+        if !start.is_aligned_4k() || !is_aligned_4k(size) {
+            return ax_err!(InvalidInput, "address not aligned");
+        }
+        if !self.is_mapped(start, size) {
+            return ax_err!(NoMemory, "address not mapped");
+        }
+        self.areas
+            .protect(start, size, |_| Some(flags), &mut self.pt)
+            .map_err(mapping_err_to_ax_err)
+    }
+
     /// Removes all mappings in the address space.
     pub fn clear(&mut self) {
         self.areas.clear(&mut self.pt).unwrap();
@@ -339,9 +559,12 @@ impl AddrSpace {
         if let Some(area) = self.areas.find(vaddr) {
             let orig_flags = area.flags();
             if orig_flags.contains(access_flags) {
-                return area
-                    .backend()
-                    .handle_page_fault(vaddr, orig_flags, &mut self.pt);
+                return area.backend().handle_page_fault(
+                    vaddr,
+                    orig_flags,
+                    access_flags,
+                    &mut self.pt,
+                );
             }
         }
         false
@@ -349,8 +572,26 @@ impl AddrSpace {
 
     /// 克隆 AddrSpace。这将创建一个新的页表，并将旧页表中的所有区域（包括内核区域）映射到新的页表中，但仅将用户区域的映射到新的 MemorySet 中。
     ///
-    /// 如果发生错误，新创建的 MemorySet 将被丢弃并返回错误。
+    /// 复制私有的页需要的物理页超过用户可用的页（见 [`GlobalAllocator::pages_above_watermark`]）时返回
+    /// `NoMemory`。如果发生错误，已经建立的部分地址空间会被完全释放。
+    ///
+    /// [`GlobalAllocator::pages_above_watermark`]: axalloc::GlobalAllocator::pages_above_watermark
     pub fn clone_or_err(&mut self) -> AxResult<Self> {
+        // 在建立任何映射之前检查内存是否足够
+        let private_pages = self
+            .areas
+            .iter()
+            .filter(|area| matches!(area.backend(), Backend::Alloc { .. }))
+            .filter_map(|area| PageIter4K::new(area.start(), area.end()))
+            .flatten()
+            .filter(|&page| {
+                query_frame(&self.pt, page).is_some_and(|(frame, _)| frame != zero_frame())
+            })
+            .count();
+        if private_pages > global_allocator().pages_above_watermark() {
+            return ax_err!(NoMemory, "not enough memory to clone the address space");
+        }
+
         // 由于要克隆的这个地址空间可能是用户空间，而用户空间在一开始创建时不会在MemorySet中管理内核区域，而是直接把相关的页表项复制到了新页表中，所以在MemorySet中没有内核区域，需要另外处理。
         let mut new_pt = PageTable::try_new().map_err(|_| AxError::NoMemory)?;
 
@@ -365,9 +606,13 @@ impl AddrSpace {
             );
         }
 
-        // 创建一个新的 MemorySet 并将原始区域映射到新的页表中。
-        let mut new_areas = MemorySet::new();
-        let mut buf = vec![0u8; PAGE_SIZE_4K];
+        // 创建一个新的地址空间并将原始区域映射到新的页表中。出错返回时新的地址空间被丢弃，
+        // 其中已经映射的区域和分配的物理页随之释放。
+        let mut new_aspace = Self {
+            va_range: self.va_range,
+            areas: MemorySet::new(),
+            pt: new_pt,
+        };
         for area in self.areas.iter() {
             let new_area = MemoryArea::new(
                 area.start(),
@@ -375,36 +620,48 @@ impl AddrSpace {
                 area.flags(),
                 area.backend().clone(),
             );
-            new_areas
-                .map(new_area, &mut new_pt, false)
-                .map_err(mapping_err_to_ax_err)?;
-
-            // 将原区域的数据复制到新区域中。
-            buf.resize(buf.capacity().max(area.size()), 0);
-            self.read(area.start(), &mut buf).map_err(|e| {
-                new_areas.clear(&mut new_pt).unwrap();
-                e
-            })?;
-            Self::process_area_data_with_page_table(
-                &new_pt,
-                &self.va_range,
-                area.start(),
-                area.size(),
-                |dst, offset, write_size| unsafe {
-                    core::ptr::copy_nonoverlapping(
-                        buf.as_ptr().add(offset),
-                        dst.as_mut_ptr(),
-                        write_size,
-                    );
-                },
-            )?;
+            new_aspace
+                .areas
+                .map(new_area, &mut new_aspace.pt, false)
+                .map_err(|_| AxError::NoMemory)?;
+
+            // 共享区域映射的是同一组物理页，不需要复制数据。
+            if !matches!(area.backend(), Backend::Alloc { .. }) {
+                continue;
+            }
+
+            // 逐页复制原区域的数据：还没有分配的页在新地址空间中同样不分配，映射零页的页继续共享零页。
+            for page in PageIter4K::new(area.start(), area.end()).unwrap() {
+                let Some((frame, flags)) = query_frame(&self.pt, page) else {
+                    continue;
+                };
+                let new_frame = if frame == zero_frame() {
+                    frame
+                } else {
+                    // 立即分配的区域在映射时已经分配了物理页
+                    let new_frame = query_frame(&new_aspace.pt, page)
+                        .map(|(new_frame, _)| new_frame)
+                        .or_else(|| alloc_frame(false))
+                        .ok_or(AxError::NoMemory)?;
+                    unsafe {
+                        core::ptr::copy_nonoverlapping(
+                            phys_to_virt(frame).as_ptr(),
+                            phys_to_virt(new_frame).as_mut_ptr(),
+                            PAGE_SIZE_4K,
+                        );
+                    }
+                    new_frame
+                };
+                new_aspace
+                    .pt
+                    .remap(page, new_frame, flags)
+                    .map_err(|_| AxError::BadState)?
+                    .1
+                    .ignore();
+            }
         }
 
-        Ok(Self {
-            va_range: self.va_range,
-            areas: new_areas,
-            pt: new_pt,
-        })
+        Ok(new_aspace)
     }
 }
 
@@ -1,11 +1,55 @@
 use axalloc::global_allocator;
 use axhal::mem::{phys_to_virt, virt_to_phys};
 use axhal::paging::{MappingFlags, PageSize, PageTable};
+use lazyinit::LazyInit;
 use memory_addr::{PageIter4K, PhysAddr, VirtAddr, PAGE_SIZE_4K};
 
 use super::Backend;
 
-fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
+/// The frame of zeros shared by all pages of lazy mappings that have been
+/// read but not written yet. It is always mapped read-only.
+static ZERO_FRAME: LazyInit<PhysAddr> = LazyInit::new();
+
+/// Allocates the shared zero frame.
+pub(crate) fn init_zero_frame() {
+    ZERO_FRAME.init_once(alloc_frame(true).expect("failed to allocate the zero frame"));
+}
+
+/// Returns the physical address of the shared zero frame.
+pub(crate) fn zero_frame() -> PhysAddr {
+    *ZERO_FRAME
+}
+
+/// Returns the frame and the flags of the page at `vaddr`, or `None` if no
+/// frame is mapped there yet.
+pub(crate) fn query_frame(pt: &PageTable, vaddr: VirtAddr) -> Option<(PhysAddr, MappingFlags)> {
+    pt.query(vaddr)
+        .ok()
+        .filter(|(_, flags, _)| !flags.is_empty())
+        .map(|(frame, flags, _)| (frame, flags))
+}
+
+/// Takes the write permission from the pages in the range that map the zero
+/// frame, after the permissions of the range changed to `flags`.
+pub(crate) fn protect_zero_frames(
+    start: VirtAddr,
+    size: usize,
+    flags: MappingFlags,
+    pt: &mut PageTable,
+) {
+    if !flags.contains(MappingFlags::WRITE) {
+        return;
+    }
+    for addr in PageIter4K::new(start, start + size).unwrap() {
+        if query_frame(pt, addr).is_some_and(|(frame, _)| frame == zero_frame()) {
+            if let Ok((_, tlb)) = pt.protect(addr, flags - MappingFlags::WRITE) {
+                tlb.flush();
+            }
+        }
+    }
+}
+
+pub(crate) fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
     let vaddr = VirtAddr::from(global_allocator().alloc_pages(1, PAGE_SIZE_4K).ok()?);
     if zeroed {
         unsafe { core::ptr::write_bytes(vaddr.as_mut_ptr(), 0, PAGE_SIZE_4K) };
@@ -14,7 +58,7 @@ fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
     Some(paddr)
 }
 
-fn dealloc_frame(frame: PhysAddr) {
+pub(super) fn dealloc_frame(frame: PhysAddr) {
     let vaddr = phys_to_virt(frame);
     global_allocator().dealloc_pages(vaddr.as_usize(), 1);
 }
@@ -22,7 +66,10 @@ fn dealloc_frame(frame: PhysAddr) {
 impl Backend {
     /// Creates a new allocation mapping backend.
     pub const fn new_alloc(populate: bool) -> Self {
-        Self::Alloc { populate }
+        Self::Alloc {
+            populate,
+            file: None,
+        }
     }
 
     pub(crate) fn map_alloc(
@@ -43,12 +90,19 @@ impl Backend {
         if populate {
             // allocate all possible physical frames for populated mapping.
             for addr in PageIter4K::new(start, start + size).unwrap() {
-                if let Some(frame) = alloc_frame(true) {
+                let mapped = alloc_frame(true).is_some_and(|frame| {
                     if let Ok(tlb) = pt.map(addr, frame, PageSize::Size4K, flags) {
                         tlb.ignore(); // TLB flush on map is unnecessary, as there are no outdated mappings.
+                        true
                     } else {
-                        return false;
+                        dealloc_frame(frame);
+                        false
                     }
+                });
+                if !mapped {
+                    // Free the frames mapped so far, as the area is not added.
+                    self.unmap_alloc(start, addr - start, pt, populate);
+                    return false;
                 }
             }
             true
@@ -77,7 +131,9 @@ impl Backend {
                     return false;
                 }
                 tlb.flush();
-                dealloc_frame(frame);
+                if frame != zero_frame() {
+                    dealloc_frame(frame);
+                }
             } else {
                 // Deallocation is needn't if the page is not mapped.
             }
@@ -85,16 +141,38 @@ impl Backend {
         true
     }
 
+    /// Handles a fault of the `access_flags` access on a lazy mapping.
+    ///
+    /// A page read before it is written maps the shared zero frame read-only,
+    /// and gets a private frame on the first write, so pages that are never
+    /// written take no memory.
     pub(crate) fn handle_page_fault_alloc(
         &self,
         vaddr: VirtAddr,
         orig_flags: MappingFlags,
+        access_flags: MappingFlags,
         pt: &mut PageTable,
         populate: bool,
     ) -> bool {
         if populate {
-            false // Populated mappings should not trigger page faults.
-        } else if let Some(frame) = alloc_frame(true) {
+            return false; // Populated mappings should not trigger page faults.
+        }
+        let write = access_flags.contains(MappingFlags::WRITE);
+        match query_frame(pt, vaddr) {
+            None if !write && orig_flags.contains(MappingFlags::READ) => {
+                return pt
+                    .remap(vaddr, zero_frame(), orig_flags - MappingFlags::WRITE)
+                    .map(|(_, tlb)| tlb.flush())
+                    .is_ok();
+            }
+            // The private frame is zeroed, so nothing is copied from the zero
+            // frame it replaces.
+            None => {}
+            Some((frame, _)) if write && frame == zero_frame() => {}
+            // Another task of the address space has handled the fault.
+            Some((_, flags)) => return flags.contains(access_flags),
+        }
+        if let Some(frame) = alloc_frame(true) {
             // Allocate a physical frame lazily and map it to the fault address.
             // `vaddr` does not need to be aligned. It will be automatically
             // aligned during `pt.remap` regardless of the page size.
@@ -1,20 +1,29 @@
 //! Memory mapping backends.
 
+use ::alloc::{string::String, sync::Arc};
 use axhal::paging::{MappingFlags, PageTable};
-use memory_addr::VirtAddr;
+use memory_addr::{PageIter4K, VirtAddr};
 use memory_set::MappingBackend;
 
 mod alloc;
 mod linear;
+mod shared;
+
+pub(crate) use self::alloc::{
+    alloc_frame, init_zero_frame, protect_zero_frames, query_frame, zero_frame,
+};
+pub use self::shared::SharedFrame;
 
 /// A unified enum type for different memory mapping backends.
 ///
-/// Currently, two backends are implemented:
+/// Currently, three backends are implemented:
 ///
 /// - **Linear**: used for linear mappings. The target physical frames are
 ///   contiguous and their addresses should be known when creating the mapping.
 /// - **Allocation**: used in general, or for lazy mappings. The target physical
 ///   frames are obtained from the global allocator.
+/// - **Shared**: the target physical frames are reference counted and may be
+///   mapped into several address spaces at the same time.
 #[derive(Clone)]
 pub enum Backend {
     /// Linear mapping backend.
@@ -31,13 +40,56 @@ pub enum Backend {
     /// If `populate` is `true`, all physical frames are allocated when the
     /// mapping is created, and no page faults are triggered during the memory
     /// access. Otherwise, the physical frames are allocated on demand (by
-    /// handling page faults).
+    /// handling page faults), and pages that are only read share one zero
+    /// frame until they are written.
     Alloc {
         /// Whether to populate the physical frames when creating the mapping.
         populate: bool,
+        /// The file copied into the mapping, if any.
+        file: Option<Arc<FileSource>>,
+    },
+    /// Shared mapping backend.
+    ///
+    /// The `i`-th frame is mapped at `start + i * PAGE_SIZE_4K`, pages beyond
+    /// the frames are left unmapped. The frames are not deallocated on unmap,
+    /// and address spaces cloned from this one map the same frames.
+    Shared {
+        /// The virtual address where the first frame is mapped.
+        start: VirtAddr,
+        /// The frames to be mapped.
+        frames: Arc<[Arc<SharedFrame>]>,
+        /// The file whose pages are the frames, if any.
+        file: Option<Arc<FileSource>>,
     },
 }
 
+/// The file mapped by a memory area, kept to report the mapping and to check
+/// later changes of its flags.
+///
+/// The areas split from one mapping share the same source.
+#[derive(Debug)]
+pub struct FileSource {
+    /// The path of the file.
+    pub path: String,
+    /// The address where the file is mapped from `offset`.
+    pub start: VirtAddr,
+    /// The offset in the file mapped at `start`.
+    pub offset: usize,
+    /// Whether the mapping is shared with the file, so that writes to it go
+    /// to the file.
+    pub shared: bool,
+    /// Whether the file was open for writing when it was mapped. A shared
+    /// mapping of a file open only for reading cannot be made writable.
+    pub writable: bool,
+}
+
+impl FileSource {
+    /// Returns the offset in the file mapped at `vaddr`.
+    pub fn offset_of(&self, vaddr: VirtAddr) -> usize {
+        self.offset + (vaddr - self.start)
+    }
+}
+
 impl MappingBackend for Backend {
     type Addr = VirtAddr;
     type Flags = MappingFlags;
@@ -45,14 +97,20 @@ impl MappingBackend for Backend {
     fn map(&self, start: VirtAddr, size: usize, flags: MappingFlags, pt: &mut PageTable) -> bool {
         match *self {
             Self::Linear { pa_va_offset } => self.map_linear(start, size, flags, pt, pa_va_offset),
-            Self::Alloc { populate } => self.map_alloc(start, size, flags, pt, populate),
+            Self::Alloc { populate, .. } => self.map_alloc(start, size, flags, pt, populate),
+            Self::Shared {
+                start: base,
+                ref frames,
+                ..
+            } => self.map_shared(start, size, flags, pt, base, frames),
         }
     }
 
     fn unmap(&self, start: VirtAddr, size: usize, pt: &mut PageTable) -> bool {
         match *self {
             Self::Linear { pa_va_offset } => self.unmap_linear(start, size, pt, pa_va_offset),
-            Self::Alloc { populate } => self.unmap_alloc(start, size, pt, populate),
+            Self::Alloc { populate, .. } => self.unmap_alloc(start, size, pt, populate),
+            Self::Shared { .. } => self.unmap_shared(start, size, pt),
         }
     }
 
@@ -63,10 +121,23 @@ impl MappingBackend for Backend {
         new_flags: Self::Flags,
         page_table: &mut Self::PageTable,
     ) -> bool {
-        page_table
-            .protect_region(start, size, new_flags, true)
-            .map(|tlb| tlb.ignore())
-            .is_ok()
+        // Pages not allocated yet are mapped with the new flags of the area
+        // when they are first accessed. A page table entry cannot deny all
+        // access on every architecture, so the pages already mapped in an
+        // area made inaccessible stay readable.
+        let mut pte_flags = new_flags;
+        if !new_flags.intersects(MappingFlags::READ | MappingFlags::WRITE | MappingFlags::EXECUTE) {
+            pte_flags |= MappingFlags::READ;
+        }
+        for addr in PageIter4K::new(start, start + size).unwrap() {
+            if let Ok((_, tlb)) = page_table.protect(addr, pte_flags) {
+                tlb.flush();
+            }
+        }
+        if matches!(self, Self::Alloc { .. }) {
+            alloc::protect_zero_frames(start, size, new_flags, page_table);
+        }
+        true
     }
 }
 
@@ -75,13 +146,23 @@ impl Backend {
         &self,
         vaddr: VirtAddr,
         orig_flags: MappingFlags,
+        access_flags: MappingFlags,
         page_table: &mut PageTable,
     ) -> bool {
         match *self {
             Self::Linear { .. } => false, // Linear mappings should not trigger page faults.
-            Self::Alloc { populate } => {
-                self.handle_page_fault_alloc(vaddr, orig_flags, page_table, populate)
+            Self::Alloc { populate, .. } => {
+                self.handle_page_fault_alloc(vaddr, orig_flags, access_flags, page_table, populate)
             }
+            Self::Shared { .. } => false, // Shared mappings are populated when created.
+        }
+    }
+
+    /// Returns the file mapped by the area, if any.
+    pub fn file(&self) -> Option<&Arc<FileSource>> {
+        match self {
+            Self::Linear { .. } => None,
+            Self::Alloc { file, .. } | Self::Shared { file, .. } => file.as_ref(),
         }
     }
 }
@@ -0,0 +1,87 @@
+use alloc::sync::Arc;
+use axhal::mem::phys_to_virt;
+use axhal::paging::{MappingFlags, PageSize, PageTable};
+use memory_addr::{PageIter4K, PhysAddr, VirtAddr, PAGE_SIZE_4K};
+
+use super::alloc::{alloc_frame, dealloc_frame};
+use super::Backend;
+
+/// A physical frame that can be mapped into several address spaces at the
+/// same time.
+///
+/// The frame is deallocated when the last reference to it is dropped.
+pub struct SharedFrame(PhysAddr);
+
+impl SharedFrame {
+    /// Allocates a zeroed frame, returns `None` if out of memory.
+    pub fn new() -> Option<Self> {
+        alloc_frame(true).map(Self)
+    }
+
+    /// Returns the physical address of the frame.
+    pub const fn paddr(&self) -> PhysAddr {
+        self.0
+    }
+
+    /// Returns a pointer to the frame in the kernel address space.
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        phys_to_virt(self.0).as_mut_ptr()
+    }
+}
+
+impl Drop for SharedFrame {
+    fn drop(&mut self) {
+        dealloc_frame(self.0);
+    }
+}
+
+impl Backend {
+    /// Creates a new shared mapping backend, the `i`-th frame is mapped at
+    /// `start + i * PAGE_SIZE_4K`.
+    pub fn new_shared(start: VirtAddr, frames: Arc<[Arc<SharedFrame>]>) -> Self {
+        Self::Shared {
+            start,
+            frames,
+            file: None,
+        }
+    }
+
+    pub(crate) fn map_shared(
+        &self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        pt: &mut PageTable,
+        base: VirtAddr,
+        frames: &[Arc<SharedFrame>],
+    ) -> bool {
+        debug!(
+            "map_shared: [{:#x}, {:#x}) {:?}",
+            start,
+            start + size,
+            flags
+        );
+        for addr in PageIter4K::new(start, start + size).unwrap() {
+            // Pages beyond the frames are left unmapped.
+            let Some(frame) = frames.get((addr - base) / PAGE_SIZE_4K) else {
+                break;
+            };
+            match pt.map(addr, frame.paddr(), PageSize::Size4K, flags) {
+                Ok(tlb) => tlb.ignore(), // TLB flush on map is unnecessary, as there are no outdated mappings.
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+
+    pub(crate) fn unmap_shared(&self, start: VirtAddr, size: usize, pt: &mut PageTable) -> bool {
+        debug!("unmap_shared: [{:#x}, {:#x})", start, start + size);
+        for addr in PageIter4K::new(start, start + size).unwrap() {
+            // The frames are still owned by the backend, only remove the mapping.
+            if let Ok((_, _, tlb)) = pt.unmap(addr) {
+                tlb.flush();
+            }
+        }
+        true
+    }
+}
@@ -10,7 +10,7 @@ mod aspace;
 mod backend;
 
 pub use self::aspace::AddrSpace;
-pub use self::backend::Backend;
+pub use self::backend::{Backend, FileSource, SharedFrame};
 
 use axerrno::{AxError, AxResult};
 use axhal::mem::phys_to_virt;
@@ -78,6 +78,7 @@ pub fn init_memory_management() {
     debug!("kernel address space init OK: {:#x?}", kernel_aspace);
     KERNEL_ASPACE.init_once(SpinNoIrq::new(kernel_aspace));
     axhal::paging::set_kernel_page_table_root(kernel_page_table_root());
+    backend::init_zero_frame();
 }
 
 /// Initializes kernel paging for secondary CPUs.
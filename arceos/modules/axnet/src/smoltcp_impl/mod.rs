@@ -5,7 +5,7 @@ mod listen_table;
 mod tcp;
 mod udp;
 
-use alloc::vec;
+use alloc::{collections::VecDeque, vec, vec::Vec};
 use core::cell::RefCell;
 use core::ops::DerefMut;
 
@@ -18,7 +18,10 @@ use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
 use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::socket::{self, AnySocket};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
+use smoltcp::wire::{
+    ArpOperation, ArpPacket, ArpRepr, EthernetAddress, EthernetFrame, EthernetProtocol,
+    HardwareAddress, IpAddress, IpCidr, Ipv4Address,
+};
 
 use self::listen_table::ListenTable;
 
@@ -40,6 +43,9 @@ const GATEWAY: &str = env_or_default!("AX_GW");
 const DNS_SEVER: &str = "8.8.8.8";
 const IP_PREFIX: u8 = 24;
 
+const LOOPBACK_IP: Ipv4Address = Ipv4Address::new(127, 0, 0, 1);
+const LOOPBACK_PREFIX: u8 = 8;
+
 const STANDARD_MTU: usize = 1500;
 
 const RANDOM_SEED: u64 = 0xA2CE_05A2_CE05_A2CE;
@@ -58,6 +64,12 @@ struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
 
 struct DeviceWrapper {
     inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    ether_addr: EthernetAddress,
+    /// The IP addresses of the interface, including the loopback address.
+    local_addrs: Vec<IpAddress>,
+    /// Frames sent to the interface itself, which are received again instead
+    /// of being transmitted by the NIC.
+    loopback: RefCell<VecDeque<Vec<u8>>>,
 }
 
 struct InterfaceWrapper {
@@ -134,7 +146,7 @@ impl InterfaceWrapper {
         let mut config = Config::new(HardwareAddress::Ethernet(ether_addr));
         config.random_seed = RANDOM_SEED;
 
-        let mut dev = DeviceWrapper::new(dev);
+        let mut dev = DeviceWrapper::new(dev, ether_addr);
         let iface = Mutex::new(Interface::new(config, &mut dev, Self::current_time()));
         Self {
             name,
@@ -157,6 +169,7 @@ impl InterfaceWrapper {
     }
 
     pub fn setup_ip_addr(&self, ip: IpAddress, prefix_len: u8) {
+        self.dev.lock().local_addrs.push(ip);
         let mut iface = self.iface.lock();
         iface.update_ip_addrs(|ip_addrs| {
             ip_addrs.push(IpCidr::new(ip, prefix_len)).unwrap();
@@ -180,9 +193,39 @@ impl InterfaceWrapper {
 }
 
 impl DeviceWrapper {
-    fn new(inner: AxNetDevice) -> Self {
+    fn new(inner: AxNetDevice, ether_addr: EthernetAddress) -> Self {
         Self {
             inner: RefCell::new(inner),
+            ether_addr,
+            local_addrs: Vec::new(),
+            loopback: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// Whether the frame is sent to the interface itself: it is addressed to
+    /// our MAC address, or it is an ARP request for one of our IP addresses.
+    fn is_local_frame(&self, frame: &[u8]) -> bool {
+        let Ok(frame) = EthernetFrame::new_checked(frame) else {
+            return false;
+        };
+        if frame.dst_addr() == self.ether_addr {
+            return true;
+        }
+        if frame.ethertype() != EthernetProtocol::Arp {
+            return false;
+        }
+        let Ok(packet) = ArpPacket::new_checked(frame.payload()) else {
+            return false;
+        };
+        match ArpRepr::parse(&packet) {
+            Ok(ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Request,
+                target_protocol_addr,
+                ..
+            }) => self
+                .local_addrs
+                .contains(&IpAddress::Ipv4(target_protocol_addr)),
+            _ => false,
         }
     }
 }
@@ -201,6 +244,9 @@ impl Device for DeviceWrapper {
         if !dev.can_transmit() {
             return None;
         }
+        if let Some(frame) = self.loopback.borrow_mut().pop_front() {
+            return Some((AxNetRxToken::Loopback(frame), AxNetTxToken(self)));
+        }
         let rx_buf = match dev.receive() {
             Ok(buf) => buf,
             Err(err) => {
@@ -210,7 +256,10 @@ impl Device for DeviceWrapper {
                 return None;
             }
         };
-        Some((AxNetRxToken(&self.inner, rx_buf), AxNetTxToken(&self.inner)))
+        Some((
+            AxNetRxToken::Device(&self.inner, rx_buf),
+            AxNetTxToken(self),
+        ))
     }
 
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
@@ -220,7 +269,7 @@ impl Device for DeviceWrapper {
             return None;
         }
         if dev.can_transmit() {
-            Some(AxNetTxToken(&self.inner))
+            Some(AxNetTxToken(self))
         } else {
             None
         }
@@ -235,27 +284,43 @@ impl Device for DeviceWrapper {
     }
 }
 
-struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr);
-struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
+enum AxNetRxToken<'a> {
+    /// A frame received by the NIC.
+    Device(&'a RefCell<AxNetDevice>, NetBufPtr),
+    /// A frame sent to the interface itself.
+    Loopback(Vec<u8>),
+}
+struct AxNetTxToken<'a>(&'a DeviceWrapper);
 
 impl<'a> RxToken for AxNetRxToken<'a> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
-        snoop_tcp_packet(self.1.packet(), sockets).ok();
+        let packet = match self {
+            Self::Device(_, rx_buf) => rx_buf.packet(),
+            Self::Loopback(frame) => frame,
+        };
+        snoop_tcp_packet(packet, sockets).ok();
     }
 
     fn consume<R, F>(self, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut rx_buf = self.1;
-        trace!(
-            "RECV {} bytes: {:02X?}",
-            rx_buf.packet_len(),
-            rx_buf.packet()
-        );
-        let result = f(rx_buf.packet_mut());
-        self.0.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
-        result
+        match self {
+            Self::Device(dev, mut rx_buf) => {
+                trace!(
+                    "RECV {} bytes: {:02X?}",
+                    rx_buf.packet_len(),
+                    rx_buf.packet()
+                );
+                let result = f(rx_buf.packet_mut());
+                dev.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
+                result
+            }
+            Self::Loopback(mut frame) => {
+                trace!("RECV {} bytes from loopback: {:02X?}", frame.len(), frame);
+                f(&mut frame)
+            }
+        }
     }
 }
 
@@ -264,9 +329,18 @@ impl<'a> TxToken for AxNetTxToken<'a> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut dev = self.0.borrow_mut();
+        // The frame is built aside, as a buffer of the NIC cannot be given
+        // back without transmitting it.
+        let mut frame = vec![0; len];
+        let ret = f(&mut frame);
+        if self.0.is_local_frame(&frame) {
+            trace!("SEND {} bytes to loopback: {:02X?}", len, frame);
+            self.0.loopback.borrow_mut().push_back(frame);
+            return ret;
+        }
+        let mut dev = self.0.inner.borrow_mut();
         let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
-        let ret = f(tx_buf.packet_mut());
+        tx_buf.packet_mut().copy_from_slice(&frame);
         trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
         dev.transmit(tx_buf).unwrap();
         ret
@@ -317,6 +391,8 @@ pub(crate) fn init(net_dev: AxNetDevice) {
     let ip = IP.parse().expect("invalid IP address");
     let gateway = GATEWAY.parse().expect("invalid gateway IP address");
     eth0.setup_ip_addr(ip, IP_PREFIX);
+    // Packets to the loopback address never leave the interface.
+    eth0.setup_ip_addr(IpAddress::Ipv4(LOOPBACK_IP), LOOPBACK_PREFIX);
     eth0.setup_gateway(gateway);
 
     ETH0.init_once(eth0);
@@ -327,4 +403,5 @@ pub(crate) fn init(net_dev: AxNetDevice) {
     info!("  ether:    {}", ETH0.ethernet_address());
     info!("  ip:       {}/{}", ip, IP_PREFIX);
     info!("  gateway:  {}", gateway);
+    info!("  loopback: {}/{}", LOOPBACK_IP, LOOPBACK_PREFIX);
 }
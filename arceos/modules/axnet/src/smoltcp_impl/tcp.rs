@@ -41,6 +41,7 @@ pub struct TcpSocket {
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
     nonblock: AtomicBool,
+    nodelay: AtomicBool,
 }
 
 unsafe impl Sync for TcpSocket {}
@@ -54,6 +55,7 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             nonblock: AtomicBool::new(false),
+            nodelay: AtomicBool::new(false),
         }
     }
 
@@ -69,6 +71,7 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
             nonblock: AtomicBool::new(false),
+            nodelay: AtomicBool::new(false),
         }
     }
 
@@ -115,6 +118,27 @@ impl TcpSocket {
         self.nonblock.store(nonblocking, Ordering::Release);
     }
 
+    /// Returns whether Nagle's algorithm is disabled on this socket.
+    #[inline]
+    pub fn nodelay(&self) -> bool {
+        self.nodelay.load(Ordering::Acquire)
+    }
+
+    /// Disables or enables Nagle's algorithm, as `TCP_NODELAY` does.
+    ///
+    /// It takes effect at once on a connected socket, and when the socket
+    /// connects otherwise.
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.nodelay.store(nodelay, Ordering::Release);
+        if self.is_connected() {
+            // SAFETY: `self.handle` should be initialized in a connected socket.
+            let handle = unsafe { self.handle.get().read().unwrap() };
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.set_nagle_enabled(!nodelay);
+            });
+        }
+    }
+
     /// Connects to the given address and port.
     ///
     /// The local port is generated automatically.
@@ -126,10 +150,15 @@ impl TcpSocket {
 
             // TODO: check remote addr unreachable
             let remote_endpoint = from_core_sockaddr(remote_addr);
-            let bound_endpoint = self.bound_endpoint()?;
+            let mut bound_endpoint = self.bound_endpoint()?;
+            if bound_endpoint.addr.is_none() && remote_addr.ip().is_loopback() {
+                // Talk to the loopback address from it, as Linux does.
+                bound_endpoint.addr = Some(remote_endpoint.addr);
+            }
             let iface = &ETH0.iface;
             let (local_endpoint, remote_endpoint) = SOCKET_SET
                 .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    socket.set_nagle_enabled(!self.nodelay());
                     socket
                         .connect(iface.lock().context(), remote_endpoint, bound_endpoint)
                         .or_else(|e| match e {
@@ -269,37 +298,34 @@ impl TcpSocket {
         Ok(())
     }
 
-    /// Receives data from the socket, stores it in the given buffer.
-    pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
-        if self.is_connecting() {
-            return Err(AxError::WouldBlock);
-        } else if !self.is_connected() {
-            return ax_err!(NotConnected, "socket recv() failed");
+    /// Closes the sending half of the connection.
+    ///
+    /// The peer receives the end of the stream after the data already sent,
+    /// while this socket can still receive data until the peer closes its
+    /// sending half too.
+    pub fn shutdown_write(&self) -> AxResult {
+        if !self.is_connected() {
+            return ax_err!(NotConnected, "socket shutdown() failed");
         }
-
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() {
-                    // not open
-                    ax_err!(ConnectionRefused, "socket recv() failed")
-                } else if !socket.may_recv() {
-                    // connection closed
-                    Ok(0)
-                } else if socket.recv_queue() > 0 {
-                    // data available
-                    // TODO: use socket.recv(|buf| {...})
-                    let len = socket
-                        .recv_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-                    Ok(len)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
-                }
-            })
-        })
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            debug!("TCP socket {}: closing the sending half", handle);
+            socket.close();
+        });
+        SOCKET_SET.poll_interfaces();
+        Ok(())
+    }
+
+    /// Receives data from the socket, stores it in the given buffer.
+    pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
+        self.recv_impl(buf, false)
+    }
+
+    /// Receives data from the socket like [`recv`](Self::recv), but leaves
+    /// the data in the receive queue.
+    pub fn peek(&self, buf: &mut [u8]) -> AxResult<usize> {
+        self.recv_impl(buf, true)
     }
 
     /// Transmits data in the given buffer.
@@ -348,6 +374,41 @@ impl TcpSocket {
 
 /// Private methods
 impl TcpSocket {
+    fn recv_impl(&self, buf: &mut [u8], peek: bool) -> AxResult<usize> {
+        if self.is_connecting() {
+            return Err(AxError::WouldBlock);
+        } else if !self.is_connected() {
+            return ax_err!(NotConnected, "socket recv() failed");
+        }
+
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        self.block_on(|| {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                if !socket.is_active() {
+                    // not open
+                    ax_err!(ConnectionRefused, "socket recv() failed")
+                } else if !socket.may_recv() {
+                    // connection closed
+                    Ok(0)
+                } else if socket.recv_queue() > 0 {
+                    // data available
+                    // TODO: use socket.recv(|buf| {...})
+                    let len = if peek {
+                        socket.peek_slice(buf)
+                    } else {
+                        socket.recv_slice(buf)
+                    };
+                    let len = len.map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
+                    Ok(len)
+                } else {
+                    // no more data
+                    Err(AxError::WouldBlock)
+                }
+            })
+        })
+    }
+
     #[inline]
     fn get_state(&self) -> u8 {
         self.state.load(Ordering::Acquire)
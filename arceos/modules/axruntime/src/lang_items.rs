@@ -3,5 +3,9 @@ use core::panic::PanicInfo;
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     error!("{}", info);
+    axhal::trap::for_each_handler(&axhal::trap::PANIC, |handler| {
+        handler(info);
+        false
+    });
     axhal::misc::terminate()
 }
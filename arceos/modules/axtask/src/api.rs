@@ -5,7 +5,12 @@ use alloc::{
     sync::{Arc, Weak},
 };
 
-pub(crate) use crate::run_queue::{AxRunQueue, RUN_QUEUE};
+pub(crate) use crate::run_queue::current_run_queue;
+
+#[doc(cfg(feature = "multitask"))]
+pub use crate::run_queue::TASK_SWITCH_HOOKS;
+#[doc(cfg(feature = "multitask"))]
+pub use linkme::distributed_slice as register_switch_hook;
 
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task::{CurrentTask, TaskId, TaskInner};
@@ -60,6 +65,19 @@ pub fn current_may_uninit() -> Option<CurrentTask> {
     CurrentTask::try_get()
 }
 
+/// Returns whether the current task can be preempted, i.e. it is not inside a
+/// critical section that disables preemption, such as holding a spin lock.
+///
+/// Always returns `true` if the `preempt` feature is not enabled, since
+/// critical sections are not tracked then.
+pub fn current_can_preempt() -> bool {
+    #[cfg(feature = "preempt")]
+    if let Some(curr) = current_may_uninit() {
+        return curr.can_preempt(0);
+    }
+    true
+}
+
 /// Gets the current task.
 ///
 /// # Panics
@@ -74,8 +92,6 @@ pub fn init_scheduler() {
     info!("Initialize scheduling...");
 
     crate::run_queue::init();
-    #[cfg(feature = "irq")]
-    crate::timers::init();
 
     info!("  use {} scheduler.", Scheduler::scheduler_name());
 }
@@ -92,13 +108,21 @@ pub fn init_scheduler_secondary() {
 #[doc(cfg(feature = "irq"))]
 pub fn on_timer_tick() {
     crate::timers::check_events();
-    RUN_QUEUE.lock().scheduler_timer_tick();
+    current_run_queue().scheduler_timer_tick();
+}
+
+/// Handles the inter-processor interrupt sent to this CPU when tasks are made
+/// ready for it.
+#[cfg(all(feature = "irq", feature = "smp"))]
+#[doc(cfg(all(feature = "irq", feature = "smp")))]
+pub fn on_ipi() {
+    current_run_queue().handle_ipi();
 }
 
 /// Adds the given task to the run queue, returns the task reference.
 pub fn spawn_task(task: TaskInner) -> AxTaskRef {
     let task_ref = task.into_arc();
-    RUN_QUEUE.lock().add_task(task_ref.clone());
+    crate::run_queue::spawn_task(task_ref.clone());
     task_ref
 }
 
@@ -135,13 +159,49 @@ where
 ///
 /// [CFS]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
 pub fn set_priority(prio: isize) -> bool {
-    RUN_QUEUE.lock().set_current_priority(prio)
+    current_run_queue().set_current_priority(prio)
+}
+
+/// Returns the bit mask of the CPUs that have started scheduling tasks.
+pub fn online_cpumask() -> usize {
+    crate::run_queue::online_cpumask()
+}
+
+/// Sends an inter-processor interrupt to the given CPU, so that it traps into
+/// the kernel as soon as it enables interrupts.
+///
+/// Does nothing if there is no other CPU to interrupt, i.e. the `irq` or
+/// `smp` feature is disabled.
+pub fn kick_cpu(cpu_id: usize) {
+    #[cfg(all(feature = "irq", feature = "smp"))]
+    axhal::irq::send_ipi(cpu_id);
+    #[cfg(not(all(feature = "irq", feature = "smp")))]
+    let _ = cpu_id;
+}
+
+/// Sets the CPUs that `task` is allowed to run on, one bit of `cpumask` for
+/// each CPU ID.
+///
+/// A task running on or queued for a CPU that is no longer allowed moves to
+/// an allowed one when it is rescheduled next time, which happens at once for
+/// the current task. Returns `false` and changes nothing if none of the CPUs
+/// in `cpumask` is online.
+pub fn set_cpumask(task: &TaskInner, cpumask: usize) -> bool {
+    if cpumask & online_cpumask() == 0 {
+        return false;
+    }
+    task.set_cpumask(cpumask);
+    let curr = current();
+    if core::ptr::eq(task, &*curr) && cpumask & (1 << curr.cpu_id()) == 0 {
+        yield_now();
+    }
+    true
 }
 
 /// Current task gives up the CPU time voluntarily, and switches to another
 /// ready task.
 pub fn yield_now() {
-    RUN_QUEUE.lock().yield_current();
+    current_run_queue().yield_current();
 }
 
 /// Current task is going to sleep for the given duration.
@@ -156,14 +216,14 @@ pub fn sleep(dur: core::time::Duration) {
 /// If the feature `irq` is not enabled, it uses busy-wait instead.
 pub fn sleep_until(deadline: axhal::time::TimeValue) {
     #[cfg(feature = "irq")]
-    RUN_QUEUE.lock().sleep_until(deadline);
+    current_run_queue().sleep_until(deadline);
     #[cfg(not(feature = "irq"))]
     axhal::time::busy_wait_until(deadline);
 }
 
 /// Exits the current task.
 pub fn exit(exit_code: i32) -> ! {
-    RUN_QUEUE.lock().exit_current(exit_code)
+    current_run_queue().exit_current(exit_code)
 }
 
 /// The idle task routine.
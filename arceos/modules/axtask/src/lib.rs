@@ -10,9 +10,11 @@
 //!   management and scheduling is used, as well as more task-related APIs.
 //!   Otherwise, only a few APIs with naive implementation is available.
 //! - `irq`: Interrupts are enabled. If this feature is enabled, timer-based
-//!    APIs can be used, such as [`sleep`], [`sleep_until`], and
-//!    [`WaitQueue::wait_timeout`].
+//!    APIs can be used, such as [`sleep`], [`sleep_until`],
+//!    [`WaitQueue::wait_timeout`] and [`register_timeout`].
 //! - `preempt`: Enable preemptive scheduling.
+//! - `smp`: Notify other CPUs with inter-processor interrupts when tasks are
+//!   made ready for them. Each CPU always has its own run queue.
 //! - `sched_fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
 //!   and it can be overriden by other scheduler features.
@@ -53,6 +55,9 @@ cfg_if::cfg_if! {
 
         #[doc(cfg(feature = "multitask"))]
         pub use self::api::*;
+        #[cfg(feature = "irq")]
+        #[doc(cfg(feature = "irq"))]
+        pub use self::timers::{register_timeout, TimerHandle};
         pub use self::api::{sleep, sleep_until, yield_now};
     } else {
         mod api_s;
@@ -1,43 +1,217 @@
+//! Per-CPU run queues.
+//!
+//! Each CPU schedules the tasks in its own run queue, locked only by itself
+//! except when an idle CPU steals a task from a busy one. Tasks that become
+//! ready (spawned or woken up) are pushed to the wake list of the CPU chosen
+//! for them, which is a separate lock that never nests a run queue lock, and
+//! the CPU moves them into its run queue the next time it reschedules. A CPU
+//! other than the current one is notified with an inter-processor interrupt.
+//!
+//! A task woken up on another CPU may be picked before the CPU it blocked on
+//! has finished switching away from it, so a CPU waits until the task is no
+//! longer running anywhere (see [`TaskInner::on_cpu`]) before switching to it.
+
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
-use kspin::SpinNoIrq;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axhal::cpu::this_cpu_id;
+use kspin::{SpinNoIrq, SpinNoIrqGuard};
 use lazyinit::LazyInit;
 use scheduler::BaseScheduler;
 
 use crate::task::{CurrentTask, TaskState};
 use crate::{AxTaskRef, Scheduler, TaskInner, WaitQueue};
 
-// TODO: per-CPU
-pub(crate) static RUN_QUEUE: LazyInit<SpinNoIrq<AxRunQueue>> = LazyInit::new();
+const SMP: usize = axconfig::SMP;
+
+/// The scheduling state of a CPU.
+struct CpuQueue {
+    run_queue: LazyInit<SpinNoIrq<AxRunQueue>>,
+    /// Tasks made ready for this CPU, not moved into its run queue yet.
+    wake_list: SpinNoIrq<Vec<AxTaskRef>>,
+    /// The number of tasks other than the idle task that are running, ready
+    /// or in the wake list of this CPU.
+    nr_tasks: AtomicUsize,
+}
+
+/// The scheduling state of a CPU before it is initialized, used to
+/// initialize [`CPU_QUEUES`].
+#[allow(clippy::declare_interior_mutable_const)]
+const CPU_QUEUE_INIT: CpuQueue = CpuQueue {
+    run_queue: LazyInit::new(),
+    wake_list: SpinNoIrq::new(Vec::new()),
+    nr_tasks: AtomicUsize::new(0),
+};
+
+/// The scheduling states of all CPUs, indexed by CPU ID.
+static CPU_QUEUES: [CpuQueue; SMP] = [CPU_QUEUE_INIT; SMP];
 
 // TODO: per-CPU
 static EXITED_TASKS: SpinNoIrq<VecDeque<AxTaskRef>> = SpinNoIrq::new(VecDeque::new());
 
 static WAIT_FOR_EXIT: WaitQueue = WaitQueue::new();
 
+/// A slice of hook functions called on every context switch.
+///
+/// Each hook receives the outgoing task and the incoming task, in that order.
+/// It is called with the run queue locked and IRQs disabled, right before the
+/// task contexts are switched, so it must not block.
+#[linkme::distributed_slice]
+pub static TASK_SWITCH_HOOKS: [fn(&TaskInner, &TaskInner)];
+
 #[percpu::def_percpu]
 static IDLE_TASK: LazyInit<AxTaskRef> = LazyInit::new();
 
+/// The task switched away from by the last context switch on this CPU, whose
+/// `on_cpu` flag is cleared once the switch is finished.
+#[percpu::def_percpu]
+static PREV_TASK: usize = 0;
+
 pub(crate) struct AxRunQueue {
+    cpu_id: usize,
     scheduler: Scheduler,
 }
 
+/// Locks the run queue of the current CPU.
+///
+/// Holding the lock disables preemption, so the task cannot move to another
+/// CPU until it is released.
+pub(crate) fn current_run_queue() -> SpinNoIrqGuard<'static, AxRunQueue> {
+    loop {
+        let cpu_id = this_cpu_id();
+        let rq = CPU_QUEUES[cpu_id].run_queue.lock();
+        // Preempted and moved to another CPU between the two steps.
+        if rq.cpu_id == this_cpu_id() {
+            return rq;
+        }
+    }
+}
+
+/// Returns the bit mask of the CPUs whose run queue is initialized.
+pub(crate) fn online_cpumask() -> usize {
+    CPU_QUEUES
+        .iter()
+        .enumerate()
+        .filter(|(_, queue)| queue.run_queue.is_inited())
+        .fold(0, |mask, (cpu_id, _)| mask | (1 << cpu_id))
+}
+
+/// Chooses the CPU to run a ready task on: the allowed CPU with the fewest
+/// tasks, preferring the one it ran on last.
+fn select_cpu(task: &TaskInner) -> usize {
+    let prev = task.cpu_id();
+    let allowed = task.cpumask() & online_cpumask();
+    (0..SMP)
+        .filter(|&cpu_id| allowed & (1 << cpu_id) != 0)
+        .min_by_key(|&cpu_id| {
+            let nr_tasks = CPU_QUEUES[cpu_id].nr_tasks.load(Ordering::Relaxed);
+            (nr_tasks, cpu_id != prev)
+        })
+        .unwrap_or(prev)
+}
+
+/// Makes a ready task runnable on the CPU chosen for it.
+///
+/// If `resched` is true and the task goes to the current CPU, the current
+/// task will be preempted when the preemption is enabled.
+fn enqueue_task(task: AxTaskRef, resched: bool) {
+    assert!(task.is_ready());
+    let cpu_id = select_cpu(&task);
+    let queue = &CPU_QUEUES[cpu_id];
+    task.set_cpu_id(cpu_id);
+    queue.nr_tasks.fetch_add(1, Ordering::Relaxed);
+    queue.wake_list.lock().push(task);
+    if cpu_id != this_cpu_id() {
+        #[cfg(all(feature = "irq", feature = "smp"))]
+        axhal::irq::send_ipi(cpu_id);
+    } else if resched {
+        #[cfg(feature = "preempt")]
+        crate::current().set_preempt_pending(true);
+    }
+}
+
+/// Adds a newly created task to the run queue of a CPU.
+pub(crate) fn spawn_task(task: AxTaskRef) {
+    debug!("task spawn: {}", task.id_name());
+    enqueue_task(task, false);
+}
+
+/// Wakes up a blocked task, or does nothing if it is not blocked (e.g. it has
+/// been woken up by another event).
+pub(crate) fn wake_task(task: AxTaskRef, resched: bool) {
+    if task.transition_state(TaskState::Blocked, TaskState::Ready) {
+        debug!("task unblock: {}", task.id_name());
+        enqueue_task(task, resched);
+    }
+}
+
 impl AxRunQueue {
-    pub fn new() -> SpinNoIrq<Self> {
-        let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE).into_arc();
-        let mut scheduler = Scheduler::new();
-        scheduler.add_task(gc_task);
-        SpinNoIrq::new(Self { scheduler })
+    fn new(cpu_id: usize) -> SpinNoIrq<Self> {
+        SpinNoIrq::new(Self {
+            cpu_id,
+            scheduler: Scheduler::new(),
+        })
+    }
+
+    fn queue(&self) -> &'static CpuQueue {
+        &CPU_QUEUES[self.cpu_id]
     }
 
-    pub fn add_task(&mut self, task: AxTaskRef) {
-        debug!("task spawn: {}", task.id_name());
-        assert!(task.is_ready());
-        self.scheduler.add_task(task);
+    /// Moves the tasks in the wake list of this CPU into the run queue.
+    fn drain_wake_list(&mut self) {
+        let tasks = core::mem::take(&mut *self.queue().wake_list.lock());
+        for task in tasks {
+            self.scheduler.add_task(task); // TODO: priority
+        }
+    }
+
+    /// Takes a ready task from a busy CPU that the current task may run on.
+    ///
+    /// Run queues of other CPUs are only tried to lock, as their CPUs may be
+    /// stealing from this one.
+    fn steal_task(&mut self) -> Option<AxTaskRef> {
+        // Other CPUs in order, starting from the next one.
+        let victims = CPU_QUEUES
+            .iter()
+            .cycle()
+            .skip(self.cpu_id + 1)
+            .take(SMP - 1);
+        for victim in victims {
+            // Leave the only task of a CPU to itself.
+            if victim.nr_tasks.load(Ordering::Relaxed) < 2 {
+                continue;
+            }
+            let Some(mut rq) = victim.run_queue.try_lock() else {
+                continue;
+            };
+            let Some(task) = rq.scheduler.pick_next_task() else {
+                continue;
+            };
+            if task.cpumask() & (1 << self.cpu_id) == 0 {
+                rq.scheduler.put_prev_task(task, false);
+                continue;
+            }
+            victim.nr_tasks.fetch_sub(1, Ordering::Relaxed);
+            self.queue().nr_tasks.fetch_add(1, Ordering::Relaxed);
+            task.set_cpu_id(self.cpu_id);
+            trace!("task steal: {} from CPU {}", task.id_name(), rq.cpu_id);
+            return Some(task);
+        }
+        None
+    }
+
+    /// Handles the inter-processor interrupt telling this CPU that tasks have
+    /// been made ready for it.
+    #[cfg(all(feature = "irq", feature = "smp"))]
+    pub fn handle_ipi(&mut self) {
+        self.drain_wake_list();
     }
 
     #[cfg(feature = "irq")]
     pub fn scheduler_timer_tick(&mut self) {
+        self.drain_wake_list();
         let curr = crate::current();
         if !curr.is_idle() && self.scheduler.task_tick(curr.as_task_ref()) {
             #[cfg(feature = "preempt")]
@@ -91,14 +265,18 @@ impl AxRunQueue {
             axhal::misc::terminate();
         } else {
             curr.set_state(TaskState::Exited);
-            curr.notify_exit(exit_code, self);
+            curr.notify_exit(exit_code);
             EXITED_TASKS.lock().push_back(curr.clone());
-            WAIT_FOR_EXIT.notify_one_locked(false, self);
+            WAIT_FOR_EXIT.notify_one(false);
+            self.queue().nr_tasks.fetch_sub(1, Ordering::Relaxed);
             self.resched(false);
         }
         unreachable!("task exited!");
     }
 
+    /// Blocks the current task, calling `wait_queue_push` to record it
+    /// somewhere after its state is set to blocked, so that it can be woken
+    /// up from then on.
     pub fn block_current<F>(&mut self, wait_queue_push: F)
     where
         F: FnOnce(AxTaskRef),
@@ -113,22 +291,11 @@ impl AxRunQueue {
         assert!(curr.can_preempt(1));
 
         curr.set_state(TaskState::Blocked);
+        self.queue().nr_tasks.fetch_sub(1, Ordering::Relaxed);
         wait_queue_push(curr.clone());
         self.resched(false);
     }
 
-    pub fn unblock_task(&mut self, task: AxTaskRef, resched: bool) {
-        debug!("task unblock: {}", task.id_name());
-        if task.is_blocked() {
-            task.set_state(TaskState::Ready);
-            self.scheduler.add_task(task); // TODO: priority
-            if resched {
-                #[cfg(feature = "preempt")]
-                crate::current().set_preempt_pending(true);
-            }
-        }
-    }
-
     #[cfg(feature = "irq")]
     pub fn sleep_until(&mut self, deadline: axhal::time::TimeValue) {
         let curr = crate::current();
@@ -138,9 +305,7 @@ impl AxRunQueue {
 
         let now = axhal::time::wall_time();
         if now < deadline {
-            crate::timers::set_alarm_wakeup(deadline, curr.clone());
-            curr.set_state(TaskState::Blocked);
-            self.resched(false);
+            self.block_current(|task| crate::timers::set_alarm_wakeup(deadline, task));
         }
     }
 }
@@ -153,13 +318,24 @@ impl AxRunQueue {
         if prev.is_running() {
             prev.set_state(TaskState::Ready);
             if !prev.is_idle() {
-                self.scheduler.put_prev_task(prev.clone(), preempt);
+                if prev.cpumask() & (1 << self.cpu_id) != 0 {
+                    self.scheduler.put_prev_task(prev.clone(), preempt);
+                } else {
+                    // Not allowed here by a new CPU mask, move it to another CPU.
+                    self.queue().nr_tasks.fetch_sub(1, Ordering::Relaxed);
+                    enqueue_task(prev.clone(), false);
+                }
             }
         }
-        let next = self.scheduler.pick_next_task().unwrap_or_else(|| unsafe {
-            // Safety: IRQs must be disabled at this time.
-            IDLE_TASK.current_ref_raw().get_unchecked().clone()
-        });
+        self.drain_wake_list();
+        let next = self
+            .scheduler
+            .pick_next_task()
+            .or_else(|| self.steal_task())
+            .unwrap_or_else(|| unsafe {
+                // Safety: IRQs must be disabled at this time.
+                IDLE_TASK.current_ref_raw().get_unchecked().clone()
+            });
         self.switch_to(prev, next);
     }
 
@@ -176,6 +352,17 @@ impl AxRunQueue {
             return;
         }
 
+        for hook in TASK_SWITCH_HOOKS {
+            hook(&prev_task, &next_task);
+        }
+
+        // The task may have blocked on another CPU that is still saving its
+        // context.
+        while next_task.on_cpu() {
+            core::hint::spin_loop();
+        }
+        next_task.set_on_cpu(true);
+
         unsafe {
             let prev_ctx_ptr = prev_task.ctx_mut_ptr();
             let next_ctx_ptr = next_task.ctx_mut_ptr();
@@ -185,9 +372,26 @@ impl AxRunQueue {
             assert!(Arc::strong_count(prev_task.as_task_ref()) > 1);
             assert!(Arc::strong_count(&next_task) >= 1);
 
+            PREV_TASK.write_current_raw(Arc::as_ptr(prev_task.as_task_ref()) as usize);
             CurrentTask::set_current(prev_task, next_task);
             (*prev_ctx_ptr).switch_to(&*next_ctx_ptr);
         }
+        finish_switch();
+    }
+}
+
+/// Marks the task switched away from on this CPU as no longer running, after
+/// its context has been saved.
+///
+/// It is called by the task switched to, right after the switch.
+pub(crate) fn finish_switch() {
+    // Safety: IRQs are disabled during the switch, and the previous task is
+    // kept alive by others until its `on_cpu` flag is cleared.
+    unsafe {
+        let prev = PREV_TASK.read_current_raw() as *const crate::AxTask;
+        if !prev.is_null() {
+            (*prev).set_on_cpu(false);
+        }
     }
 }
 
@@ -199,7 +403,7 @@ fn gc_entry() {
             // Do not do the slow drops in the critical section.
             let task = EXITED_TASKS.lock().pop_front();
             if let Some(task) = task {
-                if Arc::strong_count(&task) == 1 {
+                if Arc::strong_count(&task) == 1 && !task.on_cpu() {
                     // If I'm the last holder of the task, drop it immediately.
                     drop(task);
                 } else {
@@ -213,28 +417,57 @@ fn gc_entry() {
     }
 }
 
+/// Finishes the first switch to a newly created task.
+///
+/// # Safety
+///
+/// It must be called by the new task, which starts running with the run queue
+/// of the CPU still locked by the task switched away from.
+pub(crate) unsafe fn finish_first_switch() {
+    finish_switch();
+    CPU_QUEUES[this_cpu_id()].run_queue.force_unlock();
+}
+
 pub(crate) fn init() {
+    let cpu_id = this_cpu_id();
+
     // Create the `idle` task (not current task).
     const IDLE_TASK_STACK_SIZE: usize = 4096;
     let idle_task = TaskInner::new(|| crate::run_idle(), "idle".into(), IDLE_TASK_STACK_SIZE);
+    idle_task.set_cpu_id(cpu_id);
     IDLE_TASK.with_current(|i| {
         i.init_once(idle_task.into_arc());
     });
 
     // Put the subsequent execution into the `main` task.
-    let main_task = TaskInner::new_init("main".into()).into_arc();
+    let main_task = TaskInner::new_init("main".into());
+    main_task.set_cpu_id(cpu_id);
+    let main_task = main_task.into_arc();
     main_task.set_state(TaskState::Running);
+    CPU_QUEUES[cpu_id].nr_tasks.fetch_add(1, Ordering::Relaxed);
     unsafe { CurrentTask::init_current(main_task) };
 
-    RUN_QUEUE.init_once(AxRunQueue::new());
+    CPU_QUEUES[cpu_id]
+        .run_queue
+        .init_once(AxRunQueue::new(cpu_id));
+    let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE);
+    spawn_task(gc_task.into_arc());
 }
 
 pub(crate) fn init_secondary() {
+    let cpu_id = this_cpu_id();
+
     // Put the subsequent execution into the `idle` task.
-    let idle_task = TaskInner::new_init("idle".into()).into_arc();
+    let idle_task = TaskInner::new_init("idle".into());
+    idle_task.set_cpu_id(cpu_id);
+    let idle_task = idle_task.into_arc();
     idle_task.set_state(TaskState::Running);
     IDLE_TASK.with_current(|i| {
         i.init_once(idle_task.clone());
     });
     unsafe { CurrentTask::init_current(idle_task) }
+
+    CPU_QUEUES[cpu_id]
+        .run_queue
+        .init_once(AxRunQueue::new(cpu_id));
 }
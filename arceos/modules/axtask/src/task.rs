@@ -1,19 +1,20 @@
 use alloc::{boxed::Box, string::String, sync::Arc};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
 
-#[cfg(feature = "preempt")]
-use core::sync::atomic::AtomicUsize;
-
 #[cfg(feature = "tls")]
 use axhal::tls::TlsArea;
+#[cfg(feature = "irq")]
+use kspin::SpinNoIrq;
 
 use axhal::arch::TaskContext;
 use memory_addr::{align_up_4k, VirtAddr};
 
 use crate::task_ext::AxTaskExt;
-use crate::{AxRunQueue, AxTask, AxTaskRef, WaitQueue};
+#[cfg(feature = "irq")]
+use crate::TimerHandle;
+use crate::{AxTask, AxTaskRef, WaitQueue};
 
 /// A unique identifier for a thread.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -39,9 +40,18 @@ pub struct TaskInner {
     entry: Option<*mut dyn FnOnce()>,
     state: AtomicU8,
 
+    /// The CPU whose run queue the task is in, or that it runs on.
+    cpu_id: AtomicUsize,
+    /// The CPUs the task is allowed to run on, one bit for each CPU ID.
+    cpumask: AtomicUsize,
+    /// The context of the task is in use by a CPU, which has switched to it
+    /// and not finished switching away from it.
+    on_cpu: AtomicBool,
+
     in_wait_queue: AtomicBool,
+    /// The timeout that wakes the task up, set while it sleeps with a timeout.
     #[cfg(feature = "irq")]
-    in_timer_list: AtomicBool,
+    alarm: SpinNoIrq<Option<TimerHandle>>,
 
     #[cfg(feature = "preempt")]
     need_resched: AtomicBool,
@@ -140,6 +150,19 @@ impl TaskInner {
         self.state.load(Ordering::Acquire).into()
     }
 
+    /// Returns the bit mask of the CPUs the task is allowed to run on.
+    #[inline]
+    pub fn cpumask(&self) -> usize {
+        self.cpumask.load(Ordering::Acquire)
+    }
+
+    /// Returns the ID of the CPU the task runs on, or will run on when it is
+    /// ready, or ran on last when it is blocked.
+    #[inline]
+    pub fn cpu_id(&self) -> usize {
+        self.cpu_id.load(Ordering::Acquire)
+    }
+
     /// 返回任务的退出码
     pub fn exit_code(&self) -> i32 {
         self.exit_code.load(Ordering::Acquire)
@@ -190,9 +213,12 @@ impl TaskInner {
             is_init: false,
             entry: None,
             state: AtomicU8::new(TaskState::Ready as u8),
+            cpu_id: AtomicUsize::new(0),
+            cpumask: AtomicUsize::new(usize::MAX),
+            on_cpu: AtomicBool::new(false),
             in_wait_queue: AtomicBool::new(false),
             #[cfg(feature = "irq")]
-            in_timer_list: AtomicBool::new(false),
+            alarm: SpinNoIrq::new(None),
             #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
@@ -218,6 +244,7 @@ impl TaskInner {
     pub(crate) fn new_init(name: String) -> Self {
         let mut t = Self::new_common(TaskId::new(), name);
         t.is_init = true;
+        t.on_cpu = AtomicBool::new(true);
         if t.name() == "idle" {
             t.is_idle = true;
         }
@@ -238,6 +265,35 @@ impl TaskInner {
         self.state.store(state as u8, Ordering::Release)
     }
 
+    /// Changes the state from `from` to `to`, or returns `false` if the
+    /// state is not `from`.
+    #[inline]
+    pub(crate) fn transition_state(&self, from: TaskState, to: TaskState) -> bool {
+        self.state
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    #[inline]
+    pub(crate) fn set_cpu_id(&self, cpu_id: usize) {
+        self.cpu_id.store(cpu_id, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn set_cpumask(&self, cpumask: usize) {
+        self.cpumask.store(cpumask, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn on_cpu(&self) -> bool {
+        self.on_cpu.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_on_cpu(&self, on_cpu: bool) {
+        self.on_cpu.store(on_cpu, Ordering::Release);
+    }
+
     #[inline]
     pub(crate) fn is_running(&self) -> bool {
         matches!(self.state(), TaskState::Running)
@@ -248,11 +304,6 @@ impl TaskInner {
         matches!(self.state(), TaskState::Ready)
     }
 
-    #[inline]
-    pub(crate) fn is_blocked(&self) -> bool {
-        matches!(self.state(), TaskState::Blocked)
-    }
-
     #[inline]
     pub(crate) const fn is_init(&self) -> bool {
         self.is_init
@@ -276,13 +327,13 @@ impl TaskInner {
     #[inline]
     #[cfg(feature = "irq")]
     pub(crate) fn in_timer_list(&self) -> bool {
-        self.in_timer_list.load(Ordering::Acquire)
+        self.alarm.lock().is_some()
     }
 
     #[inline]
     #[cfg(feature = "irq")]
-    pub(crate) fn set_in_timer_list(&self, in_timer_list: bool) {
-        self.in_timer_list.store(in_timer_list, Ordering::Release);
+    pub(crate) fn alarm(&self) -> &SpinNoIrq<Option<TimerHandle>> {
+        &self.alarm
     }
 
     #[inline]
@@ -316,20 +367,26 @@ impl TaskInner {
     fn current_check_preempt_pending() {
         let curr = crate::current();
         if curr.need_resched.load(Ordering::Acquire) && curr.can_preempt(0) {
-            let mut rq = crate::RUN_QUEUE.lock();
+            let mut rq = crate::current_run_queue();
             if curr.need_resched.load(Ordering::Acquire) {
                 rq.preempt_resched();
             }
         }
     }
 
-    pub(crate) fn notify_exit(&self, exit_code: i32, rq: &mut AxRunQueue) {
+    pub(crate) fn notify_exit(&self, exit_code: i32) {
         self.exit_code.store(exit_code, Ordering::Release);
-        self.wait_for_exit.notify_all_locked(false, rq);
+        self.wait_for_exit.notify_all(false);
     }
 
+    /// Returns a raw pointer to the task context.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the context is not being accessed
+    /// concurrently, e.g. it is the current task modifying its own context.
     #[inline]
-    pub(crate) const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
+    pub const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
         self.ctx.get()
     }
 
@@ -449,7 +506,7 @@ impl Deref for CurrentTask {
 
 extern "C" fn task_entry() -> ! {
     // release the lock that was implicitly held across the reschedule
-    unsafe { crate::RUN_QUEUE.force_unlock() };
+    unsafe { crate::run_queue::finish_first_switch() };
     #[cfg(feature = "irq")]
     axhal::arch::enable_irqs();
     let task = crate::current();
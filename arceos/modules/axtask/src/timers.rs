@@ -1,48 +1,277 @@
-use alloc::sync::Arc;
-use axhal::time::wall_time;
+//! Timeouts serviced by the timer interrupt.
+//!
+//! All the timeouts of the kernel are kept in one queue ordered by deadline,
+//! so that each timer tick only looks at those that have expired. A timeout
+//! calls its callback at the first tick at or after its deadline, which is at
+//! most one tick late. The callback runs in the interrupt handler with IRQs
+//! disabled, so it must not block: it may only wake tasks up or set flags, and
+//! leave the rest of the work to them.
+//!
+//! Tasks sleeping with a timeout, such as in [`sleep`](crate::sleep) and
+//! [`WaitQueue::wait_timeout`](crate::WaitQueue::wait_timeout), are woken up
+//! by timeouts registered here.
+
+use alloc::{boxed::Box, collections::BTreeMap};
+
+use axhal::time::{wall_time, TimeValue};
 use kspin::SpinNoIrq;
-use lazyinit::LazyInit;
-use timer_list::{TimeValue, TimerEvent, TimerList};
 
-use crate::{AxTaskRef, RUN_QUEUE};
+use crate::AxTaskRef;
+
+/// The function called when a timeout expires, with the current time.
+pub type TimeoutCallback = Box<dyn FnOnce(TimeValue) + Send>;
 
 // TODO: per-CPU
-static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<TaskWakeupEvent>>> = LazyInit::new();
+static TIMEOUTS: SpinNoIrq<TimeoutQueue> = SpinNoIrq::new(TimeoutQueue::new());
+
+/// A registered timeout, used to cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerHandle {
+    deadline: TimeValue,
+    id: u64,
+}
+
+impl TimerHandle {
+    /// Returns the deadline of the timeout, in wall time.
+    pub fn deadline(&self) -> TimeValue {
+        self.deadline
+    }
+
+    /// Cancels the timeout.
+    ///
+    /// Returns `false` if it has already expired, when its callback has been
+    /// called or is being called on another CPU.
+    pub fn cancel(self) -> bool {
+        TIMEOUTS.lock().cancel(self)
+    }
+}
+
+/// Timeouts ordered by deadline, and by registration for equal deadlines.
+pub(crate) struct TimeoutQueue {
+    timeouts: BTreeMap<(TimeValue, u64), TimeoutCallback>,
+    next_id: u64,
+}
 
-struct TaskWakeupEvent(AxTaskRef);
+impl TimeoutQueue {
+    pub const fn new() -> Self {
+        Self {
+            timeouts: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn register(&mut self, deadline: TimeValue, callback: TimeoutCallback) -> TimerHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.timeouts.insert((deadline, id), callback);
+        TimerHandle { deadline, id }
+    }
+
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        self.timeouts
+            .remove(&(handle.deadline, handle.id))
+            .is_some()
+    }
 
-impl TimerEvent for TaskWakeupEvent {
-    fn callback(self, _now: TimeValue) {
-        let mut rq = RUN_QUEUE.lock();
-        self.0.set_in_timer_list(false);
-        rq.unblock_task(self.0, true);
+    /// Removes the earliest timeout if it has expired at `now`.
+    pub fn expire_one(&mut self, now: TimeValue) -> Option<TimeoutCallback> {
+        let entry = self.timeouts.first_entry()?;
+        if entry.key().0 > now {
+            return None;
+        }
+        Some(entry.remove())
+    }
+
+    /// Returns the earliest deadline.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn next_deadline(&self) -> Option<TimeValue> {
+        self.timeouts
+            .first_key_value()
+            .map(|(&(deadline, _), _)| deadline)
     }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn len(&self) -> usize {
+        self.timeouts.len()
+    }
+}
+
+/// Registers `callback` to be called from the timer interrupt at the first
+/// tick at or after `deadline`, in wall time.
+///
+/// The callback runs with IRQs disabled and must not block. It is called even
+/// if the deadline has passed, at the next tick.
+pub fn register_timeout<F>(deadline: TimeValue, callback: F) -> TimerHandle
+where
+    F: FnOnce(TimeValue) + Send + 'static,
+{
+    TIMEOUTS.lock().register(deadline, Box::new(callback))
 }
 
+/// Wakes up `task` at `deadline`, unless the alarm is cancelled before.
 pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
-    let mut timers = TIMER_LIST.lock();
-    task.set_in_timer_list(true);
-    timers.set(deadline, TaskWakeupEvent(task));
+    let mut alarm = task.alarm().lock();
+    let wakeup = task.clone();
+    let handle = register_timeout(deadline, move |_| {
+        wakeup.alarm().lock().take();
+        crate::run_queue::wake_task(wakeup, true);
+    });
+    if let Some(old) = alarm.replace(handle) {
+        old.cancel();
+    }
 }
 
 pub fn cancel_alarm(task: &AxTaskRef) {
-    let mut timers = TIMER_LIST.lock();
-    task.set_in_timer_list(false);
-    timers.cancel(|t| Arc::ptr_eq(&t.0, task));
+    if let Some(handle) = task.alarm().lock().take() {
+        handle.cancel();
+    }
 }
 
+/// Calls the callbacks of the expired timeouts, without holding the lock of
+/// the queue, so that they may register new timeouts.
 pub fn check_events() {
     loop {
         let now = wall_time();
-        let event = TIMER_LIST.lock().expire_one(now);
-        if let Some((_deadline, event)) = event {
-            event.callback(now);
-        } else {
-            break;
+        let callback = TIMEOUTS.lock().expire_one(now);
+        match callback {
+            Some(callback) => callback(now),
+            None => break,
         }
     }
 }
 
-pub fn init() {
-    TIMER_LIST.init_once(SpinNoIrq::new(TimerList::new()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{sync::Arc, vec::Vec};
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use core::time::Duration;
+    use std::sync::Mutex;
+
+    fn ms(ms: u64) -> TimeValue {
+        Duration::from_millis(ms)
+    }
+
+    /// Calls the callbacks expired at `now`, returning how many there were.
+    fn expire(queue: &Mutex<TimeoutQueue>, now: TimeValue) -> usize {
+        let mut count = 0;
+        loop {
+            let callback = queue.lock().unwrap().expire_one(now);
+            let Some(callback) = callback else {
+                return count;
+            };
+            callback(now);
+            count += 1;
+        }
+    }
+
+    #[test]
+    fn expires_in_order() {
+        let queue = Mutex::new(TimeoutQueue::new());
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        for (i, deadline) in [30, 10, 20, 10, 40].into_iter().enumerate() {
+            let fired = fired.clone();
+            queue.lock().unwrap().register(
+                ms(deadline),
+                Box::new(move |_| fired.lock().unwrap().push(i)),
+            );
+        }
+        assert_eq!(expire(&queue, ms(5)), 0);
+        assert_eq!(queue.lock().unwrap().next_deadline(), Some(ms(10)));
+        assert_eq!(expire(&queue, ms(20)), 3);
+        // Equal deadlines expire in the order they are registered.
+        assert_eq!(*fired.lock().unwrap(), [1, 3, 2]);
+        assert_eq!(expire(&queue, ms(100)), 2);
+        assert_eq!(*fired.lock().unwrap(), [1, 3, 2, 0, 4]);
+        assert_eq!(queue.lock().unwrap().next_deadline(), None);
+    }
+
+    #[test]
+    fn cancel() {
+        let mut queue = TimeoutQueue::new();
+        let a = queue.register(ms(10), Box::new(|_| panic!("cancelled")));
+        let b = queue.register(ms(10), Box::new(|_| {}));
+        assert!(queue.cancel(a));
+        assert!(!queue.cancel(a));
+        assert_eq!(queue.len(), 1);
+        assert!(queue.expire_one(ms(10)).is_some());
+        assert!(!queue.cancel(b));
+    }
+
+    #[test]
+    fn callback_registers_timeout() {
+        let queue = Arc::new(Mutex::new(TimeoutQueue::new()));
+        let q = queue.clone();
+        queue.lock().unwrap().register(
+            ms(10),
+            Box::new(move |now| {
+                q.lock().unwrap().register(now, Box::new(|_| {}));
+            }),
+        );
+        assert_eq!(expire(&queue, ms(10)), 2);
+    }
+
+    #[test]
+    fn cancel_racing_expiry() {
+        const COUNT: usize = 10000;
+        let queue = Arc::new(Mutex::new(TimeoutQueue::new()));
+        let fired: Arc<Vec<AtomicBool>> =
+            Arc::new((0..COUNT).map(|_| AtomicBool::new(false)).collect());
+        let handles: Vec<_> = (0..COUNT)
+            .map(|i| {
+                let fired = fired.clone();
+                queue.lock().unwrap().register(
+                    ms(i as u64 % 100),
+                    Box::new(move |_| fired[i].store(true, Ordering::SeqCst)),
+                )
+            })
+            .collect();
+
+        let expirer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || (0..100).map(|t| expire(&queue, ms(t))).sum::<usize>())
+        };
+        let cancelled: Vec<bool> = handles
+            .iter()
+            .rev()
+            .map(|&handle| queue.lock().unwrap().cancel(handle))
+            .collect();
+        let expired = expirer.join().unwrap();
+
+        // Each timeout is either cancelled or expired, never both or neither.
+        for (i, cancelled) in cancelled.into_iter().rev().enumerate() {
+            assert_ne!(cancelled, fired[i].load(Ordering::SeqCst), "timeout {i}");
+        }
+        let cancelled = fired.iter().filter(|f| !f.load(Ordering::SeqCst)).count();
+        assert_eq!(expired + cancelled, COUNT);
+        assert_eq!(queue.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn thousands_of_timeouts() {
+        const COUNT: u64 = 5000;
+        let queue = Mutex::new(TimeoutQueue::new());
+        let fired = Arc::new(AtomicUsize::new(0));
+        let last = Arc::new(Mutex::new(TimeValue::ZERO));
+        // Deadlines in a scrambled order.
+        for i in 0..COUNT {
+            let deadline = ms(i * 7919 % COUNT);
+            let (fired, last) = (fired.clone(), last.clone());
+            queue.lock().unwrap().register(
+                deadline,
+                Box::new(move |_| {
+                    let mut last = last.lock().unwrap();
+                    assert!(*last <= deadline);
+                    *last = deadline;
+                    fired.fetch_add(1, Ordering::Relaxed);
+                }),
+            );
+        }
+        // Expire them in steps, like timer ticks.
+        for tick in (0..COUNT).step_by(10) {
+            assert_eq!(expire(&queue, ms(tick + 9)), 10);
+            assert_eq!(fired.load(Ordering::Relaxed), tick as usize + 10);
+        }
+        assert_eq!(queue.lock().unwrap().len(), 0);
+    }
 }
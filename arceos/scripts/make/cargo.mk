@@ -18,6 +18,8 @@ build_args := \
   $(verbose)
 
 RUSTFLAGS := -C link-arg=-T$(LD_SCRIPT) -C link-arg=-no-pie -C link-arg=-znostart-stop-gc
+# Keep frame pointers so that backtraces can be printed on panics
+RUSTFLAGS += -C force-frame-pointers=yes
 RUSTDOCFLAGS := -Z unstable-options --enable-index-page -D rustdoc::broken_intra_doc_links
 
 ifeq ($(MAKECMDGOALS), doc_check_missing)
@@ -30,6 +30,12 @@ qemu_args-$(BLK) += \
   -device virtio-blk-$(vdev-suffix),drive=disk0 \
   -drive id=disk0,if=none,format=raw,file=$(DISK_IMG)
 
+ifneq ($(DISK2_IMG),)
+  qemu_args-$(BLK) += \
+    -device virtio-blk-$(vdev-suffix),drive=disk1 \
+    -drive id=disk1,if=none,format=raw,file=$(DISK2_IMG)
+endif
+
 qemu_args-$(NET) += \
   -device virtio-net-$(vdev-suffix),netdev=net0
 
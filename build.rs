@@ -106,7 +106,7 @@ fn gen_kernel_config(arch: &str) -> Result<()> {
                     writeln!(f, "pub const {}: usize = {};", key_name, i)?;
                 }
                 toml_edit::Value::String(s) => {
-                    writeln!(f, "pub const {}: &str = \"{}\";", key_name, s)?;
+                    writeln!(f, "pub const {}: &str = {:?};", key_name, s.value())?;
                 }
                 _ => {
                     panic!("Unsupported value type");
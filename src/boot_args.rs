@@ -0,0 +1,137 @@
+//! 内核命令行参数
+//!
+//! 命令行由引导程序传入（如 QEMU 的 `-append`），由以空格分隔的 `key=value` 组成。
+//! 在启动第一个用户进程之前由 [`init`] 解析，之后通过 [`boot_args`] 读取。
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use core::time::Duration;
+
+use lazyinit::LazyInit;
+use memory_addr::PAGE_SIZE_4K;
+
+/// 测例默认的超时时间
+const DEFAULT_TEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 解析后的命令行参数
+#[derive(Debug)]
+pub struct BootArgs {
+    /// 按顺序运行的测例，如 `tests=brk,chdir,clone`，只在 `junior` 模式下使用
+    pub tests: Option<Vec<String>>,
+    /// 每个测例的超时时间，如 `timeout=60`（秒），只在 `junior` 模式下使用
+    pub timeout: Duration,
+    /// init 进程的路径，如 `init=/bin/sh`
+    pub init: Option<String>,
+    /// 日志级别，如 `loglevel=debug`
+    pub loglevel: Option<String>,
+    /// nice 值为 0 的用户任务的时间片长度，如 `timeslice=50`（毫秒）
+    pub timeslice: Duration,
+    /// 块设备缓存的容量（字节），如 `blockcache=4096`（KiB），为 0 时不使用缓存
+    pub block_cache: usize,
+    /// 顺序读取时预读的字节数，如 `readahead=64`（KiB）
+    pub read_ahead: usize,
+    /// 为内核保留的空闲内存（字节），如 `minfree=4096`（KiB）
+    pub min_free: usize,
+    /// 是否在启动时打开系统调用统计，`syscallstats=on` 时打开
+    pub syscall_stats: bool,
+    /// 核心转储文件所在的目录，如 `coredir=/tmp/cores`，默认为进程的当前目录
+    pub core_dir: Option<String>,
+    /// 是否严格执行 W^X，`strictwx=on` 时拒绝同时可写和可执行的匿名映射
+    pub strict_wx: bool,
+}
+
+impl Default for BootArgs {
+    fn default() -> Self {
+        Self {
+            tests: None,
+            timeout: DEFAULT_TEST_TIMEOUT,
+            init: None,
+            loglevel: None,
+            timeslice: Duration::from_millis(crate::config::TIMESLICE_MS as u64),
+            block_cache: crate::config::BLOCK_CACHE_KB * 1024,
+            read_ahead: crate::config::READ_AHEAD_KB * 1024,
+            min_free: crate::config::MIN_FREE_KB * 1024,
+            syscall_stats: false,
+            core_dir: None,
+            strict_wx: false,
+        }
+    }
+}
+
+impl BootArgs {
+    /// 解析命令行，无法识别的参数会被忽略并输出警告
+    fn parse(cmdline: &str) -> Self {
+        let mut args = Self::default();
+        for arg in cmdline.split_whitespace() {
+            let (key, value) = arg.split_once('=').unwrap_or((arg, ""));
+            match key {
+                "tests" => {
+                    args.tests = Some(
+                        value
+                            .split(',')
+                            .filter(|test| !test.is_empty())
+                            .map(ToString::to_string)
+                            .collect(),
+                    )
+                }
+                "timeout" => match value.parse() {
+                    Ok(secs) if secs > 0 => args.timeout = Duration::from_secs(secs),
+                    _ => warn!("Ignoring invalid boot argument: {}", arg),
+                },
+                "init" if !value.is_empty() => args.init = Some(value.into()),
+                "coredir" if value.starts_with('/') => args.core_dir = Some(value.into()),
+                "loglevel"
+                    if ["off", "error", "warn", "info", "debug", "trace"].contains(&value) =>
+                {
+                    args.loglevel = Some(value.into())
+                }
+                "syscallstats" if ["on", "off"].contains(&value) => {
+                    args.syscall_stats = value == "on"
+                }
+                "strictwx" if ["on", "off"].contains(&value) => args.strict_wx = value == "on",
+                "timeslice" => match value.parse() {
+                    Ok(ms) if ms > 0 => args.timeslice = Duration::from_millis(ms),
+                    _ => warn!("Ignoring invalid boot argument: {}", arg),
+                },
+                "blockcache" => match value.parse::<usize>() {
+                    Ok(kb) => args.block_cache = kb * 1024,
+                    _ => warn!("Ignoring invalid boot argument: {}", arg),
+                },
+                "readahead" => match value.parse::<usize>() {
+                    Ok(kb) => args.read_ahead = kb * 1024,
+                    _ => warn!("Ignoring invalid boot argument: {}", arg),
+                },
+                "minfree" => match value.parse::<usize>() {
+                    Ok(kb) => args.min_free = kb * 1024,
+                    _ => warn!("Ignoring invalid boot argument: {}", arg),
+                },
+                _ => warn!("Ignoring unknown boot argument: {}", arg),
+            }
+        }
+        args
+    }
+}
+
+static BOOT_ARGS: LazyInit<BootArgs> = LazyInit::new();
+
+/// 解析内核命令行，并按其中的参数设置日志输出、块设备缓存和为内核保留的内存
+///
+/// 必须在启动任何用户进程之前调用。
+pub fn init() {
+    let cmdline = axhal::cmdline();
+    info!("Kernel command line: {:?}", cmdline);
+    let args = BOOT_ARGS.init_once(BootArgs::parse(cmdline));
+    if let Some(level) = &args.loglevel {
+        axlog::set_max_level(level);
+    }
+    axfs::set_block_cache(args.block_cache, args.read_ahead);
+    axalloc::global_allocator().set_min_free_pages(args.min_free / PAGE_SIZE_4K);
+}
+
+/// 返回解析后的命令行参数，只能在 [`init`] 之后调用
+pub fn boot_args() -> &'static BootArgs {
+    &BOOT_ARGS
+}
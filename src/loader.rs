@@ -5,9 +5,13 @@
 //! Now these apps are loaded into memory as a part of the kernel image.
 use alloc::{collections::btree_map::BTreeMap, vec::Vec};
 
+use axerrno::{AxError, AxResult};
 use axhal::paging::MappingFlags;
 use memory_addr::{MemoryAddr, VirtAddr};
 
+/// auxv 中表示时钟频率的条目，对应 `sysconf(_SC_CLK_TCK)`
+const AT_CLKTCK: u8 = 17;
+
 /// The segment of the elf file, which is used to map the elf file to the memory space
 pub struct ELFSegment {
     /// The start virtual address of the segment
@@ -32,28 +36,29 @@ pub struct ELFInfo {
     pub auxv: BTreeMap<u8, usize>,
 }
 
-/// Load the ELF files by the given app name and return
-/// the segments of the ELF file
+/// Parse the given ELF file and return the segments of the ELF file
 ///
 /// # Arguments
-/// * `name` - The name of the app
+/// * `elf_data` - The content of the ELF file
 /// * `base_addr` - The minimal address of user space
 ///
 /// # Returns
-/// Entry and information about segments of the given ELF file
-pub(crate) fn load_elf(name: &str, base_addr: VirtAddr) -> ELFInfo {
+/// Entry and information about segments of the given ELF file,
+/// or [`AxError::InvalidData`] if it is not a valid ELF file for this architecture
+pub(crate) fn load_elf(elf_data: &[u8], base_addr: VirtAddr) -> AxResult<ELFInfo> {
     use xmas_elf::program::{Flags, SegmentData};
     use xmas_elf::{header, ElfFile};
 
-    let elf_data = if let Ok(ans) = axfs::api::read(name) {
-        ans
-    } else {
-        panic!("App not found: {}", name);
-    };
-    let elf = ElfFile::new(&elf_data).expect("Error parsing app ELF file.");
+    let elf = ElfFile::new(elf_data).map_err(|err| {
+        warn!("Error parsing app ELF file: {}", err);
+        AxError::InvalidData
+    })?;
     let elf_header = elf.header;
 
-    assert_eq!(elf_header.pt1.magic, *b"\x7fELF", "invalid elf!");
+    if elf_header.pt1.magic != *b"\x7fELF" {
+        warn!("invalid elf!");
+        return Err(AxError::InvalidData);
+    }
 
     let expect_arch = if cfg!(target_arch = "x86_64") {
         header::Machine::X86_64
@@ -64,11 +69,10 @@ pub(crate) fn load_elf(name: &str, base_addr: VirtAddr) -> ELFInfo {
     } else {
         panic!("Unsupported architecture!");
     };
-    assert_eq!(
-        elf.header.pt2.machine().as_machine(),
-        expect_arch,
-        "invalid ELF arch"
-    );
+    if elf.header.pt2.machine().as_machine() != expect_arch {
+        warn!("invalid ELF arch");
+        return Err(AxError::InvalidData);
+    }
 
     fn into_mapflag(f: Flags) -> MappingFlags {
         let mut ret = MappingFlags::USER;
@@ -86,36 +90,57 @@ pub(crate) fn load_elf(name: &str, base_addr: VirtAddr) -> ELFInfo {
 
     let mut segments = Vec::new();
 
-    let elf_offset = kernel_elf_parser::get_elf_base_addr(&elf, base_addr.as_usize()).unwrap();
-    assert!(
-        memory_addr::is_aligned_4k(elf_offset),
-        "ELF base address must be aligned to 4k"
-    );
+    let elf_offset =
+        kernel_elf_parser::get_elf_base_addr(&elf, base_addr.as_usize()).map_err(|err| {
+            warn!("invalid ELF base address: {}", err);
+            AxError::InvalidData
+        })?;
+    if !memory_addr::is_aligned_4k(elf_offset) {
+        warn!("ELF base address must be aligned to 4k");
+        return Err(AxError::InvalidData);
+    }
 
-    elf.program_iter()
+    for ph in elf
+        .program_iter()
         .filter(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Load))
-        .for_each(|ph| {
-            // align the segment to 4k
-            let st_vaddr = VirtAddr::from(ph.virtual_addr() as usize) + elf_offset;
-            let st_vaddr_align: VirtAddr = st_vaddr.align_down_4k();
-            let ed_vaddr_align = VirtAddr::from((ph.virtual_addr() + ph.mem_size()) as usize)
-                .align_up_4k()
-                + elf_offset;
-            let data = match ph.get_data(&elf).unwrap() {
-                SegmentData::Undefined(data) => data,
-                _ => panic!("failed to get ELF segment data"),
-            };
-            segments.push(ELFSegment {
-                start_vaddr: st_vaddr_align,
-                size: ed_vaddr_align.as_usize() - st_vaddr_align.as_usize(),
-                flags: into_mapflag(ph.flags()),
-                data: data.to_vec(),
-                offset: st_vaddr.align_offset_4k(),
-            });
+    {
+        let end = ph
+            .virtual_addr()
+            .checked_add(ph.mem_size())
+            .filter(|_| ph.file_size() <= ph.mem_size())
+            .ok_or_else(|| {
+                warn!("invalid ELF segment size");
+                AxError::InvalidData
+            })?;
+        // align the segment to 4k
+        let st_vaddr = VirtAddr::from(ph.virtual_addr() as usize) + elf_offset;
+        let st_vaddr_align: VirtAddr = st_vaddr.align_down_4k();
+        let ed_vaddr_align = VirtAddr::from(end as usize).align_up_4k() + elf_offset;
+        let data = match ph.get_data(&elf) {
+            Ok(SegmentData::Undefined(data)) => data,
+            _ => {
+                warn!("failed to get ELF segment data");
+                return Err(AxError::InvalidData);
+            }
+        };
+        segments.push(ELFSegment {
+            start_vaddr: st_vaddr_align,
+            size: ed_vaddr_align.as_usize() - st_vaddr_align.as_usize(),
+            flags: into_mapflag(ph.flags()),
+            data: data.to_vec(),
+            offset: st_vaddr.align_offset_4k(),
         });
-    ELFInfo {
+    }
+    let mut auxv = kernel_elf_parser::get_auxv_vector(&elf, elf_offset);
+    auxv.insert(AT_CLKTCK, crate::task::USER_HZ as usize);
+    auxv.insert(
+        crate::mm::vdso::AT_SYSINFO_EHDR,
+        crate::config::VDSO_BASE + memory_addr::PAGE_SIZE_4K,
+    );
+
+    Ok(ELFInfo {
         entry: VirtAddr::from(elf.header.pt2.entry_point() as usize + elf_offset),
         segments,
-        auxv: kernel_elf_parser::get_auxv_vector(&elf, elf_offset),
-    }
+        auxv,
+    })
 }
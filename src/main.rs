@@ -11,18 +11,22 @@ extern crate axstd;
 mod config {
     include!(concat!(env!("OUT_DIR"), "/uspace_config.rs"));
 }
+mod boot_args;
 mod loader;
 mod mm;
+#[cfg(feature = "junior")]
+mod runner;
+mod smp;
 mod syscall_imp;
 mod task;
 
-use alloc::sync::Arc;
+use alloc::{string::String, sync::Arc, vec::Vec};
 
 use axhal::arch::UspaceContext;
 use axsync::Mutex;
+use axtask::{AxTaskRef, TaskExtRef};
 
-static VFAT12_IMG: &'static [u8] = include_bytes!("../vfat12.img");
-
+#[cfg(feature = "junior")]
 const JUNIOR: &[&str] = &[
     "brk", "chdir", "clone", "close", "dup2", "dup", "execve", "exit", "fork", "fstat", "getcwd",
     "getdents", "getpid", "getppid", "gettimeofday", "mkdir_", "mmap", "mount", "munmap", "openat",
@@ -37,28 +41,73 @@ fn main() {
     // .split(',')
     // .filter(|&x| !x.is_empty());
 
-    // 为mount和umount测例准备 FAT12 文件系统镜像
-    let _ = axfs::fops::File::open(
-        "/vda2",
-        &axfs::fops::OpenOptions::new()
-            .set_crate(true, true)
-            .set_read(true)
-            .set_write(true),
-    )
-    .inspect_err(|err| debug!("Failed to open /vda2: {:?}", err))
-    .and_then(|mut file| file.write(VFAT12_IMG))
-    .inspect_err(|err| debug!("Failed to write /dev/vda2: {:?}", err));
+    boot_args::init();
+    task::procfs::init();
+    task::ioprio::init();
+    mm::vdso::init();
+    syscall_imp::init_inotify();
+    syscall_imp::init_syscall_stats();
+    syscall_imp::init_sysctl();
+
+    #[cfg(feature = "junior")]
+    match &boot_args::boot_args().tests {
+        Some(tests) => runner::run_testcases(tests),
+        None => runner::run_testcases(JUNIOR),
+    }
+    #[cfg(not(feature = "junior"))]
+    run_init();
+
+    // init 退出或所有测例运行结束，写回文件系统后关机，使评测环境中的 QEMU 能够退出
+    syscall_imp::shutdown();
+}
 
-    // 加载并运行测试用例
-    let testcases = JUNIOR;
-    for testcase in testcases {
-        info!("Running testcase: {}", testcase);
-        let (entry_vaddr, ustack_top, uspace) = mm::load_user_app(testcase).unwrap();
-        let user_task = task::spawn_user_task(
-            Arc::new(Mutex::new(uspace)),
-            UspaceContext::new(entry_vaddr.into(), ustack_top, 2333),
-        );
-        let exit_code = user_task.join();
-        info!("User task {} exited with code: {:?}", testcase, exit_code);
+/// 内核主线程检查孤儿进程和测例是否退出的间隔
+const POLL_INTERVAL: core::time::Duration = core::time::Duration::from_millis(10);
+
+/// 加载程序并创建用户进程，失败时返回 `None`
+fn spawn_user_app(path: &str, args: Vec<String>, envs: &[String]) -> Option<AxTaskRef> {
+    let (entry_vaddr, ustack_top, uspace) = mm::load_user_app(path, &args, envs)
+        .inspect_err(|err| error!("Failed to load {}: {:?}", path, err))
+        .ok()?;
+    let user_task = task::spawn_user_task(
+        Arc::new(Mutex::new(uspace)),
+        UspaceContext::new(entry_vaddr.into(), ustack_top, 2333),
+    );
+    user_task.set_name(path);
+    *user_task.task_ext().cmdline.lock() = args;
+    Some(user_task)
+}
+
+/// 启动 init 进程，此后只回收父进程已经退出的僵尸进程，直到 init 退出
+///
+/// init 的路径依次取自命令行参数 `init=`、编译时的环境变量 `AX_INIT` 和配置文件，
+/// 参数和环境变量来自配置文件。
+#[cfg(not(feature = "junior"))]
+fn run_init() {
+    let boot_args = boot_args::boot_args();
+    if boot_args.tests.is_some() {
+        warn!("Boot argument `tests` is only used with the `junior` feature");
+    }
+    let path = boot_args
+        .init
+        .as_deref()
+        .or(option_env!("AX_INIT"))
+        .unwrap_or(config::INIT_PATH);
+    let args = core::iter::once(path)
+        .chain(config::INIT_ARGS.split_whitespace())
+        .map(String::from)
+        .collect();
+    let envs: Vec<_> = config::INIT_ENVS
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    let Some(init) = spawn_user_app(path, args, &envs) else {
+        return;
+    };
+    while init.state() != axtask::TaskState::Exited {
+        task::reap_orphans();
+        axtask::sleep(POLL_INTERVAL);
     }
+    task::reap_orphans();
+    info!("Init process exited with code: {}", init.exit_code());
 }
@@ -1,36 +1,51 @@
-use alloc::{string::ToString, vec};
+use alloc::{string::String, vec::Vec};
+use core::sync::atomic::AtomicU32;
 
-use axerrno::AxResult;
-use axhal::{
-    paging::MappingFlags,
-    trap::{register_trap_handler, PAGE_FAULT},
-};
+use axerrno::{AxError, AxResult};
+use axhal::{mem::phys_to_virt, paging::MappingFlags};
 use axmm::AddrSpace;
-use axtask::TaskExtRef;
-use memory_addr::VirtAddr;
+use axtask::{current, TaskExtRef};
+use memory_addr::{MemoryAddr, PageIter4K, VirtAddr, PAGE_SIZE_4K};
 
-use crate::{config, loader};
+use crate::{config, loader, task::signal::SIGRETURN_TRAMPOLINE};
 
-/// Load a user app.
+mod fault;
+pub mod file_map;
+pub mod vdso;
+
+/// Load a user app with the given arguments and environment variables.
 ///
 /// # Returns
 /// - The first return value is the entry point of the user app.
 /// - The second return value is the top of the user stack.
 /// - The third return value is the address space of the user app.
-pub fn load_user_app(app_name: &str) -> AxResult<(VirtAddr, VirtAddr, AddrSpace)> {
+pub fn load_user_app(
+    path: &str,
+    args: &[String],
+    envs: &[String],
+) -> AxResult<(VirtAddr, VirtAddr, AddrSpace)> {
     let mut uspace = axmm::new_user_aspace(
         VirtAddr::from_usize(config::USER_SPACE_BASE),
         config::USER_SPACE_SIZE,
     )?;
-    let (entry, ustack_pointer) = map_elf_sections(app_name, &mut uspace)?;
+    let elf_data = axfs::api::read(path)?;
+    let elf_info = loader::load_elf(&elf_data, uspace.base())?;
+    let (entry, ustack_pointer) = map_elf_sections(elf_info, args, envs, &mut uspace)?;
     Ok((entry, ustack_pointer, uspace))
 }
 
+/// Map the ELF file parsed by [`loader::load_elf`] into the address space, and
+/// set up the user stack with the given arguments and environment variables.
+///
+/// # Returns
+/// - The first return value is the entry point of the user app.
+/// - The second return value is the top of the user stack.
 pub fn map_elf_sections(
-    app_name: &str,
+    elf_info: loader::ELFInfo,
+    args: &[String],
+    envs: &[String],
     uspace: &mut AddrSpace,
 ) -> Result<(VirtAddr, VirtAddr), axerrno::AxError> {
-    let elf_info = loader::load_elf(app_name, uspace.base());
     for segement in elf_info.segments {
         debug!(
             "Mapping ELF segment: [{:#x?}, {:#x?}) flags: {:#x?}",
@@ -60,15 +75,9 @@ pub fn map_elf_sections(
         "Mapping user stack: {:#x?} -> {:#x?}",
         ustack_start, ustack_end
     );
-    let mut args = vec![app_name.to_string()];
-    if ["mount", "umount"].contains(&app_name) {
-        // /vda2 是提前准备好的 FAT12 文件系统镜像
-        args.push("/vda2".to_string());
-    }
-    // FIXME: Add more arguments and environment variables
     let (stack_data, ustack_pointer) = kernel_elf_parser::get_app_stack_region(
-        &args,
-        &[],
+        args,
+        envs,
         &elf_info.auxv,
         ustack_start,
         ustack_size,
@@ -81,27 +90,136 @@ pub fn map_elf_sections(
     )?;
 
     uspace.write(VirtAddr::from_usize(ustack_pointer), stack_data.as_slice())?;
+
+    // 信号处理函数返回时执行的跳板代码
+    let trampoline = VirtAddr::from_usize(config::SIGNAL_TRAMPOLINE);
+    uspace.map_alloc(
+        trampoline,
+        PAGE_SIZE_4K,
+        MappingFlags::READ | MappingFlags::EXECUTE | MappingFlags::USER,
+        true,
+    )?;
+    uspace.write(trampoline, SIGRETURN_TRAMPOLINE)?;
+
+    vdso::map_vdso(uspace)?;
+
     Ok((elf_info.entry, VirtAddr::from(ustack_pointer)))
 }
 
-#[register_trap_handler(PAGE_FAULT)]
-fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool) -> bool {
-    if is_user {
-        if !axtask::current()
-            .task_ext()
-            .aspace
-            .lock()
-            .handle_page_fault(vaddr, access_flags)
-        {
-            warn!(
-                "{}: segmentation fault at {:#x}, exit!",
-                axtask::current().id_name(),
-                vaddr
-            );
-            axtask::exit(-1);
+/// 确保用户地址空间中 `[start, start + size)` 内的每一页都已分配物理页，且允许 `access` 方式访问
+///
+/// 对于延迟分配的区域，会像处理缺页异常一样为其分配物理页，写入映射零页的页时换成私有的页。
+fn populate_user_range(
+    aspace: &mut AddrSpace,
+    start: VirtAddr,
+    size: usize,
+    access: MappingFlags,
+) -> AxResult {
+    let access = access | MappingFlags::USER;
+    let end = start
+        .as_usize()
+        .checked_add(size)
+        .and_then(|end| end.checked_next_multiple_of(PAGE_SIZE_4K))
+        .ok_or(AxError::BadAddress)?;
+    let end = VirtAddr::from_usize(end);
+    for page in PageIter4K::new(start.align_down_4k(), end).ok_or(AxError::BadAddress)? {
+        match aspace.page_table().query(page) {
+            Ok((_, flags, _)) if flags.contains(access) => {}
+            // 没有分配的页，或写入映射零页的页
+            _ => {
+                if !aspace.handle_page_fault(page, access) {
+                    return Err(AxError::BadAddress);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 将数据写入给定的用户地址空间，目标区域必须可写
+pub fn copy_to_aspace(aspace: &mut AddrSpace, dst: VirtAddr, data: &[u8]) -> AxResult {
+    populate_user_range(aspace, dst, data.len(), MappingFlags::WRITE)?;
+    aspace.write(dst, data)
+}
+
+/// 从给定的用户地址空间读取数据，源区域必须可读
+pub fn copy_from_aspace(aspace: &mut AddrSpace, src: VirtAddr, buf: &mut [u8]) -> AxResult {
+    populate_user_range(aspace, src, buf.len(), MappingFlags::READ)?;
+    aspace.read(src, buf)
+}
+
+/// 将数据写入当前任务的用户地址空间，目标区域必须可写
+pub fn copy_to_user(dst: VirtAddr, data: &[u8]) -> AxResult {
+    copy_to_aspace(&mut current().task_ext().aspace.lock(), dst, data)
+}
+
+/// 从当前任务的用户地址空间读取数据，源区域必须可读
+pub fn copy_from_user(src: VirtAddr, buf: &mut [u8]) -> AxResult {
+    copy_from_aspace(&mut current().task_ext().aspace.lock(), src, buf)
+}
+
+/// 返回用户地址空间中 `vaddr` 处的 32 位字，所在的页必须允许 `access` 方式访问
+///
+/// 通过内核对物理页的映射访问，与用户程序在同一个字上的原子操作互斥。返回的引用在
+/// 地址空间被修改之前一直有效。`vaddr` 没有按 4 字节对齐时返回 [`AxError::InvalidInput`]。
+pub fn user_atomic_u32(
+    aspace: &mut AddrSpace,
+    vaddr: VirtAddr,
+    access: MappingFlags,
+) -> AxResult<&AtomicU32> {
+    if !vaddr.is_aligned(4usize) {
+        return Err(AxError::InvalidInput);
+    }
+    populate_user_range(aspace, vaddr, 4, access)?;
+    let (paddr, _, _) = aspace
+        .page_table()
+        .query(vaddr)
+        .map_err(|_| AxError::BadAddress)?;
+    // SAFETY: 物理页已映射到地址空间中，且地址按 4 字节对齐
+    Ok(unsafe { &*(phys_to_virt(paddr).as_ptr() as *const AtomicU32) })
+}
+
+/// 从用户地址 `ptr` 处读取一个 `T` 类型的值
+pub fn read_user<T: Copy>(ptr: *const T) -> AxResult<T> {
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    let buf = unsafe {
+        core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, core::mem::size_of::<T>())
+    };
+    copy_from_user(VirtAddr::from_ptr_of(ptr), buf)?;
+    Ok(unsafe { value.assume_init() })
+}
+
+/// 从用户地址 `ptr` 处读取以 `\0` 结尾的字符串，不含结尾最长 `max_len` 字节
+///
+/// 按页读取，不会越过结尾所在的页。地址无效时返回 [`AxError::BadAddress`]，超过长度时
+/// 返回 [`AxError::InvalidInput`]，不是合法的 UTF-8 时返回 [`AxError::InvalidData`]。
+pub fn read_user_str(ptr: *const u8, max_len: usize) -> AxResult<String> {
+    let mut addr = VirtAddr::from_ptr_of(ptr);
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = PAGE_SIZE_4K - addr.align_offset_4k();
+        let start = bytes.len();
+        bytes.resize(start + chunk, 0);
+        copy_from_user(addr, &mut bytes[start..])?;
+        if let Some(len) = bytes[start..].iter().position(|&b| b == 0) {
+            bytes.truncate(start + len);
+            break;
         }
-        true
-    } else {
-        false
+        if bytes.len() > max_len {
+            return Err(AxError::InvalidInput);
+        }
+        addr += chunk;
+    }
+    if bytes.len() > max_len {
+        return Err(AxError::InvalidInput);
     }
+    String::from_utf8(bytes).map_err(|_| AxError::InvalidData)
+}
+
+/// 将 `value` 写入用户地址 `ptr` 处
+pub fn write_user<T: Copy>(ptr: *mut T, value: &T) -> AxResult {
+    let buf = unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    };
+    copy_to_user(VirtAddr::from_mut_ptr_of(ptr), buf)
 }
@@ -0,0 +1,161 @@
+//! 用户态缺页的分类与处理
+//!
+//! 所有用户态缺页都由这里处理：先根据地址空间中包含缺页地址的区域和访问类型判断缺页的
+//! 种类，再按种类解决。没有区域能够解决的缺页向进程发送 SIGSEGV 或 SIGBUS。
+//! 新的缺页来源（如写时复制）应在 [`classify`] 中加入新的种类，而不是另外注册处理函数。
+//!
+//! 系统调用直接读写用户传入的缓冲区时，内核态也会在用户地址上缺页，其中按需分配和写零页
+//! 的缺页同样在这里解决。
+
+use axalloc::global_allocator;
+use axhal::{
+    paging::MappingFlags,
+    trap::{register_trap_handler, PAGE_FAULT},
+};
+use axmm::{AddrSpace, Backend};
+use axtask::{current, TaskExtRef};
+use memory_addr::VirtAddr;
+
+use crate::task::signal::{SigInfo, BUS_ADRERR, SEGV_ACCERR, SEGV_MAPERR, SIGBUS, SIGSEGV};
+
+/// 包含缺页地址的区域
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    /// 区域的访问权限
+    flags: MappingFlags,
+    /// 区域中的页是否在首次访问时才分配
+    lazy: bool,
+}
+
+/// 分类缺页时需要从地址空间查询的信息
+trait AreaTable {
+    /// 返回包含 `vaddr` 的区域
+    fn area(&self, vaddr: VirtAddr) -> Option<Area>;
+
+    /// 返回 `vaddr` 所在的页在页表中的权限，还没有映射物理页面时返回 `None`
+    fn mapped_flags(&self, vaddr: VirtAddr) -> Option<MappingFlags>;
+
+    /// 返回 `vaddr` 所在的页是否映射了共享的零页
+    fn maps_zero_frame(&self, vaddr: VirtAddr) -> bool;
+}
+
+impl AreaTable for AddrSpace {
+    fn area(&self, vaddr: VirtAddr) -> Option<Area> {
+        self.find_area(vaddr).map(|(flags, backend)| Area {
+            flags,
+            lazy: matches!(
+                backend,
+                Backend::Alloc {
+                    populate: false,
+                    ..
+                }
+            ),
+        })
+    }
+
+    fn mapped_flags(&self, vaddr: VirtAddr) -> Option<MappingFlags> {
+        self.page_table()
+            .query(vaddr)
+            .ok()
+            .map(|(_, flags, _)| flags)
+            .filter(|flags| !flags.is_empty())
+    }
+
+    fn maps_zero_frame(&self, vaddr: VirtAddr) -> bool {
+        AddrSpace::maps_zero_frame(self, vaddr)
+    }
+}
+
+/// 缺页的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FaultKind {
+    /// 按需分配的区域（堆、匿名映射等）中还没有分配的页
+    LazyAlloc,
+    /// 写只读过的按需分配的页，该页映射了共享的零页，需要换成私有的页
+    ZeroPageWrite,
+    /// 页已经以足够的权限映射，如同一地址空间的其他任务已经处理了该缺页，重新执行即可
+    Spurious,
+    /// 地址不属于任何区域
+    MapError,
+    /// 区域或页的权限不允许这次访问，如写只读的区域
+    AccessError,
+    /// 区域中没有可以提供的页，如超出了映射文件的末尾
+    BusError,
+}
+
+/// 根据包含 `vaddr` 的区域和访问类型 `access` 判断缺页的种类
+fn classify(table: &impl AreaTable, vaddr: VirtAddr, access: MappingFlags) -> FaultKind {
+    let Some(area) = table.area(vaddr) else {
+        return FaultKind::MapError;
+    };
+    if !area.flags.contains(access) {
+        return FaultKind::AccessError;
+    }
+    match table.mapped_flags(vaddr) {
+        Some(flags) if flags.contains(access) => FaultKind::Spurious,
+        Some(_) if access.contains(MappingFlags::WRITE) && table.maps_zero_frame(vaddr) => {
+            FaultKind::ZeroPageWrite
+        }
+        // 页表与区域的权限不一致，不能靠重新执行解决
+        Some(_) => FaultKind::AccessError,
+        None if area.lazy => FaultKind::LazyAlloc,
+        None => FaultKind::BusError,
+    }
+}
+
+/// 解决当前任务在用户态发生的缺页，无法解决时向进程发送信号
+fn handle_user_fault(vaddr: VirtAddr, access: MappingFlags) {
+    let curr = current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    let kind = classify(&*aspace, vaddr, access);
+    let signal = match kind {
+        FaultKind::Spurious => None,
+        // 内存不足，为内核保留的页也不分配给用户程序
+        FaultKind::LazyAlloc | FaultKind::ZeroPageWrite => {
+            let out_of_memory = global_allocator().pages_above_watermark() == 0
+                || !aspace.handle_page_fault(vaddr, access);
+            out_of_memory.then_some((SIGBUS, BUS_ADRERR))
+        }
+        FaultKind::MapError => Some((SIGSEGV, SEGV_MAPERR)),
+        FaultKind::AccessError => Some((SIGSEGV, SEGV_ACCERR)),
+        FaultKind::BusError => Some((SIGBUS, BUS_ADRERR)),
+    };
+    drop(aspace);
+    if let Some((signo, code)) = signal {
+        warn!(
+            "{}: unresolved page fault ({:?}) at {:#x}",
+            curr.id_name(),
+            kind,
+            vaddr
+        );
+        curr.task_ext()
+            .force_signal(SigInfo::fault(signo, code, vaddr.as_usize()));
+    }
+}
+
+/// 解决内核直接访问当前任务的用户内存时发生的缺页，返回是否解决
+///
+/// 只解决按需分配和写零页的缺页，其余的缺页说明内核访问了无效的地址。
+fn handle_kernel_fault(vaddr: VirtAddr, access: MappingFlags) -> bool {
+    let curr = current();
+    if unsafe { curr.task_ext_ptr() }.is_null() {
+        return false;
+    }
+    let mut aspace = curr.task_ext().aspace.lock();
+    match classify(&*aspace, vaddr, access) {
+        FaultKind::Spurious => true,
+        FaultKind::LazyAlloc | FaultKind::ZeroPageWrite => aspace.handle_page_fault(vaddr, access),
+        _ => false,
+    }
+}
+
+/// 用户态的缺页总是在这里处理完毕，内核态的缺页只解决用户地址上可以解决的，其余交给内核自身处理
+#[register_trap_handler(PAGE_FAULT)]
+fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool) -> bool {
+    if is_user {
+        handle_user_fault(vaddr, access_flags);
+        true
+    } else {
+        handle_kernel_fault(vaddr, access_flags)
+    }
+}
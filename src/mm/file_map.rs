@@ -0,0 +1,253 @@
+//! 普通文件的共享映射
+//!
+//! 文件系统没有页缓存，`MAP_SHARED` 映射普通文件时，文件的页被读入可共享的物理页并登记在
+//! 这里，同一文件的所有共享映射（包括其他进程中的）映射同一组物理页，彼此能看到对方的修改。
+//!
+//! 映射中的修改不会自动出现在文件中：`msync`、`sync` 写回脏页，页的最后一个映射被解除
+//! （`munmap`、`exec`、进程退出）后写回并释放它。`read` 和 `write` 不经过这些物理页，
+//! 文件被 `write` 修改后，映射者需要用 `msync(MS_INVALIDATE)` 重新读入干净页。
+//!
+//! 页表中没有可用的脏位，每页记录读入或写回时内容的哈希值，内容与之不同的页就是脏页。
+
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use arceos_posix_api::FILE_TIMES;
+use axerrno::{AxError, AxResult};
+use axfs::fops::{File, OpenOptions};
+use axmm::SharedFrame;
+use axsync::Mutex;
+use axtask::WaitQueue;
+use memory_addr::{PhysAddr, PAGE_SIZE_4K};
+
+/// 已读入内存的文件页
+struct FilePage {
+    frame: Arc<SharedFrame>,
+    /// 读入或最近一次写回时页内容的哈希值
+    hash: u64,
+}
+
+struct FilePages {
+    /// 以文件的实际路径为键，记录文件中已读入的页
+    files: BTreeMap<String, BTreeMap<usize, FilePage>>,
+    /// 物理页所属的文件及其页号
+    owners: BTreeMap<PhysAddr, (String, usize)>,
+}
+
+static FILE_PAGES: Mutex<FilePages> = Mutex::new(FilePages {
+    files: BTreeMap::new(),
+    owners: BTreeMap::new(),
+});
+
+/// 等待 `MS_ASYNC` 写回的页：文件路径、页号，以及是否重新读入干净页
+static PENDING: Mutex<Vec<(String, Vec<usize>, bool)>> = Mutex::new(Vec::new());
+
+/// 写回线程在其上等待新的写回请求
+static WRITEBACK_WQ: WaitQueue = WaitQueue::new();
+
+/// 是否有尚未处理的写回请求
+static QUEUED: AtomicBool = AtomicBool::new(false);
+
+/// 写回线程是否已经启动
+static DAEMON_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// 返回文件 `path` 中 `[offset, offset + len)` 在文件末尾之内的各页对应的物理页，用于共享映射
+///
+/// `offset` 必须按页对齐，`path` 是文件的实际路径。还没有读入的页从文件中读入，
+/// 最后一页超出文件末尾的部分为 0；超出文件末尾的页不会映射，访问它们时进程收到 SIGBUS。
+pub fn shared_frames(path: &str, offset: usize, len: usize) -> AxResult<Arc<[Arc<SharedFrame>]>> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    let file = File::open(path, &options)?;
+    let size = file.get_attr()?.size() as usize;
+    let end = offset.saturating_add(len).min(size);
+
+    let mut state = FILE_PAGES.lock();
+    let FilePages { files, owners } = &mut *state;
+    let pages = files.entry(path.into()).or_default();
+    let mut frames = Vec::new();
+    for index in offset / PAGE_SIZE_4K..end.div_ceil(PAGE_SIZE_4K) {
+        if let Some(page) = pages.get(&index) {
+            frames.push(page.frame.clone());
+            continue;
+        }
+        let frame = Arc::new(SharedFrame::new().ok_or(AxError::NoMemory)?);
+        read_page(&file, index, &frame)?;
+        owners.insert(frame.paddr(), (path.into(), index));
+        pages.insert(
+            index,
+            FilePage {
+                hash: page_hash(&frame),
+                frame: frame.clone(),
+            },
+        );
+        frames.push(frame);
+    }
+    Ok(frames.into())
+}
+
+/// 写回物理页 `frames` 中属于文件共享映射的脏页，`invalidate` 时重新读入其中的干净页，
+/// 不属于文件的物理页被忽略
+///
+/// `wait` 为 `false` 时只把这些页交给写回线程，不等待写回完成。
+pub fn sync(frames: &[PhysAddr], wait: bool, invalidate: bool) -> AxResult {
+    let mut state = FILE_PAGES.lock();
+    let mut files = BTreeMap::<_, Vec<_>>::new();
+    for (path, index) in frames.iter().filter_map(|paddr| state.owners.get(paddr)) {
+        files.entry(path.clone()).or_default().push(*index);
+    }
+    if !wait {
+        drop(state);
+        let mut pending = PENDING.lock();
+        pending.extend(
+            files
+                .into_iter()
+                .map(|(path, indices)| (path, indices, invalidate)),
+        );
+        start_daemon();
+        QUEUED.store(true, Ordering::SeqCst);
+        WRITEBACK_WQ.notify_one(false);
+        return Ok(());
+    }
+    for (path, indices) in files {
+        sync_pages(&mut state, &path, &indices, invalidate)?;
+    }
+    Ok(())
+}
+
+/// 写回所有文件共享映射中的脏页，并释放已经没有映射的页
+pub fn sync_all() {
+    writeback(true);
+}
+
+/// 释放已经没有映射的页，释放前写回其中的脏页
+pub fn release_unused() {
+    writeback(false);
+}
+
+/// 写回没有映射的页（`all` 时为所有页）中的脏页，然后释放没有映射的页
+fn writeback(all: bool) {
+    let mut state = FILE_PAGES.lock();
+    let paths: Vec<_> = state.files.keys().cloned().collect();
+    for path in paths {
+        let unused = |page: &FilePage| Arc::strong_count(&page.frame) == 1;
+        let indices: Vec<_> = state.files[&path]
+            .iter()
+            .filter(|(_, page)| all || unused(page))
+            .map(|(&index, _)| index)
+            .collect();
+        if let Err(err) = sync_pages(&mut state, &path, &indices, false) {
+            warn!("Failed to write back the mapping of {}: {:?}", path, err);
+        }
+        let FilePages { files, owners } = &mut *state;
+        let pages = files.get_mut(&path).unwrap();
+        pages.retain(|_, page| {
+            let keep = !unused(page);
+            if !keep {
+                owners.remove(&page.frame.paddr());
+            }
+            keep
+        });
+        if pages.is_empty() {
+            files.remove(&path);
+        }
+    }
+}
+
+/// 写回文件 `path` 中第 `indices` 页中的脏页，`invalidate` 时重新读入其中的干净页
+fn sync_pages(state: &mut FilePages, path: &str, indices: &[usize], invalidate: bool) -> AxResult {
+    let Some(pages) = state.files.get_mut(path) else {
+        return Ok(());
+    };
+    let mut options = OpenOptions::new();
+    options.read(true);
+    let file = File::open(path, &options)?;
+    // 只读的映射不会有脏页，只在需要写回时以写方式打开文件
+    let mut writer = None;
+    for index in indices {
+        let Some(page) = pages.get_mut(index) else {
+            continue;
+        };
+        let hash = page_hash(&page.frame);
+        if hash != page.hash {
+            let writer = match &mut writer {
+                Some(writer) => writer,
+                None => {
+                    options.write(true);
+                    writer.insert(File::open(path, &options)?)
+                }
+            };
+            // 超出文件末尾的部分不写回，文件大小不变
+            let pos = index * PAGE_SIZE_4K;
+            let size = writer.get_attr()?.size() as usize;
+            let len = size.saturating_sub(pos).min(PAGE_SIZE_4K);
+            let data = unsafe { core::slice::from_raw_parts(page.frame.as_mut_ptr(), len) };
+            let mut done = 0;
+            while done < len {
+                match writer.write_at((pos + done) as u64, &data[done..])? {
+                    0 => return Err(AxError::WriteZero),
+                    n => done += n,
+                }
+            }
+            page.hash = hash;
+        } else if invalidate {
+            read_page(&file, *index, &page.frame)?;
+            page.hash = page_hash(&page.frame);
+        }
+    }
+    if let Some(writer) = writer {
+        writer.flush()?;
+        FILE_TIMES.modify(path);
+    }
+    Ok(())
+}
+
+/// 将文件的第 `index` 页读入 `frame`，超出文件末尾的部分填充为 0
+fn read_page(file: &File, index: usize, frame: &SharedFrame) -> AxResult {
+    let buf = unsafe { core::slice::from_raw_parts_mut(frame.as_mut_ptr(), PAGE_SIZE_4K) };
+    let mut done = 0;
+    while done < PAGE_SIZE_4K {
+        let n = file.read_at((index * PAGE_SIZE_4K + done) as u64, &mut buf[done..])?;
+        if n == 0 {
+            buf[done..].fill(0);
+            break;
+        }
+        done += n;
+    }
+    Ok(())
+}
+
+/// 页内容的 FNV-1a 哈希值，按 8 字节一组计算
+fn page_hash(frame: &SharedFrame) -> u64 {
+    let data = unsafe { core::slice::from_raw_parts(frame.as_mut_ptr(), PAGE_SIZE_4K) };
+    data.chunks_exact(8)
+        .fold(0xcbf2_9ce4_8422_2325, |hash, word| {
+            (hash ^ u64::from_ne_bytes(word.try_into().unwrap())).wrapping_mul(0x100_0000_01b3)
+        })
+}
+
+/// 必要时启动写回线程
+fn start_daemon() {
+    if !DAEMON_STARTED.swap(true, Ordering::SeqCst) {
+        axtask::spawn_raw(
+            writeback_daemon,
+            String::from("writeback"),
+            crate::config::KERNEL_STACK_SIZE,
+        );
+    }
+}
+
+/// 处理 `MS_ASYNC` 请求的写回线程
+fn writeback_daemon() {
+    loop {
+        WRITEBACK_WQ.wait_until(|| QUEUED.load(Ordering::SeqCst));
+        QUEUED.store(false, Ordering::SeqCst);
+        let pending = core::mem::take(&mut *PENDING.lock());
+        let mut state = FILE_PAGES.lock();
+        for (path, indices, invalidate) in pending {
+            if let Err(err) = sync_pages(&mut state, &path, &indices, invalidate) {
+                warn!("Failed to write back the mapping of {}: {:?}", path, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! vDSO：映射到每个用户地址空间的只读页，用户程序不陷入内核即可读取时间
+//!
+//! [`VDSO_BASE`](config::VDSO_BASE) 处依次是两页：
+//! - 时间参数页，内容为 [`VdsoData`]，内核在每次中断（包括每个时钟滴答）时更新；
+//! - ELF 头所在的页，其地址通过 auxv 的 `AT_SYSINFO_EHDR` 传给用户程序。
+//!
+//! 目前 vDSO 中没有代码，ELF 头中没有程序头，libc 找不到 `__vdso_clock_gettime` 等符号，
+//! 仍然使用系统调用。修改过的 libc 可以在 `AT_SYSINFO_EHDR` 的前一页找到时间参数，
+//! 按顺序锁的协议读取：先读 `seq`，为奇数时重试；读完其他字段后再读一次 `seq`，
+//! 两次不同时重试。这样得到的是最近一次更新时的时间，比系统调用的结果最多落后一个时钟滴答。
+
+use alloc::sync::Arc;
+use core::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+
+use arceos_posix_api as api;
+use axerrno::AxResult;
+use axhal::{
+    paging::MappingFlags,
+    time::{current_ticks, nanos_to_ticks, ticks_to_nanos, NANOS_PER_SEC},
+    trap::{register_trap_handler, HandlerPriority, HANDLER_PRIORITY, IRQ},
+};
+use axmm::{AddrSpace, SharedFrame};
+use axstd::os::arceos::api::config::TICKS_PER_SEC;
+use lazyinit::LazyInit;
+use memory_addr::{VirtAddr, PAGE_SIZE_4K};
+
+use crate::config;
+
+/// auxv 中 vDSO 的 ELF 头的地址
+pub const AT_SYSINFO_EHDR: u8 = 33;
+
+/// vDSO 的时间参数页和 ELF 头所在的页
+static VDSO_FRAMES: LazyInit<Arc<[Arc<SharedFrame>]>> = LazyInit::new();
+
+/// 时间参数页的内容，布局是用户态可见的接口
+#[repr(C)]
+pub struct VdsoData {
+    /// 顺序锁的序号，为奇数时内核正在更新
+    seq: AtomicU32,
+    /// 两次更新之间的最长间隔，即一个时钟滴答，单位为纳秒
+    tick_nanos: AtomicU32,
+    /// 上次更新时的 `CLOCK_MONOTONIC`，单位为纳秒
+    monotonic_nanos: AtomicU64,
+    /// `CLOCK_REALTIME` 减去 `CLOCK_MONOTONIC` 的差，单位为纳秒，按补码回绕
+    realtime_offset_nanos: AtomicU64,
+    /// 上次更新时自启动起硬件计数器的计数
+    ticks: AtomicU64,
+    /// 硬件计数器每秒的计数
+    ticks_per_sec: AtomicU64,
+}
+
+/// 当前架构在 ELF 头中的 `e_machine`
+pub const ELF_MACHINE: u16 = if cfg!(target_arch = "x86_64") {
+    62 // EM_X86_64
+} else if cfg!(target_arch = "aarch64") {
+    183 // EM_AARCH64
+} else {
+    243 // EM_RISCV
+};
+
+/// vDSO 的 ELF 头：64 位小端的共享对象，没有程序头和节
+fn elf_header() -> [u8; 64] {
+    let mut ehdr = [0u8; 64];
+    ehdr[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+    ehdr[16..18].copy_from_slice(&3u16.to_le_bytes()); // ET_DYN
+    ehdr[18..20].copy_from_slice(&ELF_MACHINE.to_le_bytes());
+    ehdr[20..24].copy_from_slice(&1u32.to_le_bytes()); // EV_CURRENT
+    ehdr[52..54].copy_from_slice(&64u16.to_le_bytes()); // e_ehsize
+    ehdr[54..56].copy_from_slice(&56u16.to_le_bytes()); // e_phentsize
+    ehdr[58..60].copy_from_slice(&64u16.to_le_bytes()); // e_shentsize
+    ehdr
+}
+
+/// 分配 vDSO 的两页并写入初始内容，须在启动第一个用户程序之前调用
+pub fn init() {
+    let frame = || Arc::new(SharedFrame::new().expect("failed to allocate the vDSO"));
+    let frames = VDSO_FRAMES.init_once(Arc::new([frame(), frame()]));
+    let ehdr = elf_header();
+    // SAFETY: 页刚分配，还没有映射到任何用户地址空间
+    unsafe { core::ptr::copy_nonoverlapping(ehdr.as_ptr(), frames[1].as_mut_ptr(), ehdr.len()) };
+    update();
+}
+
+/// 将 vDSO 只读地映射到用户地址空间的固定位置
+pub fn map_vdso(uspace: &mut AddrSpace) -> AxResult {
+    uspace.map_shared(
+        VirtAddr::from_usize(config::VDSO_BASE),
+        2 * PAGE_SIZE_4K,
+        VDSO_FRAMES.clone(),
+        MappingFlags::READ | MappingFlags::USER,
+    )
+}
+
+/// 用当前时间更新时间参数页，其他 CPU 正在更新时直接返回
+///
+/// 修改 `CLOCK_REALTIME` 后应立即调用，使 vDSO 中的时间随之跳变。
+pub fn update() {
+    let Some(frames) = VDSO_FRAMES.get() else {
+        return;
+    };
+    // SAFETY: 页由 `VdsoData` 的原子字段组成，且在内核运行期间不会被释放
+    let data = unsafe { &*(frames[0].as_mut_ptr() as *const VdsoData) };
+    let seq = data.seq.load(Ordering::Relaxed);
+    if seq % 2 == 1
+        || data
+            .seq
+            .compare_exchange(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+    {
+        return;
+    }
+    fence(Ordering::Release);
+
+    let ticks = current_ticks();
+    let now = ticks_to_nanos(ticks);
+    let realtime = api::realtime().as_nanos() as u64;
+    data.tick_nanos.store(
+        (NANOS_PER_SEC / TICKS_PER_SEC as u64) as u32,
+        Ordering::Relaxed,
+    );
+    data.monotonic_nanos.store(now, Ordering::Relaxed);
+    data.realtime_offset_nanos
+        .store(realtime.wrapping_sub(now), Ordering::Relaxed);
+    data.ticks.store(ticks, Ordering::Relaxed);
+    data.ticks_per_sec
+        .store(nanos_to_ticks(NANOS_PER_SEC), Ordering::Relaxed);
+
+    data.seq.store(seq + 2, Ordering::Release);
+}
+
+/// 在中断处理之前更新时间参数页，时钟中断使其至少每个滴答更新一次
+#[register_trap_handler(IRQ)]
+fn update_on_irq(_irq: usize) -> bool {
+    update();
+    false
+}
+
+#[register_trap_handler(HANDLER_PRIORITY)]
+static UPDATE_ON_IRQ_PRIORITY: HandlerPriority = HandlerPriority::new(update_on_irq as _, -1);
@@ -0,0 +1,166 @@
+//! 按列表依次运行测例，并在全部结束后输出 TAP 格式的结果汇总
+//!
+//! 测例运行超过命令行参数 `timeout=` 指定的时间（默认 60 秒）后，它和它的所有后代
+//! 都会被 SIGKILL 结束，之后继续运行下一个测例。汇总中每个测例的结果附带一段
+//! YAML，记录结果、`wait4` 报告的状态、运行时间和最大常驻内存。
+
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use axhal::time::{epochoffset_nanos, monotonic_time};
+use axlog::ax_println;
+use axtask::{AxTaskRef, TaskExtRef, TaskState, WaitQueue};
+use memory_addr::PAGE_SIZE_4K;
+
+use crate::{boot_args::boot_args, task, POLL_INTERVAL};
+
+/// 超时的测例被结束后，等待它退出的最长时间
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// 内核主线程在此等待测例退出，测例超时的时候被唤醒
+static WATCHDOG_WQ: WaitQueue = WaitQueue::new();
+
+/// 测例的运行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Pass,
+    Fail,
+    Timeout,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail => "fail",
+            Outcome::Timeout => "timeout",
+        }
+    }
+}
+
+struct TestResult {
+    name: String,
+    outcome: Outcome,
+    /// `wait4` 报告的状态，测例无法启动或没有退出时为 `None`
+    wait_status: Option<i32>,
+    /// 从启动到退出的时间
+    time: Duration,
+    /// 最大常驻内存页数
+    max_rss: usize,
+}
+
+/// 按顺序运行测例，每个测例退出或超时后才运行下一个，最后输出结果汇总
+pub fn run_testcases<S: AsRef<str>>(testcases: &[S]) {
+    let results: Vec<_> = testcases
+        .iter()
+        .map(|testcase| run_testcase(testcase.as_ref()))
+        .collect();
+    print_summary(&results);
+}
+
+fn run_testcase(testcase: &str) -> TestResult {
+    info!("Running testcase: {}", testcase);
+    let mut result = TestResult {
+        name: testcase.to_string(),
+        outcome: Outcome::Fail,
+        wait_status: None,
+        time: Duration::ZERO,
+        max_rss: 0,
+    };
+    let mut args = alloc::vec![testcase.to_string()];
+    if ["mount", "umount"].contains(&testcase) {
+        // 测例使用的 FAT12 镜像作为第二块磁盘传入
+        args.push("/dev/vdb".into());
+    }
+    let start = monotonic_time();
+    let Some(user_task) = crate::spawn_user_app(testcase, args, &[]) else {
+        return result;
+    };
+
+    let timed_out = !wait_exit(&user_task, start + boot_args().timeout);
+    if timed_out {
+        warn!("Testcase {} timed out, killing it", testcase);
+        task::kill_tree(&user_task);
+        if !wait_exit(&user_task, monotonic_time() + KILL_GRACE) {
+            error!("Testcase {} did not exit after being killed", testcase);
+        }
+    }
+    result.time = monotonic_time() - start;
+
+    let ext = user_task.task_ext();
+    result.max_rss = ext.update_max_rss();
+    if user_task.state() == TaskState::Exited {
+        result.wait_status = Some(ext.wait_status(user_task.exit_code()));
+    }
+    result.outcome = if timed_out {
+        Outcome::Timeout
+    } else if result.wait_status == Some(0) {
+        Outcome::Pass
+    } else {
+        Outcome::Fail
+    };
+    task::reap_orphans();
+    info!(
+        "User task {} exited with status: {:?}",
+        testcase, result.wait_status
+    );
+    result
+}
+
+/// 等待任务退出，期间采样它的常驻内存并回收孤儿进程，在 `deadline` 之前退出时返回 `true`
+///
+/// `deadline` 是单调时钟上的时刻，由注册的超时在到期后的第一个时钟中断唤醒等待者，
+/// 不必等到下一次采样。
+fn wait_exit(task: &AxTaskRef, deadline: Duration) -> bool {
+    let expired = Arc::new(AtomicBool::new(false));
+    let flag = expired.clone();
+    let wall_deadline = deadline + Duration::from_nanos(epochoffset_nanos());
+    let watchdog = axtask::register_timeout(wall_deadline, move |_| {
+        flag.store(true, Ordering::Release);
+        WATCHDOG_WQ.notify_all(false);
+    });
+    while task.state() != TaskState::Exited {
+        if expired.load(Ordering::Acquire) {
+            return false;
+        }
+        task.task_ext().update_max_rss();
+        task::reap_orphans();
+        WATCHDOG_WQ.wait_timeout_until(POLL_INTERVAL, || expired.load(Ordering::Acquire));
+    }
+    watchdog.cancel();
+    true
+}
+
+fn print_summary(results: &[TestResult]) {
+    let count = |outcome| results.iter().filter(|r| r.outcome == outcome).count();
+    ax_println!("TAP version 13");
+    ax_println!("1..{}", results.len());
+    for (i, result) in results.iter().enumerate() {
+        let ok = match result.outcome {
+            Outcome::Pass => "ok",
+            _ => "not ok",
+        };
+        ax_println!("{} {} - {}", ok, i + 1, result.name);
+        ax_println!("  ---");
+        ax_println!("  result: {}", result.outcome.as_str());
+        if let Some(status) = result.wait_status {
+            ax_println!("  wait_status: {}", status);
+        }
+        ax_println!("  time_ms: {}", result.time.as_millis());
+        ax_println!("  max_rss_kb: {}", result.max_rss * PAGE_SIZE_4K / 1024);
+        ax_println!("  ...");
+    }
+    ax_println!(
+        "# pass {}, fail {}, timeout {}",
+        count(Outcome::Pass),
+        count(Outcome::Fail),
+        count(Outcome::Timeout)
+    );
+}
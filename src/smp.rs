@@ -0,0 +1,95 @@
+//! 跨核同步
+//!
+//! 发起方递增全局的同步纪元（epoch），再向其余在线的核发送核间中断（IPI）。
+//! 各核在随之而来的陷入（或更早的系统调用、任务切换）中发现纪元变化，
+//! 便在本核上执行内存屏障、刷新 TLB 和指令缓存，再记录自己已经处理到的纪元，
+//! 作为对发起方的应答。发起方等到所有在线的核都应答后返回。
+
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+use arceos_posix_api::config::SMP;
+use axhal::cpu::this_cpu_id;
+use axtask::TaskInner;
+
+/// 尚未处理过任何同步请求的核，视为不在线，发起方不会等待它
+const CPU_OFFLINE: usize = usize::MAX;
+
+/// 最近一次发起的同步请求的纪元
+static SYNC_EPOCH: AtomicUsize = AtomicUsize::new(0);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const CPU_EPOCH_INIT: AtomicUsize = AtomicUsize::new(CPU_OFFLINE);
+
+/// 每个核已经处理到的纪元
+static CPU_EPOCH: [AtomicUsize; SMP] = [CPU_EPOCH_INIT; SMP];
+
+/// 在本核上完成一次同步：内存屏障、刷新 TLB 和指令缓存
+fn local_sync() {
+    fence(Ordering::SeqCst);
+    axhal::arch::flush_tlb(None);
+    #[cfg(target_arch = "riscv64")]
+    unsafe {
+        core::arch::asm!("fence.i");
+    }
+}
+
+/// 若有尚未处理的同步请求，则在本核上处理
+fn handle_pending() {
+    let seen = &CPU_EPOCH[this_cpu_id()];
+    let epoch = SYNC_EPOCH.load(Ordering::Acquire);
+    if seen.load(Ordering::Relaxed) != epoch {
+        local_sync();
+        seen.store(epoch, Ordering::Release);
+    }
+}
+
+/// 让所有在线的核都完成一次内存屏障、TLB 和指令缓存的刷新
+///
+/// 返回时，其他核上不会再残留本次调用之前被修改的页表项的缓存，
+/// 其之前的内存访问也都已对当前核可见。单核时只在本核上执行。
+pub fn sync_all_cpus() {
+    if SMP == 1 {
+        local_sync();
+        return;
+    }
+    let target = SYNC_EPOCH.fetch_add(1, Ordering::AcqRel) + 1;
+    let this_cpu = this_cpu_id();
+    for (cpu_id, seen) in CPU_EPOCH.iter().enumerate() {
+        if cpu_id != this_cpu && seen.load(Ordering::Acquire) != CPU_OFFLINE {
+            axtask::kick_cpu(cpu_id);
+        }
+    }
+    loop {
+        // 其他核可能也在等待本核，因此每次检查前都先处理自己的请求
+        handle_pending();
+        let done = CPU_EPOCH.iter().all(|seen| {
+            let seen = seen.load(Ordering::Acquire);
+            seen == CPU_OFFLINE || seen >= target
+        });
+        if done {
+            break;
+        }
+        axtask::yield_now();
+    }
+}
+
+/// 修改了可能被多个核共享的地址空间的页表后调用
+///
+/// `shared` 为假时，该地址空间只被当前任务使用，只需刷新本核的 TLB。
+pub fn flush_tlb_shared(shared: bool) {
+    if shared {
+        sync_all_cpus();
+    } else {
+        axhal::arch::flush_tlb(None);
+    }
+}
+
+#[axhal::trap::register_trap_handler(axhal::trap::BEFORE_ALL_TRAPS)]
+fn sync_on_trap() {
+    handle_pending();
+}
+
+#[axtask::register_switch_hook(axtask::TASK_SWITCH_HOOKS)]
+fn sync_on_switch(_prev: &TaskInner, _next: &TaskInner) {
+    handle_pending();
+}
@@ -1,12 +1,17 @@
-use alloc::string::ToString;
-use arceos_posix_api::AT_FDCWD;
-use axerrno::AxError;
+use alloc::{format, string::ToString, vec};
+use arceos_posix_api::{ctypes::timespec, FilePath};
+use axerrno::{AxError, LinuxError, LinuxResult};
 use axhal::paging::MappingFlags;
 use axtask::{current, TaskExtRef};
-use core::ffi::c_void;
-use memory_addr::VirtAddrRange;
+use core::{ffi::c_void, time::Duration};
+use memory_addr::{VirtAddr, VirtAddrRange};
 
-use crate::syscall_body;
+use super::{Cred, MemFd, R_OK, W_OK, X_OK};
+use crate::{
+    mm::{copy_to_user, read_user, write_user},
+    syscall_body,
+    task::CAP_SYS_CHROOT,
+};
 
 /// The ioctl() system call manipulates the underlying device parameters
 /// of special files.
@@ -16,8 +21,15 @@ use crate::syscall_body;
 /// * `op` - The request code. It is of type unsigned long in glibc and BSD,
 /// and of type int in musl and other UNIX systems.
 /// * `argp` - The argument to the request. It is a pointer to a memory location
-pub(crate) fn sys_ioctl(_fd: i32, _op: usize, _argp: *mut c_void) -> i32 {
+///
+/// 驱动程序注册的字符设备（如 `/dev/ttyS0`）的请求交给其驱动程序处理，
+/// 其他文件的请求尚未实现，直接返回 0。
+pub(crate) fn sys_ioctl(fd: i32, op: usize, argp: *mut c_void) -> i32 {
     syscall_body!(sys_ioctl, {
+        let file = arceos_posix_api::File::from_fd(fd).ok();
+        if let Some(result) = file.and_then(|file| file.device_ioctl(op as u32, argp as usize)) {
+            return result.map(|ret| ret as i32);
+        }
         warn!("Unimplemented syscall: SYS_IOCTL");
         Ok(0)
     })
@@ -87,24 +99,52 @@ fn allocate_user_buffer(size: usize) -> Option<*mut u8> {
     Some(addr.as_usize() as *mut u8)
 }
 
+/// 添加封印，只适用于匿名内存文件
+const F_ADD_SEALS: i32 = 1033;
+/// 获取已添加的封印
+const F_GET_SEALS: i32 = 1034;
+
+/// 操作文件描述符，封印相关的命令在这里处理，其余交给 [`arceos_posix_api::sys_fcntl`]
+pub(crate) fn sys_fcntl(fd: i32, cmd: i32, arg: usize) -> isize {
+    match cmd {
+        F_ADD_SEALS => syscall_body!(sys_fcntl, {
+            MemFd::from_fd(fd)?.add_seals(arg as u32)?;
+            Ok(0)
+        }),
+        F_GET_SEALS => syscall_body!(sys_fcntl, Ok(MemFd::from_fd(fd)?.seals())),
+        _ => arceos_posix_api::sys_fcntl(fd, cmd, arg) as _,
+    }
+}
+
 pub(crate) fn sys_dup(fd: i32) -> i32 {
-    arceos_posix_api::get_file_like(fd)
-        .and_then(|f| arceos_posix_api::add_file_like(f))
-        .unwrap_or_else(|err| {
-            warn!("Failed to duplicate file descriptor: {:?}", err);
-            -1
-        })
+    syscall_body!(sys_dup, {
+        let f = arceos_posix_api::get_file_like(fd)?;
+        arceos_posix_api::add_file_like(f)
+    })
 }
 
-pub(crate) fn sys_dup3(old_fd: i32, new_fd: i32, flags: i32) -> i32 {
-    if flags != 0 {
-        warn!("Unsupported flags: {}", flags);
-    }
+/// 将 `old_fd` 复制到 `new_fd`，`new_fd` 已打开时先将其关闭
+///
+/// `new_fd` 不小于 `RLIMIT_NOFILE` 的软限制时返回 EBADF。
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_dup2(old_fd: i32, new_fd: i32) -> i32 {
+    arceos_posix_api::sys_dup2(old_fd, new_fd)
+}
 
-    match arceos_posix_api::sys_dup2(old_fd, new_fd) {
-        ok @ 0.. => ok as _,
-        _ => -1,
-    }
+/// 与 `dup2` 相同，但 `old_fd` 与 `new_fd` 相同时返回 EINVAL，并且可以通过 `O_CLOEXEC` 设置 close-on-exec 标志
+pub(crate) fn sys_dup3(old_fd: i32, new_fd: i32, flags: i32) -> i32 {
+    syscall_body!(sys_dup3, {
+        let cloexec = arceos_posix_api::ctypes::O_CLOEXEC as i32;
+        if old_fd == new_fd || flags & !cloexec != 0 {
+            return Err(axerrno::LinuxError::EINVAL);
+        }
+        let ret = arceos_posix_api::sys_dup2(old_fd, new_fd);
+        if ret < 0 {
+            return Ok(ret);
+        }
+        arceos_posix_api::set_cloexec(new_fd, flags & cloexec != 0);
+        Ok(ret)
+    })
 }
 
 /// 将当前工作目录更改为指定路径。
@@ -123,8 +163,18 @@ pub(crate) fn sys_chdir(path: *const i8) -> i32 {
             return -1;
         }
     };
+    let path = axfs::path::canonicalize(&axfs::CURRENT_DIR_PATH.lock(), path)
+        .map_err(Into::into)
+        .and_then(|path| arceos_posix_api::resolve_symlinks(&path, true));
+    let path = match path {
+        Ok(path) => path,
+        Err(err) => {
+            warn!("Failed to resolve path: {err:?}");
+            return -1;
+        }
+    };
 
-    axfs::api::set_current_dir(path)
+    axfs::api::set_current_dir(&path)
         .map(|_| 0)
         .unwrap_or_else(|err| {
             warn!("Failed to change directory: {err:?}");
@@ -132,47 +182,83 @@ pub(crate) fn sys_chdir(path: *const i8) -> i32 {
         })
 }
 
+/// 把进程的根目录改为目录 `path`，之后进程的绝对路径从这里开始解析，".." 也不会越过它
+///
+/// 需要 `CAP_SYS_CHROOT`，当前工作目录不变。根目录在 fork 时被继承（带 `CLONE_FS` 时
+/// 共享），在 exec 后保留。
+pub(crate) fn sys_chroot(path: *const u8) -> isize {
+    syscall_body!(sys_chroot, {
+        if !current().task_ext().capable(CAP_SYS_CHROOT) {
+            return Err(LinuxError::EPERM);
+        }
+        let path =
+            arceos_posix_api::handle_file_path(arceos_posix_api::AT_FDCWD, Some(path), false)?;
+        axfs::api::set_root_dir(&path)?;
+        Ok(0)
+    })
+}
+
 /// 在给定的目录文件描述符相对路径下创建一个新目录。
 ///
 /// # 参数
 /// * `dirfd` - 目录文件描述符（-100 表示当前工作目录）
 /// * `path` - 指向包含目录路径的以 null 结尾的字符串的指针
-/// * `mode` - 目录权限（当前忽略）
+/// * `mode` - 目录权限，去掉文件创建掩码后记录，只保留权限位和粘滞位
 ///
 /// # 返回值
 /// * 成功时返回 `0`
-/// * 失败时返回 `-1`
-pub(crate) fn sys_mkdirat(dirfd: i32, path: *const i8, mode: u32) -> i32 {
-    let path = match arceos_posix_api::char_ptr_to_str(path) {
-        Ok(path) => path,
-        Err(err) => {
-            warn!("Failed to convert path: {err:?}");
-            return -1;
-        }
-    };
-
-    if !path.starts_with('/') && dirfd != AT_FDCWD as i32 {
-        warn!("Unsupported dirfd: {dirfd}");
-        return -1;
-    }
-
-    if mode != 0 {
-        info!("Directory mode {mode} is currently ignored");
-    }
+/// * 失败时返回负的错误码，目录在只读挂载的文件系统上时为 `EROFS`
+pub(crate) fn sys_mkdirat(dirfd: i32, path: *const u8, mode: u32) -> isize {
+    syscall_body!(sys_mkdirat, {
+        let path = arceos_posix_api::handle_writable_path(dirfd as isize, Some(path), false)?;
+
+        axfs::api::create_dir(&path)
+            .inspect_err(|err| warn!("Failed to create directory: {err:?}"))?;
+        let mode = mode & 0o1777 & !current().task_ext().umask();
+        arceos_posix_api::FILE_MODES.create(&path, mode);
+        Ok(0)
+    })
+}
 
-    axfs::api::create_dir(path)
-        .map(|_| 0)
-        .unwrap_or_else(|err| {
-            warn!("Failed to create directory: {err:?}");
-            -1
-        })
+/// 在 `path` 创建文件系统节点，`mode` 的文件类型部分决定节点的类型
+///
+/// 支持普通文件、命名管道（`S_IFIFO`）以及由 `dev` 指定设备号的字符设备（`S_IFCHR`）
+/// 和块设备（`S_IFBLK`）。`mode` 的权限部分去掉文件创建掩码后记录。
+pub(crate) fn sys_mknodat(dirfd: i32, path: *const u8, mode: u32, dev: u64) -> isize {
+    use arceos_posix_api::{SpecialFile, SPECIAL_FILES, S_IFBLK, S_IFCHR, S_IFIFO, S_IFMT};
+    const S_IFREG: u32 = 0o100000;
+
+    syscall_body!(sys_mknodat, {
+        let path = arceos_posix_api::handle_writable_path(dirfd as isize, Some(path), false)?;
+        let (major, minor) = SpecialFile::split_dev(dev);
+        let special = match mode & S_IFMT {
+            0 | S_IFREG => None,
+            S_IFIFO => Some(SpecialFile::Fifo),
+            S_IFCHR => Some(SpecialFile::CharDevice(major, minor)),
+            S_IFBLK => Some(SpecialFile::BlockDevice(major, minor)),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if path.exists() {
+            return Err(LinuxError::EEXIST);
+        }
+        match special {
+            Some(special) => SPECIAL_FILES.create(&path, special)?,
+            None => {
+                axfs::api::File::create_new(&path)?;
+            }
+        }
+        arceos_posix_api::FILE_TIMES.create(&path);
+        let perm = mode & 0o7777 & !current().task_ext().umask();
+        arceos_posix_api::FILE_MODES.create(&path, perm);
+        Ok(0)
+    })
 }
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 struct DirEnt {
     d_ino: u64,      // 索引结点号
-    d_off: i64,      // 到下一个dirent的偏移
+    d_off: i64,      // 下一个dirent的位置
     d_reclen: u16,   // 当前dirent的长度
     d_type: u8,      // 文件类型
     d_name: [u8; 0], // 文件名
@@ -207,11 +293,24 @@ impl From<axfs::api::FileType> for FileType {
         match ft {
             ft if ft.is_dir() => FileType::Dir,
             ft if ft.is_file() => FileType::Reg,
+            ft if ft.is_fifo() => FileType::Fifo,
+            ft if ft.is_char_device() => FileType::Chr,
+            ft if ft.is_block_device() => FileType::Blk,
             _ => FileType::Unknown,
         }
     }
 }
 
+impl From<arceos_posix_api::SpecialFile> for FileType {
+    fn from(special: arceos_posix_api::SpecialFile) -> Self {
+        match special {
+            arceos_posix_api::SpecialFile::Fifo => FileType::Fifo,
+            arceos_posix_api::SpecialFile::CharDevice(..) => FileType::Chr,
+            arceos_posix_api::SpecialFile::BlockDevice(..) => FileType::Blk,
+        }
+    }
+}
+
 impl DirEnt {
     const FIXED_SIZE: usize = core::mem::size_of::<u64>()
         + core::mem::size_of::<i64>()
@@ -228,11 +327,16 @@ impl DirEnt {
         }
     }
 
-    unsafe fn write_name(&mut self, name: &[u8]) {
-        core::ptr::copy_nonoverlapping(name.as_ptr(), self.d_name.as_mut_ptr(), name.len());
+    /// 目录项的长度：固定部分、文件名和结尾的 `'\0'` 向上对齐到 8 字节，
+    /// 使下一个目录项的 `d_ino` 和 `d_off` 也是对齐的
+    fn record_len(name_len: usize) -> usize {
+        (Self::FIXED_SIZE + name_len + 1).next_multiple_of(core::mem::align_of::<u64>())
     }
 }
 
+/// `getdents64` 一次最多填充的字节数，更大的缓冲区只是得到较少的目录项
+const MAX_GETDENTS_LEN: usize = 64 * 1024;
+
 // Directory buffer for getdents64 syscall
 struct DirBuffer<'a> {
     buf: &'a mut [u8],
@@ -252,144 +356,120 @@ impl<'a> DirBuffer<'a> {
         self.remaining_space() >= entry_size
     }
 
-    unsafe fn write_entry(&mut self, dirent: DirEnt, name: &[u8]) -> Result<(), ()> {
-        if !self.can_fit_entry(dirent.d_reclen as usize) {
+    /// 写入一个目录项，文件名之后直到下一个目录项的字节都为 0，因此文件名总以 `'\0'` 结尾
+    fn write_entry(&mut self, dirent: DirEnt, name: &[u8]) -> Result<(), ()> {
+        let reclen = dirent.d_reclen as usize;
+        if !self.can_fit_entry(reclen) {
             return Err(());
         }
 
-        let entry_ptr = self.buf.as_mut_ptr().add(self.offset) as *mut DirEnt;
-        entry_ptr.write(dirent);
-        (*entry_ptr).write_name(name);
+        let record = &mut self.buf[self.offset..self.offset + reclen];
+        // 目录项在缓冲区中不一定对齐，按字节复制固定部分
+        let fixed = unsafe {
+            core::slice::from_raw_parts(&dirent as *const DirEnt as *const u8, DirEnt::FIXED_SIZE)
+        };
+        record[..DirEnt::FIXED_SIZE].copy_from_slice(fixed);
+        record[DirEnt::FIXED_SIZE..][..name.len()].copy_from_slice(name);
+        record[DirEnt::FIXED_SIZE + name.len()..].fill(0);
 
-        self.offset += dirent.d_reclen as usize;
+        self.offset += reclen;
         Ok(())
     }
 }
 
+/// 从目录的当前位置开始读取目录项，返回写入缓冲区的字节数，读到目录末尾时返回 0
+///
+/// 目录的位置由内核记录，`d_off` 是下一个目录项的位置，可以交给 `lseek` 使用。
 pub(crate) fn sys_getdents64(fd: i32, buf: *mut c_void, len: usize) -> isize {
-    if len < DirEnt::FIXED_SIZE {
-        warn!("Buffer size too small: {len}");
-        return -1;
-    }
-
-    let current = current();
-    if let Err(e) = current
-        .task_ext()
-        .aspace
-        .lock()
-        .alloc_for_lazy((buf as usize).into(), len)
-    {
-        warn!("Memory allocation failed: {:?}", e);
-        return -1;
-    }
-
-    // 获取文件描述符对应的目录路径
-    let path = match arceos_posix_api::Directory::from_fd(fd).map(|dir| dir.path().to_string()) {
-        Ok(path) => path,
-        Err(err) => {
-            warn!("Invalid directory descriptor: {:?}", err);
-            return -1;
-        }
-    };
-
-    let mut buffer =
-        unsafe { DirBuffer::new(core::slice::from_raw_parts_mut(buf as *mut u8, len)) };
-
-    // 得到初始偏移量和目录项数量
-    let (initial_offset, count) = unsafe {
-        let mut buf_offset = 0;
-        let mut count = 0;
-        while buf_offset + DirEnt::FIXED_SIZE <= len {
-            let dir_ent = *(buf.add(buf_offset) as *const DirEnt);
-            if dir_ent.d_reclen == 0 {
-                break;
-            }
-
-            buf_offset += dir_ent.d_reclen as usize;
-            assert_eq!(dir_ent.d_off, buf_offset as i64);
-            count += 1;
+    syscall_body!(sys_getdents64, {
+        let dir = arceos_posix_api::Directory::from_fd(fd)?;
+        // 先填充内核缓冲区，再只把填充的部分复制给用户
+        let mut kbuf = vec![0u8; len.min(MAX_GETDENTS_LEN)];
+        let mut buffer = DirBuffer::new(&mut kbuf);
+
+        let path = dir.path().trim_end_matches('/');
+        let mut too_small = false;
+        dir.read_entries(|name, ty, next_offset| {
+            // 由 mknod 创建的特殊文件和符号链接在文件系统中是普通文件
+            let file_type = match FilePath::new_link(format!("{}/{}", path, name)) {
+                Ok(path) if path.symlink_target().is_some() => FileType::Lnk,
+                Ok(path) => arceos_posix_api::SPECIAL_FILES
+                    .get(&arceos_posix_api::HARDLINK_MANAGER.real_path(&path))
+                    .map_or(FileType::from(ty), FileType::from),
+                Err(_) => FileType::from(ty),
+            };
+            let entry_size = DirEnt::record_len(name.len());
+            let dirent = DirEnt::new(1, next_offset as i64, entry_size, file_type);
+            let written = buffer.write_entry(dirent, name.as_bytes()).is_ok();
+            too_small = !written && buffer.offset == 0;
+            written
+        })?;
+        if too_small {
+            return Err(LinuxError::EINVAL);
         }
-        (buf_offset as i64, count)
-    };
-
-    // 读取目录项并写入缓冲区
-    axfs::api::read_dir(&path)
-        .map_err(|_| -1)
-        .and_then(|entries| {
-            let mut total_size = initial_offset as usize;
-            let mut current_offset = initial_offset;
-
-            for entry in entries.flatten().skip(count) {
-                let mut name = entry.file_name();
-                name.push('\0');
-                let name_bytes = name.as_bytes();
-
-                let entry_size = DirEnt::FIXED_SIZE + name_bytes.len();
-                current_offset += entry_size as i64;
-
-                let dirent = DirEnt::new(
-                    1,
-                    current_offset,
-                    entry_size,
-                    FileType::from(entry.file_type()),
-                );
-
-                unsafe {
-                    if buffer.write_entry(dirent, name_bytes).is_err() {
-                        break;
-                    }
-                }
-
-                total_size += entry_size;
-            }
-
-            // 添加终止目录项
-            if total_size > 0 && buffer.can_fit_entry(DirEnt::FIXED_SIZE) {
-                let terminal = DirEnt::new(1, current_offset, 0, FileType::Reg);
-                unsafe {
-                    let _ = buffer.write_entry(terminal, &[]);
-                }
-            }
-
-            Ok(total_size as isize)
-        })
-        .unwrap_or(-1)
+        let filled = buffer.offset;
+        copy_to_user(VirtAddr::from_mut_ptr_of(buf as *mut u8), &kbuf[..filled])
+            .map_err(|_| LinuxError::EFAULT)?;
+        Ok(filled as isize)
+    })
 }
 
 /// 创建一个链接 new_path 指向 old_path。
 /// old_path - 旧文件路径
 /// new_path - 新文件路径
-/// flags - 链接标志
-/// 返回值 - 成功时返回 0，失败时返回 -1
+/// flags - 链接标志，带 `AT_EMPTY_PATH` 且 old_path 为空时链接 old_dirfd 打开的文件
+/// 返回值 - 成功时返回 0，失败时返回负的错误码
 pub(crate) fn sys_linkat(
     old_dirfd: i32,
     old_path: *const u8,
     new_dirfd: i32,
     new_path: *const u8,
     flags: i32,
-) -> i32 {
-    if flags != 0 {
-        warn!("Unsupported flags: {flags}");
-    }
+) -> isize {
+    syscall_body!(sys_linkat, {
+        if flags & !(AT_SYMLINK_FOLLOW | AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if flags & AT_EMPTY_PATH != 0
+            && arceos_posix_api::char_ptr_to_str(old_path as _)?.is_empty()
+        {
+            link_fd(old_dirfd, new_dirfd, new_path)?;
+            return Ok(0);
+        }
+        // 处理原路径，链接会修改它的链接数
+        let old_path =
+            arceos_posix_api::handle_writable_path(old_dirfd as isize, Some(old_path), false)
+                .inspect_err(|err| warn!("Failed to convert old path: {err:?}"))?;
+        // 处理新路径，它是要创建的链接，不能解析为已有的文件
+        let new_path = arceos_posix_api::handle_writable_link_path(new_dirfd as isize, new_path)
+            .inspect_err(|err| warn!("Failed to convert new path: {err:?}"))?;
+        // 创建链接
+        arceos_posix_api::HARDLINK_MANAGER
+            .create_link(&new_path, &old_path)
+            .inspect_err(|err| warn!("Failed to create link: {err:?}"))
+            .map_err(Into::<AxError>::into)?;
+        Ok(0)
+    })
+}
 
-    // 处理原路径
-    arceos_posix_api::handle_file_path(old_dirfd as isize, Some(old_path), false)
-        .inspect_err(|err| warn!("Failed to convert old path: {err:?}"))
-        .and_then(|old_path| {
-            // 处理新路径
-            arceos_posix_api::handle_file_path(new_dirfd as isize, Some(new_path), false)
-                .inspect_err(|err| warn!("Failed to convert new path: {err:?}"))
-                .map(|new_path| (old_path, new_path))
-        })
-        .and_then(|(old_path, new_path)| {
-            // 创建链接
-            arceos_posix_api::HARDLINK_MANAGER
-                .create_link(&new_path, &old_path)
-                .inspect_err(|err| warn!("Failed to create link: {err:?}"))
-                .map_err(Into::into)
-        })
-        .map(|_| 0)
-        .unwrap_or(-1)
+/// 创建链接 `new_path` 指向 `fd` 打开的文件
+///
+/// `O_TMPFILE` 创建的匿名文件由此获得名字，成为普通的文件。
+fn link_fd(fd: i32, new_dirfd: i32, new_path: *const u8) -> LinuxResult<()> {
+    // 目录不能有硬链接
+    let file = arceos_posix_api::File::from_fd(fd).map_err(|err| match err {
+        LinuxError::EINVAL => LinuxError::EPERM,
+        err => err,
+    })?;
+    let new_path = arceos_posix_api::handle_writable_link_path(new_dirfd as isize, new_path)?;
+    if file.is_unnamed() {
+        return file.link_unnamed(&new_path);
+    }
+    let old_path = FilePath::new(file.path())?;
+    arceos_posix_api::HARDLINK_MANAGER
+        .create_link(&new_path, &old_path)
+        .map_err(Into::<AxError>::into)?;
+    Ok(())
 }
 
 /// 功能:移除指定文件的链接(可用于删除文件);
@@ -398,39 +478,192 @@ pub(crate) fn sys_linkat(
 /// * `path`: *const u8, 要删除的链接的名字。如果path是相对路径,则它是相对于dir_fd目录而言的。如果path是相对路径,且dir_fd的值为AT_FDCWD,则它是相对于当前路径而言的。如果path是绝对路径,则dir_fd被忽略。
 /// * `flags`: usize, 可设置为0或AT_REMOVEDIR。
 /// # Return
-/// 成功执行,返回0。失败,返回-1。
+/// 成功执行,返回0。失败,返回负的错误码。
 pub fn syscall_unlinkat(dir_fd: isize, path: *const u8, flags: usize) -> isize {
     const AT_REMOVEDIR: usize = 0x200;
 
-    // 处理路径
-    arceos_posix_api::handle_file_path(dir_fd, Some(path), false)
-        .inspect_err(|e| debug!("unlinkat error: {:?}", e))
-        .and_then(|path| {
-            // 删除链接
-            if flags == AT_REMOVEDIR {
-                // 删除目录
-                axfs::api::remove_dir(path.as_str())
-                    .inspect_err(|e| debug!("rmdir error: {:?}", e))
-                    .map(|_| 0)
+    syscall_body!(syscall_unlinkat, {
+        // 处理路径，要删除的是链接本身，而不是它指向的文件
+        let path = arceos_posix_api::handle_writable_link_path(dir_fd, path)
+            .inspect_err(|e| debug!("unlinkat error: {:?}", e))?;
+        // 删除链接
+        if flags == AT_REMOVEDIR {
+            // 删除目录
+            axfs::api::remove_dir(path.as_str()).inspect_err(|e| debug!("rmdir error: {:?}", e))?;
+            arceos_posix_api::FILE_MODES.remove(&path);
+        } else {
+            // 删除文件
+            let real_path = arceos_posix_api::HARDLINK_MANAGER.real_path(&path);
+            if axfs::api::metadata(&real_path)?.is_dir() {
+                return Err(LinuxError::EISDIR);
+            }
+            debug!("unlink file: {:?}", path);
+            arceos_posix_api::HARDLINK_MANAGER
+                .remove_link(&path)
+                .ok_or_else(|| {
+                    debug!("unlink file error");
+                    LinuxError::ENOENT
+                })?;
+        }
+        Ok(0)
+    })
+}
+
+/// 把 `old_path` 重命名为 `new_path`，`new_path` 已存在时覆盖它
+///
+/// 两者都可以是硬链接，此时重命名的是链接本身。`flags` 只支持 `RENAME_NOREPLACE`。
+pub(crate) fn sys_renameat2(
+    old_dirfd: i32,
+    old_path: *const u8,
+    new_dirfd: i32,
+    new_path: *const u8,
+    flags: u32,
+) -> isize {
+    const RENAME_NOREPLACE: u32 = 1;
+
+    syscall_body!(sys_renameat2, {
+        if flags & !RENAME_NOREPLACE != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let old_path = arceos_posix_api::handle_writable_link_path(old_dirfd as isize, old_path)?;
+        let new_path = arceos_posix_api::handle_writable_link_path(new_dirfd as isize, new_path)?;
+        let exists = |path: &FilePath| FilePath::new(path.as_str()).is_ok_and(|path| path.exists());
+        if !exists(&old_path) {
+            return Err(LinuxError::ENOENT);
+        }
+        if flags & RENAME_NOREPLACE != 0 && exists(&new_path) {
+            return Err(LinuxError::EEXIST);
+        }
+        arceos_posix_api::HARDLINK_MANAGER.rename(&old_path, &new_path)?;
+        Ok(0)
+    })
+}
+
+/// 在 `path` 创建指向 `target` 的符号链接
+///
+/// `target` 不需要存在，相对路径的目标在解析时相对于链接所在的目录。
+pub(crate) fn sys_symlinkat(target: *const u8, new_dirfd: i32, path: *const u8) -> isize {
+    syscall_body!(sys_symlinkat, {
+        let target = arceos_posix_api::char_ptr_to_str(target as *const i8)?;
+        if target.is_empty() {
+            return Err(LinuxError::ENOENT);
+        }
+        let path = arceos_posix_api::handle_writable_link_path(new_dirfd as isize, path)?;
+        if path.exists() {
+            return Err(LinuxError::EEXIST);
+        }
+        arceos_posix_api::SYMLINKS.create(&path, target)?;
+        arceos_posix_api::FILE_TIMES.create(&path);
+        Ok(0)
+    })
+}
+
+/// 把符号链接 `path` 的目标写入 `buf`，超过 `bufsiz` 的部分被截断，不以 '\0' 结尾
+///
+/// 返回写入的字节数，`path` 不是符号链接时返回 `EINVAL`。`path` 为空时读取 `dirfd`
+/// 本身，它应是以 `O_PATH | O_NOFOLLOW` 打开的符号链接，否则返回 `ENOENT`。
+pub(crate) fn sys_readlinkat(dirfd: i32, path: *const u8, buf: *mut u8, bufsiz: usize) -> isize {
+    syscall_body!(sys_readlinkat, {
+        if bufsiz == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path_str = arceos_posix_api::char_ptr_to_str(path as *const i8)?;
+        let target = if path_str.is_empty() {
+            let file = arceos_posix_api::File::from_fd(dirfd).map_err(|_| LinuxError::ENOENT)?;
+            FilePath::new(file.path())?
+                .symlink_target()
+                .ok_or(LinuxError::ENOENT)?
+        } else {
+            let path = arceos_posix_api::handle_link_path(dirfd as isize, path)?;
+            if !path.exists() {
+                return Err(LinuxError::ENOENT);
+            }
+            path.symlink_target().ok_or(LinuxError::EINVAL)?
+        };
+        let len = target.len().min(bufsiz);
+        copy_to_user(VirtAddr::from_mut_ptr_of(buf), &target.as_bytes()[..len])
+            .map_err(|_| LinuxError::EFAULT)?;
+        Ok(len)
+    })
+}
+
+/// `utimensat` 的时间：设为当前时间
+const UTIME_NOW: i64 = (1 << 30) - 1;
+/// `utimensat` 的时间：保持不变
+const UTIME_OMIT: i64 = (1 << 30) - 2;
+/// `*at` 系列调用的选项：不跟随最后一个路径组件的符号链接
+pub(crate) const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+/// `*at` 系列调用的选项：路径为空时操作 `dirfd` 本身
+const AT_EMPTY_PATH: i32 = 0x1000;
+/// `linkat` 的选项：旧路径的最后一个组件是符号链接时链接它指向的文件
+const AT_SYMLINK_FOLLOW: i32 = 0x400;
+/// `faccessat2` 的选项：按有效用户 ID 而不是实际用户 ID 检查权限
+const AT_EACCESS: i32 = 0x200;
+
+/// 设置文件的访问时间和修改时间，状态改变时间总是设为当前时间
+///
+/// `path` 为空指针，或带 `AT_EMPTY_PATH` 且 `path` 为空时，设置 `dirfd` 本身所指向的文件，
+/// 即 `futimens` 的语义。`times` 为空指针时
+/// 两个时间都设为当前时间，否则依次是访问时间和修改时间，其中 `tv_nsec` 可以是 `UTIME_NOW`
+/// 或 `UTIME_OMIT`，`times` 的地址无效时返回 `EFAULT`。带 `AT_SYMLINK_NOFOLLOW` 时，
+/// `path` 是符号链接则设置链接本身的时间。
+pub(crate) fn sys_utimensat(
+    dirfd: i32,
+    path: *const u8,
+    times: *const timespec,
+    flags: i32,
+) -> isize {
+    syscall_body!(sys_utimensat, {
+        if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let empty_path = !path.is_null()
+            && flags & AT_EMPTY_PATH != 0
+            && arceos_posix_api::char_ptr_to_str(path as *const i8)?.is_empty();
+        let path = if path.is_null() || empty_path {
+            let path = match arceos_posix_api::File::from_fd(dirfd) {
+                Ok(file) => file.path().to_string(),
+                Err(_) => arceos_posix_api::Directory::from_fd(dirfd)
+                    .map_err(|_| LinuxError::EBADF)?
+                    .path()
+                    .to_string(),
+            };
+            let path = FilePath::new(path)?;
+            path.check_writable()?;
+            path
+        } else {
+            let path = if flags & AT_SYMLINK_NOFOLLOW != 0 {
+                arceos_posix_api::handle_writable_link_path(dirfd as isize, path)?
             } else {
-                // 删除文件
-                axfs::api::metadata(path.as_str()).and_then(|metadata| {
-                    if metadata.is_dir() {
-                        Err(AxError::IsADirectory)
-                    } else {
-                        debug!("unlink file: {:?}", path);
-                        arceos_posix_api::HARDLINK_MANAGER
-                            .remove_link(&path)
-                            .ok_or_else(|| {
-                                debug!("unlink file error");
-                                AxError::NotFound
-                            })
-                            .map(|_| 0)
-                    }
-                })
+                arceos_posix_api::handle_writable_path(dirfd as isize, Some(path), false)?
+            };
+            if !path.exists() {
+                return Err(LinuxError::ENOENT);
             }
-        })
-        .unwrap_or(-1)
+            path
+        };
+
+        let now = arceos_posix_api::realtime();
+        let time = |ts: timespec| match ts.tv_nsec {
+            UTIME_NOW => Ok(Some(now)),
+            UTIME_OMIT => Ok(None),
+            // 早于纪元的时间按纪元处理
+            0..=999_999_999 => Ok(Some(Duration::new(
+                ts.tv_sec.max(0) as u64,
+                ts.tv_nsec as u32,
+            ))),
+            _ => Err(LinuxError::EINVAL),
+        };
+        let (atime, mtime) = if times.is_null() {
+            (Some(now), Some(now))
+        } else {
+            let times: [timespec; 2] = read_user(times.cast())?;
+            (time(times[0])?, time(times[1])?)
+        };
+        arceos_posix_api::FILE_TIMES.set(&path, atime, mtime);
+        axfs::notify(axfs::FsEvent::AttribChanged { path: &path });
+        Ok(0)
+    })
 }
 
 /// 文件系统信息
@@ -506,21 +739,97 @@ impl From<arceos_posix_api::ctypes::stat> for Kstat {
     }
 }
 
-pub(crate) fn sys_fstat(fd: i32, kstatbuf: *mut c_void) -> i32 {
-    let kstatbuf = kstatbuf as *mut Kstat;
+/// 获取 `fd` 所指向文件的元数据
+fn stat_fd(fd: i32) -> LinuxResult<Kstat> {
     let mut statbuf = arceos_posix_api::ctypes::stat::default();
-
-    if unsafe {
-        arceos_posix_api::sys_fstat(fd, &mut statbuf as *mut arceos_posix_api::ctypes::stat)
-    } < 0
-    {
-        return -1;
+    let ret = unsafe { arceos_posix_api::sys_fstat(fd, &mut statbuf) };
+    if ret < 0 {
+        return Err(LinuxError::try_from(-ret).unwrap_or(LinuxError::EBADF));
     }
+    // Kstat 比 stat 在 st_rdev 与 st_size 多了一个 _pad0 字段
+    Ok(Kstat::from(statbuf))
+}
 
-    unsafe {
-        // Kstat 比 stat 在 st_rdev 与 st_size 多了一个 _pad0 字段
-        let kstat = Kstat::from(statbuf);
-        kstatbuf.write(kstat);
-    }
-    0
+pub(crate) fn sys_fstat(fd: i32, kstatbuf: *mut c_void) -> isize {
+    syscall_body!(sys_fstat, {
+        write_user(kstatbuf as *mut Kstat, &stat_fd(fd)?)?;
+        Ok(0)
+    })
+}
+
+/// 获取 `dirfd` 和 `path` 所指定文件的元数据
+///
+/// 带 `AT_SYMLINK_NOFOLLOW` 时，`path` 是符号链接则返回链接本身的元数据，即 `lstat` 的语义；
+/// 带 `AT_EMPTY_PATH` 且 `path` 为空时返回 `dirfd` 本身的元数据，即 `fstat` 的语义。
+pub(crate) fn sys_fstatat(dirfd: i32, path: *const u8, kstatbuf: *mut Kstat, flags: i32) -> isize {
+    syscall_body!(sys_fstatat, {
+        if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path_str = arceos_posix_api::char_ptr_to_str(path as *const i8)?;
+        if path_str.is_empty() {
+            if flags & AT_EMPTY_PATH == 0 {
+                return Err(LinuxError::ENOENT);
+            }
+            // dirfd 为 AT_FDCWD 时是当前目录本身
+            let kstat = if dirfd as isize == arceos_posix_api::AT_FDCWD {
+                let cwd = axfs::api::current_dir()?;
+                Kstat::from(arceos_posix_api::stat_path(&FilePath::new(cwd)?)?)
+            } else {
+                stat_fd(dirfd)?
+            };
+            write_user(kstatbuf, &kstat)?;
+            return Ok(0);
+        }
+
+        let path = if flags & AT_SYMLINK_NOFOLLOW != 0 {
+            arceos_posix_api::handle_link_path(dirfd as isize, path)?
+        } else {
+            arceos_posix_api::handle_file_path(dirfd as isize, Some(path), false)?
+        };
+        if !path.exists() {
+            return Err(LinuxError::ENOENT);
+        }
+        let stat = arceos_posix_api::stat_path(&path)?;
+        write_user(kstatbuf, &Kstat::from(stat))?;
+        Ok(0)
+    })
+}
+
+/// 检查能否以 `mode`（`R_OK`、`W_OK`、`X_OK` 的组合）访问 `dirfd` 和 `path` 所指定的文件
+///
+/// `mode` 为 0 时只检查文件是否存在。与 Linux 一致，默认按实际用户 ID 检查，带
+/// `AT_EACCESS` 时按有效用户 ID 检查；`AT_SYMLINK_NOFOLLOW` 和 `AT_EMPTY_PATH` 与
+/// [`sys_fstatat`] 中的含义相同。
+pub(crate) fn sys_faccessat(dirfd: i32, path: *const u8, mode: u32, flags: i32) -> isize {
+    syscall_body!(sys_faccessat, {
+        if mode & !(R_OK | W_OK | X_OK) != 0
+            || flags & !(AT_EACCESS | AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let path_str = arceos_posix_api::char_ptr_to_str(path as *const i8)?;
+        let stat = if path_str.is_empty() {
+            if flags & AT_EMPTY_PATH == 0 {
+                return Err(LinuxError::ENOENT);
+            }
+            arceos_posix_api::get_file_like(dirfd)?.stat()?
+        } else {
+            let path = if flags & AT_SYMLINK_NOFOLLOW != 0 {
+                arceos_posix_api::handle_link_path(dirfd as isize, path)?
+            } else {
+                arceos_posix_api::handle_file_path(dirfd as isize, Some(path), false)?
+            };
+            if !path.exists() {
+                return Err(LinuxError::ENOENT);
+            }
+            arceos_posix_api::stat_path(&path)?
+        };
+        let mut cred = Cred::current();
+        if flags & AT_EACCESS == 0 {
+            cred.uid = current().task_ext().uid();
+        }
+        cred.check_access(stat.st_mode, mode)?;
+        Ok(0)
+    })
 }
@@ -0,0 +1,117 @@
+//! 文件访问模式的建议
+//!
+//! `posix_fadvise` 的建议作用于打开的文件：`SEQUENTIAL` 和 `RANDOM` 改变读取该文件时
+//! 块缓存的预读窗口，`WILLNEED` 由后台线程把指定范围读入块缓存，`DONTNEED` 从块缓存中
+//! 删除这一范围的页。块缓存与设备总是一致的，删除的页不需要写回。
+//!
+//! 其他类型的文件接受所有有效的建议，但不做任何事；管道没有可以建议的数据，返回 ESPIPE。
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use arceos_posix_api as api;
+use axerrno::LinuxError;
+use axfs::ReadAdvice;
+use axsync::Mutex;
+use axtask::WaitQueue;
+
+use crate::syscall_body;
+
+const POSIX_FADV_NORMAL: i32 = 0;
+const POSIX_FADV_RANDOM: i32 = 1;
+const POSIX_FADV_SEQUENTIAL: i32 = 2;
+const POSIX_FADV_WILLNEED: i32 = 3;
+const POSIX_FADV_DONTNEED: i32 = 4;
+const POSIX_FADV_NOREUSE: i32 = 5;
+
+/// 后台线程每次读入的字节数，读完一段后释放文件的锁，让文件的读者不必等待整个范围
+const PREFETCH_CHUNK: u64 = 64 * 1024;
+
+/// 等待读入块缓存的文件范围
+static PENDING: Mutex<Vec<(Arc<api::File>, u64, u64)>> = Mutex::new(Vec::new());
+
+/// 预读线程在其上等待新的请求
+static PREFETCH_WQ: WaitQueue = WaitQueue::new();
+
+/// 是否有尚未处理的请求
+static QUEUED: AtomicBool = AtomicBool::new(false);
+
+/// 预读线程是否已经启动
+static DAEMON_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// 对文件 `fd` 中从 `offset` 开始的 `len` 字节（`len` 为 0 时到文件末尾）给出访问模式的建议
+///
+/// `NORMAL`、`SEQUENTIAL` 和 `RANDOM` 作用于整个打开的文件，与范围无关；`NOREUSE` 没有效果。
+pub(crate) fn sys_fadvise64(fd: i32, offset: i64, len: i64, advice: i32) -> isize {
+    syscall_body!(sys_fadvise64, {
+        let file = api::get_file_like(fd)?;
+        if file.status_flags().path_only() {
+            return Err(LinuxError::EBADF);
+        }
+        if file.stat()?.st_mode & 0o170000 == 0o010000 {
+            // S_IFIFO
+            return Err(LinuxError::ESPIPE);
+        }
+        if len < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if !(POSIX_FADV_NORMAL..=POSIX_FADV_NOREUSE).contains(&advice) {
+            return Err(LinuxError::EINVAL);
+        }
+        let Ok(file) = file.into_any().downcast::<api::File>() else {
+            return Ok(0);
+        };
+        let offset = offset.max(0) as u64;
+        let len = if len == 0 { u64::MAX } else { len as u64 };
+        match advice {
+            POSIX_FADV_NORMAL => file.inner().lock().set_read_advice(ReadAdvice::Normal),
+            POSIX_FADV_RANDOM => file.inner().lock().set_read_advice(ReadAdvice::Random),
+            POSIX_FADV_SEQUENTIAL => file.inner().lock().set_read_advice(ReadAdvice::Sequential),
+            POSIX_FADV_WILLNEED => prefetch(file, offset, len),
+            POSIX_FADV_DONTNEED => {
+                // 只是建议，无法读取文件（如只写打开）时什么也不做
+                let _ = file.inner().lock().drop_cache(offset, len);
+            }
+            _ => {}
+        }
+        Ok(0)
+    })
+}
+
+/// 让预读线程把文件中从 `offset` 开始的 `len` 字节读入块缓存
+fn prefetch(file: Arc<api::File>, offset: u64, len: u64) {
+    PENDING.lock().push((file, offset, len));
+    if !DAEMON_STARTED.swap(true, Ordering::SeqCst) {
+        axtask::spawn_raw(
+            prefetch_daemon,
+            String::from("prefetch"),
+            crate::config::KERNEL_STACK_SIZE,
+        );
+    }
+    QUEUED.store(true, Ordering::SeqCst);
+    PREFETCH_WQ.notify_one(false);
+}
+
+/// 处理 `WILLNEED` 请求的预读线程
+fn prefetch_daemon() {
+    loop {
+        PREFETCH_WQ.wait_until(|| QUEUED.load(Ordering::SeqCst));
+        QUEUED.store(false, Ordering::SeqCst);
+        let pending = core::mem::take(&mut *PENDING.lock());
+        for (file, offset, len) in pending {
+            let Ok(attr) = file.inner().lock().get_attr() else {
+                continue;
+            };
+            let end = offset.saturating_add(len).min(attr.size());
+            let mut pos = offset;
+            while pos < end {
+                let chunk = (end - pos).min(PREFETCH_CHUNK);
+                if let Err(err) = file.inner().lock().prefetch(pos, chunk) {
+                    warn!("Failed to prefetch {}: {:?}", file.path(), err);
+                    break;
+                }
+                pos += chunk;
+            }
+        }
+    }
+}
@@ -0,0 +1,498 @@
+//! 文件系统事件的监视（inotify）
+//!
+//! inotify 实例是一个可读、可被 poll 的文件描述符，读到的是一个个 `struct inotify_event`。
+//! 监视按路径记录：监视目录时报告其中的子项被创建、删除、移动、修改、修改属性和写入后
+//! 关闭，事件中带有子项的名字；监视文件或目录本身时报告它被修改、删除和移动等。
+//! 事件来自 [`axfs::FsListener`]，即通过文件系统接口做出的修改。
+//!
+//! 队列中的事件超过 [`MAX_QUEUED_EVENTS`] 时，之后的事件被丢弃，队列末尾留下一个
+//! `IN_Q_OVERFLOW` 事件，修改文件系统的进程不会因此阻塞。
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use arceos_posix_api::{
+    self as api, ctypes, FileLike, PollState, Pollable, StatusFlags, WaitQueue,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axfs::{FsEvent, FsListener};
+use axsync::Mutex;
+
+use crate::{syscall_body, task::signal::block_interruptible};
+
+const IN_MODIFY: u32 = 0x2;
+const IN_ATTRIB: u32 = 0x4;
+const IN_CLOSE_WRITE: u32 = 0x8;
+const IN_MOVED_FROM: u32 = 0x40;
+const IN_MOVED_TO: u32 = 0x80;
+const IN_CREATE: u32 = 0x100;
+const IN_DELETE: u32 = 0x200;
+const IN_DELETE_SELF: u32 = 0x400;
+const IN_MOVE_SELF: u32 = 0x800;
+/// 所有可以监视的事件
+const IN_ALL_EVENTS: u32 = 0xfff;
+
+/// 事件队列溢出，之后的事件被丢弃
+const IN_Q_OVERFLOW: u32 = 0x4000;
+/// 监视已被删除
+const IN_IGNORED: u32 = 0x8000;
+/// 事件的对象是目录
+const IN_ISDIR: u32 = 0x4000_0000;
+
+/// 只监视目录
+const IN_ONLYDIR: u32 = 0x0100_0000;
+/// 不展开路径最后的符号链接
+const IN_DONT_FOLLOW: u32 = 0x0200_0000;
+/// 不报告已删除的子项的事件，目前删除后不会再有事件，因此只被记录下来
+const IN_EXCL_UNLINK: u32 = 0x0400_0000;
+/// 路径已被监视时返回 EEXIST
+const IN_MASK_CREATE: u32 = 0x1000_0000;
+/// 路径已被监视时把事件加到原来的事件中，而不是替换它们
+const IN_MASK_ADD: u32 = 0x2000_0000;
+/// 报告一个事件后删除监视
+const IN_ONESHOT: u32 = 0x8000_0000;
+
+const IN_NONBLOCK: i32 = ctypes::O_NONBLOCK as i32;
+const IN_CLOEXEC: i32 = ctypes::O_CLOEXEC as i32;
+
+/// 每个实例的队列中最多的事件数，与 Linux 的 `max_queued_events` 默认值一致
+const MAX_QUEUED_EVENTS: usize = 16384;
+/// 最多同时存在的实例数
+const MAX_INSTANCES: usize = 128;
+/// 所有实例的监视总数的上限
+const MAX_WATCHES: usize = 8192;
+
+/// `struct inotify_event` 的固定部分的长度
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// 所有未关闭的实例
+static INSTANCES: Mutex<Vec<Weak<Inotify>>> = Mutex::new(Vec::new());
+/// 所有实例的监视总数，为 0 时不必检查文件系统事件
+static WATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+/// 用于关联同一次移动的 `IN_MOVED_FROM` 和 `IN_MOVED_TO` 事件
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// 一个被监视的文件或目录
+struct Watch {
+    /// 规范化的绝对路径，文件或目录被移动时随之改变
+    path: String,
+    /// 要报告的事件和 `IN_ONESHOT` 等标志
+    mask: u32,
+}
+
+/// 队列中的一个事件
+#[derive(PartialEq, Eq)]
+struct Event {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    /// 监视目录时子项的名字，其他情况为空
+    name: String,
+}
+
+impl Event {
+    /// 名字所占的长度：名字不为空时包括结尾的 `'\0'`，并用 `'\0'` 填充到 16 字节的倍数
+    fn name_len(&self) -> usize {
+        if self.name.is_empty() {
+            0
+        } else {
+            (self.name.len() + 1).next_multiple_of(EVENT_HEADER_SIZE)
+        }
+    }
+
+    /// 读取时的总长度
+    fn record_len(&self) -> usize {
+        EVENT_HEADER_SIZE + self.name_len()
+    }
+
+    /// 按 `struct inotify_event` 的布局写入 `buf`，`buf` 的长度为 [`Self::record_len`]
+    fn write_to(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(self.name_len() as u32).to_ne_bytes());
+        let name = &mut buf[EVENT_HEADER_SIZE..];
+        name[..self.name.len()].copy_from_slice(self.name.as_bytes());
+        name[self.name.len()..].fill(0);
+    }
+}
+
+#[derive(Default)]
+struct InotifyState {
+    /// 以监视描述符为键
+    watches: BTreeMap<i32, Watch>,
+    /// 最近分配的监视描述符，新的监视描述符递增分配，删除的不会马上被重新使用
+    last_wd: i32,
+    events: VecDeque<Event>,
+}
+
+impl InotifyState {
+    /// 将事件加入队列，与队列末尾的事件相同时合并
+    fn push(&mut self, event: Event) {
+        if self.events.back() == Some(&event) {
+            return;
+        }
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            if self
+                .events
+                .back()
+                .is_some_and(|last| last.mask != IN_Q_OVERFLOW)
+            {
+                self.events.push_back(Event {
+                    wd: -1,
+                    mask: IN_Q_OVERFLOW,
+                    cookie: 0,
+                    name: String::new(),
+                });
+            }
+            return;
+        }
+        self.events.push_back(event);
+    }
+
+    /// 删除监视，并报告 `IN_IGNORED`
+    fn remove(&mut self, wd: i32) -> LinuxResult {
+        self.watches.remove(&wd).ok_or(LinuxError::EINVAL)?;
+        WATCH_COUNT.fetch_sub(1, Ordering::Relaxed);
+        self.push(Event {
+            wd,
+            mask: IN_IGNORED,
+            cookie: 0,
+            name: String::new(),
+        });
+        Ok(())
+    }
+
+    /// 为路径为 `path` 的监视报告事件，`name` 为空时报告的是被监视的对象本身的事件
+    fn report(&mut self, path: &str, mask: u32, cookie: u32, name: &str) {
+        let matched: Vec<_> = self
+            .watches
+            .iter()
+            .filter(|(_, watch)| watch.path == path && watch.mask & mask & IN_ALL_EVENTS != 0)
+            .map(|(&wd, watch)| (wd, watch.mask))
+            .collect();
+        for (wd, watch_mask) in matched {
+            self.push(Event {
+                wd,
+                mask,
+                cookie,
+                name: name.into(),
+            });
+            if watch_mask & IN_ONESHOT != 0 {
+                let _ = self.remove(wd);
+            }
+        }
+    }
+
+    /// 为 `path` 所在目录的监视和 `path` 本身的监视报告事件
+    fn report_both(&mut self, path: &str, mask: u32) {
+        let (parent, name) = split_path(path);
+        self.report(parent, mask, 0, name);
+        self.report(path, mask, 0, "");
+    }
+
+    fn handle(&mut self, event: &FsEvent, cookie: u32) {
+        let is_dir = |is_dir: bool| if is_dir { IN_ISDIR } else { 0 };
+        match *event {
+            FsEvent::Created { path, is_dir: dir } => {
+                let (parent, name) = split_path(path);
+                self.report(parent, IN_CREATE | is_dir(dir), 0, name);
+            }
+            FsEvent::Removed { path, is_dir: dir } => {
+                let (parent, name) = split_path(path);
+                self.report(parent, IN_DELETE | is_dir(dir), 0, name);
+                self.report(path, IN_DELETE_SELF, 0, "");
+                let removed: Vec<_> = self
+                    .watches
+                    .iter()
+                    .filter(|(_, watch)| watch.path == path)
+                    .map(|(&wd, _)| wd)
+                    .collect();
+                for wd in removed {
+                    let _ = self.remove(wd);
+                }
+            }
+            FsEvent::Renamed {
+                old,
+                new,
+                is_dir: dir,
+            } => {
+                let (old_parent, old_name) = split_path(old);
+                let (new_parent, new_name) = split_path(new);
+                self.report(old_parent, IN_MOVED_FROM | is_dir(dir), cookie, old_name);
+                self.report(new_parent, IN_MOVED_TO | is_dir(dir), cookie, new_name);
+                self.report(old, IN_MOVE_SELF, 0, "");
+                // 被移动的对象及其下的监视随之移动
+                for watch in self.watches.values_mut() {
+                    if watch.path == old {
+                        watch.path = new.into();
+                    } else if let Some(rest) = watch.path.strip_prefix(old) {
+                        if rest.starts_with('/') {
+                            watch.path = alloc::format!("{}{}", new, rest);
+                        }
+                    }
+                }
+            }
+            FsEvent::Modified { path } => self.report_both(path, IN_MODIFY),
+            FsEvent::AttribChanged { path } => self.report_both(path, IN_ATTRIB),
+            FsEvent::ClosedWrite { path } => self.report_both(path, IN_CLOSE_WRITE),
+        }
+    }
+}
+
+/// 去掉路径结尾的 `/`，根目录除外
+fn normalize(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+/// 把路径分为所在目录和名字
+fn split_path(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(0) => ("/", &path[1..]),
+        Some(idx) => (&path[..idx], &path[idx + 1..]),
+        None => ("", path),
+    }
+}
+
+/// `inotify_init1` 创建的实例
+pub(crate) struct Inotify {
+    flags: StatusFlags,
+    state: Mutex<InotifyState>,
+    /// 有新的事件时唤醒等待读取的任务
+    readers: WaitQueue,
+}
+
+impl Inotify {
+    /// 获取文件描述符对应的实例，`fd` 不是 inotify 实例时返回 EINVAL
+    fn from_fd(fd: i32) -> LinuxResult<Arc<Self>> {
+        api::get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        WATCH_COUNT.fetch_sub(self.state.get_mut().watches.len(), Ordering::Relaxed);
+    }
+}
+
+impl FileLike for Inotify {
+    /// 读取尽量多的完整事件，缓冲区放不下第一个事件时返回 EINVAL
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let mut state = block_interruptible(&self.readers, None, || {
+            let state = self.state.lock();
+            if !state.events.is_empty() {
+                return Some(Ok(state));
+            }
+            if self.flags.nonblocking() {
+                return Some(Err(LinuxError::EAGAIN));
+            }
+            None
+        })??;
+        let mut written = 0;
+        while let Some(event) = state.events.front() {
+            let len = event.record_len();
+            if written + len > buf.len() {
+                break;
+            }
+            event.write_to(&mut buf[written..written + len]);
+            written += len;
+            state.events.pop_front();
+        }
+        if written == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(written)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o600,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn link_target(&self) -> String {
+        "anon_inode:inotify".into()
+    }
+}
+
+impl Pollable for Inotify {
+    /// 有事件时可读
+    fn poll(&self) -> PollState {
+        PollState {
+            readable: !self.state.lock().events.is_empty(),
+            ..Default::default()
+        }
+    }
+}
+
+struct InotifyListener;
+
+impl FsListener for InotifyListener {
+    fn notify(&self, event: &FsEvent) {
+        if WATCH_COUNT.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let cookie = match event {
+            FsEvent::Renamed { .. } => NEXT_COOKIE.fetch_add(1, Ordering::Relaxed),
+            _ => 0,
+        };
+        let normalized = match *event {
+            FsEvent::Created { path, is_dir } => FsEvent::Created {
+                path: normalize(path),
+                is_dir,
+            },
+            FsEvent::Removed { path, is_dir } => FsEvent::Removed {
+                path: normalize(path),
+                is_dir,
+            },
+            FsEvent::Renamed { old, new, is_dir } => FsEvent::Renamed {
+                old: normalize(old),
+                new: normalize(new),
+                is_dir,
+            },
+            FsEvent::Modified { path } => FsEvent::Modified {
+                path: normalize(path),
+            },
+            FsEvent::AttribChanged { path } => FsEvent::AttribChanged {
+                path: normalize(path),
+            },
+            FsEvent::ClosedWrite { path } => FsEvent::ClosedWrite {
+                path: normalize(path),
+            },
+        };
+        let instances: Vec<_> = INSTANCES.lock().iter().filter_map(Weak::upgrade).collect();
+        for instance in instances {
+            instance.state.lock().handle(&normalized, cookie);
+            instance.readers.notify_all();
+        }
+    }
+}
+
+/// 接收文件系统的事件
+pub(crate) fn init_inotify() {
+    axfs::register_fs_listener(&InotifyListener);
+}
+
+/// 创建 inotify 实例，`flags` 可以包含 `IN_NONBLOCK` 和 `IN_CLOEXEC`
+pub(crate) fn sys_inotify_init1(flags: i32) -> isize {
+    syscall_body!(sys_inotify_init1, {
+        if flags & !(IN_NONBLOCK | IN_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let inotify = Arc::new(Inotify {
+            flags: StatusFlags::new(ctypes::O_RDONLY | (flags & IN_NONBLOCK) as u32),
+            state: Mutex::new(InotifyState::default()),
+            readers: WaitQueue::new(),
+        });
+        {
+            let mut instances = INSTANCES.lock();
+            instances.retain(|instance| instance.strong_count() > 0);
+            if instances.len() >= MAX_INSTANCES {
+                return Err(LinuxError::EMFILE);
+            }
+            instances.push(Arc::downgrade(&inotify));
+        }
+        let fd = api::add_file_like(inotify)?;
+        api::set_cloexec(fd, flags & IN_CLOEXEC != 0);
+        Ok(fd as isize)
+    })
+}
+
+/// 监视 `pathname` 上的 `mask` 中的事件，返回监视描述符
+///
+/// 同一个实例中监视同一个路径时返回已有的监视描述符，并替换它的事件；
+/// 指定 `IN_MASK_ADD` 时改为加入这些事件，指定 `IN_MASK_CREATE` 时返回 EEXIST。
+pub(crate) fn sys_inotify_add_watch(fd: i32, pathname: *const u8, mask: u32) -> isize {
+    syscall_body!(sys_inotify_add_watch, {
+        let inotify = Inotify::from_fd(fd)?;
+        if mask & IN_ALL_EVENTS == 0 || mask & IN_MASK_ADD != 0 && mask & IN_MASK_CREATE != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = if mask & IN_DONT_FOLLOW != 0 {
+            api::handle_link_path(api::AT_FDCWD, pathname)?
+        } else {
+            api::handle_file_path(api::AT_FDCWD, Some(pathname), false)?
+        };
+        let path = api::HARDLINK_MANAGER.real_path(&path);
+        let metadata = axfs::api::metadata(&path)?;
+        if mask & IN_ONLYDIR != 0 && !metadata.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        let path = normalize(&path);
+        let new_mask = mask & (IN_ALL_EVENTS | IN_ONESHOT | IN_EXCL_UNLINK);
+
+        let mut state = inotify.state.lock();
+        if let Some((&wd, watch)) = state.watches.iter_mut().find(|(_, w)| w.path == path) {
+            if mask & IN_MASK_CREATE != 0 {
+                return Err(LinuxError::EEXIST);
+            }
+            if mask & IN_MASK_ADD != 0 {
+                watch.mask |= new_mask;
+            } else {
+                watch.mask = new_mask;
+            }
+            return Ok(wd as isize);
+        }
+        if WATCH_COUNT.load(Ordering::Relaxed) >= MAX_WATCHES {
+            return Err(LinuxError::ENOSPC);
+        }
+        let mut wd = state.last_wd;
+        loop {
+            wd = if wd == i32::MAX { 1 } else { wd + 1 };
+            if !state.watches.contains_key(&wd) {
+                break;
+            }
+        }
+        state.last_wd = wd;
+        state.watches.insert(
+            wd,
+            Watch {
+                path: path.into(),
+                mask: new_mask,
+            },
+        );
+        WATCH_COUNT.fetch_add(1, Ordering::Relaxed);
+        Ok(wd as isize)
+    })
+}
+
+/// 删除监视描述符 `wd`，并在队列中加入 `IN_IGNORED` 事件
+pub(crate) fn sys_inotify_rm_watch(fd: i32, wd: i32) -> isize {
+    syscall_body!(sys_inotify_rm_watch, {
+        let inotify = Inotify::from_fd(fd)?;
+        inotify.state.lock().remove(wd)?;
+        // 删除监视时产生了 `IN_IGNORED` 事件
+        inotify.readers.notify_all();
+        Ok(0)
+    })
+}
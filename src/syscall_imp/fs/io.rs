@@ -1,6 +1,14 @@
+use alloc::vec;
 use core::ffi::c_void;
 
-use arceos_posix_api::{self as api, ctypes::mode_t};
+use arceos_posix_api::{self as api, ctypes::mode_t, Cap};
+use axerrno::LinuxError;
+
+use super::{open_file, Cred, MemFd, SEEK_CUR};
+use crate::{
+    mm::{read_user, write_user},
+    syscall_body,
+};
 
 pub(crate) fn sys_read(fd: i32, buf: *mut c_void, count: usize) -> isize {
     api::sys_read(fd, buf, count)
@@ -10,28 +18,28 @@ pub(crate) fn sys_write(fd: i32, buf: *const c_void, count: usize) -> isize {
     api::sys_write(fd, buf, count)
 }
 
+pub(crate) fn sys_readv(fd: i32, iov: *const api::ctypes::iovec, iocnt: i32) -> isize {
+    unsafe { api::sys_readv(fd, iov, iocnt) }
+}
+
 pub(crate) fn sys_writev(fd: i32, iov: *const api::ctypes::iovec, iocnt: i32) -> isize {
     unsafe { api::sys_writev(fd, iov, iocnt) }
 }
 
 pub(crate) fn sys_pipe2(fds: *mut i32, flags: i32) -> isize {
-    if flags != 0 {
-        warn!("sys_pipe2: flags are not supported, ignoring");
-    }
-
     let fds = match unsafe { fds.as_mut() } {
         Some(ptr) => unsafe { core::slice::from_raw_parts_mut(ptr, 2) },
         None => {
             error!("sys_pipe2: invalid fds pointer");
-            return -1;
+            return -LinuxError::EFAULT.code() as isize;
         },
     };
 
-    match api::sys_pipe(fds) {
+    match api::sys_pipe2(fds, flags) {
         0 => 0,
         err => {
             error!("sys_pipe2: failed to create pipe, error code {}", err);
-            -1 
+            err as isize
         },
     }
 }
@@ -46,6 +54,120 @@ pub(crate) fn sys_close(fd: i32) -> isize {
     }
 }
 
+pub(crate) fn sys_close_range(first: u32, last: u32, flags: u32) -> isize {
+    api::sys_close_range(first, last, flags) as isize
+}
+
+/// 打开或创建 `dirfd` 下的文件 `path`，返回新的文件描述符，见 [`open_file`]
 pub(crate) fn sys_openat(dirfd: i32, path: *const i8, flags: i32, mode: mode_t) -> isize {
-    api::sys_openat(dirfd, path, flags, mode) as isize
-}
\ No newline at end of file
+    syscall_body!(sys_openat, {
+        let path = api::char_ptr_to_str(path)?;
+        let file = open_file(dirfd, path, flags as u32, mode, &Cred::current())?;
+        api::add_open_file(file, flags)
+    })
+}
+
+pub(crate) fn sys_sync() -> isize {
+    crate::mm::file_map::sync_all();
+    api::sys_sync() as isize
+}
+
+/// 修改文件的读写位置，返回新的位置
+pub(crate) fn sys_lseek(fd: i32, offset: i64, whence: i32) -> isize {
+    match MemFd::from_fd(fd) {
+        Ok(file) => syscall_body!(sys_lseek, file.seek(offset, whence)),
+        Err(_) => api::sys_lseek(fd, offset as _, whence) as _,
+    }
+}
+
+/// `sendfile` 每次复制的最大字节数
+const SENDFILE_CHUNK: usize = 0x4000;
+
+/// 将 `in_fd` 中至多 `count` 字节的数据复制到 `out_fd`，返回复制的字节数
+///
+/// `offset` 为空时从 `in_fd` 的读写位置读取并移动它；否则从 `*offset` 处读取，结束后将
+/// `*offset` 更新为读取结束的位置，`in_fd` 的读写位置不变，此时 `in_fd` 须为普通文件。
+/// 与 `write` 相同，已复制部分数据后被信号打断或 `out_fd` 暂时不可写时返回已复制的字节数，
+/// 没有复制任何数据时才返回错误。
+pub(crate) fn sys_sendfile(out_fd: i32, in_fd: i32, offset: *mut i64, count: usize) -> isize {
+    syscall_body!(sys_sendfile, {
+        let input = api::access_file_like(in_fd, Cap::READ, LinuxError::EBADF)?;
+        let output = api::access_file_like(out_fd, Cap::WRITE, LinuxError::EBADF)?;
+        let mut pos = if offset.is_null() {
+            None
+        } else {
+            Some(u64::try_from(read_user(offset)?).map_err(|_| LinuxError::EINVAL)?)
+        };
+        let read = |buf: &mut [u8], pos: Option<u64>| match pos {
+            None => input.read(buf),
+            Some(pos) => match MemFd::from_fd(in_fd) {
+                Ok(memfd) => Ok(memfd.read_at(pos as usize, buf)),
+                Err(_) => {
+                    let file = api::File::from_fd(in_fd).map_err(|_| LinuxError::ESPIPE)?;
+                    let len = file.inner().lock().read_at(pos, buf)?;
+                    Ok(len)
+                }
+            },
+        };
+
+        let mut buf = vec![0u8; SENDFILE_CHUNK.min(count)];
+        let mut total = 0;
+        let result = loop {
+            if total == count {
+                break Ok(());
+            }
+            let chunk = SENDFILE_CHUNK.min(count - total);
+            let len = match read(&mut buf[..chunk], pos) {
+                Ok(0) => break Ok(()),
+                Ok(len) => len,
+                Err(err) => break Err(err),
+            };
+            // 读出的数据须全部写出，否则只有读取了的部分计入结果，数据会丢失
+            let mut written = 0;
+            while written < len {
+                match output.write(&buf[written..len]) {
+                    Ok(0) => break,
+                    Ok(n) => written += n,
+                    Err(err) if total + written == 0 => return Err(err),
+                    Err(_) => break,
+                }
+            }
+            total += written;
+            if let Some(pos) = pos.as_mut() {
+                *pos += written as u64;
+            } else if written < len {
+                // 将读写位置退回到未写出的数据之前
+                let back = -((len - written) as i64);
+                sys_lseek(in_fd, back, SEEK_CUR);
+            }
+            if written < len {
+                break Ok(());
+            }
+        };
+        if let Some(pos) = pos {
+            write_user(offset, &(pos as i64))?;
+        }
+        match result {
+            Err(err) if total == 0 => Err(err),
+            _ => Ok(total as isize),
+        }
+    })
+}
+
+/// 将文件大小设为 `length`，扩大的部分填充为 0
+///
+/// 与 Linux 相同，文件描述符不可写时返回 EINVAL。
+pub(crate) fn sys_ftruncate(fd: i32, length: i64) -> isize {
+    syscall_body!(sys_ftruncate, {
+        let length = usize::try_from(length).map_err(|_| LinuxError::EINVAL)?;
+        let file = api::access_file_like(fd, Cap::WRITE, LinuxError::EINVAL)?.into_any();
+        match file.downcast::<MemFd>() {
+            Ok(memfd) => memfd.truncate(length)?,
+            Err(file) => file
+                .downcast::<api::File>()
+                .map_err(|_| LinuxError::EINVAL)?
+                .truncate(length as u64)?,
+        }
+        Ok(0)
+    })
+}
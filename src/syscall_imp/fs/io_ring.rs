@@ -0,0 +1,364 @@
+//! 批量 I/O 环：一次系统调用执行多个读、写和 fsync
+//!
+//! 小块 I/O 的开销主要在陷入内核上。进程调用 `io_setup_lite` 登记一组文件描述符，内核在
+//! 其地址空间中映射一块用户与内核共享的内存，依次是环的头部 [`RingHeader`]、提交队列和
+//! 完成队列。进程在提交队列中填写提交项 [`Sqe`] 并推进 `sq_tail`，然后调用 `io_enter`，
+//! 内核依次取出提交项执行，把结果作为完成项 [`Cqe`] 写入完成队列并推进 `cq_tail`；进程
+//! 取走完成项后推进 `cq_head`。操作目前同步执行，`io_enter` 返回时都已完成。
+//!
+//! 环可以被用户任意改写，因此内核自己保存 `sq_head` 和 `cq_tail`，每次返回前写回环中，
+//! 只从环中读取 `sq_tail`、`cq_head` 和提交项。提交项的操作码、保留字段、文件序号和缓冲区
+//! 逐项检查，不合法时对应完成项的结果为负的错误码。提交项按序号引用登记时的文件，之后关闭
+//! 或替换文件描述符不影响环。
+//!
+//! 每个任务最多有一个环，fork 的子进程不继承，exec 时释放。两个系统调用是本内核特有的，
+//! 编号见 [`SYS_IO_SETUP_LITE`](crate::syscall_imp::SYS_IO_SETUP_LITE) 和
+//! [`SYS_IO_ENTER`](crate::syscall_imp::SYS_IO_ENTER)。
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::mem::{offset_of, size_of};
+
+use arceos_posix_api::{self as api, Cap, FileLike};
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axhal::paging::MappingFlags;
+use axtask::{current, TaskExtRef};
+use memory_addr::{VirtAddr, VirtAddrRange};
+
+use super::MemFd;
+use crate::{
+    mm::{copy_from_user, copy_to_user, read_user, write_user},
+    syscall_body,
+    task::rlimit::RLIMIT_AS,
+};
+
+/// 空操作
+const IO_OP_NOP: u8 = 0;
+/// 从文件的当前位置读取 `len` 字节到 `addr`
+const IO_OP_READ: u8 = 1;
+/// 将 `addr` 处的 `len` 字节写入文件的当前位置
+const IO_OP_WRITE: u8 = 2;
+/// 将文件的修改写回设备
+const IO_OP_FSYNC: u8 = 3;
+
+/// 提交队列项数的上限
+const MAX_ENTRIES: u32 = 4096;
+/// 登记的文件描述符数的上限
+const MAX_FILES: u32 = 64;
+/// 一次读写的最大字节数
+const MAX_IO_LEN: u32 = 1 << 20;
+/// 环头部占用的字节数，提交队列从这里开始
+const HEADER_SIZE: usize = 64;
+
+/// `io_setup_lite` 的参数，即 C 中的 `struct io_lite_params`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IoLiteParams {
+    /// 提交队列的项数，为不超过 [`MAX_ENTRIES`] 的 2 的幂
+    sq_entries: u32,
+    /// 登记的文件描述符数，不超过 [`MAX_FILES`]
+    nr_fds: u32,
+    /// 登记的文件描述符数组的地址
+    fds: u64,
+    /// 返回完成队列的项数，为提交队列的两倍
+    cq_entries: u32,
+    /// 返回提交队列相对于环起始地址的偏移
+    sqes_off: u32,
+    /// 返回完成队列相对于环起始地址的偏移
+    cqes_off: u32,
+    /// 返回环的总字节数，可用于 `munmap`
+    ring_size: u32,
+}
+
+/// 环的头部，位于环的起始地址
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct RingHeader {
+    /// 下一个要执行的提交项，由内核推进
+    sq_head: u32,
+    /// 最后一个提交项之后的位置，由用户推进
+    sq_tail: u32,
+    /// 下一个要取走的完成项，由用户推进
+    cq_head: u32,
+    /// 最后一个完成项之后的位置，由内核推进
+    cq_tail: u32,
+    /// 提交队列的项数
+    sq_entries: u32,
+    /// 完成队列的项数
+    cq_entries: u32,
+}
+
+/// 提交项，即 C 中的 `struct io_lite_sqe`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Sqe {
+    /// 操作码，`IO_OP_*`
+    opcode: u8,
+    /// 必须为 0
+    flags: u8,
+    /// 必须为 0
+    reserved: u16,
+    /// 文件在登记的文件描述符数组中的序号
+    fd_index: u32,
+    /// 缓冲区的地址
+    addr: u64,
+    /// 缓冲区的字节数，不超过 [`MAX_IO_LEN`]
+    len: u32,
+    /// 必须为 0
+    reserved2: u32,
+    /// 原样复制到完成项中
+    user_data: u64,
+}
+
+/// 完成项，即 C 中的 `struct io_lite_cqe`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Cqe {
+    /// 提交项中的 `user_data`
+    user_data: u64,
+    /// 操作的结果，成功时为读写的字节数，失败时为负的错误码
+    res: i32,
+    /// 目前为 0
+    flags: u32,
+}
+
+/// 任务的批量 I/O 环在内核中的状态
+pub(crate) struct IoRing {
+    /// 环在用户地址空间中的起始地址
+    base: VirtAddr,
+    sq_entries: u32,
+    cq_entries: u32,
+    /// 登记的文件
+    files: Vec<Arc<dyn FileLike>>,
+    /// 下一个要执行的提交项，不信任环中的副本
+    sq_head: u32,
+    /// 下一个完成项的位置，不信任环中的副本
+    cq_tail: u32,
+}
+
+impl IoRing {
+    fn header(&self) -> *mut RingHeader {
+        self.base.as_mut_ptr_of()
+    }
+
+    fn sqe(&self, index: u32) -> *const Sqe {
+        let index = (index & (self.sq_entries - 1)) as usize;
+        (self.base + HEADER_SIZE + index * size_of::<Sqe>()).as_ptr_of()
+    }
+
+    fn cqe(&self, index: u32) -> *mut Cqe {
+        let index = (index & (self.cq_entries - 1)) as usize;
+        let cqes = HEADER_SIZE + self.sq_entries as usize * size_of::<Sqe>();
+        (self.base + cqes + index * size_of::<Cqe>()).as_mut_ptr_of()
+    }
+
+    /// 执行一个提交项，返回完成项中的结果
+    fn execute(&self, sqe: &Sqe) -> LinuxResult<usize> {
+        if sqe.flags != 0 || sqe.reserved != 0 || sqe.reserved2 != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if sqe.opcode == IO_OP_NOP {
+            return Ok(0);
+        }
+        let file = self
+            .files
+            .get(sqe.fd_index as usize)
+            .ok_or(LinuxError::EBADF)?;
+        let cap = file.status_flags().cap();
+        match sqe.opcode {
+            IO_OP_READ => {
+                if !cap.contains(Cap::READ) {
+                    return Err(LinuxError::EBADF);
+                }
+                let (addr, mut buf) = user_buffer(sqe)?;
+                let n = file.read(&mut buf)?;
+                copy_to_user(addr, &buf[..n])?;
+                Ok(n)
+            }
+            IO_OP_WRITE => {
+                if !cap.contains(Cap::WRITE) {
+                    return Err(LinuxError::EBADF);
+                }
+                let (addr, mut buf) = user_buffer(sqe)?;
+                copy_from_user(addr, &mut buf)?;
+                file.write(&buf)
+            }
+            IO_OP_FSYNC => fsync(file),
+            _ => Err(LinuxError::EINVAL),
+        }
+    }
+}
+
+/// 检查提交项中的缓冲区，返回其地址和同样大小的内核缓冲区
+fn user_buffer(sqe: &Sqe) -> LinuxResult<(VirtAddr, Vec<u8>)> {
+    if sqe.len > MAX_IO_LEN {
+        return Err(LinuxError::EINVAL);
+    }
+    let addr = usize::try_from(sqe.addr).map_err(|_| LinuxError::EFAULT)?;
+    if addr.checked_add(sqe.len as usize).is_none() {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok((VirtAddr::from(addr), vec![0; sqe.len as usize]))
+}
+
+/// 与 `fsync` 相同，只读打开的文件没有需要写回的内容，内存文件不需要写回
+fn fsync(file: &Arc<dyn FileLike>) -> LinuxResult<usize> {
+    let any = file.clone().into_any();
+    if any.is::<MemFd>() {
+        return Ok(0);
+    }
+    let file = any
+        .downcast::<api::File>()
+        .map_err(|_| LinuxError::EINVAL)?;
+    if file.status_flags().cap().contains(Cap::WRITE) {
+        match file.inner().lock().flush() {
+            // 文件系统不支持 fsync，没有缓存需要写回，如 ramfs
+            Ok(()) | Err(AxError::InvalidInput) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(0)
+}
+
+/// 建立当前任务的批量 I/O 环，返回环在用户地址空间中的起始地址
+///
+/// 登记 `params` 中的文件描述符，并填写环的布局。`sq_entries` 不是 2 的幂或超过上限、
+/// 登记的文件描述符过多时返回 `EINVAL`，文件描述符无效时返回 `EBADF`，任务已经有环时
+/// 返回 `EBUSY`。
+pub(crate) fn sys_io_setup_lite(params: *mut u8) -> isize {
+    syscall_body!(sys_io_setup_lite, {
+        let params = params as *mut IoLiteParams;
+        let curr = current();
+        let ext = curr.task_ext();
+        let mut slot = ext.io_ring().lock();
+        if slot.is_some() {
+            return Err(LinuxError::EBUSY);
+        }
+        let mut p = read_user(params)?;
+        if !p.sq_entries.is_power_of_two() || p.sq_entries > MAX_ENTRIES || p.nr_fds > MAX_FILES {
+            return Err(LinuxError::EINVAL);
+        }
+        let files = (0..p.nr_fds as usize)
+            .map(|i| {
+                let fd = read_user((p.fds as usize + i * size_of::<i32>()) as *const i32)?;
+                api::get_file_like(fd)
+            })
+            .collect::<LinuxResult<Vec<_>>>()?;
+
+        p.cq_entries = p.sq_entries * 2;
+        p.sqes_off = HEADER_SIZE as u32;
+        p.cqes_off = p.sqes_off + p.sq_entries * size_of::<Sqe>() as u32;
+        let size = memory_addr::align_up_4k(
+            p.cqes_off as usize + p.cq_entries as usize * size_of::<Cqe>(),
+        );
+        p.ring_size = size as u32;
+        write_user(params, &p)?;
+
+        let mut aspace = ext.aspace.lock();
+        let limit = ext.rlimits.lock().get(RLIMIT_AS);
+        if !limit.allows(aspace.mapped_size() + size) {
+            return Err(LinuxError::ENOMEM);
+        }
+        let base = aspace
+            .find_free_area(
+                aspace.base(),
+                size,
+                VirtAddrRange::new(aspace.base(), aspace.end()),
+            )
+            .ok_or(LinuxError::ENOMEM)?;
+        aspace.map_alloc(
+            base,
+            size,
+            MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
+            true,
+        )?;
+        drop(aspace);
+
+        let ring = IoRing {
+            base,
+            sq_entries: p.sq_entries,
+            cq_entries: p.cq_entries,
+            files,
+            sq_head: 0,
+            cq_tail: 0,
+        };
+        let header = RingHeader {
+            sq_head: 0,
+            sq_tail: 0,
+            cq_head: 0,
+            cq_tail: 0,
+            sq_entries: p.sq_entries,
+            cq_entries: p.cq_entries,
+        };
+        write_user(ring.header(), &header)?;
+        *slot = Some(ring);
+        Ok(base.as_usize())
+    })
+}
+
+/// 执行当前任务的环中最多 `to_submit` 个提交项，返回执行的项数
+///
+/// 完成队列满时停止执行。操作都同步完成，不会等待：没有执行任何提交项且完成队列中的项数
+/// 少于 `min_complete` 时返回 `EAGAIN`，完成队列已满而无法执行时返回 `EBUSY`。任务没有环时
+/// 返回 `ENXIO`，环中的 `sq_tail` 或 `cq_head` 不合理时返回 `EINVAL`。
+pub(crate) fn sys_io_enter(to_submit: u32, min_complete: u32) -> isize {
+    syscall_body!(sys_io_enter, {
+        let curr = current();
+        let mut slot = curr.task_ext().io_ring().lock();
+        let ring = slot.as_mut().ok_or(LinuxError::ENXIO)?;
+        if min_complete > ring.cq_entries {
+            return Err(LinuxError::EINVAL);
+        }
+        let header = read_user(ring.header())?;
+        let pending = header.sq_tail.wrapping_sub(ring.sq_head);
+        let ready = ring.cq_tail.wrapping_sub(header.cq_head);
+        if pending > ring.sq_entries || ready > ring.cq_entries {
+            return Err(LinuxError::EINVAL);
+        }
+        let count = to_submit.min(pending).min(ring.cq_entries - ready);
+        let mut submitted = 0;
+        let mut fault = None;
+        while submitted < count {
+            let sqe = match read_user(ring.sqe(ring.sq_head)) {
+                Ok(sqe) => sqe,
+                Err(err) => {
+                    fault = Some(err);
+                    break;
+                }
+            };
+            let res = match ring.execute(&sqe) {
+                Ok(n) => n as i32,
+                Err(err) => -err.code(),
+            };
+            let cqe = Cqe {
+                user_data: sqe.user_data,
+                res,
+                flags: 0,
+            };
+            ring.sq_head = ring.sq_head.wrapping_add(1);
+            if let Err(err) = write_user(ring.cqe(ring.cq_tail), &cqe) {
+                fault = Some(err);
+                break;
+            }
+            ring.cq_tail = ring.cq_tail.wrapping_add(1);
+            submitted += 1;
+        }
+        // 执行过的提交项不会再执行，即使没能写入完成项
+        let sq_head = ring.base + offset_of!(RingHeader, sq_head);
+        let cq_tail = ring.base + offset_of!(RingHeader, cq_tail);
+        write_user(sq_head.as_mut_ptr_of(), &ring.sq_head)?;
+        write_user(cq_tail.as_mut_ptr_of(), &ring.cq_tail)?;
+        if submitted > 0 {
+            return Ok(submitted as usize);
+        }
+        if let Some(err) = fault {
+            return Err(err.into());
+        }
+        if to_submit > 0 && pending > 0 {
+            return Err(LinuxError::EBUSY);
+        }
+        if ready < min_complete {
+            return Err(LinuxError::EAGAIN);
+        }
+        Ok(0)
+    })
+}
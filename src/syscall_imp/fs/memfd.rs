@@ -0,0 +1,305 @@
+//! 匿名内存文件
+//!
+//! `memfd_create` 创建的文件不属于任何文件系统，数据保存在一组可共享的物理页中。
+//! `MAP_SHARED` 映射直接映射这些物理页，因此通过 fork 继承了文件描述符或映射的进程
+//! 可以借此共享内存。
+
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+
+use arceos_posix_api::{self as api, ctypes, FileLike, PollState, Pollable, StatusFlags};
+use axerrno::{LinuxError, LinuxResult};
+use axmm::SharedFrame;
+use axsync::Mutex;
+use memory_addr::PAGE_SIZE_4K;
+
+use crate::syscall_body;
+
+/// 设置 close-on-exec 标志
+const MFD_CLOEXEC: u32 = 1;
+/// 允许添加封印
+const MFD_ALLOW_SEALING: u32 = 2;
+
+/// 从文件开头计算读写位置
+pub(crate) const SEEK_SET: i32 = 0;
+/// 从当前位置计算读写位置
+pub(crate) const SEEK_CUR: i32 = 1;
+/// 从文件末尾计算读写位置
+pub(crate) const SEEK_END: i32 = 2;
+
+/// 名称的最大长度，不含结尾的 0
+const MFD_NAME_MAX_LEN: usize = 249;
+
+/// 不能再添加封印
+const F_SEAL_SEAL: u32 = 1;
+/// 不能缩小文件
+const F_SEAL_SHRINK: u32 = 2;
+/// 不能扩大文件
+const F_SEAL_GROW: u32 = 4;
+/// 不能修改文件内容
+const F_SEAL_WRITE: u32 = 8;
+/// 不能再建立可写的映射或写入，已有的可写映射不受影响
+const F_SEAL_FUTURE_WRITE: u32 = 16;
+
+const ALL_SEALS: u32 =
+    F_SEAL_SEAL | F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_WRITE | F_SEAL_FUTURE_WRITE;
+
+struct MemFdInner {
+    /// 文件内容，最后一页中超出文件大小的部分总是为 0
+    pages: Vec<Arc<SharedFrame>>,
+    size: usize,
+    /// 读写位置，由共享同一打开文件的描述符共用
+    pos: usize,
+    seals: u32,
+}
+
+impl MemFdInner {
+    /// 调整文件大小，扩大的部分填充为 0
+    fn resize(&mut self, size: usize) -> LinuxResult {
+        let pages = size.div_ceil(PAGE_SIZE_4K);
+        while self.pages.len() < pages {
+            self.pages
+                .push(Arc::new(SharedFrame::new().ok_or(LinuxError::ENOMEM)?));
+        }
+        self.pages.truncate(pages);
+        if size < self.size && size % PAGE_SIZE_4K != 0 {
+            let last = &self.pages[pages - 1];
+            let offset = size % PAGE_SIZE_4K;
+            unsafe {
+                last.as_mut_ptr()
+                    .add(offset)
+                    .write_bytes(0, PAGE_SIZE_4K - offset)
+            };
+        }
+        self.size = size;
+        Ok(())
+    }
+
+    /// 从 `offset` 处读取数据，返回读取的字节数
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(self.size.saturating_sub(offset));
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done;
+            let chunk = (PAGE_SIZE_4K - pos % PAGE_SIZE_4K).min(len - done);
+            let src = self.pages[pos / PAGE_SIZE_4K].as_mut_ptr();
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    src.add(pos % PAGE_SIZE_4K),
+                    buf[done..].as_mut_ptr(),
+                    chunk,
+                )
+            };
+            done += chunk;
+        }
+        len
+    }
+
+    /// 在 `offset` 处写入数据，必要时扩大文件
+    fn write_at(&mut self, offset: usize, data: &[u8]) -> LinuxResult<usize> {
+        if self.seals & (F_SEAL_WRITE | F_SEAL_FUTURE_WRITE) != 0 {
+            return Err(LinuxError::EPERM);
+        }
+        let end = offset.checked_add(data.len()).ok_or(LinuxError::EFBIG)?;
+        if end > self.size {
+            if self.seals & F_SEAL_GROW != 0 {
+                return Err(LinuxError::EPERM);
+            }
+            self.resize(end)?;
+        }
+        let mut done = 0;
+        while done < data.len() {
+            let pos = offset + done;
+            let chunk = (PAGE_SIZE_4K - pos % PAGE_SIZE_4K).min(data.len() - done);
+            let dst = self.pages[pos / PAGE_SIZE_4K].as_mut_ptr();
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    data[done..].as_ptr(),
+                    dst.add(pos % PAGE_SIZE_4K),
+                    chunk,
+                )
+            };
+            done += chunk;
+        }
+        Ok(data.len())
+    }
+}
+
+/// `memfd_create` 创建的匿名内存文件
+pub(crate) struct MemFd {
+    /// 创建时指定的名称，带有 `memfd:` 前缀，显示在 `/proc/[pid]/fd` 中
+    name: String,
+    inner: Mutex<MemFdInner>,
+    flags: StatusFlags,
+}
+
+impl MemFd {
+    fn new(name: String, allow_sealing: bool) -> Self {
+        Self {
+            name,
+            inner: Mutex::new(MemFdInner {
+                pages: Vec::new(),
+                size: 0,
+                pos: 0,
+                // 不允许封印时，相当于已经封印了添加封印的操作
+                seals: if allow_sealing { 0 } else { F_SEAL_SEAL },
+            }),
+            flags: StatusFlags::new(ctypes::O_RDWR),
+        }
+    }
+
+    /// 获取文件描述符对应的匿名内存文件
+    pub(crate) fn from_fd(fd: i32) -> LinuxResult<Arc<Self>> {
+        api::get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+
+    /// 修改读写位置，返回新的位置
+    pub(crate) fn seek(&self, offset: i64, whence: i32) -> LinuxResult<usize> {
+        let mut inner = self.inner.lock();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => inner.pos as i64,
+            SEEK_END => inner.size as i64,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let pos = base.checked_add(offset).ok_or(LinuxError::EINVAL)?;
+        inner.pos = usize::try_from(pos).map_err(|_| LinuxError::EINVAL)?;
+        Ok(inner.pos)
+    }
+
+    /// 将文件大小设为 `size`
+    pub(crate) fn truncate(&self, size: usize) -> LinuxResult {
+        let mut inner = self.inner.lock();
+        if (size < inner.size && inner.seals & F_SEAL_SHRINK != 0)
+            || (size > inner.size && inner.seals & F_SEAL_GROW != 0)
+        {
+            return Err(LinuxError::EPERM);
+        }
+        inner.resize(size)
+    }
+
+    /// 返回已添加的封印
+    pub(crate) fn seals(&self) -> u32 {
+        self.inner.lock().seals
+    }
+
+    /// 添加封印
+    ///
+    /// 无法区分已有的共享映射是否可写，因此只要还有共享映射，就不能添加 `F_SEAL_WRITE`。
+    pub(crate) fn add_seals(&self, seals: u32) -> LinuxResult {
+        if seals & !ALL_SEALS != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut inner = self.inner.lock();
+        if inner.seals & F_SEAL_SEAL != 0 {
+            return Err(LinuxError::EPERM);
+        }
+        if seals & F_SEAL_WRITE != 0 && inner.pages.iter().any(|page| Arc::strong_count(page) > 1) {
+            return Err(LinuxError::EBUSY);
+        }
+        inner.seals |= seals;
+        Ok(())
+    }
+
+    /// 返回从 `offset` 开始用于共享映射的物理页，`offset` 必须按页对齐
+    pub(crate) fn shared_frames(
+        &self,
+        offset: usize,
+        writable: bool,
+    ) -> LinuxResult<Arc<[Arc<SharedFrame>]>> {
+        let inner = self.inner.lock();
+        if writable && inner.seals & (F_SEAL_WRITE | F_SEAL_FUTURE_WRITE) != 0 {
+            return Err(LinuxError::EPERM);
+        }
+        let first = (offset / PAGE_SIZE_4K).min(inner.pages.len());
+        Ok(inner.pages[first..].iter().cloned().collect())
+    }
+
+    /// 从 `offset` 处读取数据，不改变读写位置，返回读取的字节数
+    pub(crate) fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.inner.lock().read_at(offset, buf)
+    }
+
+    /// 读取从 `offset` 开始的至多 `len` 字节，用于私有映射
+    pub(crate) fn read_for_mmap(&self, offset: usize, len: usize) -> Vec<u8> {
+        let inner = self.inner.lock();
+        let mut buf = vec![0u8; len.min(inner.size.saturating_sub(offset))];
+        inner.read_at(offset, &mut buf);
+        buf
+    }
+}
+
+impl FileLike for MemFd {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let mut inner = self.inner.lock();
+        let len = inner.read_at(inner.pos, buf);
+        inner.pos += len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        let mut inner = self.inner.lock();
+        let pos = if self.flags.append() {
+            inner.size
+        } else {
+            inner.pos
+        };
+        let len = inner.write_at(pos, buf)?;
+        inner.pos = pos + len;
+        Ok(len)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let inner = self.inner.lock();
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o100000 | 0o777, // S_IFREG | rwxrwxrwx
+            st_size: inner.size as _,
+            st_blksize: PAGE_SIZE_4K as _,
+            st_blocks: (inner.pages.len() * PAGE_SIZE_4K / 512) as _,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn link_target(&self) -> String {
+        format!("/{} (deleted)", self.name)
+    }
+}
+
+impl Pollable for MemFd {
+    fn poll(&self) -> PollState {
+        PollState::ALWAYS_READY
+    }
+}
+
+/// 创建匿名内存文件，返回其文件描述符
+pub(crate) fn sys_memfd_create(name: *const i8, flags: u32) -> isize {
+    syscall_body!(sys_memfd_create, {
+        if flags & !(MFD_CLOEXEC | MFD_ALLOW_SEALING) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let name = api::char_ptr_to_str(name)?;
+        if name.len() > MFD_NAME_MAX_LEN {
+            return Err(LinuxError::EINVAL);
+        }
+        let file = MemFd::new(format!("memfd:{}", name), flags & MFD_ALLOW_SEALING != 0);
+        let fd = api::add_file_like(Arc::new(file))?;
+        api::set_cloexec(fd, flags & MFD_CLOEXEC != 0);
+        Ok(fd)
+    })
+}
@@ -1,7 +1,21 @@
 mod ctl;
+mod fadvise;
+mod inotify;
 mod io;
+mod io_ring;
+mod memfd;
 mod mount;
+mod open;
+mod poll;
+mod statfs;
 
 pub(crate) use self::ctl::*;
+pub(crate) use self::fadvise::*;
+pub(crate) use self::inotify::*;
 pub(crate) use self::io::*;
+pub(crate) use self::io_ring::*;
+pub(crate) use self::memfd::*;
 pub(crate) use self::mount::*;
+pub(crate) use self::open::*;
+pub(crate) use self::poll::*;
+pub(crate) use self::statfs::*;
@@ -1,13 +1,24 @@
 use alloc::{boxed::Box, string::ToString};
-use arceos_posix_api::AT_FDCWD;
-use axerrno::AxError;
+use arceos_posix_api::{ctypes, AT_FDCWD};
+use axerrno::LinuxError;
+use axtask::{current, TaskExtRef};
+
+use super::{open_file, Cred};
+use crate::task::CAP_SYS_ADMIN;
+
+/// 挂载参数：只读挂载
+const MS_RDONLY: u64 = 1;
+/// 挂载参数：忽略文件的 set-user-ID 和 set-group-ID 位
+const MS_NOSUID: u64 = 2;
+/// 挂载参数：修改已有挂载的参数，目前只支持切换只读
+const MS_REMOUNT: u64 = 32;
 
 // 功能：挂载文件系统；
 // 输入：
 //     special: 挂载设备；
 //     dir: 挂载点；
 //     fstype: 挂载的文件系统类型；
-//     flags: 挂载参数；
+//     flags: 挂载参数，支持 MS_RDONLY、MS_NOSUID 和 MS_REMOUNT，带 MS_REMOUNT 时忽略 special 和 fstype；
 //     data: 传递给文件系统的字符串参数，可为NULL；
 // 返回值：成功返回0，失败返回-1；
 // const char *special, const char *dir, const char *fstype, unsigned long flags, const void *data;
@@ -16,19 +27,41 @@ pub(crate) fn sys_mount(
     special: *const u8,
     dir: *const u8,
     fstype: *const u8,
-    _flags: u64,
+    flags: u64,
     _data: *const u8,
 ) -> i64 {
     let result = (|| {
-        // 处理 special 路径
-        let special_path = arceos_posix_api::handle_file_path(AT_FDCWD, Some(special), false)
-            .inspect_err(|err| log::error!("mount: special: {:?}", err))?;
+        if !current().task_ext().capable(CAP_SYS_ADMIN) {
+            return Err(LinuxError::EPERM);
+        }
+        let read_only = flags & MS_RDONLY != 0;
 
-        if special_path.is_dir() {
-            log::debug!("mount: special is a directory");
-            return Err(AxError::InvalidInput);
+        if flags & MS_REMOUNT != 0 {
+            let dir_path = arceos_posix_api::handle_file_path(AT_FDCWD, Some(dir), false)
+                .inspect_err(|err| log::error!("mount: dir: {:?}", err))?;
+            axfs::remount(&dir_path, read_only)
+                .inspect_err(|err| log::error!("mount: remount: {:?}", err))?;
+            return Ok(());
         }
 
+        // 打开 special，它是保存文件系统镜像的文件，只读挂载时只需要读权限
+        let special = arceos_posix_api::char_ptr_to_str(special as _)?;
+        let open_flags = if read_only {
+            ctypes::O_RDONLY
+        } else {
+            ctypes::O_RDWR
+        };
+        let special = open_file(AT_FDCWD as _, special, open_flags, 0, &Cred::current())
+            .inspect_err(|err| log::error!("mount: special: {:?}", err))?;
+        let special_path = special
+            .into_any()
+            .downcast::<arceos_posix_api::File>()
+            .map_err(|_| {
+                log::debug!("mount: special is a directory");
+                LinuxError::EINVAL
+            })?
+            .path();
+
         // 处理目标目录路径
         let dir_path = arceos_posix_api::handle_file_path(AT_FDCWD, Some(dir), false)
             .inspect_err(|err| log::error!("mount: dir: {:?}", err))?;
@@ -36,15 +69,19 @@ pub(crate) fn sys_mount(
         // 处理文件系统类型
         let fstype_str = arceos_posix_api::char_ptr_to_str(fstype as *const i8)
             .inspect_err(|err| log::error!("mount: fstype: {:?}", err))
-            .map_err(|_| AxError::InvalidInput)?;
+            .map_err(|_| LinuxError::EINVAL)?;
         if fstype_str != "vfat" {
             log::debug!("mount: fstype is not axfs");
-            return Err(AxError::InvalidInput);
+            return Err(LinuxError::EINVAL);
         }
 
         // 执行挂载
         let dir_path_str: &'static str = Box::leak(Box::new(dir_path.to_string()));
-        axfs::mount(&special_path, dir_path_str)
+        let mount_flags = axfs::MountFlags {
+            read_only,
+            nosuid: flags & MS_NOSUID != 0,
+        };
+        axfs::mount(&special_path, dir_path_str, mount_flags)
             .inspect_err(|err| log::error!("mount: {:?}", err))?;
         Ok(())
     })();
@@ -62,13 +99,17 @@ pub(crate) fn sys_mount(
 // int ret = syscall(SYS_umount2, special, flags);
 pub(crate) fn sys_umount2(special: *const u8, _flags: i32) -> i64 {
     let result = (|| {
+        if !current().task_ext().capable(CAP_SYS_ADMIN) {
+            return Err(LinuxError::EPERM);
+        }
+
         // 处理 special 路径
         let special_path = arceos_posix_api::handle_file_path(AT_FDCWD, Some(special), false)
             .inspect_err(|err| log::error!("umount2: special: {:?}", err))?;
 
         if special_path.is_dir() {
             log::debug!("umount2: special is a directory");
-            return Err(AxError::InvalidInput);
+            return Err(LinuxError::EINVAL);
         }
 
         // 执行卸载
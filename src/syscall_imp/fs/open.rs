@@ -0,0 +1,197 @@
+//! 打开文件的公共路径
+//!
+//! `openat`、`execve` 读取程序文件和 `mount` 打开设备文件都通过 [`open_file`] 打开文件，
+//! 路径解析、符号链接、权限检查和文件创建掩码的行为因此在这些系统调用之间保持一致。
+
+use alloc::{format, string::String, sync::Arc};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use arceos_posix_api::{self as api, ctypes, FileLike, FilePath};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, TaskExtRef};
+
+use crate::task::Pid;
+
+/// 检查读权限
+pub(crate) const R_OK: u32 = 4;
+/// 检查写权限
+pub(crate) const W_OK: u32 = 2;
+/// 检查执行权限
+pub(crate) const X_OK: u32 = 1;
+
+/// `O_TMPFILE` 中除 `O_DIRECTORY` 以外的位
+const O_TMPFILE_ONLY: u32 = ctypes::O_TMPFILE & !ctypes::O_DIRECTORY;
+
+/// 下一个 `O_TMPFILE` 匿名文件的隐藏名字的编号
+static NEXT_TMPFILE: AtomicU64 = AtomicU64::new(0);
+
+/// 打开文件时使用的进程凭证
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Cred {
+    /// 检查权限时使用的用户 ID，打开文件时为有效用户 ID
+    pub uid: u32,
+    /// 文件创建掩码，创建文件时从请求的权限中去掉
+    pub umask: u32,
+}
+
+impl Cred {
+    /// 当前进程的凭证
+    pub fn current() -> Self {
+        let curr = current();
+        let ext = curr.task_ext();
+        Self {
+            uid: ext.euid(),
+            umask: ext.umask(),
+        }
+    }
+
+    /// 检查能否以 `access`（[`R_OK`]、[`W_OK`]、[`X_OK`] 的组合）访问权限为 `st_mode`
+    /// 的文件，不能时返回 `EACCES`
+    ///
+    /// 目前所有文件都属于同一用户，因此按属主的权限位检查。与 Linux 一致，root 用户可以
+    /// 读写任何文件，执行则要求文件至少有一个执行位。
+    pub fn check_access(&self, st_mode: u32, access: u32) -> LinuxResult {
+        let allowed = if self.uid == 0 {
+            let exec = if st_mode & 0o111 != 0 { X_OK } else { 0 };
+            R_OK | W_OK | exec
+        } else {
+            (st_mode >> 6) & 0o7
+        };
+        if access & !allowed != 0 {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(())
+    }
+}
+
+/// 打开 `dirfd` 下的 `path`，返回打开的文件，它还没有加入文件描述符表
+///
+/// `path` 为绝对路径时忽略 `dirfd`，为空时返回 `ENOENT`。路径中的符号链接都会展开，
+/// 带 `O_NOFOLLOW` 时最后一个组件除外，见 [`api::open_path`]。打开已有的文件时按 `cred`
+/// 检查 `flags` 所需的读写权限，创建文件时从 `mode` 中去掉 `cred` 的文件创建掩码。
+///
+/// 带 `O_TMPFILE` 时 `path` 是一个目录，在其中创建一个没有名字的文件，关闭后即被删除，
+/// 除非之前用带 `AT_EMPTY_PATH` 的 `linkat` 把它链接到目录树中；同时带 `O_EXCL` 时
+/// 不允许链接。
+///
+/// `path` 是 `/proc/[pid]/fd` 中的链接时，按 `flags` 重新打开文件描述符所指的对象，而不是
+/// 按链接的目标路径打开，因此已被删除的文件和管道也能这样打开。
+///
+/// 调用者用 [`api::add_open_file`] 把文件加入文件描述符表，它按 `O_CLOEXEC` 设置新描述符的
+/// close-on-exec 标志。
+pub(crate) fn open_file(
+    dirfd: i32,
+    path: &str,
+    flags: u32,
+    mode: u32,
+    cred: &Cred,
+) -> LinuxResult<Arc<dyn FileLike>> {
+    let path = absolute_path(dirfd, path)?;
+    let mode = mode & !cred.umask & 0o7777;
+    if flags & O_TMPFILE_ONLY != 0 {
+        return open_tmpfile(&path, flags, mode);
+    }
+    if flags & (ctypes::O_PATH | ctypes::O_NOFOLLOW) == 0 {
+        if let Some((pid, fd)) = api::resolve_symlinks(&path, false)
+            .ok()
+            .and_then(|path| axfs::fd_link(&path))
+        {
+            return reopen_fd(pid as Pid, fd, flags, cred);
+        }
+    }
+    if flags & ctypes::O_PATH == 0 {
+        check_open_access(&path, flags, cred)?;
+    }
+    api::open_path(&path, flags as _, mode)
+}
+
+/// 按 `flags` 重新打开进程 `pid` 的文件描述符 `fd` 所指的对象，得到新的打开文件描述
+fn reopen_fd(pid: Pid, fd: u32, flags: u32, cred: &Cred) -> LinuxResult<Arc<dyn FileLike>> {
+    let file = crate::task::procfs::fd_file(pid, fd)?;
+    if flags & (ctypes::O_CREAT | ctypes::O_EXCL) == ctypes::O_CREAT | ctypes::O_EXCL {
+        return Err(LinuxError::EEXIST);
+    }
+    cred.check_access(file.stat()?.st_mode, open_access(flags))?;
+    file.reopen(flags as _)
+}
+
+/// 把相对于 `dirfd` 的 `path` 转换为规范化的绝对路径，不展开其中的符号链接
+fn absolute_path(dirfd: i32, path: &str) -> LinuxResult<String> {
+    if path.is_empty() {
+        return Err(LinuxError::ENOENT);
+    }
+    // 目录可能是以 `O_PATH` 打开的，因此按它的路径而不是通过它打开文件
+    let base = if path.starts_with('/') {
+        axfs::CURRENT_DIR_PATH.lock().clone()
+    } else {
+        api::base_dir(dirfd as isize)?
+    };
+    Ok(axfs::path::canonicalize(&base, path)?)
+}
+
+/// 检查能否按 `flags` 打开 `path` 处已有的文件，文件不存在时由打开本身报告错误
+fn check_open_access(path: &str, flags: u32, cred: &Cred) -> LinuxResult {
+    let access = open_access(flags);
+    let follow = flags & ctypes::O_NOFOLLOW == 0;
+    let Ok(stat) = api::resolve_symlinks(path, follow)
+        .and_then(|path| Ok(FilePath::new(path)?))
+        .and_then(|path| api::stat_path(&path))
+    else {
+        return Ok(());
+    };
+    cred.check_access(stat.st_mode, access)
+}
+
+/// 按 `flags` 打开文件需要的权限
+fn open_access(flags: u32) -> u32 {
+    let access = match flags & 0b11 {
+        ctypes::O_RDONLY => R_OK,
+        ctypes::O_WRONLY => W_OK,
+        _ => R_OK | W_OK,
+    };
+    if flags & ctypes::O_TRUNC != 0 {
+        access | W_OK
+    } else {
+        access
+    }
+}
+
+/// 在目录 `dir` 中创建 `O_TMPFILE` 的匿名文件
+///
+/// 文件在目录中有一个隐藏的名字，它不出现在目录的列表中。
+fn open_tmpfile(dir: &str, flags: u32, mode: u32) -> LinuxResult<Arc<dyn FileLike>> {
+    // 与 Linux 一致，`O_TMPFILE` 必须包含 `O_DIRECTORY`，不能与 `O_CREAT` 同时使用，
+    // 且必须以可写方式打开
+    if flags & ctypes::O_TMPFILE != ctypes::O_TMPFILE
+        || flags & ctypes::O_CREAT != 0
+        || flags & 0b11 == ctypes::O_RDONLY
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    let dir = api::resolve_symlinks(dir, true)?;
+    if !axfs::api::metadata(&dir)?.is_dir() {
+        return Err(LinuxError::ENOTDIR);
+    }
+    let linkable = flags & ctypes::O_EXCL == 0;
+    let flags = (flags & !ctypes::O_TMPFILE) | ctypes::O_CREAT | ctypes::O_EXCL;
+    let file = loop {
+        let id = NEXT_TMPFILE.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{}/.tmpfile.{id}", dir.trim_end_matches('/'));
+        match api::open_path(&name, flags as _, mode) {
+            Err(LinuxError::EEXIST) => continue,
+            result => break result?,
+        }
+    };
+    let tmpfile = file
+        .clone()
+        .into_any()
+        .downcast::<api::File>()
+        .map_err(|_| LinuxError::EOPNOTSUPP)?;
+    tmpfile.set_unnamed(linkable);
+    Ok(file)
+}
+
+/// 设置当前进程的文件创建掩码，返回原先的掩码
+pub(crate) fn sys_umask(mask: u32) -> isize {
+    current().task_ext().set_umask(mask) as isize
+}
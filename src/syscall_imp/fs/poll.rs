@@ -0,0 +1,128 @@
+//! 等待多个文件描述符就绪：`ppoll` 和 `poll`
+
+use alloc::{sync::Arc, vec::Vec};
+use core::mem::size_of;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use arceos_posix_api::{self as api, ctypes, PollWaker};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axtask::{current, AxTaskRef, TaskExtRef};
+
+use crate::{
+    mm::{read_user, write_user},
+    syscall_body,
+    task::signal::{SigSet, WaitOutcome},
+};
+
+/// 两次检查文件描述符之间睡眠的时间
+///
+/// 管道等文件就绪状态变化时通过 [`PollTaskWaker`] 唤醒任务，其他文件只能定期检查；
+/// 睡眠期间到达的信号会立即唤醒任务。
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// 文件的就绪状态可能变化时，唤醒在 `poll` 中睡眠的任务
+struct PollTaskWaker {
+    task: AxTaskRef,
+    woken: AtomicBool,
+}
+
+impl PollWaker for PollTaskWaker {
+    fn wake(&self) {
+        self.woken.store(true, Ordering::Release);
+        self.task.task_ext().signal_wq.notify_all(false);
+    }
+}
+
+/// 等待 `fds` 中的某个文件描述符就绪，返回有事件的项数
+///
+/// `timeout` 为 `None` 时一直等待，超时返回 0；等待期间有信号被递送时返回 EINTR。
+/// 各项的 `revents` 写回用户内存。
+fn do_poll(fds: *mut ctypes::pollfd, nfds: usize, timeout: Option<Duration>) -> LinuxResult<isize> {
+    if nfds > api::fd_limit() {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut pollfds = (0..nfds)
+        .map(|i| read_user(fds.wrapping_add(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let curr = current();
+    let waker = Arc::new(PollTaskWaker {
+        task: curr.as_task_ref().clone(),
+        woken: AtomicBool::new(false),
+    });
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+    let ready = loop {
+        // 先清除唤醒标志再注册并检查，检查之后的变化会让下面的睡眠立即结束
+        waker.woken.store(false, Ordering::Release);
+        let ready = api::poll_fds(&mut pollfds, Some(&(waker.clone() as Arc<dyn PollWaker>)));
+        if ready > 0 {
+            break ready;
+        }
+        let now = monotonic_time();
+        let wait = match deadline {
+            Some(deadline) if now >= deadline => break 0,
+            Some(deadline) => POLL_INTERVAL.min(deadline - now),
+            None => POLL_INTERVAL,
+        };
+        // 被唤醒时先重新检查文件，到达的信号留到下一次睡眠前处理
+        let woken = |_: &_| waker.woken.load(Ordering::Acquire);
+        if curr.task_ext().wait_signal(Some(wait), woken) == WaitOutcome::Interrupted {
+            return Err(LinuxError::EINTR);
+        }
+    };
+
+    for (i, pollfd) in pollfds.iter().enumerate() {
+        write_user(fds.wrapping_add(i), pollfd)?;
+    }
+    Ok(ready as isize)
+}
+
+/// 等待 `fds` 中的某个文件描述符就绪，等待期间临时将信号掩码替换为 `sigmask`
+///
+/// `timeout` 是相对时间，为空时一直等待。被信号打断时，原先的掩码在信号处理函数返回后
+/// 恢复，与 `rt_sigsuspend` 相同。
+pub(crate) fn sys_ppoll(
+    fds: *mut ctypes::pollfd,
+    nfds: usize,
+    timeout: *const ctypes::timespec,
+    sigmask: *const SigSet,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_ppoll, {
+        let timeout = if timeout.is_null() {
+            None
+        } else {
+            let ts = read_user(timeout)?;
+            if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+        };
+        if sigmask.is_null() {
+            return do_poll(fds, nfds, timeout);
+        }
+        if sigsetsize != size_of::<SigSet>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let mask = read_user(sigmask)?;
+        let curr = current();
+        let ext = curr.task_ext();
+        ext.signal.lock().suspend_mask(mask);
+        let res = do_poll(fds, nfds, timeout);
+        if !matches!(res, Err(LinuxError::EINTR)) {
+            ext.signal.lock().restore_mask();
+        }
+        res
+    })
+}
+
+/// 等待 `fds` 中的某个文件描述符就绪，`timeout` 以毫秒为单位，为负数时一直等待
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_poll(fds: *mut ctypes::pollfd, nfds: usize, timeout: i32) -> isize {
+    syscall_body!(sys_poll, {
+        let timeout = (timeout >= 0).then(|| Duration::from_millis(timeout as u64));
+        do_poll(fds, nfds, timeout)
+    })
+}
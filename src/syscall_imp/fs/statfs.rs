@@ -0,0 +1,103 @@
+//! 文件系统的统计信息：`statfs` 和 `fstatfs`
+
+use arceos_posix_api::{self as api, AT_FDCWD};
+use axerrno::{LinuxError, LinuxResult};
+use axfs::MountInfo;
+
+use crate::{mm::write_user, syscall_body};
+
+/// 文件系统以只读方式挂载
+const ST_RDONLY: i64 = 1;
+/// 文件系统忽略 set-user-ID 和 set-group-ID 位
+const ST_NOSUID: i64 = 2;
+
+/// 没有存储空间的文件系统报告的块大小
+const DEFAULT_BLOCK_SIZE: i64 = 4096;
+/// 文件名的最大长度
+const NAME_MAX: i64 = 255;
+
+/// `statfs` 和 `fstatfs` 返回的结构体，各个 64 位架构的布局相同
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct StatFs {
+    f_type: i64,
+    f_bsize: i64,
+    f_blocks: u64,
+    f_bfree: u64,
+    f_bavail: u64,
+    f_files: u64,
+    f_ffree: u64,
+    f_fsid: [i32; 2],
+    f_namelen: i64,
+    f_frsize: i64,
+    f_flags: i64,
+    f_spare: [i64; 4],
+}
+
+impl From<&MountInfo> for StatFs {
+    fn from(info: &MountInfo) -> Self {
+        // 与 Linux 中同类文件系统的魔数一致
+        let f_type = match info.fs_type {
+            "vfat" => 0x4d44,
+            "tmpfs" | "devtmpfs" => 0x0102_1994,
+            "proc" => 0x9fa0,
+            "sysfs" => 0x6265_6572,
+            _ => 0,
+        };
+        let mut f_flags = 0;
+        if info.read_only {
+            f_flags |= ST_RDONLY;
+        }
+        if info.nosuid {
+            f_flags |= ST_NOSUID;
+        }
+        let (bsize, blocks, free) = info.space.map_or((DEFAULT_BLOCK_SIZE, 0, 0), |space| {
+            (space.block_size as i64, space.blocks, space.free_blocks)
+        });
+        Self {
+            f_type,
+            f_bsize: bsize,
+            f_blocks: blocks,
+            f_bfree: free,
+            f_bavail: free,
+            f_namelen: NAME_MAX,
+            f_frsize: bsize,
+            f_flags,
+            ..Default::default()
+        }
+    }
+}
+
+/// 把 `info` 描述的文件系统的统计信息写入 `buf`
+fn write_statfs(buf: *mut StatFs, info: &MountInfo) -> LinuxResult<isize> {
+    write_user(buf, &StatFs::from(info)).map_err(|_| LinuxError::EFAULT)?;
+    Ok(0)
+}
+
+/// 获取 `path` 所在文件系统的统计信息
+///
+/// `f_flags` 按挂载参数报告 `ST_RDONLY` 和 `ST_NOSUID`。挂载点的读写计数等扩展信息
+/// 见 `/proc/mounts`。
+pub(crate) fn sys_statfs(path: *const u8, buf: *mut u8) -> isize {
+    syscall_body!(sys_statfs, {
+        let path = api::handle_file_path(AT_FDCWD, Some(path), false)?;
+        if !path.exists() {
+            return Err(LinuxError::ENOENT);
+        }
+        write_statfs(buf.cast(), &axfs::mount_info(&path)?)
+    })
+}
+
+/// 获取 `fd` 打开的文件所在文件系统的统计信息，见 [`sys_statfs`]
+///
+/// 只支持文件系统中的文件和目录，其他文件描述符返回 `EINVAL`。
+pub(crate) fn sys_fstatfs(fd: i32, buf: *mut u8) -> isize {
+    syscall_body!(sys_fstatfs, {
+        let path = match api::File::from_fd(fd) {
+            Ok(file) => file.path(),
+            Err(LinuxError::EINVAL) => api::Directory::from_fd(fd)?.path().into(),
+            Err(err) => return Err(err),
+        };
+        write_statfs(buf.cast(), &axfs::mount_info(&path)?)
+    })
+}
@@ -0,0 +1,180 @@
+//! 进程间通信：System V 消息队列和信号量，以及 POSIX 消息队列
+//!
+//! System V IPC 对象是全局的，不属于任何进程，创建者退出后仍然存在，直到被 `IPC_RMID` 删除。
+//! 每种对象有自己的 [`IpcTable`]，按标识符或创建时指定的键查找。
+//!
+//! 进程还没有用户凭证，对象的属主总是 root，也不检查访问权限。
+
+mod mqueue;
+mod msg;
+mod sem;
+
+pub(crate) use self::{mqueue::*, msg::*, sem::*};
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+use arceos_posix_api::WaitQueue;
+use axerrno::{LinuxError, LinuxResult};
+use axsync::{Mutex, MutexGuard};
+
+/// 总是创建新的对象，而不是按键查找
+const IPC_PRIVATE: i32 = 0;
+/// 键不存在时创建对象
+const IPC_CREAT: i32 = 0o1000;
+/// 与 `IPC_CREAT` 一起使用时，键已存在则失败
+const IPC_EXCL: i32 = 0o2000;
+/// 操作需要等待时立即失败
+const IPC_NOWAIT: i32 = 0o4000;
+
+/// 删除对象
+const IPC_RMID: i32 = 0;
+/// 修改对象的属主、权限等属性
+const IPC_SET: i32 = 1;
+/// 读取对象的状态
+const IPC_STAT: i32 = 2;
+/// 用户程序在命令中加入的标志，表示使用 64 位的结构体，这里总是如此
+const IPC_64: i32 = 0x100;
+
+/// IPC 对象的属主和权限，与 Linux 的 `struct ipc64_perm` 布局一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct IpcPerm {
+    key: i32,
+    uid: u32,
+    gid: u32,
+    cuid: u32,
+    cgid: u32,
+    mode: u32,
+    seq: u16,
+    _pad: u16,
+    _unused: [u64; 2],
+}
+
+impl IpcPerm {
+    /// 创建时的属性，`flags` 的低 9 位是访问权限
+    fn new(key: i32, flags: i32) -> Self {
+        Self {
+            key,
+            mode: flags as u32 & 0o777,
+            ..Default::default()
+        }
+    }
+
+    /// 按 `IPC_SET` 修改属主和访问权限
+    fn set(&mut self, new: &IpcPerm) {
+        self.uid = new.uid;
+        self.gid = new.gid;
+        self.mode = (self.mode & !0o777) | (new.mode & 0o777);
+    }
+}
+
+/// 一个 System V IPC 对象的状态，以及等待它变化的任务
+struct IpcObject<T> {
+    state: Mutex<T>,
+    /// 状态改变或对象被删除后唤醒，等待者重新检查自己的条件
+    wq: WaitQueue,
+}
+
+impl<T> IpcObject<T> {
+    fn new(state: T) -> Self {
+        Self {
+            state: Mutex::new(state),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.state.lock()
+    }
+}
+
+/// 同一种 IPC 对象的全局表
+struct IpcTable<T> {
+    /// 标识符到对象及其键的映射
+    objects: BTreeMap<i32, (i32, Arc<T>)>,
+    /// 键到标识符的映射，不含 `IPC_PRIVATE` 创建的对象
+    keys: BTreeMap<i32, i32>,
+    next_id: i32,
+    /// 对象数量的上限
+    max: usize,
+}
+
+impl<T> IpcTable<T> {
+    const fn new(max: usize) -> Self {
+        Self {
+            objects: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            next_id: 0,
+            max,
+        }
+    }
+
+    /// 按 `*get` 系统调用的规则查找或创建键为 `key` 的对象，返回其标识符
+    ///
+    /// 已存在的对象由 `check` 检查是否符合要求，新对象由 `create` 创建。
+    fn get_or_create(
+        &mut self,
+        key: i32,
+        flags: i32,
+        check: impl FnOnce(&T) -> LinuxResult,
+        create: impl FnOnce() -> LinuxResult<T>,
+    ) -> LinuxResult<i32> {
+        if key != IPC_PRIVATE {
+            if let Some(&id) = self.keys.get(&key) {
+                if flags & IPC_CREAT != 0 && flags & IPC_EXCL != 0 {
+                    return Err(LinuxError::EEXIST);
+                }
+                check(&self.objects[&id].1)?;
+                return Ok(id);
+            }
+            if flags & IPC_CREAT == 0 {
+                return Err(LinuxError::ENOENT);
+            }
+        }
+        if self.objects.len() >= self.max {
+            return Err(LinuxError::ENOSPC);
+        }
+        // 标识符不立即重用，已删除对象的标识符不会指向新的对象
+        let mut id = self.next_id;
+        while self.objects.contains_key(&id) {
+            id = id.checked_add(1).unwrap_or(0);
+        }
+        let object = Arc::new(create()?);
+        self.next_id = id.checked_add(1).unwrap_or(0);
+        self.objects.insert(id, (key, object));
+        if key != IPC_PRIVATE {
+            self.keys.insert(key, id);
+        }
+        Ok(id)
+    }
+
+    /// 返回标识符为 `id` 的对象
+    fn get(&self, id: i32) -> LinuxResult<Arc<T>> {
+        self.objects
+            .get(&id)
+            .map(|(_, object)| object.clone())
+            .ok_or(LinuxError::EINVAL)
+    }
+
+    /// 返回表中的所有对象
+    fn all(&self) -> Vec<Arc<T>> {
+        self.objects
+            .values()
+            .map(|(_, object)| object.clone())
+            .collect()
+    }
+
+    /// 从表中删除标识符为 `id` 的对象并返回它
+    fn remove(&mut self, id: i32) -> LinuxResult<Arc<T>> {
+        let (key, object) = self.objects.remove(&id).ok_or(LinuxError::EINVAL)?;
+        if key != IPC_PRIVATE {
+            self.keys.remove(&key);
+        }
+        Ok(object)
+    }
+}
+
+/// 当前的实际时间，以秒为单位，用于对象状态中的各个时间
+fn now_secs() -> i64 {
+    arceos_posix_api::realtime().as_secs() as i64
+}
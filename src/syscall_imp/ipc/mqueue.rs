@@ -0,0 +1,375 @@
+//! POSIX 消息队列
+//!
+//! 队列按名称登记在全局的命名空间中，`mq_open` 返回的是普通的文件描述符，可以被
+//! `close`、`dup` 和 `fcntl` 操作。`mq_unlink` 只删除名称，已经打开的描述符仍可使用
+//! 队列，直到最后一个描述符被关闭。
+//!
+//! 消息按优先级从高到低接收，同一优先级的消息先进先出。
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::time::Duration;
+
+use arceos_posix_api::{
+    self as api, ctypes, FileLike, PollState, Pollable, StatusFlags, WaitQueue,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::{current, TaskExtRef};
+use memory_addr::VirtAddr;
+
+use crate::{
+    mm::{copy_from_user, copy_to_user, read_user, write_user},
+    syscall_body,
+    task::{signal::block_interruptible, CAP_SYS_RESOURCE},
+};
+
+/// 名称的最大长度
+const NAME_MAX: usize = 255;
+/// 未指定属性时队列的容量和单条消息的最大长度，也是没有 `CAP_SYS_RESOURCE` 时的上限
+const DFLT_MAXMSG: i64 = 10;
+const DFLT_MSGSIZE: i64 = 8192;
+/// 有 `CAP_SYS_RESOURCE` 时队列容量和消息长度的上限
+const HARD_MAXMSG: i64 = 65536;
+const HARD_MSGSIZE: i64 = 16 * 1024 * 1024;
+/// 消息的优先级必须小于它
+const MQ_PRIO_MAX: u32 = 32768;
+
+/// 命名的消息队列，`mq_unlink` 后从中删除
+static NAMES: Mutex<BTreeMap<String, Arc<MessageQueue>>> = Mutex::new(BTreeMap::new());
+
+/// 消息队列的属性，与 Linux 的 `struct mq_attr` 布局一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct MqAttr {
+    mq_flags: i64,
+    mq_maxmsg: i64,
+    mq_msgsize: i64,
+    mq_curmsgs: i64,
+    _reserved: [i64; 4],
+}
+
+struct MessageQueue {
+    /// 队列的容量
+    maxmsg: usize,
+    /// 单条消息的最大长度
+    msgsize: usize,
+    /// 访问权限，创建时指定
+    mode: u32,
+    /// 以优先级为键，每个优先级的消息按发送顺序排列
+    messages: Mutex<BTreeMap<u32, VecDeque<Vec<u8>>>>,
+    /// 发送或接收消息后唤醒，等待空间的发送者和等待消息的接收者都在此等待
+    wq: WaitQueue,
+}
+
+impl MessageQueue {
+    fn len(&self) -> usize {
+        self.messages.lock().values().map(VecDeque::len).sum()
+    }
+}
+
+/// `mq_open` 打开的消息队列描述符
+pub(crate) struct MqFd {
+    queue: Arc<MessageQueue>,
+    flags: StatusFlags,
+}
+
+impl MqFd {
+    /// 获取文件描述符对应的消息队列，`fd` 不是消息队列时返回 EBADF
+    fn from_fd(fd: i32) -> LinuxResult<Arc<Self>> {
+        api::get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EBADF)
+    }
+
+    /// 当前的属性，`mq_flags` 中只有 `O_NONBLOCK`
+    fn attr(&self) -> MqAttr {
+        MqAttr {
+            mq_flags: (self.flags.get() & ctypes::O_NONBLOCK) as _,
+            mq_maxmsg: self.queue.maxmsg as _,
+            mq_msgsize: self.queue.msgsize as _,
+            mq_curmsgs: self.queue.len() as _,
+            ..Default::default()
+        }
+    }
+}
+
+impl FileLike for MqFd {
+    /// 消息只能通过 `mq_timedreceive` 接收
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    /// 消息只能通过 `mq_timedsend` 发送
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o100000 | self.queue.mode, // S_IFREG
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn status_flags(&self) -> &StatusFlags {
+        &self.flags
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn link_target(&self) -> String {
+        "anon_inode:[mqueue]".into()
+    }
+}
+
+impl Pollable for MqFd {
+    /// 有消息时可读，未满时可写
+    fn poll(&self) -> PollState {
+        let len = self.queue.len();
+        PollState {
+            readable: len > 0,
+            writable: len < self.queue.maxmsg,
+            ..Default::default()
+        }
+    }
+}
+
+/// 从用户空间读取队列名称，libc 已经去掉了开头的 `/`
+fn read_name(name: *const i8) -> LinuxResult<String> {
+    let name = api::char_ptr_to_str(name)?;
+    if name.is_empty() {
+        return Err(LinuxError::ENOENT);
+    }
+    if name.len() > NAME_MAX {
+        return Err(LinuxError::ENAMETOOLONG);
+    }
+    if name.contains('/') {
+        return Err(LinuxError::EACCES);
+    }
+    Ok(name.into())
+}
+
+/// 按 `attr` 创建新的队列，`attr` 为空时使用默认属性
+fn create(mode: u32, attr: *const MqAttr) -> LinuxResult<MessageQueue> {
+    let (maxmsg, msgsize) = if attr.is_null() {
+        (DFLT_MAXMSG, DFLT_MSGSIZE)
+    } else {
+        let attr = read_user(attr)?;
+        if attr.mq_maxmsg <= 0
+            || attr.mq_msgsize <= 0
+            || attr.mq_maxmsg > HARD_MAXMSG
+            || attr.mq_msgsize > HARD_MSGSIZE
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        if (attr.mq_maxmsg > DFLT_MAXMSG || attr.mq_msgsize > DFLT_MSGSIZE)
+            && !current().task_ext().capable(CAP_SYS_RESOURCE)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        (attr.mq_maxmsg, attr.mq_msgsize)
+    };
+    Ok(MessageQueue {
+        maxmsg: maxmsg as usize,
+        msgsize: msgsize as usize,
+        mode: mode & 0o777,
+        messages: Mutex::new(BTreeMap::new()),
+        wq: WaitQueue::new(),
+    })
+}
+
+/// 读取绝对时间（`CLOCK_REALTIME`）表示的超时时刻，`timeout` 为空时一直等待
+fn read_deadline(timeout: *const ctypes::timespec) -> LinuxResult<Option<Duration>> {
+    if timeout.is_null() {
+        return Ok(None);
+    }
+    let ts = read_user(timeout)?;
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)))
+}
+
+/// 将 [`read_deadline`] 得到的超时时刻换算为单调时钟的时间，作为等待时醒来的时刻
+fn wake_time(deadline: Option<Duration>) -> Option<Duration> {
+    deadline
+        .map(|deadline| axhal::time::monotonic_time() + deadline.saturating_sub(api::realtime()))
+}
+
+/// 打开名为 `name` 的消息队列，带 `O_CREAT` 时按 `mode` 和 `attr` 创建它，返回文件描述符
+pub(crate) fn sys_mq_open(name: *const i8, oflag: i32, mode: u32, attr: *const MqAttr) -> isize {
+    syscall_body!(sys_mq_open, {
+        let name = read_name(name)?;
+        let oflag = oflag as u32;
+        if oflag & 0b11 == 0b11 {
+            return Err(LinuxError::EINVAL);
+        }
+        let queue = {
+            let mut names = NAMES.lock();
+            match names.get(&name) {
+                Some(_) if oflag & ctypes::O_CREAT != 0 && oflag & ctypes::O_EXCL != 0 => {
+                    return Err(LinuxError::EEXIST);
+                }
+                Some(queue) => queue.clone(),
+                None if oflag & ctypes::O_CREAT != 0 => {
+                    let queue = Arc::new(create(mode, attr)?);
+                    names.insert(name, queue.clone());
+                    queue
+                }
+                None => return Err(LinuxError::ENOENT),
+            }
+        };
+        let file = MqFd {
+            queue,
+            flags: StatusFlags::new(oflag & (0b11 | ctypes::O_NONBLOCK)),
+        };
+        let fd = api::add_file_like(Arc::new(file))?;
+        api::set_cloexec(fd, oflag & ctypes::O_CLOEXEC != 0);
+        Ok(fd)
+    })
+}
+
+/// 删除名为 `name` 的消息队列的名称，已打开的描述符不受影响
+pub(crate) fn sys_mq_unlink(name: *const i8) -> isize {
+    syscall_body!(sys_mq_unlink, {
+        let name = read_name(name)?;
+        NAMES.lock().remove(&name).ok_or(LinuxError::ENOENT)?;
+        Ok(0)
+    })
+}
+
+/// 以优先级 `msg_prio` 发送 `msg_ptr` 处长为 `msg_len` 字节的消息
+///
+/// 队列已满时等待，非阻塞的描述符返回 EAGAIN，到 `abs_timeout` 仍未发送时返回 ETIMEDOUT。
+pub(crate) fn sys_mq_timedsend(
+    mqdes: i32,
+    msg_ptr: *const u8,
+    msg_len: usize,
+    msg_prio: u32,
+    abs_timeout: *const ctypes::timespec,
+) -> isize {
+    syscall_body!(sys_mq_timedsend, {
+        if msg_prio >= MQ_PRIO_MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        let deadline = read_deadline(abs_timeout)?;
+        let mq = MqFd::from_fd(mqdes)?;
+        if !mq.flags.writable() {
+            return Err(LinuxError::EBADF);
+        }
+        if msg_len > mq.queue.msgsize {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        let mut data = vec![0; msg_len];
+        copy_from_user(VirtAddr::from_ptr_of(msg_ptr), &mut data)?;
+        let mut data = Some(data);
+        block_interruptible(&mq.queue.wq, wake_time(deadline), || {
+            let mut messages = mq.queue.messages.lock();
+            if messages.values().map(VecDeque::len).sum::<usize>() < mq.queue.maxmsg {
+                messages
+                    .entry(msg_prio)
+                    .or_default()
+                    .push_back(data.take().unwrap());
+                drop(messages);
+                mq.queue.wq.notify_all();
+                return Some(Ok(0));
+            }
+            if mq.flags.nonblocking() {
+                return Some(Err(LinuxError::EAGAIN));
+            }
+            if deadline.is_some_and(|deadline| api::realtime() >= deadline) {
+                return Some(Err(LinuxError::ETIMEDOUT));
+            }
+            None
+        })?
+    })
+}
+
+/// 接收优先级最高的消息中最早发送的一条到 `msg_ptr`，返回其长度，`msg_prio` 不为空时写入其优先级
+///
+/// `msg_len` 不能小于队列中消息的最大长度。队列为空时等待，非阻塞的描述符返回 EAGAIN，
+/// 到 `abs_timeout` 仍未收到时返回 ETIMEDOUT。
+pub(crate) fn sys_mq_timedreceive(
+    mqdes: i32,
+    msg_ptr: *mut u8,
+    msg_len: usize,
+    msg_prio: *mut u32,
+    abs_timeout: *const ctypes::timespec,
+) -> isize {
+    syscall_body!(sys_mq_timedreceive, {
+        let deadline = read_deadline(abs_timeout)?;
+        let mq = MqFd::from_fd(mqdes)?;
+        if !mq.flags.readable() {
+            return Err(LinuxError::EBADF);
+        }
+        if msg_len < mq.queue.msgsize {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        let (prio, data) = block_interruptible(&mq.queue.wq, wake_time(deadline), || {
+            let mut messages = mq.queue.messages.lock();
+            if let Some(mut entry) = messages.last_entry() {
+                let data = entry.get_mut().pop_front().unwrap();
+                let prio = *entry.key();
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+                drop(messages);
+                mq.queue.wq.notify_all();
+                return Some(Ok((prio, data)));
+            }
+            if mq.flags.nonblocking() {
+                return Some(Err(LinuxError::EAGAIN));
+            }
+            if deadline.is_some_and(|deadline| api::realtime() >= deadline) {
+                return Some(Err(LinuxError::ETIMEDOUT));
+            }
+            None
+        })??;
+        copy_to_user(VirtAddr::from_ptr_of(msg_ptr), &data)?;
+        if !msg_prio.is_null() {
+            write_user(msg_prio, &prio)?;
+        }
+        Ok(data.len())
+    })
+}
+
+/// 读取消息队列的属性到 `omqstat`，并按 `mqstat` 修改描述符的 `O_NONBLOCK` 标志
+///
+/// 队列的容量和消息长度在创建后不能修改，`mqstat` 中的这些字段被忽略。
+pub(crate) fn sys_mq_getsetattr(mqdes: i32, mqstat: *const MqAttr, omqstat: *mut MqAttr) -> isize {
+    syscall_body!(sys_mq_getsetattr, {
+        let new = if mqstat.is_null() {
+            None
+        } else {
+            let new = read_user(mqstat)?;
+            if new.mq_flags & !(ctypes::O_NONBLOCK as i64) != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(new)
+        };
+        let mq = MqFd::from_fd(mqdes)?;
+        if !omqstat.is_null() {
+            write_user(omqstat, &mq.attr())?;
+        }
+        if let Some(new) = new {
+            let flags = mq.flags.get() & !ctypes::O_NONBLOCK;
+            mq.flags.set(flags | new.mq_flags as u32);
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,256 @@
+//! System V 消息队列
+//!
+//! 队列满时 `msgsnd` 阻塞，没有匹配的消息时 `msgrcv` 阻塞，两者都可被信号打断。
+//! 队列被删除后，阻塞在它上面的发送者和接收者都返回 EIDRM。
+
+use alloc::{collections::VecDeque, vec, vec::Vec};
+
+use axerrno::LinuxError;
+use axsync::Mutex;
+use axtask::{current, TaskExtRef};
+use memory_addr::VirtAddr;
+
+use super::{
+    now_secs, IpcObject, IpcPerm, IpcTable, IPC_64, IPC_NOWAIT, IPC_RMID, IPC_SET, IPC_STAT,
+};
+use crate::{
+    mm::{copy_from_user, copy_to_user, read_user, write_user},
+    syscall_body,
+    task::{signal::block_interruptible, CAP_SYS_RESOURCE},
+};
+
+/// 单条消息的最大长度
+const MSGMAX: usize = 8192;
+/// 新队列的容量（字节数），也是队列中消息数量的上限
+const MSGMNB: usize = 16384;
+/// 消息队列数量的上限
+const MSGMNI: usize = 32000;
+
+/// 消息比缓冲区长时截断，而不是失败
+const MSG_NOERROR: i32 = 0o10000;
+/// 类型为正数时，接收第一条类型与之不同的消息
+const MSG_EXCEPT: i32 = 0o20000;
+
+static QUEUES: Mutex<IpcTable<IpcObject<MsgQueue>>> = Mutex::new(IpcTable::new(MSGMNI));
+
+/// 消息队列的状态，与 Linux 的 `struct msqid64_ds` 布局一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct MsqidDs {
+    msg_perm: IpcPerm,
+    msg_stime: i64,
+    msg_rtime: i64,
+    msg_ctime: i64,
+    msg_cbytes: u64,
+    msg_qnum: u64,
+    msg_qbytes: u64,
+    msg_lspid: i32,
+    msg_lrpid: i32,
+    _unused: [u64; 2],
+}
+
+struct Message {
+    mtype: i64,
+    data: Vec<u8>,
+}
+
+struct MsgQueue {
+    perm: IpcPerm,
+    messages: VecDeque<Message>,
+    /// 队列中消息的总字节数
+    bytes: usize,
+    /// 队列的容量
+    qbytes: usize,
+    /// 最近一次发送、接收和修改属性的时间
+    stime: i64,
+    rtime: i64,
+    ctime: i64,
+    /// 最近一次发送和接收消息的进程
+    lspid: i32,
+    lrpid: i32,
+    /// 队列已被删除
+    removed: bool,
+}
+
+impl MsgQueue {
+    fn new(key: i32, flags: i32) -> Self {
+        Self {
+            perm: IpcPerm::new(key, flags),
+            messages: VecDeque::new(),
+            bytes: 0,
+            qbytes: MSGMNB,
+            stime: 0,
+            rtime: 0,
+            ctime: now_secs(),
+            lspid: 0,
+            lrpid: 0,
+            removed: false,
+        }
+    }
+
+    /// 按 `msgrcv` 的规则返回第一条类型匹配 `msgtyp` 的消息的位置
+    ///
+    /// `msgtyp` 为 0 时匹配任意消息；为正数时匹配该类型，`except` 时匹配其他类型；
+    /// 为负数时匹配类型不超过其绝对值的消息中类型最小的。
+    fn find(&self, msgtyp: i64, except: bool) -> Option<usize> {
+        let mut messages = self.messages.iter().enumerate();
+        match msgtyp {
+            0 => messages.next(),
+            1.. => messages.find(|(_, msg)| (msg.mtype == msgtyp) != except),
+            _ => messages
+                .filter(|(_, msg)| msg.mtype.unsigned_abs() <= msgtyp.unsigned_abs())
+                .min_by_key(|(_, msg)| msg.mtype),
+        }
+        .map(|(index, _)| index)
+    }
+
+    fn stat(&self) -> MsqidDs {
+        MsqidDs {
+            msg_perm: self.perm,
+            msg_stime: self.stime,
+            msg_rtime: self.rtime,
+            msg_ctime: self.ctime,
+            msg_cbytes: self.bytes as _,
+            msg_qnum: self.messages.len() as _,
+            msg_qbytes: self.qbytes as _,
+            msg_lspid: self.lspid,
+            msg_lrpid: self.lrpid,
+            ..Default::default()
+        }
+    }
+}
+
+/// 返回键为 `key` 的消息队列的标识符，必要时按 `msgflg` 创建它
+pub(crate) fn sys_msgget(key: i32, msgflg: i32) -> isize {
+    syscall_body!(sys_msgget, {
+        QUEUES.lock().get_or_create(
+            key,
+            msgflg,
+            |_| Ok(()),
+            || Ok(IpcObject::new(MsgQueue::new(key, msgflg))),
+        )
+    })
+}
+
+/// 向队列 `msqid` 发送 `msgp` 处类型为 `mtype`、正文长 `msgsz` 字节的消息
+///
+/// 消息的类型必须为正数。队列中的字节数或消息数将超过容量时等待，带 `IPC_NOWAIT` 时返回 EAGAIN。
+pub(crate) fn sys_msgsnd(msqid: i32, msgp: *const i64, msgsz: usize, msgflg: i32) -> isize {
+    syscall_body!(sys_msgsnd, {
+        if msgsz > MSGMAX {
+            return Err(LinuxError::EINVAL);
+        }
+        let mtype = read_user(msgp)?;
+        if mtype < 1 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut data = vec![0; msgsz];
+        copy_from_user(VirtAddr::from_ptr_of(msgp.wrapping_add(1)), &mut data)?;
+        let queue = QUEUES.lock().get(msqid)?;
+        let pid = current().task_ext().proc_id as i32;
+        let mut message = Some(Message { mtype, data });
+        let sent = block_interruptible(&queue.wq, None, || {
+            let mut queue = queue.lock();
+            if queue.removed {
+                return Some(Err(LinuxError::EIDRM));
+            }
+            if queue.bytes + msgsz <= queue.qbytes && queue.messages.len() < queue.qbytes {
+                queue.messages.push_back(message.take().unwrap());
+                queue.bytes += msgsz;
+                queue.lspid = pid;
+                queue.stime = now_secs();
+                return Some(Ok(0));
+            }
+            (msgflg & IPC_NOWAIT != 0).then_some(Err(LinuxError::EAGAIN))
+        })??;
+        // 唤醒等待消息的接收者
+        queue.wq.notify_all();
+        Ok(sent)
+    })
+}
+
+/// 从队列 `msqid` 接收一条类型匹配 `msgtyp` 的消息到 `msgp`，返回正文的字节数
+///
+/// 没有匹配的消息时等待，带 `IPC_NOWAIT` 时返回 ENOMSG。正文长于 `msgsz` 时返回 E2BIG，
+/// 消息留在队列中；带 `MSG_NOERROR` 时截断正文并删除消息。
+pub(crate) fn sys_msgrcv(
+    msqid: i32,
+    msgp: *mut i64,
+    msgsz: usize,
+    msgtyp: i64,
+    msgflg: i32,
+) -> isize {
+    syscall_body!(sys_msgrcv, {
+        if msgsz > isize::MAX as usize {
+            return Err(LinuxError::EINVAL);
+        }
+        let queue = QUEUES.lock().get(msqid)?;
+        let pid = current().task_ext().proc_id as i32;
+        let message = block_interruptible(&queue.wq, None, || {
+            let mut queue = queue.lock();
+            if queue.removed {
+                return Some(Err(LinuxError::EIDRM));
+            }
+            let Some(index) = queue.find(msgtyp, msgflg & MSG_EXCEPT != 0) else {
+                return (msgflg & IPC_NOWAIT != 0).then_some(Err(LinuxError::ENOMSG));
+            };
+            if queue.messages[index].data.len() > msgsz && msgflg & MSG_NOERROR == 0 {
+                return Some(Err(LinuxError::E2BIG));
+            }
+            let message = queue.messages.remove(index).unwrap();
+            queue.bytes -= message.data.len();
+            queue.lrpid = pid;
+            queue.rtime = now_secs();
+            Some(Ok(message))
+        })??;
+        // 唤醒等待空间的发送者
+        queue.wq.notify_all();
+        let len = message.data.len().min(msgsz);
+        write_user(msgp, &message.mtype)?;
+        copy_to_user(
+            VirtAddr::from_ptr_of(msgp.wrapping_add(1)),
+            &message.data[..len],
+        )?;
+        Ok(len)
+    })
+}
+
+/// 对队列 `msqid` 执行 `cmd`：`IPC_STAT` 读取状态，`IPC_SET` 修改属主、权限和容量，
+/// `IPC_RMID` 删除队列并唤醒所有等待者
+///
+/// 将容量设为超过 `MSGMNB` 需要 `CAP_SYS_RESOURCE`。
+pub(crate) fn sys_msgctl(msqid: i32, cmd: i32, buf: *mut MsqidDs) -> isize {
+    syscall_body!(sys_msgctl, {
+        match cmd & !IPC_64 {
+            IPC_STAT => {
+                let stat = QUEUES.lock().get(msqid)?.lock().stat();
+                write_user(buf, &stat)?;
+            }
+            IPC_SET => {
+                let new = read_user(buf)?;
+                let qbytes = usize::try_from(new.msg_qbytes).map_err(|_| LinuxError::EINVAL)?;
+                let queue = QUEUES.lock().get(msqid)?;
+                let mut state = queue.lock();
+                if qbytes > MSGMNB
+                    && qbytes > state.qbytes
+                    && !current().task_ext().capable(CAP_SYS_RESOURCE)
+                {
+                    return Err(LinuxError::EPERM);
+                }
+                state.perm.set(&new.msg_perm);
+                state.qbytes = qbytes;
+                state.ctime = now_secs();
+                // 容量可能变大了
+                queue.wq.notify_all();
+            }
+            IPC_RMID => {
+                // 等待者持有队列的引用，醒来后发现它已被删除
+                let queue = QUEUES.lock().remove(msqid)?;
+                queue.lock().removed = true;
+                queue.wq.notify_all();
+            }
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,410 @@
+//! System V 信号量集
+//!
+//! `semop` 中的一组操作要么全部完成，要么都不执行：只要有一个操作需要等待，就撤销已经
+//! 完成的部分，等到整组操作都能完成时再重试。带 `SEM_UNDO` 的操作在集合中记录调整值，
+//! 进程退出时由 [`exit_sem`] 按调整值撤销它对信号量的修改。
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::time::Duration;
+
+use arceos_posix_api::ctypes::timespec;
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::{current, TaskExtRef};
+
+use super::{
+    now_secs, IpcObject, IpcPerm, IpcTable, IPC_64, IPC_NOWAIT, IPC_RMID, IPC_SET, IPC_STAT,
+};
+use crate::{
+    mm::{read_user, write_user},
+    syscall_body,
+    task::signal::block_interruptible,
+};
+
+/// 集合中信号量数量的上限
+const SEMMSL: usize = 32000;
+/// 信号量集数量的上限
+const SEMMNI: usize = 32000;
+/// 一次 `semop` 中操作数量的上限
+const SEMOPM: usize = 500;
+/// 信号量的最大值，也是 `SEM_UNDO` 调整值绝对值的上限
+const SEMVMX: i32 = 32767;
+
+/// 进程退出时撤销该操作
+const SEM_UNDO: i16 = 0x1000;
+
+/// 最近一次操作信号量的进程
+const GETPID: i32 = 11;
+/// 单个信号量的值
+const GETVAL: i32 = 12;
+/// 所有信号量的值
+const GETALL: i32 = 13;
+/// 等待信号量增大的进程数
+const GETNCNT: i32 = 14;
+/// 等待信号量变为 0 的进程数
+const GETZCNT: i32 = 15;
+/// 设置单个信号量的值
+const SETVAL: i32 = 16;
+/// 设置所有信号量的值
+const SETALL: i32 = 17;
+
+static SETS: Mutex<IpcTable<IpcObject<SemSet>>> = Mutex::new(IpcTable::new(SEMMNI));
+
+/// 信号量集的状态，与 Linux 的 `struct semid64_ds` 布局一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct SemidDs {
+    sem_perm: IpcPerm,
+    sem_otime: i64,
+    #[cfg(target_arch = "x86_64")]
+    _unused1: u64,
+    sem_ctime: i64,
+    #[cfg(target_arch = "x86_64")]
+    _unused2: u64,
+    sem_nsems: u64,
+    _unused: [u64; 2],
+}
+
+/// `semop` 中的一个操作
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SemBuf {
+    sem_num: u16,
+    sem_op: i16,
+    sem_flg: i16,
+}
+
+#[derive(Default)]
+struct Semaphore {
+    val: i32,
+    /// 最近一次操作该信号量的进程
+    pid: i32,
+    /// 等待它增大和变为 0 的进程数
+    ncnt: usize,
+    zcnt: usize,
+}
+
+struct SemSet {
+    perm: IpcPerm,
+    sems: Vec<Semaphore>,
+    /// 最近一次 `semop` 和修改属性的时间
+    otime: i64,
+    ctime: i64,
+    /// 各进程中带 `SEM_UNDO` 的操作累积的调整值，以进程号为键
+    undo: BTreeMap<i32, Vec<i32>>,
+    /// 集合已被删除
+    removed: bool,
+}
+
+/// 一组操作无法立即完成的原因
+enum Blocked {
+    /// 第 `.0` 个信号量需要增大（`.1` 为 `false`）或变为 0（`.1` 为 `true`）
+    Wait(usize, bool),
+    Err(LinuxError),
+}
+
+impl SemSet {
+    fn new(key: i32, flags: i32, nsems: usize) -> Self {
+        Self {
+            perm: IpcPerm::new(key, flags),
+            sems: (0..nsems).map(|_| Semaphore::default()).collect(),
+            otime: 0,
+            ctime: now_secs(),
+            undo: BTreeMap::new(),
+            removed: false,
+        }
+    }
+
+    /// 原子地执行一组操作，不能全部完成时不修改任何信号量
+    fn apply(&mut self, sops: &[SemBuf], pid: i32) -> Result<(), Blocked> {
+        let mut vals: Vec<_> = self.sems.iter().map(|sem| sem.val).collect();
+        let mut adjs = self.undo.get(&pid).cloned();
+        for sop in sops {
+            let num = sop.sem_num as usize;
+            let op = sop.sem_op as i32;
+            let val = vals[num] + op;
+            if op == 0 && vals[num] != 0 || val < 0 {
+                if sop.sem_flg & IPC_NOWAIT as i16 != 0 {
+                    return Err(Blocked::Err(LinuxError::EAGAIN));
+                }
+                return Err(Blocked::Wait(num, op == 0));
+            }
+            if val > SEMVMX {
+                return Err(Blocked::Err(LinuxError::ERANGE));
+            }
+            vals[num] = val;
+            if sop.sem_flg & SEM_UNDO != 0 {
+                let adj = &mut adjs.get_or_insert_with(|| vec![0; vals.len()])[num];
+                if (*adj - op).abs() > SEMVMX {
+                    return Err(Blocked::Err(LinuxError::ERANGE));
+                }
+                *adj -= op;
+            }
+        }
+        for (sem, val) in self.sems.iter_mut().zip(vals) {
+            sem.val = val;
+        }
+        for sop in sops {
+            self.sems[sop.sem_num as usize].pid = pid;
+        }
+        if let Some(adjs) = adjs {
+            self.undo.insert(pid, adjs);
+        }
+        self.otime = now_secs();
+        Ok(())
+    }
+
+    /// 登记或取消在第 `num` 个信号量上的等待
+    fn count_waiter(&mut self, (num, zero): (usize, bool), delta: isize) {
+        let sem = &mut self.sems[num];
+        let count = if zero { &mut sem.zcnt } else { &mut sem.ncnt };
+        *count = count.wrapping_add_signed(delta);
+    }
+
+    /// 信号量被直接设置后，之前记录的调整值不再有意义
+    fn clear_undo(&mut self, num: Option<usize>) {
+        for adjs in self.undo.values_mut() {
+            match num {
+                Some(num) => adjs[num] = 0,
+                None => adjs.fill(0),
+            }
+        }
+    }
+
+    fn stat(&self) -> SemidDs {
+        SemidDs {
+            sem_perm: self.perm,
+            sem_otime: self.otime,
+            sem_ctime: self.ctime,
+            sem_nsems: self.sems.len() as _,
+            ..Default::default()
+        }
+    }
+}
+
+/// 返回键为 `key`、含 `nsems` 个信号量的信号量集的标识符，必要时按 `semflg` 创建它
+///
+/// 查找已有的集合时 `nsems` 可以为 0，但不能超过集合中信号量的数量。
+pub(crate) fn sys_semget(key: i32, nsems: i32, semflg: i32) -> isize {
+    syscall_body!(sys_semget, {
+        let nsems = usize::try_from(nsems).map_err(|_| LinuxError::EINVAL)?;
+        if nsems > SEMMSL {
+            return Err(LinuxError::EINVAL);
+        }
+        SETS.lock().get_or_create(
+            key,
+            semflg,
+            |set| {
+                if nsems > set.lock().sems.len() {
+                    return Err(LinuxError::EINVAL);
+                }
+                Ok(())
+            },
+            || {
+                if nsems == 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                Ok(IpcObject::new(SemSet::new(key, semflg, nsems)))
+            },
+        )
+    })
+}
+
+/// 对信号量集 `semid` 原子地执行 `sops` 处的 `nsops` 个操作
+pub(crate) fn sys_semop(semid: i32, sops: *const SemBuf, nsops: usize) -> isize {
+    syscall_body!(sys_semop, semop(semid, sops, nsops, None))
+}
+
+/// 与 `semop` 相同，但 `timeout` 不为空时最多等待这么长时间，超时返回 EAGAIN
+pub(crate) fn sys_semtimedop(
+    semid: i32,
+    sops: *const SemBuf,
+    nsops: usize,
+    timeout: *const timespec,
+) -> isize {
+    syscall_body!(sys_semtimedop, {
+        let timeout = if timeout.is_null() {
+            None
+        } else {
+            let ts = read_user(timeout)?;
+            if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+        };
+        semop(semid, sops, nsops, timeout)
+    })
+}
+
+/// 执行一组操作，不能全部完成时等待，带 `IPC_NOWAIT` 的操作需要等待时返回 EAGAIN
+///
+/// 等待期间登记在第一个需要等待的信号量上，供 `GETNCNT` 和 `GETZCNT` 查询。
+fn semop(
+    semid: i32,
+    sops: *const SemBuf,
+    nsops: usize,
+    timeout: Option<Duration>,
+) -> LinuxResult<isize> {
+    if nsops == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if nsops > SEMOPM {
+        return Err(LinuxError::E2BIG);
+    }
+    let sops = (0..nsops)
+        .map(|i| read_user(sops.wrapping_add(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let set = SETS.lock().get(semid)?;
+    if sops
+        .iter()
+        .any(|sop| sop.sem_num as usize >= set.lock().sems.len())
+    {
+        return Err(LinuxError::EFBIG);
+    }
+    let pid = current().task_ext().proc_id as i32;
+    let deadline = timeout.map(|timeout| axhal::time::monotonic_time() + timeout);
+    let mut waiting = None;
+    let result = block_interruptible(&set.wq, deadline, || {
+        let mut set = set.lock();
+        if set.removed {
+            return Some(Err(LinuxError::EIDRM));
+        }
+        let done = match set.apply(&sops, pid) {
+            Ok(()) => Ok(0),
+            Err(Blocked::Err(err)) => Err(err),
+            Err(Blocked::Wait(..))
+                if deadline.is_some_and(|deadline| axhal::time::monotonic_time() >= deadline) =>
+            {
+                Err(LinuxError::EAGAIN)
+            }
+            Err(Blocked::Wait(num, zero)) => {
+                if waiting != Some((num, zero)) {
+                    if let Some(old) = waiting.replace((num, zero)) {
+                        set.count_waiter(old, -1);
+                    }
+                    set.count_waiter((num, zero), 1);
+                }
+                return None;
+            }
+        };
+        if let Some(old) = waiting.take() {
+            set.count_waiter(old, -1);
+        }
+        Some(done)
+    });
+    if let Some(old) = waiting {
+        // 被信号打断时仍登记着等待
+        set.lock().count_waiter(old, -1);
+    }
+    if let Ok(Ok(_)) = result {
+        // 信号量的值变了，其他等待者可能可以继续
+        set.wq.notify_all();
+    }
+    result?
+}
+
+/// 对信号量集 `semid` 执行 `cmd`，`arg` 按命令解释为信号量的值或用户缓冲区的地址
+///
+/// - `IPC_STAT`、`IPC_SET`、`IPC_RMID` 读取状态、修改属主和权限、删除集合并唤醒所有等待者；
+/// - `GETVAL`、`GETPID`、`GETNCNT`、`GETZCNT` 返回第 `semnum` 个信号量的值、
+///   最近操作它的进程以及等待它的进程数，`GETALL` 将所有信号量的值写入 `arg`；
+/// - `SETVAL` 和 `SETALL` 设置信号量的值，同时清除各进程对这些信号量的 `SEM_UNDO` 调整值。
+pub(crate) fn sys_semctl(semid: i32, semnum: i32, cmd: i32, arg: usize) -> isize {
+    syscall_body!(sys_semctl, {
+        let cmd = cmd & !IPC_64;
+        if cmd == IPC_RMID {
+            // 等待者持有集合的引用，醒来后发现它已被删除
+            let set = SETS.lock().remove(semid)?;
+            set.lock().removed = true;
+            set.wq.notify_all();
+            return Ok(0);
+        }
+        let set = SETS.lock().get(semid)?;
+        let nsems = set.lock().sems.len();
+        let num = usize::try_from(semnum)
+            .ok()
+            .filter(|&num| num < nsems)
+            .ok_or(LinuxError::EINVAL);
+        let ret = match cmd {
+            IPC_STAT => {
+                let stat = set.lock().stat();
+                write_user(arg as *mut SemidDs, &stat)?;
+                0
+            }
+            IPC_SET => {
+                let new = read_user(arg as *const SemidDs)?;
+                let mut set = set.lock();
+                set.perm.set(&new.sem_perm);
+                set.ctime = now_secs();
+                0
+            }
+            GETVAL => set.lock().sems[num?].val as isize,
+            GETPID => set.lock().sems[num?].pid as isize,
+            GETNCNT => set.lock().sems[num?].ncnt as isize,
+            GETZCNT => set.lock().sems[num?].zcnt as isize,
+            GETALL => {
+                let vals: Vec<_> = set.lock().sems.iter().map(|sem| sem.val as u16).collect();
+                let buf = arg as *mut u16;
+                for (i, val) in vals.iter().enumerate() {
+                    write_user(buf.wrapping_add(i), val)?;
+                }
+                0
+            }
+            SETVAL => {
+                let num = num?;
+                let val = arg as i32;
+                if !(0..=SEMVMX).contains(&val) {
+                    return Err(LinuxError::ERANGE);
+                }
+                let mut state = set.lock();
+                state.sems[num].val = val;
+                state.sems[num].pid = current().task_ext().proc_id as i32;
+                state.clear_undo(Some(num));
+                state.ctime = now_secs();
+                set.wq.notify_all();
+                0
+            }
+            SETALL => {
+                let buf = arg as *const u16;
+                let vals = (0..nsems)
+                    .map(|i| read_user(buf.wrapping_add(i)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if vals.iter().any(|&val| val as i32 > SEMVMX) {
+                    return Err(LinuxError::ERANGE);
+                }
+                let pid = current().task_ext().proc_id as i32;
+                let mut state = set.lock();
+                for (sem, val) in state.sems.iter_mut().zip(vals) {
+                    sem.val = val as i32;
+                    sem.pid = pid;
+                }
+                state.clear_undo(None);
+                state.ctime = now_secs();
+                set.wq.notify_all();
+                0
+            }
+            _ => return Err(LinuxError::EINVAL),
+        };
+        Ok(ret)
+    })
+}
+
+/// 进程退出时按 `SEM_UNDO` 记录的调整值撤销它对各信号量的修改
+///
+/// 撤销后的值被限制在 0 到 `SEMVMX` 之间，已被删除的集合中的调整值随集合一起丢弃。
+pub(crate) fn exit_sem(pid: i32) {
+    let sets = SETS.lock().all();
+    for set in sets {
+        let mut state = set.lock();
+        let Some(adjs) = state.undo.remove(&pid) else {
+            continue;
+        };
+        for (sem, adj) in state.sems.iter_mut().zip(adjs) {
+            if adj != 0 {
+                sem.val = (sem.val + adj).clamp(0, SEMVMX);
+                sem.pid = pid;
+            }
+        }
+        set.wq.notify_all();
+    }
+}
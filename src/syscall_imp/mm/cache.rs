@@ -0,0 +1,27 @@
+use axerrno::LinuxError;
+
+use crate::{
+    config::{USER_SPACE_BASE, USER_SPACE_SIZE},
+    smp::sync_all_cpus,
+    syscall_body,
+};
+
+/// 只需保证调用线程看到新的指令
+const SYS_RISCV_FLUSH_ICACHE_LOCAL: usize = 1;
+
+/// 使 `[start, end)` 内写入的指令对取指可见
+///
+/// 任务随时可能迁移到其他核上，因此即使指定了 `SYS_RISCV_FLUSH_ICACHE_LOCAL`，
+/// 也在所有在线的核上执行 `fence.i`，而不只是当前核。
+pub(crate) fn sys_riscv_flush_icache(start: usize, end: usize, flags: usize) -> isize {
+    syscall_body!(sys_riscv_flush_icache, {
+        if flags & !SYS_RISCV_FLUSH_ICACHE_LOCAL != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if start > end || start < USER_SPACE_BASE || end > USER_SPACE_BASE + USER_SPACE_SIZE {
+            return Err(LinuxError::EFAULT);
+        }
+        sync_all_cpus();
+        Ok(0)
+    })
+}
@@ -0,0 +1,57 @@
+use axerrno::LinuxError;
+
+use crate::{smp::sync_all_cpus, syscall_body};
+
+bitflags::bitflags! {
+    /// commands for sys_membarrier
+    ///
+    /// See <https://github.com/torvalds/linux/blob/master/include/uapi/linux/membarrier.h>
+    #[derive(Debug, Clone, Copy)]
+    struct MembarrierCmd: i32 {
+        /// Barrier on all CPUs running any thread in the system.
+        const GLOBAL = 1 << 0;
+        /// Expedited version of `GLOBAL`.
+        const GLOBAL_EXPEDITED = 1 << 1;
+        /// Register intent to receive `GLOBAL_EXPEDITED` barriers.
+        const REGISTER_GLOBAL_EXPEDITED = 1 << 2;
+        /// Barrier on all CPUs running threads of the calling process.
+        const PRIVATE_EXPEDITED = 1 << 3;
+        /// Register intent to use `PRIVATE_EXPEDITED`.
+        const REGISTER_PRIVATE_EXPEDITED = 1 << 4;
+        /// `PRIVATE_EXPEDITED` plus instruction cache synchronization.
+        const PRIVATE_EXPEDITED_SYNC_CORE = 1 << 5;
+        /// Register intent to use `PRIVATE_EXPEDITED_SYNC_CORE`.
+        const REGISTER_PRIVATE_EXPEDITED_SYNC_CORE = 1 << 6;
+    }
+}
+
+/// 查询支持的命令
+const MEMBARRIER_CMD_QUERY: i32 = 0;
+
+/// 在运行当前进程线程的所有核上执行内存屏障
+///
+/// 目前无法得知其他核上运行的是哪个进程，因此各种屏障都作用于所有在线的核，
+/// 并同时同步指令缓存；注册命令总是成功，未注册也可以直接使用 expedited 命令。
+pub(crate) fn sys_membarrier(cmd: i32, flags: u32, _cpu_id: i32) -> isize {
+    syscall_body!(sys_membarrier, {
+        if flags != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if cmd == MEMBARRIER_CMD_QUERY {
+            return Ok(MembarrierCmd::all().bits() as isize);
+        }
+        let cmd = MembarrierCmd::from_bits(cmd).ok_or(LinuxError::EINVAL)?;
+        if cmd.bits().count_ones() != 1 {
+            return Err(LinuxError::EINVAL);
+        }
+        if cmd.intersects(
+            MembarrierCmd::GLOBAL
+                | MembarrierCmd::GLOBAL_EXPEDITED
+                | MembarrierCmd::PRIVATE_EXPEDITED
+                | MembarrierCmd::PRIVATE_EXPEDITED_SYNC_CORE,
+        ) {
+            sync_all_cpus();
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,123 @@
+use alloc::vec::Vec;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, TaskExtRef};
+use memory_addr::{PageIter4K, VirtAddr};
+
+use crate::{mm::copy_to_user, syscall_body};
+
+/// 锁定当前已有的全部映射
+const MCL_CURRENT: i32 = 1;
+/// 锁定之后新建的映射
+const MCL_FUTURE: i32 = 2;
+/// 与前两者一起使用，只在访问时才分配物理页
+const MCL_ONFAULT: i32 = 4;
+
+/// `mlock2` 的选项：只在访问时才分配物理页
+const MLOCK_ONFAULT: u32 = 1;
+
+/// 将 `[addr, addr + len)` 扩展到页边界，区域中有未映射的页时返回 ENOMEM
+fn mapped_range(addr: usize, len: usize) -> LinuxResult<(VirtAddr, usize)> {
+    let end = addr
+        .checked_add(len)
+        .and_then(|end| end.checked_add(memory_addr::PAGE_SIZE_4K - 1))
+        .ok_or(LinuxError::ENOMEM)?;
+    let start = memory_addr::align_down_4k(addr);
+    let size = memory_addr::align_down_4k(end) - start;
+    let curr = current();
+    if !curr.task_ext().aspace.lock().is_mapped(start.into(), size) {
+        return Err(LinuxError::ENOMEM);
+    }
+    Ok((start.into(), size))
+}
+
+/// 锁定内存区域，`populate` 为真时立即为其分配物理页
+///
+/// 目前没有换出机制，已分配的页总是驻留在内存中，因此锁定只需要提前分配物理页。
+fn do_mlock(addr: usize, len: usize, populate: bool) -> LinuxResult<isize> {
+    let (start, size) = mapped_range(addr, len)?;
+    if populate && size > 0 {
+        let curr = current();
+        curr.task_ext()
+            .aspace
+            .lock()
+            .alloc_for_lazy(start, size)
+            .map_err(|_| LinuxError::ENOMEM)?;
+    }
+    Ok(0)
+}
+
+/// 锁定 `[addr, addr + len)` 所在的页，使其立即驻留在内存中
+pub(crate) fn sys_mlock(addr: usize, len: usize) -> isize {
+    syscall_body!(sys_mlock, do_mlock(addr, len, true))
+}
+
+/// 与 `mlock` 相同，`MLOCK_ONFAULT` 表示只在访问时才分配物理页
+pub(crate) fn sys_mlock2(addr: usize, len: usize, flags: u32) -> isize {
+    syscall_body!(sys_mlock2, {
+        if flags & !MLOCK_ONFAULT != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        do_mlock(addr, len, flags & MLOCK_ONFAULT == 0)
+    })
+}
+
+/// 解除锁定，只检查区域是否已映射
+pub(crate) fn sys_munlock(addr: usize, len: usize) -> isize {
+    syscall_body!(sys_munlock, {
+        mapped_range(addr, len)?;
+        Ok(0)
+    })
+}
+
+/// 锁定进程的全部映射
+///
+/// `MCL_FUTURE` 会一直生效到 `munlockall` 或下一次不带该标志的 `mlockall`。
+pub(crate) fn sys_mlockall(flags: i32) -> isize {
+    syscall_body!(sys_mlockall, {
+        if flags & (MCL_CURRENT | MCL_FUTURE) == 0
+            || flags & !(MCL_CURRENT | MCL_FUTURE | MCL_ONFAULT) != 0
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let ext = curr.task_ext();
+        let populate = flags & MCL_ONFAULT == 0;
+        if flags & MCL_CURRENT != 0 && populate {
+            ext.aspace
+                .lock()
+                .populate_all()
+                .map_err(|_| LinuxError::ENOMEM)?;
+        }
+        ext.set_mlock_future(flags & MCL_FUTURE != 0 && populate);
+        Ok(0)
+    })
+}
+
+/// 解除全部锁定，之后新建的映射恢复为延迟分配
+pub(crate) fn sys_munlockall() -> isize {
+    current().task_ext().set_mlock_future(false);
+    0
+}
+
+/// 查询 `[addr, addr + len)` 中的每一页是否驻留在内存中，结果写入 `vec`，每页一个字节
+///
+/// `addr` 必须按页对齐，区域中有未映射的页时返回 ENOMEM。与 Linux 相同，只读过而映射了
+/// 共享零页的页也算作驻留。
+pub(crate) fn sys_mincore(addr: usize, len: usize, vec: *mut u8) -> isize {
+    syscall_body!(sys_mincore, {
+        if !memory_addr::is_aligned_4k(addr) {
+            return Err(LinuxError::EINVAL);
+        }
+        let (start, size) = mapped_range(addr, len)?;
+        let residency: Vec<u8> = {
+            let curr = current();
+            let aspace = curr.task_ext().aspace.lock();
+            PageIter4K::new(start, start + size)
+                .ok_or(LinuxError::EINVAL)?
+                .map(|page| aspace.is_resident(page) as u8)
+                .collect()
+        };
+        copy_to_user(VirtAddr::from_mut_ptr_of(vec), &residency)?;
+        Ok(0)
+    })
+}
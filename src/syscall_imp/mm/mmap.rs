@@ -1,10 +1,22 @@
-use alloc::vec;
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use arceos_posix_api::{self as api, Cap, FilePath};
 use axerrno::LinuxError;
 use axhal::paging::MappingFlags;
+use axmm::FileSource;
 use axtask::{current, TaskExtRef};
-use memory_addr::{VirtAddr, VirtAddrRange};
+use memory_addr::{PageIter4K, VirtAddr, VirtAddrRange, PAGE_SIZE_4K};
 
-use crate::syscall_body;
+use crate::{
+    boot_args::boot_args,
+    mm::file_map,
+    smp::flush_tlb_shared,
+    syscall_body,
+    syscall_imp::fs::MemFd,
+    task::{rlimit::RLIMIT_AS, Personality},
+};
 
 bitflags::bitflags! {
     /// permissions for sys_mmap
@@ -58,6 +70,40 @@ bitflags::bitflags! {
     }
 }
 
+/// 启发式地检查，只拒绝明显无法满足的映射
+const OVERCOMMIT_GUESS: usize = 0;
+/// 总是允许
+const OVERCOMMIT_ALWAYS: usize = 1;
+/// 严格限制提交的内存
+const OVERCOMMIT_NEVER: usize = 2;
+
+/// 内存的提交策略，即 `/proc/sys/vm/overcommit_memory`
+pub(crate) static OVERCOMMIT_MEMORY: AtomicUsize = AtomicUsize::new(OVERCOMMIT_GUESS);
+/// [`OVERCOMMIT_MEMORY`] 可以取的值
+pub(crate) const OVERCOMMIT_RANGE: RangeInclusive<usize> = OVERCOMMIT_GUESS..=OVERCOMMIT_NEVER;
+
+/// 严格模式下可以提交的内存占物理内存的百分比，与 Linux 中 `vm.overcommit_ratio` 的
+/// 默认值一致
+const OVERCOMMIT_RATIO: usize = 50;
+
+/// 按 [`OVERCOMMIT_MEMORY`] 检查能否提交 `len` 字节的内存
+///
+/// 没有交换区，也不统计各映射提交的内存：启发式模式下拒绝超过物理内存总量的映射，除非
+/// 指定了 `noreserve`（`MAP_NORESERVE`）；严格模式下拒绝使已使用的内存超过物理内存的
+/// [`OVERCOMMIT_RATIO`]% 的映射，此时 `MAP_NORESERVE` 无效。
+fn may_commit(len: usize, noreserve: bool) -> bool {
+    let alloc = axalloc::global_allocator();
+    let total = alloc.total_pages() * PAGE_SIZE_4K;
+    match OVERCOMMIT_MEMORY.load(Ordering::Relaxed) {
+        OVERCOMMIT_ALWAYS => true,
+        OVERCOMMIT_NEVER => {
+            let used = (alloc.total_pages() - alloc.available_pages()) * PAGE_SIZE_4K;
+            used.saturating_add(len) <= total / 100 * OVERCOMMIT_RATIO
+        }
+        _ => noreserve || len <= total,
+    }
+}
+
 pub(crate) fn sys_mmap(
     mut addr: *mut usize,
     length: usize,
@@ -70,7 +116,14 @@ pub(crate) fn sys_mmap(
         let curr = current();
         let curr_ext = curr.task_ext();
         let mut aspace = curr_ext.aspace.lock();
-        let permission_flags = MmapProt::from_bits_truncate(prot);
+        let mut permission_flags = MmapProt::from_bits_truncate(prot);
+        if permission_flags.contains(MmapProt::PROT_READ)
+            && curr_ext
+                .personality()
+                .contains(Personality::READ_IMPLIES_EXEC)
+        {
+            permission_flags |= MmapProt::PROT_EXEC;
+        }
         // TODO: check illegal flags for mmap
         // An example is the flags contained none of MAP_PRIVATE, MAP_SHARED, or MAP_SHARED_VALIDATE.
         let map_flags = MmapFlags::from_bits_truncate(flags);
@@ -85,6 +138,22 @@ pub(crate) fn sys_mmap(
             end = memory_addr::align_up_4k(end);
             aligned_length = end - start;
         }
+        let limit = curr_ext.rlimits.lock().get(RLIMIT_AS);
+        if !limit.allows(aspace.mapped_size() + aligned_length) {
+            return Err(LinuxError::ENOMEM);
+        }
+        let anonymous = fd == -1 || map_flags.contains(MmapFlags::MAP_ANONYMOUS);
+        // 检查请求的权限，不包括 READ_IMPLIES_EXEC 加上的可执行
+        if anonymous && violates_wx(&MmapProt::from_bits_truncate(prot)) {
+            return Err(LinuxError::EPERM);
+        }
+        // 与 Linux 相同，可写的私有映射和匿名映射需要提交内存
+        if permission_flags.contains(MmapProt::PROT_WRITE)
+            && (anonymous || map_flags.contains(MmapFlags::MAP_PRIVATE))
+            && !may_commit(aligned_length, map_flags.contains(MmapFlags::MAP_NORESERVE))
+        {
+            return Err(LinuxError::ENOMEM);
+        }
         let start_addr = if map_flags.contains(MmapFlags::MAP_FIXED) {
             VirtAddr::from(addr as usize)
         } else {
@@ -101,24 +170,111 @@ pub(crate) fn sys_mmap(
                 ))
                 .ok_or(LinuxError::ENOMEM)?
         };
-        let populate = if fd == -1 {
-            false
+        // 映射文件要求文件描述符可读，可写的共享映射还要求可写
+        let file = if anonymous {
+            None
         } else {
-            !map_flags.contains(MmapFlags::MAP_ANONYMOUS)
+            let mut cap = Cap::READ;
+            if map_flags.contains(MmapFlags::MAP_SHARED)
+                && permission_flags.contains(MmapProt::PROT_WRITE)
+            {
+                cap |= Cap::WRITE;
+            }
+            Some(api::access_file_like(fd, cap, LinuxError::EACCES)?)
+        };
+        // 记录映射的文件及其打开方式，之后用于 mprotect 的检查和 /proc/[pid]/maps
+        let source = match &file {
+            Some(file) => {
+                let source = Arc::new(FileSource {
+                    path: file.link_target(),
+                    start: start_addr,
+                    offset: usize::try_from(offset).map_err(|_| LinuxError::EINVAL)?,
+                    shared: map_flags.contains(MmapFlags::MAP_SHARED),
+                    writable: api::access_file_like(fd, Cap::WRITE, LinuxError::EACCES).is_ok(),
+                });
+                // 可执行的内容可能被改写，允许映射但记录下来
+                if permission_flags.contains(MmapProt::PROT_EXEC) && source.writable {
+                    warn!(
+                        "W^X: executable mapping at {:#x} of writable file {}",
+                        start_addr, source.path
+                    );
+                }
+                Some(source)
+            }
+            None => None,
         };
-        aspace.map_alloc(
-            start_addr,
-            aligned_length,
-            permission_flags.into(),
-            populate,
-        )?;
-
-        if populate {
-            let file = arceos_posix_api::get_file_like(fd)?;
+
+        if let Some(file) = &file {
+            if let Ok(memfd) = file.clone().into_any().downcast::<MemFd>() {
+                let offset = usize::try_from(offset).map_err(|_| LinuxError::EINVAL)?;
+                if !memory_addr::is_aligned_4k(offset) {
+                    return Err(LinuxError::EINVAL);
+                }
+                if map_flags.contains(MmapFlags::MAP_SHARED) {
+                    // 直接映射文件的物理页，与其他进程的共享映射看到同一份数据
+                    let writable = permission_flags.contains(MmapProt::PROT_WRITE);
+                    let frames = memfd.shared_frames(offset, writable)?;
+                    aspace.map_file_shared(
+                        start_addr,
+                        aligned_length,
+                        frames,
+                        permission_flags.into(),
+                        source.unwrap(),
+                    )?;
+                } else {
+                    aspace.map_file_alloc(
+                        start_addr,
+                        aligned_length,
+                        permission_flags.into(),
+                        true,
+                        source.unwrap(),
+                    )?;
+                    aspace.write(start_addr, &memfd.read_for_mmap(offset, length))?;
+                }
+                return Ok(start_addr.as_usize());
+            }
+            if map_flags.contains(MmapFlags::MAP_SHARED) {
+                if let Ok(file) = file.clone().into_any().downcast::<api::File>() {
+                    // 映射文件页所在的物理页，与该文件的其他共享映射看到同一份数据
+                    let offset = usize::try_from(offset).map_err(|_| LinuxError::EINVAL)?;
+                    if !memory_addr::is_aligned_4k(offset) {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    let path = FilePath::new(file.path())?;
+                    let frames = file_map::shared_frames(path.as_str(), offset, length)?;
+                    aspace.map_file_shared(
+                        start_addr,
+                        aligned_length,
+                        frames,
+                        permission_flags.into(),
+                        source.unwrap(),
+                    )?;
+                    return Ok(start_addr.as_usize());
+                }
+            }
+        }
+        let populate = file.is_some() || curr_ext.mlock_future();
+        match source {
+            Some(source) => aspace.map_file_alloc(
+                start_addr,
+                aligned_length,
+                permission_flags.into(),
+                populate,
+                source,
+            )?,
+            None => aspace.map_alloc(
+                start_addr,
+                aligned_length,
+                permission_flags.into(),
+                populate,
+            )?,
+        }
+
+        if let Some(file) = file {
             let file_size = file.stat()?.st_size as usize;
             let file = file
                 .into_any()
-                .downcast::<arceos_posix_api::File>()
+                .downcast::<api::File>()
                 .map_err(|_| LinuxError::EBADF)?;
             let file = file.inner().lock();
             if offset < 0 || offset as usize >= file_size {
@@ -135,6 +291,11 @@ pub(crate) fn sys_mmap(
     })
 }
 
+/// 严格 W^X 模式（`strictwx=on`）下，匿名内存不能同时可写和可执行
+fn violates_wx(prot: &MmapProt) -> bool {
+    boot_args().strict_wx && prot.contains(MmapProt::PROT_WRITE | MmapProt::PROT_EXEC)
+}
+
 pub(crate) fn sys_munmap(addr: *mut usize, mut length: usize) -> i32 {
     syscall_body!(sys_munmap, {
         let curr = current();
@@ -143,7 +304,111 @@ pub(crate) fn sys_munmap(addr: *mut usize, mut length: usize) -> i32 {
         length = memory_addr::align_up_4k(length);
         let start_addr = VirtAddr::from(addr as usize);
         aspace.unmap(start_addr, length)?;
-        axhal::arch::flush_tlb(None);
+        drop(aspace);
+        // 与其他核上的 CLONE_VM 任务共享地址空间时，它们的 TLB 也需要刷新
+        flush_tlb_shared(Arc::strong_count(&curr_ext.aspace) > 1);
+        file_map::release_unused();
+        Ok(0)
+    })
+}
+
+/// 异步写回，只把写回交给内核线程
+const MS_ASYNC: i32 = 1;
+/// 重新读入文件已被修改的干净页
+const MS_INVALIDATE: i32 = 2;
+/// 同步写回，等待写回完成
+const MS_SYNC: i32 = 4;
+
+/// 将 `[addr, addr + length)` 中文件共享映射的修改写回文件
+///
+/// `MS_SYNC` 写回范围内的脏页并等待写入完成，`MS_ASYNC` 把它们交给写回线程后立即返回，
+/// `MS_INVALIDATE` 重新读入范围内的干净页，使映射看到其他方式对文件的修改。
+/// 匿名映射和私有映射没有需要写回的内容。
+///
+/// `addr` 未按页对齐、指定了未知的标志或同时指定 `MS_SYNC` 与 `MS_ASYNC` 时返回 `EINVAL`，
+/// 范围内有不属于任何映射的地址时返回 `ENOMEM`。
+pub(crate) fn sys_msync(addr: usize, length: usize, flags: i32) -> i32 {
+    syscall_body!(sys_msync, {
+        if flags & !(MS_ASYNC | MS_INVALIDATE | MS_SYNC) != 0
+            || flags & (MS_ASYNC | MS_SYNC) == MS_ASYNC | MS_SYNC
+            || !memory_addr::is_aligned_4k(addr)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let end = addr
+            .checked_add(length)
+            .and_then(|end| end.checked_next_multiple_of(memory_addr::PAGE_SIZE_4K))
+            .ok_or(LinuxError::ENOMEM)?;
+        let mut frames = Vec::new();
+        {
+            let curr = current();
+            let aspace = curr.task_ext().aspace.lock();
+            let pages = PageIter4K::new(VirtAddr::from(addr), VirtAddr::from(end))
+                .ok_or(LinuxError::ENOMEM)?;
+            for page in pages {
+                if aspace.find_area(page).is_none() {
+                    return Err(LinuxError::ENOMEM);
+                }
+                if let Ok((paddr, ..)) = aspace.page_table().query(page) {
+                    frames.push(paddr);
+                }
+            }
+        }
+        file_map::sync(&frames, flags & MS_ASYNC == 0, flags & MS_INVALIDATE != 0)?;
+        Ok(0)
+    })
+}
+
+/// 将 `[addr, addr + length)` 的访问权限改为 `prot`，范围两端所在的映射被拆分
+///
+/// 只读打开的文件的共享映射不能变为可写，返回 `EACCES`。严格 W^X 模式下匿名映射不能
+/// 同时可写和可执行，返回 `EPERM`，但可以在可写与可执行之间切换。
+///
+/// `addr` 未按页对齐或 `prot` 含有未知的位时返回 `EINVAL`，范围内有不属于任何映射的
+/// 地址时返回 `ENOMEM`。
+pub(crate) fn sys_mprotect(addr: usize, length: usize, prot: i32) -> i32 {
+    syscall_body!(sys_mprotect, {
+        let mut prot = MmapProt::from_bits(prot).ok_or(LinuxError::EINVAL)?;
+        if !memory_addr::is_aligned_4k(addr) {
+            return Err(LinuxError::EINVAL);
+        }
+        let end = addr
+            .checked_add(length)
+            .and_then(|end| end.checked_next_multiple_of(PAGE_SIZE_4K))
+            .ok_or(LinuxError::ENOMEM)?;
+        if end == addr {
+            return Ok(0);
+        }
+        let curr = current();
+        let curr_ext = curr.task_ext();
+        let mut aspace = curr_ext.aspace.lock();
+        let (start, size) = (VirtAddr::from(addr), end - addr);
+        if !aspace.is_mapped(start, size) {
+            return Err(LinuxError::ENOMEM);
+        }
+        for (_, _, _, backend) in aspace.areas().filter(|&(area_start, area_size, ..)| {
+            area_start < end.into() && start < area_start + area_size
+        }) {
+            match backend.file() {
+                Some(file)
+                    if file.shared && !file.writable && prot.contains(MmapProt::PROT_WRITE) =>
+                {
+                    return Err(LinuxError::EACCES);
+                }
+                None if violates_wx(&prot) => return Err(LinuxError::EPERM),
+                _ => {}
+            }
+        }
+        if prot.contains(MmapProt::PROT_READ)
+            && curr_ext
+                .personality()
+                .contains(Personality::READ_IMPLIES_EXEC)
+        {
+            prot |= MmapProt::PROT_EXEC;
+        }
+        aspace.protect_areas(start, size, prot.into())?;
+        drop(aspace);
+        flush_tlb_shared(Arc::strong_count(&curr_ext.aspace) > 1);
         Ok(0)
     })
 }
@@ -1,3 +1,13 @@
+#[cfg(target_arch = "riscv64")]
+mod cache;
+mod membarrier;
+mod mlock;
 mod mmap;
+mod process_vm;
 
+#[cfg(target_arch = "riscv64")]
+pub(crate) use self::cache::*;
+pub(crate) use self::membarrier::*;
+pub(crate) use self::mlock::*;
 pub(crate) use self::mmap::*;
+pub(crate) use self::process_vm::*;
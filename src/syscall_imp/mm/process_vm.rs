@@ -0,0 +1,133 @@
+use alloc::vec::Vec;
+
+use arceos_posix_api::ctypes::iovec;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, TaskExtRef};
+use memory_addr::{MemoryAddr, VirtAddr, PAGE_SIZE_4K};
+
+use crate::{
+    mm::{copy_from_aspace, copy_to_aspace, read_user},
+    syscall_body,
+    task::{find_task_by_pid, Pid},
+};
+
+/// 一次调用中 iovec 的最大数量
+const IOV_MAX: usize = 1024;
+
+/// 读取用户传入的 iovec 数组，总长度溢出时返回 EINVAL
+fn read_iovecs(iov: *const iovec, count: usize) -> LinuxResult<Vec<(usize, usize)>> {
+    if count > IOV_MAX {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut total = 0usize;
+    (0..count)
+        .map(|i| {
+            let vec = read_user(iov.wrapping_add(i))?;
+            total = total
+                .checked_add(vec.iov_len)
+                .filter(|&total| total <= isize::MAX as usize)
+                .ok_or(LinuxError::EINVAL)?;
+            Ok((vec.iov_base as usize, vec.iov_len))
+        })
+        .collect()
+}
+
+/// 按顺序在本进程的 `local` 与目标进程的 `remote` 之间复制数据，返回复制的字节数
+///
+/// 每次最多复制到两侧下一个页边界为止，并且不会同时持有两个地址空间的锁，
+/// 因此目标进程是调用者自身时也不会死锁。遇到无法访问的页时停止，
+/// 已经复制了部分数据时返回其长度，否则返回 EFAULT。
+fn transfer(
+    pid: Pid,
+    local: *const iovec,
+    liovcnt: usize,
+    remote: *const iovec,
+    riovcnt: usize,
+    flags: usize,
+    to_remote: bool,
+) -> LinuxResult<isize> {
+    if flags != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let local = read_iovecs(local, liovcnt)?;
+    let remote = read_iovecs(remote, riovcnt)?;
+
+    let curr = current();
+    let target = find_task_by_pid(pid)
+        .filter(|task| task.state() != axtask::TaskState::Exited)
+        .ok_or(LinuxError::ESRCH)?;
+    if !curr.task_ext().may_access(target.task_ext()) {
+        return Err(LinuxError::EPERM);
+    }
+    let local_aspace = curr.task_ext().aspace.clone();
+    let remote_aspace = target.task_ext().aspace.clone();
+
+    let mut buf = [0u8; PAGE_SIZE_4K];
+    let mut copied = 0;
+    let (mut li, mut loff) = (0, 0);
+    let (mut ri, mut roff) = (0, 0);
+    while li < local.len() && ri < remote.len() {
+        let (lbase, llen) = local[li];
+        let (rbase, rlen) = remote[ri];
+        if loff == llen {
+            (li, loff) = (li + 1, 0);
+            continue;
+        }
+        if roff == rlen {
+            (ri, roff) = (ri + 1, 0);
+            continue;
+        }
+        let laddr = VirtAddr::from(lbase.wrapping_add(loff));
+        let raddr = VirtAddr::from(rbase.wrapping_add(roff));
+        let len = (llen - loff)
+            .min(rlen - roff)
+            .min(PAGE_SIZE_4K - laddr.align_offset_4k())
+            .min(PAGE_SIZE_4K - raddr.align_offset_4k());
+        let chunk = &mut buf[..len];
+        let (src, src_addr, dst, dst_addr) = if to_remote {
+            (&local_aspace, laddr, &remote_aspace, raddr)
+        } else {
+            (&remote_aspace, raddr, &local_aspace, laddr)
+        };
+        let result = copy_from_aspace(&mut src.lock(), src_addr, chunk)
+            .and_then(|_| copy_to_aspace(&mut dst.lock(), dst_addr, chunk));
+        if result.is_err() {
+            if copied == 0 {
+                return Err(LinuxError::EFAULT);
+            }
+            break;
+        }
+        copied += len;
+        loff += len;
+        roff += len;
+    }
+    Ok(copied as isize)
+}
+
+/// 将目标进程 `remote` 处的数据读入本进程的 `local`
+pub(crate) fn sys_process_vm_readv(
+    pid: Pid,
+    local: *const iovec,
+    liovcnt: usize,
+    remote: *const iovec,
+    riovcnt: usize,
+    flags: usize,
+) -> isize {
+    syscall_body!(sys_process_vm_readv, {
+        transfer(pid, local, liovcnt, remote, riovcnt, flags, false)
+    })
+}
+
+/// 将本进程 `local` 处的数据写入目标进程的 `remote`
+pub(crate) fn sys_process_vm_writev(
+    pid: Pid,
+    local: *const iovec,
+    liovcnt: usize,
+    remote: *const iovec,
+    riovcnt: usize,
+    flags: usize,
+) -> isize {
+    syscall_body!(sys_process_vm_writev, {
+        transfer(pid, local, liovcnt, remote, riovcnt, flags, true)
+    })
+}
@@ -1,21 +1,60 @@
 mod fs;
+mod ipc;
 mod mm;
+mod net;
+mod signal;
+mod stats;
+mod system_info;
 mod task;
 mod time;
-mod system_info;
+mod timer;
+
+use core::panic::PanicInfo;
 
 use axerrno::LinuxError;
 use axhal::{
     arch::TrapFrame,
-    trap::{register_trap_handler, SYSCALL},
+    trap::{register_trap_handler, PANIC, SYSCALL},
 };
+use axtask::{current, TaskExtRef};
 use syscalls::Sysno;
-use system_info::sys_uname;
+use system_info::{
+    sys_reboot, sys_setdomainname, sys_sethostname, sys_sysinfo, sys_syslog, sys_uname,
+};
+
+pub(crate) use self::fs::{init_inotify, open_file, Cred, IoRing};
+pub(crate) use self::ipc::exit_sem;
+pub(crate) use self::stats::init_syscall_stats;
+pub(crate) use self::task::clear_child_tid;
+pub(crate) use system_info::{init_sysctl, shutdown};
 
 use self::fs::*;
+use self::ipc::*;
 use self::mm::*;
+use self::net::*;
+use self::signal::*;
 use self::task::*;
 use self::time::*;
+use self::timer::*;
+
+/// 建立批量 I/O 环，本内核特有的系统调用，见 [`fs::sys_io_setup_lite`]
+///
+/// 本内核特有的系统调用号在 Linux 已分配的系统调用号之后，不会与之冲突。
+pub const SYS_IO_SETUP_LITE: usize = 500;
+/// 执行批量 I/O 环中的提交项，本内核特有的系统调用，见 [`fs::sys_io_enter`]
+pub const SYS_IO_ENTER: usize = 501;
+/// 使内核在系统调用中 panic，仅在启用 `debug_syscall` 特性时可用，用于测试 panic 的处理
+pub const SYS_DEBUG_PANIC: usize = 502;
+
+/// 系统调用号对应的名称，未知的系统调用号返回 `None`
+pub(crate) fn syscall_name(sysno: usize) -> Option<&'static str> {
+    match sysno {
+        SYS_IO_SETUP_LITE => Some("io_setup_lite"),
+        SYS_IO_ENTER => Some("io_enter"),
+        SYS_DEBUG_PANIC => Some("debug_panic"),
+        _ => Sysno::new(sysno).map(|sysno| sysno.name()),
+    }
+}
 
 /// Macro to generate syscall body
 ///
@@ -41,12 +80,123 @@ macro_rules! syscall_body {
 
 #[register_trap_handler(SYSCALL)]
 fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
-    match Sysno::from(syscall_num as u32) {
+    crate::task::ptrace::syscall_enter();
+    let curr = current();
+    curr.task_ext().enter_syscall(syscall_num);
+    let ret = if stats::enabled() {
+        stats::record(syscall_num, || dispatch(tf, syscall_num))
+    } else {
+        dispatch(tf, syscall_num)
+    };
+    curr.task_ext().leave_syscall();
+    crate::task::kstack::check(&curr, syscall_num);
+    curr.task_ext()
+        .signal
+        .lock()
+        .syscall_returned(syscall_num, tf.arg0(), ret);
+    ret
+}
+
+/// 为用户进程执行系统调用时 panic，只结束该进程，不使内核停机
+///
+/// 这类 panic 通常是因为没有检查用户传入的参数。内核不支持栈展开，panic 时持有的锁不会被释放，
+/// 之后访问同一资源的任务仍可能死锁，因此这只是尽量避免停机。在禁止抢占或关中断的临界区中
+/// panic 时，持有的自旋锁或关闭的中断会使内核无法继续运行，此时与其他情况下的 panic 一样使内核停机。
+#[register_trap_handler(PANIC)]
+fn handle_panic(_info: &PanicInfo) {
+    let Some(curr) = axtask::current_may_uninit() else {
+        return;
+    };
+    if unsafe { curr.task_ext_ptr() }.is_null() {
+        return;
+    }
+    // 取出后再次 panic 时不会进入这里，避免反复结束同一个进程
+    let Some(sysno) = curr.task_ext().take_syscall() else {
+        return;
+    };
+    if !axtask::current_can_preempt()
+        || axhal::arch::irqs_enabled() != curr.task_ext().syscall_irqs_enabled()
+    {
+        error!(
+            "{}: panicked in a critical section of syscall {}",
+            curr.id_name(),
+            syscall_name(sysno).unwrap_or("unknown")
+        );
+        return;
+    }
+    error!(
+        "{}: panicked in syscall {}, killing it",
+        curr.id_name(),
+        syscall_name(sysno).unwrap_or("unknown")
+    );
+    if let Some(top) = curr.kernel_stack_top() {
+        let top = top.as_usize();
+        error!("Backtrace:");
+        axhal::backtrace::backtrace(top - crate::config::KERNEL_STACK_SIZE..top, |addr| {
+            error!("  {:#x}", addr)
+        });
+    }
+    crate::task::signal::exit_by_signal(crate::task::signal::SIGSYS);
+}
+
+fn dispatch(tf: &TrapFrame, syscall_num: usize) -> isize {
+    match syscall_num {
+        SYS_IO_SETUP_LITE => return sys_io_setup_lite(tf.arg0() as _),
+        SYS_IO_ENTER => return sys_io_enter(tf.arg0() as _, tf.arg1() as _),
+        #[cfg(feature = "debug_syscall")]
+        SYS_DEBUG_PANIC => panic!("{}: debug_panic requested", current().id_name()),
+        _ => {}
+    }
+    let Some(sysno) = Sysno::new(syscall_num) else {
+        warn!("Unimplemented syscall: {}", syscall_num);
+        crate::task::do_exit(LinuxError::ENOSYS as _)
+    };
+    match sysno {
         Sysno::read => sys_read(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         Sysno::write => sys_write(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         Sysno::pipe2 => sys_pipe2(tf.arg0() as _, tf.arg1() as _),
         Sysno::close => sys_close(tf.arg0() as _),
-        Sysno::openat => sys_openat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _),
+        Sysno::close_range => sys_close_range(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::lseek => sys_lseek(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::sendfile => sys_sendfile(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::ftruncate => sys_ftruncate(tf.arg0() as _, tf.arg1() as _),
+        Sysno::fadvise64 => sys_fadvise64(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::fcntl => sys_fcntl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::memfd_create => sys_memfd_create(tf.arg0() as _, tf.arg1() as _),
+        Sysno::inotify_init1 => sys_inotify_init1(tf.arg0() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::inotify_init => sys_inotify_init1(0),
+        Sysno::inotify_add_watch => {
+            sys_inotify_add_watch(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)
+        }
+        Sysno::inotify_rm_watch => sys_inotify_rm_watch(tf.arg0() as _, tf.arg1() as _),
+        Sysno::ppoll => sys_ppoll(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::poll => sys_poll(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::sync => sys_sync(),
+        Sysno::umask => sys_umask(tf.arg0() as _),
+        Sysno::openat => sys_openat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
         Sysno::mmap => sys_mmap(
             tf.arg0() as _,
             tf.arg1() as _,
@@ -56,12 +206,108 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg5() as _,
         ) as _,
         Sysno::munmap => sys_munmap(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::mprotect => sys_mprotect(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::msync => sys_msync(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::mlock => sys_mlock(tf.arg0() as _, tf.arg1() as _),
+        Sysno::mlock2 => sys_mlock2(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::munlock => sys_munlock(tf.arg0() as _, tf.arg1() as _),
+        Sysno::mlockall => sys_mlockall(tf.arg0() as _),
+        Sysno::munlockall => sys_munlockall(),
+        Sysno::mincore => sys_mincore(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::process_vm_readv => sys_process_vm_readv(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ),
+        Sysno::process_vm_writev => sys_process_vm_writev(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ),
+        Sysno::membarrier => sys_membarrier(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        #[cfg(target_arch = "riscv64")]
+        Sysno::riscv_flush_icache => {
+            sys_riscv_flush_icache(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)
+        }
         Sysno::brk => sys_brk(tf.arg0() as _) as _,
+        Sysno::msgget => sys_msgget(tf.arg0() as _, tf.arg1() as _),
+        Sysno::msgsnd => sys_msgsnd(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::msgrcv => sys_msgrcv(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::msgctl => sys_msgctl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::mq_open => sys_mq_open(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::mq_unlink => sys_mq_unlink(tf.arg0() as _),
+        Sysno::mq_timedsend => sys_mq_timedsend(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::mq_timedreceive => sys_mq_timedreceive(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::mq_getsetattr => sys_mq_getsetattr(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::semget => sys_semget(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::semop => sys_semop(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::semtimedop => sys_semtimedop(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::semctl => sys_semctl(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
         Sysno::ioctl => sys_ioctl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
         Sysno::getcwd => sys_getcwd(tf.arg0() as _, tf.arg1() as _) as _,
         Sysno::chdir => sys_chdir(tf.arg0() as _) as _,
+        Sysno::chroot => sys_chroot(tf.arg0() as _),
         Sysno::mkdirat => sys_mkdirat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::mknodat => sys_mknodat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::mknod => sys_mknodat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+        ),
         Sysno::dup => sys_dup(tf.arg0() as _) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::dup2 => sys_dup2(tf.arg0() as _, tf.arg1() as _) as _,
         Sysno::dup3 => sys_dup3(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
         Sysno::getdents64 => sys_getdents64(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
         Sysno::linkat => sys_linkat(
@@ -71,8 +317,101 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg3() as _,
             tf.arg4() as _,
         ) as _,
+        Sysno::symlinkat => sys_symlinkat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::symlink => sys_symlinkat(
+            tf.arg0() as _,
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg1() as _,
+        ),
+        Sysno::readlinkat => sys_readlinkat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::readlink => sys_readlinkat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+        ),
         Sysno::unlinkat => syscall_unlinkat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
-        Sysno::fstat => sys_fstat(tf.arg0() as _, tf.arg1() as _) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::rename => sys_renameat2(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg1() as _,
+            0,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::renameat => sys_renameat2(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            0,
+        ),
+        Sysno::renameat2 => sys_renameat2(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::fstat => sys_fstat(tf.arg0() as _, tf.arg1() as _),
+        Sysno::statfs => sys_statfs(tf.arg0() as _, tf.arg1() as _),
+        Sysno::fstatfs => sys_fstatfs(tf.arg0() as _, tf.arg1() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::newfstatat => sys_fstatat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        #[cfg(not(target_arch = "x86_64"))]
+        Sysno::fstatat => sys_fstatat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::stat => sys_fstatat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            0,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::lstat => sys_fstatat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            AT_SYMLINK_NOFOLLOW,
+        ),
+        Sysno::faccessat => sys_faccessat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, 0),
+        Sysno::faccessat2 => sys_faccessat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::access => sys_faccessat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            0,
+        ),
+        Sysno::utimensat => sys_utimensat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
         Sysno::mount => sys_mount(
             tf.arg0() as _,
             tf.arg1() as _,
@@ -81,26 +420,109 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg4() as _,
         ) as isize,
         Sysno::umount2 => sys_umount2(tf.arg0() as _, tf.arg1() as _) as isize,
+        Sysno::readv => sys_readv(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         Sysno::writev => sys_writev(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::socket => sys_socket(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::socketpair => sys_socketpair(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::bind => sys_bind(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::connect => sys_connect(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::listen => sys_listen(tf.arg0() as _, tf.arg1() as _),
+        Sysno::accept => sys_accept4(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, 0),
+        Sysno::accept4 => sys_accept4(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::getsockname => sys_getsockname(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::getpeername => sys_getpeername(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::sendto => sys_sendto(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ),
+        Sysno::recvfrom => sys_recvfrom(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ),
+        Sysno::sendmsg => sys_sendmsg(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::recvmsg => sys_recvmsg(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::setsockopt => sys_setsockopt(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::getsockopt => sys_getsockopt(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::shutdown => sys_shutdown(tf.arg0() as _, tf.arg1() as _),
         Sysno::sched_yield => sys_sched_yield() as isize,
-        Sysno::nanosleep => sys_nanosleep(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::sched_getscheduler => sys_sched_getscheduler(tf.arg0() as _),
+        Sysno::sched_setscheduler => {
+            sys_sched_setscheduler(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)
+        }
+        Sysno::sched_getparam => sys_sched_getparam(tf.arg0() as _, tf.arg1() as _),
+        Sysno::sched_setparam => sys_sched_setparam(tf.arg0() as _, tf.arg1() as _),
+        Sysno::sched_get_priority_max => sys_sched_get_priority_max(tf.arg0() as _),
+        Sysno::sched_get_priority_min => sys_sched_get_priority_min(tf.arg0() as _),
+        Sysno::sched_rr_get_interval => sys_sched_rr_get_interval(tf.arg0() as _, tf.arg1() as _),
+        Sysno::sched_setaffinity => {
+            sys_sched_setaffinity(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)
+        }
+        Sysno::sched_getaffinity => {
+            sys_sched_getaffinity(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)
+        }
+        Sysno::getpriority => sys_getpriority(tf.arg0() as _, tf.arg1() as _),
+        Sysno::setpriority => sys_setpriority(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::ioprio_get => sys_ioprio_get(tf.arg0() as _, tf.arg1() as _),
+        Sysno::ioprio_set => sys_ioprio_set(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::getcpu => sys_getcpu(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::nanosleep => sys_nanosleep(tf.arg0() as _, tf.arg1() as _),
         Sysno::clock_nanosleep => sys_clock_nanosleep(
             tf.arg0() as _,
             tf.arg1() as _,
             tf.arg2() as _,
             tf.arg3() as _,
-        ) as _,
+        ),
         Sysno::getpid => sys_getpid() as isize,
         Sysno::getppid => sys_getppid(),
         Sysno::gettid => sys_gettid() as isize,
         Sysno::exit => sys_exit(tf.arg0() as _),
         Sysno::clone => sys_clone(
+            tf,
             tf.arg0() as _,
             tf.arg1() as _,
             tf.arg2() as _,
             tf.arg3() as _,
             tf.arg4() as _,
         ),
+        Sysno::futex => sys_futex(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ),
+        Sysno::clone3 => sys_clone3(tf, tf.arg0() as _, tf.arg1() as _),
         Sysno::wait4 => sys_wait4(
             tf.arg0() as _,
             tf.arg1() as _,
@@ -108,17 +530,103 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg3() as _,
         ),
         Sysno::execve => sys_execve(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::execveat => sys_execveat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
         Sysno::times => sys_times(tf.arg0() as _) as _,
+        Sysno::getrusage => sys_getrusage(tf.arg0() as _, tf.arg1() as _),
         #[cfg(target_arch = "x86_64")]
         Sysno::arch_prctl => sys_arch_prctl(tf.arg0() as _, tf.arg1() as _),
         Sysno::set_tid_address => sys_set_tid_address(tf.arg0() as _),
+        Sysno::personality => sys_personality(tf.arg0() as _),
+        Sysno::prctl => sys_prctl(tf.arg0() as _, tf.arg1() as _),
+        Sysno::unshare => sys_unshare(tf.arg0() as _),
+        Sysno::capget => sys_capget(tf.arg0() as _, tf.arg1() as _),
+        Sysno::capset => sys_capset(tf.arg0() as _, tf.arg1() as _),
+        Sysno::getuid => sys_getuid(),
+        Sysno::geteuid => sys_geteuid(),
+        Sysno::getgid => sys_getgid(),
+        Sysno::getegid => sys_getegid(),
+        Sysno::setuid => sys_setuid(tf.arg0() as _),
+        Sysno::rt_sigaction => sys_rt_sigaction(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::rt_sigprocmask => sys_rt_sigprocmask(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::rt_sigreturn => sys_rt_sigreturn(),
+        Sysno::rt_sigpending => sys_rt_sigpending(tf.arg0() as _, tf.arg1() as _),
+        Sysno::rt_sigsuspend => sys_rt_sigsuspend(tf.arg0() as _, tf.arg1() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::pause => sys_pause(),
+        Sysno::rt_sigtimedwait => sys_rt_sigtimedwait(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::sigaltstack => sys_sigaltstack(tf.arg0() as _, tf.arg1() as _),
+        Sysno::ptrace => sys_ptrace(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::prlimit64 => sys_prlimit64(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::getrlimit => sys_getrlimit(tf.arg0() as _, tf.arg1() as _),
+        Sysno::setrlimit => sys_setrlimit(tf.arg0() as _, tf.arg1() as _),
+        Sysno::kill => sys_kill(tf.arg0() as _, tf.arg1() as _),
+        Sysno::tkill => sys_tkill(tf.arg0() as _, tf.arg1() as _),
+        Sysno::tgkill => sys_tgkill(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::rt_sigqueueinfo => {
+            sys_rt_sigqueueinfo(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)
+        }
         Sysno::clock_gettime => sys_clock_gettime(tf.arg0() as _, tf.arg1() as _) as _,
         Sysno::gettimeofday => sys_gettimeofday(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::clock_settime => sys_clock_settime(tf.arg0() as _, tf.arg1() as _),
+        Sysno::settimeofday => sys_settimeofday(tf.arg0() as _, tf.arg1() as _),
+        Sysno::adjtimex => sys_adjtimex(tf.arg0() as _),
+        Sysno::clock_adjtime => sys_clock_adjtime(tf.arg0() as _, tf.arg1() as _),
+        Sysno::timer_create => sys_timer_create(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::timer_settime => sys_timer_settime(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::timer_gettime => sys_timer_gettime(tf.arg0() as _, tf.arg1() as _),
+        Sysno::timer_getoverrun => sys_timer_getoverrun(tf.arg0() as _),
+        Sysno::timer_delete => sys_timer_delete(tf.arg0() as _),
         Sysno::exit_group => sys_exit_group(tf.arg0() as _),
         Sysno::uname => sys_uname(tf.arg0() as _) as _,
+        Sysno::sethostname => sys_sethostname(tf.arg0() as _, tf.arg1() as _),
+        Sysno::setdomainname => sys_setdomainname(tf.arg0() as _, tf.arg1() as _),
+        Sysno::sysinfo => sys_sysinfo(tf.arg0() as _),
+        Sysno::syslog => sys_syslog(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::reboot => sys_reboot(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
         _ => {
             warn!("Unimplemented syscall: {}", syscall_num);
-            axtask::exit(LinuxError::ENOSYS as _)
+            crate::task::do_exit(LinuxError::ENOSYS as _)
         }
     }
 }
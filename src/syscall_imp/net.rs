@@ -0,0 +1,419 @@
+//! 套接字相关的系统调用
+//!
+//! 套接字由 `arceos_posix_api` 实现：AF_INET 套接字基于网络协议栈，`socketpair` 创建的
+//! AF_UNIX 流套接字对直接在内核中传递数据。这里负责在用户内存和内核之间复制参数，包括
+//! `sendmsg`/`recvmsg` 的 iovec 和控制消息。用 `SCM_RIGHTS` 传递的文件描述符在发送时
+//! 取出对应的文件，接收时加入接收者的文件描述符表。
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::ffi::c_void;
+use core::mem::size_of;
+
+use arceos_posix_api::{self as api, ctypes, FileLike};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, TaskExtRef};
+use memory_addr::VirtAddr;
+
+use crate::mm::{copy_from_user, copy_to_user, read_user, write_user};
+use crate::syscall_body;
+use crate::task::signal::{SigInfo, SIGPIPE};
+
+/// 一次 `sendmsg`/`recvmsg` 最多的 iovec 数量
+const UIO_MAXIOV: usize = 1024;
+/// 一条 `SCM_RIGHTS` 控制消息最多传递的文件描述符数量
+const SCM_MAX_FD: usize = 253;
+/// `sendmsg` 控制消息缓冲区的最大长度
+const MAX_CONTROL_LEN: usize = 64 * 1024;
+/// 选项值的最大长度，更长的部分被忽略
+const MAX_OPTION_LEN: usize = 64;
+/// `recvfrom` 一次最多接收的字节数，流套接字上更大的缓冲区只是得到较短的读取结果
+const MAX_RECV_LEN: usize = 64 * 1024;
+
+/// `sendmsg` 和 `recvmsg` 的消息头，与内核的 `struct msghdr` 布局一致
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MsgHdr {
+    msg_name: usize,
+    msg_namelen: u32,
+    msg_iov: usize,
+    msg_iovlen: usize,
+    msg_control: usize,
+    msg_controllen: usize,
+    msg_flags: i32,
+}
+
+/// 控制消息头，其后是按 `usize` 对齐的数据
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CmsgHdr {
+    cmsg_len: usize,
+    cmsg_level: i32,
+    cmsg_type: i32,
+}
+
+/// 控制消息头的长度，即 `CMSG_LEN(0)`
+const CMSG_HDR_LEN: usize = size_of::<CmsgHdr>();
+
+/// 控制消息按 `usize` 对齐后的长度，即 `CMSG_ALIGN`
+const fn cmsg_align(len: usize) -> usize {
+    (len + size_of::<usize>() - 1) & !(size_of::<usize>() - 1)
+}
+
+/// 按 `SOCK_NONBLOCK` 和 `SOCK_CLOEXEC` 设置新文件描述符 `fd`
+fn apply_sock_flags(fd: i32, flags: u32) -> LinuxResult {
+    if flags & ctypes::SOCK_NONBLOCK != 0 {
+        let file = api::get_file_like(fd)?;
+        file.status_flags().set(ctypes::O_NONBLOCK);
+        file.set_nonblocking(true)?;
+    }
+    if flags & ctypes::SOCK_CLOEXEC != 0 {
+        api::set_cloexec(fd, true);
+    }
+    Ok(())
+}
+
+/// 从套接字 `fd` 发送数据
+///
+/// 对端不再接收时返回 `EPIPE`，除非 `flags` 带 `MSG_NOSIGNAL`，同时向当前线程发送 `SIGPIPE`。
+fn send(fd: i32, buf: &[u8], rights: Vec<Arc<dyn FileLike>>, flags: u32) -> LinuxResult<usize> {
+    let res = api::socket_from_fd(fd)?.send_msg(buf, rights, flags);
+    if matches!(res, Err(LinuxError::EPIPE)) && flags & api::MSG_NOSIGNAL == 0 {
+        current().task_ext().send_signal(SigInfo::kernel(SIGPIPE));
+    }
+    res
+}
+
+/// 读取消息头中的 iovec 数组
+fn read_iovecs(msg: &MsgHdr) -> LinuxResult<Vec<ctypes::iovec>> {
+    if msg.msg_iovlen > UIO_MAXIOV {
+        return Err(LinuxError::EMSGSIZE);
+    }
+    let iov = msg.msg_iov as *const ctypes::iovec;
+    (0..msg.msg_iovlen)
+        .map(|i| read_user(iov.wrapping_add(i)).map_err(|_| LinuxError::EFAULT))
+        .collect()
+}
+
+/// 取出 `sendmsg` 的控制消息中用 `SCM_RIGHTS` 传递的文件，只支持这一种控制消息
+fn read_rights(msg: &MsgHdr) -> LinuxResult<Vec<Arc<dyn FileLike>>> {
+    let mut rights = Vec::new();
+    if msg.msg_controllen == 0 {
+        return Ok(rights);
+    }
+    if msg.msg_controllen > MAX_CONTROL_LEN {
+        return Err(LinuxError::ENOBUFS);
+    }
+    let mut control = vec![0u8; msg.msg_controllen];
+    copy_from_user(VirtAddr::from(msg.msg_control), &mut control)
+        .map_err(|_| LinuxError::EFAULT)?;
+    let mut offset = 0;
+    while offset + CMSG_HDR_LEN <= control.len() {
+        let hdr = unsafe {
+            control
+                .as_ptr()
+                .add(offset)
+                .cast::<CmsgHdr>()
+                .read_unaligned()
+        };
+        if hdr.cmsg_len < CMSG_HDR_LEN || hdr.cmsg_len > control.len() - offset {
+            return Err(LinuxError::EINVAL);
+        }
+        if hdr.cmsg_level != api::SOL_SOCKET || hdr.cmsg_type != api::SCM_RIGHTS {
+            return Err(LinuxError::EINVAL);
+        }
+        let data = &control[offset + CMSG_HDR_LEN..offset + hdr.cmsg_len];
+        if rights.len() + data.len() / 4 > SCM_MAX_FD {
+            return Err(LinuxError::EINVAL);
+        }
+        for fd in data.chunks_exact(4) {
+            let fd = i32::from_ne_bytes(fd.try_into().unwrap());
+            rights.push(api::get_file_like(fd)?);
+        }
+        offset += cmsg_align(hdr.cmsg_len);
+    }
+    Ok(rights)
+}
+
+/// 把收到的文件加入文件描述符表，并写入 `recvmsg` 的控制消息缓冲区
+///
+/// 返回写入控制消息的长度，以及是否因缓冲区不足或文件描述符用尽而丢弃了一些文件。
+/// 与 Linux 一致，丢弃的文件被关闭。
+fn put_rights(msg: &MsgHdr, rights: Vec<Arc<dyn FileLike>>, cloexec: bool) -> (usize, bool) {
+    if rights.is_empty() {
+        return (0, false);
+    }
+    let room = msg.msg_controllen.saturating_sub(CMSG_HDR_LEN) / size_of::<i32>();
+    let mut fds = Vec::new();
+    for file in rights.iter().take(room) {
+        let Ok(fd) = api::add_file_like(file.clone()) else {
+            break;
+        };
+        if cloexec {
+            api::set_cloexec(fd, true);
+        }
+        fds.push(fd);
+    }
+    if fds.is_empty() {
+        return (0, true);
+    }
+    let hdr = CmsgHdr {
+        cmsg_len: CMSG_HDR_LEN + fds.len() * size_of::<i32>(),
+        cmsg_level: api::SOL_SOCKET,
+        cmsg_type: api::SCM_RIGHTS,
+    };
+    let mut control = Vec::with_capacity(hdr.cmsg_len);
+    control.extend_from_slice(unsafe {
+        core::slice::from_raw_parts((&hdr as *const CmsgHdr).cast::<u8>(), CMSG_HDR_LEN)
+    });
+    for fd in &fds {
+        control.extend_from_slice(&fd.to_ne_bytes());
+    }
+    if copy_to_user(VirtAddr::from(msg.msg_control), &control).is_err() {
+        // 文件描述符没能交给用户，不能留在表中
+        for fd in fds {
+            api::sys_close(fd);
+        }
+        return (0, true);
+    }
+    let used = cmsg_align(hdr.cmsg_len).min(msg.msg_controllen);
+    (used, fds.len() < rights.len())
+}
+
+/// 创建套接字，`socktype` 可以带 `SOCK_NONBLOCK` 和 `SOCK_CLOEXEC`
+pub(crate) fn sys_socket(domain: i32, socktype: i32, protocol: i32) -> isize {
+    syscall_body!(sys_socket, {
+        let flags = socktype as u32 & (ctypes::SOCK_NONBLOCK | ctypes::SOCK_CLOEXEC);
+        let fd = api::sys_socket(domain, socktype & !flags as i32, protocol);
+        if fd < 0 {
+            return Ok(fd as isize);
+        }
+        apply_sock_flags(fd, flags)?;
+        Ok(fd as isize)
+    })
+}
+
+/// 创建一对相连的套接字，目前只支持 AF_UNIX 流套接字
+pub(crate) fn sys_socketpair(
+    domain: i32,
+    socktype: i32,
+    protocol: i32,
+    sv: *mut [i32; 2],
+) -> isize {
+    syscall_body!(sys_socketpair, {
+        let mut fds = [0; 2];
+        let ret = api::sys_socketpair(domain, socktype, protocol, &mut fds);
+        if ret < 0 {
+            return Ok(ret as isize);
+        }
+        write_user(sv, &fds).map_err(|_| {
+            api::sys_close(fds[0]);
+            api::sys_close(fds[1]);
+            LinuxError::EFAULT
+        })?;
+        Ok(0)
+    })
+}
+
+pub(crate) fn sys_bind(fd: i32, addr: *const ctypes::sockaddr, addrlen: u32) -> isize {
+    api::sys_bind(fd, addr, addrlen) as _
+}
+
+pub(crate) fn sys_connect(fd: i32, addr: *const ctypes::sockaddr, addrlen: u32) -> isize {
+    api::sys_connect(fd, addr, addrlen) as _
+}
+
+pub(crate) fn sys_listen(fd: i32, backlog: i32) -> isize {
+    api::sys_listen(fd, backlog) as _
+}
+
+/// 接受连接，`flags` 可以带 `SOCK_NONBLOCK` 和 `SOCK_CLOEXEC`
+pub(crate) fn sys_accept4(
+    fd: i32,
+    addr: *mut ctypes::sockaddr,
+    addrlen: *mut u32,
+    flags: u32,
+) -> isize {
+    syscall_body!(sys_accept4, {
+        if flags & !(ctypes::SOCK_NONBLOCK | ctypes::SOCK_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let new_fd = unsafe { api::sys_accept(fd, addr, addrlen) };
+        if new_fd < 0 {
+            return Ok(new_fd as isize);
+        }
+        apply_sock_flags(new_fd, flags)?;
+        Ok(new_fd as isize)
+    })
+}
+
+pub(crate) fn sys_getsockname(fd: i32, addr: *mut ctypes::sockaddr, addrlen: *mut u32) -> isize {
+    unsafe { api::sys_getsockname(fd, addr, addrlen) as _ }
+}
+
+pub(crate) fn sys_getpeername(fd: i32, addr: *mut ctypes::sockaddr, addrlen: *mut u32) -> isize {
+    unsafe { api::sys_getpeername(fd, addr, addrlen) as _ }
+}
+
+/// 发送数据，支持 `MSG_DONTWAIT` 和 `MSG_NOSIGNAL`
+///
+/// 流套接字已经连接，不能再指定地址，此时返回 `EISCONN`。
+pub(crate) fn sys_sendto(
+    fd: i32,
+    buf: *const c_void,
+    len: usize,
+    flags: u32,
+    addr: *const ctypes::sockaddr,
+    addrlen: u32,
+) -> isize {
+    syscall_body!(sys_sendto, {
+        if addr.is_null() {
+            let mut data = vec![0u8; len];
+            copy_from_user(VirtAddr::from_ptr_of(buf), &mut data)
+                .map_err(|_| LinuxError::EFAULT)?;
+            return Ok(send(fd, &data, Vec::new(), flags)? as isize);
+        }
+        if api::socket_from_fd(fd)?.socket_type() == ctypes::SOCK_STREAM {
+            return Err(LinuxError::EISCONN);
+        }
+        Ok(api::sys_sendto(fd, buf, len, flags as _, addr, addrlen) as isize)
+    })
+}
+
+/// 接收数据，支持 `MSG_PEEK` 和 `MSG_DONTWAIT`
+///
+/// `socketpair` 创建的套接字没有地址，`addrlen` 被置为 0。
+pub(crate) fn sys_recvfrom(
+    fd: i32,
+    buf: *mut c_void,
+    len: usize,
+    flags: u32,
+    addr: *mut ctypes::sockaddr,
+    addrlen: *mut u32,
+) -> isize {
+    syscall_body!(sys_recvfrom, {
+        let socket = api::socket_from_fd(fd)?;
+        if !addr.is_null() && socket.socket_type() != ctypes::SOCK_STREAM {
+            return Ok(
+                unsafe { api::sys_recvfrom(fd, buf, len, flags as _, addr, addrlen) } as isize,
+            );
+        }
+        let mut data = vec![0u8; len.min(MAX_RECV_LEN)];
+        let (n, _) = socket.recv_msg(&mut data, flags)?;
+        copy_to_user(VirtAddr::from_mut_ptr_of(buf), &data[..n]).map_err(|_| LinuxError::EFAULT)?;
+        if !addr.is_null() && socket.clone().into_any().is::<api::UnixSocket>() {
+            write_user(addrlen, &0).map_err(|_| LinuxError::EFAULT)?;
+        }
+        Ok(n as isize)
+    })
+}
+
+/// 发送消息，数据来自 `msg` 的 iovec，控制消息只支持 `SCM_RIGHTS`
+pub(crate) fn sys_sendmsg(fd: i32, msg: *const MsgHdr, flags: u32) -> isize {
+    syscall_body!(sys_sendmsg, {
+        let msg = read_user(msg).map_err(|_| LinuxError::EFAULT)?;
+        let socket = api::socket_from_fd(fd)?;
+        if msg.msg_name != 0 && socket.socket_type() == ctypes::SOCK_STREAM {
+            return Err(LinuxError::EISCONN);
+        }
+        let mut data = Vec::new();
+        for iov in read_iovecs(&msg)? {
+            let start = data.len();
+            data.resize(start + iov.iov_len, 0);
+            copy_from_user(VirtAddr::from_ptr_of(iov.iov_base), &mut data[start..])
+                .map_err(|_| LinuxError::EFAULT)?;
+        }
+        if msg.msg_name != 0 {
+            let ret = api::sys_sendto(
+                fd,
+                data.as_ptr().cast(),
+                data.len(),
+                flags as _,
+                msg.msg_name as _,
+                msg.msg_namelen,
+            );
+            return Ok(ret as isize);
+        }
+        let rights = read_rights(&msg)?;
+        Ok(send(fd, &data, rights, flags)? as isize)
+    })
+}
+
+/// 接收消息，数据写入 `msg` 的 iovec
+///
+/// 收到的文件加入文件描述符表，以 `SCM_RIGHTS` 控制消息返回，带 `MSG_CMSG_CLOEXEC` 时设置
+/// close-on-exec 标志；控制消息缓冲区放不下时设置 `MSG_CTRUNC`。不返回发送者的地址。
+pub(crate) fn sys_recvmsg(fd: i32, msg: *mut MsgHdr, flags: u32) -> isize {
+    syscall_body!(sys_recvmsg, {
+        let mut hdr = read_user(msg).map_err(|_| LinuxError::EFAULT)?;
+        let iovecs = read_iovecs(&hdr)?;
+        let mut data = vec![0u8; iovecs.iter().map(|iov| iov.iov_len).sum()];
+        let socket = api::socket_from_fd(fd)?;
+        let (n, rights) = socket.recv_msg(&mut data, flags & !api::MSG_CMSG_CLOEXEC)?;
+
+        let mut copied = 0;
+        for iov in iovecs {
+            if copied == n {
+                break;
+            }
+            let len = iov.iov_len.min(n - copied);
+            copy_to_user(
+                VirtAddr::from_ptr_of(iov.iov_base),
+                &data[copied..copied + len],
+            )
+            .map_err(|_| LinuxError::EFAULT)?;
+            copied += len;
+        }
+
+        let cloexec = flags & api::MSG_CMSG_CLOEXEC != 0;
+        let (controllen, truncated) = put_rights(&hdr, rights, cloexec);
+        hdr.msg_namelen = 0;
+        hdr.msg_controllen = controllen;
+        hdr.msg_flags = if truncated { api::MSG_CTRUNC as i32 } else { 0 };
+        write_user(msg, &hdr).map_err(|_| LinuxError::EFAULT)?;
+        Ok(n as isize)
+    })
+}
+
+/// 设置套接字选项，见 [`api::setsockopt`]
+pub(crate) fn sys_setsockopt(
+    fd: i32,
+    level: i32,
+    name: i32,
+    optval: *const u8,
+    optlen: u32,
+) -> isize {
+    syscall_body!(sys_setsockopt, {
+        let mut value = vec![0u8; (optlen as usize).min(MAX_OPTION_LEN)];
+        copy_from_user(VirtAddr::from_ptr_of(optval), &mut value)
+            .map_err(|_| LinuxError::EFAULT)?;
+        api::setsockopt(fd, level, name, &value)?;
+        Ok(0)
+    })
+}
+
+/// 获取套接字选项，值超过 `optlen` 的部分被截断，`optlen` 被置为写入的长度
+pub(crate) fn sys_getsockopt(
+    fd: i32,
+    level: i32,
+    name: i32,
+    optval: *mut u8,
+    optlen: *mut u32,
+) -> isize {
+    syscall_body!(sys_getsockopt, {
+        let len = read_user(optlen).map_err(|_| LinuxError::EFAULT)?;
+        if (len as i32) < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let value = api::getsockopt(fd, level, name)?;
+        let len = value.len().min(len as usize);
+        copy_to_user(VirtAddr::from_mut_ptr_of(optval), &value[..len])
+            .map_err(|_| LinuxError::EFAULT)?;
+        write_user(optlen, &(len as u32)).map_err(|_| LinuxError::EFAULT)?;
+        Ok(0)
+    })
+}
+
+/// 关闭套接字的接收、发送或两个方向
+pub(crate) fn sys_shutdown(fd: i32, how: i32) -> isize {
+    api::sys_shutdown(fd, how) as _
+}
@@ -0,0 +1,348 @@
+use core::time::Duration;
+
+use arceos_posix_api::ctypes::timespec;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, AxTaskRef, TaskExtRef};
+
+use crate::{
+    mm::{read_user, write_user},
+    syscall_body,
+    task::{
+        all_tasks, find_task_by_pid,
+        signal::{
+            current_trap_frame, SigAction, SigInfo, SigSet, SignalStack, WaitOutcome, MINSIGSTKSZ,
+            NSIG, SIGKILL, SIGSTOP, SI_TKILL, SI_USER, SS_DISABLE, SS_ONSTACK,
+        },
+        Pid,
+    },
+};
+
+/// 将信号加入掩码
+const SIG_BLOCK: i32 = 0;
+/// 将信号从掩码中移除
+const SIG_UNBLOCK: i32 = 1;
+/// 直接设置掩码
+const SIG_SETMASK: i32 = 2;
+
+/// 检查用户传入的信号集合大小，目前只支持 64 个信号
+fn check_sigsetsize(sigsetsize: usize) -> LinuxResult {
+    if sigsetsize != core::mem::size_of::<SigSet>() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(())
+}
+
+/// 检查信号编号，0 只用于检查目标进程是否存在
+fn check_signo(signo: i32) -> LinuxResult<usize> {
+    if signo < 0 || signo as usize > NSIG {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(signo as usize)
+}
+
+/// 设置或获取信号的处理方式
+pub(crate) fn sys_rt_sigaction(
+    signo: i32,
+    act: *const SigAction,
+    oldact: *mut SigAction,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_rt_sigaction, {
+        check_sigsetsize(sigsetsize)?;
+        let signo = check_signo(signo)?;
+        if signo == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let mut actions = curr.task_ext().signal_actions.lock();
+        if !oldact.is_null() {
+            write_user(oldact, &actions.get(signo))?;
+        }
+        if !act.is_null() {
+            if signo == SIGKILL || signo == SIGSTOP {
+                return Err(LinuxError::EINVAL);
+            }
+            let mut action = read_user(act)?;
+            action.mask = action.mask.blockable();
+            actions.set(signo, action);
+        }
+        Ok(0)
+    })
+}
+
+/// 修改或获取当前任务的信号掩码
+pub(crate) fn sys_rt_sigprocmask(
+    how: i32,
+    set: *const SigSet,
+    oldset: *mut SigSet,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_rt_sigprocmask, {
+        check_sigsetsize(sigsetsize)?;
+        let set = if set.is_null() {
+            None
+        } else {
+            Some(read_user(set)?)
+        };
+        let curr = current();
+        let mut state = curr.task_ext().signal.lock();
+        let old = state.blocked;
+        if let Some(set) = set {
+            state.blocked = match how {
+                SIG_BLOCK => SigSet(old.0 | set.0),
+                SIG_UNBLOCK => SigSet(old.0 & !set.0),
+                SIG_SETMASK => set,
+                _ => return Err(LinuxError::EINVAL),
+            }
+            .blockable();
+        }
+        drop(state);
+        if !oldset.is_null() {
+            write_user(oldset, &old)?;
+        }
+        Ok(0)
+    })
+}
+
+/// 从信号处理函数返回
+///
+/// 真正的恢复工作在返回用户态前完成，因为系统调用的返回值会覆盖用户态的寄存器。
+pub(crate) fn sys_rt_sigreturn() -> isize {
+    current().task_ext().signal.lock().request_sigreturn();
+    0
+}
+
+/// 设置或获取备用信号栈
+///
+/// 与 Linux 一致，栈小于 [`MINSIGSTKSZ`] 时返回 ENOMEM，正在备用信号栈上执行时不能修改它。
+pub(crate) fn sys_sigaltstack(ss: *const SignalStack, old_ss: *mut SignalStack) -> isize {
+    syscall_body!(sys_sigaltstack, {
+        let new = if ss.is_null() {
+            None
+        } else {
+            Some(read_user(ss)?)
+        };
+        let curr = current();
+        let sp = current_trap_frame().get_sp();
+        let mut state = curr.task_ext().signal.lock();
+        let old = state.altstack.at(sp);
+        if let Some(mut stack) = new {
+            if old.flags & SS_ONSTACK != 0 {
+                return Err(LinuxError::EPERM);
+            }
+            // 为兼容旧程序，SS_ONSTACK 被视为 0
+            match stack.flags {
+                0 | SS_ONSTACK => {
+                    if stack.size < MINSIGSTKSZ {
+                        return Err(LinuxError::ENOMEM);
+                    }
+                    stack.flags = 0;
+                }
+                SS_DISABLE => stack = SignalStack::default(),
+                _ => return Err(LinuxError::EINVAL),
+            }
+            state.altstack = stack;
+        }
+        drop(state);
+        if !old_ss.is_null() {
+            write_user(old_ss, &old)?;
+        }
+        Ok(0)
+    })
+}
+
+/// 获取被阻塞而未能递送的待处理信号
+pub(crate) fn sys_rt_sigpending(set: *mut SigSet, sigsetsize: usize) -> isize {
+    syscall_body!(sys_rt_sigpending, {
+        check_sigsetsize(sigsetsize)?;
+        let curr = current();
+        let pending = {
+            let state = curr.task_ext().signal.lock();
+            SigSet(state.pending().0 & state.blocked.0)
+        };
+        write_user(set, &pending)?;
+        Ok(0)
+    })
+}
+
+/// 临时将信号掩码替换为 `mask` 并睡眠，直到有信号被递送
+///
+/// 原先的掩码在信号处理函数返回后恢复，总是返回 EINTR。
+pub(crate) fn sys_rt_sigsuspend(mask: *const SigSet, sigsetsize: usize) -> isize {
+    syscall_body!(sys_rt_sigsuspend, {
+        check_sigsetsize(sigsetsize)?;
+        let mask = read_user(mask)?;
+        let curr = current();
+        let ext = curr.task_ext();
+        // 先替换掩码再睡眠；之后到达的信号会唤醒本任务，不会丢失
+        ext.signal.lock().suspend_mask(mask);
+        ext.wait_signal(None, |_| false);
+        Err::<isize, _>(LinuxError::EINTR)
+    })
+}
+
+/// 睡眠直到有信号被递送，总是返回 EINTR
+///
+/// 信号的处理方式为终止进程时不会返回。
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_pause() -> isize {
+    syscall_body!(sys_pause, {
+        current().task_ext().wait_signal(None, |_| false);
+        Err::<isize, _>(LinuxError::EINTR)
+    })
+}
+
+/// 同步地等待 `set` 中的信号，取出后不执行其处理函数，返回信号编号
+///
+/// `timeout` 为空时一直等待，超时返回 EAGAIN；期间有其他信号被递送时返回 EINTR。
+pub(crate) fn sys_rt_sigtimedwait(
+    set: *const SigSet,
+    info: *mut SigInfo,
+    timeout: *const timespec,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_rt_sigtimedwait, {
+        check_sigsetsize(sigsetsize)?;
+        let set = read_user(set)?.blockable();
+        let timeout = if timeout.is_null() {
+            None
+        } else {
+            let ts = read_user(timeout)?;
+            if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+        };
+
+        let curr = current();
+        let ext = curr.task_ext();
+        let mut received = ext.signal.lock().dequeue_from(set);
+        if received.is_none() && timeout != Some(Duration::ZERO) {
+            let outcome = ext.wait_signal(timeout, |state| state.pending().0 & set.0 != 0);
+            received = ext.signal.lock().dequeue_from(set);
+            if received.is_none() && outcome == WaitOutcome::Interrupted {
+                return Err(LinuxError::EINTR);
+            }
+        }
+        let received = received.ok_or(LinuxError::EAGAIN)?;
+        if !info.is_null() {
+            write_user(info, &received)?;
+        }
+        Ok(received.signo as isize)
+    })
+}
+
+/// 查找仍在运行的任务
+fn find_live_task(pid: Pid) -> LinuxResult<AxTaskRef> {
+    match find_task_by_pid(pid) {
+        Some(task) if task.state() != axtask::TaskState::Exited => Ok(task),
+        _ => Err(LinuxError::ESRCH),
+    }
+}
+
+/// 向任务发送信号，`signo` 为 0 时只检查任务是否存在以及能否向它发送信号
+fn send_to(task: &AxTaskRef, signo: usize, code: i32) -> LinuxResult {
+    let curr = current();
+    if !curr.task_ext().may_signal(task.task_ext()) {
+        return Err(LinuxError::EPERM);
+    }
+    if signo != 0 {
+        let sender = curr.task_ext().proc_id;
+        task.task_ext()
+            .send_signal(SigInfo::user(signo, code, sender));
+    }
+    Ok(())
+}
+
+/// 向调用者可以发送信号的所有进程发送信号，init 进程和调用者自身除外
+///
+/// 只要发送给了一个进程就成功；否则若有进程因权限不足被跳过则返回 EPERM，
+/// 没有其他进程时返回 ESRCH。
+fn kill_all(signo: usize) -> LinuxResult<isize> {
+    let curr = current();
+    let mut result = Err(LinuxError::ESRCH);
+    for task in all_tasks() {
+        let ext = task.task_ext();
+        if ext.proc_id == curr.task_ext().proc_id || ext.is_init() {
+            continue;
+        }
+        match send_to(&task, signo, SI_USER) {
+            Ok(()) => result = Ok(0),
+            Err(err) if result.is_err() => result = Err(err),
+            Err(_) => {}
+        }
+    }
+    result
+}
+
+/// 向进程发送信号
+///
+/// 目前没有进程组，`pid` 为 0 时只向调用者自身发送，为 -1 时广播给所有进程。
+pub(crate) fn sys_kill(pid: i32, signo: i32) -> isize {
+    syscall_body!(sys_kill, {
+        let signo = check_signo(signo)?;
+        let task = match pid {
+            0 => current().as_task_ref().clone(),
+            -1 => return kill_all(signo),
+            pid if pid > 0 => find_live_task(pid as Pid)?,
+            _ => {
+                warn!("Sending signals to process groups is not supported");
+                return Err(LinuxError::ESRCH);
+            }
+        };
+        send_to(&task, signo, SI_USER)?;
+        Ok(0)
+    })
+}
+
+/// 向线程发送信号
+pub(crate) fn sys_tkill(tid: i32, signo: i32) -> isize {
+    syscall_body!(sys_tkill, {
+        let signo = check_signo(signo)?;
+        if tid <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        send_to(&find_live_task(tid as Pid)?, signo, SI_TKILL)?;
+        Ok(0)
+    })
+}
+
+/// 向进程 `tgid` 发送带有附加信息 `info` 的信号，即 `sigqueue`
+///
+/// 与 Linux 一致，向其他进程发送时不能冒充内核或 `kill`，即 `si_code` 不能为非负数或
+/// `SI_TKILL`。
+pub(crate) fn sys_rt_sigqueueinfo(tgid: i32, signo: i32, info: *const SigInfo) -> isize {
+    syscall_body!(sys_rt_sigqueueinfo, {
+        let signo = check_signo(signo)?;
+        let mut info = read_user(info)?;
+        let task = find_live_task(tgid as Pid)?;
+        let curr = current();
+        let forged = (info.code >= 0 || info.code == SI_TKILL)
+            && task.task_ext().proc_id != curr.task_ext().proc_id;
+        if forged || !curr.task_ext().may_signal(task.task_ext()) {
+            return Err(LinuxError::EPERM);
+        }
+        if signo != 0 {
+            info.signo = signo as i32;
+            task.task_ext().send_signal(info);
+        }
+        Ok(0)
+    })
+}
+
+/// 向线程组 `tgid` 中的线程 `tid` 发送信号
+///
+/// 目前每个进程只有一个线程，因此要求 `tid` 与 `tgid` 相同。
+pub(crate) fn sys_tgkill(tgid: i32, tid: i32, signo: i32) -> isize {
+    syscall_body!(sys_tgkill, {
+        let signo = check_signo(signo)?;
+        if tgid <= 0 || tid <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if tgid != tid {
+            return Err(LinuxError::ESRCH);
+        }
+        send_to(&find_live_task(tid as Pid)?, signo, SI_TKILL)?;
+        Ok(0)
+    })
+}
@@ -0,0 +1,153 @@
+//! 系统调用的次数和耗时统计
+//!
+//! 统计默认关闭，可由内核命令行参数 `syscallstats=on` 在启动时打开，也可以在运行时向
+//! `/proc/syscalls` 写入 `1` 或 `0` 打开或关闭，写入 `reset` 清零。关闭时分发系统调用
+//! 只多一次对全局标志的判断。
+//!
+//! 每个 CPU 有自己的统计数组，记录每个系统调用的次数、总耗时和耗时的分布，读取
+//! `/proc/syscalls` 时汇总各 CPU 的数据。耗时按硬件计数器测量，包括在系统调用中阻塞的
+//! 时间；不返回的系统调用（如 `exit`）不计入。关机时若统计仍打开，汇总结果会输出到内核日志。
+
+use alloc::{format, string::String};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use arceos_posix_api::config::SMP;
+use axerrno::AxError;
+use axfs::ProcFileWrite;
+use axhal::{
+    cpu::this_cpu_id,
+    time::{current_ticks, ticks_to_nanos},
+};
+
+/// 统计的系统调用号的上限，不小于各架构上最大的系统调用号
+const NR_SYSCALLS: usize = 512;
+
+/// 耗时分布的桶数，除最后一个外，第 `i` 个桶记录耗时小于 `4^i` 微秒的调用
+const BUCKETS: usize = 8;
+
+/// 各个桶在 `/proc/syscalls` 中的列名
+const BUCKET_NAMES: [&str; BUCKETS] = [
+    "<1us", "<4us", "<16us", "<64us", "<256us", "<1ms", "<4ms", ">=4ms",
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 一个 CPU 上的统计
+struct CpuStats {
+    count: [AtomicU64; NR_SYSCALLS],
+    ticks: [AtomicU64; NR_SYSCALLS],
+    histogram: [[AtomicU64; BUCKETS]; NR_SYSCALLS],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+impl CpuStats {
+    const ZERO: AtomicU64 = AtomicU64::new(0);
+    const ZERO_BUCKETS: [AtomicU64; BUCKETS] = [Self::ZERO; BUCKETS];
+    const NEW: Self = Self {
+        count: [Self::ZERO; NR_SYSCALLS],
+        ticks: [Self::ZERO; NR_SYSCALLS],
+        histogram: [Self::ZERO_BUCKETS; NR_SYSCALLS],
+    };
+}
+
+static STATS: [CpuStats; SMP] = [CpuStats::NEW; SMP];
+
+/// 统计是否打开
+#[inline(always)]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 执行系统调用 `f` 并记录其次数和耗时，调用前应先检查 [`enabled`]
+pub fn record(syscall_num: usize, f: impl FnOnce() -> isize) -> isize {
+    let start = current_ticks();
+    let ret = f();
+    let ticks = current_ticks().saturating_sub(start);
+    if syscall_num < NR_SYSCALLS {
+        let micros = ticks_to_nanos(ticks) / 1000;
+        let bucket = (0..BUCKETS - 1)
+            .find(|&i| micros < 1 << (2 * i))
+            .unwrap_or(BUCKETS - 1);
+        // 任务可能已迁移到其他 CPU，计入当前所在的 CPU 即可
+        let stats = &STATS[this_cpu_id()];
+        stats.count[syscall_num].fetch_add(1, Ordering::Relaxed);
+        stats.ticks[syscall_num].fetch_add(ticks, Ordering::Relaxed);
+        stats.histogram[syscall_num][bucket].fetch_add(1, Ordering::Relaxed);
+    }
+    ret
+}
+
+/// 汇总各 CPU 上的同一个计数器
+fn total(counter: impl Fn(&CpuStats) -> &AtomicU64) -> u64 {
+    STATS
+        .iter()
+        .map(|stats| counter(stats).load(Ordering::Relaxed))
+        .sum()
+}
+
+/// 生成 `/proc/syscalls` 的内容
+///
+/// 第一行为表头，之后每行是一个调用过的系统调用：名称、次数、总耗时（纳秒）和各个桶中的次数。
+fn render() -> String {
+    let mut out = format!("{:<24}{:>12}{:>16}", "# syscall", "count", "total_ns");
+    for name in BUCKET_NAMES {
+        out += &format!("{:>10}", name);
+    }
+    out.push('\n');
+    for num in 0..NR_SYSCALLS {
+        let count = total(|stats| &stats.count[num]);
+        if count == 0 {
+            continue;
+        }
+        let name = super::syscall_name(num).map_or_else(|| format!("syscall_{}", num), Into::into);
+        let nanos = ticks_to_nanos(total(|stats| &stats.ticks[num]));
+        out += &format!("{:<24}{:>12}{:>16}", name, count, nanos);
+        for bucket in 0..BUCKETS {
+            out += &format!("{:>10}", total(|stats| &stats.histogram[num][bucket]));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// 清零所有 CPU 上的统计
+fn reset() {
+    for stats in &STATS {
+        let counters = stats.count.iter().chain(&stats.ticks);
+        for counter in counters.chain(stats.histogram.iter().flatten()) {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 处理对 `/proc/syscalls` 的写入
+fn write(buf: &[u8]) -> Result<(), AxError> {
+    match core::str::from_utf8(buf).map(str::trim) {
+        Ok("1" | "on") => ENABLED.store(true, Ordering::Relaxed),
+        Ok("0" | "off") => ENABLED.store(false, Ordering::Relaxed),
+        Ok("reset") => reset(),
+        _ => return Err(AxError::InvalidInput),
+    }
+    Ok(())
+}
+
+/// 按命令行参数设置统计是否打开，并注册 `/proc/syscalls`
+pub(crate) fn init_syscall_stats() {
+    ENABLED.store(
+        crate::boot_args::boot_args().syscall_stats,
+        Ordering::Relaxed,
+    );
+    axfs::register_proc_file("syscalls", render, Some(write as ProcFileWrite));
+}
+
+/// 统计打开时将汇总结果输出到内核日志，在关机前调用
+pub(crate) fn dump() {
+    if !enabled() {
+        return;
+    }
+    // 统计是用户显式打开的，按 warn 级别输出，使其在默认的日志级别下也可见
+    warn!("System call statistics:");
+    for line in render().lines() {
+        warn!("{}", line);
+    }
+}
@@ -1,5 +1,52 @@
+use alloc::{string::String, vec};
+
+use arceos_posix_api as api;
+use axerrno::{AxError, AxResult, LinuxError, LinuxResult};
+use axfs::{FnTunable, IntTunable};
+use axsync::spin::SpinNoIrq;
+
+use axtask::{current, TaskExtRef};
+use memory_addr::{VirtAddr, PAGE_SIZE_4K};
+
+use super::mm::{OVERCOMMIT_MEMORY, OVERCOMMIT_RANGE};
+use crate::{
+    config,
+    mm::{copy_from_user, copy_to_user, write_user},
+    syscall_body,
+    task::{all_pids, CAP_SYS_ADMIN, CAP_SYS_BOOT, PID_MAX, PID_MAX_RANGE},
+};
+
+/// 主机名的最大长度，与 Linux 中的 `__NEW_UTS_LEN` 一致
+const HOST_NAME_MAX: usize = 64;
+
+/// 当前的主机名，以 `\0` 结尾，初始值来自配置文件
+static HOSTNAME: SpinNoIrq<[u8; HOST_NAME_MAX + 1]> =
+    SpinNoIrq::new(UtsName::from_str(config::HOSTNAME));
+
+/// 当前的 NIS 域名，以 `\0` 结尾
+static DOMAINNAME: SpinNoIrq<[u8; HOST_NAME_MAX + 1]> = SpinNoIrq::new(UtsName::from_str(
+    "https://github.com/xingmin1/Starry-On-ArceOS/tree/oscomp",
+));
+
+/// 内核的发行编号
+const RELEASE: &str = "10.0.0";
+
+/// 硬件类型，与 Linux 下 `uname -m` 的输出一致
+const MACHINE: &str = if cfg!(target_arch = "x86_64") {
+    "x86_64"
+} else if cfg!(target_arch = "riscv64") {
+    "riscv64"
+} else if cfg!(target_arch = "aarch64") {
+    "aarch64"
+} else if cfg!(target_arch = "loongarch64") {
+    "loongarch64"
+} else {
+    "unknown"
+};
+
 /// sys_uname 中指定的结构体类型
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct UtsName {
     /// 系统名称
     pub sysname: [u8; 65],
@@ -19,26 +66,270 @@ impl Default for UtsName {
     fn default() -> Self {
         Self {
             sysname: Self::from_str("Starry"),
-            nodename: Self::from_str("Starry - machine[0]"),
-            release: Self::from_str("10.0.0"),
-            version: Self::from_str("10.0.0"),
-            machine: Self::from_str("RISC-V 64 on QEMU"),
-            domainname: Self::from_str("https://github.com/xingmin1/Starry-On-ArceOS/tree/oscomp"),
+            nodename: *HOSTNAME.lock(),
+            release: Self::from_str(RELEASE),
+            version: Self::from_str(RELEASE),
+            machine: Self::from_str(MACHINE),
+            domainname: *DOMAINNAME.lock(),
         }
     }
 }
 
 impl UtsName {
-    fn from_str(info: &str) -> [u8; 65] {
+    const fn from_str(info: &str) -> [u8; 65] {
         let mut data: [u8; 65] = [0; 65];
-        data[..info.len()].copy_from_slice(info.as_bytes());
+        let bytes = info.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            data[i] = bytes[i];
+            i += 1;
+        }
         data
     }
 }
 
-/// 获取系统信息
-pub fn sys_uname(name: *mut UtsName) -> i64 {
-    let utsname = unsafe { &mut *name };
-    *utsname = UtsName::default();
-    0
-}
\ No newline at end of file
+/// 获取系统信息，`name` 的地址无效时返回 -EFAULT
+pub fn sys_uname(name: *mut UtsName) -> isize {
+    syscall_body!(sys_uname, {
+        write_user(name, &UtsName::default()).map_err(|_| LinuxError::EFAULT)?;
+        Ok(0)
+    })
+}
+
+/// 将 `field` 设为 `name`，长度超过 [`HOST_NAME_MAX`] 时返回 `InvalidInput`
+fn set_name(field: &SpinNoIrq<[u8; HOST_NAME_MAX + 1]>, name: &[u8]) -> AxResult {
+    if name.len() > HOST_NAME_MAX {
+        return Err(AxError::InvalidInput);
+    }
+    let mut field = field.lock();
+    field.fill(0);
+    field[..name.len()].copy_from_slice(name);
+    Ok(())
+}
+
+/// 返回 `field` 中 `\0` 之前的部分
+fn name_of(field: &SpinNoIrq<[u8; HOST_NAME_MAX + 1]>) -> String {
+    let field = *field.lock();
+    let len = field.iter().position(|&c| c == 0).unwrap_or(HOST_NAME_MAX);
+    String::from_utf8_lossy(&field[..len]).into()
+}
+
+/// `sethostname` 和 `setdomainname` 的实现，需要 `CAP_SYS_ADMIN`
+///
+/// `name` 不需要以 `\0` 结尾，其长度由 `len` 指定，不能超过 [`HOST_NAME_MAX`]。
+/// `name` 的地址无效时返回 `EFAULT`。
+fn set_name_from_user(
+    field: &SpinNoIrq<[u8; HOST_NAME_MAX + 1]>,
+    name: *const u8,
+    len: usize,
+) -> LinuxResult<isize> {
+    if !current().task_ext().capable(CAP_SYS_ADMIN) {
+        return Err(LinuxError::EPERM);
+    }
+    if len > HOST_NAME_MAX {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut buf = [0; HOST_NAME_MAX];
+    copy_from_user(VirtAddr::from_ptr_of(name), &mut buf[..len])?;
+    set_name(field, &buf[..len])?;
+    Ok(0)
+}
+
+/// 设置主机名，之后 `uname` 返回的 `nodename` 和 `/proc/sys/kernel/hostname` 随之改变
+pub fn sys_sethostname(name: *const u8, len: usize) -> isize {
+    syscall_body!(sys_sethostname, set_name_from_user(&HOSTNAME, name, len))
+}
+
+/// 设置 NIS 域名，之后 `uname` 返回的 `domainname` 和 `/proc/sys/kernel/domainname`
+/// 随之改变
+pub fn sys_setdomainname(name: *const u8, len: usize) -> isize {
+    syscall_body!(
+        sys_setdomainname,
+        set_name_from_user(&DOMAINNAME, name, len)
+    )
+}
+
+/// 截断到 [`HOST_NAME_MAX`] 字节的 `name`
+///
+/// 与 Linux 相同，写入 `/proc/sys` 中的字符串过长时截断，而不是失败。
+fn truncate_name(name: &str) -> &[u8] {
+    &name.as_bytes()[..name.len().min(HOST_NAME_MAX)]
+}
+
+/// 注册 `/proc/sys` 中的可调参数
+///
+/// 主机名和域名与 `sethostname` 和 `setdomainname` 共用，其余参数的值由使用它们的
+/// 子系统保存。
+pub(crate) fn init_sysctl() {
+    let hostname = FnTunable::new(
+        || name_of(&HOSTNAME),
+        Some(|name| set_name(&HOSTNAME, truncate_name(name))),
+    );
+    let domainname = FnTunable::new(
+        || name_of(&DOMAINNAME),
+        Some(|name| set_name(&DOMAINNAME, truncate_name(name))),
+    );
+    axfs::register_sysctl("kernel/hostname", hostname);
+    axfs::register_sysctl("kernel/domainname", domainname);
+    axfs::register_sysctl("kernel/osrelease", FnTunable::new(|| RELEASE.into(), None));
+    axfs::register_sysctl("kernel/pid_max", IntTunable::new(&PID_MAX, PID_MAX_RANGE));
+    axfs::register_sysctl(
+        "vm/overcommit_memory",
+        IntTunable::new(&OVERCOMMIT_MEMORY, OVERCOMMIT_RANGE),
+    );
+    axfs::register_sysctl(
+        "fs/file-max",
+        IntTunable::new(&api::FILE_MAX, 0..=usize::MAX),
+    );
+}
+
+/// `sysinfo` 返回的系统统计信息，布局与 Linux 中的 `struct sysinfo` 一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SysInfo {
+    /// 启动以来的秒数
+    uptime: i64,
+    /// 1、5、15 分钟内的平均负载
+    loads: [u64; 3],
+    totalram: u64,
+    freeram: u64,
+    sharedram: u64,
+    bufferram: u64,
+    totalswap: u64,
+    freeswap: u64,
+    /// 进程数
+    procs: u16,
+    _pad: u16,
+    totalhigh: u64,
+    freehigh: u64,
+    /// 内存大小的单位，以字节计
+    mem_unit: u32,
+}
+
+/// 获取系统的统计信息
+///
+/// 内存按页分配器统计，与 `/proc/meminfo` 一致，已分配给内核堆的页也计入已使用的内存；
+/// 空闲内存中包括为内核保留的部分。目前不统计负载，
+/// 也没有交换区、共享内存和缓冲区，它们总是 0。
+pub fn sys_sysinfo(info: *mut SysInfo) -> isize {
+    syscall_body!(sys_sysinfo, {
+        let allocator = axalloc::global_allocator();
+        let sysinfo = SysInfo {
+            uptime: axhal::time::monotonic_time().as_secs() as i64,
+            totalram: (allocator.total_pages() * PAGE_SIZE_4K) as u64,
+            freeram: (allocator.available_pages() * PAGE_SIZE_4K) as u64,
+            procs: all_pids().len() as u16,
+            mem_unit: 1,
+            ..Default::default()
+        };
+        write_user(info, &sysinfo)?;
+        Ok(0)
+    })
+}
+
+/// `syslog` 支持的操作类型
+const SYSLOG_ACTION_CLOSE: i32 = 0;
+const SYSLOG_ACTION_OPEN: i32 = 1;
+const SYSLOG_ACTION_READ: i32 = 2;
+const SYSLOG_ACTION_READ_ALL: i32 = 3;
+const SYSLOG_ACTION_CLEAR: i32 = 5;
+const SYSLOG_ACTION_SIZE_UNREAD: i32 = 9;
+const SYSLOG_ACTION_SIZE_BUFFER: i32 = 10;
+
+/// 读取或清空内核日志缓冲区
+///
+/// 读取操作只返回完整的行：`SYSLOG_ACTION_READ` 按从旧到新的顺序消耗未读的日志，
+/// 没有未读日志时直接返回 0 而不会阻塞；`SYSLOG_ACTION_READ_ALL` 返回缓冲区中
+/// 最近的日志但不消耗它们。
+pub fn sys_syslog(log_type: i32, buf: *mut u8, len: i32) -> isize {
+    syscall_body!(sys_syslog, {
+        // 读入内核中的缓冲区后再复制给用户，读取的长度不会超过日志缓冲区的大小
+        let read_to_user = |read: fn(&mut [u8]) -> usize| {
+            if buf.is_null() || len < 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            let mut kbuf = vec![0; (len as usize).min(axlog::ring::LOG_BUF_SIZE)];
+            let n = read(&mut kbuf);
+            copy_to_user(VirtAddr::from_mut_ptr_of(buf), &kbuf[..n])?;
+            Ok(n)
+        };
+        match log_type {
+            SYSLOG_ACTION_CLOSE | SYSLOG_ACTION_OPEN => Ok(0),
+            SYSLOG_ACTION_READ => read_to_user(axlog::ring::read),
+            SYSLOG_ACTION_READ_ALL => read_to_user(axlog::ring::read_all),
+            SYSLOG_ACTION_CLEAR => {
+                axlog::ring::clear();
+                Ok(0)
+            }
+            SYSLOG_ACTION_SIZE_UNREAD => Ok(axlog::ring::unread_len()),
+            SYSLOG_ACTION_SIZE_BUFFER => Ok(axlog::ring::LOG_BUF_SIZE),
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
+
+const LINUX_REBOOT_MAGIC1: u32 = 0xfee1_dead;
+const LINUX_REBOOT_MAGIC2: u32 = 672_274_793;
+const LINUX_REBOOT_MAGIC2A: u32 = 85_072_278;
+const LINUX_REBOOT_MAGIC2B: u32 = 369_367_448;
+const LINUX_REBOOT_MAGIC2C: u32 = 537_993_216;
+
+/// 重启系统
+const LINUX_REBOOT_CMD_RESTART: u32 = 0x0123_4567;
+/// 停机，但不关闭电源
+const LINUX_REBOOT_CMD_HALT: u32 = 0xcdef_0123;
+/// 关闭电源
+const LINUX_REBOOT_CMD_POWER_OFF: u32 = 0x4321_fedc;
+/// 启用 Ctrl-Alt-Del 组合键重启
+const LINUX_REBOOT_CMD_CAD_ON: u32 = 0x89ab_cdef;
+/// 禁用 Ctrl-Alt-Del 组合键重启
+const LINUX_REBOOT_CMD_CAD_OFF: u32 = 0;
+
+/// 将所有文件系统写回磁盘后关闭系统
+///
+/// 测例运行结束后也通过该函数退出，保证测例写入的文件在磁盘镜像中持久化。
+pub fn shutdown() -> ! {
+    super::stats::dump();
+    axfs::sync_all();
+    axhal::misc::terminate()
+}
+
+/// 重启、停机或关闭系统
+///
+/// `magic1` 与 `magic2` 必须是 Linux 规定的魔数。目前平台层没有提供重启的接口，
+/// `LINUX_REBOOT_CMD_RESTART` 会退化为关机。
+pub fn sys_reboot(magic1: u32, magic2: u32, cmd: u32, _arg: *const u8) -> isize {
+    syscall_body!(sys_reboot, {
+        if !current().task_ext().capable(CAP_SYS_BOOT) {
+            return Err(LinuxError::EPERM);
+        }
+        if magic1 != LINUX_REBOOT_MAGIC1
+            || ![
+                LINUX_REBOOT_MAGIC2,
+                LINUX_REBOOT_MAGIC2A,
+                LINUX_REBOOT_MAGIC2B,
+                LINUX_REBOOT_MAGIC2C,
+            ]
+            .contains(&magic2)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        match cmd {
+            LINUX_REBOOT_CMD_POWER_OFF => shutdown(),
+            LINUX_REBOOT_CMD_RESTART => {
+                warn!("Reboot is not supported by the platform, power off instead");
+                shutdown()
+            }
+            LINUX_REBOOT_CMD_HALT => {
+                arceos_posix_api::sys_sync();
+                info!("System halted");
+                axhal::arch::disable_irqs();
+                loop {
+                    axhal::arch::halt();
+                }
+            }
+            LINUX_REBOOT_CMD_CAD_ON | LINUX_REBOOT_CMD_CAD_OFF => Ok(0),
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
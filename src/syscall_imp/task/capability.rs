@@ -0,0 +1,117 @@
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, AxTaskRef, TaskExtRef};
+
+use crate::{
+    mm::{read_user, write_user},
+    syscall_body,
+    task::{find_task_by_pid, Capabilities, Pid},
+};
+
+/// 32 位的能力集合，只能表示编号小于 32 的能力
+const _LINUX_CAPABILITY_VERSION_1: u32 = 0x1998_0330;
+/// 已废弃的 64 位版本，与第 3 版的布局相同
+const _LINUX_CAPABILITY_VERSION_2: u32 = 0x2007_1026;
+/// 64 位的能力集合，内核优先使用的版本
+const _LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// `capget` 和 `capset` 的头部
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CapUserHeader {
+    version: u32,
+    pid: i32,
+}
+
+/// 能力集合的一部分，第 3 版中依次存放低 32 位和高 32 位
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// 读取用户传入的头部并检查版本号，返回头部和数据数组的长度
+///
+/// 版本号不受支持时，将内核支持的版本写回头部并返回 EINVAL。地址无效时返回 EFAULT。
+fn read_header(header: *mut CapUserHeader) -> LinuxResult<(CapUserHeader, usize)> {
+    let mut hdr = read_user(header)?;
+    match hdr.version {
+        _LINUX_CAPABILITY_VERSION_1 => Ok((hdr, 1)),
+        _LINUX_CAPABILITY_VERSION_2 | _LINUX_CAPABILITY_VERSION_3 => Ok((hdr, 2)),
+        _ => {
+            hdr.version = _LINUX_CAPABILITY_VERSION_3;
+            write_user(header, &hdr)?;
+            Err(LinuxError::EINVAL)
+        }
+    }
+}
+
+/// 根据头部中的 pid 找到目标进程，0 表示当前进程
+fn target_task(pid: i32) -> LinuxResult<AxTaskRef> {
+    let curr = current();
+    if pid < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if pid == 0 || pid as Pid == curr.task_ext().proc_id {
+        return Ok(curr.as_task_ref().clone());
+    }
+    find_task_by_pid(pid as Pid).ok_or(LinuxError::ESRCH)
+}
+
+/// 获取指定进程的能力集合
+///
+/// `data` 为空时只用于协商版本号：即使版本号不受支持也返回 0。
+pub(crate) fn sys_capget(header: *mut CapUserHeader, data: *mut CapUserData) -> isize {
+    syscall_body!(sys_capget, {
+        let (header, count) = match read_header(header) {
+            Ok(header) => header,
+            Err(LinuxError::EINVAL) if data.is_null() => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        if data.is_null() {
+            return Ok(0);
+        }
+        let task = target_task(header.pid)?;
+        let caps = task.task_ext().capabilities();
+        for i in 0..count {
+            let shift = i * 32;
+            let item = CapUserData {
+                effective: (caps.effective >> shift) as u32,
+                permitted: (caps.permitted >> shift) as u32,
+                inheritable: (caps.inheritable >> shift) as u32,
+            };
+            write_user(data.wrapping_add(i), &item)?;
+        }
+        Ok(0)
+    })
+}
+
+/// 修改当前进程的能力集合
+///
+/// 只能修改调用者自身；允许集合只能缩小，丢弃的能力无法再次获得。
+pub(crate) fn sys_capset(header: *mut CapUserHeader, data: *const CapUserData) -> isize {
+    syscall_body!(sys_capset, {
+        let (header, count) = read_header(header)?;
+        let curr = current();
+        if header.pid != 0 && header.pid as Pid != curr.task_ext().proc_id {
+            return Err(LinuxError::EPERM);
+        }
+        let mut caps = Capabilities {
+            effective: 0,
+            permitted: 0,
+            inheritable: 0,
+        };
+        for i in 0..count {
+            let item = read_user(data.wrapping_add(i))?;
+            let shift = i * 32;
+            caps.effective |= (item.effective as u64) << shift;
+            caps.permitted |= (item.permitted as u64) << shift;
+            caps.inheritable |= (item.inheritable as u64) << shift;
+        }
+        if !curr.task_ext().set_capabilities(caps) {
+            return Err(LinuxError::EPERM);
+        }
+        Ok(0)
+    })
+}
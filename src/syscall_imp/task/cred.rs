@@ -0,0 +1,40 @@
+use axerrno::LinuxError;
+use axtask::{current, TaskExtRef};
+
+use crate::syscall_body;
+
+/// 返回当前进程的实际用户 ID
+pub(crate) fn sys_getuid() -> isize {
+    current().task_ext().uid() as isize
+}
+
+/// 返回当前进程的有效用户 ID
+pub(crate) fn sys_geteuid() -> isize {
+    current().task_ext().euid() as isize
+}
+
+/// 返回当前进程的实际组 ID，目前没有组 ID，总是 0
+pub(crate) fn sys_getgid() -> isize {
+    0
+}
+
+/// 返回当前进程的有效组 ID，目前没有组 ID，总是 0
+pub(crate) fn sys_getegid() -> isize {
+    0
+}
+
+/// 修改当前进程的用户 ID
+///
+/// 拥有 `CAP_SETUID` 时修改实际、有效和保存的用户 ID，否则只能把有效用户 ID 改为实际或
+/// 保存的用户 ID。不再有用户 ID 为 0 时丢弃所有能力。
+pub(crate) fn sys_setuid(uid: u32) -> isize {
+    syscall_body!(sys_setuid, {
+        if uid == u32::MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        if !current().task_ext().setuid(uid) {
+            return Err(LinuxError::EPERM);
+        }
+        Ok(0)
+    })
+}
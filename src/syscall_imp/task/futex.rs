@@ -0,0 +1,469 @@
+//! 快速用户态互斥锁 futex
+//!
+//! 等待者按 futex 的键散列到 [`BUCKETS`] 个桶中，每个桶有自己的自旋锁，不同 futex 上的
+//! 等待和唤醒互不争用。私有 futex 以地址空间和虚拟地址为键；共享 futex 位于共享映射中时
+//! 以物理地址为键，映射同一内存的不同进程因此可以互相唤醒，否则与私有 futex 相同。
+//!
+//! 等待者持有桶的锁时检查 futex 的值并加入桶中，唤醒者修改值之后才获取桶的锁，因此
+//! 不会遗漏唤醒。等待者在自己的 `signal_wq` 上睡眠，可被信号打断；超时按单调时钟的
+//! 绝对时间计算，被提前唤醒后继续等待剩余的时间。
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    cmp,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use arceos_posix_api::{self as api, ctypes::timespec};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::{
+    paging::MappingFlags,
+    time::{monotonic_time, TimeValue},
+};
+use axmm::{AddrSpace, Backend};
+use axsync::spin::{SpinNoIrq, SpinNoIrqGuard};
+use axtask::{current, AxTaskRef, TaskExtRef};
+use memory_addr::{MemoryAddr, VirtAddr, PAGE_SIZE_4K};
+
+use crate::{
+    mm::{read_user, user_atomic_u32},
+    syscall_body,
+    task::signal::WaitOutcome,
+};
+
+const FUTEX_WAIT: i32 = 0;
+const FUTEX_WAKE: i32 = 1;
+const FUTEX_REQUEUE: i32 = 3;
+const FUTEX_CMP_REQUEUE: i32 = 4;
+const FUTEX_WAKE_OP: i32 = 5;
+const FUTEX_WAIT_BITSET: i32 = 9;
+const FUTEX_WAKE_BITSET: i32 = 10;
+/// 只在本进程内使用的 futex
+const FUTEX_PRIVATE_FLAG: i32 = 128;
+/// `FUTEX_WAIT_BITSET` 的超时按 `CLOCK_REALTIME` 计算
+const FUTEX_CLOCK_REALTIME: i32 = 256;
+/// 与任意等待者匹配的位集合
+const FUTEX_BITSET_MATCH_ANY: u32 = u32::MAX;
+
+/// `FUTEX_WAKE_OP` 对 `uaddr2` 的操作
+const FUTEX_OP_SET: u32 = 0;
+const FUTEX_OP_ADD: u32 = 1;
+const FUTEX_OP_OR: u32 = 2;
+const FUTEX_OP_ANDN: u32 = 3;
+const FUTEX_OP_XOR: u32 = 4;
+/// 操作数为 `1 << oparg`
+const FUTEX_OP_OPARG_SHIFT: u32 = 8;
+
+/// `FUTEX_WAKE_OP` 对 `uaddr2` 原值的比较
+const FUTEX_OP_CMP_EQ: u32 = 0;
+const FUTEX_OP_CMP_NE: u32 = 1;
+const FUTEX_OP_CMP_LT: u32 = 2;
+const FUTEX_OP_CMP_LE: u32 = 3;
+const FUTEX_OP_CMP_GT: u32 = 4;
+const FUTEX_OP_CMP_GE: u32 = 5;
+
+/// 桶的数量，须为 2 的幂
+const BUCKETS: usize = 64;
+
+/// 区分不同 futex 的键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FutexKey {
+    /// 地址空间和其中的虚拟地址
+    Private(usize, usize),
+    /// 共享映射中的物理地址
+    Shared(usize),
+}
+
+impl FutexKey {
+    /// 找到 `uaddr` 处的 futex 的键，非私有的 futex 位于共享映射中时以物理地址为键
+    fn new(aspace: &AddrSpace, uaddr: VirtAddr, private: bool) -> LinuxResult<Self> {
+        if !uaddr.is_aligned(4usize) {
+            return Err(LinuxError::EINVAL);
+        }
+        if !private {
+            if let Some((_, Backend::Shared { start, frames, .. })) = aspace.find_area(uaddr) {
+                let offset = uaddr - *start;
+                if let Some(frame) = frames.get(offset / PAGE_SIZE_4K) {
+                    return Ok(Self::Shared(
+                        frame.paddr().as_usize() + offset % PAGE_SIZE_4K,
+                    ));
+                }
+            }
+        }
+        Ok(Self::Private(
+            aspace as *const AddrSpace as usize,
+            uaddr.as_usize(),
+        ))
+    }
+
+    /// 键所在的桶的下标
+    fn bucket(&self) -> usize {
+        let hash = match *self {
+            Self::Private(aspace, vaddr) => aspace.rotate_left(17) ^ vaddr,
+            Self::Shared(paddr) => paddr,
+        };
+        ((hash as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - BUCKETS.trailing_zeros()))
+            as usize
+    }
+}
+
+/// 一个等待中的任务
+struct FutexWaiter {
+    /// 所等待的 futex，`FUTEX_REQUEUE` 会修改它
+    key: SpinNoIrq<FutexKey>,
+    bitset: u32,
+    task: AxTaskRef,
+    /// 已被唤醒并移出桶
+    woken: AtomicBool,
+}
+
+type Bucket = Vec<Arc<FutexWaiter>>;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_BUCKET: SpinNoIrq<Bucket> = SpinNoIrq::new(Vec::new());
+
+static FUTEX_BUCKETS: [SpinNoIrq<Bucket>; BUCKETS] = [EMPTY_BUCKET; BUCKETS];
+
+/// 按下标顺序锁住两个桶，返回 `a` 和 `b` 的锁；两者相同时只锁一次
+fn lock_pair(
+    a: usize,
+    b: usize,
+) -> (
+    SpinNoIrqGuard<'static, Bucket>,
+    Option<SpinNoIrqGuard<'static, Bucket>>,
+) {
+    match a.cmp(&b) {
+        cmp::Ordering::Equal => (FUTEX_BUCKETS[a].lock(), None),
+        cmp::Ordering::Less => {
+            let first = FUTEX_BUCKETS[a].lock();
+            (first, Some(FUTEX_BUCKETS[b].lock()))
+        }
+        cmp::Ordering::Greater => {
+            let second = FUTEX_BUCKETS[b].lock();
+            (FUTEX_BUCKETS[a].lock(), Some(second))
+        }
+    }
+}
+
+/// 唤醒桶中等待 `key` 且位集合与 `bitset` 相交的至多 `count` 个任务，返回唤醒的数量
+fn wake_locked(bucket: &mut Bucket, key: FutexKey, bitset: u32, count: usize) -> usize {
+    let mut woken = 0;
+    bucket.retain(|waiter| {
+        if woken == count || *waiter.key.lock() != key || waiter.bitset & bitset == 0 {
+            return true;
+        }
+        waiter.woken.store(true, Ordering::Release);
+        waiter.task.task_ext().signal_wq.notify_all(false);
+        woken += 1;
+        false
+    });
+    woken
+}
+
+/// 将未被唤醒的 `waiter` 移出所在的桶，它已被唤醒时返回 `false`
+fn dequeue(waiter: &Arc<FutexWaiter>) -> bool {
+    loop {
+        let key = *waiter.key.lock();
+        let mut bucket = FUTEX_BUCKETS[key.bucket()].lock();
+        if let Some(i) = bucket.iter().position(|w| Arc::ptr_eq(w, waiter)) {
+            bucket.remove(i);
+            return true;
+        }
+        if waiter.woken.load(Ordering::Acquire) {
+            return false;
+        }
+        // 读取键之后被转移到了其他 futex 上，重新查找
+    }
+}
+
+/// `uaddr` 处的值等于 `val` 时等待，直到被位集合与 `bitset` 相交的唤醒操作唤醒
+///
+/// `deadline` 是单调时钟的绝对时间，到达时返回 ETIMEDOUT。
+fn futex_wait(
+    uaddr: VirtAddr,
+    private: bool,
+    val: u32,
+    bitset: u32,
+    deadline: Option<TimeValue>,
+) -> LinuxResult<isize> {
+    if bitset == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = current();
+    let ext = curr.task_ext();
+    let mut aspace = ext.aspace.lock();
+    let key = FutexKey::new(&aspace, uaddr, private)?;
+    let word = user_atomic_u32(&mut aspace, uaddr, MappingFlags::READ)?;
+    let waiter = Arc::new(FutexWaiter {
+        key: SpinNoIrq::new(key),
+        bitset,
+        task: curr.as_task_ref().clone(),
+        woken: AtomicBool::new(false),
+    });
+    {
+        let mut bucket = FUTEX_BUCKETS[key.bucket()].lock();
+        if word.load(Ordering::SeqCst) != val {
+            return Err(LinuxError::EAGAIN);
+        }
+        bucket.push(waiter.clone());
+    }
+    drop(aspace);
+
+    let err = loop {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let now = monotonic_time();
+                if now >= deadline {
+                    break LinuxError::ETIMEDOUT;
+                }
+                Some(deadline - now)
+            }
+            None => None,
+        };
+        match ext.wait_signal(timeout, |_| waiter.woken.load(Ordering::Acquire)) {
+            WaitOutcome::Ready => return Ok(0),
+            WaitOutcome::Interrupted if ext.interrupted() => break LinuxError::EINTR,
+            _ => {}
+        }
+    };
+    // 超时或被打断的同时可能恰好被唤醒，此时按被唤醒处理，唤醒者的计数才准确
+    if dequeue(&waiter) {
+        Err(err)
+    } else {
+        Ok(0)
+    }
+}
+
+/// 唤醒 `uaddr` 上位集合与 `bitset` 相交的至多 `count` 个等待者
+fn futex_wake(uaddr: VirtAddr, private: bool, count: usize, bitset: u32) -> LinuxResult<isize> {
+    if bitset == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let key = FutexKey::new(&current().task_ext().aspace.lock(), uaddr, private)?;
+    let mut bucket = FUTEX_BUCKETS[key.bucket()].lock();
+    Ok(wake_locked(&mut bucket, key, bitset, count) as isize)
+}
+
+/// 进程退出时清零 `clear_child_tid` 指向的值，并唤醒在其上等待的一个任务
+///
+/// 由退出的任务在自己的上下文中调用，因此无论是主动退出还是被信号终止，写入的都是它自己的
+/// 地址空间；所在的页尚未分配时先分配。与 Linux 一致，地址无效时忽略。
+pub(crate) fn clear_child_tid(uaddr: VirtAddr) {
+    let curr = current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    let Ok(word) = user_atomic_u32(&mut aspace, uaddr, MappingFlags::WRITE) else {
+        return;
+    };
+    word.store(0, Ordering::SeqCst);
+    // 与 Linux 一样按非私有的 futex 唤醒，共享映射中的等待者也能被唤醒
+    let Ok(key) = FutexKey::new(&aspace, uaddr, false) else {
+        return;
+    };
+    let mut bucket = FUTEX_BUCKETS[key.bucket()].lock();
+    wake_locked(&mut bucket, key, FUTEX_BITSET_MATCH_ANY, 1);
+}
+
+/// 唤醒 `uaddr` 上的至多 `wake` 个等待者，并将其余的至多 `requeue` 个转移到 `uaddr2` 上
+///
+/// 指定了 `cmp` 时，`uaddr` 处的值不等于它则返回 EAGAIN。返回唤醒和转移的总数。
+fn futex_requeue(
+    uaddr: VirtAddr,
+    uaddr2: VirtAddr,
+    private: bool,
+    wake: usize,
+    requeue: usize,
+    cmp: Option<u32>,
+) -> LinuxResult<isize> {
+    let curr = current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    let key = FutexKey::new(&aspace, uaddr, private)?;
+    let key2 = FutexKey::new(&aspace, uaddr2, private)?;
+    let word = match cmp {
+        Some(_) => Some(user_atomic_u32(&mut aspace, uaddr, MappingFlags::READ)?),
+        None => None,
+    };
+    let (mut bucket, bucket2) = lock_pair(key.bucket(), key2.bucket());
+    if let (Some(word), Some(cmp)) = (word, cmp) {
+        if word.load(Ordering::SeqCst) != cmp {
+            return Err(LinuxError::EAGAIN);
+        }
+    }
+    let woken = wake_locked(&mut bucket, key, FUTEX_BITSET_MATCH_ANY, wake);
+    let mut moved = Vec::new();
+    bucket.retain(|waiter| {
+        let mut waiter_key = waiter.key.lock();
+        if moved.len() == requeue || *waiter_key != key {
+            return true;
+        }
+        *waiter_key = key2;
+        moved.push(waiter.clone());
+        false
+    });
+    let requeued = moved.len();
+    match bucket2 {
+        Some(mut bucket2) => bucket2.extend(moved),
+        None => bucket.extend(moved),
+    }
+    Ok((woken + requeued) as isize)
+}
+
+/// 解码 `FUTEX_WAKE_OP` 的 `val3`，返回操作、操作数、比较方式和比较的值
+fn decode_wake_op(val3: u32) -> LinuxResult<(u32, u32, u32, i32)> {
+    // 12 位的有符号数
+    let sign_extend = |bits: u32| ((bits << 20) as i32) >> 20;
+    let op = val3 >> 28;
+    let cmp = (val3 >> 24) & 0xf;
+    let mut oparg = sign_extend((val3 >> 12) & 0xfff) as u32;
+    let cmparg = sign_extend(val3 & 0xfff);
+    if op & FUTEX_OP_OPARG_SHIFT != 0 {
+        oparg = 1 << (oparg & 31);
+    }
+    let op = op & !FUTEX_OP_OPARG_SHIFT;
+    if op > FUTEX_OP_XOR || cmp > FUTEX_OP_CMP_GE {
+        return Err(LinuxError::ENOSYS);
+    }
+    Ok((op, oparg, cmp, cmparg))
+}
+
+/// 原子地修改 `uaddr2` 处的值，唤醒 `uaddr` 上的至多 `wake` 个等待者；`uaddr2` 的原值
+/// 满足 `val3` 中的比较条件时，再唤醒 `uaddr2` 上的至多 `wake2` 个等待者
+fn futex_wake_op(
+    uaddr: VirtAddr,
+    uaddr2: VirtAddr,
+    private: bool,
+    wake: usize,
+    wake2: usize,
+    val3: u32,
+) -> LinuxResult<isize> {
+    let (op, oparg, cmp, cmparg) = decode_wake_op(val3)?;
+    let curr = current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    let key = FutexKey::new(&aspace, uaddr, private)?;
+    let key2 = FutexKey::new(&aspace, uaddr2, private)?;
+    let word2 = user_atomic_u32(
+        &mut aspace,
+        uaddr2,
+        MappingFlags::READ | MappingFlags::WRITE,
+    )?;
+    let (mut bucket, mut bucket2) = lock_pair(key.bucket(), key2.bucket());
+    let old = word2
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |old| {
+            Some(match op {
+                FUTEX_OP_SET => oparg,
+                FUTEX_OP_ADD => old.wrapping_add(oparg),
+                FUTEX_OP_OR => old | oparg,
+                FUTEX_OP_ANDN => old & !oparg,
+                _ => old ^ oparg,
+            })
+        })
+        .unwrap() as i32;
+    let mut woken = wake_locked(&mut bucket, key, FUTEX_BITSET_MATCH_ANY, wake);
+    let matched = match cmp {
+        FUTEX_OP_CMP_EQ => old == cmparg,
+        FUTEX_OP_CMP_NE => old != cmparg,
+        FUTEX_OP_CMP_LT => old < cmparg,
+        FUTEX_OP_CMP_LE => old <= cmparg,
+        FUTEX_OP_CMP_GT => old > cmparg,
+        _ => old >= cmparg,
+    };
+    if matched {
+        let bucket2 = bucket2.as_deref_mut().unwrap_or(&mut bucket);
+        woken += wake_locked(bucket2, key2, FUTEX_BITSET_MATCH_ANY, wake2);
+    }
+    Ok(woken as isize)
+}
+
+/// 读取 futex 的超时，`FUTEX_WAIT` 的超时是相对时间，`FUTEX_WAIT_BITSET` 的是绝对时间，
+/// 都换算为单调时钟的绝对时间
+fn read_deadline(
+    timeout: *const timespec,
+    absolute: bool,
+    realtime: bool,
+) -> LinuxResult<TimeValue> {
+    let ts = read_user(timeout)?;
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    let time = TimeValue::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+    Ok(if !absolute {
+        monotonic_time() + time
+    } else if realtime {
+        (time + monotonic_time()).saturating_sub(api::realtime())
+    } else {
+        time
+    })
+}
+
+/// futex 操作，`futex_op` 的低位为命令，可带 `FUTEX_PRIVATE_FLAG` 和 `FUTEX_CLOCK_REALTIME`
+///
+/// - `FUTEX_WAIT` 和 `FUTEX_WAIT_BITSET`：`uaddr` 处的值等于 `val` 时等待，否则返回 EAGAIN；
+///   超时分别为相对时间和绝对时间，后者的位集合为 `val3`；
+/// - `FUTEX_WAKE` 和 `FUTEX_WAKE_BITSET`：唤醒至多 `val` 个等待者，后者只唤醒位集合与
+///   `val3` 相交的等待者，返回唤醒的数量；
+/// - `FUTEX_REQUEUE` 和 `FUTEX_CMP_REQUEUE`：唤醒 `val` 个等待者，再将至多 `timeout`
+///   个转移到 `uaddr2` 上，后者先检查 `uaddr` 处的值是否等于 `val3`；
+/// - `FUTEX_WAKE_OP`：按 `val3` 修改 `uaddr2` 处的值并唤醒 `uaddr` 上的 `val` 个等待者，
+///   原值满足条件时再唤醒 `uaddr2` 上的 `timeout` 个等待者。
+///
+/// 其余命令返回 ENOSYS。
+pub(crate) fn sys_futex(
+    uaddr: *mut u32,
+    futex_op: i32,
+    val: u32,
+    timeout: usize,
+    uaddr2: *mut u32,
+    val3: u32,
+) -> isize {
+    syscall_body!(sys_futex, {
+        let private = futex_op & FUTEX_PRIVATE_FLAG != 0;
+        let realtime = futex_op & FUTEX_CLOCK_REALTIME != 0;
+        let cmd = futex_op & !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+        if realtime && cmd != FUTEX_WAIT_BITSET {
+            return Err(LinuxError::ENOSYS);
+        }
+        let uaddr = VirtAddr::from_mut_ptr_of(uaddr);
+        let uaddr2 = VirtAddr::from_mut_ptr_of(uaddr2);
+        // 与 Linux 相同，唤醒的数量为 0 或负数时仍唤醒一个等待者
+        let count = |n: u32| (n as i32).max(1) as usize;
+        let requeue_count = |n: u32| usize::try_from(n as i32).map_err(|_| LinuxError::EINVAL);
+        match cmd {
+            FUTEX_WAIT | FUTEX_WAIT_BITSET => {
+                let absolute = cmd == FUTEX_WAIT_BITSET;
+                let bitset = if absolute {
+                    val3
+                } else {
+                    FUTEX_BITSET_MATCH_ANY
+                };
+                let deadline = if timeout == 0 {
+                    None
+                } else {
+                    Some(read_deadline(
+                        timeout as *const timespec,
+                        absolute,
+                        realtime,
+                    )?)
+                };
+                futex_wait(uaddr, private, val, bitset, deadline)
+            }
+            FUTEX_WAKE => futex_wake(uaddr, private, count(val), FUTEX_BITSET_MATCH_ANY),
+            FUTEX_WAKE_BITSET => futex_wake(uaddr, private, count(val), val3),
+            FUTEX_REQUEUE | FUTEX_CMP_REQUEUE => futex_requeue(
+                uaddr,
+                uaddr2,
+                private,
+                requeue_count(val)?,
+                requeue_count(timeout as u32)?,
+                (cmd == FUTEX_CMP_REQUEUE).then_some(val3),
+            ),
+            FUTEX_WAKE_OP => futex_wake_op(
+                uaddr,
+                uaddr2,
+                private,
+                count(val),
+                count(timeout as u32),
+                val3,
+            ),
+            _ => Err(LinuxError::ENOSYS),
+        }
+    })
+}
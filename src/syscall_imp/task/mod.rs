@@ -1,5 +1,15 @@
+mod capability;
+mod cred;
+mod futex;
+mod ptrace;
+mod rlimit;
 mod schedule;
 mod thread;
 
+pub(crate) use self::capability::*;
+pub(crate) use self::cred::*;
+pub(crate) use self::futex::*;
+pub(crate) use self::ptrace::*;
+pub(crate) use self::rlimit::*;
 pub(crate) use self::schedule::*;
 pub(crate) use self::thread::*;
@@ -0,0 +1,150 @@
+use core::mem::size_of;
+
+use arceos_posix_api::ctypes::iovec;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::arch::TrapFrame;
+use axtask::{AxTaskRef, TaskExtRef};
+use memory_addr::VirtAddr;
+
+use crate::{
+    mm::{copy_from_aspace, copy_from_user, copy_to_aspace, copy_to_user, read_user, write_user},
+    syscall_body,
+    task::{
+        ptrace,
+        signal::{trap_frame_of, NSIG},
+        Pid,
+    },
+};
+
+const PTRACE_TRACEME: i32 = 0;
+const PTRACE_PEEKTEXT: i32 = 1;
+const PTRACE_PEEKDATA: i32 = 2;
+const PTRACE_POKETEXT: i32 = 4;
+const PTRACE_POKEDATA: i32 = 5;
+const PTRACE_CONT: i32 = 7;
+const PTRACE_ATTACH: i32 = 16;
+const PTRACE_DETACH: i32 = 17;
+const PTRACE_SYSCALL: i32 = 24;
+const PTRACE_SETOPTIONS: i32 = 0x4200;
+const PTRACE_GETREGSET: i32 = 0x4204;
+const PTRACE_SETREGSET: i32 = 0x4205;
+
+/// 通用寄存器，内容为被跟踪者陷入内核时保存的陷入帧
+const NT_PRSTATUS: usize = 1;
+
+/// 检查继续运行时要递送的信号
+fn resume_signal(data: usize) -> LinuxResult<usize> {
+    if data > NSIG {
+        return Err(LinuxError::EIO);
+    }
+    Ok(data)
+}
+
+/// 将被跟踪者的陷入帧复制到 `iov` 描述的缓冲区，并将 `iov_len` 改为实际复制的长度
+fn get_regset(tracee: &AxTaskRef, kind: usize, iov: *mut iovec) -> LinuxResult {
+    if kind != NT_PRSTATUS {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut vec = read_user(iov)?;
+    let tf = *trap_frame_of(tracee);
+    let bytes = unsafe {
+        core::slice::from_raw_parts(&tf as *const TrapFrame as *const u8, size_of::<TrapFrame>())
+    };
+    let len = vec.iov_len.min(bytes.len());
+    copy_to_user(
+        VirtAddr::from_mut_ptr_of(vec.iov_base as *mut u8),
+        &bytes[..len],
+    )?;
+    vec.iov_len = len;
+    write_user(iov, &vec)?;
+    Ok(())
+}
+
+/// 用 `iov` 描述的缓冲区覆盖被跟踪者的陷入帧，只修改缓冲区覆盖到的部分
+///
+/// 决定返回用户态后特权级的寄存器保持不变。
+fn set_regset(tracee: &AxTaskRef, kind: usize, iov: *mut iovec) -> LinuxResult {
+    if kind != NT_PRSTATUS {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut vec = read_user(iov)?;
+    let old = trap_frame_of(tracee);
+    let mut tf = *old;
+    let bytes = unsafe {
+        core::slice::from_raw_parts_mut(
+            &mut tf as *mut TrapFrame as *mut u8,
+            size_of::<TrapFrame>(),
+        )
+    };
+    let len = vec.iov_len.min(bytes.len());
+    copy_from_user(
+        VirtAddr::from_ptr_of(vec.iov_base as *const u8),
+        &mut bytes[..len],
+    )?;
+    #[cfg(target_arch = "x86_64")]
+    {
+        tf.cs = old.cs;
+        tf.ss = old.ss;
+        tf.rflags = old.rflags;
+    }
+    #[cfg(target_arch = "riscv64")]
+    {
+        tf.sstatus = old.sstatus;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        tf.spsr = old.spsr;
+    }
+    *old = tf;
+    vec.iov_len = len;
+    write_user(iov, &vec)?;
+    Ok(())
+}
+
+/// 读取被跟踪者 `addr` 处的一个字，写入 `data` 指向的位置
+fn peek(tracee: &AxTaskRef, addr: usize, data: *mut usize) -> LinuxResult {
+    let mut word = [0u8; size_of::<usize>()];
+    copy_from_aspace(&mut tracee.task_ext().aspace.lock(), addr.into(), &mut word)
+        .map_err(|_| LinuxError::EIO)?;
+    write_user(data, &usize::from_ne_bytes(word))?;
+    Ok(())
+}
+
+/// 将 `data` 写入被跟踪者 `addr` 处的一个字
+fn poke(tracee: &AxTaskRef, addr: usize, data: usize) -> LinuxResult {
+    copy_to_aspace(
+        &mut tracee.task_ext().aspace.lock(),
+        addr.into(),
+        &data.to_ne_bytes(),
+    )
+    .map_err(|_| LinuxError::EIO)
+}
+
+/// 跟踪其他进程，或者让父进程跟踪当前进程
+///
+/// 除 `PTRACE_TRACEME` 和 `PTRACE_ATTACH` 外，其余请求都要求 `pid` 由当前进程跟踪且已经停止。
+pub(crate) fn sys_ptrace(request: i32, pid: Pid, addr: usize, data: usize) -> isize {
+    syscall_body!(sys_ptrace, {
+        match request {
+            PTRACE_TRACEME => return ptrace::traceme().map(|_| 0),
+            PTRACE_ATTACH => return ptrace::attach(pid).map(|_| 0),
+            _ => {}
+        }
+        let tracee = ptrace::stopped_tracee(pid)?;
+        match request {
+            PTRACE_PEEKTEXT | PTRACE_PEEKDATA => peek(&tracee, addr, data as *mut usize)?,
+            PTRACE_POKETEXT | PTRACE_POKEDATA => poke(&tracee, addr, data)?,
+            PTRACE_CONT => ptrace::resume(&tracee, resume_signal(data)?, false),
+            PTRACE_SYSCALL => ptrace::resume(&tracee, resume_signal(data)?, true),
+            PTRACE_DETACH => ptrace::detach(&tracee, resume_signal(data)?),
+            PTRACE_SETOPTIONS => ptrace::set_options(&tracee, data as u32)?,
+            PTRACE_GETREGSET => get_regset(&tracee, addr, data as *mut iovec)?,
+            PTRACE_SETREGSET => set_regset(&tracee, addr, data as *mut iovec)?,
+            _ => {
+                warn!("Unsupported ptrace request: {}", request);
+                return Err(LinuxError::EIO);
+            }
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,97 @@
+use core::sync::atomic::Ordering;
+
+use arceos_posix_api::{AX_FILE_LIMIT, FD_LIMIT};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, AxTaskRef, TaskExtRef};
+
+use crate::{
+    mm::{read_user, write_user},
+    syscall_body,
+    task::{
+        find_task_by_pid,
+        rlimit::{RLimit, RLIMIT_NOFILE, RLIM_NLIMITS},
+        Pid, CAP_SYS_RESOURCE,
+    },
+};
+
+/// 根据 pid 找到目标进程，0 表示当前进程；修改其他进程的限制需要有访问它的权限
+fn target_task(pid: Pid) -> LinuxResult<AxTaskRef> {
+    let curr = current();
+    if pid == 0 || pid == curr.task_ext().proc_id {
+        return Ok(curr.as_task_ref().clone());
+    }
+    let task = find_task_by_pid(pid).ok_or(LinuxError::ESRCH)?;
+    if !curr.task_ext().may_access(task.task_ext()) {
+        return Err(LinuxError::EPERM);
+    }
+    Ok(task)
+}
+
+/// 检查并设置 `task` 的资源限制，返回原先的值
+///
+/// 软限制不能超过硬限制；提高硬限制需要 [`CAP_SYS_RESOURCE`]。`RLIMIT_NOFILE` 不能超过
+/// 文件描述符表的容量，其软限制同时同步到目标进程的文件描述符表。
+fn set_rlimit(task: &AxTaskRef, resource: usize, new: RLimit) -> LinuxResult<RLimit> {
+    if new.cur > new.max {
+        return Err(LinuxError::EINVAL);
+    }
+    let ext = task.task_ext();
+    let mut rlimits = ext.rlimits.lock();
+    let old = rlimits.get(resource);
+    if new.max > old.max && !current().task_ext().capable(CAP_SYS_RESOURCE) {
+        return Err(LinuxError::EPERM);
+    }
+    if resource == RLIMIT_NOFILE {
+        if new.max > AX_FILE_LIMIT as u64 {
+            return Err(LinuxError::EPERM);
+        }
+        FD_LIMIT
+            .deref_from(&ext.ns)
+            .store(new.cur as usize, Ordering::Relaxed);
+    }
+    rlimits.set(resource, new);
+    Ok(old)
+}
+
+/// 获取并设置进程 `pid` 的资源限制
+///
+/// `new` 不为空时设置新的限制，`old` 不为空时写入原先的限制。
+pub(crate) fn sys_prlimit64(
+    pid: Pid,
+    resource: u32,
+    new: *const RLimit,
+    old: *mut RLimit,
+) -> isize {
+    syscall_body!(sys_prlimit64, {
+        let resource = resource as usize;
+        if resource >= RLIM_NLIMITS {
+            return Err(LinuxError::EINVAL);
+        }
+        let task = target_task(pid)?;
+        let prev = if new.is_null() {
+            task.task_ext().rlimits.lock().get(resource)
+        } else {
+            set_rlimit(&task, resource, read_user(new)?)?
+        };
+        if !old.is_null() {
+            write_user(old, &prev)?;
+        }
+        Ok(0)
+    })
+}
+
+/// 获取当前进程的资源限制
+pub(crate) fn sys_getrlimit(resource: u32, rlim: *mut RLimit) -> isize {
+    if rlim.is_null() {
+        return syscall_body!(sys_getrlimit, Err::<isize, _>(LinuxError::EFAULT));
+    }
+    sys_prlimit64(0, resource, core::ptr::null(), rlim)
+}
+
+/// 设置当前进程的资源限制
+pub(crate) fn sys_setrlimit(resource: u32, rlim: *const RLimit) -> isize {
+    if rlim.is_null() {
+        return syscall_body!(sys_setrlimit, Err::<isize, _>(LinuxError::EFAULT));
+    }
+    sys_prlimit64(0, resource, rlim, core::ptr::null_mut())
+}
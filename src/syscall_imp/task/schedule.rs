@@ -1,15 +1,344 @@
+use core::{mem::size_of, time::Duration};
+
+use alloc::{vec, vec::Vec};
+
 use arceos_posix_api::{
     self as api,
     ctypes::{clockid_t, timespec},
 };
-use axerrno::LinuxError;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::mem::VirtAddr;
+use axtask::{current, AxTaskRef, TaskExtRef};
+
+use crate::{
+    mm::{copy_from_user, copy_to_user, read_user, write_user},
+    syscall_body,
+    task::{
+        all_tasks, find_task_by_pid,
+        ioprio::{
+            IOPRIO_CLASS_BE, IOPRIO_CLASS_IDLE, IOPRIO_CLASS_NONE, IOPRIO_CLASS_RT,
+            IOPRIO_CLASS_SHIFT, IOPRIO_LEVEL_MASK,
+        },
+        pid_exists,
+        sched::{policy_timeslice, SchedPolicy, NICE_MAX, NICE_MIN},
+        signal::WaitOutcome,
+        Pid, CAP_SYS_ADMIN, CAP_SYS_NICE,
+    },
+};
+
+/// `setpriority` 和 `getpriority` 的 `which`：单个进程、进程组和用户的所有进程
+const PRIO_PROCESS: i32 = 0;
+const PRIO_PGRP: i32 = 1;
+const PRIO_USER: i32 = 2;
+
+/// `ioprio_set` 和 `ioprio_get` 的 `which`，比 `setpriority` 的对应值大 1
+const IOPRIO_WHO_PROCESS: i32 = 1;
+const IOPRIO_WHO_USER: i32 = 3;
 
 pub(crate) fn sys_sched_yield() -> i32 {
     api::sys_sched_yield()
 }
 
-pub(crate) fn sys_nanosleep(req: *const timespec, rem: *mut timespec) -> i32 {
-    unsafe { api::sys_nanosleep(req, rem) }
+/// 找到调度策略相关系统调用的 `pid` 指定的进程，为 0 时表示当前进程，为负数时返回 EINVAL
+fn sched_target(pid: i32) -> LinuxResult<AxTaskRef> {
+    if pid < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    affinity_target(pid)
+}
+
+/// 获取指定进程的调度策略，`pid` 为 0 时表示当前进程
+///
+/// 僵尸进程不再保存调度策略，报告为 `SCHED_OTHER`。
+pub(crate) fn sys_sched_getscheduler(pid: i32) -> isize {
+    syscall_body!(sys_sched_getscheduler, {
+        match sched_target(pid) {
+            Ok(task) => Ok(task.task_ext().sched_policy().0 as isize),
+            Err(LinuxError::ESRCH) if pid_exists(pid as Pid) => Ok(SchedPolicy::Other as isize),
+            Err(err) => Err(err),
+        }
+    })
+}
+
+/// 设置进程的调度策略和优先级，`policy` 为 `None` 时只修改优先级
+///
+/// `param` 指向 `struct sched_param`，内核只使用其中的第一个字段 `sched_priority`。
+/// 实时调度策略需要 [`CAP_SYS_NICE`]，修改其他用户的进程同样需要，否则返回 EPERM；
+/// 优先级不在策略的范围内时返回 EINVAL。
+fn set_scheduler(pid: i32, policy: Option<u32>, param: *const i32) -> LinuxResult<isize> {
+    if param.is_null() || pid < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let priority = read_user(param)?;
+    let task = sched_target(pid)?;
+    let ext = task.task_ext();
+    let policy = match policy {
+        Some(policy) => SchedPolicy::try_from(policy).map_err(|_| LinuxError::EINVAL)?,
+        None => ext.sched_policy().0,
+    };
+    let (min, max) = policy.priority_range();
+    if priority < min as i32 || priority > max as i32 {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = current();
+    let privileged = curr.task_ext().capable(CAP_SYS_NICE);
+    if (ext.uid() != curr.task_ext().uid() || policy.is_realtime()) && !privileged {
+        return Err(LinuxError::EPERM);
+    }
+    ext.set_sched_policy(policy, priority as u32);
+    Ok(0)
+}
+
+/// 设置进程的调度策略和优先级，见 [`set_scheduler`]
+///
+/// 目前支持 `SCHED_OTHER`、`SCHED_FIFO` 和 `SCHED_RR`，不支持 `SCHED_RESET_ON_FORK`。
+pub(crate) fn sys_sched_setscheduler(pid: i32, policy: i32, param: *const i32) -> isize {
+    syscall_body!(sys_sched_setscheduler, {
+        if policy < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        set_scheduler(pid, Some(policy as u32), param)
+    })
+}
+
+/// 只修改进程的优先级，调度策略保持不变，见 [`set_scheduler`]
+pub(crate) fn sys_sched_setparam(pid: i32, param: *const i32) -> isize {
+    syscall_body!(sys_sched_setparam, set_scheduler(pid, None, param))
+}
+
+/// 获取进程的优先级，写入 `struct sched_param` 的 `sched_priority`
+pub(crate) fn sys_sched_getparam(pid: i32, param: *mut i32) -> isize {
+    syscall_body!(sys_sched_getparam, {
+        if param.is_null() {
+            return Err(LinuxError::EINVAL);
+        }
+        let task = sched_target(pid)?;
+        let priority = task.task_ext().sched_policy().1 as i32;
+        write_user(param, &priority)?;
+        Ok(0)
+    })
+}
+
+/// 调度策略可以使用的最高优先级
+pub(crate) fn sys_sched_get_priority_max(policy: i32) -> isize {
+    syscall_body!(sys_sched_get_priority_max, {
+        let policy = SchedPolicy::try_from(policy as u32).map_err(|_| LinuxError::EINVAL)?;
+        Ok(policy.priority_range().1 as isize)
+    })
+}
+
+/// 调度策略可以使用的最低优先级
+pub(crate) fn sys_sched_get_priority_min(policy: i32) -> isize {
+    syscall_body!(sys_sched_get_priority_min, {
+        let policy = SchedPolicy::try_from(policy as u32).map_err(|_| LinuxError::EINVAL)?;
+        Ok(policy.priority_range().0 as isize)
+    })
+}
+
+/// 获取进程的时间片长度
+///
+/// `SCHED_RR` 为固定的轮转时间片，`SCHED_FIFO` 没有时间片，为 0，`SCHED_OTHER` 为按
+/// nice 值缩放后的时间片。
+pub(crate) fn sys_sched_rr_get_interval(pid: i32, tp: *mut timespec) -> isize {
+    syscall_body!(sys_sched_rr_get_interval, {
+        let task = sched_target(pid)?;
+        let ext = task.task_ext();
+        let nanos = policy_timeslice(ext.sched_policy().0, ext.nice());
+        write_user(tp, &timespec::from(Duration::from_nanos(nanos)))?;
+        Ok(0)
+    })
+}
+
+/// 找到 `sched_setaffinity` 和 `sched_getaffinity` 的 `pid` 指定的进程，为 0 时表示当前进程
+fn affinity_target(pid: i32) -> LinuxResult<AxTaskRef> {
+    match pid {
+        0 => Ok(current().as_task_ref().clone()),
+        1.. => find_task_by_pid(pid as Pid)
+            .filter(|task| task.state() != axtask::TaskState::Exited)
+            .ok_or(LinuxError::ESRCH),
+        _ => Err(LinuxError::ESRCH),
+    }
+}
+
+/// 设置进程可以运行的 CPU，`mask` 中每一位对应一个 CPU
+///
+/// 超出 `usize` 的部分没有对应的 CPU，直接忽略。修改其他用户的进程需要
+/// [`CAP_SYS_NICE`]，否则返回 EPERM；`mask` 中没有在线的 CPU 时返回 EINVAL。
+/// 当前进程不能继续在所在的 CPU 上运行时，立即迁移到允许的 CPU。
+pub(crate) fn sys_sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u8) -> isize {
+    syscall_body!(sys_sched_setaffinity, {
+        let mut buf = [0u8; size_of::<usize>()];
+        let len = cpusetsize.min(buf.len());
+        copy_from_user(VirtAddr::from(mask as usize), &mut buf[..len])?;
+        let task = affinity_target(pid)?;
+        let curr = current();
+        if task.task_ext().uid() != curr.task_ext().uid() && !curr.task_ext().capable(CAP_SYS_NICE)
+        {
+            return Err(LinuxError::EPERM);
+        }
+        if !axtask::set_cpumask(&task, usize::from_ne_bytes(buf)) {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(0)
+    })
+}
+
+/// 获取进程可以运行的 CPU，只包含在线的 CPU
+///
+/// CPU 掩码占一个 `usize`，`cpusetsize` 小于它或不是它的整数倍时返回 EINVAL。
+/// 与 Linux 相同，返回写入的字节数，由 libc 将其余部分清零。
+pub(crate) fn sys_sched_getaffinity(pid: i32, cpusetsize: usize, mask: *mut u8) -> isize {
+    syscall_body!(sys_sched_getaffinity, {
+        if cpusetsize < size_of::<usize>() || cpusetsize % size_of::<usize>() != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let task = affinity_target(pid)?;
+        let cpumask = task.cpumask() & axtask::online_cpumask();
+        copy_to_user(VirtAddr::from(mask as usize), &cpumask.to_ne_bytes())?;
+        Ok(size_of::<usize>() as isize)
+    })
+}
+
+/// 找到 `which` 和 `who` 指定的所有进程，`who` 为 0 时表示当前进程或当前用户
+///
+/// 目前每个进程自成一个进程组，组号即 PID，因此进程组与单个进程相同。
+fn priority_targets(which: i32, who: u32) -> LinuxResult<Vec<AxTaskRef>> {
+    let curr = current();
+    let targets = match which {
+        PRIO_PROCESS | PRIO_PGRP if who == 0 => vec![curr.as_task_ref().clone()],
+        PRIO_PROCESS | PRIO_PGRP => find_task_by_pid(who as Pid)
+            .filter(|task| task.state() != axtask::TaskState::Exited)
+            .into_iter()
+            .collect(),
+        PRIO_USER => {
+            let uid = if who == 0 { curr.task_ext().uid() } else { who };
+            all_tasks()
+                .into_iter()
+                .filter(|task| task.task_ext().uid() == uid)
+                .collect()
+        }
+        _ => return Err(LinuxError::EINVAL),
+    };
+    if targets.is_empty() {
+        return Err(LinuxError::ESRCH);
+    }
+    Ok(targets)
+}
+
+/// 获取进程的 nice 值，指定了多个进程时返回其中最小的值
+///
+/// 为了不与错误码混淆，系统调用返回 `20 - nice`，范围为 1 到 40，由 libc 换算回 nice 值。
+pub(crate) fn sys_getpriority(which: i32, who: u32) -> isize {
+    syscall_body!(sys_getpriority, {
+        let nice = priority_targets(which, who)?
+            .iter()
+            .map(|task| task.task_ext().nice())
+            .min()
+            .unwrap_or_default();
+        Ok(20 - nice as isize)
+    })
+}
+
+/// 设置进程的 nice 值，超出 -20 到 19 的值会被截断
+///
+/// 修改其他用户的进程需要 [`CAP_SYS_NICE`]，降低 nice 值（即提高优先级）同样需要，
+/// 否则分别返回 EPERM 和 EACCES。新的值在进程下一次获得时间片时生效。
+///
+/// 所支持的架构都没有单独的 `nice` 系统调用，libc 的 `nice` 由这两个系统调用实现。
+pub(crate) fn sys_setpriority(which: i32, who: u32, prio: i32) -> isize {
+    syscall_body!(sys_setpriority, {
+        let nice = prio.clamp(NICE_MIN, NICE_MAX);
+        let curr = current();
+        let privileged = curr.task_ext().capable(CAP_SYS_NICE);
+        for task in priority_targets(which, who)? {
+            let ext = task.task_ext();
+            if ext.uid() != curr.task_ext().uid() && !privileged {
+                return Err(LinuxError::EPERM);
+            }
+            if nice < ext.nice() && !privileged {
+                return Err(LinuxError::EACCES);
+            }
+            ext.set_nice(nice);
+        }
+        Ok(0)
+    })
+}
+
+/// 获取进程的 I/O 优先级，指定了多个进程时返回其中最高的
+pub(crate) fn sys_ioprio_get(which: i32, who: u32) -> isize {
+    syscall_body!(sys_ioprio_get, {
+        let ioprio = ioprio_targets(which, who)?
+            .iter()
+            .map(|task| task.task_ext().ioprio())
+            .min_by_key(|&ioprio| {
+                // 未设置的进程按尽力而为类比较
+                let class = match ioprio >> IOPRIO_CLASS_SHIFT {
+                    IOPRIO_CLASS_NONE => IOPRIO_CLASS_BE,
+                    class => class,
+                };
+                (class, ioprio & IOPRIO_LEVEL_MASK)
+            })
+            .unwrap_or_default();
+        Ok(ioprio as isize)
+    })
+}
+
+/// 设置进程的 I/O 优先级
+///
+/// 实时类需要 [`CAP_SYS_NICE`] 或 [`CAP_SYS_ADMIN`]，修改其他用户的进程需要
+/// [`CAP_SYS_NICE`]，否则返回 EPERM。新的优先级作用于此后提交的块设备请求。
+pub(crate) fn sys_ioprio_set(which: i32, who: u32, ioprio: u32) -> isize {
+    syscall_body!(sys_ioprio_set, {
+        let curr = current();
+        let privileged = curr.task_ext().capable(CAP_SYS_NICE);
+        match ioprio >> IOPRIO_CLASS_SHIFT {
+            IOPRIO_CLASS_RT if !privileged && !curr.task_ext().capable(CAP_SYS_ADMIN) => {
+                return Err(LinuxError::EPERM);
+            }
+            IOPRIO_CLASS_RT | IOPRIO_CLASS_BE | IOPRIO_CLASS_IDLE => {}
+            IOPRIO_CLASS_NONE if ioprio & IOPRIO_LEVEL_MASK == 0 => {}
+            _ => return Err(LinuxError::EINVAL),
+        }
+        for task in ioprio_targets(which, who)? {
+            let ext = task.task_ext();
+            if ext.uid() != curr.task_ext().uid() && !privileged {
+                return Err(LinuxError::EPERM);
+            }
+            ext.set_ioprio(ioprio);
+        }
+        Ok(0)
+    })
+}
+
+/// 找到 `ioprio_set` 和 `ioprio_get` 的 `which` 和 `who` 指定的所有进程
+fn ioprio_targets(which: i32, who: u32) -> LinuxResult<Vec<AxTaskRef>> {
+    if !(IOPRIO_WHO_PROCESS..=IOPRIO_WHO_USER).contains(&which) {
+        return Err(LinuxError::EINVAL);
+    }
+    priority_targets(which - IOPRIO_WHO_PROCESS + PRIO_PROCESS, who)
+}
+
+/// 睡眠 `req` 指定的时间
+///
+/// 期间有信号被递送时提前返回 EINTR，`rem` 不为空时写入剩余的时间。与 Linux 一致，
+/// 即使信号的处理函数设置了 `SA_RESTART` 也不会重新执行。
+pub(crate) fn sys_nanosleep(req: *const timespec, rem: *mut timespec) -> isize {
+    syscall_body!(sys_nanosleep, {
+        let req = read_user(req)?;
+        if req.tv_sec < 0 || !(0..1_000_000_000).contains(&req.tv_nsec) {
+            return Err(LinuxError::EINVAL);
+        }
+        let dur = Duration::new(req.tv_sec as u64, req.tv_nsec as u32);
+        let deadline = axhal::time::monotonic_time() + dur;
+        if current().task_ext().wait_signal(Some(dur), |_| false) == WaitOutcome::Interrupted {
+            if !rem.is_null() {
+                let left = deadline.saturating_sub(axhal::time::monotonic_time());
+                write_user(rem, &timespec::from(left))?;
+            }
+            return Err(LinuxError::EINTR);
+        }
+        Ok(0)
+    })
 }
 
 pub(crate) fn sys_clock_nanosleep(
@@ -17,18 +346,33 @@ pub(crate) fn sys_clock_nanosleep(
     flags: isize,
     req: *const timespec,
     rem: *mut timespec,
-) -> i32 {
+) -> isize {
     // CLOCK defaults to CLOCK_REALTIME
     // flags defaults to 0
 
     if clock_id != api::ctypes::CLOCK_REALTIME as clockid_t {
         // For older linux headers, it does not define ENOTSUP, so we use EOPNOTSUPP instead
-        return -LinuxError::EOPNOTSUPP.code();
+        return -LinuxError::EOPNOTSUPP.code() as isize;
     }
 
     if flags != 0 {
-        return -LinuxError::EOPNOTSUPP.code();
+        return -LinuxError::EOPNOTSUPP.code() as isize;
     }
 
-    unsafe { api::sys_nanosleep(req, rem) }
+    sys_nanosleep(req, rem)
+}
+
+/// 获取调用者所在的 CPU 和 NUMA 节点，任一指针为空时跳过该项
+///
+/// 目前只有一个 NUMA 节点；`tcache` 自 Linux 2.6.24 起不再使用，直接忽略。
+pub(crate) fn sys_getcpu(cpu: *mut u32, node: *mut u32, _tcache: *mut u8) -> isize {
+    syscall_body!(sys_getcpu, {
+        if !cpu.is_null() {
+            write_user(cpu, &(current().task_ext().last_cpu() as u32))?;
+        }
+        if !node.is_null() {
+            write_user(node, &0u32)?;
+        }
+        Ok(0)
+    })
 }
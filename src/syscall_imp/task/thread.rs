@@ -1,8 +1,32 @@
-use arceos_posix_api::{self as api};
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use core::mem::size_of;
+
+use arceos_posix_api::{self as api, ctypes, AT_FDCWD};
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axhal::arch::TrapFrame;
 use axtask::{current, TaskExtRef};
+use memory_addr::VirtAddr;
 use num_enum::TryFromPrimitive;
 
-use crate::{syscall_body, task::clone_task};
+use crate::{
+    mm::{copy_from_user, read_user, read_user_str},
+    syscall_body,
+    syscall_imp::fs::{open_file, Cred, X_OK},
+    task::{clone_task, do_exit, signal::NSIG, unshare_fs, CloneFlags, Personality},
+};
+
+/// 传给 `personality` 时只查询、不修改当前的执行域
+const PERSONALITY_QUERY: u32 = 0xffff_ffff;
+
+/// `prctl`：查询进程能否转储核心
+const PR_GET_DUMPABLE: i32 = 3;
+/// `prctl`：设置进程能否转储核心
+const PR_SET_DUMPABLE: i32 = 4;
+
+/// `execveat` 的选项：`pathname` 为空时执行 `dirfd` 本身
+const AT_EMPTY_PATH: i32 = 0x1000;
+/// `execveat` 的选项：不跟随符号链接
+const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
 
 /// ARCH_PRCTL codes
 ///
@@ -30,8 +54,7 @@ pub(crate) fn sys_getpid() -> i32 {
 }
 
 pub(crate) fn sys_getppid() -> isize {
-    // 当前任务的父任务 PID，如果不存在父任务，返回 0
-    current().task_ext().parent_id().unwrap_or(0) as isize
+    current().task_ext().parent_id() as isize
 }
 
 pub(crate) fn sys_gettid() -> i32 {
@@ -39,17 +62,7 @@ pub(crate) fn sys_gettid() -> i32 {
 }
 
 pub(crate) fn sys_exit(status: i32) -> ! {
-    let curr = current();
-    let clear_child_tid = curr.task_ext().clear_child_tid() as *mut i32;
-    if !clear_child_tid.is_null() {
-        // TODO: check whether the address is valid
-        unsafe {
-            // TODO: Encapsulate all operations that access user-mode memory into a unified function
-            *(clear_child_tid) = 0;
-        }
-        // TODO: wake up threads, which are blocked by futex, and waiting for the address pointed by clear_child_tid
-    }
-    axtask::exit(status);
+    do_exit(status)
 }
 
 /// # Arguments for riscv
@@ -66,6 +79,7 @@ pub(crate) fn sys_exit(status: i32) -> ! {
 /// * `ctid` - usize
 /// * `tls` - usize
 pub fn sys_clone(
+    tf: &TrapFrame,
     flags: usize,
     user_stack: usize,
     ptid_riscv: usize,
@@ -95,15 +109,107 @@ pub fn sys_clone(
         info!("Unsupported signal: 0x{:x}", flags & SIGNAL_MASK);
     }
     let clone_flags = flags & !SIGNAL_MASK;
-    if clone_flags != 0 {
-        info!("Unsupported clone flags: 0x{:x}", clone_flags);
+    let supported_flags = (CloneFlags::CLONE_VM | CloneFlags::CLONE_VFORK).bits() as usize;
+    if clone_flags & !supported_flags != 0 {
+        info!(
+            "Unsupported clone flags: 0x{:x}",
+            clone_flags & !supported_flags
+        );
     }
 
-    if let Ok(new_task_id) = clone_task(flags, stack, ptid, tls, ctid) {
-        new_task_id as isize
-    } else {
-        -1
-    }
+    syscall_body!(sys_clone, {
+        let new_task_id = clone_task(tf, flags, stack, ptid, tls, ctid)?;
+        Ok(new_task_id as isize)
+    })
+}
+
+/// `clone3` 的参数，布局与 Linux 中的 `struct clone_args` 一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct CloneArgs {
+    flags: u64,
+    pidfd: u64,
+    child_tid: u64,
+    parent_tid: u64,
+    exit_signal: u64,
+    /// 子进程用户栈的最低地址，而不是栈顶
+    stack: u64,
+    stack_size: u64,
+    tls: u64,
+    set_tid: u64,
+    set_tid_size: u64,
+    cgroup: u64,
+}
+
+/// 第一版 `struct clone_args` 的大小，更小的结构不被接受
+const CLONE_ARGS_SIZE_VER0: usize = 64;
+
+/// 按 `struct clone_args` 创建子进程
+///
+/// 为了兼容新旧程序，`size` 可以小于或大于内核所知的结构：缺少的字段视为 0，
+/// 多出的部分必须全为 0，否则返回 E2BIG。尚未实现的功能返回 ENOSYS，
+/// 以便 glibc 等 C 库回退到 `clone`。
+pub(crate) fn sys_clone3(tf: &TrapFrame, cl_args: *const u8, size: usize) -> isize {
+    syscall_body!(sys_clone3, {
+        if size < CLONE_ARGS_SIZE_VER0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if size > axhal::mem::PAGE_SIZE_4K {
+            return Err(LinuxError::E2BIG);
+        }
+        let mut buf = vec![0u8; size.max(size_of::<CloneArgs>())];
+        copy_from_user(VirtAddr::from_ptr_of(cl_args), &mut buf[..size])?;
+        if buf[size_of::<CloneArgs>()..].iter().any(|&b| b != 0) {
+            return Err(LinuxError::E2BIG);
+        }
+        let args = unsafe { buf.as_ptr().cast::<CloneArgs>().read_unaligned() };
+
+        // 退出信号单独给出，flags 中不能再包含旧接口的信号位
+        const CSIGNAL: u64 = 0xff;
+        if args.flags & CSIGNAL != 0 || args.exit_signal > NSIG as u64 {
+            return Err(LinuxError::EINVAL);
+        }
+        if args.flags >> 32 != 0 {
+            warn!("Unsupported clone3 flags: {:#x}", args.flags);
+            return Err(LinuxError::ENOSYS);
+        }
+        if args.set_tid_size != 0 || args.cgroup != 0 {
+            warn!("clone3: set_tid and cgroup are not supported");
+            return Err(LinuxError::ENOSYS);
+        }
+        // clone_task 还不会写回 pidfd 和线程 ID，也不会设置 TLS，共享地址空间只支持 vfork，
+        // 拒绝这些标志而不是忽略它们。glibc 只在 ENOSYS 时回退到 clone，
+        // 其余错误会直接返回给调用者
+        let flags = CloneFlags::from_bits_truncate(args.flags as u32);
+        if flags.intersects(
+            CloneFlags::CLONE_PIDFD
+                | CloneFlags::CLONE_SETTLS
+                | CloneFlags::CLONE_PARENT_SETTID
+                | CloneFlags::CLONE_CHILD_SETTID,
+        ) || (flags.contains(CloneFlags::CLONE_VM) && !flags.contains(CloneFlags::CLONE_VFORK))
+        {
+            warn!("Unsupported clone3 flags: {:#x}", args.flags);
+            return Err(LinuxError::ENOSYS);
+        }
+
+        // 栈和大小要么都给出，要么都为 0；支持的架构上栈都向下增长，初始栈顶为栈的最高地址
+        let stack = match (args.stack, args.stack_size) {
+            (0, 0) => None,
+            (0, _) | (_, 0) => return Err(LinuxError::EINVAL),
+            (base, size) => Some(base.checked_add(size).ok_or(LinuxError::EINVAL)? as usize),
+        };
+
+        let flags = (args.flags | args.exit_signal) as usize;
+        let new_task_id = clone_task(
+            tf,
+            flags,
+            stack,
+            args.parent_tid as usize,
+            args.tls as usize,
+            args.child_tid as usize,
+        )?;
+        Ok(new_task_id as isize)
+    })
 }
 
 /// 等待子进程完成任务，若子进程没有完成，则自身可能会用yield轮询
@@ -113,7 +219,7 @@ pub fn sys_clone(
 /// * `exit_code_ptr` - *mut i32
 /// * `option` - WaitFlags
 pub fn sys_wait4(pid: i32, exit_code_ptr: *mut i32, option: i32, _rusage: *mut u8) -> isize {
-    unsafe { crate::task::wait_pid(pid, exit_code_ptr, option) }
+    crate::task::wait_pid(pid, exit_code_ptr, option)
 }
 
 /// 执行一个指定的程序
@@ -123,50 +229,114 @@ pub fn sys_wait4(pid: i32, exit_code_ptr: *mut i32, option: i32, _rusage: *mut u
 /// * `envp` - 环境变量数组指针，类型为 `*const usize`
 ///
 /// # 返回值
-/// 成功时不返回，失败返回 -1
+/// 成功时不返回，失败返回负的错误码
 pub fn sys_execve(path: *const i8, argv: *const usize, envp: *const usize) -> isize {
-    // 转换路径指针为字符串
-    let path_str = match arceos_posix_api::char_ptr_to_str(path) {
-        Ok(p) => p,
-        Err(err) => {
-            error!("Failed to convert path to str: {:?}", err);
-            return -1;
+    sys_execveat(AT_FDCWD as _, path, argv, envp, 0)
+}
+
+/// 以 `dirfd` 为基准解析 `path` 并执行对应的程序
+///
+/// 指定 `AT_EMPTY_PATH` 且 `path` 为空字符串时，执行 `dirfd` 本身所指向的文件，
+/// 即 `fexecve` 的语义。带 `AT_SYMLINK_NOFOLLOW` 时 `path` 是符号链接则返回 `ELOOP`。
+///
+/// # 返回值
+/// 成功时不返回，失败返回负的错误码
+pub fn sys_execveat(
+    dirfd: i32,
+    path: *const i8,
+    argv: *const usize,
+    envp: *const usize,
+    flags: i32,
+) -> isize {
+    syscall_body!(sys_execveat, {
+        if flags & !(AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW) != 0 {
+            return Err(LinuxError::EINVAL);
         }
-    };
+        let path = api::char_ptr_to_str(path)?;
 
-    // 检查路径是否包含多级目录
-    if path_str.split('/').filter(|s| !s.is_empty()).count() > 1 {
-        info!("Multi-level directories are not supported");
-        return -1;
-    }
+        // 在释放原有地址空间之前，将程序文件、参数和环境变量都拷贝到内核中
+        let (name, elf_data) = if path.is_empty() {
+            if flags & AT_EMPTY_PATH == 0 {
+                return Err(LinuxError::ENOENT);
+            }
+            read_exec_file(api::get_file_like(dirfd)?)?
+        } else {
+            let mut open_flags = ctypes::O_RDONLY;
+            if flags & AT_SYMLINK_NOFOLLOW != 0 {
+                open_flags |= ctypes::O_NOFOLLOW;
+            }
+            read_exec_file(open_file(dirfd, path, open_flags, 0, &Cred::current())?)?
+        };
+        let mut args = read_str_array(argv)?;
+        if args.is_empty() {
+            args.push(name.clone());
+        }
+        let envs = read_str_array(envp)?;
 
-    // 检查参数和环境变量是否为空指针，若不为空指针则不支持
-    let argv_valid = unsafe { argv.is_null() || *argv == 0 };
-    let envp_valid = unsafe { envp.is_null() || *envp == 0 };
+        match crate::task::exec(&name, &elf_data, &args, &envs) {
+            Ok(_) => unreachable!("exec should not return"),
+            Err(AxError::InvalidData) => Err::<isize, _>(LinuxError::ENOEXEC),
+            Err(err) => Err(err.into()),
+        }
+    })
+}
 
-    if !argv_valid {
-        info!("argv is not supported");
+/// 检查已打开的程序文件能否执行，读取它的全部内容，同时返回该文件的路径
+fn read_exec_file(file: Arc<dyn api::FileLike>) -> LinuxResult<(String, Vec<u8>)> {
+    Cred::current().check_access(file.stat()?.st_mode, X_OK)?;
+    let file = file
+        .into_any()
+        .downcast::<api::File>()
+        .map_err(|_| LinuxError::EACCES)?;
+    // `O_PATH` 打开的文件不能通过描述符读取，按路径读取它的内容
+    if file.path_only() {
+        let path = file.path();
+        let data = axfs::api::read(&path)?;
+        return Ok((path, data));
     }
-
-    if !envp_valid {
-        info!("envp is not supported");
+    let inner = file.inner().lock();
+    let size = inner.get_attr()?.size() as usize;
+    let mut data = vec![0; size];
+    let mut read = 0;
+    while read < size {
+        let n = inner.read_at(read as u64, &mut data[read..])?;
+        if n == 0 {
+            break;
+        }
+        read += n;
     }
+    data.truncate(read);
+    Ok((file.path(), data))
+}
 
-    // 执行程序
-    match crate::task::exec(path_str) {
-        Ok(_) => {
-            unreachable!("exec should not return");
-        }
-        Err(err) => {
-            error!("Failed to exec: {:?}", err);
-            -1
+/// 单个参数或环境变量的最大长度，与 Linux 的 `MAX_ARG_STRLEN` 相同
+const MAX_ARG_STRLEN: usize = 32 * 4096;
+
+/// 读取用户态以空指针结尾的字符串指针数组，如 `argv` 和 `envp`
+///
+/// 数组或字符串的地址无效时返回 EFAULT，字符串过长时返回 E2BIG。
+fn read_str_array(array: *const usize) -> LinuxResult<Vec<String>> {
+    let mut strs = Vec::new();
+    if array.is_null() {
+        return Ok(strs);
+    }
+    loop {
+        let str_ptr = read_user(array.wrapping_add(strs.len()))?;
+        if str_ptr == 0 {
+            break;
         }
+        let s = read_user_str(str_ptr as *const u8, MAX_ARG_STRLEN).map_err(|err| match err {
+            AxError::InvalidInput => LinuxError::E2BIG,
+            err => err.into(),
+        })?;
+        strs.push(s);
     }
+    Ok(strs)
 }
 
+/// 结束整个进程，目前每个进程只有一个线程，因此与 `exit` 相同
 pub(crate) fn sys_exit_group(status: i32) -> ! {
-    warn!("Temporarily replace sys_exit_group with sys_exit");
-    axtask::exit(status);
+    do_exit(status)
 }
 
 /// To set the clear_child_tid field in the task extended data.
@@ -180,9 +350,63 @@ pub(crate) fn sys_set_tid_address(tid_ptd: *const i32) -> isize {
     })
 }
 
+/// 设置当前进程的执行域，返回原先的值
+///
+/// `persona` 为 0xffffffff 时只返回当前的值。
+pub(crate) fn sys_personality(persona: usize) -> isize {
+    syscall_body!(sys_personality, {
+        let curr = current();
+        let persona = persona as u32;
+        let old = if persona == PERSONALITY_QUERY {
+            curr.task_ext().personality()
+        } else {
+            curr.task_ext()
+                .set_personality(Personality::from_bits_retain(persona))
+        };
+        Ok(old.bits() as isize)
+    })
+}
+
+/// 让当前任务不再与其他任务共享 `flags` 指定的资源
+///
+/// 复制方式与不带对应标志的 clone 相同，原本就不共享的资源保持不变：
+/// * `CLONE_FILES`：clone 总是复制文件描述符表，它从不与其他进程共享，无需处理。
+/// * `CLONE_FS`：复制根目录和当前工作目录。umask 本就属于每个任务，不会共享。
+///
+/// 挂载表是全局的，没有挂载命名空间，因此 `CLONE_NEWNS` 及其他标志返回 `EINVAL`。
+pub(crate) fn sys_unshare(flags: usize) -> isize {
+    syscall_body!(sys_unshare, {
+        let supported = (CloneFlags::CLONE_FILES | CloneFlags::CLONE_FS).bits() as usize;
+        if flags & !supported != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if flags & CloneFlags::CLONE_FS.bits() as usize != 0 {
+            unshare_fs();
+        }
+        Ok(0)
+    })
+}
+
+/// 查询或修改进程的属性
+///
+/// 目前只支持 `PR_GET_DUMPABLE` 和 `PR_SET_DUMPABLE`，其他操作返回 `EINVAL`。与 Linux
+/// 一致，`PR_SET_DUMPABLE` 只接受 0 和 1。
+pub(crate) fn sys_prctl(option: i32, arg2: usize) -> isize {
+    syscall_body!(sys_prctl, {
+        let curr = current();
+        match option {
+            PR_GET_DUMPABLE => Ok(curr.task_ext().dumpable() as isize),
+            PR_SET_DUMPABLE if arg2 <= 1 => {
+                curr.task_ext().set_dumpable(arg2 == 1);
+                Ok(0)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
+
 #[cfg(target_arch = "x86_64")]
 pub(crate) fn sys_arch_prctl(code: i32, addr: u64) -> isize {
-    use axerrno::LinuxError;
     syscall_body!(sys_arch_prctl, {
         match ArchPrctlCode::try_from(code) {
             // TODO: check the legality of the address
@@ -1,8 +1,18 @@
-use core::ffi::c_long;
+use core::{
+    ffi::c_long,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use arceos_posix_api as api;
+use axerrno::{LinuxError, LinuxResult};
 use axtask::{current, TaskExtRef};
 
+use crate::{
+    mm::{read_user, vdso, write_user},
+    syscall_body,
+    task::{nanos_to_clock_ticks, CAP_SYS_TIME, USER_HZ},
+};
+
 pub(crate) fn sys_clock_gettime(clock_id: i32, tp: *mut api::ctypes::timespec) -> i32 {
     unsafe { api::sys_clock_gettime(clock_id, tp) }
 }
@@ -18,14 +28,169 @@ pub(crate) fn sys_gettimeofday(tp: *mut api::ctypes::timeval, _tzp: usize) -> i3
     if ret != 0 {
         return ret;
     }
-    unsafe {
-        (*tp).tv_sec = ts.tv_sec;
-        (*tp).tv_usec = ts.tv_nsec / 1000;
+    if tp.is_null() {
+        return 0;
+    }
+    let tv = api::ctypes::timeval {
+        tv_sec: ts.tv_sec,
+        tv_usec: ts.tv_nsec / 1000,
+    };
+    match write_user(tp, &tv) {
+        Ok(()) => 0,
+        Err(_) => -(LinuxError::EFAULT.code()),
+    }
+}
+
+/// 设置时钟，目前只有 `CLOCK_REALTIME` 可以被设置，需要 `CAP_SYS_TIME` 能力
+///
+/// 修改的是用户可见的墙上时间，内核中按单调时钟计算的超时不受影响；vDSO 中的时间随之更新。
+pub(crate) fn sys_clock_settime(clock_id: i32, tp: *const api::ctypes::timespec) -> isize {
+    syscall_body!(sys_clock_settime, {
+        if clock_id == api::ctypes::CLOCK_REALTIME as i32
+            && !current().task_ext().capable(CAP_SYS_TIME)
+        {
+            return Err(LinuxError::EPERM);
+        }
+        let ts = read_user(tp)?;
+        let ret = unsafe { api::sys_clock_settime(clock_id, &ts) };
+        vdso::update();
+        Ok(ret as isize)
+    })
+}
+
+/// 设置墙上时间，时区 `tz` 已被废弃，直接忽略
+pub(crate) fn sys_settimeofday(tv: *const api::ctypes::timeval, _tz: usize) -> isize {
+    syscall_body!(sys_settimeofday, {
+        if tv.is_null() {
+            return Ok(0);
+        }
+        if !current().task_ext().capable(CAP_SYS_TIME) {
+            return Err(LinuxError::EPERM);
+        }
+        let tv = read_user(tv)?;
+        if tv.tv_usec < 0 || tv.tv_usec >= 1_000_000 {
+            return Err(LinuxError::EINVAL);
+        }
+        let ts = api::ctypes::timespec {
+            tv_sec: tv.tv_sec,
+            tv_nsec: tv.tv_usec * 1000,
+        };
+        let ret = unsafe { api::sys_clock_settime(api::ctypes::CLOCK_REALTIME as i32, &ts) };
+        vdso::update();
+        Ok(ret as isize)
+    })
+}
+
+/// `adjtimex` 使用的时钟状态，布局与 Linux 中的 `struct timex` 一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Timex {
+    modes: u32,
+    offset: c_long,
+    freq: c_long,
+    maxerror: c_long,
+    esterror: c_long,
+    status: i32,
+    constant: c_long,
+    precision: c_long,
+    tolerance: c_long,
+    time: api::ctypes::timeval,
+    tick: c_long,
+    ppsfreq: c_long,
+    jitter: c_long,
+    shift: i32,
+    stabil: c_long,
+    jitcnt: c_long,
+    calcnt: c_long,
+    errcnt: c_long,
+    stbcnt: c_long,
+    tai: i32,
+    _reserved: [i32; 11],
+}
+
+/// 将 `time` 加到当前时间上
+const ADJ_SETOFFSET: u32 = 0x0100;
+/// 此后 `time.tv_usec` 以微秒为单位
+const ADJ_MICRO: u32 = 0x1000;
+/// 此后 `time.tv_usec` 以纳秒为单位，本次调用给出的 `time` 也是如此
+const ADJ_NANO: u32 = 0x2000;
+/// 只读取状态，不做任何修改
+const ADJ_OFFSET_SS_READ: u32 = 0xa001;
+/// 时钟已同步
+const TIME_OK: isize = 0;
+/// 状态位：`time.tv_usec` 以纳秒为单位
+const STA_NANO: i32 = 0x2000;
+
+/// 是否处于纳秒模式，由 `ADJ_NANO` 和 `ADJ_MICRO` 切换
+static NANO_MODE: AtomicBool = AtomicBool::new(false);
+
+/// 读取或调整 `CLOCK_REALTIME` 的状态
+///
+/// 只支持 `ADJ_SETOFFSET`、`ADJ_NANO` 和 `ADJ_MICRO`，其余的调整（频率、误差估计等）
+/// 被接受但不产生效果。返回的频率和偏移总是 0，状态总是 [`TIME_OK`]。
+fn do_adjtimex(buf: *mut Timex) -> LinuxResult<isize> {
+    let mut timex = read_user(buf)?;
+    let modes = timex.modes;
+    if modes != 0 && modes != ADJ_OFFSET_SS_READ {
+        if !current().task_ext().capable(CAP_SYS_TIME) {
+            return Err(LinuxError::EPERM);
+        }
+        if modes & ADJ_SETOFFSET != 0 {
+            let unit = if modes & ADJ_NANO != 0 { 1 } else { 1000 };
+            let tv = timex.time;
+            if tv.tv_usec < 0 || tv.tv_usec * unit >= 1_000_000_000 {
+                return Err(LinuxError::EINVAL);
+            }
+            api::adjust_realtime(tv.tv_sec as i64 * 1_000_000_000 + (tv.tv_usec * unit) as i64);
+            vdso::update();
+        }
+        if modes & ADJ_NANO != 0 {
+            NANO_MODE.store(true, Ordering::Relaxed);
+        } else if modes & ADJ_MICRO != 0 {
+            NANO_MODE.store(false, Ordering::Relaxed);
+        }
     }
-    0
+
+    let now = api::realtime();
+    let nano = NANO_MODE.load(Ordering::Relaxed);
+    timex = Timex {
+        modes,
+        status: if nano { STA_NANO } else { 0 },
+        // 以微秒为单位的精度
+        precision: 1,
+        // 最大频率误差 500 ppm，按 `freq` 的格式左移 16 位
+        tolerance: 500 << 16,
+        time: api::ctypes::timeval {
+            tv_sec: now.as_secs() as _,
+            tv_usec: if nano {
+                now.subsec_nanos() as _
+            } else {
+                now.subsec_micros() as _
+            },
+        },
+        tick: (1_000_000 / USER_HZ) as _,
+        ..Default::default()
+    };
+    write_user(buf, &timex)?;
+    Ok(TIME_OK)
+}
+
+pub(crate) fn sys_adjtimex(buf: *mut Timex) -> isize {
+    syscall_body!(sys_adjtimex, do_adjtimex(buf))
+}
+
+/// 与 `adjtimex` 相同，但需要指定时钟，目前只支持 `CLOCK_REALTIME`
+pub(crate) fn sys_clock_adjtime(clock_id: i32, buf: *mut Timex) -> isize {
+    syscall_body!(sys_clock_adjtime, {
+        if clock_id != api::ctypes::CLOCK_REALTIME as i32 {
+            return Err(LinuxError::EINVAL);
+        }
+        do_adjtimex(buf)
+    })
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub(crate) struct Tms {
     tms_utime: c_long,
     tms_stime: c_long,
@@ -35,34 +200,73 @@ pub(crate) struct Tms {
 
 /// 功能：获取进程时间；
 /// 输入：tms结构体指针，用于获取保存当前进程的运行时间数据；
-/// 返回值：成功返回已经过去的滴答数，失败返回-1;
-pub(crate) fn sys_times(buf: *mut Tms) -> i32 {
-    if buf.is_null() {
-        return -1;
-    }
-
+/// 返回值：成功返回自启动以来经过的时钟滴答数（以 `USER_HZ` 为单位），`buf` 不为空但地址无效时返回 -EFAULT;
+pub(crate) fn sys_times(buf: *mut Tms) -> isize {
     let (user_time, kernel_time) = current().task_ext().time_stat.lock().info();
-    let mut children_user_time = 0;
-    let mut children_kernel_time = 0;
-    current()
-        .task_ext()
-        .children
-        .lock()
-        .iter()
-        .filter(|child| child.state() == axtask::TaskState::Exited)
-        .for_each(|child| {
-            let (child_user_time, child_kernel_time) = child.task_ext().time_stat.lock().info();
-            children_user_time += child_user_time;
-            children_kernel_time += child_kernel_time;
-        });
+    let (children_user_time, children_kernel_time) = current().task_ext().children_time();
     let tms = Tms {
-        tms_utime: user_time as c_long,
-        tms_stime: kernel_time as c_long,
-        tms_cutime: children_user_time as c_long,
-        tms_cstime: children_kernel_time as c_long,
+        tms_utime: nanos_to_clock_ticks(user_time) as c_long,
+        tms_stime: nanos_to_clock_ticks(kernel_time) as c_long,
+        tms_cutime: nanos_to_clock_ticks(children_user_time) as c_long,
+        tms_cstime: nanos_to_clock_ticks(children_kernel_time) as c_long,
     };
-    unsafe {
-        *buf = tms;
+    // 与 Linux 一致，`buf` 可以为空，此时只返回时钟滴答数
+    if !buf.is_null() && write_user(buf, &tms).is_err() {
+        return -(LinuxError::EFAULT.code() as isize);
     }
-    axhal::time::current_ticks() as i32
+    nanos_to_clock_ticks(axhal::time::monotonic_time_nanos()) as isize
+}
+
+/// 统计本进程（包括所有线程）的资源使用情况
+const RUSAGE_SELF: i32 = 0;
+/// 统计已被回收的子进程的资源使用情况
+const RUSAGE_CHILDREN: i32 = -1;
+/// 只统计调用线程的资源使用情况
+const RUSAGE_THREAD: i32 = 1;
+
+/// sys_getrusage 中指定的结构体类型
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+pub(crate) struct Rusage {
+    /// 用户态时间
+    ru_utime: api::ctypes::timeval,
+    /// 内核态时间
+    ru_stime: api::ctypes::timeval,
+    /// 最大常驻内存（KB）
+    ru_maxrss: c_long,
+    /// 其余字段目前均为 0
+    _unused: [c_long; 13],
+}
+
+fn nanos_to_timeval(nanos: u64) -> api::ctypes::timeval {
+    core::time::Duration::from_nanos(nanos).into()
+}
+
+/// 功能：获取资源使用情况；
+/// 输入：who 为 RUSAGE_SELF、RUSAGE_CHILDREN 或 RUSAGE_THREAD，usage 为保存结果的结构体指针；
+/// 返回值：成功返回0，失败返回负的错误码；
+pub(crate) fn sys_getrusage(who: i32, usage: *mut Rusage) -> isize {
+    syscall_body!(sys_getrusage, {
+        let curr = current();
+        let (user_time, kernel_time) = match who {
+            // 目前线程与进程一一对应，故二者的统计相同
+            RUSAGE_SELF | RUSAGE_THREAD => curr.task_ext().time_stat.lock().info(),
+            RUSAGE_CHILDREN => curr.task_ext().children_time(),
+            _ => return Err(LinuxError::EINVAL),
+        };
+
+        let ru_maxrss = if who == RUSAGE_CHILDREN {
+            0
+        } else {
+            (curr.task_ext().aspace.lock().mapped_size() / 1024) as c_long
+        };
+        let rusage = Rusage {
+            ru_utime: nanos_to_timeval(user_time),
+            ru_stime: nanos_to_timeval(kernel_time),
+            ru_maxrss,
+            ..Default::default()
+        };
+        write_user(usage, &rusage).map_err(|_| LinuxError::EFAULT)?;
+        Ok(0)
+    })
 }
@@ -0,0 +1,158 @@
+use core::time::Duration;
+
+use arceos_posix_api::ctypes::{timespec, CLOCK_MONOTONIC, CLOCK_REALTIME};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{current, TaskExtRef};
+
+use crate::{
+    mm::{read_user, write_user},
+    syscall_body,
+    task::{
+        signal::{NSIG, SIGALRM},
+        timer::{self, TimerClock, TimerId, TimerNotify},
+    },
+};
+
+/// 到期时发送信号
+const SIGEV_SIGNAL: i32 = 0;
+/// 到期时不通知
+const SIGEV_NONE: i32 = 1;
+/// 到期时向指定线程发送信号
+const SIGEV_THREAD_ID: i32 = 4;
+
+/// `timer_settime` 的选项：到期时间是时钟上的绝对时刻
+const TIMER_ABSTIME: i32 = 1;
+
+/// 定时器到期时的通知方式，布局与 Linux 中的 `struct sigevent` 一致
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SigEvent {
+    value: u64,
+    signo: i32,
+    notify: i32,
+    /// `SIGEV_THREAD_ID` 时为目标线程的 TID
+    tid: i32,
+    _pad: [i32; 11],
+}
+
+/// 定时器的到期时间和周期，布局与 Linux 中的 `struct itimerspec` 一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ITimerSpec {
+    interval: timespec,
+    value: timespec,
+}
+
+fn timespec_to_duration(ts: timespec) -> LinuxResult<Duration> {
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+fn duration_to_timespec(dur: Duration) -> timespec {
+    timespec {
+        tv_sec: dur.as_secs() as _,
+        tv_nsec: dur.subsec_nanos() as _,
+    }
+}
+
+/// 创建 POSIX 定时器，编号写入 `timerid`
+///
+/// `sevp` 为空时等同于发送 SIGALRM、以定时器编号作为 `si_value`。
+pub(crate) fn sys_timer_create(
+    clock_id: i32,
+    sevp: *const SigEvent,
+    timerid: *mut TimerId,
+) -> isize {
+    syscall_body!(sys_timer_create, {
+        let clock = match clock_id as u32 {
+            CLOCK_REALTIME => TimerClock::Realtime,
+            CLOCK_MONOTONIC => TimerClock::Monotonic,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let notify = if sevp.is_null() {
+            TimerNotify::Signal {
+                signo: SIGALRM,
+                value: None,
+            }
+        } else {
+            let event = read_user(sevp)?;
+            match event.notify {
+                SIGEV_NONE => TimerNotify::None,
+                SIGEV_SIGNAL | SIGEV_THREAD_ID => {
+                    // 目前每个进程只有一个线程
+                    if event.notify == SIGEV_THREAD_ID
+                        && event.tid as usize != current().task_ext().proc_id
+                    {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    if event.signo <= 0 || event.signo as usize > NSIG {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    TimerNotify::Signal {
+                        signo: event.signo as usize,
+                        value: Some(event.value),
+                    }
+                }
+                _ => return Err(LinuxError::EINVAL),
+            }
+        };
+        let id = timer::create(clock, notify)?;
+        if let Err(err) = write_user(timerid, &id) {
+            timer::delete(id)?;
+            return Err(err.into());
+        }
+        Ok(0)
+    })
+}
+
+/// 启动或停止定时器
+pub(crate) fn sys_timer_settime(
+    timerid: TimerId,
+    flags: i32,
+    new_value: *const ITimerSpec,
+    old_value: *mut ITimerSpec,
+) -> isize {
+    syscall_body!(sys_timer_settime, {
+        let new = read_user(new_value)?;
+        let value = timespec_to_duration(new.value)?;
+        let interval = timespec_to_duration(new.interval)?;
+        let (remaining, old_interval) =
+            timer::set(timerid, value, interval, flags & TIMER_ABSTIME != 0)?;
+        if !old_value.is_null() {
+            let old = ITimerSpec {
+                interval: duration_to_timespec(old_interval),
+                value: duration_to_timespec(remaining),
+            };
+            write_user(old_value, &old)?;
+        }
+        Ok(0)
+    })
+}
+
+/// 获取定时器距离下一次到期的时间和周期
+pub(crate) fn sys_timer_gettime(timerid: TimerId, curr_value: *mut ITimerSpec) -> isize {
+    syscall_body!(sys_timer_gettime, {
+        let (remaining, interval) = timer::get(timerid)?;
+        let spec = ITimerSpec {
+            interval: duration_to_timespec(interval),
+            value: duration_to_timespec(remaining),
+        };
+        write_user(curr_value, &spec)?;
+        Ok(0)
+    })
+}
+
+/// 获取定时器最近一次发出信号时错过的到期次数
+pub(crate) fn sys_timer_getoverrun(timerid: TimerId) -> isize {
+    syscall_body!(sys_timer_getoverrun, Ok(timer::overrun(timerid)?))
+}
+
+/// 删除定时器
+pub(crate) fn sys_timer_delete(timerid: TimerId) -> isize {
+    syscall_body!(sys_timer_delete, {
+        timer::delete(timerid)?;
+        Ok(0)
+    })
+}
@@ -1,24 +1,190 @@
-use core::sync::atomic::AtomicU64;
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 
-use alloc::{
-    string::{String, ToString}, sync::Arc, vec::Vec
-};
+use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc, vec::Vec};
 
-use arceos_posix_api::FD_TABLE;
-use axerrno::{AxError, AxResult};
-use axfs::{CURRENT_DIR, CURRENT_DIR_PATH};
+use arceos_posix_api::{WaitResult, FD_CLOEXEC, FD_LIMIT, FD_TABLE};
+use axerrno::{AxError, AxResult, LinuxError};
+use axfs::{CURRENT_DIR, CURRENT_DIR_PATH, CURRENT_ROOT_PATH};
 use axhal::arch::{TrapFrame, UspaceContext};
 use axmm::AddrSpace;
 use axns::{AxNamespace, AxNamespaceIf};
-use axsync::Mutex;
-use axtask::{current, AxTaskRef, TaskExtRef, TaskInner, WeakAxTaskRef};
+use axsync::{spin::SpinNoIrq, Mutex};
+use axtask::{current, AxTaskRef, TaskExtRef, TaskInner, WaitQueue, WeakAxTaskRef};
 use bitflags::bitflags;
+use completion::Completion;
 use heap::HeapManager;
-use memory_addr::MemoryAddr;
+use memory_addr::{VirtAddr, PAGE_SIZE_4K};
+use ptrace::PtraceState;
+use rlimit::RLimits;
+use signal::{SigInfo, SignalActions, SignalState, CLD_DUMPED, CLD_EXITED, CLD_KILLED};
 use time::TimeStat;
+use timer::TimerTable;
+
+use crate::syscall_imp::IoRing;
+
+pub use capability::{
+    Capabilities, CAP_KILL, CAP_SETUID, CAP_SYS_ADMIN, CAP_SYS_BOOT, CAP_SYS_CHROOT, CAP_SYS_NICE,
+    CAP_SYS_PTRACE, CAP_SYS_RESOURCE, CAP_SYS_TIME,
+};
+pub use cred::UserIds;
+pub use time::{nanos_to_clock_ticks, USER_HZ};
 
+mod capability;
+mod completion;
+mod coredump;
+mod cred;
 mod heap;
+pub mod ioprio;
+pub mod kstack;
+pub mod procfs;
+pub mod ptrace;
+pub mod rlimit;
+pub mod sched;
+pub mod signal;
 mod time;
+pub mod timer;
+
+/// 进程号
+pub type Pid = usize;
+
+/// 全局进程表，记录每个 PID 对应的任务
+///
+/// 任务在创建时加入，在被父进程回收时移除。僵尸进程的任务在退出后不久即被释放，
+/// 此后只在父进程中留有记录，[`find_task_by_pid`] 查找它会失败，
+/// 需要时由 [`pid_exists`] 一并检查父进程中的记录。
+/// PID 直接取自单调递增的任务 ID，不会被复用，
+/// 所以已回收的 PID 总是查找失败，而不会指向另一个新进程。
+static PID_TABLE: Mutex<BTreeMap<Pid, WeakAxTaskRef>> = Mutex::new(BTreeMap::new());
+
+/// PID 的上限，即 `/proc/sys/kernel/pid_max`，PID 不小于它时不能再创建任务
+///
+/// 默认值与 64 位的 Linux 允许的最大值一致。PID 不会被复用，调低后已经超过它的 PID
+/// 不会回绕，之后创建任务都返回 EAGAIN。
+pub static PID_MAX: AtomicUsize = AtomicUsize::new(PID_MAX_LIMIT);
+/// [`PID_MAX`] 可以取的值
+pub const PID_MAX_RANGE: core::ops::RangeInclusive<usize> = 301..=PID_MAX_LIMIT;
+/// 64 位的 Linux 中 PID 上限的最大值
+const PID_MAX_LIMIT: usize = 1 << 22;
+
+/// 返回所有尚未被回收的进程的 PID，包括僵尸进程
+pub fn all_pids() -> Vec<Pid> {
+    let mut pids: Vec<Pid> = PID_TABLE
+        .lock()
+        .iter()
+        .filter(|(_, task)| task.strong_count() > 0)
+        .map(|(&pid, _)| pid)
+        .collect();
+    for task in all_tasks() {
+        let zombies = task.task_ext().zombies.lock();
+        pids.extend(zombies.iter().map(|zombie| zombie.pid));
+    }
+    pids.sort_unstable();
+    pids.dedup();
+    pids
+}
+
+/// 返回所有尚未退出的进程
+///
+/// 持有 PID 表的锁时取得所有任务的引用，因此得到的是同一时刻的快照，其中的任务之后
+/// 退出也仍可以安全地访问。
+pub fn all_tasks() -> Vec<AxTaskRef> {
+    PID_TABLE
+        .lock()
+        .values()
+        .filter_map(|task| task.upgrade())
+        .filter(|task| task.state() != axtask::TaskState::Exited)
+        .collect()
+}
+
+/// 根据 PID 查找任务，任务不存在或已被回收时返回 `None`
+pub fn find_task_by_pid(pid: Pid) -> Option<AxTaskRef> {
+    let mut table = PID_TABLE.lock();
+    let task = table.get(&pid)?.upgrade();
+    if task.is_none() {
+        // 任务已被释放但未经回收（如父任务是内核线程），顺便清理表项
+        table.remove(&pid);
+    }
+    task
+}
+
+fn register_pid(task: &AxTaskRef) {
+    PID_TABLE
+        .lock()
+        .insert(task.task_ext().proc_id, Arc::downgrade(task));
+}
+
+fn unregister_pid(pid: Pid) {
+    PID_TABLE.lock().remove(&pid);
+}
+
+/// 父进程已经退出的进程，由内核主线程代替 init 进程回收
+static ORPHANS: Mutex<Vec<AxTaskRef>> = Mutex::new(Vec::new());
+
+/// 回收已经退出的孤儿进程
+pub fn reap_orphans() {
+    ORPHANS.lock().retain(|task| {
+        if task.state() != axtask::TaskState::Exited {
+            return true;
+        }
+        unregister_pid(task.task_ext().proc_id);
+        false
+    });
+}
+
+/// 向进程及其所有后代发送 SIGKILL，已交给内核主线程的孤儿进程也一并结束
+#[cfg(feature = "junior")]
+pub fn kill_tree(task: &AxTaskRef) {
+    fn kill(task: &AxTaskRef) {
+        let children = task.task_ext().children.lock().clone();
+        for child in &children {
+            kill(child);
+        }
+        task.task_ext()
+            .send_signal(SigInfo::kernel(signal::SIGKILL));
+    }
+    kill(task);
+    let orphans = ORPHANS.lock().clone();
+    for orphan in &orphans {
+        kill(orphan);
+    }
+}
+
+/// 已退出、等待父进程回收的子进程
+///
+/// 父进程只保留 `wait4` 需要的信息，子进程的内核栈、地址空间和资源命名空间
+/// 在它退出后随任务一起释放，不必等到父进程回收。
+/// `/proc/[pid]` 所需的少量信息也记录在这里。
+#[derive(Debug, Clone)]
+struct ZombieRecord {
+    pid: Pid,
+    /// 任务名，即程序的路径
+    name: String,
+    /// `wait4` 报告的状态
+    status: i32,
+    /// 进程退出时的有效用户 ID
+    uid: u32,
+    /// 子进程在 (用户态, 内核态) 的运行时间（纳秒）
+    time: (u64, u64),
+    /// 子进程已回收的后代在 (用户态, 内核态) 的累计时间（纳秒）
+    children_time: (u64, u64),
+    /// 进程创建时单调时钟的纳秒数
+    start_time: u64,
+}
+
+/// 查找尚未被回收的僵尸进程，返回父进程的 PID 和它的记录
+fn find_zombie(pid: Pid) -> Option<(Pid, ZombieRecord)> {
+    all_tasks().iter().find_map(|parent| {
+        let ext = parent.task_ext();
+        let zombies = ext.zombies.lock();
+        let zombie = zombies.iter().find(|zombie| zombie.pid == pid)?;
+        Some((ext.proc_id, zombie.clone()))
+    })
+}
+
+/// PID 是否对应一个尚未被回收的进程，包括僵尸进程
+pub fn pid_exists(pid: Pid) -> bool {
+    find_task_by_pid(pid).is_some() || find_zombie(pid).is_some()
+}
 
 /// Task extended data for the monolithic kernel.
 pub struct TaskExt {
@@ -37,15 +203,98 @@ pub struct TaskExt {
     /// The heap manager
     pub heap: Arc<Mutex<HeapManager>>,
     /// The time statistics
-    pub time_stat: Arc<Mutex<TimeStat>>,
+    ///
+    /// 会在陷入处理和上下文切换路径中访问，因此使用关中断的自旋锁。
+    pub time_stat: Arc<SpinNoIrq<TimeStat>>,
+    /// 已被回收的子进程（及其已回收的后代）在用户态的累计时间（纳秒）
+    cutime: AtomicU64,
+    /// 已被回收的子进程（及其已回收的后代）在内核态的累计时间（纳秒）
+    cstime: AtomicU64,
     /// The resource namespace
     pub ns: AxNamespace,
     /// Parent
     pub parent: Option<WeakAxTaskRef>,
     /// Children
+    ///
+    /// 只包含尚未退出的子进程，子进程退出时将自己移到 `zombies` 中。
     pub children: Mutex<Vec<AxTaskRef>>,
+    /// 已退出、尚未被回收的子进程，与 `children` 同时持有锁时先锁 `children`
+    zombies: Mutex<Vec<ZombieRecord>>,
+    /// 在 `wait4` 中等待子进程退出或被跟踪者停止的任务
+    pub child_wq: arceos_posix_api::WaitQueue,
+    /// 由 vfork 创建时，父进程在其上等待，直到当前进程执行 exec 或退出
+    vfork_done: Option<Arc<Completion>>,
+    /// 进程的执行域（personality），在 clone 和 exec 时保留
+    personality: AtomicU32,
+    /// 进程的能力集合，在 clone 和 exec 时保留
+    ///
+    /// TODO: 按照用户 ID 在 exec 时重新计算
+    capabilities: Mutex<Capabilities>,
+    /// 进程的用户 ID，与能力集合同时持有锁时先锁它
+    uids: Mutex<UserIds>,
+    /// 创建文件时从请求的权限中去掉的位，在 clone 和 exec 时保留
+    umask: AtomicU32,
+    /// 信号处理方式
+    pub signal_actions: Arc<Mutex<SignalActions>>,
+    /// 信号掩码、待处理信号和备用信号栈
+    ///
+    /// 会在调度器锁内检查，因此使用关中断的自旋锁，持有时不能访问用户内存。
+    pub signal: SpinNoIrq<SignalState>,
+    /// 等待信号到达的任务
+    pub signal_wq: WaitQueue,
+    /// 导致进程终止的信号，为 0 表示进程正常退出，转储了核心时带有 [`CORE_DUMPED`] 位
+    term_signal: AtomicU32,
+    /// 被信号终止时能否转储核心，即 `PR_SET_DUMPABLE` 的设置，fork 时复制，exec 时恢复
+    dumpable: AtomicBool,
+    /// 最近一次运行该任务的 CPU
+    last_cpu: AtomicUsize,
+    /// 进程创建的 POSIX 定时器
+    pub timers: Mutex<TimerTable>,
+    /// 是否调用过 `mlockall(MCL_FUTURE)`，此后新建的映射会立即分配物理页
+    mlock_future: AtomicBool,
+    /// 批量 I/O 环，由 `io_setup_lite` 建立，fork 时不继承，exec 时释放
+    io_ring: Mutex<Option<IoRing>>,
+    /// 被跟踪时的跟踪状态
+    ///
+    /// 被跟踪者在 `signal_wq` 上等待跟踪者让它继续运行，等待条件会检查该状态，因此使用关中断的自旋锁。
+    pub ptrace: SpinNoIrq<PtraceState>,
+    /// 当前进程跟踪的进程
+    pub tracees: Mutex<Vec<WeakAxTaskRef>>,
+    /// 进程创建时单调时钟的纳秒数
+    start_time: u64,
+    /// 最近一次 exec 时的命令行参数，fork 时复制
+    pub cmdline: Mutex<Vec<String>>,
+    /// 资源限制，fork 时复制
+    pub rlimits: Mutex<RLimits>,
+    /// 观察到的最大常驻内存页数，在采样和进程退出时更新
+    max_rss: AtomicUsize,
+    /// 正在执行的系统调用号，不在系统调用中时为 [`NO_SYSCALL`]
+    syscall: AtomicUsize,
+    /// 进入正在执行的系统调用时是否开中断
+    syscall_irqs: AtomicBool,
+    /// 是否已经警告过内核栈使用量过高
+    kstack_warned: AtomicBool,
+    /// nice 值，决定时间片的长度，在 clone 和 exec 时保留
+    nice: AtomicI32,
+    /// 调度策略，即 [`sched::SchedPolicy`] 的值，在 clone 和 exec 时保留
+    sched_policy: AtomicU32,
+    /// 实时调度策略的优先级，普通调度策略为 0，在 clone 和 exec 时保留
+    rt_priority: AtomicU32,
+    /// I/O 优先级，即 `ioprio_set` 设置的值，在 clone 和 exec 时保留
+    ioprio: AtomicU32,
+    /// 当前时间片结束时单调时钟的纳秒数
+    slice_end: AtomicU64,
 }
 
+/// 表示进程不在系统调用中
+const NO_SYSCALL: usize = usize::MAX;
+
+/// `wait4` 报告的状态中表示子进程转储了核心的位
+const CORE_DUMPED: u32 = 0x80;
+
+/// 初始进程的文件创建掩码
+const DEFAULT_UMASK: u32 = 0o022;
+
 impl TaskExt {
     pub fn new(
         proc_id: usize,
@@ -59,21 +308,263 @@ impl TaskExt {
             clear_child_tid: AtomicU64::new(0),
             aspace,
             heap: Arc::new(Mutex::new(HeapManager::default())),
-            time_stat: Arc::new(Mutex::new(TimeStat::new())),
+            time_stat: Arc::new(SpinNoIrq::new(TimeStat::new())),
+            cutime: AtomicU64::new(0),
+            cstime: AtomicU64::new(0),
             ns: AxNamespace::new_thread_local(),
             parent: Some(Arc::downgrade(parent)),
             children: Mutex::new(Vec::new()),
+            zombies: Mutex::new(Vec::new()),
+            child_wq: arceos_posix_api::WaitQueue::new(),
+            vfork_done: None,
+            personality: AtomicU32::new(0),
+            capabilities: Mutex::new(Capabilities::root()),
+            uids: Mutex::new(UserIds::root()),
+            umask: AtomicU32::new(DEFAULT_UMASK),
+            signal_actions: Arc::new(Mutex::new(SignalActions::new())),
+            signal: SpinNoIrq::new(SignalState::default()),
+            signal_wq: WaitQueue::new(),
+            term_signal: AtomicU32::new(0),
+            dumpable: AtomicBool::new(true),
+            last_cpu: AtomicUsize::new(axhal::cpu::this_cpu_id()),
+            timers: Mutex::new(TimerTable::default()),
+            mlock_future: AtomicBool::new(false),
+            io_ring: Mutex::new(None),
+            ptrace: SpinNoIrq::new(PtraceState::default()),
+            tracees: Mutex::new(Vec::new()),
+            start_time: axhal::time::monotonic_time_nanos(),
+            cmdline: Mutex::new(Vec::new()),
+            rlimits: Mutex::new(RLimits::default()),
+            max_rss: AtomicUsize::new(0),
+            syscall: AtomicUsize::new(NO_SYSCALL),
+            syscall_irqs: AtomicBool::new(false),
+            kstack_warned: AtomicBool::new(false),
+            nice: AtomicI32::new(0),
+            sched_policy: AtomicU32::new(0),
+            rt_priority: AtomicU32::new(0),
+            ioprio: AtomicU32::new(0),
+            slice_end: AtomicU64::new(0),
         }
     }
 
     pub(crate) fn clear_child_tid(&self) -> u64 {
-        self.clear_child_tid
-            .load(core::sync::atomic::Ordering::Relaxed)
+        self.clear_child_tid.load(Ordering::Relaxed)
     }
 
     pub(crate) fn set_clear_child_tid(&self, clear_child_tid: u64) {
         self.clear_child_tid
-            .store(clear_child_tid, core::sync::atomic::Ordering::Relaxed);
+            .store(clear_child_tid, Ordering::Relaxed);
+    }
+
+    /// 返回已回收子进程的 (用户态累计时间, 内核态累计时间)，单位为纳秒
+    pub fn children_time(&self) -> (u64, u64) {
+        (
+            self.cutime.load(Ordering::Relaxed),
+            self.cstime.load(Ordering::Relaxed),
+        )
+    }
+
+    /// 回收子进程时，将其自身及其已回收后代的运行时间累加到父进程中
+    fn add_children_time(&self, child: &ZombieRecord) {
+        self.cutime
+            .fetch_add(child.time.0 + child.children_time.0, Ordering::Relaxed);
+        self.cstime
+            .fetch_add(child.time.1 + child.children_time.1, Ordering::Relaxed);
+    }
+
+    /// 若当前进程由 vfork 创建，唤醒等待中的父进程
+    ///
+    /// 在 exec 成功或进程退出时调用，多次调用只有第一次生效。
+    pub fn notify_vfork_done(&self) {
+        if let Some(done) = &self.vfork_done {
+            done.complete();
+        }
+    }
+
+    /// 返回当前的执行域
+    pub fn personality(&self) -> Personality {
+        Personality::from_bits_retain(self.personality.load(Ordering::Relaxed))
+    }
+
+    /// 设置执行域，返回原先的值
+    pub fn set_personality(&self, personality: Personality) -> Personality {
+        Personality::from_bits_retain(self.personality.swap(personality.bits(), Ordering::Relaxed))
+    }
+
+    /// 新建的映射是否需要立即分配物理页
+    pub fn mlock_future(&self) -> bool {
+        self.mlock_future.load(Ordering::Relaxed)
+    }
+
+    /// 设置新建的映射是否需要立即分配物理页
+    pub fn set_mlock_future(&self, enabled: bool) {
+        self.mlock_future.store(enabled, Ordering::Relaxed);
+    }
+
+    /// 批量 I/O 环，见 [`IoRing`]
+    pub(crate) fn io_ring(&self) -> &Mutex<Option<IoRing>> {
+        &self.io_ring
+    }
+
+    /// 返回当前的能力集合
+    pub fn capabilities(&self) -> Capabilities {
+        *self.capabilities.lock()
+    }
+
+    /// 按 `capset` 的规则修改能力集合，不允许的修改返回 `false`
+    pub fn set_capabilities(&self, caps: Capabilities) -> bool {
+        self.capabilities.lock().set(caps)
+    }
+
+    /// 当前进程是否拥有指定的能力
+    pub fn capable(&self, cap: u32) -> bool {
+        self.capabilities.lock().has(cap)
+    }
+
+    /// 进程的文件创建掩码
+    pub fn umask(&self) -> u32 {
+        self.umask.load(Ordering::Relaxed)
+    }
+
+    /// 设置文件创建掩码，只保留权限位，返回原先的值
+    pub fn set_umask(&self, umask: u32) -> u32 {
+        self.umask.swap(umask & 0o777, Ordering::Relaxed)
+    }
+
+    /// 进程的实际用户 ID
+    pub fn uid(&self) -> u32 {
+        self.uids.lock().ruid
+    }
+
+    /// 进程的有效用户 ID，检查文件权限时使用
+    pub fn euid(&self) -> u32 {
+        self.uids.lock().euid
+    }
+
+    /// 进程的实际、有效和保存的用户 ID
+    pub fn user_ids(&self) -> UserIds {
+        *self.uids.lock()
+    }
+
+    /// 按 `setuid` 的规则修改用户 ID，并相应地调整能力集合，不允许时返回 `false`
+    pub fn setuid(&self, uid: u32) -> bool {
+        let mut uids = self.uids.lock();
+        let mut caps = self.capabilities.lock();
+        let old = *uids;
+        if !uids.setuid(uid, caps.has(CAP_SETUID)) {
+            return false;
+        }
+        caps.update_for_uids(&old, &uids);
+        true
+    }
+
+    /// 当前进程是否可以读写 `target` 的内存或跟踪它
+    ///
+    /// 要求两者属于同一用户，或者拥有 [`CAP_SYS_PTRACE`]。
+    pub fn may_access(&self, target: &TaskExt) -> bool {
+        self.uid() == target.uid() || self.capable(CAP_SYS_PTRACE)
+    }
+
+    /// 当前进程是否可以向 `target` 发送信号
+    ///
+    /// 要求两者属于同一用户，或者拥有 [`CAP_KILL`]。
+    pub fn may_signal(&self, target: &TaskExt) -> bool {
+        self.uid() == target.uid() || self.capable(CAP_KILL)
+    }
+
+    /// 是否为 init 进程，即父任务是内核线程的用户进程
+    pub fn is_init(&self) -> bool {
+        self.parent
+            .as_ref()
+            .and_then(|parent| parent.upgrade())
+            .is_some_and(|parent| unsafe { parent.task_ext_ptr() }.is_null())
+    }
+
+    /// 最近一次运行该任务的 CPU，对正在运行的任务即为其当前所在的 CPU
+    pub fn last_cpu(&self) -> usize {
+        self.last_cpu.load(Ordering::Relaxed)
+    }
+
+    /// 记录导致进程终止的信号，以及是否转储了核心
+    pub fn set_term_signal(&self, signo: usize, core_dumped: bool) {
+        let core = if core_dumped { CORE_DUMPED } else { 0 };
+        self.term_signal
+            .store(signo as u32 | core, Ordering::Relaxed);
+    }
+
+    /// 被信号终止时能否转储核心
+    pub fn dumpable(&self) -> bool {
+        self.dumpable.load(Ordering::Relaxed)
+    }
+
+    /// 设置被信号终止时能否转储核心，返回原先的值
+    pub fn set_dumpable(&self, dumpable: bool) -> bool {
+        self.dumpable.swap(dumpable, Ordering::Relaxed)
+    }
+
+    /// 计算 `wait4` 报告的状态：正常退出时为退出码左移 8 位，被信号终止时为信号编号，
+    /// 转储了核心时再加上 0x80
+    pub fn wait_status(&self, exit_code: i32) -> i32 {
+        match self.term_signal.load(Ordering::Relaxed) {
+            0 => exit_code << 8,
+            signo => signo as i32,
+        }
+    }
+
+    /// 进程退出时通知父进程：将自身从父进程的子进程中移除并留下僵尸记录，然后发送
+    /// SIGCHLD。此后父进程不再持有当前任务，任务在退出后即可被释放。
+    ///
+    /// 父进程将 SIGCHLD 设为忽略或设置了 `SA_NOCLDWAIT` 时，子进程不会成为僵尸进程，
+    /// 此时没有其他子进程的 `wait4` 将返回 ECHILD。
+    fn notify_parent(&self, name: &str, exit_code: i32) {
+        let Some(parent) = self.parent.as_ref().and_then(|parent| parent.upgrade()) else {
+            return;
+        };
+        // 第一个用户进程的父任务是内核线程
+        if unsafe { parent.task_ext_ptr() }.is_null() || parent.state() == axtask::TaskState::Exited
+        {
+            return;
+        }
+        let parent_ext = parent.task_ext();
+
+        let (code, status) = match self.term_signal.load(Ordering::Relaxed) {
+            0 => (CLD_EXITED, exit_code),
+            signo if signo & CORE_DUMPED != 0 => (CLD_DUMPED, (signo & !CORE_DUMPED) as i32),
+            signo => (CLD_KILLED, signo as i32),
+        };
+        let (user_time, kernel_time) = self.time_stat.lock().info();
+
+        let mut children = parent_ext.children.lock();
+        // 父进程正在退出时，子进程可能已被交给内核主线程
+        if let Some(pos) = children
+            .iter()
+            .position(|c| c.task_ext().proc_id == self.proc_id)
+        {
+            children.remove(pos);
+            if parent_ext.signal_actions.lock().reaps_children() {
+                unregister_pid(self.proc_id);
+            } else {
+                parent_ext.zombies.lock().push(ZombieRecord {
+                    pid: self.proc_id,
+                    name: name.into(),
+                    status: self.wait_status(exit_code),
+                    uid: self.euid(),
+                    time: (user_time, kernel_time),
+                    children_time: self.children_time(),
+                    start_time: self.start_time,
+                });
+            }
+        }
+        drop(children);
+        parent_ext.child_wq.notify_all();
+
+        parent_ext.send_signal(SigInfo::child(
+            self.proc_id,
+            code,
+            status,
+            nanos_to_clock_ticks(user_time),
+            nanos_to_clock_ticks(kernel_time),
+        ));
     }
 
     /// 设置父任务
@@ -87,25 +578,107 @@ impl TaskExt {
         children.push(child);
     }
 
-    /// 移除子任务
-    pub fn remove_child(&self, child_id: usize) {
-        let mut children = self.children.lock();
-        if let Some(pos) = children
-            .iter()
-            .position(|c| c.task_ext().proc_id == child_id)
-        {
-            children.remove(pos);
+    /// 记录进程开始执行系统调用 `sysno`
+    pub fn enter_syscall(&self, sysno: usize) {
+        self.syscall_irqs
+            .store(axhal::arch::irqs_enabled(), Ordering::Relaxed);
+        self.syscall.store(sysno, Ordering::Relaxed);
+    }
+
+    /// 进入正在执行的系统调用时是否开中断
+    pub fn syscall_irqs_enabled(&self) -> bool {
+        self.syscall_irqs.load(Ordering::Relaxed)
+    }
+
+    /// 记录系统调用执行完毕
+    pub fn leave_syscall(&self) {
+        self.syscall.store(NO_SYSCALL, Ordering::Relaxed);
+    }
+
+    /// 取出正在执行的系统调用号，此后视为不在系统调用中
+    pub fn take_syscall(&self) -> Option<usize> {
+        match self.syscall.swap(NO_SYSCALL, Ordering::Relaxed) {
+            NO_SYSCALL => None,
+            sysno => Some(sysno),
         }
     }
 
-    /// 获取父任务的 PID，如果父任务不存在则返回 `None`
-    pub fn parent_id(&self) -> Option<usize> {
-        // 由于parent引用是父进程的主进程，所以其tid就是父进程的pid。
-        // 第一个进程的父进程是一个内核线程，所以这样做可以统一处理。
-        self.parent
-            .as_ref()
-            .and_then(|parent| parent.upgrade())
-            .map(|task| task.id().as_u64() as usize)
+    /// 统计当前的常驻内存，更新并返回观察到的最大常驻内存页数
+    pub fn update_max_rss(&self) -> usize {
+        let pages = self.aspace.lock().resident_pages();
+        self.max_rss.fetch_max(pages, Ordering::Relaxed).max(pages)
+    }
+
+    /// 进程退出时立即关闭所有文件并释放用户地址空间，不必等到任务被回收
+    ///
+    /// 地址空间仍被 vfork 的父进程使用时不释放。
+    fn release_resources(&self) {
+        self.update_max_rss();
+        arceos_posix_api::close_all();
+        if Arc::strong_count(&self.aspace) == 1 {
+            if let Err(err) = self.aspace.lock().unmap_user_areas() {
+                warn!("Failed to release the user address space: {:?}", err);
+            }
+            axhal::arch::flush_tlb(None);
+            crate::mm::file_map::release_unused();
+        }
+    }
+
+    /// 回收一个 PID 与 `matches` 匹配的已退出子进程：删除僵尸记录和进程表项，并累加其运行时间
+    ///
+    /// 子进程只按 PID 识别，查找和删除记录在持有 `children` 和 `zombies` 锁的同一临界区内
+    /// 完成。同时等待的多个调用者（如 `wait4` 和打断它的信号处理函数）中只有一个能取得
+    /// 某个子进程的记录，其余调用者找不到它，继续等待其他子进程或得到 ECHILD。
+    ///
+    /// 没有已退出的子进程时，有匹配的子进程仍在运行则返回 `Err(true)`，否则返回 `Err(false)`。
+    fn reap_child(&self, matches: impl Fn(Pid) -> bool) -> Result<ZombieRecord, bool> {
+        let children = self.children.lock();
+        let mut zombies = self.zombies.lock();
+        let Some(index) = zombies.iter().position(|zombie| matches(zombie.pid)) else {
+            return Err(children
+                .iter()
+                .any(|child| matches(child.task_ext().proc_id)));
+        };
+        let zombie = zombies.remove(index);
+        drop(zombies);
+        drop(children);
+        info!(
+            "Waited for pid {} with status {:#x}",
+            zombie.pid, zombie.status
+        );
+        self.add_children_time(&zombie);
+        unregister_pid(zombie.pid);
+        Ok(zombie)
+    }
+
+    /// 进程退出时将子进程交给内核主线程，由它回收其中的僵尸进程，已退出子进程的记录直接丢弃
+    fn reparent_children(&self) {
+        let children = core::mem::take(&mut *self.children.lock());
+        ORPHANS.lock().extend(children);
+        for zombie in core::mem::take(&mut *self.zombies.lock()) {
+            unregister_pid(zombie.pid);
+        }
+    }
+
+    /// 获取父进程的 PID
+    ///
+    /// 第一个用户进程的父任务是内核线程，此时返回 0；
+    /// 父进程已经退出时视为被 init 进程收养，返回 1。
+    pub fn parent_id(&self) -> Pid {
+        let Some(parent) = self.parent.as_ref() else {
+            return 0;
+        };
+        let Some(parent) = parent.upgrade() else {
+            return 1;
+        };
+        if unsafe { parent.task_ext_ptr() }.is_null() {
+            return 0;
+        }
+        let ppid = parent.task_ext().proc_id;
+        match find_task_by_pid(ppid) {
+            Some(task) if task.state() != axtask::TaskState::Exited => ppid,
+            _ => 1,
+        }
     }
 
     /// 进入用户态时更新时间统计
@@ -118,13 +691,65 @@ impl TaskExt {
         self.time_stat.lock().enter_kspace();
     }
 
-    pub(crate) fn ns_init_new(&self) {
-        FD_TABLE.deref_from(&self.ns).init_new(FD_TABLE.copy_inner());
-        CURRENT_DIR.deref_from(&self.ns).init_new(CURRENT_DIR.copy_inner());
-        CURRENT_DIR_PATH.deref_from(&self.ns).init_new(CURRENT_DIR_PATH.copy_inner());
+    /// 从当前任务复制资源，`share_fs` 为 `true` 时与当前任务共享根目录和当前工作目录
+    pub(crate) fn ns_init_new(&self, share_fs: bool) {
+        FD_TABLE
+            .deref_from(&self.ns)
+            .init_new(FD_TABLE.copy_inner());
+        FD_CLOEXEC
+            .deref_from(&self.ns)
+            .init_new(FD_CLOEXEC.copy_inner());
+        FD_LIMIT
+            .deref_from(&self.ns)
+            .init_new(FD_LIMIT.copy_inner());
+        if share_fs {
+            CURRENT_DIR
+                .deref_from(&self.ns)
+                .init_new(CURRENT_DIR.share_inner());
+            CURRENT_DIR_PATH
+                .deref_from(&self.ns)
+                .init_new(CURRENT_DIR_PATH.share_inner());
+            CURRENT_ROOT_PATH
+                .deref_from(&self.ns)
+                .init_new(CURRENT_ROOT_PATH.share_inner());
+        } else {
+            CURRENT_DIR
+                .deref_from(&self.ns)
+                .init_new(CURRENT_DIR.copy_inner());
+            CURRENT_DIR_PATH
+                .deref_from(&self.ns)
+                .init_new(CURRENT_DIR_PATH.copy_inner());
+            CURRENT_ROOT_PATH
+                .deref_from(&self.ns)
+                .init_new(CURRENT_ROOT_PATH.copy_inner());
+        }
     }
 }
 
+/// 让当前任务不再与其他任务共享根目录和当前工作目录，复制方式与不带 `CLONE_FS` 的 clone 相同
+///
+/// 命名空间中的资源只能初始化一次，因此为当前任务换上新的命名空间：根目录和当前工作目录
+/// 复制一份，其余资源仍与原来的命名空间共享。原本就不共享时，复制的结果与原来相同。
+pub(crate) fn unshare_fs() {
+    let curr = current();
+    let ns = AxNamespace::new_thread_local();
+    FD_TABLE.deref_from(&ns).init_shared(FD_TABLE.share());
+    FD_CLOEXEC.deref_from(&ns).init_shared(FD_CLOEXEC.share());
+    FD_LIMIT.deref_from(&ns).init_shared(FD_LIMIT.share());
+    CURRENT_DIR
+        .deref_from(&ns)
+        .init_new(CURRENT_DIR.copy_inner());
+    CURRENT_DIR_PATH
+        .deref_from(&ns)
+        .init_new(CURRENT_DIR_PATH.copy_inner());
+    CURRENT_ROOT_PATH
+        .deref_from(&ns)
+        .init_new(CURRENT_ROOT_PATH.copy_inner());
+    // 只有当前任务会替换自己的命名空间，与 exec 替换地址空间相同
+    let task_ext = unsafe { &mut *(curr.task_ext_ptr() as *mut TaskExt) };
+    task_ext.ns = ns;
+}
+
 struct AxNamespaceImpl;
 
 #[crate_interface::impl_interface]
@@ -153,11 +778,13 @@ pub fn spawn_user_task(aspace: Arc<Mutex<AddrSpace>>, uctx: UspaceContext) -> Ax
                 curr.task_ext().uctx.get_sp(),
                 kstack_top,
             );
+            curr.task_ext().enter_uspace();
             unsafe { curr.task_ext().uctx.enter_uspace(kstack_top) };
         },
         "userboot".into(),
         crate::config::KERNEL_STACK_SIZE,
     );
+    kstack::poison(&task);
     task.ctx_mut()
         .set_page_table_root(aspace.lock().page_table_root());
     task.init_task_ext(TaskExt::new(
@@ -166,19 +793,114 @@ pub fn spawn_user_task(aspace: Arc<Mutex<AddrSpace>>, uctx: UspaceContext) -> Ax
         aspace,
         current().as_task_ref(),
     ));
-    task.task_ext().ns_init_new();
-    axtask::spawn_task(task)
+    task.task_ext().ns_init_new(false);
+    let task = axtask::spawn_task(task);
+    register_pid(&task);
+    task
+}
+
+bitflags! {
+    /// `clone` 系统调用的选项，不包括低 8 位的退出信号
+    #[derive(Debug, Clone, Copy)]
+    pub struct CloneFlags: u32 {
+        /// 共享地址空间
+        const CLONE_VM = 0x0000_0100;
+        /// 共享文件系统信息
+        const CLONE_FS = 0x0000_0200;
+        /// 共享文件描述符表
+        const CLONE_FILES = 0x0000_0400;
+        /// 共享信号处理函数
+        const CLONE_SIGHAND = 0x0000_0800;
+        /// 在父进程的 `ptid` 处写入子进程的 pidfd
+        const CLONE_PIDFD = 0x0000_1000;
+        /// 允许跟踪子进程
+        const CLONE_PTRACE = 0x0000_2000;
+        /// 父进程挂起直到子进程执行 exec 或退出
+        const CLONE_VFORK = 0x0000_4000;
+        /// 子进程与调用者拥有同一个父进程
+        const CLONE_PARENT = 0x0000_8000;
+        /// 子进程与调用者处于同一线程组
+        const CLONE_THREAD = 0x0001_0000;
+        /// 新建挂载命名空间
+        const CLONE_NEWNS = 0x0002_0000;
+        /// 共享 System V 信号量的撤销值
+        const CLONE_SYSVSEM = 0x0004_0000;
+        /// 设置线程局部存储
+        const CLONE_SETTLS = 0x0008_0000;
+        /// 在父进程的 `ptid` 处写入子进程的 TID
+        const CLONE_PARENT_SETTID = 0x0010_0000;
+        /// 子进程退出时清零其 `ctid` 处的值并唤醒 futex
+        const CLONE_CHILD_CLEARTID = 0x0020_0000;
+        /// 在子进程的 `ctid` 处写入子进程的 TID
+        const CLONE_CHILD_SETTID = 0x0100_0000;
+    }
+}
+
+bitflags! {
+    /// `personality` 系统调用设置的执行域标志，未列出的位也会被保存，但没有效果
+    ///
+    /// See <https://github.com/torvalds/linux/blob/master/include/uapi/linux/personality.h>
+    #[derive(Debug, Clone, Copy)]
+    pub struct Personality: u32 {
+        /// 关闭地址空间布局随机化
+        ///
+        /// 目前的地址空间布局本就是确定的，因此该标志总是成立。
+        const ADDR_NO_RANDOMIZE = 0x0004_0000;
+        /// 可读的映射同时可执行
+        const READ_IMPLIES_EXEC = 0x0040_0000;
+    }
+}
+
+/// 任务被调度到某个 CPU 上运行时，记录该 CPU 的编号
+#[axtask::register_switch_hook(axtask::TASK_SWITCH_HOOKS)]
+fn record_cpu(_prev: &TaskInner, next: &TaskInner) {
+    // 避开只有内核线程的情况,如 idle 线程等
+    if !unsafe { next.task_ext_ptr() }.is_null() {
+        next.task_ext()
+            .last_cpu
+            .store(axhal::cpu::this_cpu_id(), Ordering::Relaxed);
+    }
+}
+
+/// 系统调用指令的长度：riscv64 的 `ecall`、x86_64 的 `syscall` 和 aarch64 的 `svc`
+#[cfg(target_arch = "x86_64")]
+pub const SYSCALL_INSN_LEN: usize = 2;
+#[cfg(not(target_arch = "x86_64"))]
+pub const SYSCALL_INSN_LEN: usize = 4;
+
+/// 返回系统调用完成后用户态继续执行的地址，只能对正在处理的系统调用的陷入帧调用
+///
+/// x86_64 和 aarch64 的陷入帧中保存的已经是系统调用指令之后的地址；riscv64 保存的是
+/// `ecall` 本身的地址，陷入处理函数在系统调用返回后才加上指令的长度。
+pub fn syscall_return_ip(tf: &TrapFrame) -> usize {
+    if cfg!(target_arch = "riscv64") {
+        tf.get_ip() + SYSCALL_INSN_LEN
+    } else {
+        tf.get_ip()
+    }
 }
 
 /// 实现简易的clone系统调用
 /// 返回值为新产生的任务的id
+///
+/// 指定 `CLONE_VM | CLONE_VFORK` 时按 vfork 语义处理：子进程与父进程共享地址空间，
+/// 父进程被挂起，直到子进程执行 exec 或退出。
+///
+/// `tf` 为当前系统调用的陷入帧，子进程的用户态上下文由它复制而来。
 pub fn clone_task(
-    _flags: usize,
+    tf: &TrapFrame,
+    flags: usize,
     stack: Option<usize>,
     _ptid: usize,
     _tls: usize,
     _ctid: usize,
 ) -> AxResult<u64> {
+    // 新任务的内核栈也不能用掉为内核保留的内存，否则不断创建任务会使内核自身无内存可用
+    if axalloc::global_allocator().pages_above_watermark()
+        < crate::config::KERNEL_STACK_SIZE / PAGE_SIZE_4K
+    {
+        return Err(AxError::NoMemory);
+    }
     let mut new_task = TaskInner::new(
         || {
             let curr = axtask::current();
@@ -189,28 +911,38 @@ pub fn clone_task(
                 curr.task_ext().uctx.get_sp(),
                 kstack_top,
             );
+            curr.task_ext().enter_uspace();
             unsafe { curr.task_ext().uctx.enter_uspace(kstack_top) };
         },
         String::from(current().id_name()),
         crate::config::KERNEL_STACK_SIZE,
     );
+    if new_task.id().as_u64() as usize >= PID_MAX.load(Ordering::Relaxed) {
+        return Err(AxError::WouldBlock);
+    }
+    kstack::poison(&new_task);
 
     let current_task = current();
+    let flags = CloneFlags::from_bits_truncate(flags as u32);
+    let is_vfork = flags.contains(CloneFlags::CLONE_VM | CloneFlags::CLONE_VFORK);
 
-    // 复制原有的地址空间
-    let mut current_aspace = current_task.task_ext().aspace.lock();
-    let new_aspace = current_aspace.clone_or_err()?;
+    // vfork 时共享原有的地址空间，否则复制一份
+    let new_aspace = if is_vfork {
+        current_task.task_ext().aspace.clone()
+    } else {
+        Arc::new(Mutex::new(
+            current_task.task_ext().aspace.lock().clone_or_err()?,
+        ))
+    };
     new_task
         .ctx_mut()
-        .set_page_table_root(new_aspace.page_table_root());
-
-    // 复制原有的trap上下文并设置用户空间上下文
-    let trap_frame_vir_address = current_task
-        .kernel_stack_top()
-        .expect("no kernel stack top")
-        .sub(core::mem::size_of::<TrapFrame>());
-    let mut trap_frame = unsafe { *(trap_frame_vir_address.as_ptr_of::<TrapFrame>()) };
-    trap_frame.sepc += 4;
+        .set_page_table_root(new_aspace.lock().page_table_root());
+    // 浮点寄存器不在陷入帧中，从当前 CPU 复制一份
+    new_task.ctx_mut().copy_fp_state();
+
+    // 复制系统调用的陷入帧，子进程从系统调用返回处开始执行
+    let mut trap_frame = *tf;
+    trap_frame.set_ip(syscall_return_ip(tf));
     let mut new_uspace_context = UspaceContext::from(&trap_frame);
     new_uspace_context.set_retval(0);
     if let Some(stack) = stack {
@@ -219,30 +951,58 @@ pub fn clone_task(
 
     // 初始化新任务扩展，启动新任务，维护父子关系
     let return_id = new_task.id().as_u64();
-    let new_task_ext = TaskExt::new(
+    let mut new_task_ext = TaskExt::new(
         return_id as usize,
         new_uspace_context,
-        Arc::new(Mutex::new(new_aspace)),
+        new_aspace,
         current_task.as_task_ref(),
     );
-    new_task_ext.ns_init_new();
+    let vfork_done = is_vfork.then(|| Arc::new(Completion::new()));
+    new_task_ext.vfork_done = vfork_done.clone();
+    new_task_ext.set_personality(current_task.task_ext().personality());
+    new_task_ext.capabilities = Mutex::new(current_task.task_ext().capabilities());
+    new_task_ext.uids = Mutex::new(current_task.task_ext().user_ids());
+    new_task_ext.set_umask(current_task.task_ext().umask());
+    new_task_ext.set_dumpable(current_task.task_ext().dumpable());
+    new_task_ext.set_nice(current_task.task_ext().nice());
+    let (policy, priority) = current_task.task_ext().sched_policy();
+    new_task_ext.set_sched_policy(policy, priority);
+    new_task_ext.set_ioprio(current_task.task_ext().ioprio());
+    new_task_ext.signal_actions = Arc::new(Mutex::new(
+        current_task.task_ext().signal_actions.lock().clone(),
+    ));
+    new_task_ext.signal = SpinNoIrq::new(current_task.task_ext().signal.lock().inherit());
+    new_task_ext.cmdline = Mutex::new(current_task.task_ext().cmdline.lock().clone());
+    new_task_ext.rlimits = Mutex::new(current_task.task_ext().rlimits.lock().clone());
+    new_task_ext.ns_init_new(flags.contains(CloneFlags::CLONE_FS));
     new_task.init_task_ext(new_task_ext);
+    // 子进程继承父进程可以运行的 CPU
+    axtask::set_cpumask(&new_task, current_task.cpumask());
     let new_task = axtask::spawn_task(new_task);
+    register_pid(&new_task);
     current_task.task_ext().add_child(new_task);
+
+    // 子进程在共享的地址空间和用户栈上运行，父进程必须等待其 exec 或退出
+    if let Some(done) = vfork_done {
+        done.wait();
+    }
     Ok(return_id)
 }
 
 /// 等待子进程完成任务，若子进程没有完成，则自身可能会用yield轮询
-/// 成功则返回进程ID；如果指定了WNOHANG，且进程还未改变状态，直接返回0；失败则返回-1；
-///
-/// # Safety
+/// 已退出的子进程只留下僵尸记录，回收时读取记录，不需要等待子任务本身。
+/// 成功则返回进程ID；如果指定了WNOHANG，且进程还未改变状态，直接返回0；没有可等待的子进程时返回 -ECHILD；
+/// 等待期间有信号需要递送时返回 -EINTR。
 ///
-/// 保证传入的 ptr 是有效的
-pub unsafe fn wait_pid(pid: i32, exit_code_ptr: *mut i32, option: i32) -> isize {
+/// `exit_code_ptr` 不为空时将等待状态写入其中。与 Linux 一致，状态在子进程被回收后才写入，
+/// 地址无效时返回 -EFAULT，子进程仍已被回收。
+pub fn wait_pid(pid: i32, exit_code_ptr: *mut i32, option: i32) -> isize {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum WaitStatus {
         /// 子任务正常退出
         Exited,
+        /// 被跟踪者停止
+        Stopped,
         /// 子任务正在运行
         Running,
         /// 找不到对应的子任务
@@ -266,116 +1026,181 @@ pub unsafe fn wait_pid(pid: i32, exit_code_ptr: *mut i32, option: i32) -> isize
     }
     let current_task = current();
 
-    let mut exit_task_id: usize = 0;
-    let mut answer_id = 0;
-    let mut answer_status;
     let options = WaitFlags::from_bits_truncate(option as u32);
 
     if !options.difference(WaitFlags::WNOHANG).is_empty() {
         warn!("Unsupported option: {:?}", options);
     }
+    if pid == 0 {
+        warn!("Process group waiting is not supported.");
+    }
+    let matches = |child_pid: Pid| pid <= 0 || child_pid == pid as usize;
 
-    'outer: loop {
-        answer_status = WaitStatus::NotExist;
-
-        let children = current_task.task_ext().children.lock();
-        for (index, child) in children.iter().enumerate() {
-            if pid <= 0 {
-                if pid == 0 {
-                    warn!("Process group waiting is not supported.");
-                }
-
-                answer_status = WaitStatus::Running;
-                let state = child.state();
-
-                if state == axtask::TaskState::Exited {
-                    let exit_code = child.exit_code();
-                    answer_status = WaitStatus::Exited;
-
-                    exit_task_id = index;
-                    if !exit_code_ptr.is_null() {
-                        unsafe {
-                            *exit_code_ptr = exit_code << 8;
-                        }
-                    }
-                    answer_id = child.task_ext().proc_id as usize;
-                    break 'outer;
-                }
-            } else if child.task_ext().proc_id == pid as usize {
-                if let Some(exit_code) = child.join() {
-                    answer_status = WaitStatus::Exited;
-                    info!(
-                        "Waited for pid {} with exit code {:?}",
-                        child.task_ext().proc_id,
-                        exit_code
-                    );
-
-                    exit_task_id = index;
-                    if !exit_code_ptr.is_null() {
-                        unsafe {
-                            *exit_code_ptr = exit_code << 8;
-                        }
-                    }
-                    answer_id = child.task_ext().proc_id as usize;
-                } else {
-                    answer_status = WaitStatus::Running;
-                }
-                break 'outer;
-            }
+    let mut found = None;
+    let poll = || {
+        let mut answer_status = WaitStatus::NotExist;
+
+        // 先报告被跟踪者的停止，被跟踪者不一定是当前进程的子进程
+        match ptrace::wait_stopped(pid) {
+            Ok((tracee, status)) => return Some((WaitStatus::Stopped, tracee, status)),
+            Err(true) => answer_status = WaitStatus::Running,
+            Err(false) => {}
         }
 
-        drop(children);
+        match current_task.task_ext().reap_child(matches) {
+            Ok(zombie) => return Some((WaitStatus::Exited, zombie.pid, zombie.status)),
+            Err(true) => answer_status = WaitStatus::Running,
+            Err(false) => {}
+        }
 
         if !options.contains(WaitFlags::WNOHANG) && answer_status == WaitStatus::Running {
-            axtask::yield_now();
+            None
         } else {
-            break;
+            Some((answer_status, 0, 0))
+        }
+    };
+    // 子进程退出和被跟踪者停止时都会唤醒 `child_wq`
+    let wait_result = current_task.task_ext().child_wq.wait_interruptible(|| {
+        found = poll();
+        found.is_some()
+    });
+    let result = match wait_result {
+        WaitResult::Interrupted => Err(LinuxError::EINTR),
+        _ => Ok(found.unwrap()),
+    };
+    match result {
+        Ok((WaitStatus::Exited | WaitStatus::Stopped, pid, status)) => {
+            if !exit_code_ptr.is_null() && crate::mm::write_user(exit_code_ptr, &status).is_err() {
+                return -(LinuxError::EFAULT.code() as isize);
+            }
+            pid as isize
         }
+        Ok((WaitStatus::NotExist, ..)) => -(LinuxError::ECHILD.code() as isize),
+        Ok((WaitStatus::Running, ..)) => 0,
+        Err(err) => -(err.code() as isize),
     }
+}
 
-    // 若进程成功结束，需要将其从父进程的children中删除
-    if answer_status == WaitStatus::Exited {
-        let mut children = current_task.task_ext().children.lock();
-        children.remove(exit_task_id);
-        answer_id as isize
-    } else if options.contains(WaitFlags::WNOHANG) {
-        0
-    } else {
-        -1
+/// 结束当前进程，以 `exit_code` 退出
+///
+/// 进程无论是调用 `exit` 或 `exit_group`，还是被信号（包括测例超时后内核发送的 SIGKILL）
+/// 终止，都经由这里按以下顺序释放资源：
+///
+/// 1. 地址空间被共享时按 `clear_child_tid` 的要求清零并唤醒等待者，唤醒 vfork 的父进程；
+/// 2. 删除定时器，停止跟踪被跟踪者并离开跟踪者，按 `SEM_UNDO` 的记录撤销对 System V 信号量的修改；
+/// 3. 关闭所有文件描述符，管道另一端的读者因此读到文件结束，写者得到 EPIPE；
+/// 4. 释放用户地址空间中的所有页。普通文件的共享映射目前按私有映射处理，memfd 的
+///    共享映射直接映射文件的物理页，因此都不需要写回；
+/// 5. 将子进程交给内核主线程，在父进程中留下僵尸记录，此后它即可被 `wait4` 回收，
+///    运行时间在回收时累加到父进程；然后向父进程发送 SIGCHLD；
+/// 6. 最后将任务标记为已退出。
+///
+/// 父进程只持有僵尸记录，因此页表、内核栈和资源命名空间在任务切换出去后即随任务释放，
+/// 不必等到父进程回收。
+pub fn do_exit(exit_code: i32) -> ! {
+    let curr = current();
+    let clear_child_tid = curr.task_ext().clear_child_tid();
+    // 与 Linux 一致，只在地址空间还被其他任务（如 vfork 的父进程）使用时清零并唤醒
+    if clear_child_tid != 0 && Arc::strong_count(&curr.task_ext().aspace) > 1 {
+        crate::syscall_imp::clear_child_tid(VirtAddr::from(clear_child_tid as usize));
     }
+    curr.task_ext().notify_vfork_done();
+    timer::delete_all();
+    ptrace::detach_all();
+    ptrace::release_tracer();
+    crate::syscall_imp::exit_sem(curr.task_ext().proc_id as i32);
+    curr.task_ext().release_resources();
+    curr.task_ext().reparent_children();
+    curr.task_ext().notify_parent(curr.name(), exit_code);
+    axtask::exit(exit_code);
+}
+
+/// 新程序加载后，按照 POSIX 对 execve 的要求重置当前进程的状态
+///
+/// - 设置了处理函数的信号恢复为默认处理方式，被忽略的信号、信号掩码和未决信号保持不变；
+/// - 删除全部 POSIX 定时器；
+/// - 备用信号栈、`clear_child_tid` 和 `mlockall(MCL_FUTURE)` 的设置指向或作用于旧的
+///   地址空间，一并清除；
+/// - 进程恢复为可以转储核心；
+/// - 关闭设置了 close-on-exec 标志的文件，其余文件保持打开；
+/// - 进程号、父子关系、用户、能力、资源限制、nice 值、调度策略和 I/O 优先级保持不变。
+///
+/// 每个任务都是单独的进程，没有需要终止的其他线程；也还不支持 robust futex 列表。
+fn reset_on_exec(ext: &TaskExt) {
+    ext.signal_actions.lock().reset_handlers();
+    ext.signal.lock().altstack = Default::default();
+    timer::delete_all();
+    ext.set_clear_child_tid(0);
+    ext.set_mlock_future(false);
+    *ext.io_ring.lock() = None;
+    ext.set_dumpable(true);
+    arceos_posix_api::close_on_exec();
 }
 
 /// 将当前进程替换为指定的用户程序
-pub fn exec(program_name: &str) -> AxResult<()> {
+///
+/// `elf_data` 为程序文件的内容，`args` 与 `envs` 会被放置到新程序的用户栈上。
+/// 调用者需要事先将它们从用户空间拷贝出来，因为原有的用户地址空间会被释放。
+///
+/// 程序文件在释放原有地址空间之前解析，不是本架构合法的 ELF 文件时返回
+/// [`AxError::InvalidData`]，进程不受影响。此后加载失败时进程已无法继续运行，以 SIGSEGV 终止。
+pub fn exec(program_name: &str, elf_data: &[u8], args: &[String], envs: &[String]) -> AxResult<()> {
     let current_task = current();
+    let elf_info = crate::loader::load_elf(
+        elf_data,
+        VirtAddr::from_usize(crate::config::USER_SPACE_BASE),
+    )?;
 
-    // 原有的name所在页面会被unmap，所以需要提前拷贝
-    let program_name = program_name.to_string();
-
-    // 确保地址空间只被当前任务引用
-    let mut aspace = current_task.task_ext().aspace.lock();
-    if Arc::strong_count(&current_task.task_ext().aspace) != 1 {
-        warn!("Address space is shared by multiple tasks, exec is not supported");
-        return Err(AxError::Unsupported);
+    let task_ext = unsafe { &mut *(current_task.task_ext_ptr() as *mut TaskExt) };
+    if Arc::strong_count(&task_ext.aspace) != 1 {
+        // 地址空间仍被其他任务（如 vfork 的父进程）使用，不能释放，为当前任务新建一个
+        let new_aspace = axmm::new_user_aspace(
+            VirtAddr::from_usize(crate::config::USER_SPACE_BASE),
+            crate::config::USER_SPACE_SIZE,
+        )?;
+        let page_table_root = new_aspace.page_table_root();
+        task_ext.aspace = Arc::new(Mutex::new(new_aspace));
+        unsafe {
+            (*current_task.ctx_mut_ptr()).set_page_table_root(page_table_root);
+            axhal::arch::write_page_table_root(page_table_root);
+        }
+    } else {
+        // 释放旧的用户地址空间
+        task_ext.aspace.lock().unmap_user_areas()?;
+        axhal::arch::flush_tlb(None);
+        crate::mm::file_map::release_unused();
     }
-
-    // 释放旧的用户地址空间
-    aspace.unmap_user_areas()?;
-    axhal::arch::flush_tlb(None);
+    let mut aspace = task_ext.aspace.lock();
 
     // 加载新程序，获取入口点和用户栈基地址
-    let (entry_point, user_stack_base) = crate::mm::map_elf_sections(&program_name, &mut aspace)
-        .map_err(|_| {
-            error!("Failed to load app {}", program_name);
-            AxError::NotFound
-        })?;
-    current_task.set_name(&program_name);
-
-    // 更新用户上下文
-    let task_ext = unsafe { &mut *(current_task.task_ext_ptr() as *mut TaskExt) };
-    task_ext.uctx = UspaceContext::new(entry_point.as_usize(), user_stack_base, 0);
+    let (entry_point, user_stack_base) =
+        match crate::mm::map_elf_sections(elf_info, args, envs, &mut aspace) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                error!("Failed to load app {}: {:?}", program_name, err);
+                drop(aspace);
+                signal::exit_by_signal(signal::SIGSEGV);
+            }
+        };
+    current_task.set_name(program_name);
+    *task_ext.cmdline.lock() = args.to_vec();
+
+    drop(aspace);
+
+    // 新程序已经加载，vfork 的父进程可以继续运行
+    task_ext.notify_vfork_done();
+
+    reset_on_exec(task_ext);
+
+    // 更新用户上下文，被跟踪时先停下来交给跟踪者
+    task_ext.uctx = ptrace::stop_at_exec(UspaceContext::new(
+        entry_point.as_usize(),
+        user_stack_base,
+        0,
+    ));
 
     // 切换到用户态
+    task_ext.enter_uspace();
     unsafe {
         task_ext.uctx.enter_uspace(
             current_task
@@ -0,0 +1,95 @@
+//! 进程的能力（capability）集合
+//!
+//! See <https://man7.org/linux/man-pages/man7/capabilities.7.html>
+
+use super::cred::UserIds;
+
+/// 允许向任意进程发送信号
+pub const CAP_KILL: u32 = 5;
+/// 允许任意修改进程的用户 ID
+pub const CAP_SETUID: u32 = 7;
+/// 允许任意设置自己的可继承集合
+pub const CAP_SETPCAP: u32 = 8;
+/// 允许修改进程的根目录
+pub const CAP_SYS_CHROOT: u32 = 18;
+/// 允许访问任意进程的内存或跟踪它
+pub const CAP_SYS_PTRACE: u32 = 19;
+/// 允许修改主机名、挂载文件系统等系统管理操作
+pub const CAP_SYS_ADMIN: u32 = 21;
+/// 允许重启或关闭系统
+pub const CAP_SYS_BOOT: u32 = 22;
+/// 允许提高进程的优先级，或修改其他用户进程的优先级
+pub const CAP_SYS_NICE: u32 = 23;
+/// 允许提高资源限制的硬限制
+pub const CAP_SYS_RESOURCE: u32 = 24;
+/// 允许修改系统时钟
+pub const CAP_SYS_TIME: u32 = 25;
+/// 当前支持的最大能力编号，与 Linux 中的 `CAP_LAST_CAP` 一致
+pub const CAP_LAST_CAP: u32 = 40;
+
+/// 包含全部能力的集合
+const CAP_FULL_SET: u64 = (1 << (CAP_LAST_CAP + 1)) - 1;
+
+/// 进程的三个能力集合，每一位对应一种能力
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    /// 内核实际检查的能力
+    pub effective: u64,
+    /// 进程可以在有效集合中启用的能力上限
+    pub permitted: u64,
+    /// 执行 exec 时可以被新程序继承的能力
+    pub inheritable: u64,
+}
+
+impl Capabilities {
+    /// 超级用户进程的能力集合：有效和允许集合为全集，可继承集合为空
+    pub const fn root() -> Self {
+        Self {
+            effective: CAP_FULL_SET,
+            permitted: CAP_FULL_SET,
+            inheritable: 0,
+        }
+    }
+
+    /// 是否拥有指定的能力
+    pub const fn has(&self, cap: u32) -> bool {
+        cap <= CAP_LAST_CAP && self.effective & (1 << cap) != 0
+    }
+
+    /// 按 `capset` 的规则更新能力集合，违反规则时返回 `false` 且不做修改
+    ///
+    /// 允许集合只能缩小，因此丢弃的能力无法再找回；有效集合必须是新的允许集合的子集；
+    /// 没有 [`CAP_SETPCAP`] 时，可继承集合只能加入原本可继承或允许的能力。
+    pub fn set(&mut self, new: Capabilities) -> bool {
+        let new = Capabilities {
+            effective: new.effective & CAP_FULL_SET,
+            permitted: new.permitted & CAP_FULL_SET,
+            inheritable: new.inheritable & CAP_FULL_SET,
+        };
+        if new.permitted & !self.permitted != 0
+            || new.effective & !new.permitted != 0
+            || (!self.has(CAP_SETPCAP)
+                && new.inheritable & !(self.inheritable | self.permitted) != 0)
+        {
+            return false;
+        }
+        *self = new;
+        true
+    }
+
+    /// 用户 ID 从 `old` 变为 `new` 后，按 Linux 的规则调整能力集合
+    ///
+    /// 原先有用户 ID 为 0 而现在都不为 0 时，清空允许和有效集合；有效用户 ID 从 0 变为
+    /// 非 0 时清空有效集合，从非 0 变为 0 时有效集合恢复为允许集合。
+    pub fn update_for_uids(&mut self, old: &UserIds, new: &UserIds) {
+        if old.any_root() && !new.any_root() {
+            self.permitted = 0;
+            self.effective = 0;
+        }
+        if old.euid == 0 && new.euid != 0 {
+            self.effective = 0;
+        } else if old.euid != 0 && new.euid == 0 {
+            self.effective = self.permitted;
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axtask::WaitQueue;
+
+/// 一次性的完成量，用于等待某个事件发生
+///
+/// 事件只会被标记一次，之后的 [`Completion::complete`] 调用不会产生任何效果；
+/// 在事件发生之后调用 [`Completion::wait`] 会立即返回。
+pub struct Completion {
+    done: AtomicBool,
+    wq: WaitQueue,
+}
+
+impl Completion {
+    pub const fn new() -> Self {
+        Self {
+            done: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// 标记事件已发生并唤醒所有等待者
+    ///
+    /// 返回本次调用是否是第一次标记。
+    pub fn complete(&self) -> bool {
+        if self.done.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        self.wq.notify_all(true);
+        true
+    }
+
+    /// 阻塞当前任务直到事件发生
+    pub fn wait(&self) {
+        self.wq.wait_until(|| self.done.load(Ordering::Acquire));
+    }
+}
+
+impl Default for Completion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,348 @@
+//! 核心转储
+//!
+//! 进程因默认处理方式为转储核心的信号（如 SIGSEGV、SIGABRT）终止、且可以转储时（见
+//! `PR_SET_DUMPABLE`），将 ELF 格式的核心转储文件写入 `core.<pid>`。文件位于进程的当前目录，
+//! 或内核命令行参数 `coredir` 指定的目录。文件大小受 `RLIMIT_CORE` 限制，超出的部分被截断，
+//! 软限制为 0（默认值）时不转储。
+//!
+//! 文件依次是 ELF 头、程序头、一个 `PT_NOTE` 段和每个可读映射的 `PT_LOAD` 段。`PT_NOTE`
+//! 中有 `NT_PRSTATUS`（信号、进程号和陷入时保存的用户态寄存器）和 `NT_PRPSINFO`（进程名和
+//! 命令行）。映射的内容逐页从页表中读出后写入文件，不会为整个文件分配内存；还没有分配物理页
+//! 的页按全零写入，也不会为此分配物理页。读取不经过用户态地址，转储时不会再触发缺页异常。
+
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+
+use arceos_posix_api::{ctypes, FileLike, AT_FDCWD};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::{arch::TrapFrame, paging::MappingFlags};
+use axmm::Backend;
+use axtask::{current, TaskExtRef};
+use memory_addr::{MemoryAddr, VirtAddr, PAGE_SIZE_4K};
+
+use super::{rlimit::RLIMIT_CORE, TaskExt};
+use crate::{
+    boot_args::boot_args,
+    mm::vdso::ELF_MACHINE,
+    syscall_imp::{open_file, Cred},
+};
+
+/// ELF 文件类型：核心转储文件
+const ET_CORE: u16 = 4;
+/// 程序头类型：可加载的段
+const PT_LOAD: u32 = 1;
+/// 程序头类型：附加信息
+const PT_NOTE: u32 = 4;
+/// 注释类型：进程状态，即 `struct elf_prstatus`
+const NT_PRSTATUS: u32 = 1;
+/// 注释类型：进程信息，即 `struct elf_prpsinfo`
+const NT_PRPSINFO: u32 = 3;
+
+/// ELF 头的大小
+const EHDR_SIZE: usize = 64;
+/// 一个程序头的大小
+const PHDR_SIZE: usize = 56;
+/// `struct elf_prstatus` 中 `pr_reg` 之前的部分的大小
+const PRSTATUS_HEAD_SIZE: usize = 112;
+/// `struct elf_prpsinfo` 的大小
+const PRPSINFO_SIZE: usize = 136;
+
+/// 为因信号 `signo` 终止的当前进程写入核心转储文件，`tf` 为进程在用户态的寄存器
+///
+/// 返回是否写入了文件。进程不可转储、`RLIMIT_CORE` 的软限制为 0 或写入失败时返回 `false`。
+/// 开始转储时清除进程的可转储标志，转储过程中再次产生的致命信号不会导致再次转储。
+pub fn dump(signo: usize, tf: &TrapFrame) -> bool {
+    let curr = current();
+    let ext = curr.task_ext();
+    let limit = ext.rlimits.lock().get(RLIMIT_CORE).cur;
+    if limit == 0 || !ext.set_dumpable(false) {
+        return false;
+    }
+    match write_core(ext, curr.name(), signo, tf, limit) {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("{}: failed to dump core: {:?}", curr.id_name(), err);
+            false
+        }
+    }
+}
+
+/// 创建核心转储文件并依次写入各部分
+fn write_core(ext: &TaskExt, name: &str, signo: usize, tf: &TrapFrame, limit: u64) -> LinuxResult {
+    let file_name = format!("core.{}", ext.proc_id);
+    let path = match &boot_args().core_dir {
+        Some(dir) => format!("{}/{}", dir.trim_end_matches('/'), file_name),
+        None => file_name,
+    };
+    let flags = ctypes::O_WRONLY | ctypes::O_CREAT | ctypes::O_TRUNC | ctypes::O_NOFOLLOW;
+    let file = open_file(AT_FDCWD as _, &path, flags, 0o600, &Cred::current())?;
+
+    // 转储时进程不再运行，映射不会改变；跳过设备内存的线性映射，程序头的数量不能超过 16 位
+    let areas: Vec<(VirtAddr, usize, MappingFlags)> = ext
+        .aspace
+        .lock()
+        .areas()
+        .filter(|(_, _, flags, backend)| {
+            flags.contains(MappingFlags::READ) && !matches!(backend, Backend::Linear { .. })
+        })
+        .map(|(start, size, flags, _)| (start, size, flags))
+        .take(u16::MAX as usize - 1)
+        .collect();
+    let notes = notes(ext, name, signo, tf);
+    let notes_offset = EHDR_SIZE + (areas.len() + 1) * PHDR_SIZE;
+    let data_offset = (notes_offset + notes.len()).align_up_4k();
+
+    let mut out = CoreWriter {
+        file,
+        written: 0,
+        limit,
+    };
+    out.write(&elf_header(areas.len() + 1))?;
+    out.write(&program_header(PT_NOTE, 0, notes_offset, 0, notes.len(), 4))?;
+    let mut offset = data_offset;
+    for &(start, size, flags) in &areas {
+        let header = program_header(
+            PT_LOAD,
+            segment_flags(flags),
+            offset,
+            start.as_usize(),
+            size,
+            PAGE_SIZE_4K,
+        );
+        out.write(&header)?;
+        offset += size;
+    }
+    out.write(&notes)?;
+
+    let mut page = vec![0u8; PAGE_SIZE_4K];
+    out.write(&page[..data_offset - notes_offset - notes.len()])?;
+    for (start, size, _) in areas {
+        for vaddr in (start.as_usize()..start.as_usize() + size).step_by(PAGE_SIZE_4K) {
+            if out.is_full() {
+                return Ok(());
+            }
+            // 每页单独加锁，写文件时不持有地址空间的锁
+            if ext.aspace.lock().read(vaddr.into(), &mut page).is_err() {
+                page.fill(0);
+            }
+            out.write(&page)?;
+        }
+    }
+    Ok(())
+}
+
+/// 按 `RLIMIT_CORE` 截断的核心转储文件
+struct CoreWriter {
+    file: Arc<dyn FileLike>,
+    /// 已写入的字节数
+    written: u64,
+    /// 文件的最大字节数
+    limit: u64,
+}
+
+impl CoreWriter {
+    /// 是否已达到大小限制
+    fn is_full(&self) -> bool {
+        self.written >= self.limit
+    }
+
+    /// 写入 `buf`，丢弃超出大小限制的部分
+    fn write(&mut self, buf: &[u8]) -> LinuxResult {
+        let len = (buf.len() as u64).min(self.limit - self.written) as usize;
+        let mut remaining = &buf[..len];
+        while !remaining.is_empty() {
+            match self.file.write(remaining)? {
+                0 => return Err(LinuxError::ENOSPC),
+                n => remaining = &remaining[n..],
+            }
+        }
+        self.written += len as u64;
+        Ok(())
+    }
+}
+
+/// 核心转储文件的 ELF 头，有 `phnum` 个程序头，没有节
+fn elf_header(phnum: usize) -> [u8; EHDR_SIZE] {
+    let mut ehdr = [0u8; EHDR_SIZE];
+    ehdr[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+    ehdr[16..18].copy_from_slice(&ET_CORE.to_le_bytes());
+    ehdr[18..20].copy_from_slice(&ELF_MACHINE.to_le_bytes());
+    ehdr[20..24].copy_from_slice(&1u32.to_le_bytes()); // EV_CURRENT
+    ehdr[32..40].copy_from_slice(&(EHDR_SIZE as u64).to_le_bytes()); // e_phoff
+    ehdr[52..54].copy_from_slice(&(EHDR_SIZE as u16).to_le_bytes()); // e_ehsize
+    ehdr[54..56].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes()); // e_phentsize
+    ehdr[56..58].copy_from_slice(&(phnum as u16).to_le_bytes()); // e_phnum
+    ehdr[58..60].copy_from_slice(&64u16.to_le_bytes()); // e_shentsize
+    ehdr
+}
+
+/// 一个程序头，段在文件和内存中的大小相同
+fn program_header(
+    p_type: u32,
+    p_flags: u32,
+    offset: usize,
+    vaddr: usize,
+    size: usize,
+    align: usize,
+) -> [u8; PHDR_SIZE] {
+    let mut phdr = [0u8; PHDR_SIZE];
+    phdr[0..4].copy_from_slice(&p_type.to_le_bytes());
+    phdr[4..8].copy_from_slice(&p_flags.to_le_bytes());
+    phdr[8..16].copy_from_slice(&(offset as u64).to_le_bytes());
+    phdr[16..24].copy_from_slice(&(vaddr as u64).to_le_bytes());
+    phdr[32..40].copy_from_slice(&(size as u64).to_le_bytes()); // p_filesz
+    phdr[40..48].copy_from_slice(&(size as u64).to_le_bytes()); // p_memsz
+    phdr[48..56].copy_from_slice(&(align as u64).to_le_bytes());
+    phdr
+}
+
+/// 映射权限对应的段标志 `PF_R`、`PF_W`、`PF_X`
+fn segment_flags(flags: MappingFlags) -> u32 {
+    [
+        (MappingFlags::READ, 4),
+        (MappingFlags::WRITE, 2),
+        (MappingFlags::EXECUTE, 1),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags.contains(*flag))
+    .map(|(_, bit)| bit)
+    .sum()
+}
+
+/// `PT_NOTE` 段的内容
+fn notes(ext: &TaskExt, name: &str, signo: usize, tf: &TrapFrame) -> Vec<u8> {
+    let mut notes = Vec::new();
+    push_note(&mut notes, NT_PRSTATUS, &prstatus(ext, signo, tf));
+    push_note(&mut notes, NT_PRPSINFO, &prpsinfo(ext, name));
+    notes
+}
+
+/// 追加一条名字为 `CORE` 的注释，名字和内容都按 4 字节对齐
+fn push_note(notes: &mut Vec<u8>, n_type: u32, desc: &[u8]) {
+    notes.extend_from_slice(&5u32.to_le_bytes()); // n_namesz
+    notes.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&n_type.to_le_bytes());
+    notes.extend_from_slice(b"CORE\0\0\0\0");
+    notes.extend_from_slice(desc);
+    notes.resize(notes.len().next_multiple_of(4), 0);
+}
+
+/// 将 `bytes` 写到 `buf` 的 `offset` 处
+fn put(buf: &mut [u8], offset: usize, bytes: &[u8]) {
+    buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+/// 以纳秒计的时间对应的 `struct timeval`
+fn timeval(nanos: u64) -> [u8; 16] {
+    let mut tv = [0u8; 16];
+    put(&mut tv, 0, &(nanos / 1_000_000_000).to_le_bytes());
+    put(&mut tv, 8, &(nanos % 1_000_000_000 / 1000).to_le_bytes());
+    tv
+}
+
+/// `struct elf_prstatus`：终止进程的信号、进程号、运行时间和用户态寄存器
+///
+/// TODO: 支持进程组和会话后填入真实的 `pr_pgrp` 与 `pr_sid`
+fn prstatus(ext: &TaskExt, signo: usize, tf: &TrapFrame) -> Vec<u8> {
+    let pid = ext.proc_id as u32;
+    let (pending, blocked) = {
+        let signal = ext.signal.lock();
+        (signal.pending(), signal.blocked)
+    };
+    let (user_time, kernel_time) = ext.time_stat.lock().info();
+    let (cutime, cstime) = ext.children_time();
+
+    let mut desc = vec![0u8; PRSTATUS_HEAD_SIZE];
+    put(&mut desc, 0, &(signo as u32).to_le_bytes()); // pr_info.si_signo
+    put(&mut desc, 12, &(signo as u16).to_le_bytes()); // pr_cursig
+    put(&mut desc, 16, &pending.0.to_le_bytes());
+    put(&mut desc, 24, &blocked.0.to_le_bytes());
+    put(&mut desc, 32, &pid.to_le_bytes());
+    put(&mut desc, 36, &(ext.parent_id() as u32).to_le_bytes());
+    put(&mut desc, 40, &pid.to_le_bytes());
+    put(&mut desc, 44, &pid.to_le_bytes());
+    put(&mut desc, 48, &timeval(user_time));
+    put(&mut desc, 64, &timeval(kernel_time));
+    put(&mut desc, 80, &timeval(cutime));
+    put(&mut desc, 96, &timeval(cstime));
+    for reg in user_regs(tf) {
+        desc.extend_from_slice(&reg.to_le_bytes());
+    }
+    // pr_fpvalid 为 0：没有转储浮点寄存器
+    desc.resize((desc.len() + 4).next_multiple_of(8), 0);
+    desc
+}
+
+/// `struct elf_prpsinfo`：进程名和命令行
+fn prpsinfo(ext: &TaskExt, name: &str) -> [u8; PRPSINFO_SIZE] {
+    let pid = ext.proc_id as u32;
+    let mut desc = [0u8; PRPSINFO_SIZE];
+    desc[1] = b'R'; // pr_sname
+    desc[3] = ext.nice() as i8 as u8;
+    put(&mut desc, 24, &pid.to_le_bytes());
+    put(&mut desc, 28, &(ext.parent_id() as u32).to_le_bytes());
+    put(&mut desc, 32, &pid.to_le_bytes());
+    put(&mut desc, 36, &pid.to_le_bytes());
+    // 与 Linux 一致，两者都截断并保留结尾的 0
+    let comm = name.rsplit('/').next().unwrap_or(name).as_bytes();
+    put(&mut desc, 40, &comm[..comm.len().min(15)]);
+    let args: String = ext.cmdline.lock().join(" ");
+    put(&mut desc, 56, &args.as_bytes()[..args.len().min(79)]);
+    desc
+}
+
+/// 按 Linux 的 `elf_gregset_t` 排列的用户态寄存器
+#[cfg(target_arch = "x86_64")]
+fn user_regs(tf: &TrapFrame) -> Vec<u64> {
+    // 陷入时不区分系统调用，orig_rax 记为 -1；段寄存器的值在 64 位模式下不使用
+    let fs_base = axhal::arch::read_thread_pointer() as u64;
+    vec![
+        tf.r15,
+        tf.r14,
+        tf.r13,
+        tf.r12,
+        tf.rbp,
+        tf.rbx,
+        tf.r11,
+        tf.r10,
+        tf.r9,
+        tf.r8,
+        tf.rax,
+        tf.rcx,
+        tf.rdx,
+        tf.rsi,
+        tf.rdi,
+        u64::MAX,
+        tf.rip,
+        tf.cs,
+        tf.rflags,
+        tf.rsp,
+        tf.ss,
+        fs_base,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ]
+}
+
+/// 按 Linux 的 `elf_gregset_t` 排列的用户态寄存器，第一项为 pc
+#[cfg(target_arch = "riscv64")]
+fn user_regs(tf: &TrapFrame) -> Vec<u64> {
+    let r = &tf.regs;
+    [
+        tf.sepc, r.ra, r.sp, r.gp, r.tp, r.t0, r.t1, r.t2, r.s0, r.s1, r.a0, r.a1, r.a2, r.a3,
+        r.a4, r.a5, r.a6, r.a7, r.s2, r.s3, r.s4, r.s5, r.s6, r.s7, r.s8, r.s9, r.s10, r.s11, r.t3,
+        r.t4, r.t5, r.t6,
+    ]
+    .map(|reg| reg as u64)
+    .to_vec()
+}
+
+/// 按 Linux 的 `elf_gregset_t` 排列的用户态寄存器：x0 到 x30、sp、pc 和 pstate
+#[cfg(target_arch = "aarch64")]
+fn user_regs(tf: &TrapFrame) -> Vec<u64> {
+    let mut regs = tf.r.to_vec();
+    regs.extend_from_slice(&[tf.usp, tf.elr, tf.spsr]);
+    regs
+}
@@ -0,0 +1,50 @@
+//! 进程的用户 ID
+//!
+//! 目前只有用户 ID，组 ID 总是 0。所有文件都属于同一用户，非 root 用户按文件属主的
+//! 权限位访问文件。
+
+/// 进程的实际、有效和保存的用户 ID，在 clone 和 exec 时保留
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserIds {
+    /// 实际用户 ID，即进程的所有者
+    pub ruid: u32,
+    /// 有效用户 ID，检查文件权限时使用
+    pub euid: u32,
+    /// 保存的用户 ID，使进程可以把有效用户 ID 切换回它
+    pub suid: u32,
+}
+
+impl UserIds {
+    /// 超级用户进程的用户 ID
+    pub const fn root() -> Self {
+        Self {
+            ruid: 0,
+            euid: 0,
+            suid: 0,
+        }
+    }
+
+    /// 是否有某个用户 ID 为 0
+    pub const fn any_root(&self) -> bool {
+        self.ruid == 0 || self.euid == 0 || self.suid == 0
+    }
+
+    /// 按 `setuid` 的规则修改，不允许时返回 `false` 且不做修改
+    ///
+    /// `privileged` 为 `true`（拥有 `CAP_SETUID`）时三个用户 ID 都改为 `uid`，否则只能把
+    /// 有效用户 ID 改为实际或保存的用户 ID。
+    pub fn setuid(&mut self, uid: u32, privileged: bool) -> bool {
+        if privileged {
+            *self = Self {
+                ruid: uid,
+                euid: uid,
+                suid: uid,
+            };
+        } else if uid == self.ruid || uid == self.suid {
+            self.euid = uid;
+        } else {
+            return false;
+        }
+        true
+    }
+}
@@ -2,6 +2,8 @@ use axhal::paging::MappingFlags;
 use axtask::{current, TaskExtRef};
 use memory_addr::VirtAddr;
 
+use super::rlimit::{RLIMIT_AS, RLIMIT_DATA};
+
 #[derive(Debug, Clone, Copy)]
 pub struct HeapManager {
     heap_top: VirtAddr,
@@ -49,13 +51,23 @@ impl HeapManager {
         }
 
         let aligned_top: VirtAddr = memory_addr::align_up_4k(top.as_usize()).into();
-        current()
-            .task_ext()
-            .aspace
-            .lock()
+        let curr = current();
+        let mut aspace = curr.task_ext().aspace.lock();
+        let (data_limit, as_limit) = {
+            let rlimits = curr.task_ext().rlimits.lock();
+            (rlimits.get(RLIMIT_DATA), rlimits.get(RLIMIT_AS))
+        };
+        let grow = aligned_top - self.actual_heap_top;
+        if !data_limit.allows(aligned_top.as_usize() - crate::config::USER_HEAP_BOTTOM)
+            || !as_limit.allows(aspace.mapped_size() + grow)
+        {
+            debug!("Heap top exceeds the resource limit: {:#x?}", top);
+            return None;
+        }
+        aspace
             .map_alloc(
                 self.actual_heap_top,
-                aligned_top - self.actual_heap_top,
+                grow,
                 MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
                 false,
             )
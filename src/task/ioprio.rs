@@ -0,0 +1,63 @@
+//! 进程的 I/O 优先级
+//!
+//! I/O 优先级由调度类和类中的级别组成，编码与 Linux 一致：高 3 位是调度类，低 13 位中
+//! 最低的 3 位是级别，其余是给设备的提示，只被记录下来。
+//! 块设备的请求按提交者的调度类排队，实时类先于尽力而为类，尽力而为类先于空闲类，
+//! 同一类的请求按提交的顺序执行。类中的级别只被记录下来，不影响请求的顺序。
+//!
+//! 未设置过的进程为 [`IOPRIO_CLASS_NONE`] 类的 0 级，按普通进程的 CPU 调度策略视为尽力而为类。
+
+use core::sync::atomic::Ordering;
+
+use axfs::{IoClass, IoPriorityProvider};
+use axtask::{current, TaskExtRef};
+
+use super::TaskExt;
+
+/// 调度类在 I/O 优先级中的偏移
+pub const IOPRIO_CLASS_SHIFT: u32 = 13;
+/// 级别所占的位，共 8 级，0 级最高
+pub const IOPRIO_LEVEL_MASK: u32 = 0x7;
+
+/// 未设置，跟随 CPU 调度策略
+pub const IOPRIO_CLASS_NONE: u32 = 0;
+/// 实时类
+pub const IOPRIO_CLASS_RT: u32 = 1;
+/// 尽力而为类
+pub const IOPRIO_CLASS_BE: u32 = 2;
+/// 空闲类，只在没有其他请求时得到服务
+pub const IOPRIO_CLASS_IDLE: u32 = 3;
+
+impl TaskExt {
+    /// 进程的 I/O 优先级
+    pub fn ioprio(&self) -> u32 {
+        self.ioprio.load(Ordering::Relaxed)
+    }
+
+    /// 设置进程的 I/O 优先级，调用者需要保证它是有效的值
+    pub fn set_ioprio(&self, ioprio: u32) {
+        self.ioprio.store(ioprio, Ordering::Relaxed);
+    }
+}
+
+struct IoPriority;
+
+impl IoPriorityProvider for IoPriority {
+    fn current_class(&self) -> IoClass {
+        let curr = current();
+        // 内核线程（如预读线程）按尽力而为类处理
+        if unsafe { curr.task_ext_ptr() }.is_null() {
+            return IoClass::BestEffort;
+        }
+        match curr.task_ext().ioprio() >> IOPRIO_CLASS_SHIFT {
+            IOPRIO_CLASS_RT => IoClass::RealTime,
+            IOPRIO_CLASS_IDLE => IoClass::Idle,
+            _ => IoClass::BestEffort,
+        }
+    }
+}
+
+/// 向块设备的请求队列注册进程的 I/O 调度类
+pub fn init() {
+    axfs::register_io_priority(&IoPriority);
+}
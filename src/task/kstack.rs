@@ -0,0 +1,93 @@
+//! 内核栈的使用量统计和溢出检查
+//!
+//! 用户任务的内核栈在创建时用 [`POISON`] 填充，之后第一个不再是填充值的字即为栈使用的
+//! 最深处。内核栈从堆中分配，没有保护页，栈底的 [`CANARY_WORDS`] 个字相当于金丝雀：
+//! 它们被改写说明栈已经溢出，相邻的内存可能已被破坏，此时在系统调用返回时使内核停机。
+
+use core::sync::atomic::Ordering;
+
+use axtask::{TaskExtRef, TaskInner};
+
+/// 未使用的内核栈中填充的值
+const POISON: usize = 0x5aa5_5aa5_5aa5_5aa5_u64 as usize;
+/// 栈顶不填充的字节数，任务的初始上下文可能已经写在这里
+const TOP_RESERVED: usize = 256;
+/// 栈底用作金丝雀的字数
+const CANARY_WORDS: usize = 8;
+/// 使用量超过栈大小的这一比例时给出警告，以百分比表示
+const WARN_PERCENT: usize = 75;
+
+const WORD: usize = core::mem::size_of::<usize>();
+
+/// 返回内核栈的 (栈底, 栈顶)，没有内核栈的任务返回 `None`
+fn bounds(task: &TaskInner) -> Option<(usize, usize)> {
+    let top = task.kernel_stack_top()?.as_usize();
+    Some((top - crate::config::KERNEL_STACK_SIZE, top))
+}
+
+fn read_word(addr: usize) -> usize {
+    unsafe { (addr as *const usize).read_volatile() }
+}
+
+/// 填充新建任务的内核栈，应在任务开始运行前调用
+pub fn poison(task: &TaskInner) {
+    let Some((bottom, top)) = bounds(task) else {
+        return;
+    };
+    for addr in (bottom..top - TOP_RESERVED).step_by(WORD) {
+        unsafe { (addr as *mut usize).write_volatile(POISON) };
+    }
+}
+
+/// 返回任务内核栈使用量的最高水位，以字节计
+///
+/// 需要从栈底逐字扫描，只应在查询时调用，如 `/proc/[pid]/status`。
+pub fn kernel_stack_usage(task: &TaskInner) -> usize {
+    let Some((bottom, top)) = bounds(task) else {
+        return 0;
+    };
+    (bottom..top)
+        .step_by(WORD)
+        .find(|&addr| read_word(addr) != POISON)
+        .map_or(0, |addr| top - addr)
+}
+
+/// 内核栈是否已经溢出，即栈底的金丝雀被改写
+fn overflowed(bottom: usize) -> bool {
+    (0..CANARY_WORDS).any(|i| read_word(bottom + i * WORD) != POISON)
+}
+
+/// 在系统调用返回时检查当前任务的内核栈
+///
+/// 栈已经溢出时 panic，给出任务名、系统调用号和使用量。调试构建中，使用量首次超过
+/// 栈大小的 75% 时打印警告，便于调整 `KERNEL_STACK_SIZE`。两项检查都只读取固定位置，
+/// 不扫描整个栈。
+pub fn check(task: &TaskInner, sysno: usize) {
+    let Some((bottom, top)) = bounds(task) else {
+        return;
+    };
+    if overflowed(bottom) {
+        panic!(
+            "{}: kernel stack overflow in syscall {}, usage {} of {} bytes",
+            task.id_name(),
+            crate::syscall_imp::syscall_name(sysno).unwrap_or("unknown"),
+            kernel_stack_usage(task),
+            top - bottom,
+        );
+    }
+    if cfg!(debug_assertions) {
+        let mark = top - (top - bottom) * WARN_PERCENT / 100;
+        let mark = mark & !(WORD - 1);
+        if read_word(mark) != POISON && !task.task_ext().kstack_warned.swap(true, Ordering::Relaxed)
+        {
+            warn!(
+                "{}: kernel stack usage exceeds {}% ({} of {} bytes) in syscall {}",
+                task.id_name(),
+                WARN_PERCENT,
+                kernel_stack_usage(task),
+                top - bottom,
+                crate::syscall_imp::syscall_name(sysno).unwrap_or("unknown"),
+            );
+        }
+    }
+}
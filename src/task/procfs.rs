@@ -0,0 +1,388 @@
+//! `/proc/[pid]` 下的进程信息
+//!
+//! 目录和文件由 axfs 中的 procfs 生成，每次读取时调用这里的函数，按进程当前的状态重新生成内容。
+
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use core::fmt::Write;
+
+use arceos_posix_api::{Cap, FileLike, FD_TABLE};
+use axerrno::{LinuxError, LinuxResult};
+use axfs::ProcessInfoProvider;
+use axhal::paging::MappingFlags;
+use axmm::Backend;
+use axtask::{current, AxTaskRef, TaskExtRef, TaskState};
+use memory_addr::PAGE_SIZE_4K;
+
+use super::{
+    all_pids, find_task_by_pid, find_zombie, kstack, nanos_to_clock_ticks, ptrace,
+    signal::{SigSet, NSIG, SIGCHLD, SIG_DFL, SIG_IGN},
+    Pid, ZombieRecord,
+};
+
+struct ProcessInfo;
+
+impl ProcessInfoProvider for ProcessInfo {
+    fn pids(&self) -> Vec<u64> {
+        all_pids().into_iter().map(|pid| pid as u64).collect()
+    }
+
+    fn current_pid(&self) -> u64 {
+        let curr = current();
+        // 内核线程没有对应的进程目录
+        if unsafe { curr.task_ext_ptr() }.is_null() {
+            return 0;
+        }
+        curr.task_ext().proc_id as u64
+    }
+
+    fn owner(&self, pid: u64) -> Option<(u32, u32)> {
+        // 与 Linux 一致，进程目录属于进程的有效用户，目前没有组 ID
+        match find_task_by_pid(pid as Pid) {
+            Some(task) => Some((task.task_ext().euid(), 0)),
+            None => find_zombie(pid as Pid).map(|(_, zombie)| (zombie.uid, 0)),
+        }
+    }
+
+    fn files(&self) -> &'static [&'static str] {
+        &["cmdline", "maps", "stat", "status"]
+    }
+
+    fn render(&self, pid: u64, name: &str) -> Option<Vec<u8>> {
+        let Some(task) = find_task_by_pid(pid as Pid) else {
+            // 僵尸进程的任务已被释放，只能由父进程中的记录生成
+            let (ppid, zombie) = find_zombie(pid as Pid)?;
+            let content = match name {
+                "cmdline" | "maps" => Vec::new(),
+                "stat" => zombie_stat(ppid, &zombie).into_bytes(),
+                "status" => zombie_status(ppid, &zombie).into_bytes(),
+                _ => return None,
+            };
+            return Some(content);
+        };
+        let content = match name {
+            "cmdline" => cmdline(&task),
+            "maps" => maps(&task).into_bytes(),
+            "stat" => stat(&task).into_bytes(),
+            "status" => status(&task).into_bytes(),
+            _ => return None,
+        };
+        Some(content)
+    }
+
+    fn fds(&self, pid: u64) -> Option<Vec<(u32, String)>> {
+        let Some(task) = find_task_by_pid(pid as Pid) else {
+            // 僵尸进程的文件都已关闭
+            return find_zombie(pid as Pid).map(|_| Vec::new());
+        };
+        let table = FD_TABLE.deref_from(&task.task_ext().ns).read();
+        let fds = (0..table.capacity())
+            .filter_map(|fd| {
+                let file = table.get(fd)?.access(Cap::empty())?;
+                Some((fd as u32, file.link_target()))
+            })
+            .collect();
+        Some(fds)
+    }
+}
+
+/// 返回进程 `pid` 的文件描述符 `fd` 打开的文件，即 `/proc/[pid]/fd` 中的链接所指的文件
+///
+/// 与 Linux 一致，只能访问有效用户相同的进程的文件描述符，root 不受此限制，否则返回
+/// EACCES。进程不存在或没有打开 `fd` 时返回 ENOENT。
+pub fn fd_file(pid: Pid, fd: u32) -> LinuxResult<Arc<dyn FileLike>> {
+    let task = find_task_by_pid(pid).ok_or(LinuxError::ENOENT)?;
+    let euid = current().task_ext().euid();
+    if euid != 0 && euid != task.task_ext().euid() {
+        return Err(LinuxError::EACCES);
+    }
+    let table = FD_TABLE.deref_from(&task.task_ext().ns).read();
+    let file = table
+        .get(fd as usize)
+        .and_then(|entry| entry.access(Cap::empty()))
+        .ok_or(LinuxError::ENOENT)?;
+    Ok(file.clone())
+}
+
+/// 向 procfs 注册进程信息
+pub fn init() {
+    axfs::register_process_info(&ProcessInfo);
+}
+
+/// 进程名，取程序路径的最后一部分，最多 15 个字符
+fn comm(task: &AxTaskRef) -> String {
+    comm_of(task.name())
+}
+
+fn comm_of(name: &str) -> String {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    name.chars().take(15).collect()
+}
+
+/// 进程状态的单字符表示和描述
+fn state(task: &AxTaskRef) -> (char, &'static str) {
+    if task.task_ext().is_trace_stopped() {
+        return ('t', "tracing stop");
+    }
+    match task.state() {
+        TaskState::Running | TaskState::Ready => ('R', "running"),
+        TaskState::Blocked => ('S', "sleeping"),
+        TaskState::Exited => ('Z', "zombie"),
+    }
+}
+
+/// 忽略和设置了处理函数的信号
+fn signal_dispositions(task: &AxTaskRef) -> (SigSet, SigSet) {
+    let actions = task.task_ext().signal_actions.lock();
+    let (mut ignored, mut caught) = (SigSet::default(), SigSet::default());
+    for signo in 1..=NSIG {
+        match actions.get(signo).handler {
+            SIG_DFL => {}
+            SIG_IGN => ignored.add(signo),
+            _ => caught.add(signo),
+        }
+    }
+    (ignored, caught)
+}
+
+/// 以 0 分隔的命令行参数
+fn cmdline(task: &AxTaskRef) -> Vec<u8> {
+    let mut content = Vec::new();
+    for arg in task.task_ext().cmdline.lock().iter() {
+        content.extend_from_slice(arg.as_bytes());
+        content.push(0);
+    }
+    content
+}
+
+/// `/proc/[pid]/maps`，每个映射一行，权限是映射当前的权限
+///
+/// 没有设备号和 inode 号，都为 0。文件映射的名称是文件的路径，堆、栈、vDSO 和信号跳板
+/// 分别为 `[heap]`、`[stack]`、`[vdso]` 和 `[sigpage]`，其他匿名映射没有名称。
+fn maps(task: &AxTaskRef) -> String {
+    use crate::config::{
+        SIGNAL_TRAMPOLINE, USER_HEAP_BOTTOM, USER_HEAP_SIZE, USER_STACK_SIZE, USER_STACK_TOP,
+        VDSO_BASE,
+    };
+
+    let aspace = task.task_ext().aspace.lock();
+    let mut maps = String::new();
+    for (start, size, flags, backend) in aspace.areas() {
+        let addr = start.as_usize();
+        let file = backend.file();
+        let shared = match file {
+            Some(file) => file.shared,
+            None => matches!(backend, Backend::Shared { .. }),
+        };
+        let perm = |flag, c| if flags.contains(flag) { c } else { '-' };
+        let line = format!(
+            "{:08x}-{:08x} {}{}{}{} {:08x} 00:00 0",
+            addr,
+            addr + size,
+            perm(MappingFlags::READ, 'r'),
+            perm(MappingFlags::WRITE, 'w'),
+            perm(MappingFlags::EXECUTE, 'x'),
+            if shared { 's' } else { 'p' },
+            file.map_or(0, |file| file.offset_of(start)),
+        );
+        let name = match file {
+            Some(file) => file.path.as_str(),
+            None if (USER_HEAP_BOTTOM..USER_HEAP_BOTTOM + USER_HEAP_SIZE).contains(&addr) => {
+                "[heap]"
+            }
+            None if (USER_STACK_TOP - USER_STACK_SIZE..USER_STACK_TOP).contains(&addr) => "[stack]",
+            None if addr == VDSO_BASE => "[vdso]",
+            None if addr == SIGNAL_TRAMPOLINE => "[sigpage]",
+            None => "",
+        };
+        // 与 Linux 一致，名称从第 74 列开始
+        if name.is_empty() {
+            let _ = writeln!(maps, "{}", line);
+        } else {
+            let _ = writeln!(maps, "{:<72} {}", line, name);
+        }
+    }
+    maps
+}
+
+/// `/proc/[pid]/stat`，共 52 个字段，目前没有统计的字段为 0
+fn stat(task: &AxTaskRef) -> String {
+    let ext = task.task_ext();
+    let pid = ext.proc_id;
+    let (user_time, kernel_time) = ext.time_stat.lock().info();
+    let (cutime, cstime) = ext.children_time();
+    let (vsize, rss) = {
+        let aspace = ext.aspace.lock();
+        (aspace.mapped_size(), aspace.resident_pages())
+    };
+    let (pending, blocked) = {
+        let signal = ext.signal.lock();
+        (signal.pending(), signal.blocked)
+    };
+    let (ignored, caught) = signal_dispositions(task);
+    let exit_code = match task.state() {
+        TaskState::Exited => ext.wait_status(task.exit_code()),
+        _ => 0,
+    };
+
+    let mut stat = String::new();
+    // pid (comm) state ppid pgrp session tty_nr tpgid flags
+    // TODO: 支持进程组和会话后报告真实的 pgrp 与 session
+    let _ = write!(
+        stat,
+        "{} ({}) {} {} {} {} 0 -1 0 ",
+        pid,
+        comm(task),
+        state(task).0,
+        ext.parent_id(),
+        pid,
+        pid
+    );
+    // minflt cminflt majflt cmajflt utime stime cutime cstime priority nice num_threads itrealvalue
+    let _ = write!(
+        stat,
+        "0 0 0 0 {} {} {} {} {} {} 1 0 ",
+        nanos_to_clock_ticks(user_time),
+        nanos_to_clock_ticks(kernel_time),
+        nanos_to_clock_ticks(cutime),
+        nanos_to_clock_ticks(cstime),
+        20 + ext.nice(),
+        ext.nice(),
+    );
+    // starttime vsize rss rsslim startcode endcode startstack kstkesp kstkeip
+    let _ = write!(
+        stat,
+        "{} {} {} {} 0 0 0 0 0 ",
+        nanos_to_clock_ticks(ext.start_time),
+        vsize,
+        rss,
+        u64::MAX
+    );
+    // signal blocked sigignore sigcatch wchan nswap cnswap exit_signal processor rt_priority policy
+    let _ = write!(
+        stat,
+        "{} {} {} {} 0 0 0 {} {} 0 0 ",
+        pending.0,
+        blocked.0,
+        ignored.0,
+        caught.0,
+        SIGCHLD,
+        ext.last_cpu()
+    );
+    // delayacct_blkio_ticks guest_time cguest_time start_data end_data start_brk
+    // arg_start arg_end env_start env_end exit_code
+    let _ = writeln!(
+        stat,
+        "0 0 0 0 0 {} 0 0 0 0 {}",
+        crate::config::USER_HEAP_BOTTOM,
+        exit_code
+    );
+    stat
+}
+
+/// `/proc/[pid]/status`，每行一个 `名称:\t值`
+fn status(task: &AxTaskRef) -> String {
+    let ext = task.task_ext();
+    let (state, state_name) = state(task);
+    let (vsize, rss) = {
+        let aspace = ext.aspace.lock();
+        (aspace.mapped_size(), aspace.resident_pages() * PAGE_SIZE_4K)
+    };
+    let (pending, blocked) = {
+        let signal = ext.signal.lock();
+        (signal.pending(), signal.blocked)
+    };
+    let (ignored, caught) = signal_dispositions(task);
+    let uids = ext.user_ids();
+    format!(
+        "Name:\t{}\n\
+         State:\t{} ({})\n\
+         Tgid:\t{pid}\n\
+         Pid:\t{pid}\n\
+         PPid:\t{}\n\
+         TracerPid:\t{}\n\
+         Uid:\t{}\t{}\t{}\t{}\n\
+         Gid:\t0\t0\t0\t0\n\
+         VmSize:\t{} kB\n\
+         VmRSS:\t{} kB\n\
+         KStk:\t{} kB\n\
+         Threads:\t1\n\
+         SigPnd:\t{:016x}\n\
+         SigBlk:\t{:016x}\n\
+         SigIgn:\t{:016x}\n\
+         SigCgt:\t{:016x}\n",
+        comm(task),
+        state,
+        state_name,
+        ext.parent_id(),
+        ptrace::tracer_pid(ext),
+        uids.ruid,
+        uids.euid,
+        uids.suid,
+        uids.euid,
+        vsize / 1024,
+        rss / 1024,
+        kstack::kernel_stack_usage(task) / 1024,
+        pending.0,
+        blocked.0,
+        ignored.0,
+        caught.0,
+        pid = ext.proc_id,
+    )
+}
+
+/// 僵尸进程的 `/proc/[pid]/stat`，字段与 [`stat`] 相同，除运行时间外的统计都为 0
+fn zombie_stat(ppid: Pid, zombie: &ZombieRecord) -> String {
+    let pid = zombie.pid;
+    let mut stat = String::new();
+    // pid (comm) state ppid pgrp session tty_nr tpgid flags
+    let _ = write!(
+        stat,
+        "{} ({}) Z {} {} {} 0 -1 0 ",
+        pid,
+        comm_of(&zombie.name),
+        ppid,
+        pid,
+        pid
+    );
+    // minflt cminflt majflt cmajflt utime stime cutime cstime priority nice num_threads itrealvalue
+    let _ = write!(
+        stat,
+        "0 0 0 0 {} {} {} {} 20 0 1 0 ",
+        nanos_to_clock_ticks(zombie.time.0),
+        nanos_to_clock_ticks(zombie.time.1),
+        nanos_to_clock_ticks(zombie.children_time.0),
+        nanos_to_clock_ticks(zombie.children_time.1),
+    );
+    // starttime vsize rss rsslim startcode endcode startstack kstkesp kstkeip
+    let _ = write!(
+        stat,
+        "{} 0 0 {} 0 0 0 0 0 ",
+        nanos_to_clock_ticks(zombie.start_time),
+        u64::MAX
+    );
+    // signal blocked sigignore sigcatch wchan nswap cnswap exit_signal processor rt_priority policy
+    let _ = write!(stat, "0 0 0 0 0 0 0 {} 0 0 0 ", SIGCHLD);
+    // delayacct_blkio_ticks guest_time cguest_time start_data end_data start_brk
+    // arg_start arg_end env_start env_end exit_code
+    let _ = writeln!(stat, "0 0 0 0 0 0 0 0 0 0 {}", zombie.status);
+    stat
+}
+
+/// 僵尸进程的 `/proc/[pid]/status`，只包含身份信息
+fn zombie_status(ppid: Pid, zombie: &ZombieRecord) -> String {
+    format!(
+        "Name:\t{}\n\
+         State:\tZ (zombie)\n\
+         Tgid:\t{pid}\n\
+         Pid:\t{pid}\n\
+         PPid:\t{}\n\
+         TracerPid:\t0\n\
+         Uid:\t{uid}\t{uid}\t{uid}\t{uid}\n\
+         Gid:\t0\t0\t0\t0\n\
+         Threads:\t1\n",
+        comm_of(&zombie.name),
+        ppid,
+        pid = zombie.pid,
+        uid = zombie.uid,
+    )
+}
@@ -0,0 +1,316 @@
+//! 进程跟踪
+//!
+//! 被跟踪的进程在递送信号、执行 exec 以及（由 `PTRACE_SYSCALL` 恢复时）进入和离开
+//! 系统调用时停下，在自己的 `signal_wq` 上等待跟踪者让它继续运行。跟踪者通过 `wait4`
+//! 得知停止的原因，并可以在被跟踪者停止期间读写其内存和陷入帧中的寄存器。
+//!
+//! 跟踪者在 [`TaskExt::tracees`](super::TaskExt::tracees) 中记录被跟踪的进程，
+//! 被跟踪者的停止状态保存在 [`TaskExt::ptrace`](super::TaskExt::ptrace) 中。被跟踪者停止或退出时
+//! 唤醒跟踪者的 [`TaskExt::child_wq`](super::TaskExt::child_wq)。
+
+use alloc::sync::Arc;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::arch::UspaceContext;
+use axtask::{current, AxTaskRef, TaskExtRef, WeakAxTaskRef};
+
+use super::{
+    find_task_by_pid, nanos_to_clock_ticks,
+    signal::{trap_frame_of, SigInfo, CLD_TRAPPED, SIGKILL, SIGSTOP, SIGTRAP, SI_USER},
+    Pid, TaskExt,
+};
+
+/// 在系统调用停止时报告 `SIGTRAP | 0x80`，以便与真正的 SIGTRAP 区分
+pub const PTRACE_O_TRACESYSGOOD: u32 = 1;
+
+/// 被跟踪者的跟踪状态
+#[derive(Default)]
+pub struct PtraceState {
+    /// 跟踪者，为 `None` 表示没有被跟踪
+    tracer: Option<WeakAxTaskRef>,
+    /// 停止时报告给跟踪者的信号，为 `None` 表示正在运行
+    stop: Option<usize>,
+    /// 本次停止是否已经由 `wait4` 报告过
+    reported: bool,
+    /// 继续运行时递送的信号，为 0 表示不递送
+    resume_signal: usize,
+    /// 是否在进入和离开系统调用时停止
+    trace_syscall: bool,
+    /// 正在执行的系统调用在入口处报告过，返回用户态前需要在出口处停止
+    in_syscall: bool,
+    options: u32,
+}
+
+impl TaskExt {
+    /// 是否正在被跟踪
+    pub fn is_traced(&self) -> bool {
+        self.ptrace.lock().tracer.is_some()
+    }
+
+    /// 是否因被跟踪而处于停止状态
+    pub fn is_trace_stopped(&self) -> bool {
+        self.ptrace.lock().stop.is_some()
+    }
+}
+
+/// 当前进程停止并通知跟踪者，返回跟踪者让它继续运行时指定的信号
+///
+/// 未被跟踪时直接返回 `signo`。停止期间收到 SIGKILL 时立即返回，由随后的信号处理终止进程。
+pub fn stop_current(signo: usize) -> usize {
+    let curr = current();
+    let ext = curr.task_ext();
+    let tracer = {
+        let mut state = ext.ptrace.lock();
+        let Some(tracer) = state.tracer.as_ref().and_then(|tracer| tracer.upgrade()) else {
+            return signo;
+        };
+        state.stop = Some(signo);
+        state.reported = false;
+        state.resume_signal = 0;
+        tracer
+    };
+
+    tracer.task_ext().child_wq.notify_all();
+    let (user_time, kernel_time) = ext.time_stat.lock().info();
+    tracer.task_ext().send_signal(SigInfo::child(
+        ext.proc_id,
+        CLD_TRAPPED,
+        signo as i32,
+        nanos_to_clock_ticks(user_time),
+        nanos_to_clock_ticks(kernel_time),
+    ));
+
+    ext.signal_wq.wait_until(|| {
+        ext.ptrace.lock().stop.is_none() || ext.signal.lock().pending().contains(SIGKILL)
+    });
+    let mut state = ext.ptrace.lock();
+    state.stop = None;
+    core::mem::take(&mut state.resume_signal)
+}
+
+/// 在系统调用入口处停止，由系统调用处理函数在分发之前调用
+pub fn syscall_enter() {
+    let curr = current();
+    let signo = {
+        let mut state = curr.task_ext().ptrace.lock();
+        state.in_syscall = state.tracer.is_some();
+        if !state.in_syscall || !state.trace_syscall {
+            return;
+        }
+        syscall_stop_signal(&state)
+    };
+    resend(stop_current(signo));
+}
+
+/// 在系统调用出口处停止，此时返回值已经写入陷入帧
+pub fn syscall_exit() {
+    let curr = current();
+    let signo = {
+        let mut state = curr.task_ext().ptrace.lock();
+        if !core::mem::take(&mut state.in_syscall) || !state.trace_syscall {
+            return;
+        }
+        syscall_stop_signal(&state)
+    };
+    resend(stop_current(signo));
+}
+
+/// 执行 exec 之后、进入新程序之前以 SIGTRAP 停止，返回跟踪者可能修改过的用户态上下文
+///
+/// exec 直接进入新程序而不经过陷入返回的路径，因此先将新程序的寄存器写入陷入帧，
+/// 跟踪者才能通过 `PTRACE_GETREGSET` 看到它们。
+pub fn stop_at_exec(uctx: UspaceContext) -> UspaceContext {
+    let curr = current();
+    {
+        let mut state = curr.task_ext().ptrace.lock();
+        if state.tracer.is_none() {
+            return uctx;
+        }
+        // exec 不会返回，也就不会在系统调用出口处停止
+        state.in_syscall = false;
+    }
+    let tf = trap_frame_of(&curr);
+    *tf = *uctx.trap_frame();
+    resend(stop_current(SIGTRAP));
+    UspaceContext::from(tf)
+}
+
+fn syscall_stop_signal(state: &PtraceState) -> usize {
+    if state.options & PTRACE_O_TRACESYSGOOD != 0 {
+        SIGTRAP | 0x80
+    } else {
+        SIGTRAP
+    }
+}
+
+/// 跟踪者在系统调用停止后要求递送信号时，将其加入当前进程的待处理信号
+fn resend(signo: usize) {
+    if signo != 0 {
+        let curr = current();
+        let info = SigInfo::user(signo, SI_USER, tracer_pid(curr.task_ext()));
+        curr.task_ext().send_signal(info);
+    }
+}
+
+/// 跟踪者的 PID，没有被跟踪时返回 0
+pub fn tracer_pid(ext: &TaskExt) -> Pid {
+    ext.ptrace
+        .lock()
+        .tracer
+        .as_ref()
+        .and_then(|tracer| tracer.upgrade())
+        .map_or(0, |tracer| tracer.task_ext().proc_id)
+}
+
+fn start_tracing(tracer: &AxTaskRef, tracee: &AxTaskRef) -> LinuxResult {
+    let mut state = tracee.task_ext().ptrace.lock();
+    if state.tracer.is_some() {
+        return Err(LinuxError::EPERM);
+    }
+    *state = PtraceState {
+        tracer: Some(Arc::downgrade(tracer)),
+        ..Default::default()
+    };
+    drop(state);
+    tracer
+        .task_ext()
+        .tracees
+        .lock()
+        .push(Arc::downgrade(tracee));
+    Ok(())
+}
+
+/// 让父进程跟踪当前进程
+pub fn traceme() -> LinuxResult {
+    let curr = current();
+    let parent = curr
+        .task_ext()
+        .parent
+        .as_ref()
+        .and_then(|parent| parent.upgrade())
+        // 第一个用户进程的父任务是内核线程
+        .filter(|parent| !unsafe { parent.task_ext_ptr() }.is_null())
+        .ok_or(LinuxError::EPERM)?;
+    start_tracing(&parent, curr.as_task_ref())
+}
+
+/// 让当前进程跟踪 `pid`，并向其发送 SIGSTOP
+pub fn attach(pid: Pid) -> LinuxResult {
+    let curr = current();
+    let target = find_task_by_pid(pid)
+        .filter(|task| task.state() != axtask::TaskState::Exited)
+        .ok_or(LinuxError::ESRCH)?;
+    if target.task_ext().proc_id == curr.task_ext().proc_id {
+        return Err(LinuxError::EPERM);
+    }
+    if !curr.task_ext().may_access(target.task_ext()) {
+        return Err(LinuxError::EPERM);
+    }
+    start_tracing(curr.as_task_ref(), &target)?;
+    target
+        .task_ext()
+        .send_signal(SigInfo::user(SIGSTOP, SI_USER, curr.task_ext().proc_id));
+    Ok(())
+}
+
+/// 查找由当前进程跟踪且处于停止状态的进程 `pid`，否则返回 ESRCH
+pub fn stopped_tracee(pid: Pid) -> LinuxResult<AxTaskRef> {
+    let curr = current();
+    let tracee = find_task_by_pid(pid).ok_or(LinuxError::ESRCH)?;
+    let state = tracee.task_ext().ptrace.lock();
+    let traced_by_us = state
+        .tracer
+        .as_ref()
+        .is_some_and(|tracer| tracer.as_ptr() == Arc::as_ptr(curr.as_task_ref()));
+    if !traced_by_us || state.stop.is_none() {
+        return Err(LinuxError::ESRCH);
+    }
+    drop(state);
+    Ok(tracee)
+}
+
+/// 设置跟踪选项，目前只支持 [`PTRACE_O_TRACESYSGOOD`]
+pub fn set_options(tracee: &AxTaskRef, options: u32) -> LinuxResult {
+    if options & !PTRACE_O_TRACESYSGOOD != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    tracee.task_ext().ptrace.lock().options = options;
+    Ok(())
+}
+
+/// 让停止的被跟踪者继续运行并递送信号 `signo`，`trace_syscall` 为真时在下一次进入或离开系统调用时停止
+pub fn resume(tracee: &AxTaskRef, signo: usize, trace_syscall: bool) {
+    let ext = tracee.task_ext();
+    let mut state = ext.ptrace.lock();
+    state.stop = None;
+    state.resume_signal = signo;
+    state.trace_syscall = trace_syscall;
+    drop(state);
+    ext.signal_wq.notify_all(false);
+}
+
+/// 停止跟踪，被跟踪者继续运行并递送信号 `signo`
+pub fn detach(tracee: &AxTaskRef, signo: usize) {
+    let ext = tracee.task_ext();
+    let tracer = ext.ptrace.lock().tracer.take();
+    if let Some(tracer) = tracer.and_then(|tracer| tracer.upgrade()) {
+        tracer
+            .task_ext()
+            .tracees
+            .lock()
+            .retain(|task| task.as_ptr() != Arc::as_ptr(tracee));
+    }
+    resume(tracee, signo, false);
+}
+
+/// 跟踪者退出时停止跟踪它的全部被跟踪者
+pub fn detach_all() {
+    let curr = current();
+    let tracees = core::mem::take(&mut *curr.task_ext().tracees.lock());
+    for tracee in tracees.iter().filter_map(|tracee| tracee.upgrade()) {
+        tracee.task_ext().ptrace.lock().tracer = None;
+        resume(&tracee, 0, false);
+    }
+}
+
+/// 被跟踪者退出时离开跟踪者，使等待它停止的 `wait4` 不再等待
+pub fn release_tracer() {
+    let curr = current();
+    let tracer = curr.task_ext().ptrace.lock().tracer.take();
+    if let Some(tracer) = tracer.and_then(|tracer| tracer.upgrade()) {
+        tracer
+            .task_ext()
+            .tracees
+            .lock()
+            .retain(|task| task.as_ptr() != Arc::as_ptr(curr.as_task_ref()));
+        tracer.task_ext().child_wq.notify_all();
+    }
+}
+
+/// `wait4` 查找由当前进程跟踪、符合 `pid` 且尚未报告的停止
+///
+/// `pid` 的含义与 `wait4` 相同。有可报告的停止时返回被跟踪者的 PID 和对应的等待状态；
+/// 没有则返回 `Err(running)`，`running` 表示是否有符合条件且仍在运行的被跟踪者。
+pub fn wait_stopped(pid: i32) -> Result<(Pid, i32), bool> {
+    let curr = current();
+    let mut tracees = curr.task_ext().tracees.lock();
+    tracees.retain(|tracee| {
+        tracee
+            .upgrade()
+            .is_some_and(|task| task.state() != axtask::TaskState::Exited)
+    });
+    let mut running = false;
+    for tracee in tracees.iter().filter_map(|tracee| tracee.upgrade()) {
+        let ext = tracee.task_ext();
+        if pid > 0 && ext.proc_id != pid as Pid {
+            continue;
+        }
+        running = true;
+        let mut state = ext.ptrace.lock();
+        if let Some(signo) = state.stop.filter(|_| !state.reported) {
+            state.reported = true;
+            return Ok((ext.proc_id, (signo as i32) << 8 | 0x7f));
+        }
+    }
+    Err(running)
+}
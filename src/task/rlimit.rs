@@ -0,0 +1,69 @@
+//! 进程的资源限制
+//!
+//! 目前只实际检查 `RLIMIT_NOFILE`、`RLIMIT_AS`、`RLIMIT_DATA` 和 `RLIMIT_CORE`，其余限制只保存设置的值。
+
+use arceos_posix_api::AX_FILE_LIMIT;
+
+/// 数据段（堆）的最大字节数
+pub const RLIMIT_DATA: usize = 2;
+/// 用户栈的最大字节数
+pub const RLIMIT_STACK: usize = 3;
+/// 核心转储文件的最大字节数，为 0 时不转储
+pub const RLIMIT_CORE: usize = 4;
+/// 文件描述符编号的上限
+pub const RLIMIT_NOFILE: usize = 7;
+/// 地址空间的最大字节数
+pub const RLIMIT_AS: usize = 9;
+/// 资源限制的种类数
+pub const RLIM_NLIMITS: usize = 16;
+
+/// 表示不限制
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// 一项资源限制，与 Linux 的 `struct rlimit64` 布局相同
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RLimit {
+    /// 软限制，实际生效的值
+    pub cur: u64,
+    /// 硬限制，软限制的上限
+    pub max: u64,
+}
+
+impl RLimit {
+    const fn new(cur: u64, max: u64) -> Self {
+        Self { cur, max }
+    }
+
+    /// `amount` 是否没有超过软限制
+    pub fn allows(&self, amount: usize) -> bool {
+        self.cur == RLIM_INFINITY || amount as u64 <= self.cur
+    }
+}
+
+/// 进程的全部资源限制，在 fork 和 exec 时保留
+#[derive(Debug, Clone)]
+pub struct RLimits([RLimit; RLIM_NLIMITS]);
+
+impl Default for RLimits {
+    fn default() -> Self {
+        let mut limits = [RLimit::new(RLIM_INFINITY, RLIM_INFINITY); RLIM_NLIMITS];
+        limits[RLIMIT_STACK] = RLimit::new(crate::config::USER_STACK_SIZE as u64, RLIM_INFINITY);
+        // 与 Linux 的默认设置一致，进程需要先调高软限制才会转储核心
+        limits[RLIMIT_CORE] = RLimit::new(0, RLIM_INFINITY);
+        limits[RLIMIT_NOFILE] = RLimit::new(AX_FILE_LIMIT as u64, AX_FILE_LIMIT as u64);
+        Self(limits)
+    }
+}
+
+impl RLimits {
+    /// 返回资源 `resource` 的限制，调用者需保证 `resource < RLIM_NLIMITS`
+    pub fn get(&self, resource: usize) -> RLimit {
+        self.0[resource]
+    }
+
+    /// 设置资源 `resource` 的限制，调用者需保证 `resource < RLIM_NLIMITS`
+    pub fn set(&mut self, resource: usize, limit: RLimit) {
+        self.0[resource] = limit;
+    }
+}
@@ -0,0 +1,162 @@
+//! 用户任务的时间片轮转
+//!
+//! 内核使用协作式的调度器，任务只在内核中阻塞或主动让出 CPU 时才会切换，一直在用户态
+//! 运算的任务会使其他任务得不到运行。因此用户任务每次被调度运行时获得一个时间片，
+//! 从时钟中断等陷入返回用户态之前检查时间片是否用完，用完则让出 CPU，排到就绪队列末尾。
+//!
+//! 时间片的基准长度取自配置文件中的 `timeslice-ms`，可由启动参数 `timeslice=` 覆盖，
+//! 并按进程的 nice 值以 Linux 的权重表缩放，nice 值越大时间片越短。时间片用完后要到
+//! 下一次陷入才会被发现，因此实际长度按时钟中断的间隔向上取整。
+//!
+//! 实时调度策略按 Linux 的规则决定时间片：`SCHED_RR` 的任务使用固定长度的时间片，
+//! 用完后排到同一就绪队列中其他任务之后；`SCHED_FIFO` 的任务没有时间片，一直运行到阻塞
+//! 或主动让出 CPU。就绪队列本身不区分优先级，实时任务不会抢占普通任务，实时优先级目前
+//! 只保存设置的值。
+
+use core::sync::atomic::Ordering;
+
+use axhal::{
+    arch::TrapFrame,
+    time::monotonic_time_nanos,
+    trap::{register_trap_handler, RETURN_TO_USER},
+};
+use axtask::{current, TaskExtRef, TaskInner};
+use num_enum::TryFromPrimitive;
+
+use super::TaskExt;
+
+/// nice 值的范围
+pub const NICE_MIN: i32 = -20;
+pub const NICE_MAX: i32 = 19;
+
+/// nice 值为 0 的任务的权重
+const NICE_0_WEIGHT: u64 = 1024;
+
+/// nice 值从 -20 到 19 对应的权重，与 Linux 的 `sched_prio_to_weight` 一致，
+/// 相邻两级约相差 1.25 倍
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// 实时调度策略的优先级范围
+pub const RT_PRIO_MIN: u32 = 1;
+pub const RT_PRIO_MAX: u32 = 99;
+
+/// `SCHED_RR` 的时间片长度（毫秒），与 Linux 的 `RR_TIMESLICE` 一致
+pub const RR_TIMESLICE_MS: u64 = 100;
+
+/// 调度策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u32)]
+pub enum SchedPolicy {
+    /// 普通的分时调度，时间片长度由 nice 值决定
+    Other = 0,
+    /// 实时先进先出调度，没有时间片
+    Fifo = 1,
+    /// 实时时间片轮转调度
+    Rr = 2,
+}
+
+impl SchedPolicy {
+    /// 是否为实时调度策略
+    pub fn is_realtime(self) -> bool {
+        self != Self::Other
+    }
+
+    /// 该策略的优先级范围，普通调度策略只能使用 0
+    pub fn priority_range(self) -> (u32, u32) {
+        if self.is_realtime() {
+            (RT_PRIO_MIN, RT_PRIO_MAX)
+        } else {
+            (0, 0)
+        }
+    }
+}
+
+/// nice 值对应的调度权重
+pub fn weight(nice: i32) -> u64 {
+    NICE_TO_WEIGHT[(nice.clamp(NICE_MIN, NICE_MAX) - NICE_MIN) as usize]
+}
+
+/// nice 值对应的时间片长度（纳秒）
+pub fn timeslice(nice: i32) -> u64 {
+    let base = crate::boot_args::boot_args().timeslice.as_nanos() as u64;
+    base * weight(nice) / NICE_0_WEIGHT
+}
+
+/// `sched_rr_get_interval` 报告的时间片长度（纳秒），`SCHED_FIFO` 没有时间片，为 0
+pub fn policy_timeslice(policy: SchedPolicy, nice: i32) -> u64 {
+    match policy {
+        SchedPolicy::Other => timeslice(nice),
+        SchedPolicy::Fifo => 0,
+        SchedPolicy::Rr => RR_TIMESLICE_MS * 1_000_000,
+    }
+}
+
+impl TaskExt {
+    /// 进程的 nice 值
+    pub fn nice(&self) -> i32 {
+        self.nice.load(Ordering::Relaxed)
+    }
+
+    /// 设置进程的 nice 值，调用者需要保证它在 [`NICE_MIN`] 到 [`NICE_MAX`] 之间
+    pub fn set_nice(&self, nice: i32) {
+        self.nice.store(nice, Ordering::Relaxed);
+    }
+
+    /// 进程的调度策略和实时优先级
+    pub fn sched_policy(&self) -> (SchedPolicy, u32) {
+        let policy = SchedPolicy::try_from(self.sched_policy.load(Ordering::Relaxed))
+            .unwrap_or(SchedPolicy::Other);
+        (policy, self.rt_priority.load(Ordering::Relaxed))
+    }
+
+    /// 设置进程的调度策略和实时优先级，调用者需要保证优先级在策略的范围内
+    ///
+    /// 新的策略在进程下一次获得时间片时生效。
+    pub fn set_sched_policy(&self, policy: SchedPolicy, priority: u32) {
+        self.rt_priority.store(priority, Ordering::Relaxed);
+        self.sched_policy.store(policy as u32, Ordering::Relaxed);
+    }
+
+    /// 从现在开始重新计算时间片，`SCHED_FIFO` 的任务的时间片不会用完
+    fn refill_timeslice(&self) {
+        let end = match self.sched_policy().0 {
+            SchedPolicy::Fifo => u64::MAX,
+            policy => monotonic_time_nanos() + policy_timeslice(policy, self.nice()),
+        };
+        self.slice_end.store(end, Ordering::Relaxed);
+    }
+
+    /// 时间片是否已经用完
+    fn timeslice_expired(&self) -> bool {
+        monotonic_time_nanos() >= self.slice_end.load(Ordering::Relaxed)
+    }
+}
+
+/// 用户任务被调度运行时获得新的时间片
+#[axtask::register_switch_hook(axtask::TASK_SWITCH_HOOKS)]
+fn refill_on_switch(_prev: &TaskInner, next: &TaskInner) {
+    // 避开只有内核线程的情况,如 idle 线程等
+    if !unsafe { next.task_ext_ptr() }.is_null() {
+        next.task_ext().refill_timeslice();
+    }
+}
+
+/// 返回用户态前，时间片用完的任务让出 CPU
+#[register_trap_handler(RETURN_TO_USER)]
+fn preempt_expired(_tf: &mut TrapFrame) {
+    let curr = current();
+    // 避开只有内核线程的情况,如 idle 线程等
+    if unsafe { curr.task_ext_ptr() }.is_null() {
+        return;
+    }
+    let ext = curr.task_ext();
+    if ext.timeslice_expired() {
+        axtask::yield_now();
+        // 没有其他就绪任务时不会发生切换，需要在这里重新填充
+        ext.refill_timeslice();
+    }
+}
@@ -0,0 +1,955 @@
+//! 信号的产生与递送
+//!
+//! 每个任务维护自己的信号掩码、待处理信号集合和备用信号栈，信号处理方式则保存在
+//! [`SignalActions`] 中。信号在任务即将返回用户态时递送：若设置了处理函数，
+//! 就在用户栈（或备用信号栈）上压入 [`SignalFrame`]，并让任务从处理函数开始执行；
+//! 处理函数返回后经由跳板代码调用 `rt_sigreturn`，从信号帧中恢复原先的上下文。
+//!
+//! SIGKILL 不能被阻塞、忽略或捕获，但也只在上述时机生效：阻塞在可被打断的等待中
+//! （[`block_interruptible`] 和 [`TaskExt::wait_signal`]）的任务会立即醒来；处于不可被
+//! 打断的内核路径中的任务，如等待 vfork 的子进程的父进程，要到该路径结束、即将返回
+//! 用户态时才被终止，但不会再执行任何用户态指令。
+
+use alloc::collections::btree_map::BTreeMap;
+use core::{mem::size_of, time::Duration};
+
+use arceos_posix_api::{WaitQueue, WaitResult};
+use axerrno::{AxResult, LinuxError, LinuxResult};
+use axhal::{
+    arch::{FpState, TrapFrame},
+    trap::{register_trap_handler, RETURN_TO_USER},
+};
+use axtask::{current, AxTaskRef, TaskExtRef, TaskInner};
+use memory_addr::{MemoryAddr, VirtAddr};
+
+use super::{coredump, ptrace, TaskExt};
+use crate::mm::{copy_from_user, copy_to_user};
+
+/// 支持的信号数量，信号编号为 `1..=NSIG`
+pub const NSIG: usize = 64;
+
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGSEGV: usize = 11;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+pub const SIGURG: usize = 23;
+pub const SIGXCPU: usize = 24;
+pub const SIGXFSZ: usize = 25;
+pub const SIGWINCH: usize = 28;
+pub const SIGSYS: usize = 31;
+
+/// 默认处理方式
+pub const SIG_DFL: usize = 0;
+/// 忽略该信号
+pub const SIG_IGN: usize = 1;
+
+/// 由 `kill` 发送
+pub const SI_USER: i32 = 0;
+/// 由 POSIX 定时器到期产生
+pub const SI_TIMER: i32 = -2;
+/// 由 `tkill` 或 `tgkill` 发送
+pub const SI_TKILL: i32 = -6;
+/// 由内核发送
+pub const SI_KERNEL: i32 = 0x80;
+/// SIGCHLD：子进程正常退出
+pub const CLD_EXITED: i32 = 1;
+/// SIGCHLD：子进程被信号终止
+pub const CLD_KILLED: i32 = 2;
+/// SIGCHLD：子进程被信号终止并转储了核心
+pub const CLD_DUMPED: i32 = 3;
+/// SIGCHLD：被跟踪的子进程停止
+pub const CLD_TRAPPED: i32 = 4;
+/// SIGSEGV：访问的地址没有被映射
+pub const SEGV_MAPERR: i32 = 1;
+/// SIGSEGV：没有访问该地址的权限
+pub const SEGV_ACCERR: i32 = 2;
+/// SIGBUS：地址已被映射，但没有可以提供的页面，如超出了映射文件的末尾
+pub const BUS_ADRERR: i32 = 2;
+
+/// 正在备用信号栈上执行
+pub const SS_ONSTACK: i32 = 1;
+/// 备用信号栈被禁用
+pub const SS_DISABLE: i32 = 2;
+/// 备用信号栈的最小大小
+pub const MINSIGSTKSZ: usize = 2048;
+
+/// 信号处理结束后调用 `rt_sigreturn` 的跳板代码，被映射到每个用户地址空间的
+/// [`SIGNAL_TRAMPOLINE`](crate::config::SIGNAL_TRAMPOLINE) 处
+pub const SIGRETURN_TRAMPOLINE: &[u8] = if cfg!(target_arch = "riscv64") {
+    // li a7, 139; ecall
+    &[0x93, 0x08, 0xb0, 0x08, 0x73, 0x00, 0x00, 0x00]
+} else if cfg!(target_arch = "x86_64") {
+    // mov rax, 15; syscall
+    &[0x48, 0xc7, 0xc0, 0x0f, 0x00, 0x00, 0x00, 0x0f, 0x05]
+} else if cfg!(target_arch = "aarch64") {
+    // mov x8, #139; svc #0
+    &[0x68, 0x11, 0x80, 0xd2, 0x01, 0x00, 0x00, 0xd4]
+} else {
+    &[]
+};
+
+/// 信号集合，第 `i` 位对应编号为 `i + 1` 的信号
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SigSet(pub u64);
+
+impl SigSet {
+    /// 不可被阻塞的信号
+    const UNBLOCKABLE: SigSet = SigSet((1 << (SIGKILL - 1)) | (1 << (SIGSTOP - 1)));
+
+    pub const fn contains(&self, signo: usize) -> bool {
+        self.0 & (1 << (signo - 1)) != 0
+    }
+
+    pub fn add(&mut self, signo: usize) {
+        self.0 |= 1 << (signo - 1);
+    }
+
+    pub fn remove(&mut self, signo: usize) {
+        self.0 &= !(1 << (signo - 1));
+    }
+
+    /// 去掉不可被阻塞的信号，用于设置信号掩码
+    pub const fn blockable(self) -> Self {
+        Self(self.0 & !Self::UNBLOCKABLE.0)
+    }
+}
+
+bitflags::bitflags! {
+    /// `sigaction` 的选项
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct SaFlags: usize {
+        /// 子进程停止时不产生 SIGCHLD
+        const SA_NOCLDSTOP = 0x0000_0001;
+        /// 子进程退出时不变为僵尸进程
+        const SA_NOCLDWAIT = 0x0000_0002;
+        /// 处理函数接收 siginfo 和 ucontext 参数
+        const SA_SIGINFO = 0x0000_0004;
+        /// 由用户提供 `restorer` 作为处理函数的返回地址
+        const SA_RESTORER = 0x0400_0000;
+        /// 在备用信号栈上执行处理函数
+        const SA_ONSTACK = 0x0800_0000;
+        /// 被信号中断的系统调用自动重新执行
+        const SA_RESTART = 0x1000_0000;
+        /// 执行处理函数时不阻塞该信号本身
+        const SA_NODEFER = 0x4000_0000;
+        /// 处理函数执行一次后恢复为默认处理方式
+        const SA_RESETHAND = 0x8000_0000;
+    }
+}
+
+/// 内核中的 `struct sigaction`，布局与用户态传入的结构一致
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigAction {
+    /// 处理函数，或者 [`SIG_DFL`]、[`SIG_IGN`]
+    pub handler: usize,
+    pub flags: usize,
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub restorer: usize,
+    /// 执行处理函数期间额外阻塞的信号
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    pub fn flags(&self) -> SaFlags {
+        SaFlags::from_bits_truncate(self.flags)
+    }
+
+    /// 处理函数的返回地址
+    fn restorer(&self) -> usize {
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        if self.flags().contains(SaFlags::SA_RESTORER) && self.restorer != 0 {
+            return self.restorer;
+        }
+        crate::config::SIGNAL_TRAMPOLINE
+    }
+}
+
+/// 信号的默认处理方式
+enum DefaultAction {
+    /// 终止进程
+    Terminate,
+    /// 转储核心后终止进程
+    Core,
+    /// 忽略信号
+    Ignore,
+    /// 停止进程，目前不支持，按忽略处理
+    Stop,
+}
+
+fn default_action(signo: usize) -> DefaultAction {
+    match signo {
+        SIGCHLD | SIGCONT | SIGURG | SIGWINCH => DefaultAction::Ignore,
+        SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => DefaultAction::Stop,
+        SIGQUIT | SIGILL | SIGTRAP | SIGABRT | SIGBUS | SIGFPE | SIGSEGV | SIGXCPU | SIGXFSZ
+        | SIGSYS => DefaultAction::Core,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// 进程的信号处理方式
+#[derive(Clone)]
+pub struct SignalActions([SigAction; NSIG]);
+
+impl SignalActions {
+    pub fn new() -> Self {
+        Self([SigAction::default(); NSIG])
+    }
+
+    pub fn get(&self, signo: usize) -> SigAction {
+        self.0[signo - 1]
+    }
+
+    pub fn set(&mut self, signo: usize, action: SigAction) {
+        self.0[signo - 1] = action;
+    }
+
+    /// 该信号在产生时是否会被直接丢弃
+    fn ignores(&self, signo: usize) -> bool {
+        match self.get(signo).handler {
+            SIG_IGN => true,
+            SIG_DFL => !matches!(
+                default_action(signo),
+                DefaultAction::Terminate | DefaultAction::Core
+            ),
+            _ => false,
+        }
+    }
+
+    /// 子进程退出时是否被自动回收，而不成为可被等待的僵尸进程
+    ///
+    /// SIGCHLD 被设为 [`SIG_IGN`] 或设置了 `SA_NOCLDWAIT` 时成立。
+    pub fn reaps_children(&self) -> bool {
+        let action = self.get(SIGCHLD);
+        action.handler == SIG_IGN || action.flags().contains(SaFlags::SA_NOCLDWAIT)
+    }
+
+    /// 执行 exec 时调用：设置了处理函数的信号恢复为默认处理方式，被忽略的信号保持不变
+    pub fn reset_handlers(&mut self) {
+        for action in self.0.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SigAction::default();
+            }
+        }
+    }
+}
+
+impl Default for SignalActions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 信号的附加信息，布局与 Linux 中的 `siginfo_t` 一致，共 128 字节
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SigInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    _pad: i32,
+    /// 与信号种类相关的字段，如发送者的 pid、出错的地址等
+    fields: [u64; 14],
+}
+
+impl SigInfo {
+    fn new(signo: usize, code: i32) -> Self {
+        Self {
+            signo: signo as i32,
+            errno: 0,
+            code,
+            _pad: 0,
+            fields: [0; 14],
+        }
+    }
+
+    /// 由进程 `pid` 发送的信号，`code` 为 [`SI_USER`] 或 [`SI_TKILL`]
+    pub fn user(signo: usize, code: i32, pid: usize) -> Self {
+        let mut info = Self::new(signo, code);
+        // si_pid 与 si_uid，目前所有进程的 uid 都为 0
+        info.fields[0] = pid as u32 as u64;
+        info
+    }
+
+    /// 由内核发送的信号
+    pub fn kernel(signo: usize) -> Self {
+        Self::new(signo, SI_KERNEL)
+    }
+
+    /// POSIX 定时器 `timer_id` 到期时发送的信号
+    ///
+    /// `overrun` 为此前错过的到期次数，`value` 为创建定时器时指定的 `sigev_value`。
+    pub fn timer(signo: usize, timer_id: i32, overrun: i32, value: u64) -> Self {
+        let mut info = Self::new(signo, SI_TIMER);
+        info.fields[0] = timer_id as u32 as u64 | (overrun as u32 as u64) << 32;
+        info.fields[1] = value;
+        info
+    }
+
+    /// 子进程 `pid` 退出时发给父进程的 SIGCHLD
+    ///
+    /// `status` 为退出码或导致终止的信号，`utime` 与 `stime` 以时钟滴答为单位。
+    pub fn child(pid: usize, code: i32, status: i32, utime: u64, stime: u64) -> Self {
+        let mut info = Self::new(SIGCHLD, code);
+        info.fields[0] = pid as u32 as u64;
+        info.fields[1] = status as u32 as u64;
+        info.fields[2] = utime;
+        info.fields[3] = stime;
+        info
+    }
+
+    /// 由访问 `addr` 出错产生的信号
+    pub fn fault(signo: usize, code: i32, addr: usize) -> Self {
+        let mut info = Self::new(signo, code);
+        info.fields[0] = addr as u64;
+        info
+    }
+}
+
+/// 备用信号栈，布局与 Linux 中的 `stack_t` 一致
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalStack {
+    pub sp: usize,
+    pub flags: i32,
+    pub size: usize,
+}
+
+impl Default for SignalStack {
+    fn default() -> Self {
+        Self {
+            sp: 0,
+            flags: SS_DISABLE,
+            size: 0,
+        }
+    }
+}
+
+impl SignalStack {
+    pub fn enabled(&self) -> bool {
+        self.flags & SS_DISABLE == 0
+    }
+
+    /// 用户栈指针 `sp` 是否位于该栈上
+    pub fn contains(&self, sp: usize) -> bool {
+        self.enabled() && sp > self.sp && sp - self.sp <= self.size
+    }
+
+    /// 以用户栈指针为 `sp` 时的视角报告该栈，正在其上执行时带有 [`SS_ONSTACK`] 标志
+    pub fn at(&self, sp: usize) -> SignalStack {
+        let mut stack = *self;
+        if stack.contains(sp) {
+            stack.flags |= SS_ONSTACK;
+        }
+        stack
+    }
+}
+
+/// 任务的信号状态
+#[derive(Default)]
+pub struct SignalState {
+    /// 被阻塞的信号
+    pub blocked: SigSet,
+    /// 待处理的信号
+    pending: SigSet,
+    /// 待处理信号的附加信息，同一种信号不会重复排队
+    infos: BTreeMap<usize, SigInfo>,
+    /// 备用信号栈
+    pub altstack: SignalStack,
+    /// 上一个系统调用是 `rt_sigreturn`，返回用户态前需要从信号帧中恢复上下文
+    sigreturn: bool,
+    /// `rt_sigsuspend` 临时替换掉的信号掩码，递送信号后恢复
+    saved_mask: Option<SigSet>,
+    /// 正在执行的系统调用的阻塞被信号打断
+    interrupted: bool,
+    /// 被打断而返回 EINTR 的系统调用号及其第一个参数，返回用户态前决定是否重新执行它
+    restart: Option<(usize, usize)>,
+}
+
+impl SignalState {
+    /// 继承父任务的信号掩码和备用信号栈，待处理信号被清空
+    pub fn inherit(&self) -> Self {
+        Self {
+            blocked: self.blocked,
+            altstack: self.altstack,
+            ..Default::default()
+        }
+    }
+
+    fn enqueue(&mut self, info: SigInfo) {
+        let signo = info.signo as usize;
+        if !self.pending.contains(signo) {
+            self.pending.add(signo);
+            self.infos.insert(signo, info);
+        }
+    }
+
+    /// 取出一个属于 `set` 的待处理信号，编号小的优先
+    pub fn dequeue_from(&mut self, set: SigSet) -> Option<SigInfo> {
+        let candidates = self.pending.0 & set.0;
+        if candidates == 0 {
+            return None;
+        }
+        let signo = candidates.trailing_zeros() as usize + 1;
+        self.pending.remove(signo);
+        self.infos.remove(&signo)
+    }
+
+    /// 取出一个未被阻塞的待处理信号
+    fn dequeue(&mut self) -> Option<SigInfo> {
+        self.dequeue_from(SigSet(!self.blocked.0))
+    }
+
+    /// 由 POSIX 定时器 `timer_id` 产生、尚未递送的信号
+    fn timer_signal(&mut self, timer_id: i32) -> Option<&mut SigInfo> {
+        self.infos
+            .values_mut()
+            .find(|info| info.code == SI_TIMER && info.fields[0] as u32 == timer_id as u32)
+    }
+
+    /// 定时器 `timer_id` 上一次到期产生的信号仍未递送时，为其增加 `count` 次错过的到期，
+    /// 返回新的错过次数
+    pub fn add_timer_overrun(&mut self, timer_id: i32, count: i32) -> Option<i32> {
+        let info = self.timer_signal(timer_id)?;
+        let overrun = ((info.fields[0] >> 32) as i32).saturating_add(count);
+        info.fields[0] = timer_id as u32 as u64 | (overrun as u32 as u64) << 32;
+        Some(overrun)
+    }
+
+    /// 丢弃由 POSIX 定时器 `timer_id` 产生、尚未递送的信号
+    pub fn discard_timer(&mut self, timer_id: i32) {
+        if let Some(signo) = self.timer_signal(timer_id).map(|info| info.signo as usize) {
+            self.pending.remove(signo);
+            self.infos.remove(&signo);
+        }
+    }
+
+    /// 待处理的信号集合
+    pub fn pending(&self) -> SigSet {
+        self.pending
+    }
+
+    /// 当前是否有未被阻塞的待处理信号
+    pub fn has_deliverable(&self) -> bool {
+        self.pending.0 & !self.blocked.0 != 0
+    }
+
+    /// 临时将信号掩码替换为 `mask`，下一次递送信号后恢复原先的掩码
+    pub fn suspend_mask(&mut self, mask: SigSet) {
+        self.saved_mask = Some(self.blocked);
+        self.blocked = mask.blockable();
+    }
+
+    /// 没有递送信号时，恢复 [`suspend_mask`](Self::suspend_mask) 替换掉的信号掩码
+    pub fn restore_mask(&mut self) {
+        if let Some(mask) = self.saved_mask.take() {
+            self.blocked = mask;
+        }
+    }
+
+    /// 标记下一次返回用户态前执行 `rt_sigreturn` 的恢复工作
+    pub fn request_sigreturn(&mut self) {
+        self.sigreturn = true;
+    }
+
+    /// 系统调用 `sysno` 返回 `ret` 时调用
+    ///
+    /// 只有经由 [`TaskExt::interrupted`] 判定被打断而返回 EINTR 的系统调用（如通过
+    /// [`block_interruptible`] 或 POSIX 接口的等待队列阻塞的）才可能被重新执行，
+    /// 其他直接返回 EINTR 的系统调用（如 `pause`）不会。
+    pub fn syscall_returned(&mut self, sysno: usize, arg0: usize, ret: isize) {
+        let interrupted = core::mem::take(&mut self.interrupted);
+        self.restart =
+            (interrupted && ret == -(LinuxError::EINTR.code() as isize)).then_some((sysno, arg0));
+    }
+}
+
+/// 用户态上下文，`uc_sigmask` 之前的布局与 Linux 中的 `ucontext_t` 一致
+///
+/// `uc_mcontext` 直接保存了陷入时的 [`TrapFrame`] 和浮点寄存器，其格式与 Linux 的
+/// `mcontext_t` 不同，用户程序不应依赖其中的内容。
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UContext {
+    flags: usize,
+    link: usize,
+    stack: SignalStack,
+    sigmask: SigSet,
+    _unused: [u8; 120],
+    mcontext: TrapFrame,
+    fpstate: FpState,
+}
+
+/// 递送信号时压入用户栈的信号帧
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SignalFrame {
+    info: SigInfo,
+    ucontext: UContext,
+}
+
+/// 获取当前任务陷入内核时保存的用户态上下文
+pub fn current_trap_frame() -> &'static mut TrapFrame {
+    trap_frame_of(&current())
+}
+
+/// 获取任务 `task` 陷入内核时保存的用户态上下文
+///
+/// 用户态陷入时，陷入帧总是保存在内核栈的顶部。只有在该任务停在内核中时，
+/// 其他任务才能安全地访问它。
+pub fn trap_frame_of(task: &TaskInner) -> &'static mut TrapFrame {
+    let addr = task
+        .kernel_stack_top()
+        .expect("no kernel stack top")
+        .sub(size_of::<TrapFrame>());
+    unsafe { &mut *addr.as_mut_ptr_of::<TrapFrame>() }
+}
+
+impl TaskExt {
+    /// 向该任务发送信号，会被忽略且未被阻塞的信号直接丢弃
+    pub fn send_signal(&self, info: SigInfo) {
+        let signo = info.signo as usize;
+        let actions = self.signal_actions.lock();
+        let mut state = self.signal.lock();
+        // 被阻塞的信号即使会被忽略也要保留，以便用 sigtimedwait 等取出
+        if actions.ignores(signo) && !state.blocked.contains(signo) {
+            return;
+        }
+        state.enqueue(info);
+        drop(state);
+        drop(actions);
+        self.signal_wq.notify_all(false);
+    }
+
+    /// 强制向该任务发送信号，用于处理同步产生的异常
+    ///
+    /// 若该信号被阻塞或忽略，则解除阻塞并恢复默认处理方式，保证进程不会继续执行出错的指令。
+    pub fn force_signal(&self, info: SigInfo) {
+        let signo = info.signo as usize;
+        let mut actions = self.signal_actions.lock();
+        let mut state = self.signal.lock();
+        if state.blocked.contains(signo) || actions.get(signo).handler == SIG_IGN {
+            state.blocked.remove(signo);
+            actions.set(signo, SigAction::default());
+        }
+        state.enqueue(info);
+    }
+
+    /// 当前的阻塞是否应当被信号打断，即是否有未被阻塞的待处理信号
+    ///
+    /// 成立时记录下来，使系统调用因此返回 EINTR 后，若信号被忽略或其处理函数设置了
+    /// `SA_RESTART`，在返回用户态前重新执行该系统调用。
+    pub fn interrupted(&self) -> bool {
+        let mut state = self.signal.lock();
+        state.interrupted = state.has_deliverable();
+        state.interrupted
+    }
+
+    /// 阻塞当前任务，直到 `condition` 成立或有未被阻塞的信号到达
+    ///
+    /// 条件在持有调度器锁时检查，而发送信号时先将其加入队列再唤醒等待者，
+    /// 因此检查之后到达的信号不会被遗漏。`timeout` 为 `None` 时一直等待。
+    /// 条件成立的同时有信号到达时返回 [`WaitOutcome::Ready`]，调用者先处理已就绪的
+    /// 事件，信号留到返回用户态时递送。
+    pub fn wait_signal<F>(&self, timeout: Option<Duration>, condition: F) -> WaitOutcome
+    where
+        F: Fn(&SignalState) -> bool,
+    {
+        let ready = || {
+            let state = self.signal.lock();
+            state.has_deliverable() || condition(&state)
+        };
+        match timeout {
+            Some(dur) => {
+                self.signal_wq.wait_timeout_until(dur, ready);
+            }
+            None => self.signal_wq.wait_until(ready),
+        }
+        let state = self.signal.lock();
+        if condition(&state) {
+            WaitOutcome::Ready
+        } else if state.has_deliverable() {
+            WaitOutcome::Interrupted
+        } else {
+            WaitOutcome::TimedOut
+        }
+    }
+}
+
+/// [`TaskExt::wait_signal`] 醒来的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitOutcome {
+    /// 等待的条件成立
+    Ready,
+    /// 有需要递送的信号
+    Interrupted,
+    /// 超时
+    TimedOut,
+}
+
+/// 可被信号打断地在 `wq` 上阻塞当前任务，直到 `poll` 返回 `Some`
+///
+/// 使 `poll` 的结果改变的一方应在之后唤醒 `wq`。`deadline` 为单调时钟的时间，到达时
+/// 任务醒来再调用一次 `poll`，`poll` 应自行检查期限并返回超时的结果。期间有需要递送的
+/// 信号时返回 EINTR，信号的处理方式为终止进程时，进程在返回用户态前退出。会阻塞的系统
+/// 调用都应通过它等待，被打断后按 [`TaskExt::interrupted`] 的规则重新执行。
+pub fn block_interruptible<T>(
+    wq: &WaitQueue,
+    deadline: Option<Duration>,
+    mut poll: impl FnMut() -> Option<T>,
+) -> LinuxResult<T> {
+    let mut result = None;
+    let mut ready = || {
+        result = poll();
+        result.is_some()
+    };
+    let wait = match deadline {
+        Some(deadline) => wq.wait_interruptible_timeout(&mut ready, deadline),
+        None => wq.wait_interruptible(&mut ready),
+    };
+    match wait {
+        WaitResult::Ok => Ok(result.unwrap()),
+        WaitResult::TimedOut => poll().ok_or(LinuxError::ETIMEDOUT),
+        WaitResult::Interrupted => Err(LinuxError::EINTR),
+    }
+}
+
+struct SignalIfImpl;
+
+/// POSIX 接口中的管道等阻塞操作也按 [`TaskExt::interrupted`] 被信号打断
+#[crate_interface::impl_interface]
+impl arceos_posix_api::SignalIf for SignalIfImpl {
+    fn interrupted() -> bool {
+        current().task_ext().interrupted()
+    }
+}
+
+/// 内核线程没有 `signal_wq`，在 POSIX 接口的等待队列中阻塞时在此等待
+static KERNEL_WQ: axtask::WaitQueue = axtask::WaitQueue::new();
+
+struct WaitIfImpl;
+
+/// POSIX 接口的等待队列中，任务在自己的 `signal_wq` 上睡眠
+///
+/// 发送信号时会唤醒 `signal_wq`，因此可被打断的等待能及时返回；被打断时按
+/// [`TaskExt::interrupted`] 记录下来，使系统调用在适当时重新执行。
+#[crate_interface::impl_interface]
+impl arceos_posix_api::WaitIf for WaitIfImpl {
+    fn wait(
+        woken: &dyn Fn() -> bool,
+        timeout: Option<Duration>,
+        interruptible: bool,
+    ) -> WaitResult {
+        let curr = current();
+        if interruptible && !unsafe { curr.task_ext_ptr() }.is_null() {
+            let ext = curr.task_ext();
+            return match ext.wait_signal(timeout, |_| woken()) {
+                WaitOutcome::Ready => WaitResult::Ok,
+                WaitOutcome::TimedOut => WaitResult::TimedOut,
+                WaitOutcome::Interrupted if ext.interrupted() => WaitResult::Interrupted,
+                // 信号已被其他线程取走，重新检查条件
+                WaitOutcome::Interrupted => WaitResult::Ok,
+            };
+        }
+        let wq = if unsafe { curr.task_ext_ptr() }.is_null() {
+            &KERNEL_WQ
+        } else {
+            &curr.task_ext().signal_wq
+        };
+        match timeout {
+            Some(dur) if wq.wait_timeout_until(dur, woken) => WaitResult::TimedOut,
+            Some(_) => WaitResult::Ok,
+            None => {
+                wq.wait_until(woken);
+                WaitResult::Ok
+            }
+        }
+    }
+
+    fn wake(task: &AxTaskRef) {
+        if unsafe { task.task_ext_ptr() }.is_null() {
+            KERNEL_WQ.notify_all(false);
+        } else {
+            task.task_ext().signal_wq.notify_all(false);
+        }
+    }
+}
+
+/// 让任务返回用户态后重新执行被打断的系统调用 `sysno`
+///
+/// 此时陷入帧中已是系统调用指令之后的地址。返回值覆盖了保存系统调用号（x86_64）
+/// 或第一个参数（其他架构）的寄存器，需要恢复。
+fn restart_syscall(tf: &mut TrapFrame, sysno: usize, arg0: usize) {
+    #[cfg(target_arch = "riscv64")]
+    {
+        let _ = sysno;
+        tf.regs.a0 = arg0;
+    }
+    #[cfg(target_arch = "x86_64")]
+    {
+        let _ = arg0;
+        tf.rax = sysno as u64;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        let _ = sysno;
+        tf.r[0] = arg0 as u64;
+    }
+    tf.set_ip(tf.get_ip() - super::SYSCALL_INSN_LEN);
+}
+
+/// 在用户栈上构造信号帧，并让任务从处理函数开始执行
+fn setup_frame(ext: &TaskExt, tf: &mut TrapFrame, action: &SigAction, info: SigInfo) -> AxResult {
+    let signo = info.signo as usize;
+    let flags = action.flags();
+    let sp = tf.get_sp();
+    let (altstack, old_mask) = {
+        let mut state = ext.signal.lock();
+        let old_mask = state.saved_mask.take().unwrap_or(state.blocked);
+        (state.altstack, old_mask)
+    };
+
+    let stack_top =
+        if flags.contains(SaFlags::SA_ONSTACK) && altstack.enabled() && !altstack.contains(sp) {
+            altstack.sp + altstack.size
+        } else if cfg!(target_arch = "x86_64") {
+            // 跳过 System V ABI 规定的红区
+            sp - 128
+        } else {
+            sp
+        };
+    let frame_addr = (stack_top - size_of::<SignalFrame>()) & !0xf;
+    // 处理函数可能使用浮点寄存器，返回时需要恢复
+    let mut fpstate = FpState::default();
+    fpstate.save();
+    let frame = SignalFrame {
+        info,
+        ucontext: UContext {
+            flags: 0,
+            link: 0,
+            stack: altstack.at(sp),
+            sigmask: old_mask,
+            _unused: [0; 120],
+            mcontext: *tf,
+            fpstate,
+        },
+    };
+    let bytes = unsafe {
+        core::slice::from_raw_parts(&frame as *const _ as *const u8, size_of::<SignalFrame>())
+    };
+    copy_to_user(VirtAddr::from_usize(frame_addr), bytes)?;
+
+    #[cfg(target_arch = "x86_64")]
+    let new_sp = {
+        // 处理函数通过 ret 指令返回到 restorer
+        let mut new_sp = frame_addr;
+        new_sp -= size_of::<usize>();
+        copy_to_user(
+            VirtAddr::from_usize(new_sp),
+            &action.restorer().to_ne_bytes(),
+        )?;
+        new_sp
+    };
+    #[cfg(not(target_arch = "x86_64"))]
+    let new_sp = {
+        tf.set_ra(action.restorer());
+        frame_addr
+    };
+
+    tf.set_ip(action.handler);
+    tf.set_sp(new_sp);
+    tf.set_args(
+        signo,
+        frame_addr + core::mem::offset_of!(SignalFrame, info),
+        frame_addr + core::mem::offset_of!(SignalFrame, ucontext),
+    );
+
+    let mut blocked = SigSet(old_mask.0 | action.mask.0);
+    if !flags.contains(SaFlags::SA_NODEFER) {
+        blocked.add(signo);
+    }
+    ext.signal.lock().blocked = blocked.blockable();
+    if flags.contains(SaFlags::SA_RESETHAND) {
+        ext.signal_actions.lock().set(signo, SigAction::default());
+    }
+    Ok(())
+}
+
+/// 从信号帧中恢复进入处理函数前的上下文
+fn restore_frame(ext: &TaskExt, tf: &mut TrapFrame) -> AxResult {
+    let mut frame_addr = tf.get_sp();
+    if cfg!(target_arch = "x86_64") {
+        // restorer 的地址已被处理函数的 ret 指令弹出
+        frame_addr -= size_of::<usize>();
+    }
+    let mut ucontext = core::mem::MaybeUninit::<UContext>::uninit();
+    let bytes = unsafe {
+        core::slice::from_raw_parts_mut(ucontext.as_mut_ptr() as *mut u8, size_of::<UContext>())
+    };
+    copy_from_user(
+        VirtAddr::from_usize(frame_addr + core::mem::offset_of!(SignalFrame, ucontext)),
+        bytes,
+    )?;
+    let ucontext = unsafe { ucontext.assume_init() };
+
+    // 只恢复用户可以修改的寄存器，避免用户借此进入特权态
+    let mut restored = ucontext.mcontext;
+    #[cfg(target_arch = "riscv64")]
+    {
+        // 浮点寄存器被修改，返回用户态后仍标记为 Dirty，切换任务时才会保存
+        const FS_DIRTY: usize = 0b11 << 13;
+        restored.sstatus = tf.sstatus | FS_DIRTY;
+    }
+    #[cfg(target_arch = "x86_64")]
+    {
+        restored.cs = tf.cs;
+        restored.ss = tf.ss;
+        restored.rflags = (restored.rflags & 0xcd5) | (tf.rflags & !0xcd5);
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        restored.spsr = (restored.spsr & 0xf000_0000) | (tf.spsr & !0xf000_0000);
+    }
+    *tf = restored;
+    restore_fpstate(&ucontext.fpstate);
+
+    let mut state = ext.signal.lock();
+    state.blocked = ucontext.sigmask.blockable();
+    if !state.altstack.contains(tf.get_sp()) {
+        let mut stack = ucontext.stack;
+        stack.flags &= SS_DISABLE;
+        state.altstack = stack;
+    }
+    Ok(())
+}
+
+/// 恢复信号帧中的浮点寄存器
+fn restore_fpstate(fpstate: &FpState) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        // MXCSR 的保留位不为零时 fxrstor 会触发异常
+        let mut current = FpState::default();
+        current.save();
+        let mask = match current.fxsave_area.mxcsr_mask {
+            0 => 0xffbf,
+            mask => mask,
+        };
+        let mut fpstate = *fpstate;
+        fpstate.fxsave_area.mxcsr &= mask;
+        fpstate.restore();
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    fpstate.restore();
+}
+
+/// 以信号 `signo` 终止当前进程
+pub fn exit_by_signal(signo: usize) -> ! {
+    let curr = current();
+    info!("{}: killed by signal {}", curr.id_name(), signo);
+    curr.task_ext().set_term_signal(signo, false);
+    super::do_exit(0)
+}
+
+/// 以默认处理方式为转储核心的信号 `signo` 终止当前进程，`tf` 为进程在用户态的寄存器
+///
+/// 进程可以转储时先写入核心转储文件，见 [`coredump::dump`]。只在返回用户态前处理信号时
+/// 调用，此时没有持有任何锁。
+fn exit_with_core_dump(signo: usize, tf: &TrapFrame) -> ! {
+    let curr = current();
+    let dumped = coredump::dump(signo, tf);
+    info!(
+        "{}: killed by signal {}{}",
+        curr.id_name(),
+        signo,
+        if dumped { " (core dumped)" } else { "" }
+    );
+    curr.task_ext().set_term_signal(signo, dumped);
+    super::do_exit(0)
+}
+
+#[register_trap_handler(RETURN_TO_USER)]
+fn handle_signals(tf: &mut TrapFrame) {
+    let curr = current();
+    // 避开只有内核线程的情况,如 idle 线程等
+    if unsafe { curr.task_ext_ptr() }.is_null() {
+        return;
+    }
+    let ext = curr.task_ext();
+
+    let sigreturn = core::mem::take(&mut ext.signal.lock().sigreturn);
+    if sigreturn && restore_frame(ext, tf).is_err() {
+        exit_with_core_dump(SIGSEGV, tf);
+    }
+    ptrace::syscall_exit();
+    let mut restart = ext.signal.lock().restart.take();
+
+    loop {
+        let Some(mut info) = ext.signal.lock().dequeue() else {
+            break;
+        };
+        let mut signo = info.signo as usize;
+        // 被跟踪时先停下，由跟踪者决定递送哪个信号，为 0 时丢弃该信号
+        if signo != SIGKILL && ext.is_traced() {
+            match ptrace::stop_current(signo) {
+                0 => continue,
+                resumed if resumed != signo => {
+                    signo = resumed;
+                    info = SigInfo::user(signo, SI_USER, ptrace::tracer_pid(ext));
+                    // 换成的信号被阻塞时留在队列中
+                    if ext.signal.lock().blocked.contains(signo) {
+                        ext.send_signal(info);
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let action = ext.signal_actions.lock().get(signo);
+        match action.handler {
+            SIG_IGN => continue,
+            SIG_DFL => match default_action(signo) {
+                DefaultAction::Ignore => continue,
+                DefaultAction::Stop => {
+                    warn!("Stopping a process by signal {} is not supported", signo);
+                    continue;
+                }
+                DefaultAction::Terminate => exit_by_signal(signo),
+                DefaultAction::Core => exit_with_core_dump(signo, tf),
+            },
+            _ => {
+                // 不自动重新执行时，系统调用向处理函数返回后的代码报告 EINTR
+                if let Some((sysno, arg0)) = restart.take() {
+                    if action.flags().contains(SaFlags::SA_RESTART) {
+                        restart_syscall(tf, sysno, arg0);
+                    }
+                }
+                if setup_frame(ext, tf, &action, info).is_err() {
+                    // 无法在用户栈上构造信号帧，如栈已溢出且没有备用信号栈
+                    exit_with_core_dump(SIGSEGV, tf);
+                }
+                break;
+            }
+        }
+    }
+
+    // 打断系统调用的信号最终被忽略，透明地重新执行该系统调用
+    if let Some((sysno, arg0)) = restart {
+        restart_syscall(tf, sysno, arg0);
+    }
+
+    // 没有执行处理函数时，直接恢复 rt_sigsuspend 之前的信号掩码
+    ext.signal.lock().restore_mask();
+}
@@ -1,50 +1,103 @@
-use axtask::{current, TaskExtRef};
+use axhal::{
+    arch::TrapFrame,
+    time::{current_ticks, ticks_to_nanos, NANOS_PER_SEC},
+};
+use axtask::{current, TaskExtRef, TaskInner};
+
+/// 用户态可见的时钟频率，即 `sysconf(_SC_CLK_TCK)` 的值
+///
+/// `times` 的返回值和 `struct tms` 中的各字段均以此为单位。
+pub const USER_HZ: u64 = 100;
+
+/// 将纳秒转换为以 [`USER_HZ`] 为单位的时钟滴答数
+pub const fn nanos_to_clock_ticks(nanos: u64) -> u64 {
+    nanos / (NANOS_PER_SEC / USER_HZ)
+}
 
 pub struct TimeStat {
-    /// 在用户态流过的累计时间
+    /// 在用户态流过的累计时间（纳秒）
     user_time: u64,
-    /// 在内核态流过的累计时间
+    /// 在内核态流过的累计时间（纳秒）
     kernel_time: u64,
-    /// 最近一次进入用户态的时间
+    /// 最近一次进入用户态的时间（硬件滴答数）
     last_user_time: u64,
-    /// 最近一次进入内核态的时间
+    /// 最近一次进入内核态的时间（硬件滴答数）
     last_kernel_time: u64,
+    /// 当前的内核态嵌套深度，为 0 表示正处于用户态
+    ///
+    /// 任务创建时处于内核态，因此初始值为 1；每次陷入加一、返回减一，
+    /// 只有在用户态与内核态之间真正切换时才更新时间统计，
+    /// 从而避免系统调用中发生中断等嵌套陷入时重复计时。
+    kernel_depth: usize,
 }
 
 impl TimeStat {
     pub fn new() -> Self {
-        debug!(
-            "TimeStat::new. Current ticks: {}",
-            axhal::time::current_ticks()
-        );
+        debug!("TimeStat::new. Current ticks: {}", current_ticks());
         TimeStat {
             user_time: 0,
             kernel_time: 0,
             last_user_time: 0,
-            last_kernel_time: axhal::time::current_ticks(),
+            last_kernel_time: current_ticks(),
+            kernel_depth: 1,
         }
     }
 
+    /// 从内核态返回用户态，结算内核态时间
+    ///
+    /// 除了陷入返回以外，首次进入用户态以及 `execve` 等不经过陷入返回路径
+    /// 直接进入用户态的情况也需要调用该函数，它会将嵌套深度清零。
     pub fn enter_uspace(&mut self) {
-        debug!(
-            "TimeStat::enter_uspace. Current ticks: {}",
-            axhal::time::current_ticks()
-        );
-        let current_time = axhal::time::current_ticks();
+        let current_time = current_ticks();
+        debug!("TimeStat::enter_uspace. Current ticks: {}", current_time);
         self.last_user_time = current_time;
-        self.kernel_time += current_time - self.last_kernel_time;
+        self.kernel_time += ticks_to_nanos(current_time - self.last_kernel_time);
+        self.kernel_depth = 0;
     }
 
+    /// 从用户态陷入内核态，结算用户态时间
     pub fn enter_kspace(&mut self) {
-        debug!(
-            "TimeStat::enter_kspace. Current ticks: {}",
-            axhal::time::current_ticks()
-        );
-        let current_time = axhal::time::current_ticks();
+        let current_time = current_ticks();
+        debug!("TimeStat::enter_kspace. Current ticks: {}", current_time);
         self.last_kernel_time = current_time;
-        self.user_time += current_time - self.last_user_time;
+        self.user_time += ticks_to_nanos(current_time - self.last_user_time);
+        self.kernel_depth = 1;
+    }
+
+    /// 发生陷入时调用，仅在从用户态陷入时更新时间统计
+    pub fn trap_enter(&mut self) {
+        if self.kernel_depth == 0 {
+            self.enter_kspace();
+        } else {
+            self.kernel_depth += 1;
+        }
     }
 
+    /// 陷入返回时调用，只结束嵌套的陷入
+    ///
+    /// 返回用户态前还要递送信号、重新调度等，这些时间也属于内核态，因此最外层的陷入
+    /// 直到真正返回用户态时才由 [`enter_uspace`](Self::enter_uspace) 结算。
+    pub fn trap_leave(&mut self) {
+        if self.kernel_depth > 1 {
+            self.kernel_depth -= 1;
+        }
+    }
+
+    /// 任务被切换出去时调用，将本次在内核态运行的时间记到该任务上
+    ///
+    /// 上下文切换总是发生在内核态，因此只需结算内核态时间。
+    pub fn switch_out(&mut self) {
+        let current_time = current_ticks();
+        self.kernel_time += ticks_to_nanos(current_time - self.last_kernel_time);
+        self.last_kernel_time = current_time;
+    }
+
+    /// 任务被切换回来时调用，重新开始内核态计时，不计入未运行的时间
+    pub fn switch_in(&mut self) {
+        self.last_kernel_time = current_ticks();
+    }
+
+    /// 返回 (用户态累计时间, 内核态累计时间)，单位为纳秒
     pub fn info(&self) -> (u64, u64) {
         (self.user_time, self.kernel_time)
     }
@@ -62,7 +115,7 @@ fn before_all_traps() {
 
     // 避开只有内核线程的情况,如 idle 线程等
     if !unsafe { current_task.task_ext_ptr() }.is_null() {
-        current_task.task_ext().time_stat.lock().enter_uspace();
+        current_task.task_ext().time_stat.lock().trap_enter();
     }
 }
 
@@ -72,6 +125,32 @@ fn after_all_traps() {
 
     // 避开只有内核线程的情况,如 idle 线程等
     if !unsafe { current_task.task_ext_ptr() }.is_null() {
-        current_task.task_ext().time_stat.lock().enter_kspace();
+        current_task.task_ext().time_stat.lock().trap_leave();
+    }
+}
+
+/// 在其他返回用户态的钩子之后运行，结算包括信号递送在内的全部内核态时间
+#[axhal::trap::register_trap_handler(axhal::trap::RETURN_TO_USER)]
+fn return_to_user(_tf: &mut TrapFrame) {
+    let current_task = current();
+
+    // 避开只有内核线程的情况,如 idle 线程等
+    if !unsafe { current_task.task_ext_ptr() }.is_null() {
+        current_task.task_ext().time_stat.lock().enter_uspace();
+    }
+}
+
+#[axhal::trap::register_trap_handler(axhal::trap::HANDLER_PRIORITY)]
+static RETURN_TO_USER_PRIORITY: axhal::trap::HandlerPriority =
+    axhal::trap::HandlerPriority::new(return_to_user as _, i32::MAX);
+
+#[axtask::register_switch_hook(axtask::TASK_SWITCH_HOOKS)]
+fn on_task_switch(prev: &TaskInner, next: &TaskInner) {
+    // 避开只有内核线程的情况,如 idle 线程等
+    if !unsafe { prev.task_ext_ptr() }.is_null() {
+        prev.task_ext().time_stat.lock().switch_out();
+    }
+    if !unsafe { next.task_ext_ptr() }.is_null() {
+        next.task_ext().time_stat.lock().switch_in();
     }
 }
@@ -0,0 +1,289 @@
+//! POSIX 定时器
+//!
+//! 每个进程在 [`TaskExt::timers`](super::TaskExt::timers) 中保存自己创建的定时器。
+//! 正在计时的定时器还按到期时间登记在全局的 [`ARMED`] 中，并注册一个
+//! [`axtask::register_timeout`] 超时。超时在时钟中断中到期，不能阻塞，只唤醒一个内核线程，
+//! 由它向所属进程发送信号，并重新登记周期性的定时器。
+//! 到期时间一律以单调时钟的纳秒数表示。
+
+use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use axerrno::{AxError, AxResult};
+use axhal::time::{epochoffset_nanos, monotonic_time_nanos};
+use axsync::Mutex;
+use axtask::{current, AxTaskRef, TaskExtRef, TimerHandle, WaitQueue, WeakAxTaskRef};
+
+use super::{signal::SigInfo, Pid};
+
+/// 定时器的编号，在进程内唯一
+pub type TimerId = i32;
+
+/// 每个进程最多能创建的定时器数量
+const MAX_TIMERS: usize = 32;
+
+/// 定时器的计时基准
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerClock {
+    Realtime,
+    Monotonic,
+}
+
+/// 定时器到期时的通知方式
+#[derive(Debug, Clone, Copy)]
+pub enum TimerNotify {
+    /// 不发送通知，只能通过 `timer_gettime` 查询
+    None,
+    /// 发送信号 `signo`，`value` 为空时以定时器编号作为 `si_value`
+    Signal { signo: usize, value: Option<u64> },
+}
+
+struct PosixTimer {
+    clock: TimerClock,
+    notify: TimerNotify,
+    /// 下一次到期的时间，为 `None` 表示未启动
+    deadline: Option<u64>,
+    /// 周期，为 0 表示只到期一次
+    interval: u64,
+    /// 最近一次发出的信号所携带的错过次数，由 `timer_getoverrun` 返回
+    overrun: i32,
+}
+
+/// 进程的定时器表
+#[derive(Default)]
+pub struct TimerTable {
+    timers: BTreeMap<TimerId, PosixTimer>,
+    next_id: TimerId,
+}
+
+impl TimerTable {
+    fn get_mut(&mut self, id: TimerId) -> AxResult<&mut PosixTimer> {
+        self.timers.get_mut(&id).ok_or(AxError::InvalidInput)
+    }
+}
+
+/// 正在计时的定时器的键：(到期时间, 进程, 定时器)
+type ArmedKey = (u64, Pid, TimerId);
+/// 正在计时的定时器所属的任务和为它注册的超时
+type ArmedTimer = (WeakAxTaskRef, TimerHandle);
+
+/// 正在计时的定时器，按到期时间排序
+///
+/// 加锁顺序：先锁进程的定时器表，再锁该表。
+static ARMED: Mutex<BTreeMap<ArmedKey, ArmedTimer>> = Mutex::new(BTreeMap::new());
+
+/// 定时器线程在此等待有定时器到期
+static TIMER_WQ: WaitQueue = WaitQueue::new();
+
+/// 有定时器到期，定时器线程需要处理
+static EXPIRED: AtomicBool = AtomicBool::new(false);
+
+/// 定时器线程是否已经启动
+static DAEMON_STARTED: AtomicBool = AtomicBool::new(false);
+
+fn timer_daemon() {
+    loop {
+        TIMER_WQ.wait_until(|| EXPIRED.swap(false, Ordering::SeqCst));
+        fire_expired(monotonic_time_nanos());
+    }
+}
+
+/// 处理所有在 `now` 之前到期的定时器
+fn fire_expired(now: u64) {
+    loop {
+        let ((deadline, _, id), (task, handle)) = {
+            let mut armed = ARMED.lock();
+            match armed.first_key_value() {
+                Some((&(deadline, ..), _)) if deadline <= now => {}
+                _ => return,
+            }
+            armed.pop_first().unwrap()
+        };
+        // 同时到期的其他定时器的超时可能尚未到期
+        handle.cancel();
+        if let Some(task) = task.upgrade() {
+            fire(&task, id, deadline, now);
+        }
+    }
+}
+
+/// 定时器 `id` 在 `deadline` 到期：周期性的定时器重新登记，然后发送信号
+fn fire(task: &AxTaskRef, id: TimerId, deadline: u64, now: u64) {
+    let ext = task.task_ext();
+    let mut timers = ext.timers.lock();
+    let Ok(timer) = timers.get_mut(id) else {
+        return;
+    };
+    // 定时器在登记之后被重新设置过
+    if timer.deadline != Some(deadline) {
+        return;
+    }
+    let mut expirations: i32 = 1;
+    if let Some(missed) = (now - deadline).checked_div(timer.interval) {
+        expirations = expirations.saturating_add(missed.min(i32::MAX as u64) as i32);
+        let next = deadline + (missed + 1) * timer.interval;
+        timer.deadline = Some(next);
+        arm(task, id, next);
+    } else {
+        timer.deadline = None;
+    }
+
+    let TimerNotify::Signal { signo, value } = timer.notify else {
+        return;
+    };
+    // 上一次的信号尚未递送时不再重复排队，只增加其错过的次数
+    if let Some(overrun) = ext.signal.lock().add_timer_overrun(id, expirations) {
+        timer.overrun = overrun;
+        return;
+    }
+    let overrun = expirations - 1;
+    timer.overrun = overrun;
+    drop(timers);
+    ext.send_signal(SigInfo::timer(
+        signo,
+        id,
+        overrun,
+        value.unwrap_or(id as u64),
+    ));
+}
+
+/// 登记定时器，必要时启动定时器线程
+fn arm(task: &AxTaskRef, id: TimerId, deadline: u64) {
+    if !DAEMON_STARTED.swap(true, Ordering::SeqCst) {
+        axtask::spawn_raw(
+            timer_daemon,
+            String::from("posix_timer"),
+            crate::config::KERNEL_STACK_SIZE,
+        );
+    }
+    // 先锁住该表再注册超时，使定时器线程被唤醒时能看到这一项。
+    // 超时以墙上时间计，与单调时钟相差固定的偏移。
+    let mut armed = ARMED.lock();
+    let wall_deadline = Duration::from_nanos(deadline + epochoffset_nanos());
+    let handle = axtask::register_timeout(wall_deadline, |_| {
+        EXPIRED.store(true, Ordering::SeqCst);
+        TIMER_WQ.notify_one(false);
+    });
+    armed.insert(
+        (deadline, task.task_ext().proc_id, id),
+        (Arc::downgrade(task), handle),
+    );
+}
+
+/// 取消登记
+fn disarm(pid: Pid, id: TimerId, timer: &mut PosixTimer) {
+    if let Some(deadline) = timer.deadline.take() {
+        if let Some((_, handle)) = ARMED.lock().remove(&(deadline, pid, id)) {
+            handle.cancel();
+        }
+    }
+}
+
+/// 为当前进程创建定时器，返回其编号
+pub fn create(clock: TimerClock, notify: TimerNotify) -> AxResult<TimerId> {
+    let curr = current();
+    let mut table = curr.task_ext().timers.lock();
+    if table.timers.len() >= MAX_TIMERS {
+        return Err(AxError::WouldBlock);
+    }
+    let id = table.next_id;
+    table.next_id += 1;
+    table.timers.insert(
+        id,
+        PosixTimer {
+            clock,
+            notify,
+            deadline: None,
+            interval: 0,
+            overrun: 0,
+        },
+    );
+    Ok(id)
+}
+
+/// 返回定时器距离下一次到期的时间和周期
+pub fn get(id: TimerId) -> AxResult<(Duration, Duration)> {
+    let curr = current();
+    let mut table = curr.task_ext().timers.lock();
+    let timer = table.get_mut(id)?;
+    let remaining = timer.deadline.map_or(0, |deadline| {
+        deadline.saturating_sub(monotonic_time_nanos())
+    });
+    Ok((
+        Duration::from_nanos(remaining),
+        Duration::from_nanos(timer.interval),
+    ))
+}
+
+/// 设置定时器，返回原先的剩余时间和周期
+///
+/// `value` 为 0 时停止定时器；`absolute` 为真时 `value` 是所用时钟上的到期时刻。
+/// 实时时钟上的绝对时刻在设置时换算为单调时钟，之后修改系统时间不影响到期时间。
+pub fn set(
+    id: TimerId,
+    value: Duration,
+    interval: Duration,
+    absolute: bool,
+) -> AxResult<(Duration, Duration)> {
+    let old = get(id)?;
+    let curr = current();
+    let mut table = curr.task_ext().timers.lock();
+    let timer = table.get_mut(id)?;
+    disarm(curr.task_ext().proc_id, id, timer);
+    timer.interval = interval.as_nanos() as u64;
+    timer.overrun = 0;
+    if value.is_zero() {
+        return Ok(old);
+    }
+
+    let now = monotonic_time_nanos();
+    let deadline = if !absolute {
+        now + value.as_nanos() as u64
+    } else {
+        match timer.clock {
+            TimerClock::Monotonic => value.as_nanos() as u64,
+            TimerClock::Realtime => {
+                now + value
+                    .saturating_sub(arceos_posix_api::realtime())
+                    .as_nanos() as u64
+            }
+        }
+    };
+    timer.deadline = Some(deadline);
+    arm(curr.as_task_ref(), id, deadline);
+    Ok(old)
+}
+
+/// 返回最近一次发出的信号所对应的错过次数
+pub fn overrun(id: TimerId) -> AxResult<i32> {
+    let curr = current();
+    let mut table = curr.task_ext().timers.lock();
+    Ok(table.get_mut(id)?.overrun)
+}
+
+/// 删除定时器，尚未递送的到期信号也被丢弃
+pub fn delete(id: TimerId) -> AxResult {
+    let curr = current();
+    let ext = curr.task_ext();
+    let mut table = ext.timers.lock();
+    let mut timer = table.timers.remove(&id).ok_or(AxError::InvalidInput)?;
+    disarm(ext.proc_id, id, &mut timer);
+    ext.signal.lock().discard_timer(id);
+    Ok(())
+}
+
+/// 进程退出或执行 exec 时删除其全部定时器，尚未递送的到期信号也被丢弃
+pub fn delete_all() {
+    let curr = current();
+    let ext = curr.task_ext();
+    let mut table = ext.timers.lock();
+    let mut signal = ext.signal.lock();
+    for (id, timer) in table.timers.iter_mut() {
+        disarm(ext.proc_id, *id, timer);
+        signal.discard_timer(*id);
+    }
+    table.timers.clear();
+}